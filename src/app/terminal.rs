@@ -1,40 +1,76 @@
+use crate::command::workflow_runner::{PlannedStep, WorkflowRun};
+use crate::command_signatures_v2::SpecCompletionEngine;
+use crate::editor::autosuggestion::{Autosuggestion, AutosuggestionEngine};
+use crate::editor::completion_engine::CompletionEngine;
+use crate::editor::file_editor::{DiskChange, FileEditor, FileEditorError};
+use crate::editor::native_completion::NativeCompletionBridge;
+use crate::editor::readline::ReadlineOutcome;
+use crate::editor::text_input::TextInputHandler;
+use crate::executor::command_corrections::{CommandCorrection, CommandCorrector, CorrectionMemory};
 use crate::executor::command_executor::{CommandExecutor, ExecutionResult};
+use crate::executor::command_guard::{CommandGuard, GuardDecision, GuardVerdict, SystemGuardEnvironment};
 use crate::executor::shell_integration::{ShellIntegration, ShellConfig};
 
-use crate::input::KeyboardShortcuts;
-use crate::keyset::KeysetManager;
+use crate::input::keymap::Keystroke;
+use crate::input::{Dispatch, KeyContext, KeyDispatcher, KeyboardShortcuts, Keymap};
+use crate::theme::appearance::{self, ColorScheme};
+use crate::keyset::{KeysetManager, EMACS_KEYSET};
 use crate::model::block::BlockManager;
 use crate::model::pane::{PaneManager, SplitDirection};
 use crate::model::{
+    contrast::MIN_TEXT_CONTRAST,
     history::HistoryManager,
+    notebook::Notebook,
     synchronization::{SynchronizationManager, SynchronizationScope},
     theme::AppTheme,
+    theme_loader::ThemeLoader,
+    workflow_draft::WorkflowDraft,
+    workflow_loader::{WorkflowLoader, WorkflowPoll, WorkflowScan},
+    workflow_params::WorkflowParameterHandler,
 };
 use crate::persistence::settings_manager::SettingsManager;
 use crate::ui::block::view_block;
 use crate::ui::command_palette::CommandPalette;
-use crate::ui::command_search::CommandSearchPanel;
+use crate::ui::command_search::{CommandSearchPanel, SearchResultType};
 use crate::ui::command_history::CommandHistoryUI;
 use crate::ui::enhanced_input::EnhancedInputState;
-use crate::ui::input::enhanced_input_section;
+use crate::ui::input::{command_input_id, enhanced_input_section};
+use crate::ui::keybinding_editor::{KeybindingEditor, KeybindingEditorMessage};
+use crate::ui::line_editor_input::cursor_index;
+use crate::ui::save_workflow::{SaveWorkflowDialog, SaveWorkflowMessage};
+use crate::ui::workflow_arguments::{WorkflowArgumentsDialog, WorkflowArgumentsMessage};
+use crate::ui::theme_background::ThemeBackground;
 use crate::ui::settings::{
     settings_view, CursorType, HistoryDedupMode, InputType, SettingsMessage, SettingsState,
-    SettingsTab,
+    SettingsTab, DEFAULT_KEYSET,
 };
 use crate::ui::synchronization::CompactIndicatorStyle;
 use crate::ui::welcome::welcome_screen;
 use crate::handlers::BatchCommandHandler;
+use crate::utils::file_links;
 // Using arboard as a maintained alternative to the clipboard crate
 use arboard::Clipboard;
 use iced::widget::container;
-use iced::widget::{button, column, row, scrollable, text};
+use iced::widget::{button, column, row, scrollable, text, text_input, Space};
 use iced::{executor, theme, Alignment, Application, Color, Command, Element, Length};
 use std::collections::HashMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 // use warp_themes::{iced_integration::ButtonVariant, Theme as WarpTheme};
 
+/// Number of most recent history entries searched for autosuggestions.
+const AUTOSUGGESTION_HISTORY_LIMIT: usize = 1000;
+
+/// How long typing has to pause before an autosuggestion is computed.
+const AUTOSUGGESTION_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// How often files open in editor panes are checked for outside changes.
+const OPEN_FILE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How often workflow directories are checked for edited workflows.
+const WORKFLOW_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[derive(Debug, Clone)]
 pub enum Direction {
     Up,
@@ -49,7 +85,7 @@ pub struct WarpTerminal {
     command_executor: CommandExecutor,
     executing_commands: HashMap<Uuid, tokio::task::JoinHandle<(Uuid, ExecutionResult)>>,
     scroll_position: scrollable::Id,
-    // theme_manager: warp_themes::ThemeManager,
+    theme_loader: ThemeLoader,
     theme: AppTheme,
     clipboard: Clipboard,
     show_settings: bool,
@@ -61,6 +97,8 @@ pub struct WarpTerminal {
     synchronization_manager: SynchronizationManager,
     #[allow(dead_code)]
     keyset_manager: KeysetManager,
+    key_dispatcher: KeyDispatcher,
+    keybinding_editor: KeybindingEditor,
     #[allow(dead_code)]
     pane_manager: PaneManager,
     #[allow(dead_code)]
@@ -69,8 +107,41 @@ pub struct WarpTerminal {
     command_history_ui: CommandHistoryUI,
     #[allow(dead_code)]
     shell_integration: ShellIntegration,
-    #[allow(dead_code)]
     enhanced_input_state: EnhancedInputState,
+    /// Vim or readline editing of the command input
+    line_editor: TextInputHandler,
+    /// The last key the line editor took, if the event subscription still
+    /// passes it on to the key dispatcher
+    line_editor_key: Option<Keystroke>,
+    /// Latest autosuggestion request; results of older ones are dropped.
+    autosuggestion_request: u64,
+    /// Latest workflow reload; scans started before it are dropped.
+    workflow_scan: u64,
+    spec_completion_engine: SpecCompletionEngine,
+    /// Completions from the user's shell for tools without a spec.
+    native_completion: Option<NativeCompletionBridge>,
+    /// Warning or confirmation prompt from the command guard.
+    guard_notice: Option<String>,
+    /// Message from anything else, such as a file editor save conflict.
+    /// Hidden while there is a guard prompt, and never taken for one.
+    status_notice: Option<String>,
+    /// Command the guard asked to confirm; running it again confirms.
+    pending_guard_confirmation: Option<String>,
+    /// Command whose guard check is still running.
+    pending_guard_check: Option<String>,
+    /// `edit` command the shell turned out to have, to run as typed.
+    shell_edit_command: Option<String>,
+    /// Multi-step workflow whose steps are running one block at a time.
+    workflow_run: Option<WorkflowRun>,
+    /// Block or history command being saved as a workflow.
+    save_workflow_dialog: Option<SaveWorkflowDialog>,
+    workflow_arguments: Option<WorkflowArgumentsDialog>,
+    /// Light/dark preference last reported by the OS.
+    os_color_scheme: Option<ColorScheme>,
+    /// Buffers of the editor panes, by pane id.
+    file_editors: HashMap<Uuid, FileEditor>,
+    /// Editor pane with unsaved edits that closing again will discard.
+    pending_editor_close: Option<Uuid>,
     resizing_state: ResizingState,
     initial_mouse_position: Option<iced::Point>,
 }
@@ -110,7 +181,7 @@ impl fmt::Debug for WarpTerminal {
             .field("command_executor", &self.command_executor)
             .field("executing_commands", &self.executing_commands)
             .field("scroll_position", &self.scroll_position)
-            // field("theme_manager", &self.theme_manager)
+            .field("themes", &self.theme_loader.themes().len())
             .field("theme", &self.theme)
             .field("clipboard", &"ClipboardContext")
             .finish()
@@ -121,16 +192,61 @@ impl fmt::Debug for WarpTerminal {
 pub enum Message {
     InputChanged(String),
     ExecuteCommand,
+    GuardChecked {
+        command: String,
+        confirmed: bool,
+        verdict: GuardVerdict,
+    },
+    // Inline autosuggestion messages
+    AutosuggestionDue(u64),
+    AutosuggestionReady(u64, Option<Autosuggestion>),
+    AcceptAutosuggestion,
+    AcceptAutosuggestionWord,
+    /// A key vim or readline takes in the command input, with the input's
+    /// cursor as a byte offset
+    LineEditorKey(iced::keyboard::Key, iced::keyboard::Modifiers, usize),
+    /// The command line saved by the external editor `C-x C-e` opened
+    LineEditorExternalEdit(Result<String, String>),
     CommandCompleted(Uuid, ExecutionResult),
     CommandCompletedWithHistory(Uuid, Uuid, ExecutionResult), // block_id, entry_id, result
+    WorkflowStepChecked(Box<PlannedStep>, GuardVerdict),
+    WorkflowStepCompleted(Uuid, ExecutionResult),
+    ReloadChangedWorkflows,
+    WorkflowsPolled(Option<Box<WorkflowPoll>>),
+    WorkflowsScanned(u64, Box<Result<WorkflowScan, String>>),
     UpdateBlockPid(Uuid, u32),
     ChangeTheme(String),
+    OsColorSchemeChanged(ColorScheme),
     CopyCommand(Uuid),
     CopyOutput(Uuid),
     CopyBoth(Uuid),
     ShareBlock(Uuid),
     ReInputCommand(Uuid),
     BookmarkBlock(Uuid),
+    // Workflow authoring messages
+    SaveBlockAsWorkflow(Uuid),
+    SaveHistoryAsWorkflow(Uuid),
+    SaveWorkflow(SaveWorkflowMessage),
+    WorkflowArguments(WorkflowArgumentsMessage),
+    KeybindingEditor(KeybindingEditorMessage),
+    // Notebook messages
+    OpenNotebook(PathBuf),
+    RunNotebookCell(Uuid),
+    // Editor pane messages
+    OpenInEditor(PathBuf, Option<usize>), // path, one-based line
+    /// Whether the shell has its own `edit` for this `edit` command
+    EditCommandResolved { command: String, in_shell: bool },
+    FileEditor(Uuid, crate::editor::text_editor::EditorMessage),
+    FocusPane(Uuid),
+    SaveFile(Uuid),
+    OverwriteFile(Uuid),
+    ReloadFile(Uuid),
+    CheckOpenFiles,
+    // Command correction messages
+    RunCorrection(Uuid),
+    RunLastCorrection,
+    DismissCorrection(Uuid),
+    CorrectionFeedbackSaved(Result<(), String>),
     // Command Search messages
     ToggleCommandSearch,
     CommandSearchQueryChanged(String),
@@ -162,12 +278,12 @@ pub enum Message {
     PaneResizeEnd,
     EventOccurred(iced::Event),
     // Settings messages
-    SettingsLoaded((SettingsState, Option<crate::model::pane::SplitLayout>)),
+    SettingsLoaded(Box<(SettingsState, Option<crate::model::pane::SplitLayout>)>),
     SettingsSaved(Result<(), String>),
     SettingsChanged(SettingsMessage),
     ImportSettings,
     SettingsImported(Option<PathBuf>),
-    ApplyImportedSettings(Result<SettingsState, String>),
+    ApplyImportedSettings(Result<Box<SettingsState>, String>),
     ExportSettings,
     SettingsExported(Result<(), String>),
     AutoSaveSettings,
@@ -216,14 +332,37 @@ impl Application for WarpTerminal {
         let settings_manager = SettingsManager::with_config("settings.json", 10, true, 3);
         let initial_settings_state = SettingsState::default();
         let initial_pane_manager = PaneManager::new();
+        let keyset_manager = {
+            let mut manager = KeysetManager::new();
+            manager.discover_keysets("keysets").unwrap();
+            if let Some(dir) = KeysetManager::user_keysets_dir() {
+                if let Err(e) = manager.discover_keysets(&dir) {
+                    tracing::warn!("Failed to read keysets in {}: {}", dir.display(), e);
+                }
+            }
+            if let Err(e) = manager.load_keyset(DEFAULT_KEYSET) {
+                tracing::warn!("Failed to load default keyset: {}", e);
+            }
+            manager
+        };
 
-        let app = Self {
+        let mut app = Self {
             block_manager: BlockManager::new(),
             current_input: String::new(),
-            command_executor: CommandExecutor::new(),
+            command_executor: CommandExecutor::new().with_corrector(
+                CommandCorrector::new().with_persistence(CorrectionMemory::default_path()),
+            ),
             executing_commands: HashMap::new(),
             scroll_position: scrollable::Id::unique(),
-            theme: AppTheme::default(),
+            theme_loader: {
+                let mut loader = ThemeLoader::with_default_dirs();
+                let count = loader.load();
+                tracing::info!("Loaded {} themes", count);
+                loader
+            },
+            theme: AppTheme::default().with_minimum_contrast(
+                initial_settings_state.enforce_minimum_contrast.then_some(MIN_TEXT_CONTRAST),
+            ),
             clipboard: Clipboard::new().unwrap(),
             show_settings: false,
             settings_state: initial_settings_state,
@@ -232,28 +371,41 @@ impl Application for WarpTerminal {
             command_search_panel: CommandSearchPanel::new(),
             history_manager: HistoryManager::new(),
             synchronization_manager: SynchronizationManager::new(),
-            keyset_manager: {
-                let mut manager = KeysetManager::new();
-                manager.discover_keysets("keysets").unwrap();
-                if let Err(e) = manager.load_keyset("default-warp-keybindings") {
-                    tracing::warn!("Failed to load default keyset: {}", e);
-                }
-                manager
-            },
+            keyset_manager,
+            key_dispatcher: KeyDispatcher::default(),
+            keybinding_editor: KeybindingEditor::new(Keymap::user_overrides_path()),
             pane_manager: initial_pane_manager,
             keyboard_shortcuts: KeyboardShortcuts::default(),
             command_palette: CommandPalette::new(),
             command_history_ui: CommandHistoryUI::new(),
             shell_integration: ShellIntegration::new(ShellConfig::default()),
             enhanced_input_state: EnhancedInputState::new(),
+            line_editor: TextInputHandler::new(),
+            line_editor_key: None,
+            autosuggestion_request: 0,
+            workflow_scan: 0,
+            spec_completion_engine: SpecCompletionEngine::default(),
+            native_completion: NativeCompletionBridge::detect(),
+            guard_notice: None,
+            status_notice: None,
+            pending_guard_confirmation: None,
+            pending_guard_check: None,
+            shell_edit_command: None,
+            workflow_run: None,
+            save_workflow_dialog: None,
+            workflow_arguments: None,
+            os_color_scheme: None,
+            file_editors: HashMap::new(),
+            pending_editor_close: None,
             resizing_state: ResizingState::Idle,
             initial_mouse_position: None,
         };
+        app.reload_keymap();
 
         // Load settings on startup
         let load_settings_command = Command::perform(
             async move { settings_manager.load_settings().await },
-            |(loaded_settings, loaded_layout)| Message::SettingsLoaded((loaded_settings, loaded_layout)),
+            |loaded| Message::SettingsLoaded(Box::new(loaded)),
         );
 
         (app, load_settings_command)
@@ -269,17 +421,22 @@ impl Application for WarpTerminal {
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
-            Message::SettingsLoaded((loaded_settings, loaded_layout)) => {
+            Message::SettingsLoaded(loaded) => {
+                let (loaded_settings, loaded_layout) = *loaded;
                 tracing::info!("Settings loaded successfully on startup");
                 self.settings_state = loaded_settings;
                 if let Some(layout) = loaded_layout {
                     self.pane_manager.root_layout = layout;
+                    self.reopen_file_editors();
                 }
                 self.settings_errors =
                     crate::ui::settings_handler::SettingsHandler::validate(&self.settings_state);
                 if !self.settings_errors.is_empty() {
                     tracing::warn!("Settings validation errors: {:?}", self.settings_errors);
                 }
+                self.apply_minimum_contrast();
+                self.apply_os_theme();
+                self.apply_line_editor();
                 Command::none()
             }
 
@@ -335,6 +492,7 @@ impl Application for WarpTerminal {
                         "Window" => self.settings_state.active_tab = SettingsTab::Window,
                         "Icon" => self.settings_state.active_tab = SettingsTab::Icon,
                         "Input" => self.settings_state.active_tab = SettingsTab::Input,
+                        "Keybindings" => self.settings_state.active_tab = SettingsTab::Keybindings,
                         "History" => self.settings_state.active_tab = SettingsTab::History,
                         "Features" => self.settings_state.active_tab = SettingsTab::Features,
                         "Advanced" => self.settings_state.active_tab = SettingsTab::Advanced,
@@ -347,7 +505,12 @@ impl Application for WarpTerminal {
                 match input.as_str() {
                     // Theme settings
                     "sync_os" => {
-                        self.settings_state.sync_with_os = !self.settings_state.sync_with_os
+                        self.settings_state.sync_with_os = !self.settings_state.sync_with_os;
+                        self.apply_os_theme();
+                    }
+                    "min_contrast" => {
+                        self.settings_state.enforce_minimum_contrast = !self.settings_state.enforce_minimum_contrast;
+                        self.apply_minimum_contrast();
                     }
                     "light_theme_picker" => { /* TODO: Open theme picker */ }
                     "dark_theme_picker" => { /* TODO: Open theme picker */ }
@@ -400,6 +563,7 @@ impl Application for WarpTerminal {
                     "reset_defaults" => {
                         self.settings_state = SettingsState::default();
                         self.history_manager = HistoryManager::new();
+                        self.apply_line_editor();
                     }
 
                     // Icon settings
@@ -447,7 +611,16 @@ impl Application for WarpTerminal {
                     "clear_history" => {
                         self.history_manager = HistoryManager::new();
                     }
-                    _ => self.current_input = input,
+                    _ => {
+                        // Editing a command invalidates any guard prompt for it
+                        if self.pending_guard_confirmation.as_deref() != Some(input.trim()) {
+                            self.pending_guard_confirmation = None;
+                            self.guard_notice = None;
+                        }
+                        self.current_input = input;
+                        self.enhanced_input_state
+                            .set_content(self.current_input.clone());
+                    }
                 }
                 // Validate settings after any change
                 self.settings_errors =
@@ -456,13 +629,76 @@ impl Application for WarpTerminal {
                 let state = self.settings_state.clone();
                 let manager = self.settings_manager.clone();
                 let pane_layout = Some(self.pane_manager.root_layout.clone());
-                return Command::perform(
-                    async move {
-                        manager.mark_settings_changed(&state, pane_layout).await;
-                        Ok(())
-                    },
-                    Message::SettingsSaved,
-                );
+                Command::batch([
+                    Command::perform(
+                        async move {
+                            manager.mark_settings_changed(&state, pane_layout).await;
+                            Ok(())
+                        },
+                        Message::SettingsSaved,
+                    ),
+                    self.request_autosuggestion(),
+                ])
+            }
+
+            Message::AutosuggestionDue(request) => {
+                if request != self.autosuggestion_request {
+                    return Command::none();
+                }
+                self.compute_autosuggestion(request)
+            }
+
+            Message::AutosuggestionReady(request, suggestion) => {
+                if request == self.autosuggestion_request {
+                    self.enhanced_input_state.set_autosuggestion(suggestion);
+                }
+                Command::none()
+            }
+
+            Message::AcceptAutosuggestion => {
+                if self.enhanced_input_state.content != self.current_input {
+                    return Command::none();
+                }
+                match self.enhanced_input_state.accept_autosuggestion() {
+                    Some(content) => {
+                        self.current_input = content;
+                        text_input::move_cursor_to_end(command_input_id())
+                    }
+                    None => Command::none(),
+                }
+            }
+
+            Message::LineEditorKey(key, modifiers, cursor) => {
+                // The subscription passes some captured keys on; they were
+                // meant for the line editor, not the dispatcher
+                self.line_editor_key = KeyboardShortcuts::passes_captured(&key, modifiers)
+                    .then(|| Keystroke::new(key.clone(), modifiers));
+                let outcome = self.line_editor.handle_line_key(&self.current_input, cursor, &key, &modifiers);
+                if outcome == ReadlineOutcome::EditInExternalEditor {
+                    return Command::perform(self.line_editor.external_edit(), Message::LineEditorExternalEdit);
+                }
+                self.show_line_edit()
+            }
+
+            Message::LineEditorExternalEdit(result) => {
+                if let Err(e) = &result {
+                    self.status_notice = Some(format!("External editor failed: {}", e));
+                }
+                self.line_editor.finish_external_edit(result);
+                self.show_line_edit()
+            }
+
+            Message::AcceptAutosuggestionWord => {
+                if self.enhanced_input_state.content != self.current_input {
+                    return Command::none();
+                }
+                match self.enhanced_input_state.accept_autosuggestion_word() {
+                    Some(content) => {
+                        self.current_input = content;
+                        text_input::move_cursor_to_end(command_input_id())
+                    }
+                    None => Command::none(),
+                }
             }
 
             Message::SettingsSaved(result) => {
@@ -486,6 +722,7 @@ impl Application for WarpTerminal {
                             manager
                                 .import_settings(&path)
                                 .await
+                                .map(Box::new)
                                 .map_err(|e| e.to_string())
                         },
                         Message::ApplyImportedSettings,
@@ -499,8 +736,9 @@ impl Application for WarpTerminal {
                         if let Some(layout) = new_state.pane_layout.take() {
                             self.pane_manager.root_layout = layout;
                         }
-                        self.settings_state = new_state;
+                        self.settings_state = *new_state;
                         self.settings_errors.clear();
+                        self.apply_line_editor();
                         // Auto-save the imported settings
                         let state = self.settings_state.clone();
                         let manager = self.settings_manager.clone();
@@ -536,13 +774,78 @@ impl Application for WarpTerminal {
             }
 
             Message::ExecuteCommand => {
-                let mut command_text = self.current_input.trim().to_string();
+                let command_text = self.current_input.trim().to_string();
                 tracing::info!(command = %command_text, "Executing command");
                 if command_text.is_empty() {
                     return Command::none();
                 }
 
-                self.current_input.clear();
+                // `edit <file>[:line]` opens the file in an editor pane,
+                // unless the shell has an `edit` of its own
+                if command_text.starts_with("edit ")
+                    && self.shell_edit_command.take().as_deref() != Some(command_text.as_str())
+                {
+                    let command = command_text.clone();
+                    return Command::perform(self.command_executor.clone().has_command("edit"), move |in_shell| {
+                        Message::EditCommandResolved { command, in_shell }
+                    });
+                }
+
+                // Checked off the UI thread since the guard may ask git or
+                // kubectl; Enter is ignored until the check comes back
+                if self.pending_guard_check.as_deref() == Some(command_text.as_str()) {
+                    return Command::none();
+                }
+                let confirmed =
+                    self.pending_guard_confirmation.take().as_deref() == Some(command_text.as_str());
+                self.pending_guard_check = Some(command_text.clone());
+                Command::perform(self.command_guard().check_async(command_text.clone()), move |verdict| {
+                    Message::GuardChecked {
+                        command: command_text,
+                        confirmed,
+                        verdict,
+                    }
+                })
+            }
+
+            Message::GuardChecked {
+                command: mut command_text,
+                confirmed,
+                verdict,
+            } => {
+                if self.pending_guard_check.as_deref() != Some(command_text.as_str()) {
+                    return Command::none();
+                }
+                self.pending_guard_check = None;
+
+                // Dangerous commands stay in the input until confirmed
+                match verdict.decide(confirmed) {
+                    GuardDecision::Block { reasons } => {
+                        tracing::warn!(command = %command_text, "Command blocked: {}", reasons.join("; "));
+                        self.guard_notice = Some(format!("Blocked: {}", reasons.join("; ")));
+                        return Command::none();
+                    }
+                    GuardDecision::Confirm { reasons } => {
+                        self.guard_notice = Some(format!(
+                            "{}. Press Enter again to run it anyway.",
+                            reasons.join("; ")
+                        ));
+                        self.pending_guard_confirmation = Some(command_text);
+                        return Command::none();
+                    }
+                    GuardDecision::Run { warnings } => {
+                        self.status_notice = None;
+                        self.guard_notice = (!warnings.is_empty())
+                            .then(|| format!("Warning: {}", warnings.join("; ")));
+                    }
+                }
+
+                // Keep anything typed while the check ran
+                if self.current_input.trim() == command_text {
+                    self.current_input.clear();
+                    self.enhanced_input_state.set_content(String::new());
+                    self.line_editor.start_line();
+                }
 
                 let is_background_command = command_text.ends_with("&");
 
@@ -612,6 +915,8 @@ impl Application for WarpTerminal {
                     .update_block_output(block_id, result.stdout.clone());
                 self.block_manager
                     .set_block_exit_code(block_id, result.exit_code);
+                self.block_manager
+                    .set_block_correction(block_id, result.correction);
                 self.executing_commands.remove(&block_id);
                 scrollable::snap_to(
                     self.scroll_position.clone(),
@@ -625,6 +930,8 @@ impl Application for WarpTerminal {
                     .update_block_output(block_id, result.stdout.clone());
                 self.block_manager
                     .set_block_exit_code(block_id, result.exit_code);
+                self.block_manager
+                    .set_block_correction(block_id, result.correction.clone());
 
                 // Update history manager (should already be done in the spawned task, but ensure consistency)
                 self.history_manager.update_command_completion(
@@ -640,6 +947,76 @@ impl Application for WarpTerminal {
                 )
             }
 
+            Message::WorkflowStepChecked(step, verdict) => self.run_checked_workflow_step(*step, verdict),
+
+            Message::WorkflowStepCompleted(block_id, result) => {
+                tracing::info!(block_id = %block_id, exit_code = result.exit_code, "Workflow step completed");
+                self.block_manager
+                    .update_block_output(block_id, result.stdout.clone());
+                self.block_manager
+                    .set_block_exit_code(block_id, result.exit_code);
+                self.block_manager
+                    .set_block_correction(block_id, result.correction.clone());
+
+                if let Some(run) = self.workflow_run.as_mut() {
+                    if let Err(e) = run.complete_step(result.exit_code, &result.stdout) {
+                        tracing::warn!("Workflow step result ignored: {}", e);
+                    }
+                }
+                Command::batch([
+                    self.run_next_workflow_step(),
+                    scrollable::snap_to(
+                        self.scroll_position.clone(),
+                        scrollable::RelativeOffset::END,
+                    ),
+                ])
+            }
+
+            Message::ReloadChangedWorkflows => {
+                let loader = &mut self.command_palette.workflow_loader;
+                if loader.update_repo_source(self.command_executor.working_dir()) {
+                    return self.reload_workflows();
+                }
+                // The directories are walked on a blocking worker; the
+                // next tick is skipped while this poll is still running
+                match loader.begin_poll() {
+                    Some(poll) => Command::perform(poll.run_async(), |poll| {
+                        Message::WorkflowsPolled(poll.map(Box::new))
+                    }),
+                    None => Command::none(),
+                }
+            }
+
+            Message::WorkflowsPolled(poll) => {
+                let loader = &mut self.command_palette.workflow_loader;
+                match poll {
+                    Some(poll) => {
+                        if loader.finish_poll(*poll) {
+                            return self.reload_workflows();
+                        }
+                    }
+                    None => loader.restart_watcher(),
+                }
+                Command::none()
+            }
+
+            Message::WorkflowsScanned(scan_id, scan) => {
+                if scan_id != self.workflow_scan {
+                    return Command::none();
+                }
+                let palette = &mut self.command_palette;
+                match *scan {
+                    Ok(scan) if !palette.workflow_loader.is_current(&scan) => return self.reload_workflows(),
+                    Ok(scan) => {
+                        let stats = palette.workflow_loader.apply_scan(scan, &mut palette.command_registry);
+                        tracing::info!(loaded = stats.workflows_loaded, "Workflows reloaded");
+                        palette.update_results();
+                    }
+                    Err(e) => tracing::warn!("Failed to reload workflows: {}", e),
+                }
+                Command::none()
+            }
+
             Message::UpdateBlockPid(block_id, pid) => {
                 if let Some(block) = self.block_manager.blocks_mut().iter_mut().find(|b| b.id == block_id) {
                     if let crate::model::block::BlockContent::Background { pid: block_pid, .. } = &mut block.content {
@@ -654,6 +1031,12 @@ impl Application for WarpTerminal {
                 Command::none()
             }
 
+            Message::OsColorSchemeChanged(scheme) => {
+                self.os_color_scheme = Some(scheme);
+                self.apply_os_theme();
+                Command::none()
+            }
+
             Message::CopyCommand(id) => {
                 if let Some(block) = self.block_manager.blocks().iter().find(|b| b.id == id) {
                     let _ = self.clipboard.set_text(block.get_command_text());
@@ -698,6 +1081,290 @@ impl Application for WarpTerminal {
                 Command::none()
             }
 
+            Message::SaveBlockAsWorkflow(id) => {
+                self.save_workflow_dialog = self
+                    .block_manager
+                    .blocks()
+                    .iter()
+                    .find(|block| block.id == id)
+                    .and_then(WorkflowDraft::from_block)
+                    .map(SaveWorkflowDialog::new);
+                Command::none()
+            }
+
+            Message::SaveHistoryAsWorkflow(entry_id) => {
+                self.save_workflow_dialog = self
+                    .history_manager
+                    .get_combined_history()
+                    .into_iter()
+                    .find(|entry| entry.id == entry_id)
+                    .map(|entry| SaveWorkflowDialog::new(WorkflowDraft::from_history_entry(entry)));
+                if self.save_workflow_dialog.is_some() {
+                    self.command_history_ui.hide();
+                }
+                Command::none()
+            }
+
+            Message::OpenNotebook(path) => {
+                match Notebook::load(&path) {
+                    Ok(notebook) => {
+                        for cell in &notebook.cells {
+                            self.block_manager.add_markdown(cell.to_markdown(), cell.command());
+                        }
+                        if notebook.cells.is_empty() {
+                            self.block_manager
+                                .add_markdown(format!("# {}\n\n*This notebook is empty.*", notebook.title()), None);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        self.status_notice = Some(e.to_string());
+                        return Command::none();
+                    }
+                }
+                scrollable::snap_to(self.scroll_position.clone(), scrollable::RelativeOffset::END)
+            }
+
+            Message::RunNotebookCell(id) => {
+                let Some(command) = self
+                    .block_manager
+                    .blocks()
+                    .iter()
+                    .find(|block| block.id == id)
+                    .and_then(|block| block.notebook_command.clone())
+                else {
+                    return Command::none();
+                };
+                self.current_input = command;
+                self.enhanced_input_state.set_content(self.current_input.clone());
+                self.update(Message::ExecuteCommand)
+            }
+
+            Message::EditCommandResolved { command, in_shell } => {
+                // The input changed while the shell was asked
+                if self.current_input.trim() != command {
+                    return Command::none();
+                }
+                if in_shell {
+                    self.shell_edit_command = Some(command);
+                    return self.update(Message::ExecuteCommand);
+                }
+                let argument = command["edit ".len()..].trim();
+                let (path, line) = match argument.rsplit_once(':') {
+                    Some((path, line)) if line.parse::<usize>().is_ok() => (path, line.parse().ok()),
+                    _ => (argument, None),
+                };
+                let message = Message::OpenInEditor(PathBuf::from(path), line);
+                self.current_input.clear();
+                self.enhanced_input_state.set_content(String::new());
+                self.line_editor.start_line();
+                self.update(message)
+            }
+
+            Message::OpenInEditor(path, line) => {
+                let path = file_links::resolve_path(&path, self.command_executor.working_dir());
+                // One pane per file, however its path was written
+                let path = path.canonicalize().unwrap_or(path);
+                let pane_id = match self.pane_manager.editor_pane(&path).map(|pane| pane.id) {
+                    Some(pane_id) if self.file_editors.contains_key(&pane_id) => {
+                        self.pane_manager.root_layout.set_active_pane(pane_id);
+                        pane_id
+                    }
+                    _ => {
+                        let editor = match self.open_file_editor(&path) {
+                            Ok(editor) => editor,
+                            Err(e) => {
+                                tracing::warn!("{}", e);
+                                self.status_notice = Some(e.to_string());
+                                return Command::none();
+                            }
+                        };
+                        match self.pane_manager.open_editor(path) {
+                            Ok(pane_id) => {
+                                self.file_editors.insert(pane_id, editor);
+                                pane_id
+                            }
+                            Err(e) => {
+                                tracing::warn!("Failed to open editor pane: {}", e);
+                                return Command::none();
+                            }
+                        }
+                    }
+                };
+                if let (Some(line), Some(editor)) = (line, self.file_editors.get_mut(&pane_id)) {
+                    editor.go_to_line(line);
+                }
+                self.status_notice = None;
+                self.sync_input_focus()
+            }
+
+            Message::FileEditor(pane_id, editor_msg) => {
+                if let Some(editor) = self.file_editors.get_mut(&pane_id) {
+                    editor.update(editor_msg);
+                }
+                Command::none()
+            }
+
+            Message::FocusPane(pane_id) => {
+                self.pane_manager.root_layout.set_active_pane(pane_id);
+                self.sync_input_focus()
+            }
+
+            Message::SaveFile(pane_id) | Message::OverwriteFile(pane_id) | Message::ReloadFile(pane_id) => {
+                let Some(editor) = self.file_editors.get_mut(&pane_id) else {
+                    return Command::none();
+                };
+                let result = match message {
+                    Message::SaveFile(_) => editor.save(),
+                    Message::OverwriteFile(_) => editor.overwrite(),
+                    _ => editor.reload(),
+                };
+                match result {
+                    Ok(()) => {
+                        tracing::info!(path = %editor.path().display(), "File saved or reloaded");
+                        self.status_notice = None;
+                    }
+                    Err(FileEditorError::ModifiedOnDisk(path)) => {
+                        self.status_notice = Some(format!(
+                            "{} changed on disk. Overwrite it with your edits or reload to drop them.",
+                            path.display()
+                        ));
+                    }
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        self.status_notice = Some(e.to_string());
+                    }
+                }
+                Command::none()
+            }
+
+            Message::CheckOpenFiles => {
+                for editor in self.file_editors.values_mut() {
+                    match editor.check_disk() {
+                        DiskChange::Unchanged => {}
+                        DiskChange::Reloaded => {
+                            tracing::info!(path = %editor.path().display(), "Reloaded file changed on disk");
+                        }
+                        DiskChange::Conflict => {
+                            self.status_notice = Some(format!(
+                                "{} changed on disk while it has unsaved edits.",
+                                editor.path().display()
+                            ));
+                        }
+                        DiskChange::Deleted => {
+                            self.status_notice = Some(format!(
+                                "{} was deleted. Save to create it again.",
+                                editor.path().display()
+                            ));
+                        }
+                    }
+                }
+                Command::none()
+            }
+
+            Message::KeybindingEditor(KeybindingEditorMessage::Open) => {
+                self.show_settings = true;
+                self.settings_state.active_tab = SettingsTab::Keybindings;
+                Command::none()
+            }
+            Message::KeybindingEditor(message) => {
+                if self.keybinding_editor.update(message) {
+                    if let Some(dir) = KeysetManager::user_keysets_dir() {
+                        let _ = self.keyset_manager.discover_keysets(dir);
+                    }
+                    self.reload_keymap();
+                }
+                Command::none()
+            }
+
+            Message::SaveWorkflow(SaveWorkflowMessage::Cancel) => {
+                self.save_workflow_dialog = None;
+                Command::none()
+            }
+
+            Message::SaveWorkflow(SaveWorkflowMessage::Save) => {
+                let Some(dialog) = self.save_workflow_dialog.as_mut() else {
+                    return Command::none();
+                };
+                match dialog.save(WorkflowLoader::user_workflows_dir().as_deref()) {
+                    Ok(path) => {
+                        tracing::info!("Saved workflow to {}", path.display());
+                        self.save_workflow_dialog = None;
+                        self.reload_workflows()
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to save workflow: {}", e);
+                        Command::none()
+                    }
+                }
+            }
+
+            Message::SaveWorkflow(message) => {
+                if let Some(dialog) = self.save_workflow_dialog.as_mut() {
+                    dialog.update(message);
+                }
+                Command::none()
+            }
+
+            Message::WorkflowArguments(WorkflowArgumentsMessage::Cancel) => {
+                self.workflow_arguments = None;
+                Command::none()
+            }
+
+            Message::WorkflowArguments(message) => {
+                let Some(dialog) = self.workflow_arguments.as_mut() else {
+                    return Command::none();
+                };
+                let argument = dialog.current().map(|arg| arg.name.clone());
+                dialog.update(message);
+                if dialog.is_complete() {
+                    return match self.workflow_arguments.take() {
+                        Some(dialog) => self.finish_workflow_arguments(dialog),
+                        None => Command::none(),
+                    };
+                }
+                if dialog.current().map(|arg| arg.name.clone()) != argument {
+                    return self.load_workflow_argument_suggestions();
+                }
+                Command::none()
+            }
+
+            Message::RunCorrection(id) => {
+                let Some(correction) = self.block_manager.take_block_correction(id) else {
+                    return Command::none();
+                };
+                let save_feedback = self.record_correction_feedback(&correction, true);
+                self.current_input = correction.corrected_command;
+                self.enhanced_input_state
+                    .set_content(self.current_input.clone());
+                Command::batch([save_feedback, self.update(Message::ExecuteCommand)])
+            }
+
+            Message::RunLastCorrection => {
+                // Like `fuck`, only the most recent command is corrected
+                match self.block_manager.blocks().last() {
+                    Some(block) if block.correction.is_some() => {
+                        let id = block.id;
+                        self.update(Message::RunCorrection(id))
+                    }
+                    _ => Command::none(),
+                }
+            }
+
+            Message::DismissCorrection(id) => {
+                match self.block_manager.take_block_correction(id) {
+                    Some(correction) => self.record_correction_feedback(&correction, false),
+                    None => Command::none(),
+                }
+            }
+
+            Message::CorrectionFeedbackSaved(result) => {
+                if let Err(e) = result {
+                    tracing::warn!("Failed to save correction feedback: {}", e);
+                }
+                Command::none()
+            }
+
             // Command Search message handling
             Message::ToggleCommandSearch => {
                 self.command_search_panel.toggle_visibility();
@@ -740,6 +1407,14 @@ impl Application for WarpTerminal {
             }
 
             Message::CommandSearchExecuteSelected => {
+                if let Some(SearchResultType::Notebook(path)) = self
+                    .command_search_panel
+                    .get_selected_result()
+                    .map(|result| result.result_type.clone())
+                {
+                    self.command_search_panel.toggle_visibility();
+                    return self.update(Message::OpenNotebook(path));
+                }
                 if let Some(result) = self.command_search_panel.get_selected_result() {
                     self.current_input = result.text.clone();
                     self.command_search_panel
@@ -823,27 +1498,44 @@ impl Application for WarpTerminal {
             }
 
             Message::PaneClose => {
+                // Closing an editor with unsaved edits needs asking twice
+                if let Some((pane_id, editor)) = self
+                    .focused_file_editor()
+                    .and_then(|pane_id| Some((pane_id, self.file_editors.get(&pane_id)?)))
+                {
+                    if editor.is_dirty() && self.pending_editor_close != Some(pane_id) {
+                        self.status_notice = Some(format!(
+                            "{} has unsaved changes. Close it again to discard them.",
+                            editor.path().display()
+                        ));
+                        self.pending_editor_close = Some(pane_id);
+                        return Command::none();
+                    }
+                }
+                self.pending_editor_close = None;
                 if let Err(e) = self.pane_manager.close_current_pane() {
                     tracing::warn!("Failed to close pane: {}", e);
                 }
-                Command::none()
+                let layout = &self.pane_manager.root_layout;
+                self.file_editors.retain(|pane_id, _| layout.find_pane(*pane_id).is_some());
+                self.sync_input_focus()
             }
 
             Message::PaneFocusNext => {
                 self.pane_manager.focus_next_pane();
-                Command::none()
+                self.sync_input_focus()
             }
 
             Message::PaneFocusPrevious => {
                 self.pane_manager.focus_previous_pane();
-                Command::none()
+                self.sync_input_focus()
             }
 
             Message::PaneFocusDirection(_direction) => {
                 // TODO: Implement directional pane navigation
                 // For now, just cycle to next pane
                 self.pane_manager.focus_next_pane();
-                Command::none()
+                self.sync_input_focus()
             }
 
             Message::PaneResize(pane_id_1, pane_id_2, delta) => {
@@ -879,11 +1571,34 @@ impl Application for WarpTerminal {
                 Command::none()
             }
             Message::EventOccurred(event) => {
-                let active_pane_id = self.pane_manager.root_layout.active_pane;
-                if let Some(message) = self.keyboard_shortcuts.handle_event(&event, active_pane_id, &self.pane_manager) {
-                    return self.update(message);
+                let iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+                    return Command::none();
+                };
+                if self.show_settings && self.keybinding_editor.is_recording() {
+                    self.keybinding_editor.record(Keystroke::new(key, modifiers));
+                    return Command::none();
+                }
+                if self.line_editor_key.take() == Some(Keystroke::new(key.clone(), modifiers)) {
+                    return Command::none();
+                }
+                let dispatch = self.key_dispatcher.dispatch(&key, modifiers, self.key_contexts());
+                if let Some(pane_id) = self.focused_file_editor().filter(|_| self.key_contexts()[0] == KeyContext::Editor) {
+                    return self.file_editor_key(pane_id, dispatch, key, modifiers);
+                }
+                match dispatch {
+                    Dispatch::Action(action) => {
+                        let active_pane_id = self.pane_manager.root_layout.active_pane;
+                        match self.keyboard_shortcuts.action_message(&action, active_pane_id, &self.pane_manager) {
+                            Some(message) => self.update(message),
+                            None => Command::none(),
+                        }
+                    }
+                    Dispatch::Cancelled(keys) => {
+                        tracing::debug!("No binding for {}", keys);
+                        Command::none()
+                    }
+                    Dispatch::Pending(_) | Dispatch::Unhandled => Command::none(),
                 }
-                Command::none()
             }
 
             Message::ImportSettings => {
@@ -908,6 +1623,10 @@ impl Application for WarpTerminal {
                     return Command::none();
                 }
 
+                self.apply_minimum_contrast();
+                self.apply_os_theme();
+                self.apply_line_editor();
+
                 // Validate settings after change
                 self.settings_errors =
                     crate::ui::settings_handler::SettingsHandler::validate(&self.settings_state);
@@ -979,13 +1698,32 @@ impl Application for WarpTerminal {
                     
                     // Execute the selected command
                     if command_id.starts_with("workflow.") {
-                        // Handle workflow execution
-                        let workflow_name = command_id.strip_prefix("workflow.").unwrap_or("");
-                        tracing::info!("Executing workflow: {}", workflow_name);
-                        
-                        // For now, just add the workflow command to input
-                        // In a real implementation, you would load and execute the workflow
-                        self.current_input = format!("# Workflow: {}\necho 'Workflow execution not yet implemented'", workflow_name);
+                        let workflow = match self.command_palette.workflow_loader.get_workflow_by_id(&command_id) {
+                            Ok(workflow) => workflow,
+                            Err(e) => {
+                                tracing::warn!("Failed to load workflow: {}", e);
+                                return Command::none();
+                            }
+                        };
+                        tracing::info!(workflow = %workflow.name, "Executing workflow");
+
+                        // Arguments are asked for one at a time before anything runs
+                        if !workflow.arguments.is_empty() {
+                            self.workflow_arguments = Some(WorkflowArgumentsDialog::new(
+                                workflow,
+                                self.command_executor.working_dir(),
+                            ));
+                            return self.load_workflow_argument_suggestions();
+                        }
+                        if workflow.is_multi_step() {
+                            return self.start_workflow_run(&workflow, HashMap::new());
+                        }
+                        // Single commands go to the input so their arguments can be filled in
+                        self.current_input = workflow.command.clone();
+                        self.enhanced_input_state.set_content(workflow.command.clone());
+                        if WorkflowParameterHandler::command_has_parameters(&workflow.command) {
+                            return Command::none();
+                        }
                         return self.update(Message::ExecuteCommand);
                     } else {
                         // Handle built-in commands
@@ -1013,7 +1751,18 @@ impl Application for WarpTerminal {
                                 return self.update(Message::BatchProcessorExecute(command_id, current_dir));
                             }
                             _ => {
-                                tracing::warn!("Unknown command executed: {}", command_id);
+                                let action = self
+                                    .command_palette
+                                    .command_registry
+                                    .get(&command_id)
+                                    .and_then(|command| command.action.clone());
+                                let active_pane_id = self.pane_manager.root_layout.active_pane;
+                                match action.and_then(|action| {
+                                    self.keyboard_shortcuts.action_message(&action, active_pane_id, &self.pane_manager)
+                                }) {
+                                    Some(message) => return self.update(message),
+                                    None => tracing::warn!("Unknown command executed: {}", command_id),
+                                }
                             }
                         }
                     }
@@ -1181,10 +1930,37 @@ impl Application for WarpTerminal {
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        iced::event::listen().map(Message::EventOccurred)
+        let os_appearance = if self.settings_state.sync_with_os {
+            appearance::subscription().map(Message::OsColorSchemeChanged)
+        } else {
+            iced::Subscription::none()
+        };
+        let open_files = if self.file_editors.is_empty() {
+            iced::Subscription::none()
+        } else {
+            iced::time::every(OPEN_FILE_POLL_INTERVAL).map(|_| Message::CheckOpenFiles)
+        };
+        iced::Subscription::batch([
+            iced::event::listen_with(KeyboardShortcuts::key_event),
+            iced::time::every(WORKFLOW_POLL_INTERVAL).map(|_| Message::ReloadChangedWorkflows),
+            os_appearance,
+            open_files,
+        ])
     }
 
     fn view(&self) -> Element<Self::Message> {
+        // The theme's background color or gradient and its image, if any,
+        // are drawn behind everything
+        ThemeBackground::new(
+            container(self.view_content()).width(Length::Fill).height(Length::Fill),
+            &self.theme,
+        )
+        .into()
+    }
+}
+
+impl WarpTerminal {
+    fn view_content(&self) -> Element<'_, Message> {
         let font_size = self.settings_state.font_size;
         let font = iced::Font::DEFAULT;
 
@@ -1226,10 +2002,24 @@ impl Application for WarpTerminal {
         ]
         .spacing(8);
 
+        if let Some(dialog) = &self.save_workflow_dialog {
+            return column![header_buttons, dialog.view(font, font_size)]
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into();
+        }
+
+        if let Some(dialog) = &self.workflow_arguments {
+            return column![header_buttons, dialog.view(font, font_size)]
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into();
+        }
+
         if self.show_settings {
             return column![
                 header_buttons,
-                settings_view(&self.settings_state, &self.settings_errors)
+                settings_view(&self.settings_state, &self.settings_errors, &self.keybinding_editor, self.keyset_names())
             ]
             .align_items(Alignment::Start)
             .width(Length::Fill)
@@ -1246,6 +2036,9 @@ impl Application for WarpTerminal {
                     welcome_screen(font, font_size),
                     enhanced_input_section(
                         &self.current_input,
+                        self.autosuggestion_suffix(),
+                        self.input_notice(),
+                        self.active_line_editor(),
                         font,
                         font_size,
                         !self.executing_commands.is_empty(),
@@ -1258,7 +2051,7 @@ impl Application for WarpTerminal {
                 let blocks = self.block_manager.blocks().iter().fold(
                     column![].spacing(8).padding(16),
                     |col, block| {
-                        let block_widget = view_block(block, font, font_size);
+                        let block_widget = view_block(block, &self.theme, font, font_size);
                         col.push(block_widget)
                     },
                 );
@@ -1270,6 +2063,9 @@ impl Application for WarpTerminal {
                     scrollable_blocks,
                     enhanced_input_section(
                         &self.current_input,
+                        self.autosuggestion_suffix(),
+                        self.input_notice(),
+                        self.active_line_editor(),
                         font,
                         font_size,
                         !self.executing_commands.is_empty(),
@@ -1296,6 +2092,9 @@ impl Application for WarpTerminal {
 
         let input_section = enhanced_input_section(
             &self.current_input,
+            self.autosuggestion_suffix(),
+            self.input_notice(),
+            self.active_line_editor(),
             font,
             font_size,
             !self.executing_commands.is_empty(),
@@ -1317,7 +2116,7 @@ impl Application for WarpTerminal {
                 let blocks_column = self.block_manager.blocks().iter().fold(
                     column![].spacing(8).padding(16),
                     |col, block| {
-                        let block_widget = view_block(block, font, font_size);
+                        let block_widget = view_block(block, &self.theme, font, font_size);
                         col.push(block_widget)
                     },
                 );
@@ -1332,6 +2131,11 @@ impl Application for WarpTerminal {
                 .into()
         };
 
+        let _main_content = match self.view_file_editors(font, font_size) {
+            Some(editors) => row![_main_content, editors].spacing(8).into(),
+            None => _main_content,
+        };
+
         // Show Command History UI if toggled, otherwise show main content
         if self.command_history_ui.is_visible {
             self.command_history_ui.view(font, font_size)
@@ -1403,10 +2207,452 @@ impl WarpTerminal {
         }
     }
 
-    fn change_theme(&mut self, _theme_name: &str) {
-        // if let Some(theme_data) = self.theme_manager.get_theme(theme_name) {
-        //     self.theme.load_theme(theme_data.clone());
-        // }
+    /// Begin running a multi-step workflow, replacing any run in progress.
+    fn start_workflow_run(
+        &mut self,
+        workflow: &warp_workflows_types::Workflow,
+        arguments: HashMap<String, String>,
+    ) -> Command<Message> {
+        match WorkflowRun::new(workflow) {
+            Ok(run) => {
+                self.workflow_run = Some(
+                    run.with_variables(arguments)
+                        .with_working_dir(self.command_executor.working_dir().to_path_buf())
+                        .with_dialect(self.command_executor.dialect()),
+                );
+                self.run_next_workflow_step()
+            }
+            Err(e) => {
+                tracing::warn!(workflow = %workflow.name, "Cannot run workflow: {}", e);
+                self.status_notice = Some(e.to_string());
+                Command::none()
+            }
+        }
+    }
+
+    /// Suggestions for the argument the workflow dialog asks for, worked
+    /// out off the UI thread
+    fn load_workflow_argument_suggestions(&mut self) -> Command<Message> {
+        match self.workflow_arguments.as_mut().and_then(WorkflowArgumentsDialog::load_suggestions) {
+            Some(suggestions) => Command::perform(suggestions, Message::WorkflowArguments),
+            None => Command::none(),
+        }
+    }
+
+    /// Run a multi-step workflow with the arguments filled in, or put a
+    /// single command in the input to be reviewed and run
+    fn finish_workflow_arguments(&mut self, dialog: WorkflowArgumentsDialog) -> Command<Message> {
+        let Some(workflow) = dialog.workflow() else {
+            return Command::none();
+        };
+        if workflow.is_multi_step() {
+            let workflow = workflow.clone();
+            return self.start_workflow_run(&workflow, dialog.handler.parameter_values);
+        }
+        self.current_input = dialog.handler.render_command().unwrap_or_default();
+        self.enhanced_input_state.set_content(self.current_input.clone());
+        text_input::focus(command_input_id())
+    }
+
+    /// Execute the next step of the running workflow in a new block, or
+    /// finish the run when no steps are left.
+    fn run_next_workflow_step(&mut self) -> Command<Message> {
+        let Some(run) = self.workflow_run.as_mut() else {
+            return Command::none();
+        };
+        let step = match run.next_step() {
+            Ok(Some(step)) => step,
+            Ok(None) => {
+                tracing::info!(workflow = %run.name(), succeeded = run.succeeded(), "Workflow finished");
+                self.workflow_run = None;
+                return Command::none();
+            }
+            Err(e) => {
+                tracing::warn!(workflow = %run.name(), "Workflow stopped: {}", e);
+                self.status_notice = Some(format!("Workflow stopped: {}", e));
+                self.workflow_run = None;
+                return Command::none();
+            }
+        };
+
+        // Steps are held to the same guard as typed commands, checked off
+        // the UI thread
+        let command = step.command.clone();
+        Command::perform(self.command_guard().check_async(command), move |verdict| {
+            Message::WorkflowStepChecked(Box::new(step), verdict)
+        })
+    }
+
+    /// Execute a workflow step the guard has checked. Steps cannot be
+    /// confirmed mid-run, so one that needs confirmation stops the run.
+    fn run_checked_workflow_step(&mut self, step: PlannedStep, verdict: GuardVerdict) -> Command<Message> {
+        if self.workflow_run.is_none() {
+            return Command::none();
+        }
+        if let GuardDecision::Block { reasons } | GuardDecision::Confirm { reasons } = verdict.decide(false) {
+            self.guard_notice = Some(format!(
+                "Workflow stopped at step '{}': {}",
+                step.name,
+                reasons.join("; ")
+            ));
+            self.workflow_run = None;
+            return Command::none();
+        }
+
+        let block_id = self.block_manager.add_command(step.command.clone()).id;
+        self.history_manager.add_command(step.command.clone());
+        let mut executor = self.command_executor.clone();
+        if let Some(dir) = step.working_directory {
+            executor.set_working_dir(dir);
+        }
+        Command::perform(
+            async move { executor.execute_command(&step.command).await },
+            move |result| Message::WorkflowStepCompleted(block_id, result),
+        )
+    }
+
+    /// Load the workflow layers again on the blocking thread pool; only
+    /// the swap into the palette happens on the UI thread
+    fn reload_workflows(&mut self) -> Command<Message> {
+        self.workflow_scan += 1;
+        let scan_id = self.workflow_scan;
+        Command::perform(self.command_palette.workflow_loader.scan_async(), move |scan| {
+            Message::WorkflowsScanned(scan_id, Box::new(scan))
+        })
+    }
+
+    /// Ask for an autosuggestion once typing pauses. Requests made in the
+    /// meantime replace this one, and only the latest result is shown.
+    fn request_autosuggestion(&mut self) -> Command<Message> {
+        self.autosuggestion_request += 1;
+        if !self.settings_state.enable_autocomplete || self.current_input.trim().is_empty() {
+            return Command::none();
+        }
+        let request = self.autosuggestion_request;
+        Command::perform(tokio::time::sleep(AUTOSUGGESTION_DEBOUNCE), move |()| {
+            Message::AutosuggestionDue(request)
+        })
+    }
+
+    /// Compute an autosuggestion for the current input off the UI thread.
+    /// Ghost text follows every keystroke, so it never starts completion
+    /// generators or asks the user's shell; those feed the completion menu.
+    fn compute_autosuggestion(&self, request: u64) -> Command<Message> {
+        if !self.settings_state.enable_autocomplete || self.current_input.trim().is_empty() {
+            return Command::none();
+        }
+
+        let history = self
+            .history_manager
+            .get_combined_history()
+            .into_iter()
+            .take(AUTOSUGGESTION_HISTORY_LIMIT)
+            .map(|entry| entry.command.clone())
+            .collect();
+        let engine = AutosuggestionEngine::new(history)
+            .with_spec_engine(self.spec_completion_engine.clone().cached_generators_only())
+            .with_completion_engine(CompletionEngine::new());
+
+        Command::perform(engine.suggest_async(self.current_input.clone()), move |suggestion| {
+            Message::AutosuggestionReady(request, suggestion)
+        })
+    }
+
+    /// The notice under the command input: a guard prompt wins over any
+    /// other message.
+    fn input_notice(&self) -> Option<&str> {
+        self.guard_notice.as_deref().or(self.status_notice.as_deref())
+    }
+
+    /// Ghost text for the command input, if it still matches what is typed.
+    fn autosuggestion_suffix(&self) -> Option<&str> {
+        if self.enhanced_input_state.content == self.current_input {
+            self.enhanced_input_state.autosuggestion_suffix()
+        } else {
+            None
+        }
+    }
+
+    /// The line editor, when the command input goes through it
+    fn active_line_editor(&self) -> Option<&TextInputHandler> {
+        Some(&self.line_editor).filter(|editor| editor.is_vim_enabled() || editor.is_emacs_enabled())
+    }
+
+    /// The keysets to choose from, by name
+    fn keyset_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.keyset_manager.list_keysets().into_iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Load the keyset the settings name and turn vim or readline editing
+    /// of the command input on or off to follow them. Readline comes with
+    /// the emacs keyset; vim keys win over it.
+    fn apply_line_editor(&mut self) {
+        let keyset = self.settings_state.keyset.as_str();
+        if self.keyset_manager.active_keyset().map(|active| active.name.as_str()) != Some(keyset) {
+            match self.keyset_manager.load_keyset(keyset) {
+                Ok(()) => self.reload_keymap(),
+                Err(e) => tracing::warn!("Failed to load keyset {}: {}", keyset, e),
+            }
+        }
+        if self.line_editor.is_vim_enabled() != self.settings_state.vim_mode {
+            self.line_editor.set_vim_mode(self.settings_state.vim_mode);
+            self.line_editor.start_line();
+        }
+        let emacs = !self.settings_state.vim_mode
+            && self.keyset_manager.active_keyset().is_some_and(|active| active.name == EMACS_KEYSET);
+        self.line_editor.set_emacs_mode(emacs);
+    }
+
+    /// Show the line editor's text and cursor in the command input
+    fn show_line_edit(&mut self) -> Command<Message> {
+        let content = self.line_editor.get_content();
+        let cursor = cursor_index(&content, self.line_editor.get_cursor_position());
+        let move_cursor = text_input::move_cursor_to(command_input_id(), cursor);
+        if content == self.current_input {
+            return move_cursor;
+        }
+        // Editing a command invalidates any guard prompt for it
+        if self.pending_guard_confirmation.as_deref() != Some(content.trim()) {
+            self.pending_guard_confirmation = None;
+            self.guard_notice = None;
+        }
+        self.current_input = content;
+        self.enhanced_input_state.set_content(self.current_input.clone());
+        Command::batch([move_cursor, self.request_autosuggestion()])
+    }
+
+    /// Switch to the configured light or dark theme when following the OS
+    fn apply_os_theme(&mut self) {
+        let Some(scheme) = self.os_color_scheme.filter(|_| self.settings_state.sync_with_os) else {
+            return;
+        };
+        let theme_name = if scheme.prefers_dark() {
+            self.settings_state.current_dark_theme.clone()
+        } else {
+            self.settings_state.current_light_theme.clone()
+        };
+        if self.theme_loader.get(&theme_name).map(|theme| theme.name()) != Some(self.theme.name()) {
+            self.change_theme(&theme_name);
+        }
+    }
+
+    /// Layer the built-in bindings, the active keyset and the user keyset,
+    /// and hand the result to the dispatcher, the palette, the editor and
+    /// readline
+    fn reload_keymap(&mut self) {
+        let keyset = self.keyset_manager.active_keyset().map(|keyset| keyset.path.as_path());
+        let commands = self.command_palette.command_registry.get_bindable_commands();
+        self.keybinding_editor.refresh(commands, Keymap::load(keyset, None));
+        let keymap = self.keybinding_editor.keymap();
+        for error in keymap.errors() {
+            tracing::warn!("Keybinding error: {}", error);
+        }
+        for conflict in keymap.conflicts() {
+            tracing::warn!("Keybinding conflict: {}", conflict);
+        }
+        self.command_palette.sync_shortcuts(&keymap);
+        self.line_editor.readline.set_keymap(&keymap);
+        self.key_dispatcher.set_keymap(keymap);
+    }
+
+    /// Contexts a key press is resolved in, most specific first
+    fn key_contexts(&self) -> &'static [KeyContext] {
+        if self.command_palette.is_visible {
+            KeyContext::Palette.chain()
+        } else if self.command_search_panel.is_visible {
+            KeyContext::Search.chain()
+        } else if self.focused_file_editor().is_some() && !self.show_settings {
+            KeyContext::Editor.chain()
+        } else {
+            KeyContext::Terminal.chain()
+        }
+    }
+
+    /// The active pane, when it is an editor pane with its file loaded
+    fn focused_file_editor(&self) -> Option<Uuid> {
+        self.pane_manager
+            .root_layout
+            .active_pane
+            .filter(|pane_id| self.file_editors.contains_key(pane_id))
+    }
+
+    /// Keys for a focused editor pane: pane and workspace shortcuts still
+    /// apply and every other key edits the file
+    fn file_editor_key(
+        &mut self,
+        pane_id: Uuid,
+        dispatch: Dispatch,
+        key: iced::keyboard::Key,
+        modifiers: iced::keyboard::Modifiers,
+    ) -> Command<Message> {
+        use crate::editor::keybindings::editor_action;
+        use crate::editor::text_editor::EditorMessage;
+
+        let editor_msg = match dispatch {
+            Dispatch::Action(action) if action == "editor:save" => {
+                return self.update(Message::SaveFile(pane_id));
+            }
+            Dispatch::Action(action) if action.starts_with("pane_group:") || action.starts_with("workspace:") => {
+                return match self.keyboard_shortcuts.action_message(&action, Some(pane_id), &self.pane_manager) {
+                    Some(message) => self.update(message),
+                    None => Command::none(),
+                };
+            }
+            Dispatch::Action(action) => match editor_action(&action) {
+                Some(action) => EditorMessage::ActionRequested(action),
+                None => EditorMessage::KeyPressed(key, modifiers),
+            },
+            Dispatch::Pending(_) | Dispatch::Cancelled(_) => return Command::none(),
+            Dispatch::Unhandled => EditorMessage::KeyPressed(key, modifiers),
+        };
+        self.update(Message::FileEditor(pane_id, editor_msg))
+    }
+
+    /// Give keyboard focus to the command input unless an editor pane is
+    /// active, whose keys arrive through the event subscription instead
+    fn sync_input_focus(&self) -> Command<Message> {
+        if self.focused_file_editor().is_some() {
+            // Focusing an id no widget has takes focus from the input
+            text_input::focus(text_input::Id::new("file-editor"))
+        } else {
+            text_input::focus(command_input_id())
+        }
+    }
+
+    /// Learn from a correction the user ran (`accepted`) or dismissed, and
+    /// save what was learned without blocking the UI
+    fn record_correction_feedback(&self, correction: &CommandCorrection, accepted: bool) -> Command<Message> {
+        let corrector = self.command_executor.corrector();
+        corrector.learn(correction, accepted);
+        Command::perform(corrector.clone().save_feedback(), Message::CorrectionFeedbackSaved)
+    }
+
+    /// The command guard for the current settings, reading git and kubectl
+    /// state where commands run
+    fn command_guard(&self) -> CommandGuard {
+        CommandGuard::new(self.settings_state.command_guard.clone())
+            .with_environment(Box::new(SystemGuardEnvironment::new(self.command_executor.working_dir())))
+            .with_dialect(self.command_executor.dialect())
+    }
+
+    /// Open `path` for an editor pane, completing through the user's shell
+    /// from the terminal's working directory
+    fn open_file_editor(&self, path: &Path) -> Result<FileEditor, FileEditorError> {
+        let editor = FileEditor::open(path)?;
+        Ok(match &self.native_completion {
+            Some(bridge) => editor.with_native_completions(bridge.clone(), self.command_executor.working_dir()),
+            None => editor,
+        })
+    }
+
+    /// Load the files of editor panes in a restored layout, closing the
+    /// panes whose file can no longer be read
+    fn reopen_file_editors(&mut self) {
+        self.file_editors.clear();
+        let panes: Vec<(Uuid, PathBuf)> = self
+            .pane_manager
+            .root_layout
+            .panes()
+            .into_iter()
+            .filter_map(|pane| Some((pane.id, pane.editor_path()?.to_path_buf())))
+            .collect();
+        for (pane_id, path) in panes {
+            match self.open_file_editor(&path) {
+                Ok(editor) => {
+                    self.file_editors.insert(pane_id, editor);
+                }
+                Err(e) => {
+                    tracing::warn!("Closing editor pane: {}", e);
+                    if let Err(e) = self.pane_manager.root_layout.close_pane(pane_id) {
+                        tracing::warn!("Failed to close pane: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The editor panes beside the terminal, in layout order
+    fn view_file_editors(&self, font: iced::Font, font_size: u16) -> Option<Element<'_, Message>> {
+        let focused = self.focused_file_editor();
+        let panes: Vec<Element<Message>> = self
+            .pane_manager
+            .root_layout
+            .panes()
+            .into_iter()
+            .filter_map(|pane| Some((pane.id, self.file_editors.get(&pane.id)?)))
+            .map(|(pane_id, editor)| {
+                let title = button(text(editor.title()).font(font).size(font_size - 2))
+                    .on_press(Message::FocusPane(pane_id))
+                    .style(if focused == Some(pane_id) {
+                        theme::Button::Primary
+                    } else {
+                        theme::Button::Text
+                    });
+                let mut header = row![title, Space::with_width(Length::Fill)]
+                    .spacing(8)
+                    .align_items(Alignment::Center);
+                header = if editor.has_conflict() {
+                    header
+                        .push(
+                            text("Changed on disk")
+                                .font(font)
+                                .size(font_size - 2)
+                                .style(Color::from_rgb(0.95, 0.7, 0.3)),
+                        )
+                        .push(
+                            button(text("Overwrite").size(font_size - 2))
+                                .on_press(Message::OverwriteFile(pane_id))
+                                .style(theme::Button::Destructive),
+                        )
+                        .push(
+                            button(text("Reload").size(font_size - 2))
+                                .on_press(Message::ReloadFile(pane_id))
+                                .style(theme::Button::Secondary),
+                        )
+                } else {
+                    header.push(
+                        button(text("Save").size(font_size - 2))
+                            .on_press_maybe(editor.is_dirty().then_some(Message::SaveFile(pane_id)))
+                            .style(theme::Button::Secondary),
+                    )
+                };
+                column![
+                    header,
+                    editor
+                        .editor()
+                        .view()
+                        .map(move |editor_msg| Message::FileEditor(pane_id, editor_msg)),
+                ]
+                .spacing(4)
+                .height(Length::Fill)
+                .into()
+            })
+            .collect();
+        (!panes.is_empty()).then(|| {
+            iced::widget::Column::with_children(panes)
+                .spacing(8)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        })
+    }
+
+    /// Carry the minimum contrast setting onto the current theme
+    fn apply_minimum_contrast(&mut self) {
+        let ratio = self.settings_state.enforce_minimum_contrast.then_some(MIN_TEXT_CONTRAST);
+        self.theme = self.theme.clone().with_minimum_contrast(ratio);
+    }
+
+    fn change_theme(&mut self, theme_name: &str) {
+        match self.theme_loader.get(theme_name) {
+            Some(theme) => {
+                tracing::info!("Switched to theme {}", theme.name());
+                self.theme = theme.clone().with_minimum_contrast(self.theme.minimum_contrast());
+            }
+            None => self.status_notice = Some(format!("Unknown theme: {}", theme_name)),
+        }
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,80 @@
+//! Command line tools for theme files.
+//!
+//! `warp-themes audit [--errors-only] [--min-contrast RATIO] [PATH...]`
+//! reports low-contrast color pairs in every theme under the given files or
+//! directories (default `themes`) and exits non-zero when any errors are
+//! found.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use warp_terminal::model::theme_audit::ThemeAuditor;
+
+const USAGE: &str = "usage: warp-themes audit [--errors-only] [--min-contrast RATIO] [PATH...]";
+const DEFAULT_THEMES_DIR: &str = "themes";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("audit") => audit(args.collect()),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn audit(args: Vec<String>) -> ExitCode {
+    let mut auditor = ThemeAuditor::new();
+    let mut paths = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--errors-only" => auditor = auditor.with_warnings(false),
+            "--min-contrast" => match args.next().and_then(|ratio| ratio.parse::<f32>().ok()) {
+                Some(ratio) if (1.0..=21.0).contains(&ratio) => auditor = auditor.with_min_text_contrast(ratio),
+                _ => {
+                    eprintln!("--min-contrast takes a ratio from 1 to 21\n{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown option '{}'\n{}", flag, USAGE);
+                return ExitCode::from(2);
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from(DEFAULT_THEMES_DIR));
+    }
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        eprintln!("{}: no such file or directory", missing.display());
+        return ExitCode::from(2);
+    }
+
+    let report = auditor.audit_paths(&paths);
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!(
+        "checked {} theme(s): {} error(s), {} warning(s)",
+        report.files_checked,
+        report.error_count(),
+        report.warning_count()
+    );
+
+    if report.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,88 @@
+//! Command line tools for workflow specs.
+//!
+//! `warp-workflows lint [--errors-only] [PATH...]` checks every spec under
+//! the given files or directories (default `workflows/specs`) and exits
+//! non-zero when any errors are found. Errors listed in
+//! `workflows/lint-allowlist.txt` are reported as warnings.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use warp_terminal::model::workflow_lint::{LintAllowlist, WorkflowLinter};
+
+const USAGE: &str = "usage: warp-workflows lint [--errors-only] [PATH...]";
+const DEFAULT_SPECS_DIR: &str = "workflows/specs";
+const ALLOWLIST: &str = "workflows/lint-allowlist.txt";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("lint") => lint(args.collect()),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn lint(args: Vec<String>) -> ExitCode {
+    let mut errors_only = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--errors-only" => errors_only = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown option '{}'\n{}", flag, USAGE);
+                return ExitCode::from(2);
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from(DEFAULT_SPECS_DIR));
+    }
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        eprintln!("{}: no such file or directory", missing.display());
+        return ExitCode::from(2);
+    }
+
+    let allowlist = if Path::new(ALLOWLIST).exists() {
+        match LintAllowlist::load(Path::new(ALLOWLIST)) {
+            Ok(allowlist) => allowlist,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        LintAllowlist::default()
+    };
+
+    let report = WorkflowLinter::new()
+        .with_warnings(!errors_only)
+        .with_allowlist(allowlist)
+        .lint_paths(&paths);
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    println!(
+        "checked {} workflow(s): {} error(s), {} warning(s)",
+        report.files_checked,
+        report.error_count(),
+        report.warning_count()
+    );
+
+    if report.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
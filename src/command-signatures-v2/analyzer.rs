@@ -0,0 +1,314 @@
+//! Walks a completion spec against a parsed command line.
+//!
+//! The analyzer resolves which subcommand the user is in, which options have
+//! been used and what kind of token is expected at the cursor. Completion and
+//! validation are both built on top of it.
+
+use super::parser::{CompletionSpec, SpecArg, SpecOption};
+use crate::syntax_tree::{parse_shell, CommandView, ShellDialect, WordView};
+
+/// A word of the command line with its byte span. Quotes and escapes are
+/// removed from `text` but included in the span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<&WordView> for Token {
+    fn from(word: &WordView) -> Self {
+        Self {
+            text: word.value.clone(),
+            start: word.span.start,
+            end: word.span.end,
+        }
+    }
+}
+
+/// The command name and arguments of every simple command on the line.
+///
+/// The line is parsed with the shell parser, so quotes, pipes, `&&`,
+/// redirects and command substitutions are all handled; assignments,
+/// leading keywords and redirect targets are not included.
+pub fn tokenize(line: &str) -> Vec<Vec<Token>> {
+    parse_shell(line, ShellDialect::default())
+        .commands()
+        .iter()
+        .map(command_tokens)
+        .filter(|tokens| !tokens.is_empty())
+        .collect()
+}
+
+fn command_tokens(command: &CommandView) -> Vec<Token> {
+    command.words().into_iter().map(Token::from).collect()
+}
+
+/// The words of the simple command being typed at byte offset `cursor`,
+/// up to the cursor. Returns `None` when the cursor is not inside a command,
+/// e.g. right after `&&`.
+pub fn tokenize_at(line: &str, cursor: usize) -> Option<Vec<Token>> {
+    let cursor = cursor.min(line.len());
+    let command = parse_shell(&line[..cursor], ShellDialect::default()).command_at_cursor(cursor)?;
+    let tokens = command_tokens(&command);
+    if tokens.is_empty() {
+        None
+    } else {
+        Some(tokens)
+    }
+}
+
+/// What the spec expects at a given position.
+#[derive(Debug, Clone, Copy)]
+pub enum Expectation<'a> {
+    /// A subcommand, an option, or the positional argument (if any).
+    Any { arg: Option<&'a SpecArg> },
+    /// The value of an option that takes an argument.
+    OptionValue {
+        option: &'a SpecOption,
+        arg: &'a SpecArg,
+    },
+}
+
+/// The result of walking a spec up to the cursor.
+#[derive(Debug, Clone)]
+pub struct SpecContext<'a> {
+    /// The deepest subcommand reached.
+    pub command: &'a CompletionSpec,
+    /// Names of the subcommands walked through, starting with the root command.
+    pub path: Vec<String>,
+    /// What is expected for the word under the cursor.
+    pub expecting: Expectation<'a>,
+    /// The (unquoted) word under the cursor, possibly empty.
+    pub current_word: String,
+    /// Byte offset where the word under the cursor starts.
+    pub word_start: usize,
+    /// Options already present on the line, by the name used.
+    pub used_options: Vec<String>,
+    /// Positional arguments already given to `command`.
+    pub positional_count: usize,
+}
+
+/// Walks `tokens` through a spec. Shared by [`analyze`] and the validator.
+pub(crate) struct SpecWalker<'a> {
+    root: &'a CompletionSpec,
+    pub command: &'a CompletionSpec,
+    pub path: Vec<String>,
+    pub pending_option: Option<(&'a SpecOption, usize)>,
+    pub used_options: Vec<String>,
+    pub positional_count: usize,
+}
+
+/// Outcome of feeding one token to the walker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Step {
+    Subcommand,
+    Option,
+    UnknownOption,
+    OptionValue,
+    Positional,
+    UnexpectedPositional,
+    EndOfOptions,
+}
+
+impl<'a> SpecWalker<'a> {
+    pub fn new(root: &'a CompletionSpec) -> Self {
+        Self {
+            root,
+            command: root,
+            path: vec![root.primary_name().to_string()],
+            pending_option: None,
+            used_options: Vec::new(),
+            positional_count: 0,
+        }
+    }
+
+    /// Look up an option on the current subcommand, falling back to the root
+    /// command's options which apply globally.
+    pub fn find_option(&self, name: &str) -> Option<&'a SpecOption> {
+        self.command
+            .find_option(name)
+            .or_else(|| self.root.find_option(name))
+    }
+
+    pub fn positional_arg(&self) -> Option<&'a SpecArg> {
+        positional_arg(self.command, self.positional_count)
+    }
+
+    pub fn feed(&mut self, word: &str, options_ended: bool) -> Step {
+        if let Some((option, remaining)) = self.pending_option.take() {
+            if remaining > 1 {
+                self.pending_option = Some((option, remaining - 1));
+            }
+            return Step::OptionValue;
+        }
+
+        if !options_ended && word == "--" {
+            return Step::EndOfOptions;
+        }
+
+        if !options_ended && word.starts_with('-') && word.len() > 1 {
+            let (name, inline_value) = match word.split_once('=') {
+                Some((name, _)) if name.starts_with("--") => (name, true),
+                _ => (word, false),
+            };
+            return match self.find_option(name) {
+                Some(option) => {
+                    self.used_options.push(name.to_string());
+                    let value_count = option.args.as_slice().len();
+                    if value_count > 0 && !inline_value {
+                        self.pending_option = Some((option, value_count));
+                    }
+                    Step::Option
+                }
+                None => Step::UnknownOption,
+            };
+        }
+
+        if self.positional_count == 0 {
+            if let Some(sub) = self.command.find_subcommand(word) {
+                self.command = sub;
+                self.path.push(sub.primary_name().to_string());
+                return Step::Subcommand;
+            }
+        }
+
+        let known = self.positional_arg().is_some();
+        self.positional_count += 1;
+        if known {
+            Step::Positional
+        } else {
+            Step::UnexpectedPositional
+        }
+    }
+}
+
+/// The positional argument at `index`, repeating a trailing variadic argument.
+pub fn positional_arg(command: &CompletionSpec, index: usize) -> Option<&SpecArg> {
+    let args = command.args.as_slice();
+    args.get(index)
+        .or_else(|| args.last().filter(|arg| arg.is_variadic))
+}
+
+/// Resolve the spec context at byte offset `cursor` of `line`.
+///
+/// Returns `None` when the cursor is still on the command name itself.
+pub fn analyze<'a>(spec: &'a CompletionSpec, line: &str, cursor: usize) -> Option<SpecContext<'a>> {
+    let cursor = cursor.min(line.len());
+    let tokens = tokenize_at(line, cursor)?;
+
+    // The word under the cursor is the last token unless the cursor sits
+    // after it, in which case a new empty word is being started.
+    let at_word_boundary = tokens.last().is_none_or(|t| t.end < cursor);
+    let (complete, current) = if at_word_boundary {
+        (&tokens[..], None)
+    } else {
+        (&tokens[..tokens.len() - 1], tokens.last())
+    };
+
+    if complete.is_empty() {
+        return None;
+    }
+
+    let mut walker = SpecWalker::new(spec);
+    let mut options_ended = false;
+    for token in &complete[1..] {
+        if walker.feed(&token.text, options_ended) == Step::EndOfOptions {
+            options_ended = true;
+        }
+    }
+
+    let expecting = match walker.pending_option {
+        Some((option, remaining)) => {
+            let args = option.args.as_slice();
+            let arg = &args[args.len() - remaining];
+            Expectation::OptionValue { option, arg }
+        }
+        None => Expectation::Any {
+            arg: walker.positional_arg(),
+        },
+    };
+
+    Some(SpecContext {
+        command: walker.command,
+        path: walker.path,
+        expecting,
+        current_word: current.map(|t| t.text.clone()).unwrap_or_default(),
+        word_start: current.map(|t| t.start).unwrap_or(cursor),
+        used_options: walker.used_options,
+        positional_count: walker.positional_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_signatures_v2::parser::bundled_specs;
+
+    #[test]
+    fn test_tokenize_quotes_and_spans() {
+        let commands = tokenize(r#"git commit -m "fix the build" it\'s"#);
+        let texts: Vec<_> = commands[0].iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["git", "commit", "-m", "fix the build", "it's"]);
+        assert_eq!(commands[0][3].start, 14);
+        assert_eq!(commands[0][3].end, 29);
+    }
+
+    #[test]
+    fn test_tokenize_splits_commands() {
+        let commands = tokenize("FOO=1 git log | grep -v 'a b' > out.txt && make");
+        let names: Vec<_> = commands.iter().map(|c| c[0].text.as_str()).collect();
+        assert_eq!(names, vec!["git", "grep", "make"]);
+        assert_eq!(commands[1].len(), 3);
+    }
+
+    #[test]
+    fn test_analyze_command_after_operator() {
+        let specs = bundled_specs();
+        let line = "cd src && git checkout fea";
+        let ctx = analyze(&specs["git"], line, line.len()).unwrap();
+        assert_eq!(ctx.path, vec!["git", "checkout"]);
+        assert_eq!(ctx.current_word, "fea");
+        assert_eq!(ctx.word_start, 23);
+
+        assert!(tokenize_at("git status && ", 14).is_none());
+    }
+
+    #[test]
+    fn test_analyze_subcommand_path() {
+        let specs = bundled_specs();
+        let ctx = analyze(&specs["git"], "git stash p", 11).unwrap();
+        assert_eq!(ctx.path, vec!["git", "stash"]);
+        assert_eq!(ctx.current_word, "p");
+        assert_eq!(ctx.word_start, 10);
+    }
+
+    #[test]
+    fn test_analyze_option_value() {
+        let specs = bundled_specs();
+        let line = "git checkout -b ";
+        let ctx = analyze(&specs["git"], line, line.len()).unwrap();
+        match ctx.expecting {
+            Expectation::OptionValue { arg, .. } => assert_eq!(arg.name, "new-branch"),
+            other => panic!("unexpected expectation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_analyze_positional_after_options() {
+        let specs = bundled_specs();
+        let line = "git push --force origin ";
+        let ctx = analyze(&specs["git"], line, line.len()).unwrap();
+        assert_eq!(ctx.used_options, vec!["--force"]);
+        match ctx.expecting {
+            Expectation::Any { arg: Some(arg) } => assert_eq!(arg.name, "branch"),
+            other => panic!("unexpected expectation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_analyze_on_command_name() {
+        let specs = bundled_specs();
+        assert!(analyze(&specs["git"], "gi", 2).is_none());
+    }
+}
@@ -0,0 +1,497 @@
+//! Spec-driven completion engine.
+//!
+//! Walks a [`CompletionSpec`] against the cursor position and offers
+//! subcommands, options and argument values. Argument values come from static
+//! suggestions, file system templates or generators that run a shell command
+//! (for example listing git branches or docker containers).
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::analyzer::{analyze, tokenize_at, Expectation, SpecContext};
+use super::parser::{bundled_specs, ArgTemplate, CompletionSpec, Generator, SpecArg};
+use crate::utils::process::run_with_deadline;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecSuggestionKind {
+    Subcommand,
+    Option,
+    Argument,
+    File,
+    Folder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecSuggestion {
+    pub text: String,
+    pub description: String,
+    pub kind: SpecSuggestionKind,
+}
+
+/// Suggestions for the word under the cursor. Accepting a suggestion replaces
+/// `line[replace_start..cursor]` with its text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpecCompletions {
+    pub replace_start: usize,
+    pub items: Vec<SpecSuggestion>,
+}
+
+/// Runs generator scripts. Abstracted so completion can be tested without a shell.
+pub trait GeneratorExecutor: Send + Sync {
+    fn run(&self, script: &str, cwd: &Path) -> Result<String, String>;
+}
+
+/// How long a generator may run before it is killed. Generators run as the
+/// user types, so a hung one must not hold a worker for long.
+const GENERATOR_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Executes generator scripts with `sh -c` in the given directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShellGeneratorExecutor;
+
+impl GeneratorExecutor for ShellGeneratorExecutor {
+    fn run(&self, script: &str, cwd: &Path) -> Result<String, String> {
+        run_with_deadline(script, Some(cwd), GENERATOR_TIMEOUT)
+    }
+}
+
+type GeneratorCache = HashMap<(String, PathBuf), (Instant, Vec<String>)>;
+
+/// Completion engine backed by completion specs.
+///
+/// Cloning is cheap; clones share the generator executor and result cache.
+#[derive(Clone)]
+pub struct SpecCompletionEngine {
+    specs: Arc<HashMap<String, CompletionSpec>>,
+    executor: Arc<dyn GeneratorExecutor>,
+    cache: Arc<Mutex<GeneratorCache>>,
+    cwd: PathBuf,
+    /// When false, generators only contribute output already in the cache.
+    run_generators: bool,
+}
+
+impl std::fmt::Debug for SpecCompletionEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpecCompletionEngine")
+            .field("specs", &self.specs.len())
+            .field("cwd", &self.cwd)
+            .finish()
+    }
+}
+
+impl Default for SpecCompletionEngine {
+    fn default() -> Self {
+        Self::new(bundled_specs())
+    }
+}
+
+impl SpecCompletionEngine {
+    pub fn new(specs: HashMap<String, CompletionSpec>) -> Self {
+        Self {
+            specs: Arc::new(specs),
+            executor: Arc::new(ShellGeneratorExecutor),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            run_generators: true,
+        }
+    }
+
+    /// Never start a generator; use only output cached by earlier
+    /// completions. For callers that run on every keystroke, such as ghost
+    /// text.
+    pub fn cached_generators_only(mut self) -> Self {
+        self.run_generators = false;
+        self
+    }
+
+    pub fn with_executor(mut self, executor: Arc<dyn GeneratorExecutor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = cwd.into();
+        self
+    }
+
+    pub fn set_cwd(&mut self, cwd: impl Into<PathBuf>) {
+        self.cwd = cwd.into();
+    }
+
+    pub fn has_spec(&self, command: &str) -> bool {
+        self.specs.contains_key(command)
+    }
+
+    pub fn spec(&self, command: &str) -> Option<&CompletionSpec> {
+        self.specs.get(command)
+    }
+
+    /// Complete the word at byte offset `cursor` of `line`.
+    pub fn complete(&self, line: &str, cursor: usize) -> SpecCompletions {
+        let cursor = cursor.min(line.len());
+        let command = tokenize_at(line, cursor)
+            .and_then(|tokens| tokens.into_iter().next())
+            .map(|token| token.text)
+            .unwrap_or_default();
+
+        let Some(spec) = self.specs.get(&command) else {
+            return SpecCompletions {
+                replace_start: cursor,
+                items: Vec::new(),
+            };
+        };
+
+        match analyze(spec, line, cursor) {
+            Some(ctx) => SpecCompletions {
+                replace_start: ctx.word_start,
+                items: self.suggestions_for(&ctx),
+            },
+            None => SpecCompletions {
+                replace_start: cursor,
+                items: Vec::new(),
+            },
+        }
+    }
+
+    fn suggestions_for(&self, ctx: &SpecContext<'_>) -> Vec<SpecSuggestion> {
+        let word = ctx.current_word.as_str();
+        let mut items = Vec::new();
+
+        match ctx.expecting {
+            Expectation::OptionValue { arg, .. } => {
+                items.extend(self.arg_suggestions(arg, word));
+            }
+            Expectation::Any { arg } => {
+                if word.starts_with('-') {
+                    for option in &ctx.command.options {
+                        let used = option
+                            .name
+                            .as_slice()
+                            .iter()
+                            .any(|n| ctx.used_options.contains(n));
+                        if used && !option.is_repeatable {
+                            continue;
+                        }
+                        items.extend(
+                            option
+                                .name
+                                .as_slice()
+                                .iter()
+                                .filter(|name| name.starts_with(word))
+                                .map(|name| SpecSuggestion {
+                                    text: name.clone(),
+                                    description: option.description.clone(),
+                                    kind: SpecSuggestionKind::Option,
+                                }),
+                        );
+                    }
+                } else {
+                    if ctx.positional_count == 0 {
+                        items.extend(
+                            ctx.command
+                                .subcommands
+                                .iter()
+                                .filter(|sub| sub.primary_name().starts_with(word))
+                                .map(|sub| SpecSuggestion {
+                                    text: sub.primary_name().to_string(),
+                                    description: sub.description.clone(),
+                                    kind: SpecSuggestionKind::Subcommand,
+                                }),
+                        );
+                    }
+                    if let Some(arg) = arg {
+                        items.extend(self.arg_suggestions(arg, word));
+                    }
+                }
+            }
+        }
+
+        items
+    }
+
+    fn arg_suggestions(&self, arg: &SpecArg, word: &str) -> Vec<SpecSuggestion> {
+        let mut items: Vec<SpecSuggestion> = arg
+            .suggestions
+            .iter()
+            .filter(|s| s.starts_with(word))
+            .map(|s| SpecSuggestion {
+                text: s.clone(),
+                description: arg.description.clone(),
+                kind: SpecSuggestionKind::Argument,
+            })
+            .collect();
+
+        for generator in &arg.generators {
+            for candidate in self.run_generator(generator) {
+                if candidate.starts_with(word) && !items.iter().any(|i| i.text == candidate) {
+                    items.push(SpecSuggestion {
+                        text: candidate,
+                        description: arg.name.clone(),
+                        kind: SpecSuggestionKind::Argument,
+                    });
+                }
+            }
+        }
+
+        if let Some(template) = arg.template {
+            items.extend(self.template_suggestions(template, word));
+        }
+
+        items
+    }
+
+    /// Run a generator, reusing cached output for the same directory while it
+    /// is fresh.
+    fn run_generator(&self, generator: &Generator) -> Vec<String> {
+        let key = (generator.script.clone(), self.cwd.clone());
+        let ttl = Duration::from_secs(generator.cache_ttl_secs);
+
+        if let Ok(cache) = self.cache.lock() {
+            if let Some((at, values)) = cache.get(&key) {
+                if at.elapsed() < ttl {
+                    return values.clone();
+                }
+            }
+        }
+        if !self.run_generators {
+            return Vec::new();
+        }
+
+        let values = match self.executor.run(&generator.script, &self.cwd) {
+            Ok(output) => parse_generator_output(generator, &output),
+            Err(e) => {
+                tracing::debug!("Completion generator '{}' failed: {}", generator.script, e);
+                Vec::new()
+            }
+        };
+
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, (Instant::now(), values.clone()));
+        }
+        values
+    }
+
+    fn template_suggestions(&self, template: ArgTemplate, word: &str) -> Vec<SpecSuggestion> {
+        let (dir_part, file_prefix) = match word.rfind('/') {
+            Some(i) => (&word[..=i], &word[i + 1..]),
+            None => ("", word),
+        };
+        let dir = if dir_part.is_empty() {
+            self.cwd.clone()
+        } else if Path::new(dir_part).is_absolute() {
+            PathBuf::from(dir_part)
+        } else {
+            self.cwd.join(dir_part)
+        };
+
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+
+        let mut items: Vec<SpecSuggestion> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                if !name.starts_with(file_prefix)
+                    || (name.starts_with('.') && !file_prefix.starts_with('.'))
+                    || (template == ArgTemplate::Folders && !is_dir)
+                {
+                    return None;
+                }
+                Some(SpecSuggestion {
+                    text: format!("{}{}{}", dir_part, name, if is_dir { "/" } else { "" }),
+                    description: String::new(),
+                    kind: if is_dir {
+                        SpecSuggestionKind::Folder
+                    } else {
+                        SpecSuggestionKind::File
+                    },
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| a.text.cmp(&b.text));
+        items
+    }
+}
+
+/// Split generator output into candidates.
+fn parse_generator_output(generator: &Generator, output: &str) -> Vec<String> {
+    let pattern = generator
+        .pattern
+        .as_deref()
+        .and_then(|p| Regex::new(p).map_err(|e| tracing::warn!("Invalid generator pattern: {}", e)).ok());
+
+    let pieces: Vec<&str> = match generator.split_on.as_deref() {
+        Some(separator) if !separator.is_empty() => output.split(separator).collect(),
+        _ => output.lines().collect(),
+    };
+
+    let mut values = Vec::new();
+    for piece in pieces {
+        let candidate = match &pattern {
+            Some(re) => match re.captures(piece) {
+                Some(caps) => caps
+                    .get(1)
+                    .or_else(|| caps.get(0))
+                    .map(|m| m.as_str().trim().to_string()),
+                None => None,
+            },
+            None => Some(piece.trim().to_string()),
+        };
+        if let Some(candidate) = candidate.filter(|c| !c.is_empty()) {
+            if !values.contains(&candidate) {
+                values.push(candidate);
+            }
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers generator scripts from a fixed table and counts invocations.
+    struct FakeExecutor {
+        outputs: HashMap<&'static str, &'static str>,
+        calls: AtomicUsize,
+    }
+
+    impl GeneratorExecutor for FakeExecutor {
+        fn run(&self, script: &str, _cwd: &Path) -> Result<String, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.outputs
+                .iter()
+                .find(|(prefix, _)| script.starts_with(*prefix))
+                .map(|(_, out)| out.to_string())
+                .ok_or_else(|| "unknown script".to_string())
+        }
+    }
+
+    fn engine() -> (SpecCompletionEngine, Arc<FakeExecutor>) {
+        let executor = Arc::new(FakeExecutor {
+            outputs: HashMap::from([
+                ("git branch", "main\nfeature/login\nrelease\n"),
+                ("git remote", "origin\nupstream\n"),
+                ("docker ps", "web\ndb\n"),
+                ("make -qp", "all: build\nbuild: main.o\n.PHONY: all\nCC = gcc\nclean:\n"),
+            ]),
+            calls: AtomicUsize::new(0),
+        });
+        let engine = SpecCompletionEngine::default().with_executor(executor.clone());
+        (engine, executor)
+    }
+
+    fn texts(completions: &SpecCompletions) -> Vec<&str> {
+        completions.items.iter().map(|i| i.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_subcommand_completion() {
+        let (engine, _) = engine();
+        let completions = engine.complete("git che", 7);
+        assert_eq!(completions.replace_start, 4);
+        assert_eq!(texts(&completions), vec!["checkout"]);
+    }
+
+    #[test]
+    fn test_option_completion_skips_used_options() {
+        let (engine, _) = engine();
+        let line = "git commit --amend --";
+        let completions = engine.complete(line, line.len());
+        let texts = texts(&completions);
+        assert!(texts.contains(&"--message"));
+        assert!(!texts.contains(&"--amend"));
+    }
+
+    #[test]
+    fn test_generator_completion_for_git_branches() {
+        let (engine, _) = engine();
+        let line = "git checkout f";
+        let completions = engine.complete(line, line.len());
+        assert_eq!(texts(&completions), vec!["feature/login"]);
+    }
+
+    #[test]
+    fn test_generator_completion_for_docker_containers() {
+        let (engine, _) = engine();
+        let line = "docker logs -f ";
+        let completions = engine.complete(line, line.len());
+        assert_eq!(texts(&completions), vec!["web", "db"]);
+    }
+
+    #[test]
+    fn test_generator_pattern_for_make_targets() {
+        let (engine, _) = engine();
+        let completions = engine.complete("make ", 5);
+        assert_eq!(texts(&completions), vec!["all", "build", "clean"]);
+    }
+
+    #[test]
+    fn test_generator_results_are_cached() {
+        let (engine, executor) = engine();
+        engine.complete("git merge ", 10);
+        engine.complete("git merge m", 11);
+        assert_eq!(executor.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cached_generators_only() {
+        let (engine, executor) = engine();
+        let cached_only = engine.clone().cached_generators_only();
+        assert!(cached_only.complete("git merge m", 11).items.is_empty());
+        assert_eq!(executor.calls.load(Ordering::SeqCst), 0);
+
+        // Clones share the cache, so output from a normal completion is used
+        engine.complete("git merge ", 10);
+        assert!(!cached_only.complete("git merge m", 11).items.is_empty());
+        assert_eq!(executor.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_static_suggestions_for_option_values() {
+        let (engine, _) = engine();
+        let line = "kubectl get pods -o y";
+        let completions = engine.complete(line, line.len());
+        assert_eq!(texts(&completions), vec!["yaml"]);
+    }
+
+    #[test]
+    fn test_completion_in_middle_of_line() {
+        let (engine, _) = engine();
+        let completions = engine.complete("git sta --short", 7);
+        assert_eq!(texts(&completions), vec!["stash", "status"]);
+    }
+
+    #[test]
+    fn test_folder_template() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("setup.py"), "").unwrap();
+        let (engine, _) = engine();
+        let engine = engine.with_cwd(dir.path());
+
+        let completions = engine.complete("git -C s", 8);
+        assert_eq!(texts(&completions), vec!["src/"]);
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let (engine, _) = engine();
+        assert!(engine.complete("frobnicate --", 13).items.is_empty());
+    }
+
+    #[test]
+    fn test_shell_generator_is_killed_at_the_deadline() {
+        let started = Instant::now();
+        let result = ShellGeneratorExecutor.run("sleep 10; echo late", Path::new("."));
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
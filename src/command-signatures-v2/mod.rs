@@ -2,6 +2,10 @@
 //! 
 //! Enhanced command signature detection and analysis system
 //! with improved parsing, validation, and completion features.
+//!
+//! Signatures are backed by Fig-style completion specs (see [`parser`]);
+//! a set of specs for common tools ships with the application and more can
+//! be loaded from JSON or YAML files at runtime.
 
 pub mod parser;
 pub mod validator;
@@ -10,6 +14,11 @@ pub mod analyzer;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+
+pub use completion::{SpecCompletionEngine, SpecCompletions, SpecSuggestion, SpecSuggestionKind};
+pub use parser::{CompletionSpec, SpecError};
+pub use validator::{validate, IssueSeverity, SpecIssue};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandSignature {
@@ -53,6 +62,7 @@ pub struct Flag {
 
 pub struct CommandSignatureManager {
     signatures: HashMap<String, CommandSignature>,
+    specs: HashMap<String, CompletionSpec>,
     custom_parsers: HashMap<String, Box<dyn Fn(&str) -> Option<CommandSignature>>>,
 }
 
@@ -60,35 +70,82 @@ impl CommandSignatureManager {
     pub fn new() -> Self {
         Self {
             signatures: HashMap::new(),
+            specs: HashMap::new(),
             custom_parsers: HashMap::new(),
         }
     }
 
+    /// Create a manager preloaded with the bundled completion specs.
+    pub fn with_bundled_specs() -> Self {
+        let mut manager = Self::new();
+        for spec in parser::bundled_specs().into_values() {
+            manager.register_spec(spec);
+        }
+        manager
+    }
+
     pub fn register_signature(&mut self, signature: CommandSignature) {
         self.signatures.insert(signature.name.clone(), signature);
     }
 
+    /// Register a completion spec, replacing any spec for the same command,
+    /// and derive its signature.
+    pub fn register_spec(&mut self, spec: CompletionSpec) {
+        self.register_signature(CommandSignature::from(&spec));
+        self.specs.insert(spec.primary_name().to_string(), spec);
+    }
+
+    pub fn get_spec(&self, command: &str) -> Option<&CompletionSpec> {
+        self.specs.get(command)
+    }
+
+    /// Load every spec file in `dir`. Returns the number of specs loaded;
+    /// files that fail to parse are logged and skipped.
+    pub fn load_specs_from_dir(&mut self, dir: &Path) -> Result<usize, SpecError> {
+        let (specs, errors) = parser::load_spec_dir(dir)?;
+        for error in errors {
+            tracing::warn!("Skipping completion spec: {}", error);
+        }
+        let count = specs.len();
+        for spec in specs {
+            self.register_spec(spec);
+        }
+        Ok(count)
+    }
+
+    /// Build a completion engine over the registered specs.
+    pub fn completion_engine(&self) -> SpecCompletionEngine {
+        SpecCompletionEngine::new(self.specs.clone())
+    }
+
+    /// Validate every command on the line that has a registered spec.
+    pub fn validate_command(&self, command_line: &str) -> Vec<SpecIssue> {
+        analyzer::tokenize(command_line)
+            .iter()
+            .filter_map(|tokens| {
+                self.specs
+                    .get(&tokens[0].text)
+                    .map(|spec| validator::validate_tokens(spec, tokens))
+            })
+            .flatten()
+            .collect()
+    }
+
     pub fn get_signature(&self, command: &str) -> Option<&CommandSignature> {
         self.signatures.get(command)
     }
 
     pub fn parse_command(&self, command_line: &str) -> Option<ParsedCommand> {
-        // Basic parsing implementation
-        let parts: Vec<&str> = command_line.split_whitespace().collect();
-        if parts.is_empty() {
-            return None;
-        }
+        let tree = crate::syntax_tree::parse_shell(command_line, Default::default());
+        let mut argv = tree.commands().into_iter().find(|c| c.name.is_some())?.argv();
+        let command_name = argv.remove(0);
 
-        let command_name = parts[0];
-        if let Some(signature) = self.get_signature(command_name) {
-            Some(ParsedCommand {
-                name: command_name.to_string(),
-                args: parts[1..].iter().map(|s| s.to_string()).collect(),
-                signature: signature.clone(),
-            })
-        } else {
-            None
-        }
+        let signature = self.get_signature(&command_name)?;
+        Some(ParsedCommand {
+            name: command_name,
+            args: argv,
+            signature: signature.clone(),
+        })
     }
 }
 
@@ -104,3 +161,46 @@ impl Default for CommandSignatureManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_specs_register_signatures() {
+        let manager = CommandSignatureManager::with_bundled_specs();
+        let signature = manager.get_signature("docker").unwrap();
+        assert!(signature.subcommands.contains(&"run".to_string()));
+        assert!(manager.parse_command("docker ps -a").is_some());
+    }
+
+    #[test]
+    fn test_load_specs_from_dir_overrides_bundled() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("git.json"),
+            r#"{"name": "git", "subcommands": [{"name": "frob"}]}"#,
+        )
+        .unwrap();
+
+        let mut manager = CommandSignatureManager::with_bundled_specs();
+        assert_eq!(manager.load_specs_from_dir(dir.path()).unwrap(), 1);
+        assert_eq!(manager.get_signature("git").unwrap().subcommands, vec!["frob"]);
+    }
+
+    #[test]
+    fn test_validate_command() {
+        let manager = CommandSignatureManager::with_bundled_specs();
+        assert!(manager.validate_command("unknown-tool --x").is_empty());
+        assert!(!manager.validate_command("git status --bogus").is_empty());
+        assert!(!manager.validate_command("ls -la && docker logs").is_empty());
+    }
+
+    #[test]
+    fn test_parse_command_unquotes_arguments() {
+        let manager = CommandSignatureManager::with_bundled_specs();
+        let parsed = manager.parse_command("GIT_PAGER= git commit -m \"two words\" | cat").unwrap();
+        assert_eq!(parsed.name, "git");
+        assert_eq!(parsed.args, vec!["commit", "-m", "two words"]);
+    }
+}
@@ -0,0 +1,385 @@
+//! Completion spec format and loader.
+//!
+//! Specs describe a command as a Fig-style tree of subcommands, options and
+//! arguments. They can be written in JSON or YAML:
+//!
+//! ```yaml
+//! name: git
+//! description: The stupid content tracker
+//! subcommands:
+//!   - name: checkout
+//!     args:
+//!       name: branch
+//!       generators:
+//!         - script: git branch --format='%(refname:short)'
+//!     options:
+//!       - name: ["-b"]
+//!         description: Create a new branch
+//!         args: { name: new-branch }
+//! ```
+//!
+//! Arguments can list static `suggestions`, a file system `template`, or
+//! `generators` that run a shell command and turn each output line into a
+//! candidate.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{CommandSignature, Flag, Parameter, ParameterType};
+
+/// Specs shipped with the application.
+const BUNDLED_SPECS: &[(&str, &str)] = &[
+    ("cargo.yaml", include_str!("specs/cargo.yaml")),
+    ("docker.yaml", include_str!("specs/docker.yaml")),
+    ("git.yaml", include_str!("specs/git.yaml")),
+    ("kubectl.yaml", include_str!("specs/kubectl.yaml")),
+    ("make.yaml", include_str!("specs/make.yaml")),
+    ("npm.yaml", include_str!("specs/npm.yaml")),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpecError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid JSON spec {0}: {1}")]
+    Json(String, serde_json::Error),
+
+    #[error("Invalid YAML spec {0}: {1}")]
+    Yaml(String, serde_yaml::Error),
+
+    #[error("Unsupported spec file extension: {0}")]
+    UnsupportedFormat(String),
+}
+
+/// A single value or a list of values; specs accept both spellings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> Default for OneOrMany<T> {
+    fn default() -> Self {
+        OneOrMany::Many(Vec::new())
+    }
+}
+
+impl<T> OneOrMany<T> {
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// A command or subcommand node in a completion spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionSpec {
+    pub name: OneOrMany<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub subcommands: Vec<CompletionSpec>,
+    #[serde(default)]
+    pub options: Vec<SpecOption>,
+    #[serde(default)]
+    pub args: OneOrMany<SpecArg>,
+}
+
+/// A flag such as `-b` / `--branch`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecOption {
+    pub name: OneOrMany<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub args: OneOrMany<SpecArg>,
+    #[serde(default)]
+    pub is_repeatable: bool,
+    #[serde(default)]
+    pub is_required: bool,
+}
+
+/// A positional argument or option value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecArg {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    #[serde(default)]
+    pub template: Option<ArgTemplate>,
+    #[serde(default)]
+    pub generators: Vec<Generator>,
+    #[serde(default)]
+    pub is_optional: bool,
+    #[serde(default)]
+    pub is_variadic: bool,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// Built-in file system suggestions for an argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgTemplate {
+    Filepaths,
+    Folders,
+}
+
+/// Produces candidates by running a shell command in the current directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Generator {
+    pub script: String,
+    /// Separator between candidates in the script output. Defaults to newlines.
+    #[serde(default)]
+    pub split_on: Option<String>,
+    /// Optional regex; when set, the first capture group of each line is used
+    /// as the candidate and lines that do not match are dropped.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// How long results may be reused for the same directory, in seconds.
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl_secs: u64,
+}
+
+fn default_cache_ttl() -> u64 {
+    10
+}
+
+impl CompletionSpec {
+    /// The primary name of this command.
+    pub fn primary_name(&self) -> &str {
+        self.name.as_slice().first().map(String::as_str).unwrap_or("")
+    }
+
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name.as_slice().iter().any(|n| n == name)
+    }
+
+    pub fn find_subcommand(&self, name: &str) -> Option<&CompletionSpec> {
+        self.subcommands.iter().find(|sub| sub.matches_name(name))
+    }
+
+    pub fn find_option(&self, name: &str) -> Option<&SpecOption> {
+        self.options.iter().find(|opt| opt.matches_name(name))
+    }
+}
+
+impl SpecOption {
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name.as_slice().iter().any(|n| n == name)
+    }
+
+    pub fn takes_value(&self) -> bool {
+        !self.args.as_slice().is_empty()
+    }
+}
+
+/// Parse a spec from a string; `format` is a file extension (`json`, `yaml`, `yml`).
+pub fn parse_spec(source: &str, format: &str, origin: &str) -> Result<CompletionSpec, SpecError> {
+    match format {
+        "json" => serde_json::from_str(source).map_err(|e| SpecError::Json(origin.to_string(), e)),
+        "yaml" | "yml" => {
+            serde_yaml::from_str(source).map_err(|e| SpecError::Yaml(origin.to_string(), e))
+        }
+        other => Err(SpecError::UnsupportedFormat(other.to_string())),
+    }
+}
+
+/// Load a single spec file.
+pub fn load_spec_file(path: &Path) -> Result<CompletionSpec, SpecError> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let source = fs::read_to_string(path)?;
+    parse_spec(&source, &extension, &path.display().to_string())
+}
+
+/// Load every `.json`, `.yaml` and `.yml` spec in `dir`. Files that fail to
+/// parse are reported alongside the specs that loaded.
+pub fn load_spec_dir(dir: &Path) -> Result<(Vec<CompletionSpec>, Vec<SpecError>), SpecError> {
+    let mut specs = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("json" | "yaml" | "yml")
+            )
+        })
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match load_spec_file(&path) {
+            Ok(spec) => specs.push(spec),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    Ok((specs, errors))
+}
+
+/// Parse the specs bundled into the binary, keyed by command name.
+pub fn bundled_specs() -> HashMap<String, CompletionSpec> {
+    BUNDLED_SPECS
+        .iter()
+        .filter_map(|(file, source)| match parse_spec(source, "yaml", file) {
+            Ok(spec) => Some((spec.primary_name().to_string(), spec)),
+            Err(e) => {
+                tracing::warn!("Failed to parse bundled completion spec: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+impl From<&CompletionSpec> for CommandSignature {
+    fn from(spec: &CompletionSpec) -> Self {
+        let parameters = spec
+            .args
+            .as_slice()
+            .iter()
+            .map(|arg| Parameter {
+                name: arg.name.clone(),
+                param_type: match arg.template {
+                    Some(_) => ParameterType::Path,
+                    None => ParameterType::String,
+                },
+                required: !arg.is_optional,
+                description: arg.description.clone(),
+                default_value: arg.default.clone(),
+            })
+            .collect();
+
+        let flags = spec
+            .options
+            .iter()
+            .map(|option| {
+                let names = option.name.as_slice();
+                let short = names
+                    .iter()
+                    .find(|n| n.len() == 2 && n.starts_with('-') && !n.starts_with("--"))
+                    .and_then(|n| n.chars().nth(1));
+                let long = names
+                    .iter()
+                    .find(|n| n.starts_with("--"))
+                    .or_else(|| names.first())
+                    .map(|n| n.trim_start_matches('-').to_string())
+                    .unwrap_or_default();
+                Flag {
+                    short,
+                    long,
+                    description: option.description.clone(),
+                    takes_value: option.takes_value(),
+                    multiple: option.is_repeatable,
+                }
+            })
+            .collect();
+
+        CommandSignature {
+            name: spec.primary_name().to_string(),
+            description: spec.description.clone(),
+            parameters,
+            examples: Vec::new(),
+            flags,
+            subcommands: spec
+                .subcommands
+                .iter()
+                .map(|sub| sub.primary_name().to_string())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yaml_spec_with_single_and_list_names() {
+        let spec = parse_spec(
+            r#"
+name: tool
+subcommands:
+  - name: [remove, rm]
+    args: { name: item, isVariadic: true }
+options:
+  - name: ["-v", "--verbose"]
+"#,
+            "yaml",
+            "test",
+        )
+        .unwrap();
+
+        assert_eq!(spec.primary_name(), "tool");
+        let rm = spec.find_subcommand("rm").unwrap();
+        assert_eq!(rm.primary_name(), "remove");
+        assert!(rm.args.as_slice()[0].is_variadic);
+        assert!(spec.find_option("--verbose").is_some());
+    }
+
+    #[test]
+    fn test_parse_json_spec() {
+        let spec = parse_spec(
+            r#"{"name": "make", "args": {"name": "target", "generators": [{"script": "make -qp"}]}}"#,
+            "json",
+            "test",
+        )
+        .unwrap();
+        let arg = &spec.args.as_slice()[0];
+        assert_eq!(arg.generators[0].script, "make -qp");
+        assert_eq!(arg.generators[0].cache_ttl_secs, 10);
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        assert!(matches!(
+            parse_spec("", "txt", "test"),
+            Err(SpecError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_bundled_specs_parse() {
+        let specs = bundled_specs();
+        assert_eq!(specs.len(), BUNDLED_SPECS.len());
+        assert!(specs["git"].find_subcommand("checkout").is_some());
+    }
+
+    #[test]
+    fn test_spec_to_signature() {
+        let specs = bundled_specs();
+        let signature = CommandSignature::from(&specs["git"]);
+        assert_eq!(signature.name, "git");
+        assert!(signature.subcommands.contains(&"commit".to_string()));
+    }
+
+    #[test]
+    fn test_load_spec_dir_reports_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("ok.json"), r#"{"name": "ok"}"#).unwrap();
+        fs::write(dir.path().join("bad.yaml"), "name: [unterminated").unwrap();
+        fs::write(dir.path().join("ignored.txt"), "not a spec").unwrap();
+
+        let (specs, errors) = load_spec_dir(dir.path()).unwrap();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -0,0 +1,180 @@
+//! Validates a command line against its completion spec.
+
+use super::analyzer::{positional_arg, tokenize, SpecWalker, Step, Token};
+use super::parser::CompletionSpec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+/// A problem found on the command line, with the byte span it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecIssue {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+    pub severity: IssueSeverity,
+}
+
+/// Check every invocation of `spec`'s command on `line` and report unknown
+/// options, missing option values, unexpected arguments and missing required
+/// arguments.
+pub fn validate(spec: &CompletionSpec, line: &str) -> Vec<SpecIssue> {
+    tokenize(line)
+        .iter()
+        .filter(|tokens| spec.matches_name(&tokens[0].text))
+        .flat_map(|tokens| validate_tokens(spec, tokens))
+        .collect()
+}
+
+/// Validate the words of a single command (name first) against `spec`.
+pub(crate) fn validate_tokens(spec: &CompletionSpec, tokens: &[Token]) -> Vec<SpecIssue> {
+    let mut issues = Vec::new();
+    let Some(last) = tokens.last() else {
+        return issues;
+    };
+    let end = last.end;
+
+    let mut walker = SpecWalker::new(spec);
+    let mut options_ended = false;
+    for token in &tokens[1..] {
+        match walker.feed(&token.text, options_ended) {
+            Step::EndOfOptions => options_ended = true,
+            Step::UnknownOption => issues.push(SpecIssue {
+                message: format!("Unknown option '{}' for '{}'", token.text, walker.path.join(" ")),
+                start: token.start,
+                end: token.end,
+                severity: IssueSeverity::Warning,
+            }),
+            Step::UnexpectedPositional => {
+                let message = if walker.command.subcommands.is_empty() {
+                    format!("Unexpected argument '{}'", token.text)
+                } else {
+                    format!(
+                        "Unknown subcommand '{}' for '{}'",
+                        token.text,
+                        walker.path.join(" ")
+                    )
+                };
+                issues.push(SpecIssue {
+                    message,
+                    start: token.start,
+                    end: token.end,
+                    severity: IssueSeverity::Warning,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((option, _)) = walker.pending_option {
+        issues.push(SpecIssue {
+            message: format!(
+                "Option '{}' requires a value",
+                option.name.as_slice().first().map(String::as_str).unwrap_or_default()
+            ),
+            start: last.start,
+            end: last.end,
+            severity: IssueSeverity::Error,
+        });
+    }
+
+    // Only report missing arguments for leaf commands; a bare `git` is fine.
+    if walker.command.subcommands.is_empty() || walker.positional_count > 0 {
+        let required = walker
+            .command
+            .args
+            .as_slice()
+            .iter()
+            .filter(|arg| !arg.is_optional)
+            .count();
+        if walker.positional_count < required {
+            if let Some(arg) = positional_arg(walker.command, walker.positional_count) {
+                issues.push(SpecIssue {
+                    message: format!(
+                        "Missing required argument <{}> for '{}'",
+                        arg.name,
+                        walker.path.join(" ")
+                    ),
+                    start: end,
+                    end,
+                    severity: IssueSeverity::Error,
+                });
+            }
+        }
+    }
+
+    for option in &walker.command.options {
+        let present = option
+            .name
+            .as_slice()
+            .iter()
+            .any(|n| walker.used_options.contains(n));
+        if option.is_required && !present {
+            issues.push(SpecIssue {
+                message: format!(
+                    "Missing required option '{}'",
+                    option.name.as_slice().first().map(String::as_str).unwrap_or_default()
+                ),
+                start: end,
+                end,
+                severity: IssueSeverity::Error,
+            });
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_signatures_v2::parser::bundled_specs;
+
+    #[test]
+    fn test_valid_command_has_no_issues() {
+        let specs = bundled_specs();
+        assert!(validate(&specs["git"], "git commit -m 'wip' --amend").is_empty());
+        assert!(validate(&specs["git"], "git").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_option_and_subcommand() {
+        let specs = bundled_specs();
+        let issues = validate(&specs["git"], "git comit --bogus");
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].message.contains("Unknown subcommand 'comit'"));
+        assert_eq!((issues[0].start, issues[0].end), (4, 9));
+
+        let issues = validate(&specs["git"], "git status --bogus");
+        assert!(issues[0].message.contains("Unknown option '--bogus'"));
+    }
+
+    #[test]
+    fn test_missing_option_value() {
+        let specs = bundled_specs();
+        let issues = validate(&specs["git"], "git commit -m");
+        assert_eq!(issues[0].severity, IssueSeverity::Error);
+        assert!(issues[0].message.contains("requires a value"));
+    }
+
+    #[test]
+    fn test_missing_required_argument() {
+        let specs = bundled_specs();
+        let issues = validate(&specs["docker"], "docker logs");
+        assert!(issues[0].message.contains("<container>"));
+    }
+
+    #[test]
+    fn test_validates_each_invocation_on_the_line() {
+        let specs = bundled_specs();
+        let line = "git status --bogus | grep x && git log > out.txt";
+        let issues = validate(&specs["git"], line);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(&line[issues[0].start..issues[0].end], "--bogus");
+        // Redirect targets are not arguments
+        assert!(validate(&specs["make"], "make build 2> errors.log").is_empty());
+    }
+}
@@ -1,11 +1,11 @@
-pub mod corrections;
 pub mod history;
 pub mod search;
 pub mod synchronized_inputs;
 pub mod workflow_manager;
+pub mod workflow_runner;
 
-pub use corrections::{CommandCorrections, Correction, CorrectionType};
 pub use history::{CommandHistory, HistoryEntry, SearchResult, MatchType, ExportFormat};
 pub use search::{CommandSearch, UnifiedSearchResult, SearchSource, Notebook};
 pub use synchronized_inputs::{SynchronizedInputs, YAMLWorkflow, WorkflowStep};
 pub use workflow_manager::{WorkflowManager, Workflow};
+pub use workflow_runner::{WorkflowRun, WorkflowRunError, PlannedStep, StepPreview, StepResult, StepStatus};
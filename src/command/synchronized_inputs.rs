@@ -2,6 +2,11 @@ use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::fs;
 
+use crate::executor::CommandExecutor;
+use super::workflow_runner::{WorkflowRun, WorkflowRunError};
+
+pub use warp_workflows_types::WorkflowStep;
+
 #[derive(Debug, Clone)]
 pub struct SynchronizedInputs {
     sessions: HashMap<String, Vec<String>>, // session ID -> list of commands
@@ -52,13 +57,6 @@ pub struct YAMLWorkflow {
     pub steps: Vec<WorkflowStep>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkflowStep {
-    pub name: String,
-    pub command: String,
-    pub description: Option<String>,
-}
-
 impl YAMLWorkflow {
     pub fn new(name: &str) -> Self {
         Self {
@@ -68,20 +66,20 @@ impl YAMLWorkflow {
     }
 
     pub fn add_step(&mut self, name: &str, command: &str, description: Option<&str>) {
-        self.steps.push(WorkflowStep {
-            name: name.to_string(),
-            command: command.to_string(),
-            description: description.map(|d| d.to_string()),
-        });
+        let mut step = WorkflowStep::new(name, command);
+        step.description = description.map(|d| d.to_string());
+        self.steps.push(step);
     }
 
-    pub fn execute(&self) {
-        println!("Executing workflow: {}", self.name);
-        for step in &self.steps {
-            println!("Executing step: {}", step.name);
-            println!("Command: {}", step.command);
-            // Logic to execute step.command would go here
-        }
+    /// The equivalent multi-step `Workflow`
+    pub fn to_workflow(&self) -> warp_workflows_types::Workflow {
+        warp_workflows_types::Workflow::new(self.name.clone(), "").with_steps(self.steps.clone())
+    }
+
+    /// Run the steps in order with `executor`
+    pub async fn execute(&self, executor: &CommandExecutor) -> Result<WorkflowRun, WorkflowRunError> {
+        tracing::info!(workflow = %self.name, "Executing workflow");
+        WorkflowRun::new(&self.to_workflow())?.run(executor).await
     }
 
     pub fn export_to_yaml(&self, file_path: &str) -> std::io::Result<()> {
@@ -126,4 +124,17 @@ mod tests {
         
         fs::remove_file(file_path).unwrap(); // Clean up
     }
+
+    #[tokio::test]
+    async fn test_yaml_workflow_execute() {
+        let mut workflow = YAMLWorkflow::new("Greeting");
+        workflow.add_step("Greet", "echo Hello", None);
+        workflow.add_step("Fail", "false", None);
+        workflow.add_step("Never", "echo unreachable", None);
+
+        let run = workflow.execute(&CommandExecutor::new()).await.unwrap();
+        assert!(!run.succeeded());
+        assert_eq!(run.results()[1].exit_code, Some(1));
+        assert_eq!(run.results()[2].status, crate::command::StepStatus::Skipped);
+    }
 }
@@ -1,24 +1,13 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
-pub struct WorkflowManager {
-    workflows: HashMap<String, Workflow>,
-}
+use crate::executor::CommandExecutor;
+use super::workflow_runner::{StepPreview, WorkflowRun, WorkflowRunError};
 
-#[derive(Debug, Clone)]
-pub struct Workflow {
-    pub name: String,
-    pub steps: Vec<WorkflowStep>,
-}
+pub use warp_workflows_types::{Workflow, WorkflowStep};
 
-#[derive(Debug, Clone)]
-pub struct WorkflowStep {
-    pub name: String,
-    pub command: String,
-    pub description: Option<String>,
-    pub dependencies: Vec<String>,
-    pub working_directory: Option<PathBuf>,
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowManager {
+    workflows: HashMap<String, Workflow>,
 }
 
 impl WorkflowManager {
@@ -32,18 +21,34 @@ impl WorkflowManager {
         self.workflows.insert(workflow.name.clone(), workflow);
     }
 
-    pub fn run_workflow(&self, name: &str) -> Result<(), String> {
-        if let Some(workflow) = self.workflows.get(name) {
-            for step in &workflow.steps {
-                println!("Running step: {}", step.name);
-                // Here you would execute the command, handle directories and dependencies
-                // For now, just printing the command
-                println!("Executing command: {}", step.command);
-            }
-            Ok(())
-        } else {
-            Err(format!("Workflow '{}' not found.", name))
-        }
+    pub fn get_workflow(&self, name: &str) -> Option<&Workflow> {
+        self.workflows.get(name)
+    }
+
+    /// Start a run of the named workflow with the given argument values
+    pub fn start_workflow<I, K, V>(&self, name: &str, variables: I) -> Result<WorkflowRun, WorkflowRunError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let workflow = self
+            .workflows
+            .get(name)
+            .ok_or_else(|| WorkflowRunError::NotFound(name.to_string()))?;
+        Ok(WorkflowRun::new(workflow)?.with_variables(variables))
+    }
+
+    /// Run every step of the named workflow, using argument defaults
+    pub async fn run_workflow(&self, name: &str, executor: &CommandExecutor) -> Result<WorkflowRun, WorkflowRunError> {
+        self.start_workflow(name, Vec::<(String, String)>::new())?
+            .run(executor)
+            .await
+    }
+
+    /// The steps the named workflow would run, without running them
+    pub fn dry_run(&self, name: &str) -> Result<Vec<StepPreview>, WorkflowRunError> {
+        Ok(self.start_workflow(name, Vec::<(String, String)>::new())?.preview())
     }
 
     pub fn list_workflows(&self) -> Vec<String> {
@@ -55,37 +60,34 @@ impl WorkflowManager {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_add_and_run_workflow() {
+    #[tokio::test]
+    async fn test_add_and_run_workflow() {
         let mut manager = WorkflowManager::new();
 
-        let workflow = Workflow {
-            name: "Build Project".to_string(),
-            steps: vec![WorkflowStep {
-                name: "Compile".to_string(),
-                command: "cargo build".to_string(),
-                description: Some("Compile the project".to_string()),
-                dependencies: vec![],
-                working_directory: None,
-            }],
-        };
+        let workflow = Workflow::new("Build Project", "").with_steps(vec![
+            WorkflowStep::new("Compile", "echo compiling").with_description("Compile the project"),
+        ]);
         manager.add_workflow(workflow);
 
-        assert!(manager.run_workflow("Build Project").is_ok());
+        let run = manager
+            .run_workflow("Build Project", &CommandExecutor::new())
+            .await
+            .unwrap();
+        assert!(run.succeeded());
+        assert_eq!(run.results().len(), 1);
+        assert_eq!(manager.dry_run("Build Project").unwrap()[0].command, "echo compiling");
+        assert_eq!(
+            manager.dry_run("Missing").unwrap_err(),
+            WorkflowRunError::NotFound("Missing".to_string())
+        );
     }
 
     #[test]
     fn test_list_workflows() {
         let mut manager = WorkflowManager::new();
 
-        manager.add_workflow(Workflow {
-            name: "Test Workflow 1".to_string(),
-            steps: vec![],
-        });
-        manager.add_workflow(Workflow {
-            name: "Test Workflow 2".to_string(),
-            steps: vec![],
-        });
+        manager.add_workflow(Workflow::new("Test Workflow 1", "echo 1"));
+        manager.add_workflow(Workflow::new("Test Workflow 2", "echo 2"));
 
         let workflows = manager.list_workflows();
         assert_eq!(workflows.len(), 2);
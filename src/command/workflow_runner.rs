@@ -0,0 +1,778 @@
+//! Runs multi-step workflows one step at a time.
+//!
+//! A [`WorkflowRun`] decides which step runs next, renders its command with
+//! the workflow's arguments and the outputs captured from earlier steps, and
+//! records each step's result. The caller executes the commands, which lets
+//! the terminal give every step its own block; [`WorkflowRun::run`] drives a
+//! whole run with a [`CommandExecutor`] instead.
+//!
+//! Values are quoted for the shell the steps run in, so an argument or a
+//! captured output such as `x; rm -rf ~` stays one word of data.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use thiserror::Error;
+use warp_workflows_types::{Workflow, WorkflowStep};
+
+use crate::executor::CommandExecutor;
+use crate::syntax_tree::ShellDialect;
+
+/// An exit code comparison, optionally naming the step
+static EXIT_CODE_CONDITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(exit_code|steps\.([\w-]+)\.exit_code)\s*(==|!=|<=|>=|<|>)\s*(-?\d+)$").unwrap()
+});
+
+/// A `{{name}}` placeholder. Other uses of double braces, such as Go
+/// templates in `docker inspect -f '{{.State.Status}}'`, are left alone.
+static VARIABLE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap());
+
+/// Errors raised while planning or running a workflow
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum WorkflowRunError {
+    #[error("Workflow '{0}' not found")]
+    NotFound(String),
+    #[error("Workflow '{0}' has no steps")]
+    NoSteps(String),
+    #[error("Step name '{0}' is used more than once")]
+    DuplicateStep(String),
+    #[error("Invalid condition on step '{step}': {message}")]
+    InvalidCondition { step: String, message: String },
+    #[error("Step '{step}' uses '{{{{{name}}}}}', which has no value")]
+    UnresolvedVariable { step: String, name: String },
+    #[error("No step is running")]
+    NoRunningStep,
+}
+
+/// How a step ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    /// Failed, but the step allows the workflow to continue
+    FailedAllowed,
+    /// Not run because its condition was false
+    Skipped,
+}
+
+/// Result of one step of a run
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    pub name: String,
+    /// The rendered command, `None` for skipped steps
+    pub command: Option<String>,
+    pub status: StepStatus,
+    pub exit_code: Option<i32>,
+    pub attempts: u32,
+}
+
+/// A step that is ready to execute
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedStep {
+    pub index: usize,
+    pub name: String,
+    pub command: String,
+    pub working_directory: Option<PathBuf>,
+    /// 1 for the first attempt, higher for retries
+    pub attempt: u32,
+}
+
+/// A step as a dry run shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepPreview {
+    pub name: String,
+    /// The command with known values filled in and later outputs shown as `<name>`
+    pub command: String,
+    pub condition: Option<String>,
+    pub output: Option<String>,
+    pub retries: u32,
+    pub continue_on_error: bool,
+}
+
+impl fmt::Display for StepPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.command)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " [if {}]", condition)?;
+        }
+        if let Some(output) = &self.output {
+            write!(f, " [-> {}]", output)?;
+        }
+        if self.retries > 0 {
+            write!(f, " [retries {}]", self.retries)?;
+        }
+        if self.continue_on_error {
+            write!(f, " [continue on error]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Condition deciding whether a step runs
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Success,
+    Failure,
+    Always,
+    /// Exit code of the previous step that ran, or of the named step
+    ExitCode {
+        step: Option<String>,
+        op: Comparison,
+        value: i32,
+    },
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(self, left: i32, right: i32) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    fn parse(text: &str) -> Result<Self, String> {
+        if let Some((left, right)) = text.split_once("||") {
+            return Ok(Condition::Or(Box::new(Self::parse(left)?), Box::new(Self::parse(right)?)));
+        }
+        if let Some((left, right)) = text.split_once("&&") {
+            return Ok(Condition::And(Box::new(Self::parse(left)?), Box::new(Self::parse(right)?)));
+        }
+
+        let text = text.trim();
+        match text.trim_end_matches("()") {
+            "success" => return Ok(Condition::Success),
+            "failure" => return Ok(Condition::Failure),
+            "always" => return Ok(Condition::Always),
+            _ => {}
+        }
+
+        let caps = EXIT_CODE_CONDITION
+            .captures(text)
+            .ok_or_else(|| format!("cannot understand '{}'", text))?;
+        let op = match &caps[3] {
+            "==" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<=" => Comparison::Le,
+            ">=" => Comparison::Ge,
+            "<" => Comparison::Lt,
+            _ => Comparison::Gt,
+        };
+        let value = caps[4]
+            .parse()
+            .map_err(|_| format!("'{}' is not an exit code", &caps[4]))?;
+        Ok(Condition::ExitCode {
+            step: caps.get(2).map(|name| name.as_str().to_string()),
+            op,
+            value,
+        })
+    }
+
+    fn referenced_steps<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Condition::ExitCode { step: Some(step), .. } => names.push(step),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.referenced_steps(names);
+                right.referenced_steps(names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The state of one run of a workflow
+#[derive(Debug, Clone)]
+pub struct WorkflowRun {
+    name: String,
+    steps: Vec<WorkflowStep>,
+    conditions: Vec<Option<Condition>>,
+    /// Argument values and captured step outputs
+    variables: BTreeMap<String, String>,
+    results: Vec<StepResult>,
+    /// Index of the next step to consider
+    next: usize,
+    running: Option<PlannedStep>,
+    /// A step failed without `continue_on_error`
+    failed: bool,
+    /// Directory relative step directories are resolved against
+    working_dir: Option<PathBuf>,
+    /// Shell the steps run in, for quoting values
+    dialect: ShellDialect,
+}
+
+impl WorkflowRun {
+    /// Prepare a run, checking step names and conditions up front
+    pub fn new(workflow: &Workflow) -> Result<Self, WorkflowRunError> {
+        let steps = workflow.resolved_steps();
+        if steps.iter().all(|step| step.command.trim().is_empty()) {
+            return Err(WorkflowRunError::NoSteps(workflow.name.clone()));
+        }
+
+        for (i, step) in steps.iter().enumerate() {
+            if steps[..i].iter().any(|earlier| earlier.name == step.name) {
+                return Err(WorkflowRunError::DuplicateStep(step.name.clone()));
+            }
+        }
+
+        let mut conditions = Vec::with_capacity(steps.len());
+        for (i, step) in steps.iter().enumerate() {
+            let condition = match &step.condition {
+                Some(text) => {
+                    let condition = Condition::parse(text).map_err(|message| {
+                        WorkflowRunError::InvalidCondition {
+                            step: step.name.clone(),
+                            message,
+                        }
+                    })?;
+                    let mut referenced = Vec::new();
+                    condition.referenced_steps(&mut referenced);
+                    if let Some(unknown) = referenced
+                        .into_iter()
+                        .find(|name| !steps[..i].iter().any(|earlier| earlier.name == *name))
+                    {
+                        return Err(WorkflowRunError::InvalidCondition {
+                            step: step.name.clone(),
+                            message: format!("'{}' is not an earlier step", unknown),
+                        });
+                    }
+                    Some(condition)
+                }
+                None => None,
+            };
+            conditions.push(condition);
+        }
+
+        let variables = workflow
+            .arguments
+            .iter()
+            .filter_map(|arg| Some((arg.name.clone(), arg.default_value.clone()?)))
+            .collect();
+
+        Ok(Self {
+            name: workflow.name.clone(),
+            steps,
+            conditions,
+            variables,
+            results: Vec::new(),
+            next: 0,
+            running: None,
+            failed: false,
+            working_dir: None,
+            dialect: ShellDialect::default(),
+        })
+    }
+
+    /// Set argument values, overriding their defaults
+    pub fn with_variables<I, K, V>(mut self, variables: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.variables
+            .extend(variables.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Resolve relative step directories against `dir`
+    pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Quote values for the shell the steps run in
+    pub fn with_dialect(mut self, dialect: ShellDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    pub fn results(&self) -> &[StepResult] {
+        &self.results
+    }
+
+    /// The step handed out by [`Self::next_step`] and not yet completed
+    pub fn running_step(&self) -> Option<&PlannedStep> {
+        self.running.as_ref()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.running.is_none() && self.next >= self.steps.len()
+    }
+
+    /// True when no step failed, ignoring steps allowed to fail
+    pub fn succeeded(&self) -> bool {
+        !self.failed
+    }
+
+    /// The next step to execute, skipping steps whose condition is false.
+    /// Returns the running step again if it has not completed yet.
+    pub fn next_step(&mut self) -> Result<Option<PlannedStep>, WorkflowRunError> {
+        if let Some(running) = &self.running {
+            return Ok(Some(running.clone()));
+        }
+
+        while self.next < self.steps.len() {
+            let index = self.next;
+            let step = &self.steps[index];
+            if !self.should_run(index) {
+                tracing::debug!(workflow = %self.name, step = %step.name, "Skipping workflow step");
+                self.results.push(StepResult {
+                    name: step.name.clone(),
+                    command: None,
+                    status: StepStatus::Skipped,
+                    exit_code: None,
+                    attempts: 0,
+                });
+                self.next += 1;
+                continue;
+            }
+
+            let planned = PlannedStep {
+                index,
+                name: step.name.clone(),
+                command: self.render(step)?,
+                working_directory: step.working_directory.as_ref().map(|dir| match &self.working_dir {
+                    Some(base) => base.join(dir),
+                    None => PathBuf::from(dir),
+                }),
+                attempt: 1,
+            };
+            self.running = Some(planned.clone());
+            return Ok(Some(planned));
+        }
+
+        Ok(None)
+    }
+
+    /// Record the result of the running step. A failing step with retries
+    /// left stays running with its attempt count raised.
+    pub fn complete_step(&mut self, exit_code: i32, stdout: &str) -> Result<(), WorkflowRunError> {
+        let mut running = self.running.take().ok_or(WorkflowRunError::NoRunningStep)?;
+        let step = &self.steps[running.index];
+
+        if exit_code != 0 && running.attempt <= step.retries {
+            tracing::info!(workflow = %self.name, step = %step.name, attempt = running.attempt, "Retrying workflow step");
+            running.attempt += 1;
+            self.running = Some(running);
+            return Ok(());
+        }
+
+        let status = match (exit_code, step.continue_on_error) {
+            (0, _) => StepStatus::Succeeded,
+            (_, true) => StepStatus::FailedAllowed,
+            (_, false) => StepStatus::Failed,
+        };
+        if status == StepStatus::Failed {
+            self.failed = true;
+        }
+        if let Some(variable) = &step.output {
+            self.variables
+                .insert(variable.clone(), stdout.trim().to_string());
+        }
+
+        self.results.push(StepResult {
+            name: step.name.clone(),
+            command: Some(running.command),
+            status,
+            exit_code: Some(exit_code),
+            attempts: running.attempt,
+        });
+        self.next = running.index + 1;
+        Ok(())
+    }
+
+    /// What the run would execute, without running anything
+    pub fn preview(&self) -> Vec<StepPreview> {
+        let mut known = self.variables.clone();
+        self.steps
+            .iter()
+            .map(|step| {
+                let command = render_template(&step.command, &known, self.dialect, |name| format!("<{}>", name));
+                // Shown as `<name>` until the step has run
+                if let Some(output) = &step.output {
+                    known.remove(output);
+                }
+                StepPreview {
+                    name: step.name.clone(),
+                    command,
+                    condition: step.condition.clone(),
+                    output: step.output.clone(),
+                    retries: step.retries,
+                    continue_on_error: step.continue_on_error,
+                }
+            })
+            .collect()
+    }
+
+    /// Execute every remaining step with `executor`
+    pub async fn run(mut self, executor: &CommandExecutor) -> Result<Self, WorkflowRunError> {
+        self.dialect = executor.dialect();
+        while let Some(step) = self.next_step()? {
+            let mut step_executor = executor.clone();
+            if let Some(dir) = step.working_directory {
+                step_executor.set_working_dir(dir);
+            }
+            let result = step_executor.execute_command(&step.command).await;
+            self.complete_step(result.exit_code, &result.stdout)?;
+        }
+        Ok(self)
+    }
+
+    fn should_run(&self, index: usize) -> bool {
+        match &self.conditions[index] {
+            Some(condition) => self.evaluate(condition),
+            None => !self.failed,
+        }
+    }
+
+    fn evaluate(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Success => !self.failed,
+            Condition::Failure => self.failed,
+            Condition::Always => true,
+            Condition::ExitCode { step, op, value } => {
+                let exit_code = match step {
+                    Some(name) => self
+                        .results
+                        .iter()
+                        .find(|result| &result.name == name)
+                        .and_then(|result| result.exit_code),
+                    None => self
+                        .results
+                        .iter()
+                        .rev()
+                        .find_map(|result| result.exit_code),
+                };
+                exit_code.is_some_and(|code| op.holds(code, *value))
+            }
+            Condition::And(left, right) => self.evaluate(left) && self.evaluate(right),
+            Condition::Or(left, right) => self.evaluate(left) || self.evaluate(right),
+        }
+    }
+
+    fn render(&self, step: &WorkflowStep) -> Result<String, WorkflowRunError> {
+        let mut unresolved = Vec::new();
+        let command = render_template(&step.command, &self.variables, self.dialect, |name| {
+            unresolved.push(name.to_string());
+            String::new()
+        });
+        match unresolved.into_iter().next() {
+            Some(name) => Err(WorkflowRunError::UnresolvedVariable {
+                step: step.name.clone(),
+                name,
+            }),
+            None => Ok(command),
+        }
+    }
+}
+
+/// Replace every `{{name}}` in one pass, so a value that itself contains
+/// `{{other}}` is not expanded again. Values are quoted for `dialect` as
+/// the quotes around the placeholder require; `missing` gives the text for
+/// names without a value.
+fn render_template(
+    template: &str,
+    variables: &BTreeMap<String, String>,
+    dialect: ShellDialect,
+    mut missing: impl FnMut(&str) -> String,
+) -> String {
+    VARIABLE
+        .replace_all(template, |caps: &Captures| match variables.get(&caps[1]) {
+            Some(value) => {
+                let start = caps.get(0).map_or(0, |m| m.start());
+                quote_value(value, quoting_at(&template[..start], dialect), dialect)
+            }
+            None => missing(&caps[1]),
+        })
+        .into_owned()
+}
+
+/// The quotes a placeholder is inside of
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quoting {
+    Bare,
+    Single,
+    Double,
+}
+
+/// The quotes open at the end of `prefix`
+fn quoting_at(prefix: &str, dialect: ShellDialect) -> Quoting {
+    let mut quoting = Quoting::Bare;
+    let mut chars = prefix.chars();
+    while let Some(c) = chars.next() {
+        quoting = match (quoting, c) {
+            (Quoting::Bare | Quoting::Double, '\\') => {
+                chars.next();
+                quoting
+            }
+            // Fish also escapes `\'` and `\\` inside single quotes
+            (Quoting::Single, '\\') if dialect == ShellDialect::Fish => {
+                chars.next();
+                quoting
+            }
+            (Quoting::Bare, '\'') => Quoting::Single,
+            (Quoting::Bare, '"') => Quoting::Double,
+            (Quoting::Single, '\'') | (Quoting::Double, '"') => Quoting::Bare,
+            _ => quoting,
+        };
+    }
+    quoting
+}
+
+/// `value` as literal text at a place quoted by `quoting`
+fn quote_value(value: &str, quoting: Quoting, dialect: ShellDialect) -> String {
+    let fish = dialect == ShellDialect::Fish;
+    match quoting {
+        Quoting::Bare => dialect.quote(value),
+        Quoting::Single if fish => value.replace('\\', r"\\").replace('\'', r"\'"),
+        Quoting::Single => value.replace('\'', r"'\''"),
+        Quoting::Double => {
+            let special: &[char] = if fish { &['\\', '"', '$'] } else { &['\\', '"', '$', '`'] };
+            let mut escaped = String::with_capacity(value.len());
+            for c in value.chars() {
+                if special.contains(&c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            escaped
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp_workflows_types::Argument;
+
+    fn release_workflow() -> Workflow {
+        Workflow::new("Release", "")
+            .with_arguments(vec![Argument::new("version").with_default_value("1.0")])
+            .with_steps(vec![
+                WorkflowStep::new("test", "cargo test").with_retries(1),
+                WorkflowStep::new("sha", "git rev-parse HEAD").with_output("sha"),
+                WorkflowStep::new("tag", "git tag v{{version}} {{sha}}"),
+                WorkflowStep::new("cleanup", "git tag -d v{{version}}").with_condition("failure"),
+            ])
+    }
+
+    /// Complete every step with the exit code `outcome` gives for its command
+    fn drive(mut run: WorkflowRun, outcome: impl Fn(&PlannedStep) -> (i32, &'static str)) -> (WorkflowRun, Vec<String>) {
+        let mut executed = Vec::new();
+        while let Some(step) = run.next_step().unwrap() {
+            let (code, stdout) = outcome(&step);
+            executed.push(step.command.clone());
+            run.complete_step(code, stdout).unwrap();
+        }
+        (run, executed)
+    }
+
+    #[test]
+    fn test_steps_run_in_order_with_captured_output() {
+        let run = WorkflowRun::new(&release_workflow()).unwrap();
+        let (run, executed) = drive(run, |step| match step.name.as_str() {
+            "sha" => (0, "abc123\n"),
+            _ => (0, ""),
+        });
+
+        assert_eq!(executed, vec!["cargo test", "git rev-parse HEAD", "git tag v1.0 abc123"]);
+        assert!(run.is_finished());
+        assert!(run.succeeded());
+        assert_eq!(run.results()[3].status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn test_failure_skips_to_failure_steps() {
+        let run = WorkflowRun::new(&release_workflow())
+            .unwrap()
+            .with_variables([("version", "2.0")]);
+        let (run, executed) = drive(run, |step| match step.name.as_str() {
+            "test" => (101, ""),
+            _ => (0, ""),
+        });
+
+        // The failing step is retried once, then only the cleanup runs
+        assert_eq!(executed, vec!["cargo test", "cargo test", "git tag -d v2.0"]);
+        assert!(!run.succeeded());
+        assert_eq!(run.results()[0].attempts, 2);
+        assert_eq!(run.results()[0].status, StepStatus::Failed);
+        assert_eq!(run.results()[1].status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn test_continue_on_error_and_exit_code_conditions() {
+        let workflow = Workflow::new("Commit if dirty", "").with_steps(vec![
+            WorkflowStep::new("probe", "git diff --quiet").with_continue_on_error(true),
+            WorkflowStep::new("commit", "git commit -am wip").with_condition("steps.probe.exit_code != 0"),
+            WorkflowStep::new("done", "echo done").with_condition("exit_code == 0 && success"),
+        ]);
+        let (run, executed) = drive(WorkflowRun::new(&workflow).unwrap(), |step| match step.name.as_str() {
+            "probe" => (1, ""),
+            _ => (0, ""),
+        });
+
+        assert_eq!(executed, vec!["git diff --quiet", "git commit -am wip", "echo done"]);
+        assert_eq!(run.results()[0].status, StepStatus::FailedAllowed);
+        assert!(run.succeeded());
+    }
+
+    #[test]
+    fn test_invalid_workflows_are_rejected() {
+        let duplicate = Workflow::new("Dup", "")
+            .with_steps(vec![WorkflowStep::new("a", "true"), WorkflowStep::new("a", "false")]);
+        assert_eq!(
+            WorkflowRun::new(&duplicate).unwrap_err(),
+            WorkflowRunError::DuplicateStep("a".to_string())
+        );
+
+        let bad_condition = Workflow::new("Bad", "")
+            .with_steps(vec![WorkflowStep::new("a", "true").with_condition("sometimes")]);
+        assert!(matches!(
+            WorkflowRun::new(&bad_condition),
+            Err(WorkflowRunError::InvalidCondition { .. })
+        ));
+
+        let later_step = Workflow::new("Later", "").with_steps(vec![
+            WorkflowStep::new("a", "true").with_condition("steps.b.exit_code == 0"),
+            WorkflowStep::new("b", "true"),
+        ]);
+        assert!(WorkflowRun::new(&later_step).is_err());
+
+        let mut missing = WorkflowRun::new(&Workflow::new("Echo", "echo {{name}}")).unwrap();
+        assert!(matches!(
+            missing.next_step(),
+            Err(WorkflowRunError::UnresolvedVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dry_run_preview() {
+        let run = WorkflowRun::new(&release_workflow()).unwrap();
+        let preview = run.preview();
+
+        assert_eq!(preview.len(), 4);
+        assert_eq!(preview[2].command, "git tag v1.0 <sha>");
+        assert_eq!(preview[0].to_string(), "test: cargo test [retries 1]");
+        assert_eq!(preview[3].to_string(), "cleanup: git tag -d v1.0 [if failure]");
+        assert!(run.results().is_empty());
+    }
+
+    #[test]
+    fn test_single_command_workflow_is_one_step() {
+        let workflow = Workflow::new("List", "ls {{dir}}")
+            .with_arguments(vec![Argument::new("dir")]);
+        let mut run = WorkflowRun::new(&workflow).unwrap().with_variables([("dir", "/tmp")]);
+
+        let step = run.next_step().unwrap().unwrap();
+        assert_eq!(step.name, "List");
+        assert_eq!(step.command, "ls /tmp");
+        run.complete_step(0, "").unwrap();
+        assert!(run.is_finished());
+        assert_eq!(run.complete_step(0, ""), Err(WorkflowRunError::NoRunningStep));
+    }
+
+    #[test]
+    fn test_templates_render_in_one_pass_and_ignore_go_templates() {
+        let workflow = Workflow::new("Inspect", "").with_steps(vec![
+            WorkflowStep::new("status", "docker inspect -f '{{.State.Status}}' {{ container }}"),
+            WorkflowStep::new("names", "kubectl get pods -o go-template='{{.metadata.name}}' -l {{label}}"),
+        ]);
+        let run = WorkflowRun::new(&workflow)
+            .unwrap()
+            .with_variables([("container", "web"), ("label", "{{container}}")]);
+        let (_, executed) = drive(run, |_| (0, ""));
+
+        assert_eq!(
+            executed,
+            vec![
+                "docker inspect -f '{{.State.Status}}' web",
+                "kubectl get pods -o go-template='{{.metadata.name}}' -l '{{container}}'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_are_quoted_for_the_shell() {
+        let workflow = Workflow::new("Notify", "").with_steps(vec![
+            WorkflowStep::new("branch", "git branch --show-current").with_output("branch"),
+            WorkflowStep::new("bare", "echo {{branch}}"),
+            WorkflowStep::new("single", "echo 'on {{branch}}'"),
+            WorkflowStep::new("double", "echo \"on {{branch}}\" \\\"{{branch}}"),
+        ]);
+        let captured = "x; rm -rf ~ $(touch pwned) `id` it's \\ \"q\"\n";
+        let (_, bash) = drive(WorkflowRun::new(&workflow).unwrap(), |_| (0, captured));
+        assert_eq!(
+            bash[1..],
+            [
+                r#"echo 'x; rm -rf ~ $(touch pwned) `id` it'\''s \ "q"'"#,
+                r#"echo 'on x; rm -rf ~ $(touch pwned) `id` it'\''s \ "q"'"#,
+                r#"echo "on x; rm -rf ~ \$(touch pwned) \`id\` it's \\ \"q\"" \"'x; rm -rf ~ $(touch pwned) `id` it'\''s \ "q"'"#,
+            ]
+        );
+
+        let run = WorkflowRun::new(&workflow).unwrap().with_dialect(ShellDialect::Fish);
+        let (_, fish) = drive(run, |_| (0, captured));
+        assert_eq!(fish[1], r#"echo 'x; rm -rf ~ $(touch pwned) `id` it\'s \\ "q"'"#);
+        assert_eq!(fish[2], r#"echo 'on x; rm -rf ~ $(touch pwned) `id` it\'s \\ "q"'"#);
+    }
+
+    #[tokio::test]
+    async fn test_captured_output_stays_data() {
+        let workflow = Workflow::new("Echo", "").with_steps(vec![
+            WorkflowStep::new("first", "printf '%s' 'a; echo injected $(echo too)'").with_output("captured"),
+            WorkflowStep::new("second", "printf '%s\\n' {{captured}}").with_output("message"),
+        ]);
+        let run = WorkflowRun::new(&workflow)
+            .unwrap()
+            .run(&CommandExecutor::new())
+            .await
+            .unwrap();
+
+        assert_eq!(run.variables()["message"], "a; echo injected $(echo too)");
+    }
+
+    #[tokio::test]
+    async fn test_run_with_executor() {
+        let workflow = Workflow::new("Echo", "").with_steps(vec![
+            WorkflowStep::new("first", "echo hello").with_output("greeting"),
+            WorkflowStep::new("second", "echo {{greeting}} world").with_output("message"),
+        ]);
+        let run = WorkflowRun::new(&workflow)
+            .unwrap()
+            .run(&CommandExecutor::new())
+            .await
+            .unwrap();
+
+        assert!(run.succeeded());
+        assert_eq!(run.variables()["message"], "hello world");
+    }
+}
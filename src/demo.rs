@@ -1,5 +1,6 @@
+use warp_terminal::executor::CommandCorrector;
 use warp_terminal::command::{
-    CommandCorrections, CommandHistory, CommandSearch, SynchronizedInputs, 
+    CommandHistory, CommandSearch, SynchronizedInputs, 
     YAMLWorkflow, WorkflowManager, Workflow, Notebook,
 };
 use warp_terminal::text_input::TextInputHandler;
@@ -11,7 +12,7 @@ fn main() {
 
     // Initialize all components
     let mut history = CommandHistory::new();
-    let mut corrections = CommandCorrections::new();
+    let corrections = CommandCorrector::new();
     let mut sync_inputs = SynchronizedInputs::new();
     let mut text_handler = TextInputHandler::new();
     let mut search = CommandSearch::new();
@@ -39,10 +40,10 @@ fn main() {
     println!("-".repeat(30));
     
     // Test command corrections
-    let typo_corrections = corrections.suggest_corrections("gti status");
+    let typo_corrections = corrections.suggest_correction("gti status", "gti: command not found", 127);
     println!("Corrections for 'gti status':");
     for correction in typo_corrections {
-        println!("  - {} (confidence: {:.2})", correction.suggestion, correction.confidence);
+        println!("  - {} (confidence: {:.2})", correction.corrected_command, correction.confidence);
     }
 
     println!("\n3. YAML Workflow Demo");
@@ -109,34 +110,25 @@ fn main() {
     println!("-".repeat(30));
     
     // Create and add workflow to manager
-    let manager_workflow = warp_terminal::command::workflow_manager::Workflow {
-        name: "Docker Setup".to_string(),
-        steps: vec![
-            warp_terminal::command::workflow_manager::WorkflowStep {
-                name: "Build image".to_string(),
-                command: "docker build -t myapp .".to_string(),
-                description: Some("Build Docker image".to_string()),
-                dependencies: vec![],
-                working_directory: None,
-            },
-            warp_terminal::command::workflow_manager::WorkflowStep {
-                name: "Run container".to_string(),
-                command: "docker run -p 8080:8080 myapp".to_string(),
-                description: Some("Start the container".to_string()),
-                dependencies: vec!["Build image".to_string()],
-                working_directory: None,
-            },
-        ],
-    };
+    let manager_workflow = Workflow::new("Docker Setup", "").with_steps(vec![
+        warp_terminal::command::workflow_manager::WorkflowStep::new("Build image", "docker build -t myapp .")
+            .with_description("Build Docker image"),
+        warp_terminal::command::workflow_manager::WorkflowStep::new("Run container", "docker run -p 8080:8080 myapp")
+            .with_description("Start the container"),
+    ]);
     
     workflow_manager.add_workflow(manager_workflow);
     
     let workflows = workflow_manager.list_workflows();
     println!("Available workflows: {:?}", workflows);
     
-    match workflow_manager.run_workflow("Docker Setup") {
-        Ok(_) => println!("Workflow manager executed successfully!"),
-        Err(e) => println!("Workflow manager execution failed: {}", e),
+    match workflow_manager.dry_run("Docker Setup") {
+        Ok(steps) => {
+            for step in steps {
+                println!("  {}", step);
+            }
+        }
+        Err(e) => println!("Workflow manager dry run failed: {}", e),
     }
 
     println!("\nDemo completed! All features are working including:");
@@ -0,0 +1,260 @@
+//! Fish-style inline autosuggestions for the input editor.
+//!
+//! The [`AutosuggestionEngine`] predicts the rest of the line from command
+//! history (most recent first) and falls back to completion specs and the
+//! completion engine when no history entry extends the current input. The predicted suffix is rendered as
+//! dimmed ghost text after the cursor and can be accepted whole or word by word.
+
+use super::completion_engine::{CompletionEngine, CompletionKind};
+use crate::command_signatures_v2::SpecCompletionEngine;
+
+/// A predicted completion of the current input line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autosuggestion {
+    /// The input the suggestion was computed for.
+    pub input: String,
+    /// Text to append after `input`; never empty.
+    pub suffix: String,
+    /// Where the suggestion came from.
+    pub source: AutosuggestionSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosuggestionSource {
+    History,
+    Completion,
+}
+
+impl Autosuggestion {
+    /// The complete line that accepting the suggestion would produce.
+    pub fn full_text(&self) -> String {
+        format!("{}{}", self.input, self.suffix)
+    }
+
+    /// The part of the suffix up to the end of the next word, including any
+    /// leading whitespace. This is what Alt-Right accepts.
+    pub fn next_word(&self) -> &str {
+        let trimmed = self.suffix.trim_start();
+        let leading = self.suffix.len() - trimmed.len();
+        let word_len = trimmed
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .map(|i| {
+                // Keep a trailing path separator with the word it terminates
+                if trimmed[i..].starts_with('/') { i + 1 } else { i }
+            })
+            .unwrap_or(trimmed.len());
+        &self.suffix[..leading + word_len]
+    }
+
+    /// Re-anchor the suggestion after the input changed. Returns `None` when
+    /// the new input no longer agrees with the predicted line.
+    pub fn rebase(&self, new_input: &str) -> Option<Autosuggestion> {
+        let full = self.full_text();
+        if new_input.len() < full.len() && full.starts_with(new_input) && !new_input.is_empty() {
+            Some(Autosuggestion {
+                input: new_input.to_string(),
+                suffix: full[new_input.len()..].to_string(),
+                source: self.source,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Computes autosuggestions from a snapshot of history and completion data.
+///
+/// The engine owns its data so that it can be moved onto a blocking task;
+/// see [`AutosuggestionEngine::suggest_async`].
+#[derive(Debug, Clone)]
+pub struct AutosuggestionEngine {
+    /// History commands, most recent first.
+    history: Vec<String>,
+    spec_engine: Option<SpecCompletionEngine>,
+    completion_engine: Option<CompletionEngine>,
+}
+
+impl AutosuggestionEngine {
+    pub fn new(history: Vec<String>) -> Self {
+        Self {
+            history,
+            spec_engine: None,
+            completion_engine: None,
+        }
+    }
+
+    pub fn with_spec_engine(mut self, engine: SpecCompletionEngine) -> Self {
+        self.spec_engine = Some(engine);
+        self
+    }
+
+    pub fn with_completion_engine(mut self, engine: CompletionEngine) -> Self {
+        self.completion_engine = Some(engine);
+        self
+    }
+
+    /// Predict the rest of `input`. History wins over completions.
+    pub fn suggest(&mut self, input: &str) -> Option<Autosuggestion> {
+        if input.trim().is_empty() {
+            return None;
+        }
+
+        self.suggest_from_history(input)
+            .or_else(|| self.suggest_from_specs(input))
+            .or_else(|| self.suggest_from_completions(input))
+    }
+
+    /// Run [`suggest`](Self::suggest) on the blocking thread pool so that file
+    /// system lookups made by the completion engine never stall typing.
+    pub async fn suggest_async(mut self, input: String) -> Option<Autosuggestion> {
+        tokio::task::spawn_blocking(move || self.suggest(&input))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn suggest_from_history(&self, input: &str) -> Option<Autosuggestion> {
+        self.history
+            .iter()
+            .find(|command| command.len() > input.len() && command.starts_with(input))
+            .map(|command| Autosuggestion {
+                input: input.to_string(),
+                suffix: command[input.len()..].to_string(),
+                source: AutosuggestionSource::History,
+            })
+    }
+
+    fn suggest_from_specs(&self, input: &str) -> Option<Autosuggestion> {
+        let engine = self.spec_engine.as_ref()?;
+        if input.ends_with(char::is_whitespace) {
+            return None;
+        }
+
+        let completions = engine.complete(input, input.len());
+        completions.items.into_iter().find_map(|item| {
+            let candidate = format!("{}{}", &input[..completions.replace_start], item.text);
+            (candidate.len() > input.len() && candidate.starts_with(input)).then(|| {
+                Autosuggestion {
+                    input: input.to_string(),
+                    suffix: candidate[input.len()..].to_string(),
+                    source: AutosuggestionSource::Completion,
+                }
+            })
+        })
+    }
+
+    fn suggest_from_completions(&mut self, input: &str) -> Option<Autosuggestion> {
+        let engine = self.completion_engine.as_mut()?;
+
+        // Only complete the word under the cursor; a trailing space means the
+        // user has not started the next word yet.
+        if input.ends_with(char::is_whitespace) {
+            return None;
+        }
+        let word_start = input
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let current_word = &input[word_start..];
+
+        let mut candidates = engine.get_command_completions(input);
+        candidates.extend(engine.get_file_completions(current_word));
+        candidates.extend(engine.get_native_completions(input, input.len()));
+        candidates.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let file_prefix = match current_word.rfind('/') {
+            Some(i) => &current_word[..=i],
+            None => "",
+        };
+
+        candidates.into_iter().find_map(|item| {
+            let insert = item.insert_text.unwrap_or(item.text);
+            let word = match item.kind {
+                CompletionKind::File | CompletionKind::Directory => {
+                    format!("{}{}", file_prefix, insert)
+                }
+                _ => insert,
+            };
+            let candidate = format!("{}{}", &input[..word_start], word);
+            (candidate.len() > input.len() && candidate.starts_with(input)).then(|| {
+                Autosuggestion {
+                    input: input.to_string(),
+                    suffix: candidate[input.len()..].to_string(),
+                    source: AutosuggestionSource::Completion,
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> AutosuggestionEngine {
+        AutosuggestionEngine::new(vec![
+            "git commit -m 'fix build'".to_string(),
+            "git checkout main".to_string(),
+            "ls -la".to_string(),
+        ])
+    }
+
+    #[test]
+    fn test_history_suggestion_prefers_most_recent() {
+        let suggestion = engine().suggest("git c").unwrap();
+        assert_eq!(suggestion.suffix, "ommit -m 'fix build'");
+        assert_eq!(suggestion.source, AutosuggestionSource::History);
+        assert_eq!(suggestion.full_text(), "git commit -m 'fix build'");
+    }
+
+    #[test]
+    fn test_no_suggestion_for_empty_or_exact_input() {
+        assert!(engine().suggest("").is_none());
+        assert!(engine().suggest("ls -la").is_none());
+    }
+
+    #[test]
+    fn test_completion_fallback() {
+        let mut engine =
+            AutosuggestionEngine::new(Vec::new()).with_completion_engine(CompletionEngine::new());
+        let suggestion = engine.suggest("git stat").unwrap();
+        assert_eq!(suggestion.full_text(), "git status");
+        assert_eq!(suggestion.source, AutosuggestionSource::Completion);
+    }
+
+    #[test]
+    fn test_spec_fallback() {
+        let mut engine =
+            AutosuggestionEngine::new(Vec::new()).with_spec_engine(SpecCompletionEngine::default());
+        let suggestion = engine.suggest("docker ru").unwrap();
+        assert_eq!(suggestion.full_text(), "docker run");
+    }
+
+    #[test]
+    fn test_next_word() {
+        let suggestion = engine().suggest("git c").unwrap();
+        assert_eq!(suggestion.next_word(), "ommit");
+
+        let suggestion = engine().suggest("git commit").unwrap();
+        assert_eq!(suggestion.next_word(), " -m");
+    }
+
+    #[test]
+    fn test_rebase_keeps_matching_suggestion() {
+        let suggestion = engine().suggest("git ch").unwrap();
+        let rebased = suggestion.rebase("git che").unwrap();
+        assert_eq!(rebased.suffix, "ckout main");
+        assert!(suggestion.rebase("git cx").is_none());
+        assert!(suggestion.rebase("git checkout main").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_suggest_async() {
+        let suggestion = engine().suggest_async("ls".to_string()).await.unwrap();
+        assert_eq!(suggestion.suffix, " -la");
+    }
+}
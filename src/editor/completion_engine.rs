@@ -1,13 +1,17 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs;
 
+use super::native_completion::NativeCompletionBridge;
+
 #[derive(Debug, Clone)]
 pub struct CompletionEngine {
     commands: HashMap<String, Vec<String>>,
     file_cache: HashMap<String, Vec<String>>,
     cache_ttl: std::time::Duration,
     last_cache_update: std::time::Instant,
+    native: Option<NativeCompletionBridge>,
+    cwd: PathBuf,
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +22,9 @@ pub struct CompletionItem {
     pub documentation: Option<String>,
     pub insert_text: Option<String>,
     pub score: f32,
+    /// Byte offset in the line where the text this item replaces starts;
+    /// `None` replaces the word before the cursor
+    pub replace_start: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,13 +53,50 @@ impl CompletionEngine {
             file_cache: HashMap::new(),
             cache_ttl: std::time::Duration::from_secs(30),
             last_cache_update: std::time::Instant::now(),
+            native: None,
+            cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
         };
         
         engine.load_default_commands();
         engine
     }
 
+    /// Also ask the user's shell for completions of command arguments.
+    pub fn with_native_completions(mut self, bridge: NativeCompletionBridge) -> Self {
+        self.native = Some(bridge);
+        self
+    }
+
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = cwd.into();
+        self
+    }
+
+    pub fn has_native_completions(&self) -> bool {
+        self.native.is_some()
+    }
+
     pub fn get_completions(&mut self, partial: &str) -> Vec<CompletionItem> {
+        let completions = self.word_completions(partial);
+        Self::best(completions)
+    }
+
+    /// Completions at byte offset `cursor` of `line`. The built-in sources
+    /// complete `partial`, the word before the cursor, while the user's shell
+    /// sees the whole line, so it can complete the arguments of any command
+    /// it has completions for. An empty `partial` leaves out the built-in
+    /// sources.
+    pub fn get_line_completions(&mut self, line: &str, cursor: usize, partial: &str) -> Vec<CompletionItem> {
+        let mut completions = if partial.is_empty() {
+            Vec::new()
+        } else {
+            self.word_completions(partial)
+        };
+        completions.extend(self.get_native_completions(line, cursor));
+        Self::best(completions)
+    }
+
+    fn word_completions(&mut self, partial: &str) -> Vec<CompletionItem> {
         let mut completions = Vec::new();
         
         // Update cache if needed
@@ -67,12 +111,13 @@ impl CompletionEngine {
         // Variable completions
         completions.extend(self.get_variable_completions(partial));
         
-        // Sort by score (descending)
+        completions
+    }
+
+    /// The top 20 completions by score
+    fn best(mut completions: Vec<CompletionItem>) -> Vec<CompletionItem> {
         completions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        
-        // Limit to top 20 results
         completions.truncate(20);
-        
         completions
     }
 
@@ -91,6 +136,7 @@ impl CompletionEngine {
                         documentation: self.get_command_description(command),
                         insert_text: Some(command.clone()),
                         score,
+                        replace_start: None,
                     });
                 }
             }
@@ -111,6 +157,7 @@ impl CompletionEngine {
                                 documentation: self.get_subcommand_description(base_command, subcommand),
                                 insert_text: Some(subcommand.clone()),
                                 score,
+                                replace_start: None,
                             });
                         }
                     }
@@ -121,6 +168,43 @@ impl CompletionEngine {
         completions
     }
 
+    /// Completions for the argument at byte offset `cursor` of `line`, as
+    /// reported by the user's shell. Each item replaces the shell's word
+    /// before the cursor.
+    pub fn get_native_completions(&self, line: &str, cursor: usize) -> Vec<CompletionItem> {
+        let Some(bridge) = &self.native else {
+            return Vec::new();
+        };
+
+        let completions = match bridge.complete(line, cursor, &self.cwd) {
+            Ok(completions) => completions,
+            Err(e) => {
+                tracing::debug!("Native completion failed: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let current_word = line.get(completions.replace_start..cursor).unwrap_or_default();
+        let detail = format!("{} completion", bridge.shell().program());
+        completions
+            .items
+            .into_iter()
+            .map(|candidate| CompletionItem {
+                kind: if candidate.text.starts_with('-') {
+                    CompletionKind::Option
+                } else {
+                    CompletionKind::Keyword
+                },
+                detail: Some(detail.clone()),
+                documentation: candidate.description,
+                insert_text: Some(candidate.text.clone()),
+                score: self.calculate_prefix_score(current_word, &candidate.text),
+                replace_start: Some(completions.replace_start),
+                text: candidate.text,
+            })
+            .collect()
+    }
+
     pub fn get_file_completions(&mut self, partial: &str) -> Vec<CompletionItem> {
         let mut completions = Vec::new();
         
@@ -162,6 +246,7 @@ impl CompletionEngine {
                                 entry
                             }),
                             score,
+                            replace_start: None,
                         });
                     }
                 }
@@ -194,6 +279,7 @@ impl CompletionEngine {
                         documentation: std::env::var(var).ok(),
                         insert_text: Some(format!("${}", var)),
                         score,
+                        replace_start: None,
                     });
                 }
             }
@@ -210,6 +296,7 @@ impl CompletionEngine {
                         documentation: Some(value),
                         insert_text: Some(format!("${}", key)),
                         score,
+                        replace_start: None,
                     });
                 }
             }
@@ -449,6 +536,32 @@ mod tests {
         assert!(completions.iter().any(|c| c.text == "status"));
     }
 
+    #[test]
+    fn test_line_completions_ask_the_shell_about_the_whole_line() {
+        if which::which("bash").is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let rc_path = dir.path().join("rc.bash");
+        std::fs::write(&rc_path, "complete -W '--alpha --beta' warp-test-cli").unwrap();
+        let bridge = NativeCompletionBridge::new(super::super::native_completion::NativeShell::Bash)
+            .with_init_files(vec![rc_path])
+            .with_timeout(std::time::Duration::from_secs(10));
+        let mut engine = CompletionEngine::new().with_native_completions(bridge).with_cwd(dir.path());
+
+        // The word-based sources only see "al"; the shell sees the command
+        let line = "warp-test-cli --al";
+        let completions = engine.get_line_completions(line, line.len(), "al");
+        let alpha = completions.iter().find(|c| c.text == "--alpha").expect("shell completion");
+        assert_eq!(alpha.replace_start, Some(14));
+        assert!(!completions.iter().any(|c| c.text == "--beta"));
+
+        let completions = engine.get_line_completions("warp-test-cli ", 14, "");
+        let texts: Vec<_> = completions.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts.len(), 2);
+        assert!(texts.contains(&"--alpha") && texts.contains(&"--beta"));
+    }
+
     #[test]
     fn test_variable_completions() {
         let engine = CompletionEngine::new();
@@ -1,10 +1,15 @@
 
 use std::collections::VecDeque;
+use std::ops::Range;
 use std::time::{SystemTime, Duration};
 
+use crate::editor::multi_cursor::{merge_selections, MultiCursorManager, Selection};
+use crate::editor::rope::Rope;
+use crate::string_offset;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EditorState {
-    pub content: String,
+    pub content: Rope,
     pub cursor_position: usize,
     pub selection_start: Option<usize>,
     pub selection_end: Option<usize>,
@@ -12,6 +17,8 @@ pub struct EditorState {
     pub is_multiline: bool,
     pub line_numbers_visible: bool,
     pub syntax_highlighting_enabled: bool,
+    /// Cursors besides the primary one above
+    pub multi_cursor: MultiCursorManager,
     
     // History for undo/redo
     history: VecDeque<EditorSnapshot>,
@@ -23,14 +30,24 @@ pub struct EditorState {
     pub total_lines: usize,
     pub current_line: usize,
     pub current_column: usize,
+
+    /// Changes to the text not yet taken by [`Self::take_edits`], each a
+    /// replaced range and the length of its replacement
+    edits: Vec<(Range<usize>, usize)>,
+    /// Bumped by every change to the text
+    version: u64,
 }
 
+/// Edits kept before they are merged into one spanning them all
+const MAX_PENDING_EDITS: usize = 64;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EditorSnapshot {
-    pub content: String,
+    pub content: Rope,
     pub cursor_position: usize,
     pub selection_start: Option<usize>,
     pub selection_end: Option<usize>,
+    pub cursors: Vec<Selection>,
     pub timestamp: SystemTime,
 }
 
@@ -69,6 +86,9 @@ pub enum EditorAction {
     // Multi-cursor operations
     AddCursor(usize),
     RemoveCursor(usize),
+    AddCursorAbove,
+    AddCursorBelow,
+    SelectAllOccurrences,
     
     // Folding
     FoldRegion(usize, usize),
@@ -92,6 +112,9 @@ pub enum EditorAction {
     // Command operations
     ExecuteCommand(String),
     ValidateCommand(String),
+    /// Open the buffer in `$VISUAL`/`$EDITOR`; see
+    /// [`readline::edit_in_external_editor`](crate::editor::readline::edit_in_external_editor)
+    EditInExternalEditor,
 }
 
 impl Default for EditorState {
@@ -103,7 +126,7 @@ impl Default for EditorState {
 impl EditorState {
     pub fn new() -> Self {
         Self {
-            content: String::new(),
+            content: Rope::new(),
             cursor_position: 0,
             selection_start: None,
             selection_end: None,
@@ -111,6 +134,7 @@ impl EditorState {
             is_multiline: true,
             line_numbers_visible: true,
             syntax_highlighting_enabled: true,
+            multi_cursor: MultiCursorManager::new(),
             history: VecDeque::new(),
             future: VecDeque::new(),
             last_save_time: SystemTime::now(),
@@ -118,6 +142,8 @@ impl EditorState {
             total_lines: 1,
             current_line: 1,
             current_column: 1,
+            edits: Vec::new(),
+            version: 0,
         }
     }
 
@@ -129,23 +155,27 @@ impl EditorState {
 
     pub fn set_content(&mut self, content: String) {
         self.save_snapshot();
-        self.content = content;
-        self.cursor_position = self.cursor_position.min(self.content.len());
+        self.reset_content(Rope::from(content));
+        self.multi_cursor.clear_cursors();
+        self.cursor_position = self.content.clip_offset(self.cursor_position);
         self.update_metrics();
     }
 
     pub fn insert_text(&mut self, text: &str) {
+        if self.has_multiple_cursors() {
+            return self.edit_selections(|_, selection| (selection.range(), text.to_string()));
+        }
         self.save_snapshot();
         
         if let (Some(start), Some(end)) = (self.selection_start, self.selection_end) {
             // Replace selection
             let (start, end) = (start.min(end), start.max(end));
-            self.content.replace_range(start..end, text);
+            self.replace_content(start..end, text);
             self.cursor_position = start + text.len();
             self.clear_selection();
         } else {
             // Insert at cursor
-            self.content.insert_str(self.cursor_position, text);
+            self.replace_content(self.cursor_position..self.cursor_position, text);
             self.cursor_position += text.len();
         }
         
@@ -158,7 +188,8 @@ impl EditorState {
         }
         
         self.save_snapshot();
-        self.content.replace_range(start..end, "");
+        self.replace_content(start..end, "");
+        self.multi_cursor.map_through_edit(start..end, 0);
         self.cursor_position = start.min(self.content.len());
         self.clear_selection();
         self.update_metrics();
@@ -175,40 +206,61 @@ impl EditorState {
     }
 
     pub fn backspace(&mut self) {
+        if self.has_multiple_cursors() {
+            return self.edit_selections(|content, selection| {
+                if selection.is_empty() {
+                    (content.prev_grapheme_boundary(selection.head)..selection.head, String::new())
+                } else {
+                    (selection.range(), String::new())
+                }
+            });
+        }
         if self.delete_current_selection() {
             return;
         }
         
         if self.cursor_position > 0 {
             self.save_snapshot();
-            let char_boundary = self.find_prev_char_boundary(self.cursor_position);
-            self.content.replace_range(char_boundary..self.cursor_position, "");
-            self.cursor_position = char_boundary;
+            let boundary = self.content.prev_grapheme_boundary(self.cursor_position);
+            self.replace_content(boundary..self.cursor_position, "");
+            self.cursor_position = boundary;
             self.update_metrics();
         }
     }
 
     pub fn delete(&mut self) {
+        if self.has_multiple_cursors() {
+            return self.edit_selections(|content, selection| {
+                if selection.is_empty() {
+                    (selection.head..content.next_grapheme_boundary(selection.head), String::new())
+                } else {
+                    (selection.range(), String::new())
+                }
+            });
+        }
         if self.delete_current_selection() {
             return;
         }
         
         if self.cursor_position < self.content.len() {
             self.save_snapshot();
-            let char_boundary = self.find_next_char_boundary(self.cursor_position);
-            self.content.replace_range(self.cursor_position..char_boundary, "");
+            let boundary = self.content.next_grapheme_boundary(self.cursor_position);
+            self.replace_content(self.cursor_position..boundary, "");
             self.update_metrics();
         }
     }
 
     pub fn move_cursor(&mut self, position: usize) {
-        self.cursor_position = position.min(self.content.len());
+        self.cursor_position = self.content.clip_offset(position);
         self.update_cursor_position();
     }
 
     pub fn move_cursor_left(&mut self, extend_selection: bool) {
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| content.prev_grapheme_boundary(head));
+        }
         if self.cursor_position > 0 {
-            let new_pos = self.find_prev_char_boundary(self.cursor_position);
+            let new_pos = self.content.prev_grapheme_boundary(self.cursor_position);
             
             if extend_selection {
                 self.extend_selection_to(new_pos);
@@ -222,8 +274,11 @@ impl EditorState {
     }
 
     pub fn move_cursor_right(&mut self, extend_selection: bool) {
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| content.next_grapheme_boundary(head));
+        }
         if self.cursor_position < self.content.len() {
-            let new_pos = self.find_next_char_boundary(self.cursor_position);
+            let new_pos = self.content.next_grapheme_boundary(self.cursor_position);
             
             if extend_selection {
                 self.extend_selection_to(new_pos);
@@ -237,14 +292,14 @@ impl EditorState {
     }
 
     pub fn move_cursor_up(&mut self, extend_selection: bool) {
-        let current_line_start = self.find_line_start(self.cursor_position);
-        if current_line_start > 0 {
-            let prev_line_start = self.find_line_start(current_line_start - 1);
-            let prev_line_end = current_line_start - 1;
-            let column_offset = self.cursor_position - current_line_start;
-            let prev_line_length = prev_line_end - prev_line_start;
-            
-            let new_pos = prev_line_start + column_offset.min(prev_line_length);
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| vertical_offset(content, head, false).unwrap_or(head));
+        }
+        let row = self.content.offset_to_point(self.cursor_position).row;
+        if row > 0 {
+            // Keep the screen column, which wide characters make differ from the byte column
+            let column = self.content.offset_to_display_column(self.cursor_position);
+            let new_pos = self.content.display_column_to_offset(row - 1, column);
             
             if extend_selection {
                 self.extend_selection_to(new_pos);
@@ -258,16 +313,13 @@ impl EditorState {
     }
 
     pub fn move_cursor_down(&mut self, extend_selection: bool) {
-        let current_line_start = self.find_line_start(self.cursor_position);
-        let current_line_end = self.find_line_end(self.cursor_position);
-        
-        if current_line_end < self.content.len() {
-            let next_line_start = current_line_end + 1;
-            let next_line_end = self.find_line_end(next_line_start);
-            let column_offset = self.cursor_position - current_line_start;
-            let next_line_length = next_line_end - next_line_start;
-            
-            let new_pos = next_line_start + column_offset.min(next_line_length);
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| vertical_offset(content, head, true).unwrap_or(head));
+        }
+        let row = self.content.offset_to_point(self.cursor_position).row;
+        if row + 1 < self.content.line_count() {
+            let column = self.content.offset_to_display_column(self.cursor_position);
+            let new_pos = self.content.display_column_to_offset(row + 1, column);
             
             if extend_selection {
                 self.extend_selection_to(new_pos);
@@ -281,6 +333,11 @@ impl EditorState {
     }
 
     pub fn move_cursor_to_line_start(&mut self, extend_selection: bool) {
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| {
+                content.line_start(content.offset_to_point(head).row)
+            });
+        }
         let line_start = self.find_line_start(self.cursor_position);
         
         if extend_selection {
@@ -294,6 +351,11 @@ impl EditorState {
     }
 
     pub fn move_cursor_to_line_end(&mut self, extend_selection: bool) {
+        if self.has_multiple_cursors() {
+            return self.move_selections(extend_selection, |content, head| {
+                content.line_range(content.offset_to_point(head).row).end
+            });
+        }
         let line_end = self.find_line_end(self.cursor_position);
         
         if extend_selection {
@@ -307,6 +369,7 @@ impl EditorState {
     }
 
     pub fn select_all(&mut self) {
+        self.multi_cursor.clear_cursors();
         self.selection_start = Some(0);
         self.selection_end = Some(self.content.len());
     }
@@ -331,7 +394,7 @@ impl EditorState {
     pub fn get_selected_text(&self) -> Option<String> {
         if let (Some(start), Some(end)) = (self.selection_start, self.selection_end) {
             let (start, end) = (start.min(end), start.max(end));
-            Some(self.content[start..end].to_string())
+            Some(self.content.slice(start..end))
         } else {
             None
         }
@@ -368,6 +431,24 @@ impl EditorState {
         }
     }
 
+    /// Records an undo snapshot right away, bypassing the typing debounce.
+    /// Modal editors call this once before each change so that a whole
+    /// operator or insert session undoes as one step.
+    pub fn checkpoint(&mut self) {
+        self.last_save_time = SystemTime::UNIX_EPOCH;
+        self.save_snapshot();
+    }
+
+    /// Replaces `range` with `text` without recording a snapshot, leaving the
+    /// cursor after the inserted text.
+    pub fn splice(&mut self, range: Range<usize>, text: &str) {
+        self.replace_content(range.clone(), text);
+        self.multi_cursor.map_through_edit(range.clone(), text.len());
+        self.cursor_position = range.start + text.len();
+        self.clear_selection();
+        self.update_metrics();
+    }
+
     pub fn can_undo(&self) -> bool {
         !self.history.is_empty()
     }
@@ -377,13 +458,21 @@ impl EditorState {
     }
 
     pub fn insert_newline(&mut self) {
+        if self.has_multiple_cursors() {
+            let auto_indent = self.syntax_highlighting_enabled;
+            return self.edit_selections(|content, selection| {
+                let line = content.slice(content.line_start(content.offset_to_point(selection.start()).row)..selection.start());
+                let indentation = if auto_indent { Self::calculate_indentation(&line) } else { String::new() };
+                (selection.range(), format!("\n{indentation}"))
+            });
+        }
         self.insert_text("\n");
         
         // Auto-indentation
         if self.syntax_highlighting_enabled {
             let current_line_start = self.find_line_start(self.cursor_position - 1);
-            let prev_line = &self.content[current_line_start..self.cursor_position - 1];
-            let indentation = self.calculate_indentation(prev_line);
+            let prev_line = self.content.slice(current_line_start..self.cursor_position - 1);
+            let indentation = Self::calculate_indentation(&prev_line);
             
             if !indentation.is_empty() {
                 self.insert_text(&indentation);
@@ -391,18 +480,217 @@ impl EditorState {
         }
     }
 
-    pub fn get_lines(&self) -> Vec<&str> {
-        self.content.lines().collect()
+    /// The whole buffer as one string
+    pub fn text(&self) -> String {
+        self.content.to_string()
     }
 
-    pub fn get_line(&self, line_number: usize) -> Option<&str> {
-        self.content.lines().nth(line_number)
+    pub fn get_lines(&self) -> Vec<String> {
+        self.content.lines()
     }
 
-    pub fn get_current_line_text(&self) -> &str {
-        let line_start = self.find_line_start(self.cursor_position);
-        let line_end = self.find_line_end(self.cursor_position);
-        &self.content[line_start..line_end]
+    pub fn get_line(&self, line_number: usize) -> Option<String> {
+        (line_number < self.content.line_count()).then(|| self.content.line(line_number))
+    }
+
+    pub fn get_current_line_text(&self) -> String {
+        let row = self.content.offset_to_point(self.cursor_position).row;
+        self.content.line(row)
+    }
+
+    // Multiple cursors
+
+    /// The primary cursor and its selection
+    pub fn primary_selection(&self) -> Selection {
+        match (self.selection_start, self.selection_end) {
+            (Some(anchor), Some(head)) => Selection::new(anchor, head),
+            _ => Selection::cursor(self.cursor_position),
+        }
+    }
+
+    /// Every selection in document order, the primary included
+    pub fn selections(&self) -> Vec<Selection> {
+        self.all_selections().0
+    }
+
+    pub fn has_multiple_cursors(&self) -> bool {
+        self.multi_cursor.has_multiple_cursors()
+    }
+
+    pub fn add_cursor(&mut self, position: usize) {
+        let position = self.content.clip_offset(position);
+        self.add_selection(position, position);
+    }
+
+    pub fn add_selection(&mut self, anchor: usize, head: usize) {
+        let (mut selections, primary) = self.all_selections();
+        selections.push(Selection::new(self.content.clip_offset(anchor), self.content.clip_offset(head)));
+        self.set_selections(selections, primary);
+    }
+
+    /// Drop every cursor but the primary one
+    pub fn clear_multiple_cursors(&mut self) {
+        self.multi_cursor.clear_cursors();
+    }
+
+    /// Add a cursor on the line above the topmost cursor, at the same
+    /// screen column
+    pub fn add_cursor_above(&mut self) {
+        let top = self.all_selections().0[0].head;
+        if let Some(position) = vertical_offset(&self.content, top, false) {
+            self.add_cursor(position);
+        }
+    }
+
+    /// Add a cursor on the line below the bottommost cursor
+    pub fn add_cursor_below(&mut self) {
+        let bottom = self.all_selections().0.last().map_or(self.cursor_position, |selection| selection.head);
+        if let Some(position) = vertical_offset(&self.content, bottom, true) {
+            self.add_cursor(position);
+        }
+    }
+
+    /// Select every occurrence of the primary selection, or of the word at
+    /// the cursor when nothing is selected, in which case only whole words
+    /// match. Returns how many there are.
+    pub fn select_all_occurrences(&mut self) -> usize {
+        let primary = self.primary_selection();
+        let whole_words = primary.is_empty();
+        let range = if whole_words {
+            self.find_word_boundary_left(primary.head)..self.find_word_boundary_right(primary.head)
+        } else {
+            primary.range()
+        };
+        if range.is_empty() {
+            return 0;
+        }
+
+        let text = self.text();
+        let needle = &text[range.clone()];
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut selections = Vec::new();
+        let mut main = None;
+        for (start, _) in text.match_indices(needle) {
+            let end = start + needle.len();
+            if whole_words
+                && (text[..start].chars().next_back().is_some_and(is_word) || text[end..].chars().next().is_some_and(is_word))
+            {
+                continue;
+            }
+            if start == range.start {
+                main = Some(selections.len());
+            }
+            selections.push(Selection::new(start, end));
+        }
+        // An occurrence overlapping an earlier one is not matched on its own
+        let main = main.unwrap_or_else(|| {
+            selections.push(Selection::new(range.start, range.end));
+            selections.len() - 1
+        });
+        self.set_selections(selections, main);
+        self.multi_cursor.cursor_count()
+    }
+
+    /// Select the block between two screen cells, given as row and display
+    /// column, with one selection per row as Alt-drag does. Rows that end
+    /// left of the block are skipped; the head's row is the primary.
+    pub fn select_columns(&mut self, anchor: (usize, usize), head: (usize, usize)) {
+        let last_row = self.content.line_count() - 1;
+        let (anchor_row, head_row) = (anchor.0.min(last_row), head.0.min(last_row));
+        let left = anchor.1.min(head.1);
+        let mut selections = Vec::new();
+        let mut primary = 0;
+        for row in anchor_row.min(head_row)..=anchor_row.max(head_row) {
+            if row == head_row {
+                primary = selections.len();
+            } else if string_offset::display_width(&self.content.line(row)) < left {
+                continue;
+            }
+            selections.push(Selection::new(
+                self.content.display_column_to_offset(row, anchor.1),
+                self.content.display_column_to_offset(row, head.1),
+            ));
+        }
+        self.set_selections(selections, primary);
+    }
+
+    /// Replace one range per selection, as a single undo step. `edit` gets
+    /// the text and a selection and returns the range to replace and the
+    /// new text. The edits apply from the last to the first, so offsets
+    /// computed against the old text stay valid, and each selection becomes
+    /// a cursor after its new text.
+    pub fn edit_selections(&mut self, edit: impl Fn(&Rope, Selection) -> (Range<usize>, String)) {
+        let (selections, primary) = self.all_selections();
+        let mut edits: Vec<(Range<usize>, String)> =
+            selections.iter().map(|selection| edit(&self.content, *selection)).collect();
+        // A deletion next to a cursor may reach into its neighbour's range
+        let mut previous_end = 0;
+        for (range, _) in &mut edits {
+            range.start = range.start.max(previous_end);
+            range.end = range.end.max(range.start);
+            previous_end = range.end;
+        }
+        if edits.iter().all(|(range, text)| range.is_empty() && text.is_empty()) {
+            return;
+        }
+
+        self.save_snapshot();
+        for (range, text) in edits.iter().rev() {
+            self.replace_content(range.clone(), text);
+        }
+        let (mut inserted, mut removed) = (0, 0);
+        let cursors = edits
+            .iter()
+            .map(|(range, text)| {
+                let cursor = range.start + inserted - removed + text.len();
+                inserted += text.len();
+                removed += range.len();
+                Selection::cursor(cursor)
+            })
+            .collect();
+        self.set_selections(cursors, primary);
+    }
+
+    /// Move the head of every selection with `motion`, keeping anchors when
+    /// extending and collapsing to cursors otherwise
+    fn move_selections(&mut self, extend_selection: bool, motion: impl Fn(&Rope, usize) -> usize) {
+        let (selections, primary) = self.all_selections();
+        let moved = selections
+            .into_iter()
+            .map(|selection| {
+                let head = motion(&self.content, selection.head);
+                if extend_selection {
+                    Selection::new(selection.anchor, head)
+                } else {
+                    Selection::cursor(head)
+                }
+            })
+            .collect();
+        self.set_selections(moved, primary);
+    }
+
+    /// The primary and secondary selections merged, and the primary's index
+    fn all_selections(&self) -> (Vec<Selection>, usize) {
+        let mut selections = self.multi_cursor.selections().to_vec();
+        selections.push(self.primary_selection());
+        let primary = selections.len() - 1;
+        merge_selections(selections, primary)
+    }
+
+    /// Merge `selections` and store the one at `primary` in the cursor
+    /// fields and the rest as secondary cursors
+    fn set_selections(&mut self, selections: Vec<Selection>, primary: usize) {
+        let (mut selections, primary) = merge_selections(selections, primary);
+        let main = selections.remove(primary);
+        self.cursor_position = main.head;
+        if main.is_empty() {
+            self.clear_selection();
+        } else {
+            self.selection_start = Some(main.anchor);
+            self.selection_end = Some(main.head);
+        }
+        self.multi_cursor.set_selections(selections);
+        self.update_metrics();
     }
 
     // Private helper methods
@@ -433,15 +721,17 @@ impl EditorState {
             cursor_position: self.cursor_position,
             selection_start: self.selection_start,
             selection_end: self.selection_end,
+            cursors: self.multi_cursor.selections().to_vec(),
             timestamp: SystemTime::now(),
         }
     }
 
     fn restore_snapshot(&mut self, snapshot: EditorSnapshot) {
-        self.content = snapshot.content;
+        self.reset_content(snapshot.content);
         self.cursor_position = snapshot.cursor_position;
         self.selection_start = snapshot.selection_start;
         self.selection_end = snapshot.selection_end;
+        self.multi_cursor.set_selections(snapshot.cursors);
         self.update_metrics();
     }
 
@@ -457,84 +747,103 @@ impl EditorState {
         self.selection_end = None;
     }
 
-    fn update_metrics(&mut self) {
-        self.total_lines = self.content.lines().count().max(1);
-        self.update_cursor_position();
+    /// The changes made to the text since the last call, in order. Each
+    /// range is into the text as it was after the changes before it.
+    pub fn take_edits(&mut self) -> Vec<(Range<usize>, usize)> {
+        std::mem::take(&mut self.edits)
     }
 
-    fn update_cursor_position(&mut self) {
-        let before_cursor = &self.content[..self.cursor_position];
-        self.current_line = before_cursor.matches('\n').count() + 1;
-        
-        if let Some(last_newline) = before_cursor.rfind('\n') {
-            self.current_column = self.cursor_position - last_newline;
-        } else {
-            self.current_column = self.cursor_position + 1;
+    /// Counts the changes made to the text. An unchanged version means an
+    /// unchanged text, without comparing it.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn replace_content(&mut self, range: Range<usize>, text: &str) {
+        self.content.replace(range.clone(), text);
+        self.record_edit(range, text.len());
+    }
+
+    /// Swap in a whole new text, recording only the part that differs
+    fn reset_content(&mut self, content: Rope) {
+        let (old, new) = (self.content.to_string(), content.to_string());
+        let prefix = old.bytes().zip(new.bytes()).take_while(|(a, b)| a == b).count();
+        let suffix = old.as_bytes()[prefix..]
+            .iter()
+            .rev()
+            .zip(new.as_bytes()[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.content = content;
+        if old != new {
+            self.record_edit(prefix..old.len() - suffix, new.len() - suffix - prefix);
         }
     }
 
-    fn find_prev_char_boundary(&self, mut pos: usize) -> usize {
-        while pos > 0 && !self.content.is_char_boundary(pos) {
-            pos -= 1;
+    fn record_edit(&mut self, range: Range<usize>, len: usize) {
+        self.version += 1;
+        if self.edits.len() < MAX_PENDING_EDITS {
+            return self.edits.push((range, len));
         }
-        if pos > 0 {
-            pos -= 1;
-            while pos > 0 && !self.content.is_char_boundary(pos) {
-                pos -= 1;
-            }
+        // Too many to keep apart: merge them with this one into a single
+        // edit from the first changed byte to the last
+        let mut merged: Option<(Range<usize>, usize)> = None;
+        for (range, len) in self.edits.drain(..).chain(std::iter::once((range, len))) {
+            merged = Some(match merged {
+                None => (range, len),
+                Some((old, old_len)) => {
+                    let start = old.start.min(range.start);
+                    let end = (old.start + old_len).max(range.end);
+                    let old_end = if end == old.start + old_len { old.end } else { end - old_len + old.len() };
+                    (start..old_end, end - start - range.len() + len)
+                }
+            });
         }
-        pos
+        self.edits.extend(merged);
     }
 
-    fn find_next_char_boundary(&self, mut pos: usize) -> usize {
-        let len = self.content.len();
-        if pos < len {
-            pos += 1;
-            while pos < len && !self.content.is_char_boundary(pos) {
-                pos += 1;
-            }
-        }
-        pos
+    fn update_metrics(&mut self) {
+        self.total_lines = self.content.line_count();
+        self.update_cursor_position();
+    }
+
+    fn update_cursor_position(&mut self) {
+        self.current_line = self.content.offset_to_point(self.cursor_position).row + 1;
+        self.current_column = self.content.offset_to_display_column(self.cursor_position) + 1;
     }
 
     pub fn find_line_start(&self, pos: usize) -> usize {
-        self.content[..pos].rfind('\n').map_or(0, |i| i + 1)
+        self.content.line_start(self.content.offset_to_point(pos).row)
     }
 
-    fn find_line_end(&self, pos: usize) -> usize {
-        self.content[pos..].find('\n').map_or(self.content.len(), |i| pos + i)
+    pub fn find_line_end(&self, pos: usize) -> usize {
+        self.content.line_range(self.content.offset_to_point(pos).row).end
     }
 
     fn find_word_boundary_left(&self, mut pos: usize) -> usize {
-        while pos > 0 {
-            let ch = self.content.chars().nth(pos - 1);
-            if let Some(c) = ch {
-                if c.is_alphanumeric() || c == '_' {
-                    pos -= 1;
-                } else {
-                    break;
-                }
-            } else {
+        let line_start = self.find_line_start(pos);
+        let line = self.content.slice(line_start..pos);
+        for c in line.chars().rev() {
+            if !(c.is_alphanumeric() || c == '_') {
                 break;
             }
+            pos -= c.len_utf8();
         }
         pos
     }
 
     fn find_word_boundary_right(&self, mut pos: usize) -> usize {
-        let chars: Vec<char> = self.content.chars().collect();
-        while pos < chars.len() {
-            let ch = chars[pos];
-            if ch.is_alphanumeric() || ch == '_' {
-                pos += 1;
-            } else {
+        let line = self.content.slice(pos..self.find_line_end(pos));
+        for c in line.chars() {
+            if !(c.is_alphanumeric() || c == '_') {
                 break;
             }
+            pos += c.len_utf8();
         }
         pos
     }
 
-    fn calculate_indentation(&self, line: &str) -> String {
+    fn calculate_indentation(line: &str) -> String {
         let mut indent = String::new();
         for ch in line.chars() {
             if ch == ' ' || ch == '\t' {
@@ -547,6 +856,14 @@ impl EditorState {
     }
 }
 
+/// The offset on the row above or below `offset` at the same screen column
+fn vertical_offset(content: &Rope, offset: usize, down: bool) -> Option<usize> {
+    let row = content.offset_to_point(offset).row;
+    let target = if down { row + 1 } else { row.checked_sub(1)? };
+    (target < content.line_count())
+        .then(|| content.display_column_to_offset(target, content.offset_to_display_column(offset)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -611,4 +928,130 @@ mod tests {
         state.move_cursor_up(false);
         assert_eq!(state.cursor_position, 0); // Back to start
     }
+
+    #[test]
+    fn test_grapheme_cursoring() {
+        let mut state = EditorState::with_content("ok 👍🏽 cafe\u{301}");
+        state.move_cursor(state.content.len());
+        state.move_cursor_left(false);
+        assert_eq!(state.cursor_position, "ok 👍🏽 caf".len());
+        state.backspace();
+        assert_eq!(state.content, "ok 👍🏽 cae\u{301}");
+
+        state.move_cursor("ok ".len());
+        state.delete();
+        assert_eq!(state.content, "ok  cae\u{301}");
+        state.move_cursor_right(false);
+        assert_eq!(state.cursor_position, "ok  ".len());
+
+        // A cursor inside a character is pulled back to its start
+        state.set_content("é".to_string());
+        state.move_cursor(1);
+        assert_eq!(state.cursor_position, 0);
+    }
+
+    #[test]
+    fn test_vertical_movement_keeps_display_column() {
+        let mut state = EditorState::with_content("日本語\nabcdef\nx");
+        state.move_cursor("日本".len());
+        assert_eq!(state.current_column, 5);
+
+        state.move_cursor_down(false);
+        assert_eq!(state.cursor_position, "日本語\nabcd".len());
+        state.move_cursor_left(false);
+        state.move_cursor_up(false);
+        assert_eq!(state.cursor_position, "日".len(), "column 3 is inside 本");
+        state.move_cursor_down(false);
+        state.move_cursor_down(false);
+        assert_eq!(state.cursor_position, state.content.len());
+        assert_eq!(state.content.offset_to_utf16(state.cursor_position), 12);
+    }
+
+    #[test]
+    fn test_multi_cursor_edit_is_one_undo_step() {
+        let mut state = EditorState::with_content("foo\nfoo\nfoo");
+        state.move_cursor(3);
+        state.add_cursor(7);
+        state.add_cursor(11);
+        state.last_save_time = SystemTime::UNIX_EPOCH;
+
+        state.insert_text("!");
+        assert_eq!(state.content, "foo!\nfoo!\nfoo!");
+        assert_eq!(state.selections(), [Selection::cursor(4), Selection::cursor(9), Selection::cursor(14)]);
+        assert_eq!(state.cursor_position, 4, "the primary cursor stays primary");
+
+        state.insert_newline();
+        state.move_cursor_left(false);
+        state.backspace();
+        assert_eq!(state.content, "foo\n\nfoo\n\nfoo\n");
+
+        state.last_save_time = SystemTime::UNIX_EPOCH;
+        state.insert_text("ab");
+        assert!(state.undo());
+        assert_eq!(state.content, "foo\n\nfoo\n\nfoo\n", "every cursor's insertion undoes together");
+        assert_eq!(state.selections().len(), 3);
+    }
+
+    #[test]
+    fn test_overlapping_selections_merge() {
+        let mut state = EditorState::with_content("abcdef");
+        state.move_cursor(1);
+        state.add_cursor(2);
+        state.add_cursor(3);
+        state.add_cursor(3);
+        assert_eq!(state.selections().len(), 3);
+
+        state.backspace();
+        assert_eq!(state.content, "def");
+        assert!(!state.has_multiple_cursors(), "the cursors met at the start");
+
+        state.set_content("one two three".to_string());
+        state.selection_start = Some(0);
+        state.selection_end = Some(5);
+        state.add_selection(3, 9);
+        state.add_cursor(12);
+        assert_eq!(state.selections(), [Selection::new(0, 9), Selection::cursor(12)]);
+        state.move_cursor_right(true);
+        assert_eq!(state.selections(), [Selection::new(0, 10), Selection::new(12, 13)]);
+        state.insert_text("_");
+        assert_eq!(state.content, "_re_");
+    }
+
+    #[test]
+    fn test_column_selection() {
+        let mut state = EditorState::with_content("abcd\nab\n\nab日ef");
+        state.select_columns((0, 1), (3, 3));
+        assert_eq!(
+            state.selections(),
+            [Selection::new(1, 3), Selection::new(6, 7), Selection::new(10, 11)],
+            "the empty row is skipped and column 3 is inside 日"
+        );
+        assert_eq!(state.current_line, 4, "the head's row is the primary");
+
+        state.insert_text("X");
+        assert_eq!(state.content, "aXd\naX\n\naX日ef");
+        state.move_cursor_to_line_end(false);
+        state.insert_text(";");
+        assert_eq!(state.content, "aXd;\naX;\n\naX日ef;");
+        state.move_cursor_up(false);
+        state.move_cursor_to_line_start(false);
+        assert_eq!(state.selections(), [Selection::cursor(0), Selection::cursor(9)], "the first two rows meet on row 0");
+    }
+
+    #[test]
+    fn test_select_all_occurrences() {
+        let mut state = EditorState::with_content("let foo = foo_bar + foo;");
+        state.move_cursor(5);
+        assert_eq!(state.select_all_occurrences(), 2, "only whole words match a word at the cursor");
+        assert_eq!(state.get_selected_text().as_deref(), Some("foo"));
+        state.insert_text("baz");
+        assert_eq!(state.content, "let baz = foo_bar + baz;");
+
+        state.clear_multiple_cursors();
+        state.selection_start = Some(4);
+        state.selection_end = Some(6);
+        assert_eq!(state.select_all_occurrences(), 3);
+        state.delete();
+        assert_eq!(state.content, "let z = foo_r + z;");
+    }
 }
@@ -0,0 +1,261 @@
+//! A file opened in an editor pane.
+//!
+//! Wraps a [`ModernTextEditor`] with the file it came from. The buffer's
+//! edit version at the last read or write tells whether there are unsaved
+//! edits, and the file's modification time and size are kept to notice when
+//! something else changed it. A clean buffer follows such changes; a dirty
+//! one is marked as conflicting and is only written back on request.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::editor::native_completion::NativeCompletionBridge;
+use crate::editor::text_editor::{EditorMessage, ModernTextEditor};
+use crate::languages::LanguageRegistry;
+use crate::watcher::fs_watcher::FileStamp;
+
+#[derive(Debug, Error)]
+pub enum FileEditorError {
+    #[error("Failed to read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("{} is not UTF-8 text", .0.display())]
+    NotText(PathBuf),
+    #[error("Failed to write {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+    #[error("{} changed on disk since it was opened", .0.display())]
+    ModifiedOnDisk(PathBuf),
+}
+
+/// What [`FileEditor::check_disk`] found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskChange {
+    Unchanged,
+    /// The buffer had no edits and now holds the new contents
+    Reloaded,
+    /// The file changed under unsaved edits
+    Conflict,
+    Deleted,
+}
+
+#[derive(Debug)]
+pub struct FileEditor {
+    path: PathBuf,
+    editor: ModernTextEditor,
+    /// Buffer version as last read or written
+    saved_version: u64,
+    stamp: Option<FileStamp>,
+    conflict: bool,
+}
+
+impl FileEditor {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, FileEditorError> {
+        let path = path.into();
+        let (saved, stamp) = read(&path)?;
+        let mut editor = ModernTextEditor::with_content(saved);
+        let language = LanguageRegistry::global().for_path(&path);
+        editor.set_language(language.map(|language| language.name().to_string()));
+        Ok(Self {
+            path,
+            saved_version: editor.state().version(),
+            editor,
+            stamp,
+            conflict: false,
+        })
+    }
+
+    /// Complete command arguments through the user's shell when the file
+    /// is a shell script
+    pub fn with_native_completions(mut self, bridge: NativeCompletionBridge, cwd: impl Into<PathBuf>) -> Self {
+        self.editor.set_native_completions(bridge, cwd);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// File name, marked when there are unsaved edits
+    pub fn title(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map_or_else(|| self.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        if self.is_dirty() {
+            format!("{name} ●")
+        } else {
+            name
+        }
+    }
+
+    pub fn editor(&self) -> &ModernTextEditor {
+        &self.editor
+    }
+
+    pub fn update(&mut self, message: EditorMessage) {
+        self.editor.update(message);
+    }
+
+    pub fn content(&self) -> String {
+        self.editor.get_content()
+    }
+
+    /// Any edit since the last read or write counts, even one that was
+    /// undone, so the title can ask on every frame without reading the text
+    pub fn is_dirty(&self) -> bool {
+        self.editor.state().version() != self.saved_version
+    }
+
+    /// The file changed on disk while there were unsaved edits
+    pub fn has_conflict(&self) -> bool {
+        self.conflict
+    }
+
+    /// Move to one-based `line`, as printed by compilers and grep
+    pub fn go_to_line(&mut self, line: usize) {
+        self.editor.move_to_line(line.saturating_sub(1));
+    }
+
+    /// Write the buffer back, unless the file changed on disk since it was
+    /// read; [`Self::overwrite`] writes it anyway
+    pub fn save(&mut self) -> Result<(), FileEditorError> {
+        if self.conflict || FileStamp::read(&self.path) != self.stamp {
+            self.conflict = true;
+            return Err(FileEditorError::ModifiedOnDisk(self.path.clone()));
+        }
+        self.overwrite()
+    }
+
+    pub fn overwrite(&mut self) -> Result<(), FileEditorError> {
+        let content = self.editor.get_content();
+        fs::write(&self.path, &content).map_err(|source| FileEditorError::Write {
+            path: self.path.clone(),
+            source,
+        })?;
+        self.saved_version = self.editor.state().version();
+        self.stamp = FileStamp::read(&self.path);
+        self.conflict = false;
+        Ok(())
+    }
+
+    /// Drop unsaved edits and read the file again
+    pub fn reload(&mut self) -> Result<(), FileEditorError> {
+        let (saved, stamp) = read(&self.path)?;
+        if self.editor.get_content() != saved {
+            self.editor.set_content(saved);
+        }
+        self.saved_version = self.editor.state().version();
+        self.stamp = stamp;
+        self.conflict = false;
+        Ok(())
+    }
+
+    /// Look for changes made to the file by something else
+    pub fn check_disk(&mut self) -> DiskChange {
+        let stamp = FileStamp::read(&self.path);
+        if stamp == self.stamp {
+            return DiskChange::Unchanged;
+        }
+        if stamp.is_none() {
+            self.stamp = None;
+            return DiskChange::Deleted;
+        }
+        if !self.is_dirty() && self.reload().is_ok() {
+            return DiskChange::Reloaded;
+        }
+        self.stamp = stamp;
+        self.conflict = true;
+        DiskChange::Conflict
+    }
+}
+
+fn read(path: &Path) -> Result<(String, Option<FileStamp>), FileEditorError> {
+    let bytes = fs::read(path).map_err(|source| FileEditorError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let text = String::from_utf8(bytes).map_err(|_| FileEditorError::NotText(path.to_path_buf()))?;
+    Ok((text, FileStamp::read(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::EditorAction;
+    use tempfile::TempDir;
+
+    fn type_text(editor: &mut FileEditor, text: &str) {
+        editor.update(EditorMessage::ActionRequested(EditorAction::Insert(text.to_string())));
+    }
+
+    #[test]
+    fn test_dirty_tracking_and_save() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("main.rs");
+        fs::write(&path, "fn main() {}\n").unwrap();
+
+        let mut editor = FileEditor::open(&path).unwrap();
+        assert_eq!(editor.editor().language(), Some("rust"));
+        assert!(!editor.is_dirty());
+        assert_eq!(editor.title(), "main.rs");
+
+        type_text(&mut editor, "// ");
+        assert!(editor.is_dirty());
+        assert_eq!(editor.title(), "main.rs ●");
+
+        editor.save().unwrap();
+        assert!(!editor.is_dirty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "// fn main() {}\n");
+
+        // A reload that finds the same text leaves the buffer clean
+        editor.reload().unwrap();
+        assert!(!editor.is_dirty());
+        type_text(&mut editor, "x");
+        editor.reload().unwrap();
+        assert!(!editor.is_dirty());
+        assert_eq!(editor.content(), "// fn main() {}\n");
+    }
+
+    #[test]
+    fn test_external_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "one\n").unwrap();
+        let mut editor = FileEditor::open(&path).unwrap();
+        assert_eq!(editor.check_disk(), DiskChange::Unchanged);
+
+        // A clean buffer follows the file
+        fs::write(&path, "one\ntwo\n").unwrap();
+        assert_eq!(editor.check_disk(), DiskChange::Reloaded);
+        assert_eq!(editor.content(), "one\ntwo\n");
+
+        // Unsaved edits are kept and saving refuses to clobber the file
+        type_text(&mut editor, "zero\n");
+        fs::write(&path, "changed elsewhere\n").unwrap();
+        assert_eq!(editor.check_disk(), DiskChange::Conflict);
+        assert!(editor.has_conflict());
+        assert!(matches!(editor.save(), Err(FileEditorError::ModifiedOnDisk(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "changed elsewhere\n");
+
+        editor.overwrite().unwrap();
+        assert!(!editor.has_conflict());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "zero\none\ntwo\n");
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(editor.check_disk(), DiskChange::Deleted);
+    }
+
+    #[test]
+    fn test_open_rejects_binary_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("image.png");
+        fs::write(&path, [0x89, b'P', b'N', b'G', 0xff, 0xfe]).unwrap();
+        assert!(matches!(FileEditor::open(&path), Err(FileEditorError::NotText(_))));
+        assert!(matches!(
+            FileEditor::open(dir.path().join("missing.rs")),
+            Err(FileEditorError::Read { .. })
+        ));
+    }
+}
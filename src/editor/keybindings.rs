@@ -2,6 +2,85 @@ use iced::keyboard::{Key, Modifiers};
 use iced::keyboard::key::Named;
 use std::collections::HashMap;
 use crate::editor::EditorAction;
+use crate::input::keymap::Keystroke;
+
+/// Default editor bindings as action name, keys and description. They also
+/// form the editor context of the built-in layer in
+/// [`crate::input::keymap`], where keysets and user overrides can rebind
+/// them by name.
+pub const DEFAULT_EDITOR_BINDINGS: &[(&str, &str, &str)] = &[
+    ("editor:undo", "ctrl-z", "Undo last action"),
+    ("editor:redo", "ctrl-y", "Redo last undone action"),
+    ("editor:redo", "ctrl-shift-Z", "Redo last undone action"),
+    ("editor:select_all", "ctrl-a", "Select all text"),
+    ("editor:cut", "ctrl-x", "Cut selected text"),
+    ("editor:copy", "ctrl-c", "Copy selected text"),
+    ("editor:paste", "ctrl-v", "Paste text from clipboard"),
+    ("editor:move_to_line_start", "home", "Move cursor to beginning of line"),
+    ("editor:move_to_line_end", "end", "Move cursor to end of line"),
+    ("editor:move_to_document_start", "ctrl-home", "Move cursor to beginning of document"),
+    ("editor:move_to_document_end", "ctrl-end", "Move cursor to end of document"),
+    ("editor:insert_newline", "shift-enter", "Insert new line"),
+    ("editor:duplicate_line", "ctrl-d", "Duplicate current line"),
+    ("editor:delete_line", "ctrl-k", "Delete current line"),
+    ("editor:indent", "tab", "Indent selected text"),
+    ("editor:unindent", "shift-tab", "Unindent selected text"),
+    ("editor:find", "ctrl-f", "Show search dialog"),
+    ("editor:replace", "ctrl-h", "Show find and replace dialog"),
+    ("editor:go_to_line", "ctrl-g", "Go to specific line"),
+    ("editor:add_cursor_below", "alt-d", "Add cursor on line below"),
+    ("editor:add_cursor_above", "alt-u", "Add cursor on line above"),
+    ("editor:select_all_occurrences", "ctrl-shift-L", "Select all occurrences of the selection"),
+    ("editor:move_to_previous_word", "ctrl-left", "Move to previous word"),
+    ("editor:move_to_next_word", "ctrl-right", "Move to next word"),
+    ("editor:delete_word_left", "ctrl-backspace", "Delete previous word"),
+    ("editor:delete_word_right", "ctrl-delete", "Delete next word"),
+    ("editor:toggle_comment", "ctrl-/", "Toggle line comment"),
+    ("editor:toggle_line_numbers", "ctrl-l", "Toggle line numbers"),
+    ("editor:save", "ctrl-s", "Save current content"),
+    ("editor:format_document", "alt-shift-F", "Format entire document"),
+    ("editor:fold", "ctrl--", "Fold current region"),
+    ("editor:unfold", "ctrl-+", "Unfold current region"),
+];
+
+/// The editor action for an action name from [`DEFAULT_EDITOR_BINDINGS`]
+pub fn editor_action(action: &str) -> Option<EditorAction> {
+    let action = match action {
+        "editor:undo" => EditorAction::Undo,
+        "editor:redo" => EditorAction::Redo,
+        "editor:select_all" => EditorAction::SELECT_ALL,
+        "editor:cut" => EditorAction::CUT,
+        "editor:copy" => EditorAction::Copy,
+        "editor:paste" => EditorAction::Paste,
+        "editor:move_to_line_start" => EditorAction::MOVE_TO_LINE_START,
+        "editor:move_to_line_end" => EditorAction::MOVE_TO_LINE_END,
+        "editor:move_to_document_start" => EditorAction::MOVE_TO_DOCUMENT_START,
+        "editor:move_to_document_end" => EditorAction::MOVE_TO_DOCUMENT_END,
+        "editor:insert_newline" => EditorAction::InsertNewLine,
+        "editor:duplicate_line" => EditorAction::DUPLICATE_CURRENT_LINE,
+        "editor:delete_line" => EditorAction::DELETE_CURRENT_LINE,
+        "editor:indent" => EditorAction::INDENT_SELECTION,
+        "editor:unindent" => EditorAction::UNINDENT_SELECTION,
+        "editor:find" => EditorAction::show_search(),
+        "editor:replace" => EditorAction::show_replace(),
+        "editor:go_to_line" => EditorAction::GO_TO_LINE,
+        "editor:add_cursor_below" => EditorAction::ADD_CURSOR_BELOW,
+        "editor:add_cursor_above" => EditorAction::ADD_CURSOR_ABOVE,
+        "editor:select_all_occurrences" => EditorAction::SelectAllOccurrences,
+        "editor:move_to_previous_word" => EditorAction::MOVE_TO_PREVIOUS_WORD,
+        "editor:move_to_next_word" => EditorAction::MOVE_TO_NEXT_WORD,
+        "editor:delete_word_left" => EditorAction::DELETE_PREVIOUS_WORD,
+        "editor:delete_word_right" => EditorAction::DELETE_NEXT_WORD,
+        "editor:toggle_comment" => EditorAction::TOGGLE_COMMENT,
+        "editor:toggle_line_numbers" => EditorAction::TOGGLE_LINE_NUMBERS,
+        "editor:save" => EditorAction::SAVE,
+        "editor:format_document" => EditorAction::FORMAT_DOCUMENT,
+        "editor:fold" => EditorAction::FOLD_CURRENT_REGION,
+        "editor:unfold" => EditorAction::UNFOLD_CURRENT_REGION,
+        _ => return None,
+    };
+    Some(action)
+}
 
 #[derive(Debug, Clone)]
 pub struct KeybindingManager {
@@ -44,7 +123,8 @@ impl KeybindingManager {
             return None;
         }
 
-        let binding = KeyBinding { key: key.clone(), modifiers: *modifiers };
+        let stroke = Keystroke::new(key.clone(), *modifiers);
+        let binding = KeyBinding { key: stroke.key, modifiers: stroke.modifiers };
         self.bindings.get(&binding).map(|kb| kb.action.clone())
     }
 
@@ -79,235 +159,12 @@ impl KeybindingManager {
     }
 
     fn load_default_keybindings(&mut self) {
-        // Basic editing
-        self.add_keybinding(
-            Key::Character("z".into()),
-            Modifiers::CTRL,
-            EditorAction::Undo,
-            "Undo last action".to_string(),
-        );
-
-        self.add_keybinding(
-            Key::Character("y".into()),
-            Modifiers::CTRL,
-            EditorAction::Redo,
-            "Redo last undone action".to_string(),
-        );
-
-        self.add_keybinding(
-            Key::Character("z".into()),
-            Modifiers::CTRL | Modifiers::SHIFT,
-            EditorAction::Redo,
-            "Redo last undone action".to_string(),
-        );
-
-        // Selection
-self.add_keybinding(
-            Key::Character("a".into()),
-            Modifiers::CTRL,
-            EditorAction::SELECT_ALL,
-            "Select all text".to_string(),
-        );
-
-        // Copy/Cut/Paste would be handled by the system, but we can add custom actions
-self.add_keybinding(
-            Key::Character("x".into()),
-            Modifiers::CTRL,
-            EditorAction::CUT,
-            "Cut selected text".to_string(),
-        );
-
-        self.add_keybinding(
-            Key::Character("c".into()),
-            Modifiers::CTRL,
-            EditorAction::Copy,
-            "Copy selected text".to_string(),
-        );
-
-        self.add_keybinding(
-            Key::Character("v".into()),
-            Modifiers::CTRL,
-            EditorAction::Paste,
-            "Paste text from clipboard".to_string(),
-        );
-
-        // Navigation
-self.add_keybinding(
-            Key::Named(Named::Home),
-            Modifiers::empty(),
-            EditorAction::MOVE_TO_LINE_START,
-            "Move cursor to beginning of line".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::End),
-            Modifiers::empty(),
-            EditorAction::MOVE_TO_LINE_END,
-            "Move cursor to end of line".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::Home),
-            Modifiers::CTRL,
-            EditorAction::MOVE_TO_DOCUMENT_START,
-            "Move cursor to beginning of document".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::End),
-            Modifiers::CTRL,
-            EditorAction::MOVE_TO_DOCUMENT_END,
-            "Move cursor to end of document".to_string(),
-        );
-
-        // Line operations
-        self.add_keybinding(
-            Key::Named(Named::Enter),
-            Modifiers::SHIFT,
-            EditorAction::InsertNewLine,
-            "Insert new line".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("d".into()),
-            Modifiers::CTRL,
-            EditorAction::DUPLICATE_CURRENT_LINE,
-            "Duplicate current line".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("k".into()),
-            Modifiers::CTRL,
-            EditorAction::DELETE_CURRENT_LINE,
-            "Delete current line".to_string(),
-        );
-
-        // Indentation
-self.add_keybinding(
-            Key::Named(Named::Tab),
-            Modifiers::empty(),
-            EditorAction::INDENT_SELECTION,
-            "Indent selected text".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::Tab),
-            Modifiers::SHIFT,
-            EditorAction::UNINDENT_SELECTION,
-            "Unindent selected text".to_string(),
-        );
-
-        // Search
-        self.add_keybinding(
-            Key::Character("f".into()),
-            Modifiers::CTRL,
-            EditorAction::show_search(),
-            "Show search dialog".to_string(),
-        );
-
-        self.add_keybinding(
-            Key::Character("h".into()),
-            Modifiers::CTRL,
-            EditorAction::show_replace(),
-            "Show find and replace dialog".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("g".into()),
-            Modifiers::CTRL,
-            EditorAction::GO_TO_LINE,
-            "Go to specific line".to_string(),
-        );
-
-        // Multiple cursors
-self.add_keybinding(
-            Key::Character("d".into()),
-            Modifiers::ALT,
-            EditorAction::ADD_CURSOR_BELOW,
-            "Add cursor on line below".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("u".into()),
-            Modifiers::ALT,
-            EditorAction::ADD_CURSOR_ABOVE,
-            "Add cursor on line above".to_string(),
-        );
-
-        // Word operations
-self.add_keybinding(
-            Key::Named(Named::ArrowLeft),
-            Modifiers::CTRL,
-            EditorAction::MOVE_TO_PREVIOUS_WORD,
-            "Move to previous word".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::ArrowRight),
-            Modifiers::CTRL,
-            EditorAction::MOVE_TO_NEXT_WORD,
-            "Move to next word".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::Backspace),
-            Modifiers::CTRL,
-            EditorAction::DELETE_PREVIOUS_WORD,
-            "Delete previous word".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Named(Named::Delete),
-            Modifiers::CTRL,
-            EditorAction::DELETE_NEXT_WORD,
-            "Delete next word".to_string(),
-        );
-
-        // Toggle features
-self.add_keybinding(
-            Key::Character("/".into()),
-            Modifiers::CTRL,
-            EditorAction::TOGGLE_COMMENT,
-            "Toggle line comment".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("l".into()),
-            Modifiers::CTRL,
-            EditorAction::TOGGLE_LINE_NUMBERS,
-            "Toggle line numbers".to_string(),
-        );
-
-        // Save (even though this might be handled elsewhere)
-self.add_keybinding(
-            Key::Character("s".into()),
-            Modifiers::CTRL,
-            EditorAction::SAVE,
-            "Save current content".to_string(),
-        );
-
-        // Format
-self.add_keybinding(
-            Key::Character("f".into()),
-            Modifiers::ALT | Modifiers::SHIFT,
-            EditorAction::FORMAT_DOCUMENT,
-            "Format entire document".to_string(),
-        );
-
-        // Folding
-self.add_keybinding(
-            Key::Character("-".into()),
-            Modifiers::CTRL,
-            EditorAction::FOLD_CURRENT_REGION,
-            "Fold current region".to_string(),
-        );
-
-self.add_keybinding(
-            Key::Character("+".into()),
-            Modifiers::CTRL,
-            EditorAction::UNFOLD_CURRENT_REGION,
-            "Unfold current region".to_string(),
-        );
+        for (action, keys, description) in DEFAULT_EDITOR_BINDINGS {
+            let stroke = Keystroke::parse(keys).expect("default editor bindings parse");
+            if let Some(action) = editor_action(action) {
+                self.add_keybinding(stroke.key, stroke.modifiers, action, description.to_string());
+            }
+        }
     }
 
     pub fn format_key_binding(&self, binding: &KeyBinding) -> String {
@@ -401,8 +258,8 @@ impl EditorAction {
     pub const GO_TO_LINE: EditorAction = EditorAction::MoveCursor(0);
     
     // Multiple cursor operations
-    pub const ADD_CURSOR_BELOW: EditorAction = EditorAction::AddCursor(0);
-    pub const ADD_CURSOR_ABOVE: EditorAction = EditorAction::AddCursor(0);
+    pub const ADD_CURSOR_BELOW: EditorAction = EditorAction::AddCursorBelow;
+    pub const ADD_CURSOR_ABOVE: EditorAction = EditorAction::AddCursorAbove;
     
     // Word operations
     pub const MOVE_TO_PREVIOUS_WORD: EditorAction = EditorAction::MoveCursor(0);
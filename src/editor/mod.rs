@@ -2,11 +2,23 @@ pub mod text_editor;
 pub mod syntax_highlighter;
 pub mod completion_engine;
 pub mod editor_state;
+pub mod rope;
+pub mod multi_cursor;
+pub mod file_editor;
 pub mod keybindings;
 pub mod vim_mode;
+pub mod vim_motion;
+pub mod vim_ex;
+pub mod readline;
+pub mod autosuggestion;
+pub mod native_completion;
+pub mod text_input;
 
 pub use text_editor::ModernTextEditor;
+pub use file_editor::{FileEditor, FileEditorError};
 pub use editor_state::{EditorState, EditorAction};
 pub use syntax_highlighter::{SyntaxHighlighter, HighlightedSpan};
 pub use completion_engine::{CompletionEngine, CompletionItem};
 pub use keybindings::{KeybindingManager, EditorKeybinding};
+pub use autosuggestion::{Autosuggestion, AutosuggestionEngine, AutosuggestionSource};
+pub use native_completion::{NativeCompletionBridge, NativeShell};
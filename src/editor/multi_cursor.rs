@@ -1,230 +1,167 @@
-use crate::editor::{EditorAction, EditorState};
-use std::collections::BTreeSet;
-
-#[derive(Debug, Clone)]
-pub struct MultiCursorManager {
-    cursors: BTreeSet<usize>,
-    primary_cursor: usize,
-    selection_ranges: Vec<(usize, usize)>, // (start, end) pairs
-    enabled: bool,
+//! Secondary cursors for [`EditorState`](super::EditorState).
+//!
+//! The primary cursor lives in the state's own cursor and selection fields,
+//! which single-cursor code such as vim mode and readline works on. Every
+//! other cursor is a [`Selection`] kept here. Edits made with several
+//! cursors go through the state's batched editing, which applies one edit
+//! per selection from last to first and records a single undo step.
+
+use std::ops::Range;
+
+/// A cursor and the text it selects: `anchor` stays put while `head` moves
+/// when the selection is extended. A bare cursor has both at one offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Selection {
+    pub anchor: usize,
+    pub head: usize,
 }
 
-impl Default for MultiCursorManager {
-    fn default() -> Self {
-        Self::new()
+impl Selection {
+    pub fn new(anchor: usize, head: usize) -> Self {
+        Self { anchor, head }
     }
-}
 
-impl MultiCursorManager {
-    pub fn new() -> Self {
-        Self {
-            cursors: BTreeSet::new(),
-            primary_cursor: 0,
-            selection_ranges: Vec::new(),
-            enabled: true,
-        }
+    pub fn cursor(offset: usize) -> Self {
+        Self::new(offset, offset)
     }
 
-    pub fn add_cursor(&mut self, position: usize) {
-        if self.enabled {
-            self.cursors.insert(position);
-        }
+    pub fn start(&self) -> usize {
+        self.anchor.min(self.head)
     }
 
-    pub fn remove_cursor(&mut self, position: usize) {
-        self.cursors.remove(&position);
-        if self.cursors.is_empty() {
-            self.primary_cursor = position;
-        }
+    pub fn end(&self) -> usize {
+        self.anchor.max(self.head)
     }
 
-    pub fn clear_cursors(&mut self) {
-        self.cursors.clear();
-        self.selection_ranges.clear();
+    pub fn range(&self) -> Range<usize> {
+        self.start()..self.end()
     }
 
-    pub fn get_all_positions(&self) -> Vec<usize> {
-        let mut positions = vec![self.primary_cursor];
-        positions.extend(self.cursors.iter().copied());
-        positions.sort();
-        positions
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
     }
 
-    pub fn set_primary_cursor(&mut self, position: usize) {
-        self.primary_cursor = position;
+    /// The head comes before the anchor
+    pub fn is_reversed(&self) -> bool {
+        self.head < self.anchor
     }
 
-    pub fn get_primary_cursor(&self) -> usize {
-        self.primary_cursor
+    /// This selection after the bytes at `edit` were replaced by `len`
+    /// bytes. Offsets inside the replaced range move to the end of the new
+    /// text.
+    pub fn map(&self, edit: &Range<usize>, len: usize) -> Self {
+        let map = |offset: usize| {
+            if offset <= edit.start {
+                offset
+            } else if offset >= edit.end {
+                offset - edit.len() + len
+            } else {
+                edit.start + len
+            }
+        };
+        Self::new(map(self.anchor), map(self.head))
     }
+}
 
-    pub fn has_multiple_cursors(&self) -> bool {
-        !self.cursors.is_empty()
+/// Sort `selections` and merge the ones that overlap, or that touch when one
+/// of them is a bare cursor. Returns the merged selections and the index of
+/// the one the selection at `primary` ended up in.
+pub fn merge_selections(selections: Vec<Selection>, primary: usize) -> (Vec<Selection>, usize) {
+    let mut indexed: Vec<(usize, Selection)> = selections.into_iter().enumerate().collect();
+    indexed.sort_by_key(|(_, selection)| (selection.start(), selection.end()));
+
+    let mut merged: Vec<Selection> = Vec::with_capacity(indexed.len());
+    let mut merged_primary = 0;
+    for (index, selection) in indexed {
+        match merged.last_mut() {
+            Some(last)
+                if selection.start() < last.end()
+                    || (selection.start() == last.end() && (selection.is_empty() || last.is_empty())) =>
+            {
+                let (start, end) = (last.start(), last.end().max(selection.end()));
+                *last = if last.is_reversed() {
+                    Selection::new(end, start)
+                } else {
+                    Selection::new(start, end)
+                };
+            }
+            _ => merged.push(selection),
+        }
+        if index == primary {
+            merged_primary = merged.len() - 1;
+        }
     }
+    (merged, merged_primary)
+}
 
-    pub fn cursor_count(&self) -> usize {
-        self.cursors.len() + 1 // +1 for primary cursor
+/// The cursors other than the primary one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiCursorManager {
+    selections: Vec<Selection>,
+    enabled: bool,
+}
+
+impl Default for MultiCursorManager {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Add cursor at each occurrence of selected text
-    pub fn add_cursors_for_selection(&mut self, editor_state: &EditorState, selected_text: &str) {
-        if selected_text.is_empty() {
-            return;
+impl MultiCursorManager {
+    pub fn new() -> Self {
+        Self {
+            selections: Vec::new(),
+            enabled: true,
         }
+    }
 
-        let content = &editor_state.content;
-        let mut start_pos = 0;
-
-        while let Some(pos) = content[start_pos..].find(selected_text) {
-            let absolute_pos = start_pos + pos;
-            self.add_cursor(absolute_pos);
-            start_pos = absolute_pos + selected_text.len();
-        }
+    pub fn add_cursor(&mut self, position: usize) {
+        self.add_selection(Selection::cursor(position));
     }
 
-    /// Add cursor above current position
-    pub fn add_cursor_above(&mut self, editor_state: &EditorState) {
-        let current_line_start = editor_state.find_line_start(self.primary_cursor);
-        if current_line_start > 0 {
-            let prev_line_start = editor_state.find_line_start(current_line_start - 1);
-            let column_offset = self.primary_cursor - current_line_start;
-            let prev_line_end = current_line_start - 1;
-            let prev_line_length = prev_line_end - prev_line_start;
-            
-            let new_cursor_pos = prev_line_start + column_offset.min(prev_line_length);
-            self.add_cursor(new_cursor_pos);
+    pub fn add_selection(&mut self, selection: Selection) {
+        if self.enabled && !self.selections.contains(&selection) {
+            self.selections.push(selection);
         }
     }
 
-    /// Add cursor below current position
-    pub fn add_cursor_below(&mut self, editor_state: &EditorState) {
-        let current_line_start = editor_state.find_line_start(self.primary_cursor);
-        let current_line_end = self.find_line_end(editor_state, self.primary_cursor);
-        
-        if current_line_end < editor_state.content.len() {
-            let next_line_start = current_line_end + 1;
-            let column_offset = self.primary_cursor - current_line_start;
-            let next_line_end = self.find_line_end(editor_state, next_line_start);
-            let next_line_length = next_line_end - next_line_start;
-            
-            let new_cursor_pos = next_line_start + column_offset.min(next_line_length);
-            self.add_cursor(new_cursor_pos);
-        }
+    /// Remove the cursors whose head is at `position`
+    pub fn remove_cursor(&mut self, position: usize) {
+        self.selections.retain(|selection| selection.head != position);
     }
 
-    /// Apply action to all cursors
-    pub fn apply_action_to_all(&self, action: &EditorAction, editor_state: &EditorState) -> Vec<EditorAction> {
-        if !self.has_multiple_cursors() {
-            return vec![action.clone()];
-        }
+    pub fn clear_cursors(&mut self) {
+        self.selections.clear();
+    }
 
-        let mut actions = Vec::new();
-        let positions = self.get_all_positions();
-
-        match action {
-            EditorAction::Insert(text) => {
-                // Apply insertions in reverse order to maintain position accuracy
-                for &pos in positions.iter().rev() {
-                    actions.push(EditorAction::Replace(pos, 0, text.clone()));
-                }
-            },
-            
-            EditorAction::Delete(_, length) => {
-                // Apply deletions in reverse order
-                for &pos in positions.iter().rev() {
-                    actions.push(EditorAction::Delete(pos, *length));
-                }
-            },
-            
-            EditorAction::Replace(_, length, replacement) => {
-                // Apply replacements in reverse order
-                for &pos in positions.iter().rev() {
-                    actions.push(EditorAction::Replace(pos, *length, replacement.clone()));
-                }
-            },
-            
-            _ => {
-                // For other actions, apply to primary cursor only
-                actions.push(action.clone());
-            }
-        }
+    pub fn selections(&self) -> &[Selection] {
+        &self.selections
+    }
 
-        actions
+    pub fn set_selections(&mut self, selections: Vec<Selection>) {
+        self.selections = if self.enabled { selections } else { Vec::new() };
     }
 
-    /// Update cursor positions after text modification
-    pub fn update_positions_after_edit(&mut self, edit_position: usize, length_change: i32) {
-        // Update primary cursor
-        if self.primary_cursor >= edit_position {
-            if length_change >= 0 {
-                self.primary_cursor += length_change as usize;
-            } else {
-                let decrease = (-length_change) as usize;
-                if self.primary_cursor >= edit_position + decrease {
-                    self.primary_cursor -= decrease;
-                } else {
-                    self.primary_cursor = edit_position;
-                }
-            }
-        }
+    /// Head offsets of the secondary cursors, in document order
+    pub fn positions(&self) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.selections.iter().map(|selection| selection.head).collect();
+        positions.sort_unstable();
+        positions
+    }
 
-        // Update all other cursors
-        let mut updated_cursors = BTreeSet::new();
-        for &cursor in &self.cursors {
-            let updated_pos = if cursor >= edit_position {
-                if length_change >= 0 {
-                    cursor + length_change as usize
-                } else {
-                    let decrease = (-length_change) as usize;
-                    if cursor >= edit_position + decrease {
-                        cursor - decrease
-                    } else {
-                        edit_position
-                    }
-                }
-            } else {
-                cursor
-            };
-            updated_cursors.insert(updated_pos);
-        }
-        self.cursors = updated_cursors;
+    pub fn has_multiple_cursors(&self) -> bool {
+        !self.selections.is_empty()
     }
 
-    /// Select word at each cursor position
-    pub fn select_word_at_cursors(&mut self, editor_state: &EditorState) -> Vec<EditorAction> {
-        let mut actions = Vec::new();
-        
-        for &cursor_pos in &self.get_all_positions() {
-            let word_start = self.find_word_start_at(editor_state, cursor_pos);
-            let word_end = self.find_word_end_at(editor_state, cursor_pos);
-            
-            if word_start < word_end {
-                self.selection_ranges.push((word_start, word_end));
-                actions.push(EditorAction::SetSelection(word_start, word_end));
-            }
-        }
-        
-        actions
+    pub fn cursor_count(&self) -> usize {
+        self.selections.len() + 1 // +1 for primary cursor
     }
 
-    /// Find next occurrence of word under cursor and add cursor there
-    pub fn find_and_add_next_occurrence(&mut self, editor_state: &EditorState) -> Option<EditorAction> {
-        let word_start = self.find_word_start_at(editor_state, self.primary_cursor);
-        let word_end = self.find_word_end_at(editor_state, self.primary_cursor);
-        
-        if word_start >= word_end {
-            return None;
-        }
-        
-        let word = &editor_state.content[word_start..word_end];
-        let search_start = word_end;
-        
-        if let Some(next_pos) = editor_state.content[search_start..].find(word) {
-            let absolute_pos = search_start + next_pos;
-            self.add_cursor(absolute_pos);
-            Some(EditorAction::MoveCursor(absolute_pos))
-        } else {
-            None
+    /// Keep the cursors on the same text after `edit` was replaced by `len`
+    /// bytes somewhere else
+    pub fn map_through_edit(&mut self, edit: Range<usize>, len: usize) {
+        for selection in &mut self.selections {
+            *selection = selection.map(&edit, len);
         }
     }
 
@@ -240,50 +177,6 @@ impl MultiCursorManager {
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
-
-    // Helper methods
-    
-    fn find_line_end(&self, editor_state: &EditorState, pos: usize) -> usize {
-        editor_state.content[pos..].find('\n').map_or(editor_state.content.len(), |i| pos + i)
-    }
-
-    fn find_word_start_at(&self, editor_state: &EditorState, pos: usize) -> usize {
-        let content = &editor_state.content;
-        let mut start = pos;
-        
-        while start > 0 {
-            let ch = content.chars().nth(start - 1);
-            if let Some(c) = ch {
-                if c.is_alphanumeric() || c == '_' {
-                    start -= 1;
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
-        }
-        
-        start
-    }
-
-    fn find_word_end_at(&self, editor_state: &EditorState, pos: usize) -> usize {
-        let content = &editor_state.content;
-        let chars: Vec<char> = content.chars().collect();
-        let mut end = pos;
-        
-        while end < chars.len() {
-            let ch = chars[end];
-            if ch.is_alphanumeric() || ch == '_' {
-                end += 1;
-            } else {
-                break;
-            }
-        }
-        
-        // Convert back to byte position
-        content.char_indices().nth(end).map_or(content.len(), |(i, _)| i)
-    }
 }
 
 #[cfg(test)]
@@ -295,7 +188,7 @@ mod tests {
         let mut manager = MultiCursorManager::new();
         assert!(!manager.has_multiple_cursors());
         assert_eq!(manager.cursor_count(), 1);
-        
+
         manager.add_cursor(10);
         assert!(manager.has_multiple_cursors());
         assert_eq!(manager.cursor_count(), 2);
@@ -304,26 +197,49 @@ mod tests {
     #[test]
     fn test_cursor_positions() {
         let mut manager = MultiCursorManager::new();
-        manager.set_primary_cursor(5);
-        manager.add_cursor(10);
         manager.add_cursor(15);
-        
-        let positions = manager.get_all_positions();
-        assert_eq!(positions, vec![5, 10, 15]);
+        manager.add_cursor(5);
+        manager.add_cursor(10);
+        manager.add_cursor(10);
+
+        assert_eq!(manager.positions(), vec![5, 10, 15]);
+        manager.remove_cursor(10);
+        assert_eq!(manager.positions(), vec![5, 15]);
     }
 
     #[test]
     fn test_position_updates_after_edit() {
         let mut manager = MultiCursorManager::new();
-        manager.set_primary_cursor(10);
+        manager.add_cursor(10);
         manager.add_cursor(20);
-        manager.add_cursor(30);
-        
-        // Insert 5 characters at position 5
-        manager.update_positions_after_edit(5, 5);
-        
-        let positions = manager.get_all_positions();
-        assert_eq!(positions, vec![15, 25, 35]);
+        manager.add_selection(Selection::new(30, 25));
+
+        // Insert 5 bytes at position 5
+        manager.map_through_edit(5..5, 5);
+        assert_eq!(manager.positions(), vec![15, 25, 30]);
+
+        // Replace 12..28 with 2 bytes: the cursor inside lands after them
+        manager.map_through_edit(12..28, 2);
+        assert_eq!(manager.selections(), [Selection::cursor(14), Selection::cursor(14), Selection::new(21, 16)]);
+    }
+
+    #[test]
+    fn test_merge_selections() {
+        let selections = vec![
+            Selection::new(8, 4),
+            Selection::cursor(12),
+            Selection::new(6, 10),
+            Selection::cursor(12),
+            Selection::new(12, 14),
+            Selection::cursor(0),
+        ];
+        let (merged, primary) = merge_selections(selections, 2);
+        assert_eq!(merged, [Selection::cursor(0), Selection::new(10, 4), Selection::new(12, 14)]);
+        assert_eq!(primary, 1, "the primary is in the selection it merged into");
+
+        // Selections that only touch stay apart
+        let (merged, _) = merge_selections(vec![Selection::new(0, 2), Selection::new(2, 4)], 0);
+        assert_eq!(merged.len(), 2);
     }
 
     #[test]
@@ -331,10 +247,14 @@ mod tests {
         let mut manager = MultiCursorManager::new();
         manager.add_cursor(10);
         manager.add_cursor(20);
-        
+
         assert!(manager.has_multiple_cursors());
-        
+
         manager.clear_cursors();
         assert!(!manager.has_multiple_cursors());
+
+        manager.disable();
+        manager.add_cursor(10);
+        assert!(!manager.has_multiple_cursors());
     }
 }
@@ -0,0 +1,810 @@
+//! Completions from the user's own shell.
+//!
+//! Many tools have no completion spec but ship completions for bash, zsh or
+//! fish, and teams often write their own for internal CLIs. The
+//! [`NativeCompletionBridge`] keeps a sidecar shell running with the user's
+//! rc files loaded and asks it for completions:
+//!
+//! - bash: the function or options registered with `complete`, loading them
+//!   on demand through bash-completion's `_completion_loader`
+//! - zsh: the real completion system, driven through a `zpty` pseudo terminal
+//!   with `compadd` hooked to report matches
+//! - fish: `complete --do-complete`
+//!
+//! Requests and responses are line based. A request is the request id, the
+//! working directory, the cursor position, the index of the current word, the
+//! command line and its words, separated by `\x1f`. The sidecar answers with
+//! `<id>\x1f<candidate>[\t<description>]` lines followed by `<id>\x1e`, so
+//! anything else the user's rc files print is ignored. Once the rc files are
+//! sourced the sidecar prints `0\x1e` to say it is ready for requests.
+
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::shell::SupportedShell;
+use crate::syntax_tree::{parse_shell, ShellDialect};
+use crate::utils::process::kill_process_group;
+
+const FIELD_SEPARATOR: char = '\x1f';
+const END_OF_RESPONSE: char = '\x1e';
+
+/// How long to wait for the sidecar before giving up on a request.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(750);
+
+/// How long the sidecar may take to start and source the rc files.
+const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Requests in a row that may time out before the sidecar is restarted.
+const MAX_CONSECUTIVE_TIMEOUTS: u32 = 3;
+
+/// Request ids start at 1; the ready line uses 0.
+const READY_ID: u64 = 0;
+
+const BASH_DRIVER: &str = r#"
+[[ -r /usr/share/bash-completion/bash_completion ]] &&
+    . /usr/share/bash-completion/bash_completion >/dev/null 2>&1
+for __warp_rc in "$@"; do
+    [[ -r $__warp_rc ]] && . "$__warp_rc" >/dev/null 2>&1
+done
+
+__warp_complete() {
+    local cmd=${COMP_WORDS[0]} cur=${COMP_WORDS[COMP_CWORD]} prev= spec
+    (( COMP_CWORD > 0 )) && prev=${COMP_WORDS[COMP_CWORD-1]}
+    COMPREPLY=()
+    spec=$(complete -p -- "$cmd" 2>/dev/null)
+    if [[ -z $spec ]] && declare -F _completion_loader >/dev/null; then
+        _completion_loader "$cmd" >/dev/null 2>&1
+        spec=$(complete -p -- "$cmd" 2>/dev/null)
+    fi
+    [[ -z $spec ]] && return
+    if [[ $spec =~ \ -F\ ([^ ]+) ]]; then
+        "${BASH_REMATCH[1]}" "$cmd" "$cur" "$prev" >/dev/null 2>&1
+    else
+        spec=${spec% *}
+        mapfile -t COMPREPLY < <(eval "compgen ${spec#complete } -- \"\$cur\"" 2>/dev/null)
+    fi
+}
+
+printf '0\x1e\n'
+while IFS= read -r __warp_request; do
+    IFS=$'\x1f' read -r -a __warp_fields <<< "$__warp_request"
+    builtin cd -- "${__warp_fields[1]}" 2>/dev/null
+    COMP_POINT=${__warp_fields[2]}
+    COMP_CWORD=${__warp_fields[3]}
+    COMP_LINE=${__warp_fields[4]}
+    COMP_WORDS=("${__warp_fields[@]:5}")
+    COMP_TYPE=9
+    COMP_KEY=9
+    __warp_complete
+    for __warp_candidate in "${COMPREPLY[@]}"; do
+        printf '%s\x1f%s\n' "${__warp_fields[0]}" "$__warp_candidate"
+    done
+    printf '%s\x1e\n' "${__warp_fields[0]}"
+done
+"#;
+
+const ZSH_DRIVER: &str = r#"
+zmodload zsh/zpty || exit 1
+zpty __warp zsh -f -i
+zpty -w __warp 'PROMPT= RPROMPT= PS2='
+zpty -w __warp 'autoload -U compinit && compinit -u -d "${TMPDIR:-/tmp}/.warp-zcompdump-$UID"'
+for __warp_rc in "$@"; do
+    zpty -w __warp "source ${(q)__warp_rc} >/dev/null 2>&1"
+done
+zpty -w __warp "bindkey '^I' complete-word"
+zpty -w __warp "zstyle ':completion:*' list-grouped false"
+zpty -w __warp "zstyle ':completion:*' insert-tab false"
+zpty -w __warp "zstyle ':completion:*' list-separator ''"
+zpty -w __warp '__warp_null_line() { print -r -- $'"'"'\0'"'"' }'
+zpty -w __warp 'compprefuncs=(__warp_null_line); comppostfuncs=(__warp_null_line)'
+zpty -w __warp 'compadd() {
+    if [[ ${@[1,(i)(-|--)]} == *-(O|A|D)\ * ]]; then
+        builtin compadd "$@"
+        return $?
+    fi
+    typeset -a __hits __dscr
+    if (( $@[(I)-d] )); then
+        local __d=${@[$[${@[(i)-d]}+1]]}
+        if [[ $__d == \(* ]]; then eval "__dscr=$__d"; else __dscr=( "${(@P)__d}" ); fi
+    fi
+    builtin compadd -A __hits -D __dscr "$@"
+    local __i
+    for (( __i = 1; __i <= $#__hits; __i++ )); do
+        print -r -- "$IPREFIX$__hits[__i]"$'"'"'\t'"'"'"${${${__dscr[__i]}##$__hits[__i] #}#-- }"
+    done
+}'
+# The echo of this line does not match the pattern, only its output does
+zpty -w __warp 'print -r -- __warp_ready_$((6 * 7))'
+zpty -r __warp __warp_line '*__warp_ready_42*' || exit 1
+print -r -- 0$'\x1e'
+
+while IFS= read -r __warp_request; do
+    __warp_fields=("${(@ps:\x1f:)__warp_request}")
+    zpty -w __warp "builtin cd -- ${(q)__warp_fields[2]} 2>/dev/null"
+    zpty -w -n __warp "${__warp_fields[5]}"$'\t'
+    __warp_nulls=0
+    while (( __warp_nulls < 2 )) && zpty -r __warp __warp_line; do
+        if [[ $__warp_line == *$'\0'* ]]; then
+            (( __warp_nulls++ ))
+        elif (( __warp_nulls == 1 )); then
+            __warp_line=${__warp_line%%$'\r'*}
+            [[ -n ${__warp_line%%$'\t'*} ]] &&
+                print -r -- "${__warp_fields[1]}"$'\x1f'"$__warp_line"
+        fi
+    done
+    # Clear the line buffer for the next request
+    zpty -w -n __warp $'\x15'
+    print -r -- "${__warp_fields[1]}"$'\x1e'
+done
+"#;
+
+const FISH_DRIVER: &str = r#"
+for rc in $argv
+    source $rc >/dev/null 2>&1
+end
+printf '0\x1e\n'
+while read -l request
+    set -l fields (string split \x1f -- $request)
+    builtin cd $fields[2] 2>/dev/null
+    for candidate in (complete --do-complete=$fields[5])
+        printf '%s\x1f%s\n' $fields[1] $candidate
+    end
+    printf '%s\x1e\n' $fields[1]
+end
+"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NativeCompletionError {
+    #[error("Native completions are not supported for {0}")]
+    UnsupportedShell(String),
+
+    #[error("Failed to start completion sidecar: {0}")]
+    Spawn(std::io::Error),
+
+    #[error("Completion sidecar I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Completion sidecar did not answer within {0:?}")]
+    Timeout(Duration),
+
+    #[error("Completion sidecar is still starting")]
+    Starting,
+
+    #[error("Completion sidecar did not start within {0:?}")]
+    StartupTimeout(Duration),
+
+    #[error("Completion sidecar exited")]
+    Exited,
+}
+
+/// A shell that can provide completions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NativeShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl NativeShell {
+    /// The shell named by `$SHELL`, if it is supported.
+    pub fn detect() -> Option<Self> {
+        std::env::var("SHELL")
+            .ok()
+            .and_then(|shell| Self::from_dialect(ShellDialect::from_shell_path(&shell)))
+    }
+
+    pub fn from_dialect(dialect: ShellDialect) -> Option<Self> {
+        match dialect {
+            ShellDialect::Bash => Some(NativeShell::Bash),
+            ShellDialect::Zsh => Some(NativeShell::Zsh),
+            ShellDialect::Fish => Some(NativeShell::Fish),
+            ShellDialect::Posix => None,
+        }
+    }
+
+    pub fn program(&self) -> &'static str {
+        match self {
+            NativeShell::Bash => "bash",
+            NativeShell::Zsh => "zsh",
+            NativeShell::Fish => "fish",
+        }
+    }
+
+    /// The rc files that usually register the user's completions. Fish
+    /// autoloads completions, so none are needed there.
+    pub fn default_init_files(&self) -> Vec<PathBuf> {
+        let Some(home) = dirs::home_dir() else {
+            return Vec::new();
+        };
+        match self {
+            NativeShell::Bash => vec![home.join(".bashrc")],
+            NativeShell::Zsh => vec![home.join(".zshrc")],
+            NativeShell::Fish => Vec::new(),
+        }
+    }
+
+    fn dialect(&self) -> ShellDialect {
+        match self {
+            NativeShell::Bash => ShellDialect::Bash,
+            NativeShell::Zsh => ShellDialect::Zsh,
+            NativeShell::Fish => ShellDialect::Fish,
+        }
+    }
+
+    fn command(&self, init_files: &[PathBuf]) -> Command {
+        let mut command = Command::new(self.program());
+        match self {
+            // Interactive so rc files that return early for non-interactive
+            // shells still register their completions; no job control so the
+            // sidecar never takes over the terminal.
+            NativeShell::Bash => {
+                command.args(["--noprofile", "--norc", "+m", "-i", "-c", BASH_DRIVER, "bash"]);
+            }
+            NativeShell::Zsh => {
+                command.args(["-f", "-c", ZSH_DRIVER, "zsh"]);
+            }
+            NativeShell::Fish => {
+                command.args(["-c", FISH_DRIVER]);
+            }
+        }
+        command.args(init_files);
+        command
+    }
+}
+
+impl TryFrom<&SupportedShell> for NativeShell {
+    type Error = NativeCompletionError;
+
+    fn try_from(shell: &SupportedShell) -> Result<Self, Self::Error> {
+        match shell {
+            SupportedShell::Bash | SupportedShell::GitBash | SupportedShell::Wsl2 => {
+                Ok(NativeShell::Bash)
+            }
+            SupportedShell::Zsh => Ok(NativeShell::Zsh),
+            SupportedShell::Fish => Ok(NativeShell::Fish),
+            other => Err(NativeCompletionError::UnsupportedShell(format!("{:?}", other))),
+        }
+    }
+}
+
+/// A candidate reported by the shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeCandidate {
+    pub text: String,
+    pub description: Option<String>,
+}
+
+/// Candidates for the word that starts at `replace_start`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NativeCompletions {
+    pub replace_start: usize,
+    pub items: Vec<NativeCandidate>,
+}
+
+/// What is sent to the sidecar for one completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NativeRequest {
+    /// The command being completed, up to the cursor.
+    line: String,
+    /// Raw words of the command; the last one may be empty.
+    words: Vec<String>,
+    /// Index of the word under the cursor.
+    cword: usize,
+    /// Byte offset in the original input where the current word starts.
+    replace_start: usize,
+}
+
+impl NativeRequest {
+    /// Build a request for the command under `cursor`. Returns `None` while
+    /// the command name itself is being typed or outside any command.
+    fn new(input: &str, cursor: usize, dialect: ShellDialect) -> Option<Self> {
+        let cursor = cursor.min(input.len());
+        let prefix = &input[..cursor];
+        let command = parse_shell(prefix, dialect).command_at_cursor(cursor)?;
+        // Assignments and keywords before the name are not part of the words
+        let start = command.name.as_ref()?.span.start;
+
+        let mut words: Vec<(usize, String)> = command
+            .words()
+            .into_iter()
+            .map(|word| (word.span.start, word.raw.clone()))
+            .collect();
+        let continues_word = command.words().last().is_some_and(|w| w.span.end == cursor);
+        if !continues_word {
+            words.push((cursor, String::new()));
+        }
+        if words.len() < 2 {
+            return None;
+        }
+
+        let (replace_start, _) = words[words.len() - 1];
+        Some(Self {
+            line: prefix[start..].replace('\n', " "),
+            cword: words.len() - 1,
+            words: words.into_iter().map(|(_, word)| word).collect(),
+            replace_start,
+        })
+    }
+
+    fn encode(&self, id: u64, cwd: &Path) -> String {
+        let mut fields = vec![
+            id.to_string(),
+            cwd.display().to_string(),
+            self.line.len().to_string(),
+            self.cword.to_string(),
+            self.line.clone(),
+        ];
+        fields.extend(self.words.iter().cloned());
+        let mut request: String = fields
+            .iter()
+            .map(|field| field.replace(['\n', FIELD_SEPARATOR], " "))
+            .collect::<Vec<_>>()
+            .join(&FIELD_SEPARATOR.to_string());
+        // Keeps a trailing empty word from being dropped by `read -a`
+        request.push(FIELD_SEPARATOR);
+        request.push('\n');
+        request
+    }
+}
+
+struct Sidecar {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    started: Instant,
+    /// Set once the ready line has been read
+    ready: bool,
+    /// Requests in a row that timed out
+    timeouts: u32,
+}
+
+impl Sidecar {
+    fn spawn(shell: NativeShell, init_files: &[PathBuf]) -> Result<Self, NativeCompletionError> {
+        let mut command = shell.command(init_files);
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        let mut child = command.spawn().map_err(NativeCompletionError::Spawn)?;
+        let stdin = child.stdin.take().ok_or(NativeCompletionError::Exited)?;
+        let stdout = child.stdout.take().ok_or(NativeCompletionError::Exited)?;
+
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            started: Instant::now(),
+            ready: false,
+            timeouts: 0,
+        })
+    }
+
+    /// Wait up to `timeout` for the ready line. Returns
+    /// [`NativeCompletionError::Starting`] while the shell is still sourcing
+    /// the rc files and it has not used up `startup_timeout` yet.
+    fn wait_until_ready(&mut self, timeout: Duration, startup_timeout: Duration) -> Result<(), NativeCompletionError> {
+        if self.ready {
+            return Ok(());
+        }
+
+        let ready_line = format!("{}{}", READY_ID, END_OF_RESPONSE);
+        let startup_deadline = self.started + startup_timeout;
+        let deadline = (Instant::now() + timeout).min(startup_deadline);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) if line == ready_line => {
+                    self.ready = true;
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) if Instant::now() >= startup_deadline => {
+                    return Err(NativeCompletionError::StartupTimeout(startup_timeout))
+                }
+                Err(RecvTimeoutError::Timeout) => return Err(NativeCompletionError::Starting),
+                Err(RecvTimeoutError::Disconnected) => return Err(NativeCompletionError::Exited),
+            }
+        }
+    }
+
+    fn request(
+        &mut self,
+        id: u64,
+        request: &str,
+        timeout: Duration,
+    ) -> Result<Vec<NativeCandidate>, NativeCompletionError> {
+        // Drop anything left over from a request that timed out
+        while self.lines.try_recv().is_ok() {}
+
+        self.stdin.write_all(request.as_bytes())?;
+        self.stdin.flush()?;
+
+        let prefix = format!("{}{}", id, FIELD_SEPARATOR);
+        let end = format!("{}{}", id, END_OF_RESPONSE);
+        let deadline = Instant::now() + timeout;
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(remaining) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(NativeCompletionError::Timeout(timeout)),
+                Err(RecvTimeoutError::Disconnected) => return Err(NativeCompletionError::Exited),
+            };
+            if line == end {
+                return Ok(items);
+            }
+            let Some(candidate) = line.strip_prefix(&prefix) else {
+                continue;
+            };
+            let (text, description) = match candidate.split_once('\t') {
+                Some((text, description)) => (text, Some(description.trim())),
+                None => (candidate, None),
+            };
+            let text = text.trim_end();
+            if !text.is_empty() && seen.insert(text.to_string()) {
+                items.push(NativeCandidate {
+                    text: text.to_string(),
+                    description: description.filter(|d| !d.is_empty()).map(str::to_string),
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Sidecar {
+    fn drop(&mut self) {
+        // The shell runs completion helpers of its own; they go with it
+        kill_process_group(&mut self.child);
+    }
+}
+
+/// Asks the user's shell for completions through a long running sidecar.
+///
+/// The sidecar is started on first use. While it sources the rc files,
+/// requests wait for it only up to their own timeout and then fail with
+/// [`NativeCompletionError::Starting`]; the startup itself has a separate,
+/// longer budget. A request that times out leaves the sidecar running, and
+/// it is restarted only after it exits or keeps timing out. Clones share the
+/// same sidecar.
+#[derive(Clone)]
+pub struct NativeCompletionBridge {
+    shell: NativeShell,
+    init_files: Vec<PathBuf>,
+    timeout: Duration,
+    startup_timeout: Duration,
+    sidecar: Arc<Mutex<Option<Sidecar>>>,
+    next_id: Arc<AtomicU64>,
+    /// Set when the shell cannot be started at all, so that typing does not
+    /// keep trying to spawn it.
+    unavailable: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for NativeCompletionBridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeCompletionBridge")
+            .field("shell", &self.shell)
+            .field("init_files", &self.init_files)
+            .field("timeout", &self.timeout)
+            .field("startup_timeout", &self.startup_timeout)
+            .finish()
+    }
+}
+
+impl NativeCompletionBridge {
+    pub fn new(shell: NativeShell) -> Self {
+        Self {
+            shell,
+            init_files: shell.default_init_files(),
+            timeout: DEFAULT_TIMEOUT,
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+            sidecar: Arc::new(Mutex::new(None)),
+            next_id: Arc::new(AtomicU64::new(1)),
+            unavailable: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// A bridge for the shell named by `$SHELL`, if it is supported.
+    pub fn detect() -> Option<Self> {
+        NativeShell::detect().map(Self::new)
+    }
+
+    /// Replace the rc files sourced when the sidecar starts.
+    pub fn with_init_files(mut self, init_files: Vec<PathBuf>) -> Self {
+        self.init_files = init_files;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How long the sidecar may take to become ready before the shell is
+    /// given up on.
+    pub fn with_startup_timeout(mut self, startup_timeout: Duration) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+
+    pub fn shell(&self) -> NativeShell {
+        self.shell
+    }
+
+    /// Complete the word at byte offset `cursor` of `input`, running the
+    /// shell's completion in `cwd`. Completing the command name itself is
+    /// left to the other sources and returns no items.
+    pub fn complete(
+        &self,
+        input: &str,
+        cursor: usize,
+        cwd: &Path,
+    ) -> Result<NativeCompletions, NativeCompletionError> {
+        let Some(request) = NativeRequest::new(input, cursor, self.shell.dialect()) else {
+            return Ok(NativeCompletions {
+                replace_start: cursor.min(input.len()),
+                items: Vec::new(),
+            });
+        };
+        if self.unavailable.load(Ordering::Relaxed) {
+            return Err(NativeCompletionError::UnsupportedShell(
+                self.shell.program().to_string(),
+            ));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let encoded = request.encode(id, cwd);
+
+        let mut guard = self.sidecar.lock().unwrap_or_else(|e| e.into_inner());
+        if guard.is_none() {
+            match Sidecar::spawn(self.shell, &self.init_files) {
+                Ok(sidecar) => *guard = Some(sidecar),
+                Err(e) => {
+                    self.unavailable.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+
+        let sidecar = guard.as_mut().expect("sidecar was just started");
+        let result = sidecar
+            .wait_until_ready(self.timeout, self.startup_timeout)
+            .and_then(|()| sidecar.request(id, &encoded, self.timeout));
+        let started = sidecar.ready;
+        match result {
+            Ok(items) => {
+                sidecar.timeouts = 0;
+                Ok(NativeCompletions {
+                    replace_start: request.replace_start,
+                    items,
+                })
+            }
+            Err(NativeCompletionError::Starting) => Err(NativeCompletionError::Starting),
+            Err(NativeCompletionError::Timeout(timeout)) => {
+                sidecar.timeouts += 1;
+                if sidecar.timeouts >= MAX_CONSECUTIVE_TIMEOUTS {
+                    tracing::debug!(
+                        "Restarting {} completion sidecar after {} timeouts",
+                        self.shell.program(),
+                        sidecar.timeouts
+                    );
+                    *guard = None;
+                }
+                Err(NativeCompletionError::Timeout(timeout))
+            }
+            Err(e) if !started => {
+                // The rc files hang or exit the shell; starting it again on
+                // every keystroke would not help
+                tracing::warn!("{} completion sidecar failed to start: {}", self.shell.program(), e);
+                self.unavailable.store(true, Ordering::Relaxed);
+                *guard = None;
+                Err(e)
+            }
+            Err(e) => {
+                tracing::debug!("Restarting {} completion sidecar: {}", self.shell.program(), e);
+                *guard = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn bash_bridge(rc: &str) -> Option<(NativeCompletionBridge, tempfile::TempDir)> {
+        which::which("bash").ok()?;
+        let dir = tempfile::tempdir().unwrap();
+        let rc_path = dir.path().join("rc.bash");
+        fs::write(&rc_path, rc).unwrap();
+        let bridge = NativeCompletionBridge::new(NativeShell::Bash)
+            .with_init_files(vec![rc_path])
+            .with_timeout(Duration::from_secs(10));
+        Some((bridge, dir))
+    }
+
+    fn texts(completions: &NativeCompletions) -> Vec<&str> {
+        completions.items.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_request_words_and_current_word() {
+        let request = NativeRequest::new("cd /tmp && FOO=1 deploy --env pr", 32, ShellDialect::Bash).unwrap();
+        assert_eq!(request.words, vec!["deploy", "--env", "pr"]);
+        assert_eq!(request.cword, 2);
+        assert_eq!(request.replace_start, 30);
+        assert_eq!(request.line, "deploy --env pr");
+
+        let request = NativeRequest::new("deploy 'a b' ", 13, ShellDialect::Bash).unwrap();
+        assert_eq!(request.words, vec!["deploy", "'a b'", ""]);
+        assert_eq!(request.cword, 2);
+        assert_eq!(request.replace_start, 13);
+
+        assert!(NativeRequest::new("depl", 4, ShellDialect::Bash).is_none());
+    }
+
+    #[test]
+    fn test_request_encoding_keeps_empty_last_word() {
+        let request = NativeRequest::new("tool ", 5, ShellDialect::Bash).unwrap();
+        let encoded = request.encode(7, Path::new("/tmp"));
+        assert_eq!(encoded, "7\x1f/tmp\x1f5\x1f1\x1ftool \x1ftool\x1f\x1f\n");
+    }
+
+    #[test]
+    fn test_bash_function_completion() {
+        let Some((bridge, dir)) = bash_bridge(
+            r#"
+_warp_test_tool() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    if (( COMP_CWORD == 1 )); then
+        COMPREPLY=($(compgen -W "deploy destroy status" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -W "--env --dry-run" -- "$cur"))
+    fi
+}
+complete -F _warp_test_tool warp-test-tool
+echo "rc output is ignored"
+"#,
+        ) else {
+            return;
+        };
+
+        let completions = bridge.complete("warp-test-tool de", 17, dir.path()).unwrap();
+        assert_eq!(texts(&completions), vec!["deploy", "destroy"]);
+        assert_eq!(completions.replace_start, 15);
+
+        // The sidecar is reused for the next request
+        let completions = bridge.complete("warp-test-tool deploy --", 24, dir.path()).unwrap();
+        assert_eq!(texts(&completions), vec!["--env", "--dry-run"]);
+    }
+
+    #[test]
+    fn test_bash_wordlist_completion() {
+        let Some((bridge, dir)) = bash_bridge("complete -W 'alpha beta' warp-test-words") else {
+            return;
+        };
+        let completions = bridge.complete("warp-test-words ", 16, dir.path()).unwrap();
+        assert_eq!(texts(&completions), vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn test_command_without_completion() {
+        let Some((bridge, dir)) = bash_bridge("") else {
+            return;
+        };
+        let completions = bridge.complete("warp-no-such-tool x", 19, dir.path()).unwrap();
+        assert!(completions.items.is_empty());
+    }
+
+    #[test]
+    fn test_slow_startup_does_not_use_up_requests() {
+        let Some((bridge, dir)) = bash_bridge("sleep 1\ncomplete -W 'alpha beta' warp-test-words") else {
+            return;
+        };
+        let bridge = bridge.with_timeout(Duration::from_millis(100));
+        assert!(matches!(
+            bridge.complete("warp-test-words ", 16, dir.path()),
+            Err(NativeCompletionError::Starting)
+        ));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let completions = loop {
+            match bridge.complete("warp-test-words ", 16, dir.path()) {
+                Err(NativeCompletionError::Starting) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(100))
+                }
+                result => break result.unwrap(),
+            }
+        };
+        assert_eq!(texts(&completions), vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn test_startup_timeout_gives_up_on_the_shell() {
+        let Some((bridge, dir)) = bash_bridge("sleep 5") else {
+            return;
+        };
+        let bridge = bridge
+            .with_timeout(Duration::from_millis(500))
+            .with_startup_timeout(Duration::from_millis(200));
+        assert!(matches!(
+            bridge.complete("tool x", 6, dir.path()),
+            Err(NativeCompletionError::StartupTimeout(_))
+        ));
+        assert!(matches!(
+            bridge.complete("tool x", 6, dir.path()),
+            Err(NativeCompletionError::UnsupportedShell(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_timeout_keeps_the_sidecar() {
+        let Some((bridge, dir)) = bash_bridge(
+            r#"
+_warp_slow_tool() {
+    [[ ${COMP_WORDS[1]} == slow ]] && sleep 1
+    COMPREPLY=(done)
+}
+complete -F _warp_slow_tool warp-slow-tool
+"#,
+        ) else {
+            return;
+        };
+        // Wait for the rc files with the long timeout, then shorten it
+        bridge.complete("warp-slow-tool x", 16, dir.path()).unwrap();
+        let sidecar_pid = |bridge: &NativeCompletionBridge| bridge.sidecar.lock().unwrap().as_ref().map(|s| s.child.id());
+        let pid = sidecar_pid(&bridge);
+        let bridge = bridge.with_timeout(Duration::from_millis(200));
+        assert!(matches!(
+            bridge.complete("warp-slow-tool slow", 19, dir.path()),
+            Err(NativeCompletionError::Timeout(_))
+        ));
+
+        // The same sidecar answers once it is done with the slow request
+        std::thread::sleep(Duration::from_secs(1));
+        let completions = bridge.complete("warp-slow-tool x", 16, dir.path()).unwrap();
+        assert_eq!(texts(&completions), vec!["done"]);
+        assert_eq!(sidecar_pid(&bridge), pid);
+    }
+
+    #[test]
+    fn test_missing_shell_is_reported_once() {
+        let bridge = NativeCompletionBridge::new(NativeShell::Fish).with_init_files(Vec::new());
+        if which::which("fish").is_ok() {
+            return;
+        }
+        assert!(matches!(
+            bridge.complete("tool x", 6, Path::new(".")),
+            Err(NativeCompletionError::Spawn(_))
+        ));
+        assert!(matches!(
+            bridge.complete("tool x", 6, Path::new(".")),
+            Err(NativeCompletionError::UnsupportedShell(_))
+        ));
+    }
+}
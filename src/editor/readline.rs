@@ -0,0 +1,838 @@
+//! Readline (emacs-style) editing for the input editor.
+//!
+//! [`Readline`] runs the readline commands bash and zsh users rely on
+//! directly on an [`EditorState`]: the kill ring with `C-y` and `M-y`,
+//! `C-t`/`M-t` transposition, `M-u`/`M-l`/`M-c` case changes, numeric
+//! arguments (`M-3 C-d`, `M-- M-u`), `C-x C-e` and `C-_` undo.
+//!
+//! Bindings start from [`READLINE_BINDINGS`]; the keyset and user layers of
+//! a [`Keymap`] go on top, so `keysets/emacs.yaml` or `user.yaml` can rebind
+//! any of them. Each command is one undo step, except that a run of typed
+//! characters is undone together.
+//!
+//! [`TextInputHandler`](super::text_input::TextInputHandler) hosts it. The
+//! terminal's command input goes through it while the emacs keyset is
+//! active and vim keys are off.
+
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::Path;
+use std::process::Command;
+
+use crate::editor::vim_motion::{char_at, line_end, line_start, next_char, prev_char};
+use crate::editor::EditorState;
+use crate::input::keymap::{BindingLayer, BindingSource, KeyContext, Keymap, Keystroke, Lookup};
+
+/// Readline's emacs-mode defaults, as (action, keys)
+pub const READLINE_BINDINGS: &[(&str, &str)] = &[
+    ("editor:move_to_line_start", "ctrl-a"),
+    ("editor:move_to_line_start", "home"),
+    ("editor:move_to_line_end", "ctrl-e"),
+    ("editor:move_to_line_end", "end"),
+    ("editor:backward_char", "ctrl-b"),
+    ("editor:backward_char", "left"),
+    ("editor:forward_char", "ctrl-f"),
+    ("editor:forward_char", "right"),
+    ("editor:move_to_previous_word", "alt-b"),
+    ("editor:move_to_previous_word", "ctrl-left"),
+    ("editor:move_to_next_word", "alt-f"),
+    ("editor:move_to_next_word", "ctrl-right"),
+    ("editor:delete_char", "ctrl-d"),
+    ("editor:delete_char", "delete"),
+    ("editor:backward_delete_char", "backspace"),
+    ("editor:backward_delete_char", "ctrl-h"),
+    ("editor:kill_line", "ctrl-k"),
+    ("editor:unix_line_discard", "ctrl-u"),
+    ("editor:kill_word", "alt-d"),
+    ("editor:backward_kill_word", "alt-backspace"),
+    ("editor:unix_word_rubout", "ctrl-w"),
+    ("editor:yank", "ctrl-y"),
+    ("editor:yank_pop", "alt-y"),
+    ("editor:transpose_chars", "ctrl-t"),
+    ("editor:transpose_words", "alt-t"),
+    ("editor:upcase_word", "alt-u"),
+    ("editor:downcase_word", "alt-l"),
+    ("editor:capitalize_word", "alt-c"),
+    ("editor:undo", "ctrl-_"),
+    ("editor:undo", "ctrl-x ctrl-u"),
+    ("editor:edit_in_external_editor", "ctrl-x ctrl-e"),
+];
+
+/// Entries kept by the kill ring, as emacs' `kill-ring-max`.
+pub const KILL_RING_MAX: usize = 60;
+
+/// Largest numeric argument, as in readline.
+const MAX_ARGUMENT: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadlineOutcome {
+    /// The key was a readline command or typed text
+    Handled,
+    /// Not a readline key; the host should handle it
+    Unhandled,
+    /// `C-x C-e`: the host should await [`edit_in_external_editor`] off the
+    /// UI thread and pass the result to [`Readline::finish_external_edit`]
+    EditInExternalEditor,
+}
+
+/// Killed text, newest first. Consecutive kills grow the newest entry.
+#[derive(Debug, Clone, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    yank_index: usize,
+}
+
+impl KillRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record killed text. With `append`, it joins the newest entry: after
+    /// it, or before it for a backward kill.
+    pub fn kill(&mut self, text: &str, append: bool, backward: bool) {
+        match self.entries.front_mut() {
+            Some(newest) if append => {
+                if backward {
+                    newest.insert_str(0, text);
+                } else {
+                    newest.push_str(text);
+                }
+            }
+            _ => {
+                self.entries.push_front(text.to_string());
+                self.entries.truncate(KILL_RING_MAX);
+            }
+        }
+        self.yank_index = 0;
+    }
+
+    /// The newest entry, restarting the yank-pop cycle
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// The next older entry, wrapping around to the newest
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// `M-digits` and `M--`, then plain digits once an argument is started
+#[derive(Debug, Clone, Copy, Default)]
+struct Argument {
+    value: Option<u32>,
+    negative: bool,
+}
+
+impl Argument {
+    fn count(self) -> i64 {
+        let value = i64::from(self.value.unwrap_or(1));
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+/// What the previous key did, for kill appending, yank-pop and undo
+/// grouping
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum LastCommand {
+    #[default]
+    Other,
+    SelfInsert,
+    Kill,
+    Yank,
+}
+
+#[derive(Debug, Clone)]
+pub struct Readline {
+    enabled: bool,
+    keymap: Keymap,
+    /// Keys of an unfinished chord such as `C-x`
+    pending: Vec<Keystroke>,
+    argument: Option<Argument>,
+    kill_ring: KillRing,
+    last: LastCommand,
+    /// Text inserted by the last yank, replaced by yank-pop
+    yanked: Range<usize>,
+    /// Whether the current undo group already has its snapshot
+    group_open: bool,
+}
+
+impl Default for Readline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Readline {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            keymap: Keymap::new().with_layer(Self::default_layer()),
+            pending: Vec::new(),
+            argument: None,
+            kill_ring: KillRing::new(),
+            last: LastCommand::Other,
+            yanked: 0..0,
+            group_open: false,
+        }
+    }
+
+    /// [`READLINE_BINDINGS`] as a built-in layer
+    pub fn default_layer() -> BindingLayer {
+        let mut layer = BindingLayer::new(BindingSource::Builtin);
+        for (action, keys) in READLINE_BINDINGS {
+            layer.bind(action, keys, None);
+        }
+        layer
+    }
+
+    /// Take the keyset and user layers of `keymap` over the readline
+    /// defaults. Its built-in layer is left out: the editor's own defaults
+    /// (`ctrl-a` select all, `ctrl-k` delete line) are not readline's.
+    pub fn set_keymap(&mut self, keymap: &Keymap) {
+        let mut readline = Keymap::new().with_layer(Self::default_layer());
+        for layer in keymap.layers().iter().filter(|layer| layer.source != BindingSource::Builtin) {
+            readline.push_layer(layer.clone());
+        }
+        self.keymap = readline;
+        self.pending.clear();
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+        self.pending.clear();
+        self.argument = None;
+        self.last = LastCommand::Other;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn kill_ring(&self) -> &KillRing {
+        &self.kill_ring
+    }
+
+    /// The numeric argument typed so far, if any
+    pub fn argument(&self) -> Option<i64> {
+        self.argument.map(Argument::count)
+    }
+
+    pub fn handle_key(&mut self, key: &Key, modifiers: &Modifiers, state: &mut EditorState) -> ReadlineOutcome {
+        if !self.enabled {
+            return ReadlineOutcome::Unhandled;
+        }
+        // `_` and `?` arrive shifted; bindings name the character instead
+        let modifiers = match key {
+            Key::Character(c) if !c.chars().any(char::is_alphabetic) => modifiers.difference(Modifiers::SHIFT),
+            _ => *modifiers,
+        };
+        let stroke = Keystroke::new(key.clone(), modifiers);
+        if stroke.is_modifier_only() {
+            return ReadlineOutcome::Unhandled;
+        }
+        if self.pending.is_empty() && self.numeric_argument(key, modifiers) {
+            return ReadlineOutcome::Handled;
+        }
+
+        self.pending.push(stroke);
+        let action = match self.keymap.lookup(&self.pending, &[KeyContext::Editor]) {
+            Lookup::Matched(binding) => binding.action.clone(),
+            Lookup::Prefix => return ReadlineOutcome::Handled,
+            Lookup::Unbound => {
+                let chord = self.pending.len() > 1;
+                self.pending.clear();
+                if chord {
+                    // Like readline, an unknown chord is dropped whole
+                    self.argument = None;
+                    self.last = LastCommand::Other;
+                    return ReadlineOutcome::Handled;
+                }
+                return self.self_insert(key, modifiers, state);
+            }
+        };
+        self.pending.clear();
+        let count = self.argument.take().map_or(1, Argument::count);
+        self.run(&action, count, state)
+    }
+
+    /// Replace the buffer with the text the external editor saved, as one
+    /// undo step. Like zsh's `edit-command-line`, the command is left for
+    /// the user to run.
+    pub fn finish_external_edit(&mut self, state: &mut EditorState, text: &str) {
+        self.last = LastCommand::Other;
+        self.group_open = false;
+        if state.content != text {
+            self.edit(state, 0..state.content.len(), text);
+        }
+        state.move_cursor(state.content.len());
+    }
+
+    fn numeric_argument(&mut self, key: &Key, modifiers: Modifiers) -> bool {
+        let Key::Character(c) = key else {
+            return false;
+        };
+        let meta = modifiers == Modifiers::ALT;
+        let continuing = modifiers.is_empty() && self.argument.is_some();
+        if !meta && !continuing {
+            return false;
+        }
+        match c.as_str() {
+            "-" if self.argument.is_none_or(|argument| argument.value.is_none()) => {
+                let argument = self.argument.get_or_insert_with(Argument::default);
+                argument.negative = !argument.negative;
+                true
+            }
+            digit => match digit.parse::<u32>() {
+                Ok(digit) if digit < 10 => {
+                    let argument = self.argument.get_or_insert_with(Argument::default);
+                    let value = argument.value.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                    argument.value = Some(value.min(MAX_ARGUMENT));
+                    true
+                }
+                _ => false,
+            },
+        }
+    }
+
+    fn self_insert(&mut self, key: &Key, modifiers: Modifiers, state: &mut EditorState) -> ReadlineOutcome {
+        let count = self.argument.take().map_or(1, Argument::count);
+        let text = match key {
+            Key::Character(c) if modifiers.difference(Modifiers::SHIFT).is_empty() => c.to_string(),
+            Key::Named(Named::Space) if modifiers.difference(Modifiers::SHIFT).is_empty() => " ".to_string(),
+            _ => {
+                self.last = LastCommand::Other;
+                return ReadlineOutcome::Unhandled;
+            }
+        };
+        if self.last != LastCommand::SelfInsert {
+            self.group_open = false;
+        }
+        let pos = state.cursor_position;
+        self.edit(state, pos..pos, &text.repeat(count.unsigned_abs() as usize));
+        self.last = LastCommand::SelfInsert;
+        ReadlineOutcome::Handled
+    }
+
+    fn run(&mut self, action: &str, count: i64, state: &mut EditorState) -> ReadlineOutcome {
+        let previous = std::mem::take(&mut self.last);
+        self.group_open = false;
+        let text = state.text();
+        let pos = state.cursor_position;
+        // Warp's names for the same commands are accepted too
+        match action {
+            "editor:move_to_line_start" | "editor_view:move_to_line_start" | "editor_view:home" => state.move_cursor(line_start(&text, pos)),
+            "editor:move_to_line_end" | "editor_view:move_to_line_end" | "editor_view:end" => state.move_cursor(line_end(&text, pos)),
+            "editor:forward_char" => state.move_cursor(chars_from(&text, pos, count)),
+            "editor:backward_char" => state.move_cursor(chars_from(&text, pos, -count)),
+            "editor:move_to_next_word" | "editor_view:move_forward_one_word" => state.move_cursor(words_from(&text, pos, count)),
+            "editor:move_to_previous_word" | "editor_view:move_backward_one_word" => state.move_cursor(words_from(&text, pos, -count)),
+            "editor:delete_char" => {
+                // On an empty line `C-d` is end-of-file, which is the host's
+                if text.is_empty() {
+                    return ReadlineOutcome::Unhandled;
+                }
+                self.delete(state, pos, chars_from(&text, pos, count));
+            }
+            "editor:backward_delete_char" | "editor_view:backspace" => self.delete(state, pos, chars_from(&text, pos, -count)),
+            "editor:kill_line" | "editor_view:cut_all_right" => {
+                let target = if count < 0 {
+                    line_start(&text, pos)
+                } else if line_end(&text, pos) == pos {
+                    next_char(&text, pos)
+                } else {
+                    line_end(&text, pos)
+                };
+                self.kill(state, pos, target, previous);
+            }
+            "editor:unix_line_discard" => self.kill(state, pos, line_start(&text, pos), previous),
+            "editor:kill_word" | "editor_view:cut_word_right" => {
+                self.kill(state, pos, words_from(&text, pos, count), previous)
+            }
+            "editor:backward_kill_word" | "editor_view:cut_word_left" => self.kill(state, pos, words_from(&text, pos, -count), previous),
+            "editor:unix_word_rubout" => self.kill(state, pos, rubout_from(&text, pos, count.unsigned_abs()), previous),
+            "editor:yank" => self.yank(state),
+            "editor:yank_pop" => {
+                // Only right after a yank, as in readline
+                if previous == LastCommand::Yank {
+                    self.yank_pop(state);
+                }
+            }
+            "editor:transpose_chars" => self.transpose_chars(state, count),
+            "editor:transpose_words" => self.transpose_words(state),
+            "editor:upcase_word" => self.change_case(state, count, str::to_uppercase),
+            "editor:downcase_word" => self.change_case(state, count, str::to_lowercase),
+            "editor:capitalize_word" => self.change_case(state, count, capitalize),
+            "editor:undo" => {
+                for _ in 0..count.max(1) {
+                    if !state.undo() {
+                        break;
+                    }
+                }
+            }
+            "editor:edit_in_external_editor" => return ReadlineOutcome::EditInExternalEditor,
+            _ => return ReadlineOutcome::Unhandled,
+        }
+        ReadlineOutcome::Handled
+    }
+
+    /// Replace `range`, taking the undo snapshot on the group's first edit
+    fn edit(&mut self, state: &mut EditorState, range: Range<usize>, text: &str) {
+        if !self.group_open {
+            state.checkpoint();
+            self.group_open = true;
+        }
+        state.splice(range, text);
+    }
+
+    fn delete(&mut self, state: &mut EditorState, from: usize, to: usize) {
+        if from != to {
+            self.edit(state, from.min(to)..from.max(to), "");
+        }
+    }
+
+    fn kill(&mut self, state: &mut EditorState, from: usize, to: usize, previous: LastCommand) {
+        let range = from.min(to)..from.max(to);
+        if !range.is_empty() {
+            let killed = state.content.slice(range.clone()).to_string();
+            self.kill_ring.kill(&killed, previous == LastCommand::Kill, to < from);
+            self.edit(state, range, "");
+        }
+        self.last = LastCommand::Kill;
+    }
+
+    fn yank(&mut self, state: &mut EditorState) {
+        let Some(text) = self.kill_ring.yank().map(str::to_string) else {
+            return;
+        };
+        let pos = state.cursor_position;
+        self.edit(state, pos..pos, &text);
+        self.yanked = pos..pos + text.len();
+        self.last = LastCommand::Yank;
+    }
+
+    fn yank_pop(&mut self, state: &mut EditorState) {
+        let Some(text) = self.kill_ring.rotate().map(str::to_string) else {
+            return;
+        };
+        let start = self.yanked.start;
+        self.edit(state, self.yanked.clone(), &text);
+        self.yanked = start..start + text.len();
+        self.last = LastCommand::Yank;
+    }
+
+    /// Drag the character before point forward over the next one, or
+    /// backward for a negative count. At the end of a line the two
+    /// characters before point are swapped.
+    fn transpose_chars(&mut self, state: &mut EditorState, count: i64) {
+        let mut pos = state.cursor_position;
+        let start = line_start(&state.text(), pos);
+        let end = line_end(&state.text(), pos);
+        if pos == end && count > 0 {
+            pos = prev_char(&state.text(), pos);
+        }
+        if pos == start {
+            return;
+        }
+        for _ in 0..count.unsigned_abs() {
+            let text = state.text();
+            if count > 0 {
+                if pos >= line_end(&text, pos) {
+                    break;
+                }
+                let (before, after) = (prev_char(&text, pos), next_char(&text, pos));
+                self.edit(state, before..after, &format!("{}{}", &text[pos..after], &text[before..pos]));
+                pos = after;
+            } else {
+                let before = prev_char(&text, pos);
+                if before <= line_start(&text, pos) {
+                    break;
+                }
+                let first = prev_char(&text, before);
+                self.edit(state, first..pos, &format!("{}{}", &text[before..pos], &text[first..before]));
+                pos = first + (pos - before);
+            }
+        }
+        state.move_cursor(pos);
+    }
+
+    /// Swap the word before point with the one at or after it, leaving
+    /// point after both. At the end of the text the last two words swap.
+    fn transpose_words(&mut self, state: &mut EditorState) {
+        let text = state.text();
+        let pos = state.cursor_position;
+        let (second_start, second_end) = if text[pos..].chars().any(is_word) {
+            let end = words_from(&text, pos, 1);
+            (words_from(&text, end, -1), end)
+        } else {
+            let start = words_from(&text, pos, -1);
+            (start, words_from(&text, start, 1))
+        };
+        let first_start = words_from(&text, second_start, -1);
+        let first_end = words_from(&text, first_start, 1);
+        if first_start == second_start || first_end > second_start {
+            return;
+        }
+        let swapped = format!(
+            "{}{}{}",
+            &text[second_start..second_end],
+            &text[first_end..second_start],
+            &text[first_start..first_end]
+        );
+        self.edit(state, first_start..second_end, &swapped);
+    }
+
+    /// Convert `count` words from point; a negative count converts the
+    /// words before point and leaves point where it is.
+    fn change_case(&mut self, state: &mut EditorState, count: i64, convert: fn(&str) -> String) {
+        let text = state.text();
+        let pos = state.cursor_position;
+        let target = words_from(&text, pos, count);
+        let range = pos.min(target)..pos.max(target);
+        let converted = convert(&text[range.clone()]);
+        if converted != text[range.clone()] {
+            self.edit(state, range.clone(), &converted);
+        }
+        state.move_cursor(range.start + converted.len());
+    }
+}
+
+/// Editors that open their own window, so they work without a terminal
+const WINDOWED_EDITORS: &[&str] = &[
+    "code", "code-insiders", "codium", "cursor", "subl", "mate", "zed", "atom", "gedit", "kate",
+    "gvim", "mvim", "xed", "pluma", "mousepad", "open", "xdg-open", "notepad", "notepad++",
+];
+
+/// Run `$VISUAL`, then `$EDITOR`, then `vi` on a temporary file holding
+/// `text` and return what was saved. The editor runs on a blocking worker,
+/// so the host can await this from `Command::perform`; a failing exit
+/// status is an error, as in bash.
+///
+/// A terminal editor needs the terminal the app was started from. Without
+/// one (launched from a desktop, say) it would hang on a detached stdin, so
+/// it is refused unless it opens its own window.
+pub async fn edit_in_external_editor(text: String) -> Result<String, String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    if needs_terminal(&editor) && !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        return Err(format!(
+            "{editor} needs a terminal; set $VISUAL to an editor with its own window, such as `code --wait`"
+        ));
+    }
+    tokio::task::spawn_blocking(move || run_editor(&editor, &text).map_err(|e| e.to_string()))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()))
+}
+
+/// Whether `editor` (a command line, possibly with arguments) runs inside
+/// the terminal rather than opening a window. `emacsclient -c` opens a
+/// frame; plain `emacsclient` and `emacs -nw` do not.
+fn needs_terminal(editor: &str) -> bool {
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        return true;
+    };
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    match name {
+        "emacsclient" => !words.any(|arg| matches!(arg, "-c" | "--create-frame")),
+        _ => !WINDOWED_EDITORS.contains(&name),
+    }
+}
+
+fn run_editor(editor: &str, text: &str) -> std::io::Result<String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let path = std::env::temp_dir().join(format!("warp-edit-{}-{}.sh", std::process::id(), stamp));
+    std::fs::write(&path, format!("{text}\n"))?;
+    let result = editor_command(editor, &path).status().and_then(|status| {
+        if status.success() {
+            std::fs::read_to_string(&path)
+        } else {
+            Err(std::io::Error::other(format!("{editor} exited with {status}")))
+        }
+    });
+    let _ = std::fs::remove_file(&path);
+    let edited = result?;
+    let edited = edited.strip_suffix('\n').unwrap_or(&edited);
+    Ok(edited.strip_suffix('\r').unwrap_or(edited).to_string())
+}
+
+/// The editor variable may carry arguments (`code --wait`), so it goes
+/// through the shell
+fn editor_command(editor: &str, path: &Path) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(format!("{editor} \"{}\"", path.display()));
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!("{editor} \"$1\"")).arg("sh").arg(path);
+        command
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+fn chars_from(text: &str, pos: usize, count: i64) -> usize {
+    let mut pos = pos;
+    for _ in 0..count.unsigned_abs() {
+        pos = if count > 0 { next_char(text, pos) } else { prev_char(text, pos) };
+    }
+    pos
+}
+
+/// Readline word motion: forward to the end of the `count`th word, or
+/// backward to the start of one. Words are runs of letters and digits.
+fn words_from(text: &str, pos: usize, count: i64) -> usize {
+    let mut pos = pos;
+    for _ in 0..count.unsigned_abs() {
+        if count > 0 {
+            while char_at(text, pos).is_some_and(|c| !is_word(c)) {
+                pos = next_char(text, pos);
+            }
+            while char_at(text, pos).is_some_and(is_word) {
+                pos = next_char(text, pos);
+            }
+        } else {
+            while pos > 0 && !text[..pos].chars().next_back().is_some_and(is_word) {
+                pos = prev_char(text, pos);
+            }
+            while text[..pos].chars().next_back().is_some_and(is_word) {
+                pos = prev_char(text, pos);
+            }
+        }
+    }
+    pos
+}
+
+/// Start of the `count`th whitespace-delimited word before point, for `C-w`
+fn rubout_from(text: &str, pos: usize, count: u64) -> usize {
+    let mut pos = pos;
+    for _ in 0..count {
+        while text[..pos].chars().next_back().is_some_and(char::is_whitespace) {
+            pos = prev_char(text, pos);
+        }
+        while text[..pos].chars().next_back().is_some_and(|c| !c.is_whitespace()) {
+            pos = prev_char(text, pos);
+        }
+    }
+    pos
+}
+
+fn capitalize(text: &str) -> String {
+    let mut in_word = false;
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if is_word(c) {
+            if in_word {
+                out.extend(c.to_lowercase());
+            } else {
+                out.extend(c.to_uppercase());
+            }
+            in_word = true;
+        } else {
+            out.push(c);
+            in_word = false;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> (Readline, EditorState) {
+        let mut readline = Readline::new();
+        readline.enable();
+        (readline, EditorState::new())
+    }
+
+    /// Feed space-separated keys such as `ctrl-k alt-y`; `type:` feeds text
+    fn feed(readline: &mut Readline, state: &mut EditorState, keys: &str) -> ReadlineOutcome {
+        let mut outcome = ReadlineOutcome::Handled;
+        for keys in keys.split(' ') {
+            if let Some(text) = keys.strip_prefix("type:") {
+                for c in text.chars() {
+                    let c = if c == '_' { ' ' } else { c };
+                    let key = if c == ' ' { Key::Named(Named::Space) } else { Key::Character(c.to_string().into()) };
+                    outcome = readline.handle_key(&key, &Modifiers::empty(), state);
+                }
+            } else {
+                let stroke = Keystroke::parse(keys).unwrap();
+                outcome = readline.handle_key(&stroke.key, &stroke.modifiers, state);
+            }
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_kill_ring_and_yank_pop() {
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "type:one_two_three ctrl-w ctrl-w");
+        assert_eq!(state.content, "one ");
+        assert_eq!(readline.kill_ring().len(), 1, "consecutive kills join");
+
+        feed(&mut readline, &mut state, "ctrl-a alt-d ctrl-e ctrl-y");
+        assert_eq!(state.content, " one");
+        feed(&mut readline, &mut state, "alt-y");
+        assert_eq!(state.content, " two three");
+        feed(&mut readline, &mut state, "alt-y");
+        assert_eq!(state.content, " one");
+
+        // M-y does nothing unless the last command was a yank
+        feed(&mut readline, &mut state, "ctrl-b alt-y");
+        assert_eq!(state.content, " one");
+
+        feed(&mut readline, &mut state, "ctrl-a ctrl-k ctrl-k type:x");
+        assert_eq!(state.content, "x");
+    }
+
+    #[test]
+    fn test_transpose_and_case() {
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "type:teh ctrl-t");
+        assert_eq!(state.content, "the");
+        feed(&mut readline, &mut state, "ctrl-a ctrl-f ctrl-t");
+        assert_eq!(state.content, "hte");
+        assert_eq!(state.cursor_position, 2);
+
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "type:git_status_--short alt-t");
+        assert_eq!(state.content, "git short --status");
+        feed(&mut readline, &mut state, "ctrl-a alt-u alt-c");
+        assert_eq!(state.content, "GIT Short --status");
+        feed(&mut readline, &mut state, "alt-- alt-l");
+        assert_eq!(state.content, "GIT short --status");
+        assert_eq!(state.cursor_position, "GIT Short".len(), "negative case change leaves point");
+    }
+
+    #[test]
+    fn test_numeric_arguments() {
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "alt-3 type:ab");
+        assert_eq!(state.content, "aaab");
+        feed(&mut readline, &mut state, "alt-1 type:2 ctrl-b");
+        assert_eq!(state.cursor_position, 0);
+        feed(&mut readline, &mut state, "alt-2 ctrl-d");
+        assert_eq!(state.content, "ab");
+
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "type:echo_one_two_three alt-- alt-2 alt-d");
+        assert_eq!(state.content, "echo one ");
+        assert_eq!(readline.argument(), None);
+        feed(&mut readline, &mut state, "ctrl-y");
+        assert_eq!(state.content, "echo one two three");
+    }
+
+    #[test]
+    fn test_undo_groups() {
+        let (mut readline, mut state) = enabled();
+        feed(&mut readline, &mut state, "type:ls_-la ctrl-w type:/tmp");
+        assert_eq!(state.content, "ls /tmp");
+        feed(&mut readline, &mut state, "ctrl-_");
+        assert_eq!(state.content, "ls ");
+        feed(&mut readline, &mut state, "ctrl-x ctrl-u");
+        assert_eq!(state.content, "ls -la");
+        feed(&mut readline, &mut state, "ctrl-_");
+        assert_eq!(state.content, "");
+    }
+
+    #[test]
+    fn test_chords_and_rebinding() {
+        let (mut readline, mut state) = enabled();
+        assert_eq!(feed(&mut readline, &mut state, "type:ls ctrl-x"), ReadlineOutcome::Handled);
+        assert_eq!(feed(&mut readline, &mut state, "ctrl-e"), ReadlineOutcome::EditInExternalEditor);
+        // An unknown chord is swallowed rather than typed
+        feed(&mut readline, &mut state, "ctrl-x q");
+        assert_eq!(state.content, "ls");
+        assert_eq!(feed(&mut readline, &mut state, "enter"), ReadlineOutcome::Unhandled);
+
+        readline.finish_external_edit(&mut state, "ls -la");
+        assert_eq!(state.content, "ls -la");
+        feed(&mut readline, &mut state, "ctrl-_");
+        assert_eq!(state.content, "ls");
+
+        let user = BindingLayer::parse(
+            "\"editor:yank\": ctrl-v\n\"editor:undo\": ctrl-z",
+            BindingSource::User("user.yaml".into()),
+        )
+        .unwrap();
+        readline.set_keymap(&Keymap::builtin().with_layer(user));
+        feed(&mut readline, &mut state, "ctrl-u ctrl-v");
+        assert_eq!(state.content, "ls");
+        feed(&mut readline, &mut state, "ctrl-z");
+        assert_eq!(state.content, "");
+        // The editor's built-in ctrl-a (select all) stays out of readline
+        feed(&mut readline, &mut state, "type:b ctrl-a type:a");
+        assert_eq!(state.content, "ab");
+    }
+
+    #[test]
+    fn test_external_editor() {
+        let edited = run_editor("sed -i s/foo/bar/", "echo foo").unwrap();
+        assert_eq!(edited, "echo bar");
+        assert!(run_editor("false", "echo foo").is_err());
+    }
+
+    #[test]
+    fn test_terminal_editors() {
+        for editor in ["vi", "/usr/bin/nvim", "nano -w", "emacsclient -t", "emacs -nw", ""] {
+            assert!(needs_terminal(editor), "{editor}");
+        }
+        for editor in ["code --wait", "/usr/local/bin/subl -w", "emacsclient -c", "gvim -f"] {
+            assert!(!needs_terminal(editor), "{editor}");
+        }
+    }
+
+    #[test]
+    fn test_emacs_keyset_has_no_conflicts() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("keysets").join("emacs.yaml");
+        let keyset = BindingLayer::load(&path, BindingSource::Keyset(path.clone())).unwrap();
+        assert!(keyset.errors.is_empty(), "{:?}", keyset.errors);
+        let keymap = Keymap::builtin().with_layer(keyset);
+        let conflicts: Vec<String> = keymap
+            .conflicts()
+            .iter()
+            .filter(|conflict| conflict.context == KeyContext::Editor)
+            .map(ToString::to_string)
+            .collect();
+        assert!(conflicts.is_empty(), "{conflicts:#?}");
+    }
+}
@@ -0,0 +1,580 @@
+//! Chunked text storage for [`EditorState`](super::EditorState).
+//!
+//! Text is split into chunks of at most [`MAX_CHUNK`] bytes, kept in a
+//! [`SumTree`] with a [`TextSummary`] per node, so offset, line, char and
+//! UTF-16 lookups take O(log n), an edit only rewrites the chunks it
+//! touches and a clone shares all of them. Offsets are bytes. Grapheme and
+//! display-column queries look at the line an offset falls in, using
+//! [`string_offset`](crate::string_offset).
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Range};
+
+use crate::string_offset;
+use crate::sum_tree::{self, Bias, Dimension, Found, SumTree};
+
+/// Edits leave no chunk smaller than this, unless it is the only one.
+const MIN_CHUNK: usize = 256;
+pub const MAX_CHUNK: usize = 1024;
+
+/// Sizes of a piece of text in each unit the editor counts in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextSummary {
+    pub bytes: usize,
+    pub chars: usize,
+    pub utf16: usize,
+    pub newlines: usize,
+    /// Chars before the first newline
+    pub first_line_chars: usize,
+    /// Chars after the last newline
+    pub last_line_chars: usize,
+    /// Chars in the longest line, newline excluded
+    pub max_line_chars: usize,
+}
+
+impl TextSummary {
+    pub fn of(text: &str) -> Self {
+        let mut summary = Self {
+            bytes: text.len(),
+            ..Self::default()
+        };
+        for c in text.chars() {
+            summary.chars += 1;
+            summary.utf16 += c.len_utf16();
+            if c == '\n' {
+                summary.newlines += 1;
+                summary.last_line_chars = 0;
+            } else {
+                if summary.newlines == 0 {
+                    summary.first_line_chars += 1;
+                }
+                summary.last_line_chars += 1;
+                summary.max_line_chars = summary.max_line_chars.max(summary.last_line_chars);
+            }
+        }
+        summary
+    }
+}
+
+impl sum_tree::Summary for TextSummary {
+    fn add_summary(&mut self, other: &Self) {
+        *self += *other;
+    }
+}
+
+impl Add for TextSummary {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl AddAssign for TextSummary {
+    fn add_assign(&mut self, other: Self) {
+        // A line running across the join is this last line and the other's first
+        let joined = self.last_line_chars + other.first_line_chars;
+        self.max_line_chars = self.max_line_chars.max(other.max_line_chars).max(joined);
+        if self.newlines == 0 {
+            self.first_line_chars = joined;
+        }
+        self.last_line_chars = if other.newlines == 0 { joined } else { other.last_line_chars };
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.utf16 += other.utf16;
+        self.newlines += other.newlines;
+    }
+}
+
+/// Byte offsets, for seeking the chunk tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Bytes(usize);
+
+impl Dimension<TextSummary> for Bytes {
+    fn add_summary(&mut self, summary: &TextSummary) {
+        self.0 += summary.bytes;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Newlines(usize);
+
+impl Dimension<TextSummary> for Newlines {
+    fn add_summary(&mut self, summary: &TextSummary) {
+        self.0 += summary.newlines;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Chars(usize);
+
+impl Dimension<TextSummary> for Chars {
+    fn add_summary(&mut self, summary: &TextSummary) {
+        self.0 += summary.chars;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Utf16(usize);
+
+impl Dimension<TextSummary> for Utf16 {
+    fn add_summary(&mut self, summary: &TextSummary) {
+        self.0 += summary.utf16;
+    }
+}
+
+/// A zero-based row and a byte column within it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Point {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+#[derive(Clone)]
+struct Chunk {
+    text: String,
+    summary: TextSummary,
+}
+
+impl Chunk {
+    fn new(text: String) -> Self {
+        Self {
+            summary: TextSummary::of(&text),
+            text,
+        }
+    }
+}
+
+impl sum_tree::Item for Chunk {
+    type Summary = TextSummary;
+
+    fn summary(&self) -> TextSummary {
+        self.summary
+    }
+}
+
+/// Editable text. Chunks are never empty.
+#[derive(Clone, Default)]
+pub struct Rope {
+    chunks: SumTree<Chunk>,
+}
+
+impl Rope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn summary(&self) -> TextSummary {
+        *self.chunks.summary()
+    }
+
+    pub fn len(&self) -> usize {
+        self.summary().bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Rows, counting the empty row after a trailing newline
+    pub fn line_count(&self) -> usize {
+        self.summary().newlines + 1
+    }
+
+    /// Chars in the longest row
+    pub fn max_line_chars(&self) -> usize {
+        self.summary().max_line_chars
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.chunks.iter().map(|chunk| chunk.text.as_str())
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.chunks().flat_map(str::chars)
+    }
+
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.chunks().flat_map(str::bytes)
+    }
+
+    pub fn slice(&self, range: Range<usize>) -> String {
+        let mut text = String::with_capacity(range.len());
+        let Some(first) = self.chunks.find(&Bytes(range.start), Bias::Right) else {
+            return text;
+        };
+        let mut start = first.before.bytes;
+        for chunk in self.chunks.iter_from(first.index) {
+            if start >= range.end {
+                break;
+            }
+            let end = start + chunk.summary.bytes;
+            let from = range.start.max(start) - start;
+            let to = range.end.min(end) - start;
+            text.push_str(&chunk.text[from..to]);
+            start = end;
+        }
+        text
+    }
+
+    /// Replace the bytes in `range` with `text`. Panics if the range is out
+    /// of bounds or splits a character, like `String::replace_range`.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "range {range:?} out of bounds for rope of length {}",
+            self.len()
+        );
+        assert!(
+            self.is_char_boundary(range.start) && self.is_char_boundary(range.end),
+            "range {range:?} is not on character boundaries"
+        );
+        let (Some(first), Some(last)) = (self.locate(range.start), self.locate(range.end)) else {
+            self.chunks = SumTree::from_items(split(text));
+            return;
+        };
+        let mut combined = String::with_capacity(text.len() + MAX_CHUNK);
+        combined.push_str(&first.item.text[..range.start - first.before.bytes]);
+        combined.push_str(text);
+        combined.push_str(&last.item.text[range.end - last.before.bytes..]);
+
+        // Take in a neighbour rather than leave a small chunk behind
+        let (mut lo, mut hi) = (first.index, last.index + 1);
+        if combined.len() < MIN_CHUNK {
+            if let Some(next) = self.chunks.get(hi) {
+                combined.push_str(&next.text);
+                hi += 1;
+            } else if lo > 0 {
+                lo -= 1;
+                combined.insert_str(0, &self.chunks.get(lo).unwrap().text);
+            }
+        }
+        self.chunks.splice(lo..hi, split(&combined));
+    }
+
+    pub fn insert(&mut self, offset: usize, text: &str) {
+        self.replace(offset..offset, text);
+    }
+
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.replace(range, "");
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        let len = self.len();
+        self.replace(len..len, text);
+    }
+
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        if offset > self.len() {
+            return false;
+        }
+        self.locate(offset)
+            .is_none_or(|found| found.item.text.is_char_boundary(offset - found.before.bytes))
+    }
+
+    /// `offset` clamped to the text and moved back to a character start
+    pub fn clip_offset(&self, offset: usize) -> usize {
+        let offset = offset.min(self.len());
+        self.locate(offset).map_or(offset, |found| {
+            let start = found.before.bytes;
+            start + string_offset::floor_char_boundary(&found.item.text, offset - start)
+        })
+    }
+
+    pub fn char_at(&self, offset: usize) -> Option<char> {
+        let found = self.chunks.find(&Bytes(offset), Bias::Right)?;
+        found.item.text.get(offset - found.before.bytes..).and_then(|rest| rest.chars().next())
+    }
+
+    pub fn offset_to_point(&self, offset: usize) -> Point {
+        let offset = self.clip_offset(offset);
+        let row = self.locate(offset).map_or(0, |found| {
+            found.before.newlines + found.item.text[..offset - found.before.bytes].matches('\n').count()
+        });
+        Point::new(row, offset - self.line_start(row))
+    }
+
+    /// The offset of `point`, with the column clamped to the row's length
+    pub fn point_to_offset(&self, point: Point) -> usize {
+        let range = self.line_range(point.row);
+        self.clip_offset(range.start + point.column.min(range.len()))
+    }
+
+    /// Offset of the first byte of `row`; rows past the end clamp to the end
+    pub fn line_start(&self, row: usize) -> usize {
+        if row == 0 {
+            return 0;
+        }
+        let Some(found) = self.chunks.find(&Newlines(row), Bias::Left) else {
+            return self.len();
+        };
+        let newline = found
+            .item
+            .text
+            .match_indices('\n')
+            .nth(row - found.before.newlines - 1)
+            .map_or(found.item.text.len(), |(index, _)| index);
+        found.before.bytes + newline + 1
+    }
+
+    /// `row` without its newline
+    pub fn line_range(&self, row: usize) -> Range<usize> {
+        let start = self.line_start(row);
+        let end = if row < self.summary().newlines {
+            self.line_start(row + 1) - 1
+        } else {
+            self.len()
+        };
+        start..end
+    }
+
+    pub fn line(&self, row: usize) -> String {
+        self.slice(self.line_range(row))
+    }
+
+    /// Every row, including an empty last row after a trailing newline
+    pub fn lines(&self) -> Vec<String> {
+        self.to_string().split('\n').map(str::to_string).collect()
+    }
+
+    /// Start of the grapheme cluster before `offset`
+    pub fn prev_grapheme_boundary(&self, offset: usize) -> usize {
+        let offset = self.clip_offset(offset);
+        if offset == 0 {
+            return 0;
+        }
+        // A line's window ends with its newline, so `\r\n` stays whole
+        let (start, window) = self.grapheme_window(offset - 1);
+        start + string_offset::prev_grapheme_boundary(&window, offset - start)
+    }
+
+    /// End of the grapheme cluster at `offset`
+    pub fn next_grapheme_boundary(&self, offset: usize) -> usize {
+        let offset = self.clip_offset(offset);
+        if offset >= self.len() {
+            return self.len();
+        }
+        let (start, window) = self.grapheme_window(offset);
+        start + string_offset::next_grapheme_boundary(&window, offset - start)
+    }
+
+    /// The row holding `offset`, newline included, and where it starts
+    fn grapheme_window(&self, offset: usize) -> (usize, String) {
+        let row = self.offset_to_point(offset).row;
+        let start = self.line_start(row);
+        let end = if row < self.summary().newlines {
+            self.line_start(row + 1)
+        } else {
+            self.len()
+        };
+        (start, self.slice(start..end))
+    }
+
+    /// Display column of `offset` within its row
+    pub fn offset_to_display_column(&self, offset: usize) -> usize {
+        let point = self.offset_to_point(offset);
+        string_offset::byte_to_column(&self.line(point.row), point.column)
+    }
+
+    /// Offset of the grapheme drawn at `column` on `row`, clamped to the row
+    pub fn display_column_to_offset(&self, row: usize, column: usize) -> usize {
+        let range = self.line_range(row);
+        range.start + string_offset::column_to_byte(&self.slice(range.clone()), column)
+    }
+
+    pub fn offset_to_utf16(&self, offset: usize) -> usize {
+        self.offset_to(offset, |summary| summary.utf16, string_offset::byte_to_utf16)
+    }
+
+    pub fn utf16_to_offset(&self, utf16: usize) -> usize {
+        self.offset_from(utf16, Utf16, |summary| summary.utf16, string_offset::utf16_to_byte)
+    }
+
+    pub fn offset_to_char(&self, offset: usize) -> usize {
+        self.offset_to(offset, |summary| summary.chars, string_offset::byte_to_char)
+    }
+
+    pub fn char_to_offset(&self, chars: usize) -> usize {
+        self.offset_from(chars, Chars, |summary| summary.chars, string_offset::char_to_byte)
+    }
+
+    /// Convert a byte offset to another dimension: whole chunks by their
+    /// summary, then `within` for the chunk holding `offset`
+    fn offset_to(&self, offset: usize, dimension: fn(&TextSummary) -> usize, within: fn(&str, usize) -> usize) -> usize {
+        match self.chunks.find(&Bytes(offset), Bias::Right) {
+            Some(found) => dimension(&found.before) + within(&found.item.text, offset - found.before.bytes),
+            None => dimension(&self.summary()),
+        }
+    }
+
+    /// The reverse of [`Self::offset_to`]; `seek` wraps `target` as the
+    /// dimension the chunks are searched by
+    fn offset_from<D: Dimension<TextSummary>>(
+        &self,
+        target: usize,
+        seek: fn(usize) -> D,
+        dimension: fn(&TextSummary) -> usize,
+        within: fn(&str, usize) -> usize,
+    ) -> usize {
+        match self.chunks.find(&seek(target), Bias::Right) {
+            Some(found) => found.before.bytes + within(&found.item.text, target - dimension(&found.before)),
+            None => self.len(),
+        }
+    }
+
+    /// The chunk holding `offset`. An offset between two chunks belongs to
+    /// the first; `None` when the rope is empty or `offset` is past the end.
+    fn locate(&self, offset: usize) -> Option<Found<'_, Chunk, Bytes>> {
+        self.chunks.find(&Bytes(offset), Bias::Left)
+    }
+}
+
+/// Cut `text` into chunks of at most [`MAX_CHUNK`] bytes at character
+/// boundaries, as evenly as it allows
+fn split(text: &str) -> Vec<Chunk> {
+    let count = text.len().div_ceil(MAX_CHUNK);
+    let mut chunks = Vec::with_capacity(count);
+    let mut start = 0;
+    for remaining in (1..=count).rev() {
+        let target = start + (text.len() - start).div_ceil(remaining);
+        let mut end = string_offset::floor_char_boundary(text, target);
+        if end <= start {
+            end = string_offset::ceil_char_boundary(text, target);
+        }
+        chunks.push(Chunk::new(text[start..end].to_string()));
+        start = end;
+    }
+    chunks
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        Self {
+            chunks: SumTree::from_items(split(text)),
+        }
+    }
+}
+
+impl From<String> for Rope {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl fmt::Debug for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+impl PartialEq for Rope {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.bytes().eq(other.bytes())
+    }
+}
+
+impl Eq for Rope {}
+
+impl PartialEq<str> for Rope {
+    fn eq(&self, other: &str) -> bool {
+        self.len() == other.len() && self.bytes().eq(other.bytes())
+    }
+}
+
+impl PartialEq<&str> for Rope {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Rope {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small deterministic generator, so failures reproduce
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound.max(1) as u64) as usize
+        }
+    }
+
+    #[test]
+    fn test_edits_match_string() {
+        let pieces = ["a", "é", "日本", "😀", "\n", "e\u{301}", "line\r\n", &"x".repeat(700)];
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut rope = Rope::new();
+        let mut expected = String::new();
+        for _ in 0..400 {
+            let start = string_offset::floor_char_boundary(&expected, rng.below(expected.len() + 1));
+            let end = string_offset::floor_char_boundary(&expected, start + rng.below(64)).max(start);
+            let text: String = (0..rng.below(4)).map(|_| pieces[rng.below(pieces.len())]).collect();
+            rope.replace(start..end, &text);
+            expected.replace_range(start..end, &text);
+            assert_eq!(rope, expected);
+            assert!(rope.chunks.iter().all(|chunk| !chunk.text.is_empty() && chunk.text.len() <= MAX_CHUNK));
+            assert_eq!(rope.max_line_chars(), expected.split('\n').map(|line| line.chars().count()).max().unwrap());
+        }
+        assert!(rope.chunks.len() > 1);
+        assert_eq!(rope.summary(), TextSummary::of(&expected));
+
+        for offset in (0..=expected.len()).filter(|offset| expected.is_char_boundary(*offset)) {
+            let point = rope.offset_to_point(offset);
+            assert_eq!(point.row, expected[..offset].matches('\n').count());
+            assert_eq!(rope.point_to_offset(point), offset);
+            assert_eq!(rope.offset_to_utf16(offset), string_offset::byte_to_utf16(&expected, offset));
+            assert_eq!(rope.utf16_to_offset(rope.offset_to_utf16(offset)), offset);
+            assert_eq!(rope.char_to_offset(rope.offset_to_char(offset)), offset);
+        }
+        for (row, line) in expected.split('\n').enumerate() {
+            assert_eq!(rope.line(row), line);
+        }
+        assert_eq!(rope.line_count(), expected.split('\n').count());
+    }
+
+    #[test]
+    fn test_graphemes_and_columns() {
+        let rope = Rope::from("ab\r\n日e\u{301}👍🏽\tz");
+        let second = rope.line_start(1);
+        assert_eq!(second, 4);
+        assert_eq!(rope.next_grapheme_boundary(2), 4, "\\r\\n is one grapheme");
+        assert_eq!(rope.prev_grapheme_boundary(4), 2);
+
+        let accent = second + "日".len();
+        let thumb = accent + "e\u{301}".len();
+        let tab = thumb + "👍🏽".len();
+        assert_eq!(rope.next_grapheme_boundary(accent), thumb);
+        assert_eq!(rope.prev_grapheme_boundary(tab), thumb);
+        assert_eq!(rope.prev_grapheme_boundary(tab - 1), thumb, "inside the emoji");
+
+        assert_eq!(rope.offset_to_display_column(thumb), 3);
+        assert_eq!(rope.offset_to_display_column(tab + 1), 8, "tab runs to the next stop");
+        assert_eq!(rope.display_column_to_offset(1, 1), second, "inside the wide character");
+        assert_eq!(rope.display_column_to_offset(1, 6), tab);
+        assert_eq!(rope.display_column_to_offset(1, 40), rope.len());
+    }
+}
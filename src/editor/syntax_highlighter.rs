@@ -2,6 +2,8 @@ use iced::Color;
 use std::collections::HashMap;
 use std::ops::Range;
 
+use crate::syntax_tree::highlight::Scope;
+
 #[derive(Debug, Clone)]
 pub struct SyntaxHighlighter {
     rules: Vec<HighlightRule>,
@@ -41,6 +43,16 @@ pub enum TokenType {
     Punctuation,
     Error,
     Default,
+
+    // Language tokens
+    Function,
+    Type,
+    Property,
+    Attribute,
+    Heading,
+    Emphasis,
+    Strong,
+    Link,
 }
 
 #[derive(Debug, Clone)]
@@ -151,6 +163,20 @@ impl SyntaxHighlighter {
         spans
     }
 
+    /// Styled spans for highlights from a
+    /// [`HighlightMap`](crate::syntax_tree::highlight::HighlightMap)
+    pub fn spans(&self, highlights: impl IntoIterator<Item = (Range<usize>, Scope)>) -> Vec<HighlightedSpan> {
+        let theme = self.get_current_theme();
+        highlights
+            .into_iter()
+            .map(|(range, scope)| theme.span(range, scope.token_type()))
+            .collect()
+    }
+
+    pub fn theme(&self, theme_name: &str) -> Option<&SyntaxTheme> {
+        self.themes.get(theme_name)
+    }
+
     pub fn set_theme(&mut self, theme_name: &str) {
         if self.themes.contains_key(theme_name) {
             self.current_theme = theme_name.to_string();
@@ -449,6 +475,17 @@ impl SyntaxHighlighter {
             underline: false,
         });
         
+        insert_language_styles(&mut dark_colors, [
+            (0.8, 0.55, 1.0),  // Keyword: violet
+            (0.4, 0.7, 1.0),   // Function: blue
+            (0.35, 0.85, 0.8), // Type: teal
+            (0.6, 0.8, 1.0),   // Property: pale blue
+            (0.9, 0.75, 0.45), // Attribute: sand
+            (0.75, 0.75, 0.8), // Operator and punctuation: silver
+            (1.0, 0.65, 0.5),  // Literal: salmon
+            (0.4, 0.7, 1.0),   // Link: blue
+        ]);
+        
         self.themes.insert("dark".to_string(), SyntaxTheme {
             name: "Dark".to_string(),
             colors: dark_colors,
@@ -535,6 +572,17 @@ impl SyntaxHighlighter {
             underline: false,
         });
         
+        insert_language_styles(&mut light_colors, [
+            (0.5, 0.1, 0.7),   // Keyword: purple
+            (0.0, 0.35, 0.75), // Function: blue
+            (0.0, 0.5, 0.5),   // Type: teal
+            (0.1, 0.3, 0.6),   // Property: navy
+            (0.6, 0.4, 0.0),   // Attribute: ochre
+            (0.3, 0.3, 0.35),  // Operator and punctuation: slate
+            (0.7, 0.25, 0.1),  // Literal: rust
+            (0.0, 0.35, 0.75), // Link: blue
+        ]);
+        
         self.themes.insert("light".to_string(), SyntaxTheme {
             name: "Light".to_string(),
             colors: light_colors,
@@ -591,6 +639,51 @@ impl SyntaxHighlighter {
     }
 }
 
+/// Styles for the tokens languages produce beyond shell commands, from
+/// their keyword, function, type, property, attribute, operator, literal
+/// and link colors. Token types the theme already styles keep their style.
+fn insert_language_styles(colors: &mut HashMap<TokenType, TokenStyle>, palette: [(f32, f32, f32); 8]) {
+    let [keyword, function, ty, property, attribute, operator, literal, link] =
+        palette.map(|(r, g, b)| Color::from_rgb(r, g, b));
+    let text = colors.get(&TokenType::Default).map_or(TokenStyle::default().color, |style| style.color);
+    let styles = [
+        (TokenType::Keyword, keyword, true, false, false),
+        (TokenType::Function, function, false, false, false),
+        (TokenType::Type, ty, false, false, false),
+        (TokenType::Property, property, false, false, false),
+        (TokenType::Attribute, attribute, false, false, false),
+        (TokenType::Operator, operator, false, false, false),
+        (TokenType::Punctuation, operator, false, false, false),
+        (TokenType::Literal, literal, false, false, false),
+        (TokenType::Heading, keyword, true, false, false),
+        (TokenType::Emphasis, text, false, true, false),
+        (TokenType::Strong, text, true, false, false),
+        (TokenType::Link, link, false, false, true),
+    ];
+    for (token_type, color, bold, italic, underline) in styles {
+        colors.entry(token_type).or_insert(TokenStyle { color, bold, italic, underline });
+    }
+}
+
+impl SyntaxTheme {
+    /// The style of `token_type`, or plain text when the theme has none
+    pub fn style(&self, token_type: &TokenType) -> TokenStyle {
+        self.colors.get(token_type).cloned().unwrap_or_default()
+    }
+
+    pub fn span(&self, range: Range<usize>, token_type: TokenType) -> HighlightedSpan {
+        let style = self.style(&token_type);
+        HighlightedSpan {
+            range,
+            token_type,
+            color: style.color,
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct ShellToken {
     range: Range<usize>,
@@ -658,4 +751,20 @@ mod tests {
         highlighter.set_theme("nonexistent");
         assert_eq!(highlighter.current_theme, "light"); // Should remain unchanged
     }
+
+    #[test]
+    fn test_language_styles_keep_existing_styles() {
+        let keyword = TokenStyle {
+            color: Color::from_rgb(1.0, 0.0, 0.0),
+            bold: false,
+            italic: true,
+            underline: false,
+        };
+        let mut colors = HashMap::from([(TokenType::Keyword, keyword)]);
+        insert_language_styles(&mut colors, [(0.5, 0.5, 0.5); 8]);
+
+        assert_eq!(colors[&TokenType::Keyword].color, Color::from_rgb(1.0, 0.0, 0.0));
+        assert!(colors[&TokenType::Keyword].italic);
+        assert_eq!(colors[&TokenType::Function].color, Color::from_rgb(0.5, 0.5, 0.5));
+    }
 }
@@ -14,18 +14,13 @@ use crate::editor::{
     EditorState, EditorAction, SyntaxHighlighter, KeybindingManager,
     CompletionEngine, CompletionItem,
 };
+use crate::editor::native_completion::NativeCompletionBridge;
+use crate::languages::LanguageRegistry;
+use crate::syntax_tree::highlight::HighlightMap;
+use crate::string_offset::display_width;
 
 
 // Additional structs for modern text editing features
-#[derive(Debug, Clone)]
-pub struct CursorState {
-    pub position: usize,
-    pub line: usize,
-    pub column: usize,
-    pub selection_start: Option<usize>,
-    pub selection_end: Option<usize>,
-}
-
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub start: usize,
@@ -48,6 +43,11 @@ pub struct CollaborationCursor {
 pub struct ModernTextEditor {
     state: EditorState,
     syntax_highlighter: SyntaxHighlighter,
+    /// Language the text is highlighted as: shell for the input line, the
+    /// file's language in an editor pane, `None` for plain text
+    language: Option<String>,
+    /// Kept up to date through edits by [`Self::invalidate_cache`]
+    highlights: Option<HighlightMap>,
     keybinding_manager: KeybindingManager,
     completion_engine: CompletionEngine,
     
@@ -68,7 +68,6 @@ pub struct ModernTextEditor {
     wrap_lines: bool,
     
     // Advanced features
-    multi_cursors: Vec<CursorState>,
     bracket_matching: bool,
     #[allow(dead_code)]
     auto_indent: bool,
@@ -78,7 +77,10 @@ pub struct ModernTextEditor {
     
     // Interaction state
     is_focused: bool,
+    modifiers: Modifiers,
     mouse_position: Point,
+    /// The cell an Alt-drag column selection started from
+    column_drag: Option<(usize, usize)>,
     last_click_time: std::time::Instant,
     click_count: u8,
     
@@ -112,8 +114,10 @@ pub struct ModernTextEditor {
 pub enum EditorMessage {
     InputChanged(String),
     KeyPressed(Key, Modifiers),
+    ModifiersChanged(Modifiers),
     MouseClicked(Point),
     MouseMoved(Point),
+    MouseReleased,
     ScrollChanged(f32),
     CompletionSelected(usize),
     ActionRequested(EditorAction),
@@ -130,6 +134,8 @@ impl ModernTextEditor {
         Self {
             state: EditorState::new(),
             syntax_highlighter: SyntaxHighlighter::new(),
+            language: Some("shell".to_string()),
+            highlights: LanguageRegistry::global().get("shell").map(|shell| HighlightMap::new(shell, "")),
             keybinding_manager: KeybindingManager::new(),
             completion_engine: CompletionEngine::new(),
             
@@ -145,14 +151,15 @@ impl ModernTextEditor {
             show_folding: true,
             wrap_lines: false,
             
-            multi_cursors: Vec::new(),
             bracket_matching: true,
             auto_indent: true,
             smart_indent: true,
             auto_closing_brackets: true,
             
             is_focused: false,
+            modifiers: Modifiers::empty(),
             mouse_position: Point::ORIGIN,
+            column_drag: None,
             last_click_time: std::time::Instant::now(),
             click_count: 0,
             
@@ -192,7 +199,8 @@ impl ModernTextEditor {
             }
             
             EditorMessage::KeyPressed(key, modifiers) => {
-            if let Some(action) = self.keybinding_manager.handle_key_press(&key, &modifiers) {
+                self.modifiers = modifiers;
+                if let Some(action) = self.keybinding_manager.handle_key_press(&key, &modifiers) {
                     self.perform_action(action);
                 } else {
                     // Handle regular key input
@@ -206,8 +214,20 @@ impl ModernTextEditor {
                 self.invalidate_cache();
             }
             
+            EditorMessage::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+            }
+            
             EditorMessage::MouseMoved(point) => {
                 self.mouse_position = point;
+                if let Some(anchor) = self.column_drag {
+                    self.state.select_columns(anchor, self.point_to_cell(point));
+                    self.invalidate_cache();
+                }
+            }
+            
+            EditorMessage::MouseReleased => {
+                self.column_drag = None;
             }
             
             EditorMessage::ScrollChanged(offset) => {
@@ -288,14 +308,14 @@ impl ModernTextEditor {
 
             self.draw_text_content(frame, text_area, start_line, end_line);
 
-            // Draw cursor
-            if self.is_focused {
-                self.draw_cursor(frame, text_area, line_number_width);
-            }
-
-            // Draw selection
-            if self.state.has_selection() {
-                self.draw_selection(frame, text_area, line_number_width);
+            // Draw every cursor and its selection
+            for selection in self.state.selections() {
+                if self.is_focused {
+                    self.draw_cursor(frame, text_area, selection.head);
+                }
+                if !selection.is_empty() {
+                    self.draw_selection(frame, text_area, selection.range());
+                }
             }
 
             // Highlight current line
@@ -340,6 +360,18 @@ impl ModernTextEditor {
             EditorAction::InsertNewLine => {
                 self.state.insert_newline();
             }
+            EditorAction::AddCursor(position) => {
+                self.state.add_cursor(position);
+            }
+            EditorAction::AddCursorAbove => {
+                self.state.add_cursor_above();
+            }
+            EditorAction::AddCursorBelow => {
+                self.state.add_cursor_below();
+            }
+            EditorAction::SelectAllOccurrences => {
+                self.state.select_all_occurrences();
+            }
             _ => {
                 // Handle other actions as needed
             }
@@ -365,6 +397,8 @@ impl ModernTextEditor {
             Key::Named(Named::Escape) => {
                 if self.completion_visible {
                     self.hide_completions();
+                } else if self.state.has_multiple_cursors() {
+                    self.state.clear_multiple_cursors();
                 } else {
                     self.state.clear_selection();
                 }
@@ -425,6 +459,16 @@ impl ModernTextEditor {
         self.last_click_time = now;
         self.is_focused = true;
 
+        // Alt-drag selects a block, one selection per row
+        if self.modifiers.alt() {
+            let cell = self.point_to_cell(point);
+            self.column_drag = Some(cell);
+            self.state.select_columns(cell, cell);
+            return;
+        }
+        self.column_drag = None;
+        self.state.clear_multiple_cursors();
+
         // Convert point to text position
         let position = self.point_to_position(point);
         
@@ -449,45 +493,51 @@ impl ModernTextEditor {
     }
 
     fn point_to_position(&self, point: Point) -> usize {
+        let (line, column) = self.point_to_cell(point);
+        
+        // Columns are display cells, so wide characters take two
+        self.state.content.display_column_to_offset(line, column)
+    }
+
+    /// The row and display column under `point`, which may lie past the
+    /// end of the row
+    fn point_to_cell(&self, point: Point) -> (usize, usize) {
         let line_number_width = if self.show_line_numbers { 50.0 } else { 0.0 };
         let text_x = point.x - line_number_width - 10.0;
         let text_y = point.y;
         
         let line = ((text_y / self.line_height) as usize + self.state.scroll_offset).min(self.state.total_lines - 1);
-        let column = (text_x / self.char_width) as usize;
-        
-        // Convert line/column to absolute position
-        let lines = self.state.get_lines();
-        let mut position = 0;
-        
-        for (i, line_text) in lines.iter().enumerate() {
-            if i == line {
-                position += column.min(line_text.len());
-                break;
-            }
-            position += line_text.len() + 1; // +1 for newline
-        }
-        
-        position.min(self.state.content.len())
+        let column = (text_x.max(0.0) / self.char_width) as usize;
+        (line, column)
     }
 
     fn update_completions(&mut self) {
         let current_line = self.state.get_current_line_text();
-        let cursor_column = self.state.current_column.saturating_sub(1);
-        
-        if let Some(word_start) = self.find_word_start(current_line, cursor_column) {
-            let partial_word = &current_line[word_start..cursor_column];
-            
-            if partial_word.len() >= 2 {
-                self.completion_items = self.completion_engine.get_completions(partial_word);
-                self.completion_visible = !self.completion_items.is_empty();
-                self.selected_completion = 0;
-            } else {
-                self.hide_completions();
-            }
+        let cursor_column = self.state.cursor_position - self.state.find_line_start(self.state.cursor_position);
+
+        let partial_word = self
+            .find_word_start(&current_line, cursor_column)
+            .map(|word_start| &current_line[word_start..cursor_column])
+            .filter(|partial_word| partial_word.len() >= 2);
+        // The shell completes arguments, so it is asked once the command
+        // name is followed by a space
+        let in_arguments = current_line
+            .get(..cursor_column)
+            .is_some_and(|before| before.trim_start().contains(char::is_whitespace));
+        let ask_shell = in_arguments
+            && self.language.as_deref() == Some("shell")
+            && self.completion_engine.has_native_completions();
+
+        self.completion_items = if ask_shell {
+            self.completion_engine
+                .get_line_completions(&current_line, cursor_column, partial_word.unwrap_or_default())
+        } else if let Some(partial_word) = partial_word {
+            self.completion_engine.get_completions(partial_word)
         } else {
-            self.hide_completions();
-        }
+            Vec::new()
+        };
+        self.completion_visible = !self.completion_items.is_empty();
+        self.selected_completion = 0;
     }
 
     fn find_word_start(&self, line: &str, column: usize) -> Option<usize> {
@@ -518,10 +568,11 @@ impl ModernTextEditor {
     }
 
     fn apply_completion(&mut self, item: &CompletionItem) {
-        let current_line = self.state.get_current_line_text().to_string();
-        let cursor_column = self.state.current_column.saturating_sub(1);
+        let current_line = self.state.get_current_line_text();
+        let cursor_column = self.state.cursor_position - self.state.find_line_start(self.state.cursor_position);
         
-        if let Some(word_start) = self.find_word_start(&current_line, cursor_column) {
+        let word_start = item.replace_start.or_else(|| self.find_word_start(&current_line, cursor_column));
+        if let Some(word_start) = word_start.filter(|&word_start| word_start <= cursor_column) {
             let line_start = self.state.find_line_start(self.state.cursor_position);
             let absolute_word_start = line_start + word_start;
             let absolute_cursor = line_start + cursor_column;
@@ -643,7 +694,12 @@ impl ModernTextEditor {
 
     fn draw_text_content(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle, start_line: usize, end_line: usize) {
         let lines = self.state.get_lines();
-        let highlighted_spans = self.syntax_highlighter.highlight_command_line(&self.state.content);
+        let highlighted_spans = match &self.highlights {
+            Some(highlights) if highlights.len() == self.state.content.len() => {
+                self.syntax_highlighter.spans(highlights.highlights(start_line..end_line))
+            }
+            _ => Vec::new(),
+        };
         
         for line_idx in start_line..end_line {
             if let Some(line_text) = lines.get(line_idx) {
@@ -693,7 +749,7 @@ impl ModernTextEditor {
                                 horizontal_alignment: alignment::Horizontal::Left,
                                 vertical_alignment: alignment::Vertical::Top,
                             });
-                            x_offset += before_text.len() as f32 * self.char_width;
+                            x_offset += display_width(before_text) as f32 * self.char_width;
                         }
                         
                         // Draw highlighted span
@@ -709,7 +765,7 @@ impl ModernTextEditor {
                             horizontal_alignment: alignment::Horizontal::Left,
                             vertical_alignment: alignment::Vertical::Top,
                         });
-                        x_offset += span_text.len() as f32 * self.char_width;
+                        x_offset += display_width(span_text) as f32 * self.char_width;
                         
                         last_end = span.range.end;
                     }
@@ -733,9 +789,9 @@ impl ModernTextEditor {
         }
     }
 
-    fn draw_cursor(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle, _line_number_width: f32) {
-        let cursor_line = self.state.current_line.saturating_sub(1);
-        let cursor_column = self.state.current_column.saturating_sub(1);
+    fn draw_cursor(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle, position: usize) {
+        let cursor_line = self.state.content.offset_to_point(position).row;
+        let cursor_column = self.state.content.offset_to_display_column(position);
         
         if cursor_line >= self.state.scroll_offset 
             && cursor_line < self.state.scroll_offset + (text_area.height / self.line_height) as usize {
@@ -751,57 +807,29 @@ impl ModernTextEditor {
         }
     }
 
-    fn draw_selection(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle, _line_number_width: f32) {
-        if let (Some(start), Some(end)) = (self.state.selection_start, self.state.selection_end) {
-            let (start, end) = (start.min(end), start.max(end));
-            
-            // Convert positions to line/column coordinates
-            let lines = self.state.get_lines();
-            let mut pos = 0;
-            let mut start_line = 0;
-            let mut start_column = 0;
-            let mut end_line = 0;
-            let mut end_column = 0;
-            
-            for (line_idx, line_text) in lines.iter().enumerate() {
-                if pos + line_text.len() >= start && start_line == 0 {
-                    start_line = line_idx;
-                    start_column = start - pos;
-                }
-                if pos + line_text.len() >= end && end_line == 0 {
-                    end_line = line_idx;
-                    end_column = end - pos;
-                    break;
-                }
-                pos += line_text.len() + 1; // +1 for newline
-            }
-            
-            // Draw selection rectangles
-            for line in start_line..=end_line {
-                if line >= self.state.scroll_offset 
-                    && line < self.state.scroll_offset + (text_area.height / self.line_height) as usize {
-                    
-                    let y = text_area.y + (line - self.state.scroll_offset) as f32 * self.line_height;
-                    
-                    let (sel_start, sel_end) = if line == start_line && line == end_line {
-                        (start_column, end_column)
-                    } else if line == start_line {
-                        (start_column, lines[line].len())
-                    } else if line == end_line {
-                        (0, end_column)
-                    } else {
-                        (0, lines[line].len())
-                    };
-                    
-                    let x1 = text_area.x + sel_start as f32 * self.char_width;
-                    let x2 = text_area.x + sel_end as f32 * self.char_width;
-                    
-                    frame.fill_rectangle(
-                        Point::new(x1, y),
-                        Size::new(x2 - x1, self.line_height - 2.0),
-                        Color::from_rgba(0.2, 0.4, 0.8, 0.3)
-                    );
-                }
+    fn draw_selection(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle, range: std::ops::Range<usize>) {
+        let content = &self.state.content;
+        let start_line = content.offset_to_point(range.start).row;
+        let end_line = content.offset_to_point(range.end).row;
+        
+        // Draw selection rectangles
+        for line in start_line..=end_line {
+            if line >= self.state.scroll_offset 
+                && line < self.state.scroll_offset + (text_area.height / self.line_height) as usize {
+                
+                let y = text_area.y + (line - self.state.scroll_offset) as f32 * self.line_height;
+                let line_range = content.line_range(line);
+                let sel_start = range.start.max(line_range.start);
+                let sel_end = range.end.min(line_range.end);
+                
+                let x1 = text_area.x + content.offset_to_display_column(sel_start) as f32 * self.char_width;
+                let x2 = text_area.x + content.offset_to_display_column(sel_end) as f32 * self.char_width;
+                
+                frame.fill_rectangle(
+                    Point::new(x1, y),
+                    Size::new(x2 - x1, self.line_height - 2.0),
+                    Color::from_rgba(0.2, 0.4, 0.8, 0.3)
+                );
             }
         }
     }
@@ -823,7 +851,7 @@ impl ModernTextEditor {
     }
 
     fn draw_syntax_errors(&self, frame: &mut iced::widget::canvas::Frame, text_area: Rectangle) {
-        let errors = self.syntax_highlighter.validate_syntax(&self.state.content);
+        let errors = self.syntax_highlighter.validate_syntax(&self.state.text());
         
         for _error in errors {
             // Convert error range to visual position and draw underline
@@ -839,10 +867,24 @@ impl ModernTextEditor {
     }
 
     fn invalidate_cache(&mut self) {
+        self.sync_highlights();
         self.cache.clear();
         self.needs_redraw = true;
     }
 
+    /// Relex the lines changed since the last sync
+    fn sync_highlights(&mut self) {
+        let edits = self.state.take_edits();
+        let Some(highlights) = &mut self.highlights else { return };
+        if edits.is_empty() {
+            return;
+        }
+        for (range, len) in edits {
+            highlights.edit(range, len);
+        }
+        highlights.reparse(&self.state.content);
+    }
+
     pub fn set_font_size(&mut self, size: u16) {
         self.font_size = size;
         self.line_height = size as f32 * 1.4;
@@ -855,8 +897,49 @@ impl ModernTextEditor {
         self.invalidate_cache();
     }
 
-    pub fn get_content(&self) -> &str {
-        &self.state.content
+    pub fn get_content(&self) -> String {
+        self.state.text()
+    }
+
+    /// Replace the whole text, as one undoable step
+    pub fn set_content(&mut self, content: String) {
+        self.state.set_content(content);
+        self.invalidate_cache();
+    }
+
+    pub fn state(&self) -> &EditorState {
+        &self.state
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Offer the completions the user's shell has for command arguments
+    /// while editing shell text, running them in `cwd`
+    pub fn set_native_completions(&mut self, bridge: NativeCompletionBridge, cwd: impl Into<std::path::PathBuf>) {
+        self.completion_engine = CompletionEngine::new().with_native_completions(bridge).with_cwd(cwd);
+    }
+
+    /// Highlight as the registry language called `language`, or as plain
+    /// text with `None` or a name the registry does not know
+    pub fn set_language(&mut self, language: Option<String>) {
+        self.state.take_edits();
+        self.highlights = language
+            .as_deref()
+            .and_then(|name| LanguageRegistry::global().get(name))
+            .map(|language| HighlightMap::new(language, &self.state.content));
+        self.language = language;
+        self.invalidate_cache();
+    }
+
+    /// Put the cursor at the start of zero-based `row` and scroll it into view
+    pub fn move_to_line(&mut self, row: usize) {
+        let row = row.min(self.state.content.line_count().saturating_sub(1));
+        self.state.clear_selection();
+        self.state.move_cursor(self.state.content.line_start(row));
+        self.state.scroll_offset = row.saturating_sub(3);
+        self.invalidate_cache();
     }
 
     pub fn can_undo(&self) -> bool {
@@ -876,32 +959,14 @@ impl ModernTextEditor {
     
     /// Add a new cursor at the specified position for multi-cursor editing
     pub fn add_cursor(&mut self, position: usize) {
-        let lines = self.state.get_lines();
-        let mut line = 0;
-        let mut column = 0;
-        let mut pos = 0;
-        
-        for (i, line_text) in lines.iter().enumerate() {
-            if pos + line_text.len() >= position {
-                line = i;
-                column = position - pos;
-                break;
-            }
-            pos += line_text.len() + 1;
-        }
-        
-        self.multi_cursors.push(CursorState {
-            position,
-            line,
-            column,
-            selection_start: None,
-            selection_end: None,
-        });
+        self.state.add_cursor(position);
+        self.invalidate_cache();
     }
     
     /// Remove all multi-cursors
     pub fn clear_multi_cursors(&mut self) {
-        self.multi_cursors.clear();
+        self.state.clear_multiple_cursors();
+        self.invalidate_cache();
     }
     
     /// Search for text and return results
@@ -913,7 +978,7 @@ impl ModernTextEditor {
             return self.search_results.clone();
         }
         
-        let content = &self.state.content;
+        let content = &self.state.text();
         let lines = self.state.get_lines();
         let mut start = 0;
         
@@ -987,6 +1052,7 @@ impl ModernTextEditor {
             self.state.delete_range(start, end);
             self.state.cursor_position = start;
             self.state.insert_text(replacement);
+            self.invalidate_cache();
             true
         } else {
             false
@@ -1004,6 +1070,7 @@ impl ModernTextEditor {
             count += 1;
         }
         self.search_results.clear();
+        self.invalidate_cache();
         count
     }
     
@@ -1,17 +1,26 @@
+//! Key handling for a command-line editor built on [`EditorState`]: vim or
+//! readline editing, completion, bracket pairing and live validation.
+//!
+//! Hosts that own the buffer call [`TextInputHandler::handle_key_input`].
+//! The terminal's command input keeps its own text and cursor, so it goes
+//! through [`TextInputHandler::handle_line_key`] instead: the handler takes
+//! the line and cursor from the input, edits them, and the input shows the
+//! result. Keys neither vim nor readline take stay with the input.
+
 use crate::editor::{EditorAction, EditorState};
-use crate::editor::vim_mode::{VimModeManager, VimMode};
+use crate::editor::vim_mode::{VimKey, VimModeManager, VimMode};
+use crate::editor::readline::{self, Readline, ReadlineOutcome};
 use crate::editor::syntax_highlighter::{SyntaxHighlighter, HighlightedSpan};
 use crate::inspector::CommandInspector;
 use iced::keyboard::{Key, Modifiers};
-use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct TextInputHandler {
     pub editor_state: EditorState,
     pub vim_manager: VimModeManager,
+    pub readline: Readline,
     pub syntax_highlighter: SyntaxHighlighter,
     pub command_inspector: CommandInspector,
-    pub multi_cursors: Vec<usize>,
     pub auto_complete_enabled: bool,
     pub auto_indent_enabled: bool,
     pub bracket_matching_enabled: bool,
@@ -31,9 +40,9 @@ impl TextInputHandler {
         Self {
             editor_state: EditorState::new(),
             vim_manager: VimModeManager::new(),
+            readline: Readline::new(),
             syntax_highlighter: SyntaxHighlighter::new(),
             command_inspector: CommandInspector::new(),
-            multi_cursors: Vec::new(),
             auto_complete_enabled: true,
             auto_indent_enabled: true,
             bracket_matching_enabled: true,
@@ -48,20 +57,27 @@ impl TextInputHandler {
 
         // Handle Vim mode if enabled
         if self.vim_manager.is_enabled() {
-            if let Some(vim_action) = self.vim_manager.handle_key_press(key, modifiers) {
-                actions.push(vim_action);
-            }
-            
-            // In insert mode, allow normal text input
-            if self.vim_manager.current_mode() != &VimMode::Insert {
+            // Vim edits the buffer directly; keys it leaves alone in insert
+            // mode (such as Tab) still reach completion below
+            let consumed = self.vim_manager.handle_key(key, modifiers, &mut self.editor_state);
+            if consumed || self.vim_manager.current_mode() != &VimMode::Insert {
                 return actions;
             }
+        } else if self.readline.is_enabled() {
+            match self.readline.handle_key(key, modifiers, &mut self.editor_state) {
+                ReadlineOutcome::Handled => return actions,
+                ReadlineOutcome::EditInExternalEditor => {
+                    actions.push(EditorAction::EditInExternalEditor);
+                    return actions;
+                }
+                ReadlineOutcome::Unhandled => {}
+            }
         }
 
         // Handle special key combinations
         match (key, *modifiers) {
             // Auto-completion
-            (Key::Named(iced::keyboard::key::Named::Tab), Modifiers::empty()) => {
+            (Key::Named(iced::keyboard::key::Named::Tab), m) if m.is_empty() => {
                 if !self.current_completions.is_empty() {
                     actions.push(self.apply_completion());
                 } else {
@@ -75,7 +91,7 @@ impl TextInputHandler {
             },
 
             // Bracket auto-completion
-            (Key::Character(c), Modifiers::empty()) if self.is_opening_bracket(c) => {
+            (Key::Character(c), m) if m.is_empty() && self.is_opening_bracket(c) => {
                 if self.bracket_matching_enabled {
                     actions.extend(self.handle_bracket_input(c));
                 } else {
@@ -88,7 +104,7 @@ impl TextInputHandler {
                 actions.push(EditorAction::Insert(c.to_string()));
                 
                 // Trigger real-time validation
-                if self.command_inspector.enabled {
+                if self.command_inspector.is_enabled() {
                     actions.extend(self.validate_current_line());
                 }
             },
@@ -103,7 +119,7 @@ impl TextInputHandler {
             },
 
             // Backspace with smart deletion
-            (Key::Named(iced::keyboard::key::Named::Backspace), Modifiers::empty()) => {
+            (Key::Named(iced::keyboard::key::Named::Backspace), m) if m.is_empty() => {
                 actions.extend(self.smart_backspace());
             },
 
@@ -118,17 +134,47 @@ impl TextInputHandler {
             _ => {}
         }
 
-        // Update multi-cursors if any exist
-        if !self.multi_cursors.is_empty() {
-            actions.extend(self.apply_to_multi_cursors(&actions.clone()));
+        actions
+    }
+
+    /// Handles `key` for a host input showing `content` with its cursor at
+    /// byte `cursor`. Vim or readline, whichever is on, edits the line;
+    /// `Unhandled` leaves the key to the host's input.
+    pub fn handle_line_key(&mut self, content: &str, cursor: usize, key: &Key, modifiers: &Modifiers) -> ReadlineOutcome {
+        if self.editor_state.text() != content {
+            self.set_content(content.to_string());
         }
+        self.editor_state.move_cursor(cursor);
+        line_key(&mut self.vim_manager, &mut self.readline, &mut self.editor_state, key, modifiers)
+    }
 
-        actions
+    /// Whether [`Self::handle_line_key`] would take `key`, worked out on
+    /// copies so nothing changes
+    pub fn claims_line_key(&self, content: &str, cursor: usize, key: &Key, modifiers: &Modifiers) -> bool {
+        if !self.vim_manager.is_enabled() && !self.readline.is_enabled() {
+            return false;
+        }
+        let mut state = self.editor_state.clone();
+        if state.text() != content {
+            state.set_content(content.to_string());
+        }
+        state.move_cursor(cursor);
+        let outcome = line_key(&mut self.vim_manager.clone(), &mut self.readline.clone(), &mut state, key, modifiers);
+        outcome != ReadlineOutcome::Unhandled
+    }
+
+    /// Empty the line for the next command. Vim starts it in insert mode,
+    /// as a shell's vi mode starts each prompt.
+    pub fn start_line(&mut self) {
+        self.set_content(String::new());
+        if self.vim_manager.is_enabled() && self.vim_manager.current_mode() != &VimMode::Insert {
+            self.vim_manager.feed_keys("<Esc><Esc>i", &mut self.editor_state);
+        }
     }
 
     pub fn get_highlighted_content(&self) -> Vec<HighlightedSpan> {
         if self.editor_state.syntax_highlighting_enabled {
-            self.syntax_highlighter.highlight_command_line(&self.editor_state.content)
+            self.syntax_highlighter.highlight_command_line(&self.editor_state.text())
         } else {
             Vec::new()
         }
@@ -146,6 +192,33 @@ impl TextInputHandler {
         self.vim_manager.is_enabled()
     }
 
+    /// Readline (emacs) editing, used when vim mode is off
+    pub fn set_emacs_mode(&mut self, enabled: bool) {
+        if enabled {
+            self.readline.enable();
+        } else {
+            self.readline.disable();
+        }
+    }
+
+    pub fn is_emacs_enabled(&self) -> bool {
+        self.readline.is_enabled()
+    }
+
+    /// The external edit asked for with [`EditorAction::EditInExternalEditor`].
+    /// The host runs it with `Command::perform` and hands the result to
+    /// [`Self::finish_external_edit`].
+    pub fn external_edit(&self) -> impl std::future::Future<Output = Result<String, String>> + Send + 'static {
+        readline::edit_in_external_editor(self.editor_state.text())
+    }
+
+    pub fn finish_external_edit(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(text) => self.readline.finish_external_edit(&mut self.editor_state, &text),
+            Err(e) => tracing::warn!("External editor failed: {}", e),
+        }
+    }
+
     pub fn get_current_vim_mode(&self) -> String {
         if self.vim_manager.is_enabled() {
             self.vim_manager.get_mode_display().to_string()
@@ -189,7 +262,7 @@ impl TextInputHandler {
     }
 
     fn is_quote_already_closed(&self, quote: &str) -> bool {
-        let content = &self.editor_state.content;
+        let content = &self.editor_state.text();
         let before_cursor = &content[..self.editor_state.cursor_position];
         let after_cursor = &content[self.editor_state.cursor_position..];
         
@@ -207,7 +280,7 @@ impl TextInputHandler {
         
         // Calculate indentation based on previous line
         let current_line_text = self.editor_state.get_current_line_text();
-        let indentation = self.calculate_indentation(current_line_text);
+        let indentation = self.calculate_indentation(&current_line_text);
         
         if !indentation.is_empty() {
             actions.push(EditorAction::Insert(indentation));
@@ -243,8 +316,9 @@ impl TextInputHandler {
         // Check if we're deleting a bracket pair
         if self.bracket_matching_enabled && self.editor_state.cursor_position > 0 {
             let content = &self.editor_state.content;
-            let before_cursor = content.chars().nth(self.editor_state.cursor_position - 1);
-            let at_cursor = content.chars().nth(self.editor_state.cursor_position);
+            let cursor = self.editor_state.cursor_position;
+            let before_cursor = content.char_at(content.clip_offset(cursor - 1));
+            let at_cursor = content.char_at(cursor);
             
             if let (Some(before), Some(at)) = (before_cursor, at_cursor) {
                 let before_str = before.to_string();
@@ -288,9 +362,7 @@ impl TextInputHandler {
         
         // Get completions from command inspector
         let current_dir = std::env::current_dir().unwrap_or_default();
-        self.current_completions = self.command_inspector
-            .command_executor
-            .get_completions(&current_word, &current_dir);
+        self.current_completions = self.command_inspector.completions(&current_word, &current_dir);
         
         if !self.current_completions.is_empty() {
             self.completion_index = Some(0);
@@ -320,7 +392,7 @@ impl TextInputHandler {
     }
 
     fn get_current_word(&self) -> String {
-        let content = &self.editor_state.content;
+        let content = &self.editor_state.text();
         let cursor = self.editor_state.cursor_position;
         
         let word_start = content[..cursor]
@@ -332,7 +404,7 @@ impl TextInputHandler {
     }
 
     fn find_word_start(&self) -> usize {
-        let content = &self.editor_state.content;
+        let content = &self.editor_state.text();
         let cursor = self.editor_state.cursor_position;
         
         content[..cursor]
@@ -343,44 +415,22 @@ impl TextInputHandler {
 
     fn validate_current_line(&self) -> Vec<EditorAction> {
         let current_line = self.editor_state.get_current_line_text();
-        self.command_inspector.inspect_command(current_line)
-    }
-
-    fn apply_to_multi_cursors(&self, actions: &[EditorAction]) -> Vec<EditorAction> {
-        let mut multi_actions = Vec::new();
-        
-        for &cursor_pos in &self.multi_cursors {
-            for action in actions {
-                match action {
-                    EditorAction::Insert(text) => {
-                        multi_actions.push(EditorAction::Replace(cursor_pos, 0, text.clone()));
-                    },
-                    EditorAction::Delete(start, length) => {
-                        let adjusted_start = cursor_pos + start - self.editor_state.cursor_position;
-                        multi_actions.push(EditorAction::Delete(adjusted_start, *length));
-                    },
-                    _ => {} // Other actions don't apply to multi-cursors
-                }
-            }
-        }
-        
-        multi_actions
+        self.command_inspector.inspect_command(&current_line)
     }
 
+    /// Extra cursors live in the [`EditorState`], whose edits apply at
+    /// every cursor as one undo step
     pub fn add_cursor(&mut self, position: usize) {
-        if !self.multi_cursors.contains(&position) {
-            self.multi_cursors.push(position);
-        }
+        self.editor_state.add_cursor(position);
     }
 
     pub fn clear_multi_cursors(&mut self) {
-        self.multi_cursors.clear();
+        self.editor_state.clear_multiple_cursors();
     }
 
+    /// Every cursor in document order
     pub fn get_cursor_positions(&self) -> Vec<usize> {
-        let mut positions = vec![self.editor_state.cursor_position];
-        positions.extend(&self.multi_cursors);
-        positions
+        self.editor_state.selections().iter().map(|selection| selection.head).collect()
     }
 
     pub fn set_content(&mut self, content: String) {
@@ -390,8 +440,8 @@ impl TextInputHandler {
         self.completion_index = None;
     }
 
-    pub fn get_content(&self) -> &str {
-        &self.editor_state.content
+    pub fn get_content(&self) -> String {
+        self.editor_state.text()
     }
 
     pub fn get_cursor_position(&self) -> usize {
@@ -424,6 +474,36 @@ impl TextInputHandler {
     }
 }
 
+/// A key on a single-line input. Enter, Tab, Up and Down stay with the
+/// host, which runs, completes and walks history with them, except while
+/// vim reads a `:` command line. Vim's normal and visual modes swallow any
+/// other key they have no use for, as vim does.
+fn line_key(
+    vim: &mut VimModeManager,
+    readline: &mut Readline,
+    state: &mut EditorState,
+    key: &Key,
+    modifiers: &Modifiers,
+) -> ReadlineOutcome {
+    if vim.is_enabled() {
+        let Some(vim_key) = VimKey::from_key(key, *modifiers) else {
+            return ReadlineOutcome::Unhandled;
+        };
+        let host_key = matches!(vim_key, VimKey::Enter | VimKey::Tab | VimKey::Up | VimKey::Down);
+        if host_key && vim.current_mode() != &VimMode::Command {
+            return ReadlineOutcome::Unhandled;
+        }
+        let consumed = vim.handle_key(key, modifiers, state);
+        if consumed || vim.current_mode() != &VimMode::Insert {
+            ReadlineOutcome::Handled
+        } else {
+            ReadlineOutcome::Unhandled
+        }
+    } else {
+        readline.handle_key(key, modifiers, state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,15 +535,55 @@ mod tests {
         
         assert!(handler.is_vim_enabled());
         assert_eq!(handler.get_current_vim_mode(), "NORMAL");
+
+        for key in ["i", "l", "s", " ", "-", "l", "a"] {
+            handler.handle_key_input(&Key::Character(key.into()), &Modifiers::empty());
+        }
+        handler.handle_key_input(&Key::Named(iced::keyboard::key::Named::Escape), &Modifiers::empty());
+        for key in ["0", "d", "w"] {
+            assert!(handler.handle_key_input(&Key::Character(key.into()), &Modifiers::empty()).is_empty());
+        }
+        assert_eq!(handler.editor_state.content, "-la");
+        assert_eq!(handler.get_current_vim_mode(), "NORMAL");
+    }
+
+    #[test]
+    fn test_emacs_mode_integration() {
+        let mut handler = TextInputHandler::new();
+        handler.set_emacs_mode(true);
+        assert!(handler.is_emacs_enabled());
+
+        for key in ["l", "s", "-"] {
+            assert!(handler.handle_key_input(&Key::Character(key.into()), &Modifiers::empty()).is_empty());
+        }
+        handler.handle_key_input(&Key::Character("a".into()), &Modifiers::CTRL);
+        handler.handle_key_input(&Key::Character("k".into()), &Modifiers::CTRL);
+        handler.handle_key_input(&Key::Character("y".into()), &Modifiers::CTRL);
+        handler.handle_key_input(&Key::Character("y".into()), &Modifiers::CTRL);
+        assert_eq!(handler.editor_state.content, "ls-ls-");
+        assert_eq!(handler.readline.kill_ring().len(), 1);
+
+        // The external editor is left to the host instead of blocking here
+        handler.handle_key_input(&Key::Character("x".into()), &Modifiers::CTRL);
+        let actions = handler.handle_key_input(&Key::Character("e".into()), &Modifiers::CTRL);
+        assert!(matches!(actions.as_slice(), [EditorAction::EditInExternalEditor]));
+        handler.finish_external_edit(Ok("ls -la".to_string()));
+        assert_eq!(handler.editor_state.content, "ls -la");
     }
 
     #[test]
     fn test_multi_cursor() {
         let mut handler = TextInputHandler::new();
+        handler.set_content("echo a\necho b\necho c".to_string());
         handler.add_cursor(10);
         handler.add_cursor(20);
-        
-        let positions = handler.get_cursor_positions();
-        assert_eq!(positions.len(), 3); // Main cursor + 2 additional
+        handler.add_cursor(20);
+        assert_eq!(handler.get_cursor_positions(), vec![0, 10, 20]);
+
+        handler.editor_state.insert_text("#");
+        assert_eq!(handler.get_content(), "#echo a\nech#o b\necho c#");
+
+        handler.clear_multi_cursors();
+        assert_eq!(handler.get_cursor_positions().len(), 1);
     }
 }
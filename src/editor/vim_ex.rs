@@ -0,0 +1,482 @@
+//! `:` command-line support for the vim emulation.
+//!
+//! Only the ex commands that make sense when editing a command line are
+//! supported: `:s` (substitute) with the usual ranges and flags, and jumping
+//! to a line with `:N`. Vim patterns are translated to [`regex`] syntax.
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use crate::editor::vim_motion::{line_count, line_end, line_start_of};
+
+/// Errors carry vim's own error numbers so the messages read the same.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ExError {
+    #[error("E492: Not an editor command: {0}")]
+    NotAnEditorCommand(String),
+    #[error("E16: Invalid range")]
+    InvalidRange,
+    #[error("E20: Mark not set")]
+    MarkNotSet,
+    #[error("E35: No previous regular expression")]
+    NoPreviousPattern,
+    #[error("E383: Invalid search string: {0}")]
+    InvalidPattern(String),
+    #[error("E486: Pattern not found: {0}")]
+    PatternNotFound(String),
+    #[error("E488: Trailing characters: {0}")]
+    TrailingCharacters(String),
+}
+
+/// Editor state an ex command line is evaluated against. Lines are
+/// zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExContext {
+    pub current_line: usize,
+    pub last_line: usize,
+    /// Lines of the last visual selection, for `'<` and `'>`.
+    pub visual: Option<(usize, usize)>,
+}
+
+/// Zero-based, inclusive line range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitute {
+    pub range: LineRange,
+    /// The pattern in vim syntax; empty reuses the last search pattern.
+    pub pattern: String,
+    pub replacement: String,
+    /// `g`: replace every match on a line, not just the first.
+    pub global: bool,
+    /// `i` / `I`: force case-insensitive or case-sensitive matching.
+    pub ignore_case: Option<bool>,
+    /// `n`: report the number of matches without substituting.
+    pub count_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExCommand {
+    Substitute(Substitute),
+    GotoLine(usize),
+    /// An empty command line.
+    Nothing,
+}
+
+/// The outcome of a substitution: `start..end` of the original text is
+/// replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub matches: usize,
+    pub lines: usize,
+    /// Line of the last substitution in the new text, where the cursor goes.
+    pub last_line: usize,
+}
+
+/// Parses a command line (without the leading `:`).
+pub fn parse(input: &str, ctx: &ExContext) -> Result<ExCommand, ExError> {
+    let input = input.trim_start_matches([' ', ':']);
+    let mut chars = input.char_indices().peekable();
+    let range = parse_range(input, &mut chars, ctx)?;
+    let rest = chars.peek().map_or("", |&(i, _)| &input[i..]).trim_start();
+
+    if rest.is_empty() {
+        return Ok(match range {
+            Some(range) => ExCommand::GotoLine(range.end),
+            None => ExCommand::Nothing,
+        });
+    }
+
+    let name_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if name.is_empty() || !"substitute".starts_with(name) {
+        return Err(ExError::NotAnEditorCommand(input.to_string()));
+    }
+    let args = &rest[name_len..];
+    let delimiter = args
+        .chars()
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && !matches!(c, '\\' | '"' | '|'))
+        .ok_or_else(|| ExError::NotAnEditorCommand(input.to_string()))?;
+
+    let body = &args[delimiter.len_utf8()..];
+    let (pattern, body) = split_at_delimiter(body, delimiter);
+    let (replacement, flags) = body.map_or((String::new(), ""), |body| {
+        let (replacement, flags) = split_at_delimiter(body, delimiter);
+        (replacement, flags.unwrap_or(""))
+    });
+
+    let mut substitute = Substitute {
+        range: range.unwrap_or(LineRange { start: ctx.current_line, end: ctx.current_line }),
+        pattern,
+        replacement,
+        global: false,
+        ignore_case: None,
+        count_only: false,
+    };
+    for flag in flags.trim_end().chars() {
+        match flag {
+            'g' => substitute.global = !substitute.global,
+            'i' => substitute.ignore_case = Some(true),
+            'I' => substitute.ignore_case = Some(false),
+            'n' => substitute.count_only = true,
+            '&' | 'e' => {}
+            _ => return Err(ExError::TrailingCharacters(flags.to_string())),
+        }
+    }
+    Ok(ExCommand::Substitute(substitute))
+}
+
+/// Splits `text` at the first unescaped `delimiter`, unescaping `\<delimiter>`
+/// in the part before it.
+fn split_at_delimiter(text: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut out = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == delimiter {
+            return (out, Some(&text[i + c.len_utf8()..]));
+        }
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => out.push(next),
+                Some((_, next)) => {
+                    out.push('\\');
+                    out.push(next);
+                }
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    (out, None)
+}
+
+fn parse_range(
+    input: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    ctx: &ExContext,
+) -> Result<Option<LineRange>, ExError> {
+    if chars.next_if(|&(_, c)| c == '%').is_some() {
+        return Ok(Some(LineRange { start: 0, end: ctx.last_line }));
+    }
+    let Some(start) = parse_address(input, chars, ctx)? else {
+        return Ok(None);
+    };
+    let end = if chars.next_if(|&(_, c)| c == ',' || c == ';').is_some() {
+        parse_address(input, chars, ctx)?.unwrap_or(ctx.current_line)
+    } else {
+        start
+    };
+    if start.max(end) > ctx.last_line {
+        return Err(ExError::InvalidRange);
+    }
+    Ok(Some(LineRange { start: start.min(end), end: start.max(end) }))
+}
+
+fn parse_address(
+    input: &str,
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    ctx: &ExContext,
+) -> Result<Option<usize>, ExError> {
+    let number = |chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>| {
+        let start = chars.peek()?.0;
+        let mut end = start;
+        while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            end = i + c.len_utf8();
+        }
+        input[start..end].parse::<usize>().ok()
+    };
+
+    let mut line = match chars.peek().map(|&(_, c)| c) {
+        Some('.') => {
+            chars.next();
+            Some(ctx.current_line as isize)
+        }
+        Some('$') => {
+            chars.next();
+            Some(ctx.last_line as isize)
+        }
+        Some('\'') => {
+            chars.next();
+            let (start, end) = ctx.visual.ok_or(ExError::MarkNotSet)?;
+            match chars.next().map(|(_, c)| c) {
+                Some('<') => Some(start as isize),
+                Some('>') => Some(end as isize),
+                _ => return Err(ExError::MarkNotSet),
+            }
+        }
+        Some(c) if c.is_ascii_digit() => number(chars).map(|n| n.max(1) as isize - 1),
+        _ => None,
+    };
+
+    while let Some((_, sign)) = chars.next_if(|&(_, c)| c == '+' || c == '-') {
+        let offset = number(chars).unwrap_or(1) as isize;
+        let base = line.unwrap_or(ctx.current_line as isize);
+        line = Some(if sign == '+' { base + offset } else { base - offset });
+    }
+
+    match line {
+        Some(line) if line < 0 => Err(ExError::InvalidRange),
+        Some(line) => Ok(Some(line as usize)),
+        None => Ok(None),
+    }
+}
+
+/// Compiles a vim ("magic") pattern. `\v` switches to very magic syntax and
+/// `\c` / `\C` override `ignore_case`.
+pub fn compile_pattern(pattern: &str, ignore_case: bool) -> Result<Regex, ExError> {
+    let (translated, case) = translate_pattern(pattern);
+    RegexBuilder::new(&translated)
+        .case_insensitive(case.unwrap_or(ignore_case))
+        .build()
+        .map_err(|_| ExError::InvalidPattern(pattern.to_string()))
+}
+
+fn translate_pattern(pattern: &str) -> (String, Option<bool>) {
+    let mut out = String::new();
+    let mut very_magic = false;
+    let mut ignore_case = None;
+    let mut chars = pattern.chars().peekable();
+    let literal = |out: &mut String, c: char| out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+
+    while let Some(c) = chars.next() {
+        if c == '[' {
+            // Character classes share their syntax with regex
+            out.push(c);
+            if let Some(first) = chars.next_if(|&c| c == '^' || c == ']') {
+                out.push(first);
+            }
+            for c in chars.by_ref() {
+                out.push(c);
+                if c == ']' {
+                    break;
+                }
+            }
+            continue;
+        }
+        let special = if c == '\\' {
+            let Some(escaped) = chars.next() else {
+                out.push_str("\\\\");
+                break;
+            };
+            match escaped {
+                'v' => {
+                    very_magic = true;
+                    continue;
+                }
+                'm' | 'M' | 'V' => {
+                    very_magic = false;
+                    continue;
+                }
+                'c' => {
+                    ignore_case = Some(true);
+                    continue;
+                }
+                'C' => {
+                    ignore_case = Some(false);
+                    continue;
+                }
+                'n' => {
+                    out.push_str("\\n");
+                    continue;
+                }
+                't' => {
+                    out.push_str("\\t");
+                    continue;
+                }
+                's' | 'S' | 'd' | 'D' | 'w' | 'W' => {
+                    out.push('\\');
+                    out.push(escaped);
+                    continue;
+                }
+                '<' | '>' if !very_magic => {
+                    out.push_str("\\b");
+                    continue;
+                }
+                '(' | ')' | '|' | '+' | '?' | '=' | '{' if !very_magic => Some(escaped),
+                _ => {
+                    literal(&mut out, escaped);
+                    continue;
+                }
+            }
+        } else if very_magic && "()|+?={<>".contains(c) {
+            Some(c)
+        } else {
+            None
+        };
+
+        match special {
+            Some('<' | '>') => out.push_str("\\b"),
+            Some('=') => out.push('?'),
+            Some('{') => {
+                let mut inner: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let lazy = inner.starts_with('-');
+                if lazy {
+                    inner.remove(0);
+                }
+                if inner.is_empty() {
+                    out.push('*');
+                } else {
+                    out.push('{');
+                    out.push_str(&inner);
+                    out.push('}');
+                }
+                if lazy {
+                    out.push('?');
+                }
+            }
+            Some(c) => out.push(c),
+            None if "(){}|+?".contains(c) => literal(&mut out, c),
+            None => out.push(c),
+        }
+    }
+    (out, ignore_case)
+}
+
+/// Translates a vim replacement string (`&`, `\1`, `\r`) into the
+/// [`Regex::replace`] syntax.
+fn translate_replacement(replacement: &str) -> String {
+    let mut out = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => {
+                    out.push_str("${");
+                    out.push(d);
+                    out.push('}');
+                }
+                Some('n' | 'r') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Applies `substitute` to `text`, line by line as vim does.
+pub fn substitute(text: &str, substitute: &Substitute, regex: &Regex) -> Result<Substitution, ExError> {
+    let last = line_count(text) - 1;
+    if substitute.range.start > last {
+        return Err(ExError::InvalidRange);
+    }
+    let end_line = substitute.range.end.min(last);
+    let start = line_start_of(text, substitute.range.start);
+    let end = line_end(text, line_start_of(text, end_line));
+    let replacement = translate_replacement(&substitute.replacement);
+
+    let mut out = String::new();
+    let mut matches = 0;
+    let mut lines = 0;
+    let mut new_line = substitute.range.start;
+    let mut last_line = substitute.range.start;
+    for (i, line) in text[start..end].split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+            new_line += 1;
+        }
+        let found = if substitute.global { regex.find_iter(line).count() } else { usize::from(regex.is_match(line)) };
+        if found == 0 || substitute.count_only {
+            matches += found;
+            lines += usize::from(found > 0);
+            out.push_str(line);
+            continue;
+        }
+        matches += found;
+        lines += 1;
+        let replaced = regex.replacen(line, if substitute.global { 0 } else { 1 }, replacement.as_str());
+        new_line += replaced.matches('\n').count();
+        last_line = new_line;
+        out.push_str(&replaced);
+    }
+
+    if matches == 0 {
+        return Err(ExError::PatternNotFound(substitute.pattern.clone()));
+    }
+    Ok(Substitution { start, end, text: out, matches, lines, last_line })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> ExContext {
+        ExContext { current_line: 1, last_line: 3, visual: Some((2, 3)) }
+    }
+
+    fn parse_substitute(input: &str) -> Substitute {
+        match parse(input, &ctx()) {
+            Ok(ExCommand::Substitute(substitute)) => substitute,
+            other => panic!("expected a substitute, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_ranges_and_flags() {
+        let s = parse_substitute("%s/foo/bar/gi");
+        assert_eq!(s.range, LineRange { start: 0, end: 3 });
+        assert_eq!((s.pattern.as_str(), s.replacement.as_str()), ("foo", "bar"));
+        assert!(s.global);
+        assert_eq!(s.ignore_case, Some(true));
+
+        assert_eq!(parse_substitute("'<,'>s#a\\#b#c#").range, LineRange { start: 2, end: 3 });
+        assert_eq!(parse_substitute("'<,'>s#a\\#b#c#").pattern, "a#b");
+        assert_eq!(parse_substitute("s/x/y").range, LineRange { start: 1, end: 1 });
+        assert_eq!(parse_substitute(".,$s/x/y/").range, LineRange { start: 1, end: 3 });
+        assert_eq!(parse_substitute("1,.+1s/x/y/").range, LineRange { start: 0, end: 2 });
+
+        assert_eq!(parse("3", &ctx()), Ok(ExCommand::GotoLine(2)));
+        assert_eq!(parse("", &ctx()), Ok(ExCommand::Nothing));
+        assert_eq!(parse("9s/x/y/", &ctx()), Err(ExError::InvalidRange));
+        assert_eq!(parse("wq", &ctx()), Err(ExError::NotAnEditorCommand("wq".to_string())));
+        assert_eq!(parse("s/x/y/q", &ctx()), Err(ExError::TrailingCharacters("q".to_string())));
+    }
+
+    #[test]
+    fn test_pattern_translation() {
+        assert_eq!(translate_pattern("\\(foo\\|bar\\)\\+").0, "(foo|bar)+");
+        assert_eq!(translate_pattern("a(b)+c?").0, "a\\(b\\)\\+c\\?");
+        assert_eq!(translate_pattern("\\<x\\{2,3}\\>").0, "\\bx{2,3}\\b");
+        assert_eq!(translate_pattern("\\v(a|b){-1,}").0, "(a|b){1,}?");
+        assert_eq!(translate_pattern("[(]\\.").0, "[(]\\.");
+        assert_eq!(translate_pattern("\\cfoo"), ("foo".to_string(), Some(true)));
+        assert_eq!(translate_replacement("<&> \\1 $x\\r"), "<${0}> ${1} $$x\n");
+    }
+
+    #[test]
+    fn test_substitute_lines() {
+        let text = "echo foo foo\necho foo\necho bar";
+        let s = Substitute {
+            range: LineRange { start: 0, end: 2 },
+            pattern: "foo".to_string(),
+            replacement: "[&]".to_string(),
+            global: false,
+            ignore_case: None,
+            count_only: false,
+        };
+        let regex = compile_pattern(&s.pattern, false).unwrap();
+        let result = substitute(text, &s, &regex).unwrap();
+        assert_eq!(result.text, "echo [foo] foo\necho [foo]\necho bar");
+        assert_eq!((result.matches, result.lines, result.last_line), (2, 2, 1));
+
+        let global = Substitute { global: true, ..s.clone() };
+        assert_eq!(substitute(text, &global, &regex).unwrap().matches, 3);
+
+        let missing = Substitute { range: LineRange { start: 2, end: 2 }, ..s };
+        assert_eq!(substitute(text, &missing, &regex), Err(ExError::PatternNotFound("foo".to_string())));
+    }
+}
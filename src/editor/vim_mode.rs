@@ -1,79 +1,452 @@
-use iced::keyboard::{Key, Modifiers};
+//! Vim emulation for the input editor.
+//!
+//! [`VimModeManager`] implements vim's command grammar directly on an
+//! [`EditorState`]: `["x][count]operator[count]motion`, text objects, named
+//! and clipboard registers, `.` repeat, visual, visual-line and visual-block
+//! modes, and the `/`, `?` and `:` command lines. Motions and text objects
+//! live in [`vim_motion`](super::vim_motion), ex commands in
+//! [`vim_ex`](super::vim_ex).
+//!
+//! Each change is recorded as one undo step, and its keys (minus the count)
+//! are kept so `.` can replay them with either the original or a new count.
+//!
+//! [`TextInputHandler`](super::text_input::TextInputHandler) hosts it; the
+//! terminal's command input does not go through it yet.
+
 use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use regex::Regex;
 use std::collections::HashMap;
-use crate::editor::EditorAction;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use crate::editor::vim_ex::{self, ExCommand, ExContext, ExError};
+use crate::editor::vim_motion::{
+    self, char_at, clamp_normal, column, first_non_blank, line_count, line_end, line_index, line_start,
+    line_start_of, next_char, pos_at_column, prev_char, FindChar, Motion, MotionKind,
+};
+use crate::editor::EditorState;
+use crate::string_offset::floor_char_boundary;
+
+/// Columns added or removed by `>` and `<`.
+const SHIFT_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VimMode {
     Normal,
     Insert,
     Visual,
+    VisualLine,
+    VisualBlock,
     Command,
 }
 
+/// A key as the vim grammar sees it, independent of iced's key types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimKey {
+    Char(char),
+    Ctrl(char),
+    Escape,
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+impl VimKey {
+    pub fn from_key(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        let key = match key {
+            Key::Named(named) => match named {
+                Named::Escape => Self::Escape,
+                Named::Enter => Self::Enter,
+                Named::Backspace => Self::Backspace,
+                Named::Delete => Self::Delete,
+                Named::Tab => Self::Tab,
+                Named::ArrowLeft => Self::Left,
+                Named::ArrowRight => Self::Right,
+                Named::ArrowUp => Self::Up,
+                Named::ArrowDown => Self::Down,
+                Named::Home => Self::Home,
+                Named::End => Self::End,
+                Named::Space => Self::Char(' '),
+                _ => return None,
+            },
+            Key::Character(text) => {
+                let mut chars = text.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() || modifiers.logo() || modifiers.alt() {
+                    return None;
+                }
+                if modifiers.control() {
+                    if c == '[' { Self::Escape } else { Self::Ctrl(c.to_ascii_lowercase()) }
+                } else {
+                    Self::Char(c)
+                }
+            }
+            Key::Unidentified => return None,
+        };
+        Some(key)
+    }
+
+    /// Parses vim key notation such as `d2w`, `ci"` or `ifoo<Esc>`.
+    pub fn parse(keys: &str) -> Vec<Self> {
+        let mut out = Vec::new();
+        let mut rest = keys;
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                if let Some((key, len)) = rest.find('>').and_then(|end| Self::from_name(&rest[1..end]).map(|key| (key, end + 1))) {
+                    out.push(key);
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+            out.push(Self::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+        out
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Some(match name.as_str() {
+            "esc" => Self::Escape,
+            "cr" | "enter" | "return" => Self::Enter,
+            "bs" => Self::Backspace,
+            "del" => Self::Delete,
+            "tab" => Self::Tab,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "up" => Self::Up,
+            "down" => Self::Down,
+            "home" => Self::Home,
+            "end" => Self::End,
+            "lt" => Self::Char('<'),
+            "space" => Self::Char(' '),
+            _ => {
+                let c = name.strip_prefix("c-")?;
+                let mut chars = c.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                Self::Ctrl(c)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    Charwise,
+    /// Whole lines; the text ends with a newline.
+    Linewise,
+    /// A visual-block selection; one line of the block per line of text.
+    Blockwise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+/// Where the `"+` and `"*` registers read and write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardBackend {
+    System,
+    /// An in-process clipboard, for tests and headless use.
+    Memory(Option<String>),
+}
+
+/// Vim's registers: the unnamed register, `a`-`z` (appended to through
+/// `A`-`Z`), the yank register `0`, the delete history `1`-`9`, the small
+/// delete register `-`, the black hole `_` and the clipboard `+`/`*`.
 #[derive(Debug, Clone)]
-pub struct VimModeManager {
-    current_mode: VimMode,
-    keybindings: HashMap<VimMode, HashMap<Key, VimCommand>>,
-    pending_keys: Vec<Key>,
-    repeat_count: Option<usize>,
-    last_command: Option<VimCommand>,
-    enabled: bool,
+pub struct Registers {
+    unnamed: Option<Register>,
+    named: HashMap<char, Register>,
+    clipboard: ClipboardBackend,
+    /// The last register written to the clipboard, so reading it back keeps
+    /// its kind.
+    clipboard_written: Option<Register>,
+}
+
+impl Registers {
+    pub fn new(clipboard: ClipboardBackend) -> Self {
+        Self { unnamed: None, named: HashMap::new(), clipboard, clipboard_written: None }
+    }
+
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric() || "\"-_+*".contains(name)
+    }
+
+    pub fn get(&self, name: Option<char>) -> Option<Register> {
+        match name {
+            None | Some('"') => self.unnamed.clone(),
+            Some('+' | '*') => self.read_clipboard(),
+            Some(name) => self.named.get(&name.to_ascii_lowercase()).cloned(),
+        }
+    }
+
+    /// Stores yanked (`deleted == false`) or deleted text in `name`, or in
+    /// the numbered registers when no register was given.
+    pub fn store(&mut self, name: Option<char>, register: Register, deleted: bool) {
+        let register = match name {
+            Some('_') => return,
+            Some('+' | '*') => {
+                self.write_clipboard(&register);
+                register
+            }
+            Some(name) if name.is_ascii_uppercase() => {
+                let name = name.to_ascii_lowercase();
+                let merged = match self.named.remove(&name) {
+                    Some(mut existing) if existing.kind == RegisterKind::Linewise || register.kind == RegisterKind::Linewise => {
+                        if !existing.text.ends_with('\n') {
+                            existing.text.push('\n');
+                        }
+                        existing.text.push_str(&register.text);
+                        if !existing.text.ends_with('\n') {
+                            existing.text.push('\n');
+                        }
+                        existing.kind = RegisterKind::Linewise;
+                        existing
+                    }
+                    Some(mut existing) => {
+                        existing.text.push_str(&register.text);
+                        existing
+                    }
+                    None => register,
+                };
+                self.named.insert(name, merged.clone());
+                merged
+            }
+            Some(name) if name.is_ascii_lowercase() => {
+                self.named.insert(name, register.clone());
+                register
+            }
+            _ if deleted => {
+                if register.kind != RegisterKind::Charwise || register.text.contains('\n') {
+                    for n in (1..9u32).rev() {
+                        if let Some(older) = self.named.remove(&digit(n)) {
+                            self.named.insert(digit(n + 1), older);
+                        }
+                    }
+                    self.named.insert('1', register.clone());
+                } else {
+                    self.named.insert('-', register.clone());
+                }
+                register
+            }
+            _ => {
+                self.named.insert('0', register.clone());
+                register
+            }
+        };
+        self.unnamed = Some(register);
+    }
+
+    fn read_clipboard(&self) -> Option<Register> {
+        let text = match &self.clipboard {
+            ClipboardBackend::System => arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).ok()?,
+            ClipboardBackend::Memory(text) => text.clone()?,
+        };
+        if let Some(written) = self.clipboard_written.as_ref().filter(|written| written.text == text) {
+            return Some(written.clone());
+        }
+        let kind = if text.ends_with('\n') { RegisterKind::Linewise } else { RegisterKind::Charwise };
+        Some(Register { text, kind })
+    }
+
+    fn write_clipboard(&mut self, register: &Register) {
+        match &mut self.clipboard {
+            ClipboardBackend::System => {
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    if let Err(e) = clipboard.set_text(register.text.clone()) {
+                        tracing::warn!("Failed to write the vim clipboard register: {}", e);
+                    }
+                }
+            }
+            ClipboardBackend::Memory(text) => *text = Some(register.text.clone()),
+        }
+        self.clipboard_written = Some(register.clone());
+    }
+}
+
+fn digit(n: u32) -> char {
+    char::from_digit(n, 10).unwrap_or('9')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+    ToggleCase,
+    Lowercase,
+    Uppercase,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        Some(match c {
+            'd' => Self::Delete,
+            'c' => Self::Change,
+            'y' => Self::Yank,
+            '>' => Self::Indent,
+            '<' => Self::Outdent,
+            _ => return None,
+        })
+    }
+
+    fn case_from_char(c: char) -> Option<Self> {
+        Some(match c {
+            '~' => Self::ToggleCase,
+            'u' => Self::Lowercase,
+            'U' => Self::Uppercase,
+            _ => return None,
+        })
+    }
+
+    /// The key that, typed again, applies the operator to whole lines
+    /// (`dd`, `>>`, `g~~`).
+    fn line_key(self) -> char {
+        match self {
+            Self::Delete => 'd',
+            Self::Change => 'c',
+            Self::Yank => 'y',
+            Self::Indent => '>',
+            Self::Outdent => '<',
+            Self::ToggleCase => '~',
+            Self::Lowercase => 'u',
+            Self::Uppercase => 'U',
+        }
+    }
+
+    fn transform(self, text: &str) -> String {
+        match self {
+            Self::ToggleCase => text
+                .chars()
+                .flat_map(|c| -> Box<dyn Iterator<Item = char>> {
+                    if c.is_uppercase() { Box::new(c.to_lowercase()) } else { Box::new(c.to_uppercase()) }
+                })
+                .collect(),
+            Self::Lowercase => text.to_lowercase(),
+            Self::Uppercase => text.to_uppercase(),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// What the next key completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Awaiting {
+    Register,
+    Find { forward: bool, till: bool },
+    TextObject { around: bool },
+    GPrefix,
+    Replace,
+}
+
+/// The text an operator applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    /// `start..end`, end exclusive.
+    Charwise { start: usize, end: usize },
+    /// Every line touched by `start` and `end`.
+    Linewise { start: usize, end: usize },
+    Blockwise { anchor: usize, cursor: usize, to_eol: bool },
+}
+
+/// The command typed so far in normal, visual or operator-pending mode.
+#[derive(Debug, Clone, Default)]
+struct Pending {
+    /// Keys typed, without the count, for dot-repeat.
+    keys: Vec<VimKey>,
+    register: Option<char>,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    operator_count: Option<usize>,
+    awaiting: Option<Awaiting>,
+}
+
+impl Pending {
+    /// The effective count; `2d3w` deletes six words.
+    fn count(&self) -> Option<usize> {
+        match (self.count, self.operator_count) {
+            (None, None) => None,
+            (count, operator_count) => Some(count.unwrap_or(1).saturating_mul(operator_count.unwrap_or(1))),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.count.is_none()
+    }
+}
+
+/// The size of the visual selection a change was made on, so `.` can apply
+/// it to the same amount of text at the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct VisualExtent {
+    mode: VimMode,
+    lines: usize,
+    columns: usize,
+}
+
+#[derive(Debug, Clone)]
+struct RecordedChange {
+    keys: Vec<VimKey>,
+    count: Option<usize>,
+    visual: Option<VisualExtent>,
+}
+
+#[derive(Debug, Clone)]
+enum InsertRepeat {
+    /// `3ifoo<Esc>` inserts the text two more times.
+    Inline,
+    /// `3ofoo<Esc>` opens two more lines with the text.
+    Lines,
+    /// Visual-block `I`/`A`/`c` copies the text to the other block lines.
+    /// `column` is `None` after `$`, meaning the end of each line.
+    Block { lines: Vec<usize>, column: Option<usize>, pad: bool },
+}
+
+#[derive(Debug, Clone)]
+struct InsertSession {
+    start: usize,
+    count: usize,
+    repeat: InsertRepeat,
 }
 
 #[derive(Debug, Clone)]
-pub enum VimCommand {
-    // Navigation
-    MoveLeft,
-    MoveRight,
-    MoveUp,
-    MoveDown,
-    MoveWordForward,
-    MoveWordBackward,
-    MoveToLineStart,
-    MoveToLineEnd,
-    MoveToDocumentStart,
-    MoveToDocumentEnd,
-    
-    // Mode changes
-    EnterInsertMode,
-    EnterInsertModeAfterCursor,
-    EnterInsertModeAtLineStart,
-    EnterInsertModeAtLineEnd,
-    EnterVisualMode,
-    EnterCommandMode,
-    ExitToNormal,
-    
-    // Editing
-    DeleteChar,
-    DeleteLine,
-    DeleteWord,
-    YankLine,
-    YankWord,
-    Paste,
-    PasteBefore,
-    Undo,
-    Redo,
-    
-    // Search
-    SearchForward,
-    SearchBackward,
-    SearchNext,
-    SearchPrevious,
-    
-    // Text objects
-    SelectInnerWord,
-    SelectAroundWord,
-    SelectInnerParagraph,
-    SelectAroundParagraph,
-    
-    // Commands
-    Save,
-    Quit,
-    ForceQuit,
-    
-    // Editor actions
-    EditorAction(EditorAction),
+pub struct VimModeManager {
+    current_mode: VimMode,
+    enabled: bool,
+    pending: Pending,
+    registers: Registers,
+    last_find: Option<FindChar>,
+    /// Last search pattern and whether it searched forward.
+    last_search: Option<(String, bool)>,
+    last_change: Option<RecordedChange>,
+    /// A change whose insert-mode keys are still being typed.
+    recording: Option<RecordedChange>,
+    change_extent: Option<VisualExtent>,
+    insert: Option<InsertSession>,
+    /// Whether the current change already has its undo snapshot.
+    undo_open: bool,
+    visual_anchor: usize,
+    block_to_eol: bool,
+    /// Anchor and cursor of the last visual selection, for `'<,'>`.
+    last_visual: Option<(usize, usize)>,
+    command_line: String,
+    command_prefix: char,
+    command_origin: VimMode,
+    message: Option<String>,
 }
 
 impl Default for VimModeManager {
@@ -84,237 +457,1368 @@ impl Default for VimModeManager {
 
 impl VimModeManager {
     pub fn new() -> Self {
-        let mut manager = Self {
+        Self {
             current_mode: VimMode::Normal,
-            keybindings: HashMap::new(),
-            pending_keys: Vec::new(),
-            repeat_count: None,
-            last_command: None,
             enabled: false,
-        };
-        
-        manager.load_default_keybindings();
-        manager
+            pending: Pending::default(),
+            registers: Registers::new(ClipboardBackend::System),
+            last_find: None,
+            last_search: None,
+            last_change: None,
+            recording: None,
+            change_extent: None,
+            insert: None,
+            undo_open: false,
+            visual_anchor: 0,
+            block_to_eol: false,
+            last_visual: None,
+            command_line: String::new(),
+            command_prefix: ':',
+            command_origin: VimMode::Normal,
+            message: None,
+        }
+    }
+
+    pub fn with_clipboard(mut self, clipboard: ClipboardBackend) -> Self {
+        self.registers = Registers::new(clipboard);
+        self
     }
-    
+
     pub fn enable(&mut self) {
         self.enabled = true;
     }
-    
+
     pub fn disable(&mut self) {
         self.enabled = false;
         self.current_mode = VimMode::Normal;
-        self.pending_keys.clear();
-        self.repeat_count = None;
+        self.pending = Pending::default();
+        self.recording = None;
+        self.insert = None;
+        self.undo_open = false;
+        self.command_line.clear();
     }
-    
+
     pub fn is_enabled(&self) -> bool {
         self.enabled
     }
-    
+
     pub fn current_mode(&self) -> &VimMode {
         &self.current_mode
     }
-    
-    pub fn handle_key_press(&mut self, key: &Key, _modifiers: &Modifiers) -> Option<EditorAction> {
+
+    pub fn get_mode_display(&self) -> &str {
+        match self.current_mode {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::Visual => "VISUAL",
+            VimMode::VisualLine => "VISUAL LINE",
+            VimMode::VisualBlock => "VISUAL BLOCK",
+            VimMode::Command => "COMMAND",
+        }
+    }
+
+    /// The command line being typed, with its `:`, `/` or `?` prefix.
+    pub fn command_line(&self) -> Option<String> {
+        (self.current_mode == VimMode::Command).then(|| format!("{}{}", self.command_prefix, self.command_line))
+    }
+
+    /// The last error or report, such as `E486: Pattern not found: foo`.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Handles a key press, editing `state`. Returns `false` for keys vim
+    /// leaves to the host, such as Enter in normal mode.
+    pub fn handle_key(&mut self, key: &Key, modifiers: &Modifiers, state: &mut EditorState) -> bool {
         if !self.enabled {
-            return None;
-        }
-        
-        // Handle numeric prefixes for repeat counts
-        if let Key::Character(c) = key {
-            if let Ok(digit) = c.parse::<usize>() {
-                if self.current_mode == VimMode::Normal && digit > 0 {
-                    self.repeat_count = Some(self.repeat_count.unwrap_or(0) * 10 + digit);
-                    return None;
+            return false;
+        }
+        match VimKey::from_key(key, *modifiers) {
+            Some(key) => self.feed(key, state),
+            None => false,
+        }
+    }
+
+    /// Feeds keys in vim notation, e.g. `d2w` or `ciwfoo<Esc>`.
+    pub fn feed_keys(&mut self, keys: &str, state: &mut EditorState) {
+        for key in VimKey::parse(keys) {
+            self.feed(key, state);
+        }
+    }
+
+    pub fn feed(&mut self, key: VimKey, state: &mut EditorState) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match self.current_mode {
+            VimMode::Insert => self.insert_key(key, state),
+            VimMode::Command => {
+                self.command_key(key, state);
+                true
+            }
+            _ => self.normal_key(key, state),
+        }
+    }
+
+    fn is_visual(&self) -> bool {
+        matches!(self.current_mode, VimMode::Visual | VimMode::VisualLine | VimMode::VisualBlock)
+    }
+
+    // Normal, visual and operator-pending modes
+
+    fn normal_key(&mut self, key: VimKey, state: &mut EditorState) -> bool {
+        if self.pending.is_empty() {
+            self.message = None;
+        }
+        if let Some(awaiting) = self.pending.awaiting.take() {
+            self.pending.keys.push(key);
+            match key {
+                VimKey::Char(c) => self.resolve_awaiting(awaiting, c, state),
+                _ => self.cancel(state),
+            }
+            return true;
+        }
+
+        let c = match key {
+            VimKey::Char(c) => c,
+            VimKey::Escape => {
+                let had_pending = !self.pending.is_empty() || self.is_visual();
+                if self.is_visual() {
+                    self.exit_visual(state);
+                }
+                self.cancel(state);
+                return had_pending;
+            }
+            VimKey::Ctrl('r') if self.pending.operator.is_none() => {
+                for _ in 0..self.pending.count().unwrap_or(1) {
+                    if !state.redo() {
+                        self.message = Some("Already at newest change".to_string());
+                        break;
+                    }
                 }
+                self.finish(state);
+                return true;
+            }
+            VimKey::Ctrl('v') if self.pending.operator.is_none() => {
+                self.pending.keys.push(key);
+                self.enter_visual(VimMode::VisualBlock, state);
+                return true;
             }
+            VimKey::Left | VimKey::Backspace => 'h',
+            VimKey::Right => 'l',
+            VimKey::Up => 'k',
+            VimKey::Down => 'j',
+            VimKey::Home => '0',
+            VimKey::End => '$',
+            VimKey::Delete if self.pending.operator.is_none() => 'x',
+            _ => {
+                let consumed = !self.pending.is_empty();
+                self.cancel(state);
+                return consumed;
+            }
+        };
+
+        let counting = if self.pending.operator.is_some() { self.pending.operator_count.is_some() } else { self.pending.count.is_some() };
+        if let Some(digit) = c.to_digit(10).filter(|&d| d != 0 || counting) {
+            let slot = if self.pending.operator.is_some() { &mut self.pending.operator_count } else { &mut self.pending.count };
+            *slot = Some(slot.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize));
+            return true;
         }
-        
-        // Get command for current mode and key
-        if let Some(mode_bindings) = self.keybindings.get(&self.current_mode) {
-            if let Some(command) = mode_bindings.get(key) {
-                return self.execute_command(command.clone());
-            }
-        }
-        
-        // Reset repeat count if no valid command
-        self.repeat_count = None;
-        None
-    }
-    
-    fn execute_command(&mut self, command: VimCommand) -> Option<EditorAction> {
-        let repeat_count = self.repeat_count.unwrap_or(1);
-        self.repeat_count = None;
-        self.last_command = Some(command.clone());
-        
-        match command {
-            // Mode changes
-            VimCommand::EnterInsertMode => {
-                self.current_mode = VimMode::Insert;
-                None
-            },
-            VimCommand::EnterInsertModeAfterCursor => {
-                self.current_mode = VimMode::Insert;
-                Some(EditorAction::MoveCursor(1))
-            },
-            VimCommand::EnterInsertModeAtLineStart => {
-                self.current_mode = VimMode::Insert;
-                Some(EditorAction::MoveCursor(0)) // Move to line start
-            },
-            VimCommand::EnterInsertModeAtLineEnd => {
-                self.current_mode = VimMode::Insert;
-                Some(EditorAction::MoveCursor(usize::MAX)) // Move to line end
-            },
-            VimCommand::EnterVisualMode => {
-                self.current_mode = VimMode::Visual;
-                None
-            },
-            VimCommand::EnterCommandMode => {
-                self.current_mode = VimMode::Command;
-                None
+
+        self.pending.keys.push(VimKey::Char(c));
+        self.command_char(c, state);
+        true
+    }
+
+    fn command_char(&mut self, c: char, state: &mut EditorState) {
+        if c == '"' && self.pending.operator.is_none() {
+            self.pending.awaiting = Some(Awaiting::Register);
+            return;
+        }
+        if let Some(motion) = self.motion_for(c) {
+            self.run_motion(motion, state);
+            return;
+        }
+        match c {
+            'f' | 'F' | 't' | 'T' => {
+                self.pending.awaiting = Some(Awaiting::Find { forward: c.is_ascii_lowercase(), till: c.eq_ignore_ascii_case(&'t') });
+                return;
+            }
+            'g' => {
+                self.pending.awaiting = Some(Awaiting::GPrefix);
+                return;
+            }
+            '/' | '?' => {
+                self.open_command_line(c, state);
+                return;
+            }
+            'n' | 'N' => {
+                match self.last_search.clone() {
+                    Some((pattern, forward)) => self.run_search(&pattern, forward == (c == 'n'), state),
+                    None => self.fail(ExError::NoPreviousPattern.to_string(), state),
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(operator) = self.pending.operator {
+            match c {
+                'i' | 'a' => self.pending.awaiting = Some(Awaiting::TextObject { around: c == 'a' }),
+                c if c == operator.line_key() => self.operate_lines(operator, state),
+                _ => self.cancel(state),
+            }
+        } else if self.is_visual() {
+            self.visual_char(c, state);
+        } else {
+            self.normal_char(c, state);
+        }
+    }
+
+    fn motion_for(&self, c: char) -> Option<Motion> {
+        Some(match c {
+            'h' => Motion::Left,
+            'l' | ' ' => Motion::Right,
+            'j' => Motion::Down,
+            'k' => Motion::Up,
+            'w' => Motion::WordForward(false),
+            'W' => Motion::WordForward(true),
+            'b' => Motion::WordBackward(false),
+            'B' => Motion::WordBackward(true),
+            'e' => Motion::WordEnd(false),
+            'E' => Motion::WordEnd(true),
+            '0' => Motion::LineStart,
+            '^' => Motion::FirstNonBlank,
+            '$' => Motion::LineEnd,
+            'G' => Motion::LastLine,
+            '%' => Motion::MatchPair,
+            '{' => Motion::ParagraphBackward,
+            '}' => Motion::ParagraphForward,
+            ';' => Motion::Find { find: self.last_find?, repeat: true },
+            ',' => Motion::Find { find: self.last_find?.reversed(), repeat: true },
+            _ => return None,
+        })
+    }
+
+    fn normal_char(&mut self, c: char, state: &mut EditorState) {
+        let pos = state.cursor_position;
+        let count = self.pending.count();
+        match c {
+            'x' => self.operator_motion(Operator::Delete, Motion::Right, state),
+            'X' => self.operator_motion(Operator::Delete, Motion::Left, state),
+            'D' => self.operator_motion(Operator::Delete, Motion::LineEnd, state),
+            'C' => self.operator_motion(Operator::Change, Motion::LineEnd, state),
+            's' => self.operator_motion(Operator::Change, Motion::Right, state),
+            'S' => self.operate_lines(Operator::Change, state),
+            'Y' => self.operate_lines(Operator::Yank, state),
+            'p' | 'P' => self.paste(c == 'P', state),
+            'u' => {
+                for _ in 0..count.unwrap_or(1) {
+                    if !state.undo() {
+                        self.message = Some("Already at oldest change".to_string());
+                        break;
+                    }
+                }
+                self.finish(state);
+            }
+            'i' => self.start_insert(pos, InsertRepeat::Inline, count.unwrap_or(1), state),
+            'a' => {
+                let at = if pos >= line_end(&state.text(), pos) { pos } else { next_char(&state.text(), pos) };
+                self.start_insert(at, InsertRepeat::Inline, count.unwrap_or(1), state);
+            }
+            'I' => self.start_insert(first_non_blank(&state.text(), pos), InsertRepeat::Inline, count.unwrap_or(1), state),
+            'A' => self.start_insert(line_end(&state.text(), pos), InsertRepeat::Inline, count.unwrap_or(1), state),
+            'o' | 'O' => {
+                let text = state.text();
+                let start = line_start(&text, pos);
+                let indent: String = text[start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+                let (at, session_start) = if c == 'o' {
+                    let end = line_end(&text, pos);
+                    self.edit(state, end..end, &format!("\n{}", indent));
+                    (end + 1 + indent.len(), end + 1)
+                } else {
+                    self.edit(state, start..start, &format!("{}\n", indent));
+                    (start + indent.len(), start)
+                };
+                self.start_insert(session_start, InsertRepeat::Lines, count.unwrap_or(1), state);
+                state.move_cursor(at);
+            }
+            'v' => self.enter_visual(VimMode::Visual, state),
+            'V' => self.enter_visual(VimMode::VisualLine, state),
+            'r' => self.pending.awaiting = Some(Awaiting::Replace),
+            'J' => {
+                let line = line_index(&state.text(), pos);
+                self.join_lines(line, count.unwrap_or(2).max(2), state);
+            }
+            '~' => {
+                let end = line_end(&state.text(), pos);
+                let mut stop = pos;
+                for _ in 0..count.unwrap_or(1) {
+                    if stop >= end {
+                        break;
+                    }
+                    stop = next_char(&state.text(), stop);
+                }
+                if stop == pos {
+                    self.cancel(state);
+                    return;
+                }
+                self.map_region(Region::Charwise { start: pos, end: stop }, |text| Operator::ToggleCase.transform(text), state);
+                state.move_cursor(stop);
+                self.finish_change(state);
+            }
+            '.' => self.repeat_change(state),
+            ':' => self.open_command_line(':', state),
+            c => match Operator::from_char(c) {
+                Some(operator) => self.pending.operator = Some(operator),
+                None => self.cancel(state),
             },
-            VimCommand::ExitToNormal => {
-                self.current_mode = VimMode::Normal;
-                None
+        }
+    }
+
+    fn resolve_awaiting(&mut self, awaiting: Awaiting, c: char, state: &mut EditorState) {
+        match awaiting {
+            Awaiting::Register if Registers::is_valid_name(c) => self.pending.register = Some(c),
+            Awaiting::Register => self.cancel(state),
+            Awaiting::Find { forward, till } => {
+                let find = FindChar { ch: c, forward, till };
+                self.last_find = Some(find);
+                self.run_motion(Motion::Find { find, repeat: false }, state);
+            }
+            Awaiting::TextObject { around } => self.run_text_object(c, around, state),
+            Awaiting::Replace => self.replace_chars(c, state),
+            Awaiting::GPrefix => match (c, Operator::case_from_char(c)) {
+                ('g', _) => self.run_motion(Motion::FirstLine, state),
+                (_, Some(case)) => match self.pending.operator {
+                    Some(operator) if operator.line_key() == c => self.operate_lines(operator, state),
+                    Some(_) => self.cancel(state),
+                    None if self.is_visual() => self.visual_operator(case, false, state),
+                    None => self.pending.operator = Some(case),
+                },
+                _ => self.cancel(state),
             },
-            
-            // Navigation
-            VimCommand::MoveLeft => Some(EditorAction::MoveCursor(0)), // Will be handled specially
-            VimCommand::MoveRight => Some(EditorAction::MoveCursor(1)),
-            VimCommand::MoveUp => Some(EditorAction::MoveCursor(0)), // Line up
-            VimCommand::MoveDown => Some(EditorAction::MoveCursor(0)), // Line down
-            VimCommand::MoveWordForward => Some(EditorAction::MoveCursor(0)), // Next word
-            VimCommand::MoveWordBackward => Some(EditorAction::MoveCursor(0)), // Previous word
-            VimCommand::MoveToLineStart => Some(EditorAction::MoveCursor(0)),
-            VimCommand::MoveToLineEnd => Some(EditorAction::MoveCursor(usize::MAX)),
-            VimCommand::MoveToDocumentStart => Some(EditorAction::MoveCursor(0)),
-            VimCommand::MoveToDocumentEnd => Some(EditorAction::MoveCursor(usize::MAX)),
-            
-            // Editing
-            VimCommand::DeleteChar => Some(EditorAction::Delete(0, repeat_count)),
-            VimCommand::DeleteLine => Some(EditorAction::DeleteLine(0)),
-            VimCommand::DeleteWord => Some(EditorAction::Delete(0, 0)), // Delete word
-            VimCommand::YankLine => Some(EditorAction::CopyLine(0)),
-            VimCommand::YankWord => Some(EditorAction::CopySelection),
-            VimCommand::Paste => Some(EditorAction::Paste),
-            VimCommand::PasteBefore => Some(EditorAction::Paste), // Paste before cursor
-            VimCommand::Undo => Some(EditorAction::Undo),
-            VimCommand::Redo => Some(EditorAction::Redo),
-            
-            // Search
-            VimCommand::SearchForward => Some(EditorAction::Find(String::new())),
-            VimCommand::SearchBackward => Some(EditorAction::Find(String::new())),
-            VimCommand::SearchNext => Some(EditorAction::FindNext),
-            VimCommand::SearchPrevious => Some(EditorAction::FindPrevious),
-            
-            // Commands
-            VimCommand::Save => Some(EditorAction::SaveSnapshot), // Will be handled specially
-            VimCommand::Quit => Some(EditorAction::SaveSnapshot), // Will be handled specially
-            VimCommand::ForceQuit => Some(EditorAction::SaveSnapshot), // Will be handled specially
-            
-            // Editor actions passthrough
-            VimCommand::EditorAction(action) => Some(action),
-            
-            _ => None,
-        }
-    }
-    
-    fn load_default_keybindings(&mut self) {
-        // Normal mode keybindings
-        let mut normal_bindings = HashMap::new();
-        
-        // Navigation
-        normal_bindings.insert(Key::Character("h".into()), VimCommand::MoveLeft);
-        normal_bindings.insert(Key::Character("j".into()), VimCommand::MoveDown);
-        normal_bindings.insert(Key::Character("k".into()), VimCommand::MoveUp);
-        normal_bindings.insert(Key::Character("l".into()), VimCommand::MoveRight);
-        normal_bindings.insert(Key::Character("w".into()), VimCommand::MoveWordForward);
-        normal_bindings.insert(Key::Character("b".into()), VimCommand::MoveWordBackward);
-        normal_bindings.insert(Key::Character("0".into()), VimCommand::MoveToLineStart);
-        normal_bindings.insert(Key::Character("$".into()), VimCommand::MoveToLineEnd);
-        normal_bindings.insert(Key::Character("g".into()), VimCommand::MoveToDocumentStart); // gg
-        normal_bindings.insert(Key::Character("G".into()), VimCommand::MoveToDocumentEnd);
-        
-        // Mode changes
-        normal_bindings.insert(Key::Character("i".into()), VimCommand::EnterInsertMode);
-        normal_bindings.insert(Key::Character("a".into()), VimCommand::EnterInsertModeAfterCursor);
-        normal_bindings.insert(Key::Character("I".into()), VimCommand::EnterInsertModeAtLineStart);
-        normal_bindings.insert(Key::Character("A".into()), VimCommand::EnterInsertModeAtLineEnd);
-        normal_bindings.insert(Key::Character("v".into()), VimCommand::EnterVisualMode);
-        normal_bindings.insert(Key::Character(":".into()), VimCommand::EnterCommandMode);
-        
-        // Editing
-        normal_bindings.insert(Key::Character("x".into()), VimCommand::DeleteChar);
-        normal_bindings.insert(Key::Character("d".into()), VimCommand::DeleteLine); // dd
-        normal_bindings.insert(Key::Character("y".into()), VimCommand::YankLine); // yy
-        normal_bindings.insert(Key::Character("p".into()), VimCommand::Paste);
-        normal_bindings.insert(Key::Character("P".into()), VimCommand::PasteBefore);
-        normal_bindings.insert(Key::Character("u".into()), VimCommand::Undo);
-        normal_bindings.insert(Key::Character("r".into()), VimCommand::Redo); // Ctrl+R normally
-        
-        // Search
-        normal_bindings.insert(Key::Character("/".into()), VimCommand::SearchForward);
-        normal_bindings.insert(Key::Character("?".into()), VimCommand::SearchBackward);
-        normal_bindings.insert(Key::Character("n".into()), VimCommand::SearchNext);
-        normal_bindings.insert(Key::Character("N".into()), VimCommand::SearchPrevious);
-        
-        self.keybindings.insert(VimMode::Normal, normal_bindings);
-        
-        // Insert mode keybindings
-        let mut insert_bindings = HashMap::new();
-        insert_bindings.insert(Key::Named(Named::Escape), VimCommand::ExitToNormal);
-        self.keybindings.insert(VimMode::Insert, insert_bindings);
-        
-        // Visual mode keybindings
-        let mut visual_bindings = HashMap::new();
-        visual_bindings.insert(Key::Named(Named::Escape), VimCommand::ExitToNormal);
-        visual_bindings.insert(Key::Character("d".into()), VimCommand::DeleteChar);
-        visual_bindings.insert(Key::Character("y".into()), VimCommand::YankWord);
-        visual_bindings.insert(Key::Character("x".into()), VimCommand::DeleteChar);
-        self.keybindings.insert(VimMode::Visual, visual_bindings);
-        
-        // Command mode keybindings
-        let mut command_bindings = HashMap::new();
-        command_bindings.insert(Key::Named(Named::Escape), VimCommand::ExitToNormal);
-        command_bindings.insert(Key::Named(Named::Enter), VimCommand::ExitToNormal);
-        self.keybindings.insert(VimMode::Command, command_bindings);
-    }
-    
-    pub fn get_mode_display(&self) -> &str {
+        }
+    }
+
+    fn operator_motion(&mut self, operator: Operator, motion: Motion, state: &mut EditorState) {
+        self.pending.operator = Some(operator);
+        self.run_motion(motion, state);
+    }
+
+    fn run_motion(&mut self, motion: Motion, state: &mut EditorState) {
+        let text = &state.text();
+        let pos = state.cursor_position;
+        let count = self.pending.count();
+        let operator = self.pending.operator;
+        let resolved = match (operator, motion) {
+            (Some(Operator::Change), Motion::WordForward(big)) if char_at(text, pos).is_some_and(|c| !c.is_whitespace()) => {
+                Some((vim_motion::change_word_end(text, pos, big, count), MotionKind::Inclusive))
+            }
+            _ => vim_motion::resolve(text, pos, motion, count, operator.is_some()),
+        };
+        match resolved {
+            Some((target, kind)) => self.apply_target(target, kind, Some(motion), state),
+            // `s` and `cl` on an empty line still start inserting
+            None if operator == Some(Operator::Change) && matches!(motion, Motion::Right | Motion::LineEnd) => {
+                self.apply_operator(Operator::Change, Region::Charwise { start: pos, end: pos }, state)
+            }
+            None => self.cancel(state),
+        }
+    }
+
+    fn apply_target(&mut self, target: usize, kind: MotionKind, motion: Option<Motion>, state: &mut EditorState) {
+        let Some(operator) = self.pending.operator else {
+            if self.current_mode == VimMode::VisualBlock {
+                self.block_to_eol = match motion {
+                    Some(Motion::LineEnd) => true,
+                    Some(Motion::Up | Motion::Down) => self.block_to_eol,
+                    _ => false,
+                };
+            }
+            state.move_cursor(clamp_normal(&state.text(), target));
+            if self.is_visual() {
+                self.sync_selection(state);
+            }
+            self.finish(state);
+            return;
+        };
+        let region = motion_region(&state.text(), state.cursor_position, target, kind, motion);
+        self.apply_operator(operator, region, state);
+    }
+
+    fn run_text_object(&mut self, object: char, around: bool, state: &mut EditorState) {
+        let Some(object) = vim_motion::text_object(&state.text(), state.cursor_position, object, around, self.pending.count()) else {
+            self.cancel(state);
+            return;
+        };
+        if let Some(operator) = self.pending.operator {
+            let region = if object.linewise {
+                Region::Linewise { start: object.start, end: object.end }
+            } else {
+                Region::Charwise { start: object.start, end: object.end }
+            };
+            self.apply_operator(operator, region, state);
+        } else if self.is_visual() {
+            if object.linewise && self.current_mode == VimMode::Visual {
+                self.current_mode = VimMode::VisualLine;
+            }
+            self.visual_anchor = object.start;
+            let end = if object.linewise { object.end } else { prev_char(&state.text(), object.end).max(object.start) };
+            state.move_cursor(end);
+            self.sync_selection(state);
+            self.finish(state);
+        } else {
+            self.cancel(state);
+        }
+    }
+
+    fn operate_lines(&mut self, operator: Operator, state: &mut EditorState) {
+        let text = &state.text();
+        let pos = state.cursor_position;
+        let last = (line_index(text, pos) + self.pending.count().unwrap_or(1) - 1).min(line_count(text) - 1);
+        let end = line_start_of(text, last);
+        self.apply_operator(operator, Region::Linewise { start: pos, end }, state);
+    }
+
+    fn apply_operator(&mut self, operator: Operator, region: Region, state: &mut EditorState) {
+        let text = state.text();
+        let (start, end, linewise, anchor) = match region {
+            Region::Charwise { start, end } => (start, end, false, start),
+            Region::Linewise { start, end } => (line_start(&text, start), line_end(&text, end), true, start),
+            Region::Blockwise { .. } => return self.apply_block_operator(operator, region, state),
+        };
+        let register = self.pending.register;
+        let mut yanked = text[start..end].to_string();
+        if linewise {
+            yanked.push('\n');
+        }
+        let yanked = Register { text: yanked, kind: if linewise { RegisterKind::Linewise } else { RegisterKind::Charwise } };
+
+        match operator {
+            Operator::Yank => {
+                self.registers.store(register, yanked, false);
+                state.move_cursor(anchor);
+                self.finish(state);
+            }
+            Operator::Delete => {
+                self.registers.store(register, yanked, true);
+                let (start, end) = if !linewise {
+                    (start, end)
+                } else if end < text.len() {
+                    (start, end + 1)
+                } else {
+                    // Deleting the last line takes the newline before it
+                    (start.saturating_sub(1), end)
+                };
+                self.edit(state, start..end, "");
+                let pos = if linewise {
+                    first_non_blank(&state.text(), line_start(&state.text(), start.min(state.content.len())))
+                } else {
+                    start
+                };
+                state.move_cursor(pos);
+                self.finish_change(state);
+            }
+            Operator::Change => {
+                self.registers.store(register, yanked, true);
+                let indent: String = if linewise {
+                    text[start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+                } else {
+                    String::new()
+                };
+                self.edit(state, start..end, &indent);
+                self.start_insert(start + indent.len(), InsertRepeat::Inline, 1, state);
+            }
+            Operator::Indent | Operator::Outdent => {
+                let (first, last) = (line_index(&text, start), line_index(&text, end));
+                self.shift_lines(first, last, operator == Operator::Outdent, 1, state);
+            }
+            Operator::ToggleCase | Operator::Lowercase | Operator::Uppercase => {
+                self.edit(state, start..end, &operator.transform(&text[start..end]));
+                state.move_cursor(anchor);
+                self.finish_change(state);
+            }
+        }
+    }
+
+    fn apply_block_operator(&mut self, operator: Operator, region: Region, state: &mut EditorState) {
+        let text = state.text();
+        let spans = region_spans(&text, region);
+        let Some(&(top_left, _)) = spans.first() else {
+            self.cancel(state);
+            return;
+        };
+        let pieces: Vec<&str> = spans.iter().map(|&(start, end)| &text[start..end]).collect();
+        let yanked = Register { text: pieces.join("\n"), kind: RegisterKind::Blockwise };
+        let register = self.pending.register;
+        let Region::Blockwise { anchor, cursor, .. } = region else { return };
+        let first_line = line_index(&text, anchor.min(cursor));
+
+        match operator {
+            Operator::Yank => {
+                self.registers.store(register, yanked, false);
+                state.move_cursor(top_left);
+                self.finish(state);
+            }
+            Operator::Delete | Operator::Change => {
+                self.registers.store(register, yanked, true);
+                for &(start, end) in spans.iter().rev() {
+                    self.edit(state, start..end, "");
+                }
+                state.move_cursor(top_left);
+                if operator == Operator::Delete {
+                    self.finish_change(state);
+                } else {
+                    let lines = spans
+                        .iter()
+                        .enumerate()
+                        .skip(1)
+                        .filter(|(_, (start, end))| start < end)
+                        .map(|(i, _)| first_line + i)
+                        .collect();
+                    let column = column(&text, top_left);
+                    self.start_insert(top_left, InsertRepeat::Block { lines, column: Some(column), pad: false }, 1, state);
+                }
+            }
+            Operator::Indent | Operator::Outdent => {
+                let last = first_line + spans.len() - 1;
+                self.shift_lines(first_line, last, operator == Operator::Outdent, 1, state);
+            }
+            Operator::ToggleCase | Operator::Lowercase | Operator::Uppercase => {
+                self.map_region(region, |text| operator.transform(text), state);
+                state.move_cursor(top_left);
+                self.finish_change(state);
+            }
+        }
+    }
+
+    /// Replaces every span of `region` with `f` applied to it.
+    fn map_region(&mut self, region: Region, f: impl Fn(&str) -> String, state: &mut EditorState) {
+        let text = state.text();
+        for (start, end) in region_spans(&text, region).into_iter().rev() {
+            let replaced = f(&text[start..end]);
+            if replaced != text[start..end] {
+                self.edit(state, start..end, &replaced);
+            }
+        }
+    }
+
+    fn shift_lines(&mut self, first: usize, last: usize, outdent: bool, levels: usize, state: &mut EditorState) {
+        let width = SHIFT_WIDTH * levels;
+        for line in (first..=last).rev() {
+            let start = line_start_of(&state.text(), line);
+            let end = line_end(&state.text(), start);
+            if outdent {
+                let mut removed = 0;
+                let mut columns = 0;
+                for c in state.content.slice(start..end).chars() {
+                    if columns >= width {
+                        break;
+                    }
+                    match c {
+                        ' ' => columns += 1,
+                        '\t' => columns += SHIFT_WIDTH,
+                        _ => break,
+                    }
+                    removed += 1;
+                }
+                if removed > 0 {
+                    self.edit(state, start..start + removed, "");
+                }
+            } else if end > start {
+                self.edit(state, start..start, &" ".repeat(width));
+            }
+        }
+        let start = line_start_of(&state.text(), first);
+        state.move_cursor(first_non_blank(&state.text(), start));
+        self.finish_change(state);
+    }
+
+    fn join_lines(&mut self, first: usize, lines: usize, state: &mut EditorState) {
+        let last = line_count(&state.text()) - 1;
+        if first >= last {
+            self.cancel(state);
+            return;
+        }
+        let mut join_point = 0;
+        for _ in 0..(lines - 1).min(last - first) {
+            let text = &state.text();
+            let start = line_start_of(text, first);
+            let end = line_end(text, start);
+            let next = first_non_blank(text, end + 1);
+            let next_empty = next == line_end(text, end + 1);
+            let separator = if next_empty || end == start || text[start..end].ends_with(' ') || char_at(text, next) == Some(')') {
+                ""
+            } else {
+                " "
+            };
+            self.edit(state, end..next, separator);
+            join_point = end;
+        }
+        state.move_cursor(join_point);
+        self.finish_change(state);
+    }
+
+    fn replace_chars(&mut self, c: char, state: &mut EditorState) {
+        let replace = |text: &str| text.chars().map(|x| if x == '\n' { x } else { c }).collect::<String>();
+        if self.is_visual() {
+            let region = self.visual_region(state, false);
+            self.change_extent = Some(self.visual_extent(state));
+            self.exit_visual(state);
+            let start = region_spans(&state.text(), region).first().map_or(state.cursor_position, |span| span.0);
+            self.map_region(region, replace, state);
+            state.move_cursor(start);
+            self.finish_change(state);
+            return;
+        }
+        let text = &state.text();
+        let pos = state.cursor_position;
+        let end = line_end(text, pos);
+        let mut stop = pos;
+        for _ in 0..self.pending.count().unwrap_or(1) {
+            if stop >= end {
+                self.cancel(state);
+                return;
+            }
+            stop = next_char(text, stop);
+        }
+        let replacement = replace(&text[pos..stop]);
+        self.edit(state, pos..stop, &replacement);
+        state.move_cursor(pos + replacement.len() - c.len_utf8());
+        self.finish_change(state);
+    }
+
+    fn paste(&mut self, before: bool, state: &mut EditorState) {
+        let count = self.pending.count().unwrap_or(1);
+        let Some(register) = self.registers.get(self.pending.register) else {
+            let name = self.pending.register.unwrap_or('"');
+            self.fail(format!("E353: Nothing in register {}", name), state);
+            return;
+        };
+        let text = state.text();
+        let pos = state.cursor_position;
+        match register.kind {
+            RegisterKind::Charwise => {
+                let insert = register.text.repeat(count);
+                let at = if before || pos >= line_end(&text, pos) { pos } else { next_char(&text, pos) };
+                self.edit(state, at..at, &insert);
+                state.move_cursor(prev_char(&state.text(), at + insert.len()).max(at));
+            }
+            RegisterKind::Linewise => {
+                let mut insert = register.text.repeat(count);
+                let end = line_end(&text, pos);
+                let (at, first_line) = if before {
+                    (line_start(&text, pos), line_start(&text, pos))
+                } else if end < text.len() {
+                    (end + 1, end + 1)
+                } else {
+                    // The last line has no newline to paste after
+                    insert.pop();
+                    insert.insert(0, '\n');
+                    (end, end + 1)
+                };
+                self.edit(state, at..at, &insert);
+                state.move_cursor(first_non_blank(&state.text(), first_line));
+            }
+            RegisterKind::Blockwise => {
+                let line = line_index(&text, pos);
+                let empty = line_start(&text, pos) == line_end(&text, pos);
+                let col = column(&text, pos) + usize::from(!before && !empty);
+                let pieces: Vec<String> = register.text.split('\n').map(|piece| piece.repeat(count)).collect();
+                let missing = (line + pieces.len()).saturating_sub(line_count(&text));
+                if missing > 0 {
+                    self.edit(state, text.len()..text.len(), &"\n".repeat(missing));
+                }
+                for (i, piece) in pieces.iter().enumerate().rev() {
+                    let start = line_start_of(&state.text(), line + i);
+                    let end = line_end(&state.text(), start);
+                    let width = state.content.slice(start..end).chars().count();
+                    if width < col {
+                        let padded = format!("{}{}", " ".repeat(col - width), piece);
+                        self.edit(state, end..end, &padded);
+                    } else {
+                        let at = pos_at_column(&state.text(), start, col);
+                        self.edit(state, at..at, piece);
+                    }
+                }
+                let start = line_start_of(&state.text(), line);
+                state.move_cursor(pos_at_column(&state.text(), start, col));
+            }
+        }
+        self.finish_change(state);
+    }
+
+    fn repeat_change(&mut self, state: &mut EditorState) {
+        let count = self.pending.count;
+        self.pending = Pending::default();
+        let Some(change) = self.last_change.clone() else { return };
+        if let Some(extent) = change.visual {
+            self.reselect(extent, state);
+        }
+        if let Some(count) = count.or(change.count) {
+            for c in count.to_string().chars() {
+                self.feed(VimKey::Char(c), state);
+            }
+        }
+        for key in change.keys {
+            self.feed(key, state);
+        }
+    }
+
+    // Visual modes
+
+    fn enter_visual(&mut self, mode: VimMode, state: &mut EditorState) {
+        if self.current_mode == mode {
+            self.exit_visual(state);
+            self.finish(state);
+            return;
+        }
+        if !self.is_visual() {
+            self.visual_anchor = state.cursor_position;
+        }
+        self.current_mode = mode;
+        self.block_to_eol = false;
+        self.sync_selection(state);
+        self.finish(state);
+    }
+
+    fn exit_visual(&mut self, state: &mut EditorState) {
+        self.last_visual = Some((self.visual_anchor, state.cursor_position));
+        self.current_mode = VimMode::Normal;
+        state.clear_selection();
+    }
+
+    /// The visual anchor on a character of `text`. Edits made outside
+    /// visual mode can leave it past the end or inside a character.
+    fn anchor(&self, text: &str) -> usize {
+        floor_char_boundary(text, self.visual_anchor)
+    }
+
+    fn sync_selection(&self, state: &mut EditorState) {
+        let text = &state.text();
+        let anchor = self.anchor(text);
+        let (start, end) = (anchor.min(state.cursor_position), anchor.max(state.cursor_position));
+        let (start, end) = match self.current_mode {
+            VimMode::VisualLine => (line_start(text, start), line_end(text, end)),
+            _ => (start, next_char(text, end)),
+        };
+        state.selection_start = Some(start);
+        state.selection_end = Some(end);
+    }
+
+    fn visual_char(&mut self, c: char, state: &mut EditorState) {
+        let block = self.current_mode == VimMode::VisualBlock;
+        match c {
+            'v' => self.enter_visual(VimMode::Visual, state),
+            'V' => self.enter_visual(VimMode::VisualLine, state),
+            'o' => {
+                let cursor = state.cursor_position;
+                state.move_cursor(self.visual_anchor);
+                self.visual_anchor = cursor;
+                self.sync_selection(state);
+                self.finish(state);
+            }
+            'i' | 'a' => self.pending.awaiting = Some(Awaiting::TextObject { around: c == 'a' }),
+            'd' | 'x' => self.visual_operator(Operator::Delete, false, state),
+            'y' => self.visual_operator(Operator::Yank, false, state),
+            'c' | 's' => self.visual_operator(Operator::Change, false, state),
+            '>' => self.visual_operator(Operator::Indent, false, state),
+            '<' => self.visual_operator(Operator::Outdent, false, state),
+            '~' => self.visual_operator(Operator::ToggleCase, false, state),
+            'u' => self.visual_operator(Operator::Lowercase, false, state),
+            'U' => self.visual_operator(Operator::Uppercase, false, state),
+            'D' if block => {
+                self.block_to_eol = true;
+                self.visual_operator(Operator::Delete, false, state);
+            }
+            'X' | 'D' => self.visual_operator(Operator::Delete, true, state),
+            'Y' => self.visual_operator(Operator::Yank, true, state),
+            'C' | 'S' | 'R' => self.visual_operator(Operator::Change, true, state),
+            'I' | 'A' if block => self.block_insert(c == 'A', state),
+            'J' => {
+                let (first, last) = self.visual_lines(state);
+                self.change_extent = Some(self.visual_extent(state));
+                self.exit_visual(state);
+                self.join_lines(first, (last - first + 1).max(2), state);
+            }
+            'r' => self.pending.awaiting = Some(Awaiting::Replace),
+            'p' | 'P' => self.visual_paste(state),
+            ':' => self.open_command_line(':', state),
+            _ => self.pending = Pending::default(),
+        }
+    }
+
+    fn visual_lines(&self, state: &EditorState) -> (usize, usize) {
+        let text = &state.text();
+        let anchor = line_index(text, self.anchor(text));
+        let cursor = line_index(text, state.cursor_position);
+        (anchor.min(cursor), anchor.max(cursor))
+    }
+
+    fn visual_region(&self, state: &EditorState, linewise: bool) -> Region {
+        let text = &state.text();
+        let anchor = self.anchor(text);
+        let cursor = state.cursor_position;
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
         match self.current_mode {
-            VimMode::Normal => "NORMAL",
-            VimMode::Insert => "INSERT",
-            VimMode::Visual => "VISUAL",
-            VimMode::Command => "COMMAND",
+            _ if linewise => Region::Linewise { start, end },
+            VimMode::VisualLine => Region::Linewise { start, end },
+            VimMode::VisualBlock => Region::Blockwise { anchor, cursor, to_eol: self.block_to_eol },
+            _ => Region::Charwise { start, end: next_char(text, end) },
+        }
+    }
+
+    fn visual_extent(&self, state: &EditorState) -> VisualExtent {
+        let text = &state.text();
+        let anchor = self.anchor(text);
+        let cursor = state.cursor_position;
+        let (first, last) = self.visual_lines(state);
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        let columns = match self.current_mode {
+            VimMode::VisualBlock => column(text, anchor).abs_diff(column(text, cursor)) + 1,
+            VimMode::Visual if first == last => text[start..end].chars().count() + 1,
+            _ => column(text, end) + 1,
+        };
+        VisualExtent { mode: self.current_mode, lines: last - first + 1, columns }
+    }
+
+    /// Selects text of the same size as `extent` at the cursor, for `.`.
+    fn reselect(&mut self, extent: VisualExtent, state: &mut EditorState) {
+        let text = &state.text();
+        let pos = state.cursor_position;
+        let line = (line_index(text, pos) + extent.lines - 1).min(line_count(text) - 1);
+        let start = line_start_of(text, line);
+        let target = match extent.mode {
+            VimMode::Visual if extent.lines == 1 => {
+                let end = line_end(text, pos);
+                let mut target = pos;
+                for _ in 1..extent.columns {
+                    if next_char(text, target) >= end {
+                        break;
+                    }
+                    target = next_char(text, target);
+                }
+                target
+            }
+            VimMode::VisualBlock => pos_at_column(text, start, column(text, pos) + extent.columns - 1),
+            VimMode::VisualLine => start,
+            _ => pos_at_column(text, start, extent.columns - 1),
+        };
+        self.current_mode = extent.mode;
+        self.visual_anchor = pos;
+        self.block_to_eol = false;
+        state.move_cursor(target);
+        self.sync_selection(state);
+    }
+
+    fn visual_operator(&mut self, operator: Operator, linewise: bool, state: &mut EditorState) {
+        let levels = self.pending.count().unwrap_or(1);
+        let region = self.visual_region(state, linewise);
+        let (first, last) = self.visual_lines(state);
+        self.change_extent = Some(self.visual_extent(state));
+        self.exit_visual(state);
+        match operator {
+            Operator::Indent | Operator::Outdent => self.shift_lines(first, last, operator == Operator::Outdent, levels, state),
+            _ => self.apply_operator(operator, region, state),
         }
     }
-    
-    pub fn add_custom_keybinding(&mut self, mode: VimMode, key: Key, command: VimCommand) {
-        if let Some(mode_bindings) = self.keybindings.get_mut(&mode) {
-            mode_bindings.insert(key, command);
+
+    fn visual_paste(&mut self, state: &mut EditorState) {
+        let Some(register) = self.registers.get(self.pending.register) else {
+            let name = self.pending.register.unwrap_or('"');
+            self.fail(format!("E353: Nothing in register {}", name), state);
+            return;
+        };
+        let region = self.visual_region(state, false);
+        self.change_extent = Some(self.visual_extent(state));
+        self.exit_visual(state);
+        let text = state.text();
+        let (start, end, linewise) = match region {
+            Region::Charwise { start, end } => (start, end, false),
+            Region::Linewise { start, end } => (line_start(&text, start), line_end(&text, end), true),
+            Region::Blockwise { .. } if register.kind == RegisterKind::Charwise && !register.text.contains('\n') => {
+                let start = region_spans(&text, region).first().map_or(state.cursor_position, |span| span.0);
+                self.map_region(region, |_| register.text.clone(), state);
+                state.move_cursor(start);
+                self.finish_change(state);
+                return;
+            }
+            Region::Blockwise { .. } => {
+                self.cancel(state);
+                return;
+            }
+        };
+        let mut replaced = text[start..end].to_string();
+        if linewise {
+            replaced.push('\n');
         }
+        let kind = if linewise { RegisterKind::Linewise } else { RegisterKind::Charwise };
+        self.registers.store(None, Register { text: replaced, kind }, true);
+        let insert = match (linewise, register.kind) {
+            (true, RegisterKind::Linewise) => register.text.trim_end_matches('\n').to_string(),
+            (false, RegisterKind::Linewise) => format!("\n{}", register.text),
+            _ => register.text,
+        };
+        self.edit(state, start..end, &insert);
+        let pos = if linewise { first_non_blank(&state.text(), start) } else { start };
+        state.move_cursor(pos);
+        self.finish_change(state);
+    }
+
+    fn block_insert(&mut self, append: bool, state: &mut EditorState) {
+        let text = state.text();
+        let anchor = self.anchor(&text);
+        let cursor = state.cursor_position;
+        let (first, last) = self.visual_lines(state);
+        let (left, right) = {
+            let (a, b) = (column(&text, anchor), column(&text, cursor));
+            (a.min(b), a.max(b))
+        };
+        let to_eol = self.block_to_eol;
+        self.change_extent = Some(self.visual_extent(state));
+        self.exit_visual(state);
+
+        let column = match (append, to_eol) {
+            (true, true) => None,
+            (true, false) => Some(right + 1),
+            (false, _) => Some(left),
+        };
+        let start = line_start_of(&text, first);
+        let end = line_end(&text, start);
+        let at = match column {
+            None => end,
+            Some(col) => {
+                let width = text[start..end].chars().count();
+                if append && width < col {
+                    let padding = " ".repeat(col - width);
+                    self.edit(state, end..end, &padding);
+                    end + padding.len()
+                } else {
+                    pos_at_column(&text, start, col)
+                }
+            }
+        };
+        let lines = (first + 1..=last).collect();
+        self.start_insert(at, InsertRepeat::Block { lines, column, pad: append }, 1, state);
     }
-    
-    pub fn remove_keybinding(&mut self, mode: VimMode, key: &Key) {
-        if let Some(mode_bindings) = self.keybindings.get_mut(&mode) {
-            mode_bindings.remove(key);
+
+    // Insert mode
+
+    fn start_insert(&mut self, at: usize, repeat: InsertRepeat, count: usize, state: &mut EditorState) {
+        state.clear_selection();
+        state.move_cursor(at);
+        self.current_mode = VimMode::Insert;
+        self.insert = Some(InsertSession { start: at, count, repeat });
+        self.finish_change(state);
+    }
+
+    fn insert_key(&mut self, key: VimKey, state: &mut EditorState) -> bool {
+        let pos = state.cursor_position;
+        let text = &state.text();
+        match key {
+            VimKey::Escape | VimKey::Ctrl('c') => {
+                self.record_key(VimKey::Escape);
+                self.leave_insert(state);
+                return true;
+            }
+            VimKey::Char(c) => self.edit(state, pos..pos, c.encode_utf8(&mut [0; 4])),
+            VimKey::Enter => self.edit(state, pos..pos, "\n"),
+            VimKey::Backspace | VimKey::Ctrl('h') => {
+                if pos > 0 {
+                    let start = prev_char(text, pos);
+                    self.edit(state, start..pos, "");
+                }
+            }
+            VimKey::Delete => {
+                if pos < text.len() {
+                    let end = next_char(text, pos);
+                    self.edit(state, pos..end, "");
+                }
+            }
+            VimKey::Ctrl('w') => {
+                let line = line_start(text, pos);
+                let mut start = pos;
+                while start > line && char_at(text, prev_char(text, start)).is_some_and(char::is_whitespace) {
+                    start = prev_char(text, start);
+                }
+                let is_word = |c: char| c.is_alphanumeric() || c == '_';
+                if let Some(kind) = (start > line).then(|| char_at(text, prev_char(text, start)).is_some_and(is_word)) {
+                    while start > line
+                        && char_at(text, prev_char(text, start)).is_some_and(|c| !c.is_whitespace() && is_word(c) == kind)
+                    {
+                        start = prev_char(text, start);
+                    }
+                }
+                if start == pos {
+                    start = prev_char(text, pos);
+                }
+                self.edit(state, start..pos, "");
+            }
+            VimKey::Ctrl('u') => {
+                let start = line_start(text, pos);
+                self.edit(state, start..pos, "");
+            }
+            VimKey::Left | VimKey::Right | VimKey::Up | VimKey::Down | VimKey::Home | VimKey::End => {
+                let target = match key {
+                    VimKey::Left if pos > line_start(text, pos) => prev_char(text, pos),
+                    VimKey::Right if pos < line_end(text, pos) => next_char(text, pos),
+                    VimKey::Up => vim_motion::resolve(text, pos, Motion::Up, None, true).map_or(pos, |(target, _)| target),
+                    VimKey::Down => vim_motion::resolve(text, pos, Motion::Down, None, true).map_or(pos, |(target, _)| target),
+                    VimKey::Home => line_start(text, pos),
+                    VimKey::End => line_end(text, pos),
+                    _ => pos,
+                };
+                state.move_cursor(target);
+                // Moving around starts a new insert, as in vim
+                if let Some(session) = &mut self.insert {
+                    *session = InsertSession { start: target, count: 1, repeat: InsertRepeat::Inline };
+                }
+            }
+            _ => return false,
         }
+        self.record_key(key);
+        true
     }
-    
-    pub fn repeat_last_command(&mut self) -> Option<EditorAction> {
-        if let Some(command) = self.last_command.clone() {
-            self.execute_command(command)
+
+    fn record_key(&mut self, key: VimKey) {
+        if let Some(recording) = &mut self.recording {
+            recording.keys.push(key);
+        }
+    }
+
+    fn leave_insert(&mut self, state: &mut EditorState) {
+        let mut pos = state.cursor_position;
+        let mut step_back = true;
+        if let Some(session) = self.insert.take() {
+            let inserted = state.text().get(session.start..pos).unwrap_or_default().to_string();
+            if !inserted.is_empty() {
+                match session.repeat {
+                    InsertRepeat::Inline if session.count > 1 => {
+                        self.edit(state, pos..pos, &inserted.repeat(session.count - 1));
+                    }
+                    InsertRepeat::Lines if session.count > 1 => {
+                        self.edit(state, pos..pos, &format!("\n{}", inserted).repeat(session.count - 1));
+                    }
+                    InsertRepeat::Block { lines, column, pad } if !inserted.contains('\n') => {
+                        for line in lines.into_iter().rev() {
+                            let start = line_start_of(&state.text(), line);
+                            let end = line_end(&state.text(), start);
+                            let width = state.content.slice(start..end).chars().count();
+                            let at = match column {
+                                None => end,
+                                Some(col) if width >= col => pos_at_column(&state.text(), start, col),
+                                Some(col) if pad => {
+                                    let padding = " ".repeat(col - width);
+                                    self.edit(state, end..end, &padding);
+                                    end + padding.len()
+                                }
+                                Some(_) => continue,
+                            };
+                            self.edit(state, at..at, &inserted);
+                        }
+                        pos = session.start;
+                        step_back = false;
+                    }
+                    _ => {}
+                }
+                if step_back {
+                    pos = state.cursor_position;
+                }
+            }
+        }
+        self.current_mode = VimMode::Normal;
+        let text = &state.text();
+        if step_back && pos > line_start(text, pos) {
+            pos = prev_char(text, pos);
+        }
+        state.move_cursor(pos);
+        if let Some(recording) = self.recording.take() {
+            self.last_change = Some(recording);
+        }
+        self.undo_open = false;
+    }
+
+    // Command line
+
+    fn open_command_line(&mut self, prefix: char, state: &mut EditorState) {
+        self.command_prefix = prefix;
+        self.command_line.clear();
+        if prefix == ':' && self.is_visual() {
+            self.exit_visual(state);
+            self.command_line.push_str("'<,'>");
+        }
+        self.command_origin = self.current_mode;
+        self.current_mode = VimMode::Command;
+    }
+
+    fn command_key(&mut self, key: VimKey, state: &mut EditorState) {
+        self.pending.keys.push(key);
+        match key {
+            VimKey::Escape | VimKey::Ctrl('c') => {
+                self.current_mode = self.command_origin;
+                self.cancel(state);
+            }
+            VimKey::Enter => {
+                let line = std::mem::take(&mut self.command_line);
+                self.current_mode = self.command_origin;
+                if self.command_prefix == ':' {
+                    self.run_ex(&line, state);
+                    return;
+                }
+                let pattern = if line.is_empty() {
+                    match &self.last_search {
+                        Some((pattern, _)) => pattern.clone(),
+                        None => return self.fail(ExError::NoPreviousPattern.to_string(), state),
+                    }
+                } else {
+                    line
+                };
+                let forward = self.command_prefix == '/';
+                self.last_search = Some((pattern.clone(), forward));
+                self.run_search(&pattern, forward, state);
+            }
+            VimKey::Backspace if self.command_line.is_empty() => {
+                self.current_mode = self.command_origin;
+                self.cancel(state);
+            }
+            VimKey::Backspace => {
+                self.command_line.pop();
+            }
+            VimKey::Ctrl('u') => self.command_line.clear(),
+            VimKey::Char(c) => self.command_line.push(c),
+            _ => {}
+        }
+    }
+
+    fn run_search(&mut self, pattern: &str, forward: bool, state: &mut EditorState) {
+        let regex = match vim_ex::compile_pattern(pattern, false) {
+            Ok(regex) => regex,
+            Err(e) => return self.fail(e.to_string(), state),
+        };
+        let mut pos = state.cursor_position;
+        for _ in 0..self.pending.count().unwrap_or(1) {
+            match search_from(&state.text(), pos, &regex, forward) {
+                Some(found) => pos = found,
+                None => return self.fail(ExError::PatternNotFound(pattern.to_string()).to_string(), state),
+            }
+        }
+        self.apply_target(pos, MotionKind::Exclusive, None, state);
+    }
+
+    fn run_ex(&mut self, line: &str, state: &mut EditorState) {
+        let text = &state.text();
+        let ctx = ExContext {
+            current_line: line_index(text, state.cursor_position),
+            last_line: line_count(text) - 1,
+            visual: self.last_visual.map(|(anchor, cursor)| {
+                let anchor = line_index(text, floor_char_boundary(text, anchor));
+                let cursor = line_index(text, floor_char_boundary(text, cursor));
+                (anchor.min(cursor), anchor.max(cursor))
+            }),
+        };
+        let mut substitute = match vim_ex::parse(line, &ctx) {
+            Ok(ExCommand::Substitute(substitute)) => substitute,
+            Ok(ExCommand::GotoLine(line)) => {
+                state.move_cursor(first_non_blank(text, line_start_of(text, line)));
+                return self.finish(state);
+            }
+            Ok(ExCommand::Nothing) => return self.finish(state),
+            Err(e) => return self.fail(e.to_string(), state),
+        };
+
+        if substitute.pattern.is_empty() {
+            match &self.last_search {
+                Some((pattern, _)) => substitute.pattern = pattern.clone(),
+                None => return self.fail(ExError::NoPreviousPattern.to_string(), state),
+            }
+        }
+        self.last_search = Some((substitute.pattern.clone(), true));
+        let result = vim_ex::compile_pattern(&substitute.pattern, substitute.ignore_case.unwrap_or(false))
+            .and_then(|regex| vim_ex::substitute(text, &substitute, &regex));
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => return self.fail(e.to_string(), state),
+        };
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        if substitute.count_only {
+            self.message = Some(format!("{} match{} on {} line{}", result.matches, if result.matches == 1 { "" } else { "es" }, result.lines, plural(result.lines)));
+            return self.finish(state);
+        }
+        self.edit(state, result.start..result.end, &result.text);
+        let start = line_start_of(&state.text(), result.last_line);
+        state.move_cursor(first_non_blank(&state.text(), start));
+        if result.lines > 1 {
+            self.message = Some(format!("{} substitution{} on {} lines", result.matches, plural(result.matches), result.lines));
+        }
+        self.finish(state);
+    }
+
+    // Bookkeeping
+
+    /// Applies an edit, taking the undo snapshot for the current change
+    /// first if it has not been taken yet.
+    fn edit(&mut self, state: &mut EditorState, range: std::ops::Range<usize>, text: &str) {
+        if !self.undo_open {
+            state.checkpoint();
+            self.undo_open = true;
+        }
+        // `'<,'>` follows the text it marked
+        if let Some((anchor, cursor)) = &mut self.last_visual {
+            for pos in [anchor, cursor] {
+                if *pos >= range.end {
+                    *pos = *pos - range.end + range.start + text.len();
+                } else if *pos > range.start {
+                    *pos = range.start;
+                }
+            }
+        }
+        state.splice(range, text);
+    }
+
+    /// Ends a command that did not change the text.
+    fn finish(&mut self, state: &mut EditorState) {
+        self.pending = Pending::default();
+        self.change_extent = None;
+        self.settle(state);
+    }
+
+    /// Ends a change, remembering its keys for `.`. A change that entered
+    /// insert mode is remembered once insert mode is left.
+    fn finish_change(&mut self, state: &mut EditorState) {
+        let pending = std::mem::take(&mut self.pending);
+        let change = RecordedChange { count: pending.count(), keys: pending.keys, visual: self.change_extent.take() };
+        if self.current_mode == VimMode::Insert {
+            self.recording = Some(change);
         } else {
-            None
+            self.last_change = Some(change);
+        }
+        self.settle(state);
+    }
+
+    fn cancel(&mut self, state: &mut EditorState) {
+        self.pending = Pending::default();
+        self.change_extent = None;
+        self.settle(state);
+    }
+
+    fn fail(&mut self, message: String, state: &mut EditorState) {
+        self.cancel(state);
+        self.message = Some(message);
+    }
+
+    fn settle(&mut self, state: &mut EditorState) {
+        if self.current_mode == VimMode::Insert {
+            return;
+        }
+        self.undo_open = false;
+        if self.current_mode == VimMode::Normal {
+            let pos = clamp_normal(&state.text(), state.cursor_position);
+            state.move_cursor(pos);
         }
     }
 }
 
+/// Turns a motion from `pos` to `target` into the region an operator acts
+/// on, applying vim's special cases for `dw` and exclusive motions.
+fn motion_region(text: &str, pos: usize, target: usize, kind: MotionKind, motion: Option<Motion>) -> Region {
+    let (start, mut end) = (pos.min(target), pos.max(target));
+    match kind {
+        MotionKind::Linewise => return Region::Linewise { start, end },
+        MotionKind::Inclusive => {
+            let end = match char_at(text, end) {
+                Some('\n') | None => end,
+                Some(c) => end + c.len_utf8(),
+            };
+            return Region::Charwise { start, end };
+        }
+        MotionKind::Exclusive => {}
+    }
+    // `dw` on the last word of a line stops at the end of the line
+    if matches!(motion, Some(Motion::WordForward(_))) && text[start..end].contains('\n') {
+        let trimmed = start + text[start..end].trim_end().len();
+        end = if trimmed > start { trimmed } else { line_end(text, start) };
+    }
+    // An exclusive motion ending at the start of a line stops at the end of
+    // the previous one, and becomes linewise if it also started the line
+    if end > start && end == line_start(text, end) && line_index(text, start) < line_index(text, end) {
+        if start <= first_non_blank(text, start) {
+            return Region::Linewise { start, end: prev_char(text, end) };
+        }
+        end = prev_char(text, end);
+    }
+    Region::Charwise { start, end }
+}
+
+/// The byte spans a region covers, one per line for lines and blocks.
+fn region_spans(text: &str, region: Region) -> Vec<(usize, usize)> {
+    match region {
+        Region::Charwise { start, end } => vec![(start, end)],
+        Region::Linewise { start, end } => {
+            let start = line_start(text, start);
+            vec![(start, line_end(text, end))]
+        }
+        Region::Blockwise { anchor, cursor, to_eol } => {
+            let (first, last) = {
+                let (a, b) = (line_index(text, anchor), line_index(text, cursor));
+                (a.min(b), a.max(b))
+            };
+            let (left, right) = {
+                let (a, b) = (column(text, anchor), column(text, cursor));
+                (a.min(b), a.max(b))
+            };
+            (first..=last)
+                .map(|line| {
+                    let start = line_start_of(text, line);
+                    let end = if to_eol { line_end(text, start) } else { pos_at_column(text, start, right + 1) };
+                    (pos_at_column(text, start, left), end)
+                })
+                .collect()
+        }
+    }
+}
+
+/// The next match of `regex` after `pos`, wrapping around the text.
+fn search_from(text: &str, pos: usize, regex: &Regex, forward: bool) -> Option<usize> {
+    if forward {
+        let after = next_char(text, pos);
+        regex
+            .find_at(text, after)
+            .or_else(|| regex.find(text))
+            .map(|m| m.start())
+    } else {
+        let mut before = None;
+        let mut last = None;
+        for m in regex.find_iter(text) {
+            if m.start() < pos {
+                before = Some(m.start());
+            }
+            last = Some(m.start());
+        }
+        before.or(last)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn vim(text: &str, cursor: usize, keys: &str) -> (VimModeManager, EditorState) {
+        let mut state = EditorState::with_content(text);
+        state.move_cursor(cursor);
+        let mut manager = VimModeManager::new().with_clipboard(ClipboardBackend::Memory(None));
+        manager.enable();
+        manager.feed_keys(keys, &mut state);
+        (manager, state)
+    }
+
+    fn edit(text: &str, cursor: usize, keys: &str) -> String {
+        vim(text, cursor, keys).1.text()
+    }
+
     #[test]
     fn test_vim_mode_creation() {
         let manager = VimModeManager::new();
@@ -325,27 +1829,129 @@ mod tests {
     #[test]
     fn test_mode_switching() {
         let mut manager = VimModeManager::new();
+        let mut state = EditorState::new();
         manager.enable();
-        
-        // Enter insert mode
-        let action = manager.handle_key_press(&Key::Character("i".into()), &Modifiers::empty());
+
+        assert!(manager.handle_key(&Key::Character("i".into()), &Modifiers::empty(), &mut state));
         assert_eq!(manager.current_mode(), &VimMode::Insert);
-        
-        // Exit to normal mode
-        let action = manager.handle_key_press(&Key::Named(Named::Escape), &Modifiers::empty());
+        assert!(manager.handle_key(&Key::Named(Named::Escape), &Modifiers::empty(), &mut state));
         assert_eq!(manager.current_mode(), &VimMode::Normal);
+        // Enter in normal mode is left to the host, e.g. to run the command
+        assert!(!manager.handle_key(&Key::Named(Named::Enter), &Modifiers::empty(), &mut state));
     }
 
     #[test]
-    fn test_repeat_count() {
-        let mut manager = VimModeManager::new();
-        manager.enable();
-        
-        // Enter repeat count
-        manager.handle_key_press(&Key::Character("3".into()), &Modifiers::empty());
-        
-        // Execute delete command
-        let action = manager.handle_key_press(&Key::Character("x".into()), &Modifiers::empty());
-        assert!(action.is_some());
+    fn test_operators_with_counts_and_motions() {
+        assert_eq!(edit("git commit --amend --no-edit", 0, "d3w"), "amend --no-edit");
+        assert_eq!(edit("a b c d e f g", 0, "2d2w"), "e f g");
+        assert_eq!(edit("echo one\necho two", 5, "dw"), "echo \necho two");
+        assert_eq!(edit("echo one two", 5, "cwthree<Esc>"), "echo three two");
+        assert_eq!(edit("one\ntwo\nthree\nfour", 4, "2dd"), "one\nfour");
+        assert_eq!(edit("one\ntwo\nthree", 8, "dk"), "one");
+        assert_eq!(edit("ls -la /tmp", 3, "D"), "ls ");
+        assert_eq!(edit("ls -la /tmp", 0, "3x"), "-la /tmp");
+        assert_eq!(edit("ls -la /tmp", 0, "dG"), "");
+        assert_eq!(edit("abc def", 0, "g~iw"), "ABC def");
+        assert_eq!(edit("abc def", 0, "gUU"), "ABC DEF");
+        assert_eq!(edit("abc", 0, ">>"), "    abc");
+        assert_eq!(edit("a\n  b", 0, "J"), "a b");
+        assert_eq!(edit("abc", 0, "2rx"), "xxc");
+    }
+
+    #[test]
+    fn test_text_objects_and_find_motions() {
+        let (manager, state) = vim("echo \"hello world\" done", 8, "ci\"bye<Esc>");
+        assert_eq!(state.content, "echo \"bye\" done");
+        assert_eq!(manager.current_mode(), &VimMode::Normal);
+        assert_eq!(state.cursor_position, 8);
+
+        assert_eq!(edit("f(a, (b, c))", 6, "di("), "f(a, ())");
+        assert_eq!(edit("f(a, (b, c))", 6, "d2i("), "f()");
+        assert_eq!(edit("cd foo && make", 4, "daw"), "cd && make");
+        assert_eq!(edit("a,b,c,d", 0, "f,;;D"), "a,b,c");
+        assert_eq!(edit("a,b,c,d", 0, "dt,"), ",b,c,d");
+        assert_eq!(edit("a,b,c,d", 6, "dF,"), "a,b,cd");
+        assert_eq!(edit("one two one", 0, "d/two<CR>"), "two one");
+    }
+
+    #[test]
+    fn test_registers() {
+        let (manager, state) = vim("one\ntwo\n\nthree", 0, "yapGP");
+        assert_eq!(state.content, "one\ntwo\n\none\ntwo\n\nthree");
+        let yanked = manager.registers().get(Some('0')).unwrap();
+        assert_eq!(yanked, Register { text: "one\ntwo\n\n".to_string(), kind: RegisterKind::Linewise });
+
+        let (manager, state) = vim("alpha beta", 0, "\"ayiww\"Ayiw\"ap");
+        assert_eq!(manager.registers().get(Some('a')).unwrap().text, "alphabeta");
+        assert_eq!(state.content, "alpha balphabetaeta");
+
+        let (manager, _) = vim("alpha beta", 0, "\"+yy");
+        assert_eq!(manager.registers().get(Some('+')).unwrap().kind, RegisterKind::Linewise);
+        assert!(matches!(&manager.registers().clipboard, ClipboardBackend::Memory(Some(text)) if text == "alpha beta\n"));
+
+        let (manager, _) = vim("one\ntwo three", 4, "dddw");
+        assert_eq!(manager.registers().get(Some('1')).unwrap().text, "two three\n");
+        assert_eq!(manager.registers().get(Some('-')).unwrap().text, "one");
+        assert_eq!(edit("one two", 0, "\"_dwP"), "two");
+    }
+
+    #[test]
+    fn test_dot_repeat() {
+        assert_eq!(edit("foo foo foo", 0, "cwbar<Esc>w.w."), "bar bar bar");
+        assert_eq!(edit("a b c d e", 0, "dw2."), "d e");
+        assert_eq!(edit("x", 0, "3ahi<Esc>"), "xhihihi");
+        assert_eq!(edit("x", 0, "2oab<Esc>"), "x\nab\nab");
+        assert_eq!(edit("abcdef", 0, "vlld."), "");
+        assert_eq!(edit("one\ntwo\nthree", 0, "A;<Esc>j."), "one;\ntwo;\nthree");
+    }
+
+    #[test]
+    fn test_visual_modes() {
+        assert_eq!(edit("one\ntwo\nthree", 0, "Vjd"), "three");
+        assert_eq!(edit("ls -la /tmp", 3, "veU"), "ls -LA /tmp");
+        assert_eq!(edit("abc\ndef\nghi", 0, "<C-v>jld"), "c\nf\nghi");
+        assert_eq!(edit("abc\ndef\nghi", 0, "<C-v>jI# <Esc>"), "# abc\n# def\nghi");
+        assert_eq!(edit("ab\nabcd", 0, "<C-v>j$A;<Esc>"), "ab;\nabcd;");
+        assert_eq!(edit("abc\ndef", 0, "<C-v>jly$p"), "abcab\ndefde");
+    }
+
+    #[test]
+    fn test_ex_substitute() {
+        assert_eq!(edit("echo foo\necho foo foo", 0, ":%s/foo/bar/g<CR>"), "echo bar\necho bar bar");
+        assert_eq!(edit("echo foo\necho foo foo", 9, ":s/\\(fo\\+\\)/[\\1]/<CR>"), "echo foo\necho [foo] foo");
+        assert_eq!(edit("a\na\na", 2, "Vj:s/a/b/<CR>"), "a\nb\nb");
+
+        let (manager, state) = vim("echo foo", 0, ":s/zzz/x/<CR>");
+        assert_eq!(state.content, "echo foo");
+        assert_eq!(manager.message(), Some("E486: Pattern not found: zzz"));
+        assert_eq!(manager.current_mode(), &VimMode::Normal);
+    }
+
+    #[test]
+    fn test_visual_marks_stay_on_characters_after_edits() {
+        let text = "é👍🏽 x\n日本語 (a \"b\" c)";
+        assert_eq!(edit(text, 2, "VD:1s/a/b/<CR>"), "日本語 (b \"b\" c)");
+        let (manager, state) = vim(text, 2, "VD:1,2s/a/b<CR>");
+        assert_eq!(state.content, "日本語 (a \"b\" c)");
+        assert_eq!(manager.current_mode(), &VimMode::Normal);
+
+        let (manager, state) = vim(text, 19, "<C-v>>:bad<CR>");
+        assert_eq!(state.content, "é👍🏽 x\n    日本語 (a \"b\" c)");
+        assert!(manager.message().is_some());
+    }
+
+    #[test]
+    fn test_changes_undo_as_one_step() {
+        let (mut manager, mut state) = vim("one two three", 0, "d2w");
+        assert_eq!(state.content, "three");
+        manager.feed_keys("u", &mut state);
+        assert_eq!(state.content, "one two three");
+        manager.feed_keys("<C-r>", &mut state);
+        assert_eq!(state.content, "three");
+
+        let (mut manager, mut state) = vim("", 0, "3ihi<Esc>");
+        assert_eq!(state.content, "hihihi");
+        manager.feed_keys("u", &mut state);
+        assert_eq!(state.content, "");
     }
 }
@@ -0,0 +1,698 @@
+//! Motions and text objects for the vim emulation.
+//!
+//! Everything here is a pure function over the buffer text and byte offsets
+//! into it, so [`VimModeManager`](super::vim_mode::VimModeManager) can compose
+//! the results with counts and operators. Offsets are always kept on char
+//! boundaries.
+
+/// How the range between the cursor and a motion's target is measured when
+/// an operator is applied to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKind {
+    /// The character under the target is not included (`w`, `b`, `0`).
+    Exclusive,
+    /// The character under the target is included (`e`, `$`, `f`).
+    Inclusive,
+    /// Whole lines from the cursor's line to the target's line (`j`, `G`).
+    Linewise,
+}
+
+/// A pending `f`, `F`, `t` or `T` search, remembered for `;` and `,`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindChar {
+    pub ch: char,
+    pub forward: bool,
+    pub till: bool,
+}
+
+impl FindChar {
+    pub fn reversed(self) -> Self {
+        Self { forward: !self.forward, ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w`, or `W` when the flag is set.
+    WordForward(bool),
+    /// `b` / `B`.
+    WordBackward(bool),
+    /// `e` / `E`.
+    WordEnd(bool),
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    /// `gg`; a count selects the line.
+    FirstLine,
+    /// `G`; a count selects the line.
+    LastLine,
+    /// `f`, `F`, `t` and `T`. `repeat` is set for `;` and `,`, which skip a
+    /// match right next to the cursor for `t`/`T` so they make progress.
+    Find { find: FindChar, repeat: bool },
+    MatchPair,
+    ParagraphForward,
+    ParagraphBackward,
+}
+
+/// A text object selection. `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextObject {
+    pub start: usize,
+    pub end: usize,
+    pub linewise: bool,
+}
+
+pub fn char_at(text: &str, pos: usize) -> Option<char> {
+    text.get(pos..).and_then(|rest| rest.chars().next())
+}
+
+pub fn next_char(text: &str, pos: usize) -> usize {
+    char_at(text, pos).map_or(pos, |c| pos + c.len_utf8())
+}
+
+pub fn prev_char(text: &str, pos: usize) -> usize {
+    text[..pos].chars().next_back().map_or(pos, |c| pos - c.len_utf8())
+}
+
+pub fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Offset of the newline ending the line at `pos`, or the text length.
+pub fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+pub fn line_index(text: &str, pos: usize) -> usize {
+    text[..pos].matches('\n').count()
+}
+
+pub fn line_count(text: &str) -> usize {
+    text.matches('\n').count() + 1
+}
+
+/// Start offset of the zero-based `line`, clamped to the last line.
+pub fn line_start_of(text: &str, line: usize) -> usize {
+    if line == 0 {
+        return 0;
+    }
+    text.match_indices('\n')
+        .nth(line - 1)
+        .map_or_else(|| line_start(text, text.len()), |(i, _)| i + 1)
+}
+
+pub fn first_non_blank(text: &str, pos: usize) -> usize {
+    let start = line_start(text, pos);
+    let end = line_end(text, start);
+    text[start..end]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(end, |i| start + i)
+}
+
+/// Zero-based character column of `pos` within its line.
+pub fn column(text: &str, pos: usize) -> usize {
+    text[line_start(text, pos)..pos].chars().count()
+}
+
+/// Offset of character column `col` on the line starting at `start`,
+/// clamped to the end of that line.
+pub fn pos_at_column(text: &str, start: usize, col: usize) -> usize {
+    let end = line_end(text, start);
+    text[start..end]
+        .char_indices()
+        .nth(col)
+        .map_or(end, |(i, _)| start + i)
+}
+
+/// Normal mode keeps the cursor on a character, never past the end of a
+/// non-empty line.
+pub fn clamp_normal(text: &str, pos: usize) -> usize {
+    let pos = pos.min(text.len());
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+    if pos >= end && end > start {
+        prev_char(text, end)
+    } else {
+        pos
+    }
+}
+
+fn is_blank_line(text: &str, line: usize) -> bool {
+    let start = line_start_of(text, line);
+    line_end(text, start) == start
+}
+
+fn class(c: char, big: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        2
+    } else {
+        1
+    }
+}
+
+/// Resolves `motion` from `pos`, returning the target offset and how an
+/// operator should treat it. `None` means the motion failed and any pending
+/// operator is abandoned, as vim does.
+pub fn resolve(text: &str, pos: usize, motion: Motion, count: Option<usize>, operator_pending: bool) -> Option<(usize, MotionKind)> {
+    let n = count.unwrap_or(1).max(1);
+    let target = match motion {
+        Motion::Left => {
+            let start = line_start(text, pos);
+            let mut target = pos;
+            for _ in 0..n {
+                if target <= start {
+                    break;
+                }
+                target = prev_char(text, target);
+            }
+            return (target != pos).then_some((target, MotionKind::Exclusive));
+        }
+        Motion::Right => {
+            let end = line_end(text, pos);
+            let limit = if operator_pending { end } else { clamp_normal(text, end) };
+            let mut target = pos;
+            for _ in 0..n {
+                if target >= limit {
+                    break;
+                }
+                target = next_char(text, target);
+            }
+            return (target != pos).then_some((target, MotionKind::Exclusive));
+        }
+        Motion::Up | Motion::Down => {
+            let line = line_index(text, pos);
+            let target_line = if motion == Motion::Up {
+                line.checked_sub(n).or((line > 0).then_some(0))?
+            } else {
+                let last = line_count(text) - 1;
+                if line == last {
+                    return None;
+                }
+                (line + n).min(last)
+            };
+            let start = line_start_of(text, target_line);
+            return Some((pos_at_column(text, start, column(text, pos)), MotionKind::Linewise));
+        }
+        Motion::WordForward(big) => {
+            let mut target = pos;
+            for _ in 0..n {
+                target = word_forward(text, target, big);
+            }
+            if target == pos {
+                return None;
+            }
+            (target, MotionKind::Exclusive)
+        }
+        Motion::WordBackward(big) => {
+            let mut target = pos;
+            for _ in 0..n {
+                target = word_backward(text, target, big);
+            }
+            if target == pos {
+                return None;
+            }
+            (target, MotionKind::Exclusive)
+        }
+        Motion::WordEnd(big) => {
+            let mut target = pos;
+            for _ in 0..n {
+                target = word_end(text, target, big);
+            }
+            if target == pos {
+                return None;
+            }
+            (target, MotionKind::Inclusive)
+        }
+        Motion::LineStart => (line_start(text, pos), MotionKind::Exclusive),
+        Motion::FirstNonBlank => (first_non_blank(text, pos), MotionKind::Exclusive),
+        Motion::LineEnd => {
+            let line = (line_index(text, pos) + n - 1).min(line_count(text) - 1);
+            let start = line_start_of(text, line);
+            (prev_char(text, line_end(text, start)).max(start), MotionKind::Inclusive)
+        }
+        Motion::FirstLine | Motion::LastLine => {
+            let line = match count {
+                Some(line) => line.max(1) - 1,
+                None if motion == Motion::FirstLine => 0,
+                None => line_count(text) - 1,
+            };
+            (first_non_blank(text, line_start_of(text, line)), MotionKind::Linewise)
+        }
+        Motion::Find { find, repeat } => (find_char(text, pos, find, n, repeat)?, if find.forward { MotionKind::Inclusive } else { MotionKind::Exclusive }),
+        Motion::MatchPair => {
+            let end = line_end(text, pos);
+            let bracket = text[pos..end].char_indices().find(|(_, c)| "()[]{}".contains(*c))?.0 + pos;
+            (matching_bracket(text, bracket)?, MotionKind::Inclusive)
+        }
+        Motion::ParagraphForward => {
+            let last = line_count(text) - 1;
+            let mut line = line_index(text, pos);
+            let mut target = pos;
+            for _ in 0..n {
+                while line <= last && is_blank_line(text, line) {
+                    line += 1;
+                }
+                while line <= last && !is_blank_line(text, line) {
+                    line += 1;
+                }
+                if line > last {
+                    target = text.len();
+                    break;
+                }
+                target = line_start_of(text, line);
+            }
+            if target == pos {
+                return None;
+            }
+            (target, MotionKind::Exclusive)
+        }
+        Motion::ParagraphBackward => {
+            let mut line = line_index(text, pos);
+            for _ in 0..n {
+                while line > 0 && is_blank_line(text, line) {
+                    line -= 1;
+                }
+                while line > 0 && !is_blank_line(text, line) {
+                    line -= 1;
+                }
+            }
+            let target = line_start_of(text, line);
+            if target == pos {
+                return None;
+            }
+            (target, MotionKind::Exclusive)
+        }
+    };
+    Some(target)
+}
+
+/// `cw` works like `ce` except that it never leaves the word under the
+/// cursor, so changing the last character of a word changes just that.
+pub fn change_word_end(text: &str, pos: usize, big: bool, count: Option<usize>) -> usize {
+    let cls = class(char_at(text, pos).unwrap_or(' '), big);
+    let mut end = pos;
+    while char_at(text, next_char(text, end)).is_some_and(|c| class(c, big) == cls) {
+        end = next_char(text, end);
+    }
+    for _ in 1..count.unwrap_or(1).max(1) {
+        end = word_end(text, end, big);
+    }
+    end
+}
+
+fn word_forward(text: &str, pos: usize, big: bool) -> usize {
+    let mut i = pos;
+    let Some(c) = char_at(text, i) else { return i };
+    let cls = class(c, big);
+    if cls != 0 {
+        while char_at(text, i).is_some_and(|c| class(c, big) == cls) {
+            i = next_char(text, i);
+        }
+    }
+    while let Some(c) = char_at(text, i) {
+        if c == '\n' {
+            i += 1;
+            // An empty line counts as a word of its own
+            if char_at(text, i) == Some('\n') {
+                return i;
+            }
+        } else if c.is_whitespace() {
+            i = next_char(text, i);
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn word_end(text: &str, pos: usize, big: bool) -> usize {
+    let mut i = next_char(text, pos);
+    while char_at(text, i).is_some_and(char::is_whitespace) {
+        i = next_char(text, i);
+    }
+    let Some(c) = char_at(text, i) else { return pos };
+    let cls = class(c, big);
+    loop {
+        let next = next_char(text, i);
+        match char_at(text, next) {
+            Some(c) if class(c, big) == cls => i = next,
+            _ => return i,
+        }
+    }
+}
+
+fn word_backward(text: &str, pos: usize, big: bool) -> usize {
+    let mut i = pos;
+    loop {
+        if i == 0 {
+            return 0;
+        }
+        let p = prev_char(text, i);
+        let c = char_at(text, p).unwrap_or(' ');
+        if !c.is_whitespace() {
+            break;
+        }
+        if c == '\n' && (p == 0 || char_at(text, prev_char(text, p)) == Some('\n')) {
+            return p;
+        }
+        i = p;
+    }
+    i = prev_char(text, i);
+    let cls = class(char_at(text, i).unwrap_or(' '), big);
+    while i > 0 {
+        let p = prev_char(text, i);
+        if char_at(text, p).is_some_and(|c| class(c, big) == cls) {
+            i = p;
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn find_char(text: &str, pos: usize, find: FindChar, count: usize, repeat: bool) -> Option<usize> {
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+    let mut found = pos;
+    let mut skip_adjacent = repeat && find.till;
+    for _ in 0..count {
+        loop {
+            found = if find.forward {
+                let next = next_char(text, found);
+                if next >= end {
+                    return None;
+                }
+                next
+            } else {
+                if found <= start {
+                    return None;
+                }
+                prev_char(text, found)
+            };
+            if char_at(text, found) == Some(find.ch) {
+                let adjacent = if find.forward { prev_char(text, found) == pos } else { next_char(text, found) == pos };
+                if skip_adjacent && adjacent {
+                    skip_adjacent = false;
+                    continue;
+                }
+                break;
+            }
+        }
+    }
+    Some(match (find.till, find.forward) {
+        (false, _) => found,
+        (true, true) => prev_char(text, found),
+        (true, false) => next_char(text, found),
+    })
+}
+
+/// Offset of the bracket matching the one at `pos`.
+pub fn matching_bracket(text: &str, pos: usize) -> Option<usize> {
+    let c = char_at(text, pos)?;
+    let (open, close, forward) = match c {
+        '(' => ('(', ')', true),
+        '[' => ('[', ']', true),
+        '{' => ('{', '}', true),
+        ')' => ('(', ')', false),
+        ']' => ('[', ']', false),
+        '}' => ('{', '}', false),
+        _ => return None,
+    };
+    let mut depth = 0usize;
+    if forward {
+        for (i, c) in text[pos..].char_indices() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos + i);
+                }
+            }
+        }
+    } else {
+        for (i, c) in text[..=pos].char_indices().rev() {
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Resolves the text object named by `object` (the key after `i` or `a`).
+pub fn text_object(text: &str, pos: usize, object: char, around: bool, count: Option<usize>) -> Option<TextObject> {
+    let count = count.unwrap_or(1).max(1);
+    let charwise = |(start, end): (usize, usize)| TextObject { start, end, linewise: false };
+    match object {
+        'w' | 'W' => word_object(text, pos, object == 'W', around, count).map(charwise),
+        'p' => Some(paragraph_object(text, pos, around, count)),
+        '"' | '\'' | '`' => quote_object(text, pos, object, around).map(charwise),
+        '(' | ')' | 'b' => bracket_object(text, pos, '(', ')', around, count).map(charwise),
+        '[' | ']' => bracket_object(text, pos, '[', ']', around, count).map(charwise),
+        '{' | '}' | 'B' => bracket_object(text, pos, '{', '}', around, count).map(charwise),
+        '<' | '>' => bracket_object(text, pos, '<', '>', around, count).map(charwise),
+        _ => None,
+    }
+}
+
+fn word_object(text: &str, pos: usize, big: bool, around: bool, count: usize) -> Option<(usize, usize)> {
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+    if pos >= end {
+        return None;
+    }
+    let cls = |i: usize| class(char_at(text, i).unwrap_or(' '), big);
+    let run_end = |mut i: usize| {
+        let c = cls(i);
+        while i < end && cls(i) == c {
+            i = next_char(text, i);
+        }
+        i
+    };
+    let mut s = pos;
+    while s > start && cls(prev_char(text, s)) == cls(pos) {
+        s = prev_char(text, s);
+    }
+    let mut e = run_end(pos);
+    if !around {
+        for _ in 1..count {
+            if e >= end {
+                break;
+            }
+            e = run_end(e);
+        }
+        return Some((s, e));
+    }
+    let on_space = cls(pos) == 0;
+    for i in 0..count {
+        if i > 0 && e < end {
+            e = run_end(e);
+        }
+        if on_space {
+            if e < end {
+                e = run_end(e);
+            }
+        } else if e < end && cls(e) == 0 {
+            e = run_end(e);
+        } else if i == 0 {
+            // No trailing white space: take the leading white space instead
+            while s > start && cls(prev_char(text, s)) == 0 {
+                s = prev_char(text, s);
+            }
+        }
+    }
+    Some((s, e))
+}
+
+fn paragraph_object(text: &str, pos: usize, around: bool, count: usize) -> TextObject {
+    let last = line_count(text) - 1;
+    let current = line_index(text, pos);
+    let blank = is_blank_line(text, current);
+    let mut first = current;
+    while first > 0 && is_blank_line(text, first - 1) == blank {
+        first -= 1;
+    }
+    let mut end_line = current;
+    let extend = |mut line: usize| {
+        let kind = is_blank_line(text, line + 1);
+        line += 1;
+        while line < last && is_blank_line(text, line + 1) == kind {
+            line += 1;
+        }
+        line
+    };
+    while end_line < last && is_blank_line(text, end_line + 1) == blank {
+        end_line += 1;
+    }
+    for _ in 1..count {
+        if end_line < last {
+            end_line = extend(end_line);
+        }
+    }
+    if around {
+        if end_line < last {
+            end_line = extend(end_line);
+        } else {
+            while first > 0 && is_blank_line(text, first - 1) != blank {
+                first -= 1;
+            }
+        }
+    }
+    let start = line_start_of(text, first);
+    TextObject { start, end: line_end(text, line_start_of(text, end_line)), linewise: true }
+}
+
+fn quote_object(text: &str, pos: usize, quote: char, around: bool) -> Option<(usize, usize)> {
+    let start = line_start(text, pos);
+    let end = line_end(text, pos);
+    let quotes: Vec<usize> = text[start..end]
+        .char_indices()
+        .filter(|&(i, c)| c == quote && !text[start..start + i].ends_with('\\'))
+        .map(|(i, _)| start + i)
+        .collect();
+    let (open, close) = if let Some(index) = quotes.iter().position(|&q| q == pos) {
+        if index % 2 == 0 {
+            (pos, *quotes.get(index + 1)?)
+        } else {
+            (quotes[index - 1], pos)
+        }
+    } else {
+        match (quotes.iter().rev().find(|&&q| q < pos), quotes.iter().find(|&&q| q > pos)) {
+            (Some(&open), Some(&close)) => (open, close),
+            (None, Some(_)) => {
+                let mut after = quotes.iter().filter(|&&q| q > pos);
+                (*after.next()?, *after.next()?)
+            }
+            _ => return None,
+        }
+    };
+    if !around {
+        return Some((open + quote.len_utf8(), close));
+    }
+    let mut s = open;
+    let mut e = close + quote.len_utf8();
+    let is_space = |c: Option<char>| c.is_some_and(|c| c == ' ' || c == '\t');
+    if is_space(char_at(text, e)) {
+        while e < end && is_space(char_at(text, e)) {
+            e = next_char(text, e);
+        }
+    } else {
+        while s > start && is_space(char_at(text, prev_char(text, s))) {
+            s = prev_char(text, s);
+        }
+    }
+    Some((s, e))
+}
+
+fn bracket_object(text: &str, pos: usize, open: char, close: char, around: bool, count: usize) -> Option<(usize, usize)> {
+    let mut open_pos = enclosing_open(text, pos, open, close, true)?;
+    for _ in 1..count {
+        open_pos = enclosing_open(text, open_pos, open, close, false)?;
+    }
+    let close_pos = {
+        let mut depth = 0usize;
+        text[open_pos..].char_indices().find_map(|(i, c)| {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open_pos + i);
+                }
+            }
+            None
+        })?
+    };
+    if around {
+        return Some((open_pos, close_pos + close.len_utf8()));
+    }
+    let mut s = open_pos + open.len_utf8();
+    let mut e = close_pos;
+    // A block whose brackets sit on their own lines selects just the lines between
+    if char_at(text, s) == Some('\n') {
+        s += 1;
+        let close_line = line_start(text, close_pos);
+        if close_line >= s && text[close_line..close_pos].trim().is_empty() {
+            e = close_line;
+        }
+    }
+    Some((s, e.max(s)))
+}
+
+fn enclosing_open(text: &str, pos: usize, open: char, close: char, at_cursor: bool) -> Option<usize> {
+    if at_cursor && char_at(text, pos) == Some(open) {
+        return Some(pos);
+    }
+    let mut depth = 0usize;
+    for (i, c) in text[..pos].char_indices().rev() {
+        if c == close {
+            depth += 1;
+        } else if c == open {
+            if depth == 0 {
+                return Some(i);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_motions() {
+        let text = "git commit -m \"fix\"\n\ncargo build";
+        assert_eq!(resolve(text, 0, Motion::WordForward(false), Some(2), false), Some((11, MotionKind::Exclusive)));
+        assert_eq!(resolve(text, 11, Motion::WordForward(false), None, false), Some((12, MotionKind::Exclusive)));
+        assert_eq!(resolve(text, 11, Motion::WordForward(true), Some(2), false), Some((20, MotionKind::Exclusive)));
+        assert_eq!(resolve(text, 21, Motion::WordBackward(false), None, false), Some((20, MotionKind::Exclusive)));
+        assert_eq!(resolve(text, 0, Motion::WordEnd(false), None, false), Some((2, MotionKind::Inclusive)));
+    }
+
+    #[test]
+    fn test_find_char_motions() {
+        let text = "a,b,c,d";
+        let find = FindChar { ch: ',', forward: true, till: false };
+        assert_eq!(resolve(text, 0, Motion::Find { find, repeat: false }, Some(2), false), Some((3, MotionKind::Inclusive)));
+        let till = FindChar { till: true, ..find };
+        assert_eq!(resolve(text, 0, Motion::Find { find: till, repeat: false }, None, false), Some((0, MotionKind::Inclusive)));
+        assert_eq!(resolve(text, 0, Motion::Find { find: till, repeat: true }, None, false), Some((2, MotionKind::Inclusive)));
+        assert_eq!(resolve(text, 6, Motion::Find { find: find.reversed(), repeat: false }, None, false), Some((5, MotionKind::Exclusive)));
+        assert_eq!(resolve(text, 0, Motion::Find { find: FindChar { ch: 'z', ..find }, repeat: false }, None, false), None);
+    }
+
+    #[test]
+    fn test_text_objects() {
+        let text = "echo \"hello world\" (a (b) c)";
+        let object = |pos, c, around| text_object(text, pos, c, around, None).map(|o| &text[o.start..o.end]);
+        assert_eq!(object(8, '"', false), Some("hello world"));
+        assert_eq!(object(8, '"', true), Some("\"hello world\" "));
+        assert_eq!(object(8, 'w', false), Some("hello"));
+        assert_eq!(object(8, 'w', true), Some("hello "));
+        assert_eq!(object(24, '(', false), Some("b"));
+        assert_eq!(object(20, ')', true), Some("(a (b) c)"));
+        assert_eq!(text_object(text, 24, 'b', false, Some(2)).map(|o| &text[o.start..o.end]), Some("a (b) c"));
+    }
+
+    #[test]
+    fn test_paragraph_object() {
+        let text = "one\ntwo\n\nthree";
+        let object = text_object(text, 5, 'p', true, None).unwrap();
+        assert!(object.linewise);
+        assert_eq!(&text[object.start..object.end], "one\ntwo\n");
+    }
+}
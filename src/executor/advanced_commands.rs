@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use crate::executor::command_executor::ExecutionResult;
 
@@ -71,6 +71,7 @@ impl CommandSuggestion {
 }
 
 /// Advanced command utilities for enhanced terminal functionality
+#[derive(Debug, Clone)]
 pub struct AdvancedCommands {
     aliases: HashMap<String, String>,
 }
@@ -147,7 +148,7 @@ impl AdvancedCommands {
     }
 
     /// Get command completions for a given input
-    pub fn get_completions(&self, input: &str, current_dir: &PathBuf) -> Vec<String> {
+    pub fn get_completions(&self, input: &str, current_dir: &Path) -> Vec<String> {
         let parts: Vec<&str> = input.split_whitespace().collect();
         
         if parts.is_empty() {
@@ -221,7 +222,7 @@ impl AdvancedCommands {
         completions
     }
 
-    fn complete_paths(&self, prefix: &str, current_dir: &PathBuf) -> Vec<String> {
+    fn complete_paths(&self, prefix: &str, current_dir: &Path) -> Vec<String> {
         let mut completions = Vec::new();
         
         let expanded_prefix = self.expand_path(prefix);
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::CommandCorrection;
+
+/// Errors raised while loading or saving learned corrections
+#[derive(Debug, Error)]
+pub enum CorrectionError {
+    #[error("Failed to access corrections file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid corrections file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// How often a suggestion was taken or dismissed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Feedback {
+    pub accepted: u32,
+    pub rejected: u32,
+}
+
+impl Feedback {
+    fn record(&mut self, accepted: bool) {
+        if accepted {
+            self.accepted = self.accepted.saturating_add(1);
+        } else {
+            self.rejected = self.rejected.saturating_add(1);
+        }
+    }
+
+    /// The user dismissed this more than once and more often than they took it.
+    pub fn is_rejected(&self) -> bool {
+        self.rejected >= 2 && self.rejected > self.accepted
+    }
+}
+
+/// What the corrector has learned from the user's accepts and rejects.
+///
+/// Feedback is kept per exact command, per rule, and as a table of learned
+/// command-name typos derived from accepted corrections that only changed
+/// the command name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CorrectionMemory {
+    /// Original command -> corrected command -> feedback
+    #[serde(default)]
+    commands: BTreeMap<String, BTreeMap<String, Feedback>>,
+    /// Rule name -> feedback across all commands
+    #[serde(default)]
+    rules: BTreeMap<String, Feedback>,
+    /// Misspelled command name -> accepted replacement
+    #[serde(default)]
+    typos: BTreeMap<String, String>,
+    /// File the memory is saved to, if any
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl CorrectionMemory {
+    /// Create an empty, in-memory store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default location of the corrections file
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("warp-terminal")
+            .join("corrections.json")
+    }
+
+    /// Load learned corrections from `path`. A missing file yields an empty
+    /// store that will be saved to `path`.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, CorrectionError> {
+        let path = path.into();
+        let mut memory = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str::<Self>(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        memory.path = Some(path);
+        Ok(memory)
+    }
+
+    /// Save this store to `path` from now on
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// File this store is persisted to
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the store to its file. Does nothing for in-memory stores.
+    pub fn save(&self) -> Result<(), CorrectionError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a sibling file first so a crash never leaves a truncated file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Remember that the user ran (`accepted`) or dismissed a correction
+    pub fn record(&mut self, correction: &CommandCorrection, accepted: bool) {
+        let original = correction.original_command.trim();
+        let corrected = correction.corrected_command.trim();
+
+        self.commands
+            .entry(original.to_string())
+            .or_default()
+            .entry(corrected.to_string())
+            .or_default()
+            .record(accepted);
+        self.rules
+            .entry(correction.rule.clone())
+            .or_default()
+            .record(accepted);
+
+        if let Some((typo, replacement)) = renamed_command(original, corrected) {
+            if accepted {
+                self.typos.insert(typo, replacement);
+            } else if self.typos.get(&typo) == Some(&replacement) {
+                self.typos.remove(&typo);
+            }
+        }
+    }
+
+    /// Feedback for one exact correction
+    pub fn feedback(&self, original: &str, corrected: &str) -> Feedback {
+        self.commands
+            .get(original.trim())
+            .and_then(|corrections| corrections.get(corrected.trim()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Feedback for every correction produced by `rule`
+    pub fn rule_feedback(&self, rule: &str) -> Feedback {
+        self.rules.get(rule).copied().unwrap_or_default()
+    }
+
+    /// Corrections previously accepted for exactly this command
+    pub fn accepted_for<'a>(&'a self, original: &str) -> impl Iterator<Item = (&'a str, Feedback)> + 'a {
+        self.commands
+            .get(original.trim())
+            .into_iter()
+            .flatten()
+            .filter(|(_, feedback)| feedback.accepted > 0 && !feedback.is_rejected())
+            .map(|(corrected, feedback)| (corrected.as_str(), *feedback))
+    }
+
+    /// Learned replacement for a misspelled command name
+    pub fn learned_typo(&self, name: &str) -> Option<&str> {
+        self.typos.get(name).map(String::as_str)
+    }
+}
+
+/// If `corrected` only renames the command at the start of `original`,
+/// return the old and new names.
+fn renamed_command(original: &str, corrected: &str) -> Option<(String, String)> {
+    let (old, rest) = original.split_once(char::is_whitespace).unwrap_or((original, ""));
+    let (new, new_rest) = corrected.split_once(char::is_whitespace).unwrap_or((corrected, ""));
+    (old != new && rest.trim() == new_rest.trim() && !new.is_empty())
+        .then(|| (old.to_string(), new.to_string()))
+}
@@ -0,0 +1,678 @@
+//! Corrections for failed commands, in the spirit of `thefuck`.
+//!
+//! A [`CommandCorrector`] runs its [`CorrectionRule`]s against a
+//! [`CorrectionContext`] — the command line, its real stderr and exit code,
+//! the directory it ran in and the executables on `PATH` — and ranks what
+//! they suggest. Accepted and dismissed suggestions are recorded in a
+//! [`CorrectionMemory`], which can be persisted, so corrections the user
+//! keeps taking rise to the top and ones they keep dismissing disappear.
+
+mod learning;
+mod rules;
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::syntax_tree::{parse_shell, CommandView, ShellDialect, WordView};
+
+pub use learning::{CorrectionError, CorrectionMemory, Feedback};
+pub use rules::{
+    default_rules, CargoSimilarCommand, CdCorrection, ChmodExecutable, CommonTypos,
+    GitCheckoutNewBranch, GitPushUpstream, GitSimilarCommand, NpmToNpx, PathTypo,
+    SimilarCommand, Sudo,
+};
+
+/// A command correction suggestion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandCorrection {
+    /// The original (incorrect) command
+    pub original_command: String,
+    /// The suggested corrected command
+    pub corrected_command: String,
+    /// Confidence level (0.0 - 1.0)
+    pub confidence: f32,
+    /// Reason for the correction
+    pub reason: CorrectionReason,
+    /// Rule that generated this correction
+    pub rule: String,
+}
+
+/// Types of corrections that can be made
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CorrectionReason {
+    /// Command was misspelled
+    Misspelling,
+    /// Missing required flags or arguments
+    MissingFlags,
+    /// Permission denied - needs sudo or chmod
+    PermissionIssue,
+    /// Directory doesn't exist or path issue
+    PathIssue,
+    /// Command not found but similar command exists
+    CommandNotFound,
+    /// Wrong argument order or syntax
+    ArgumentIssue,
+    /// The user accepted this correction for the same command before
+    Learned,
+}
+
+/// A correction rule. Rules inspect a failed command and return zero or more
+/// replacements; the corrector takes care of ranking and learning.
+pub trait CorrectionRule: Send + Sync {
+    /// Stable identifier recorded on every correction the rule produces
+    fn name(&self) -> &'static str;
+
+    /// Suggested replacements for the failed command, empty if the rule
+    /// does not apply
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection>;
+}
+
+/// Everything known about a failed command
+#[derive(Debug, Clone)]
+pub struct CorrectionContext {
+    /// The command line as it was run
+    pub command: String,
+    /// Captured standard error
+    pub stderr: String,
+    pub exit_code: i32,
+    /// Directory the command ran in
+    pub cwd: PathBuf,
+    /// Executables available on `PATH`
+    pub path: PathCommands,
+}
+
+impl CorrectionContext {
+    /// Context for a command run in the current directory, with an empty `PATH`
+    pub fn new(command: impl Into<String>, stderr: impl Into<String>, exit_code: i32) -> Self {
+        Self {
+            command: command.into(),
+            stderr: stderr.into(),
+            exit_code,
+            cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
+            path: PathCommands::default(),
+        }
+    }
+
+    pub fn with_cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = cwd.into();
+        self
+    }
+
+    pub fn with_path(mut self, path: PathCommands) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// The first command on the line
+    pub fn first_command(&self) -> Option<CommandView> {
+        parse_shell(&self.command, ShellDialect::default())
+            .commands()
+            .into_iter()
+            .find(|command| command.name.is_some())
+    }
+
+    /// The name of the first command, skipping assignments such as `FOO=1`
+    /// and keywords such as `if`
+    pub fn command_name(&self) -> Option<WordView> {
+        self.first_command().and_then(|command| command.name)
+    }
+
+    /// Unquoted name of the first command
+    pub fn program(&self) -> Option<String> {
+        self.command_name().map(|name| name.value)
+    }
+
+    /// Unquoted name and arguments of the first command
+    pub fn argv(&self) -> Vec<String> {
+        self.first_command().map(|command| command.argv()).unwrap_or_default()
+    }
+
+    /// Case-insensitive search of stderr
+    pub fn stderr_contains(&self, pattern: &str) -> bool {
+        self.stderr.to_lowercase().contains(&pattern.to_lowercase())
+    }
+
+    /// Build a correction of this context's command
+    pub fn correction(
+        &self,
+        corrected_command: impl Into<String>,
+        confidence: f32,
+        reason: CorrectionReason,
+        rule: &str,
+    ) -> CommandCorrection {
+        CommandCorrection {
+            original_command: self.command.clone(),
+            corrected_command: corrected_command.into(),
+            confidence,
+            reason,
+            rule: rule.to_string(),
+        }
+    }
+}
+
+/// Names of the executables found on a `PATH`
+#[derive(Debug, Clone, Default)]
+pub struct PathCommands {
+    names: Arc<BTreeSet<String>>,
+}
+
+impl PathCommands {
+    /// Scan every directory of a `PATH`-style variable for executables
+    pub fn scan(path_var: &str) -> Self {
+        let names = std::env::split_paths(path_var)
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .filter(|entry| is_executable_file(&entry.path()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        Self { names: Arc::new(names) }
+    }
+
+    pub fn from_names<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            names: Arc::new(names.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Command corrections engine
+#[derive(Clone)]
+pub struct CommandCorrector {
+    rules: Vec<Arc<dyn CorrectionRule>>,
+    /// Shared between clones so feedback reaches the executor's copies
+    memory: Arc<RwLock<CorrectionMemory>>,
+    /// Last scanned `PATH` value and its executables
+    path_cache: Arc<Mutex<Option<(String, PathCommands)>>>,
+    /// Held while the corrections file is written, so saves never overlap
+    save_lock: Arc<Mutex<()>>,
+    /// Whether the corrector is enabled
+    enabled: bool,
+}
+
+impl CommandCorrector {
+    /// Create a new command corrector with the default rules and an
+    /// in-memory learning store
+    pub fn new() -> Self {
+        Self {
+            rules: default_rules().into_iter().map(Arc::from).collect(),
+            memory: Arc::new(RwLock::new(CorrectionMemory::new())),
+            path_cache: Arc::new(Mutex::new(None)),
+            save_lock: Arc::new(Mutex::new(())),
+            enabled: true,
+        }
+    }
+
+    /// Add a rule after the existing ones
+    pub fn with_rule(mut self, rule: impl CorrectionRule + 'static) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+
+    /// Replace the learning store
+    pub fn with_memory(self, memory: CorrectionMemory) -> Self {
+        *self.memory.write().unwrap_or_else(|e| e.into_inner()) = memory;
+        self
+    }
+
+    /// Load learned corrections from `path` and save feedback back to it.
+    /// An unreadable file is logged and replaced on the next save.
+    pub fn with_persistence(self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let memory = CorrectionMemory::load(&path).unwrap_or_else(|e| {
+            tracing::warn!(path = %path.display(), "Ignoring learned corrections: {}", e);
+            CorrectionMemory::new().with_path(path)
+        });
+        self.with_memory(memory)
+    }
+
+    /// Names of the active rules, in evaluation order
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    /// Enable or disable command corrections
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Check if corrections are enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Snapshot of what has been learned so far
+    pub fn memory(&self) -> CorrectionMemory {
+        self.memory.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Executables on `path_var`, rescanned only when the value changes
+    pub fn path_commands(&self, path_var: &str) -> PathCommands {
+        let mut cache = self.path_cache.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.as_ref() {
+            Some((cached_var, commands)) if cached_var == path_var => commands.clone(),
+            _ => {
+                let commands = PathCommands::scan(path_var);
+                *cache = Some((path_var.to_string(), commands.clone()));
+                commands
+            }
+        }
+    }
+
+    /// Best correction for a command that failed in the current directory
+    /// with the current `PATH`
+    pub fn suggest_correction(&self, command: &str, stderr: &str, exit_code: i32) -> Option<CommandCorrection> {
+        let path = std::env::var("PATH").unwrap_or_default();
+        let ctx = CorrectionContext::new(command, stderr, exit_code).with_path(self.path_commands(&path));
+        self.suggest_corrections(&ctx).into_iter().next()
+    }
+
+    /// All corrections for a failed command, best first
+    pub fn suggest_corrections(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        if !self.enabled || ctx.exit_code == 0 || ctx.command.trim().is_empty() {
+            return Vec::new();
+        }
+
+        let memory = self.memory.read().unwrap_or_else(|e| e.into_inner());
+        let mut corrections = learned_corrections(ctx, &memory);
+        for rule in &self.rules {
+            corrections.extend(rule.suggest(ctx));
+        }
+
+        let original = ctx.command.trim();
+        corrections.retain(|c| {
+            c.corrected_command.trim() != original
+                && !memory.feedback(&c.original_command, &c.corrected_command).is_rejected()
+        });
+        for correction in &mut corrections {
+            correction.confidence = ranked_confidence(correction, &memory);
+        }
+        // Stable sort: on ties the earlier rule wins
+        corrections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+        let mut seen = HashSet::new();
+        corrections.retain(|c| seen.insert(c.corrected_command.trim().to_string()));
+        corrections
+    }
+
+    /// Record that the user ran (`accepted`) or dismissed a correction, and
+    /// persist it if the corrector has a corrections file
+    pub fn record_feedback(&self, correction: &CommandCorrection, accepted: bool) -> Result<(), CorrectionError> {
+        self.learn(correction, accepted);
+        self.save_memory()
+    }
+
+    /// Record feedback without writing the corrections file, for the UI,
+    /// which saves it with [`Self::save_feedback`] off its own thread
+    pub fn learn(&self, correction: &CommandCorrection, accepted: bool) {
+        let mut memory = self.memory.write().unwrap_or_else(|e| e.into_inner());
+        memory.record(correction, accepted);
+    }
+
+    /// Write what has been learned to the corrections file on a blocking
+    /// worker
+    pub async fn save_feedback(self) -> Result<(), String> {
+        tokio::task::spawn_blocking(move || self.save_memory())
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())
+    }
+
+    /// Saves run one at a time, each writing the memory as it is when that
+    /// save starts, so the file always ends up with the latest feedback
+    fn save_memory(&self) -> Result<(), CorrectionError> {
+        let _saving = self.save_lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.memory().save()
+    }
+}
+
+impl Default for CommandCorrector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CommandCorrector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandCorrector")
+            .field("rules", &self.rule_names())
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+/// Corrections the user accepted before for this exact command, and learned
+/// command-name typos
+fn learned_corrections(ctx: &CorrectionContext, memory: &CorrectionMemory) -> Vec<CommandCorrection> {
+    let mut corrections: Vec<CommandCorrection> = memory
+        .accepted_for(&ctx.command)
+        .map(|(corrected, _)| ctx.correction(corrected, 0.85, CorrectionReason::Learned, "learned"))
+        .collect();
+
+    if let Some(name) = ctx.command_name() {
+        if let Some(fixed) = memory.learned_typo(&name.value) {
+            if !ctx.path.contains(&name.value) {
+                corrections.push(ctx.correction(
+                    replace_span(&ctx.command, name.span, fixed),
+                    0.85,
+                    CorrectionReason::Misspelling,
+                    "learned_typo",
+                ));
+            }
+        }
+    }
+    corrections
+}
+
+/// A rule's confidence adjusted by what the user did with the same
+/// correction and with the rule in general
+fn ranked_confidence(correction: &CommandCorrection, memory: &CorrectionMemory) -> f32 {
+    let exact = memory.feedback(&correction.original_command, &correction.corrected_command);
+    let rule = memory.rule_feedback(&correction.rule);
+
+    let exact_bonus = 0.1 * exact.accepted.min(5) as f32 - 0.15 * exact.rejected.min(5) as f32;
+    let rule_bonus = (0.02 * (rule.accepted as f32 - rule.rejected as f32)).clamp(-0.1, 0.1);
+    (correction.confidence + exact_bonus + rule_bonus).clamp(0.0, 1.0)
+}
+
+/// `command` with the bytes in `span` replaced
+fn replace_span(command: &str, span: Range<usize>, replacement: &str) -> String {
+    let mut corrected = command.to_string();
+    corrected.replace_range(span, replacement);
+    corrected
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps of
+/// adjacent characters (so `gti` is one edit away from `git`)
+fn edit_distance(s1: &str, s2: &str) -> usize {
+    let a: Vec<char> = s1.chars().collect();
+    let b: Vec<char> = s2.chars().collect();
+
+    if a.is_empty() {
+        return b.len();
+    }
+    if b.is_empty() {
+        return a.len();
+    }
+
+    let mut matrix = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (matrix[i - 1][j] + 1)
+                .min(matrix[i][j - 1] + 1)
+                .min(matrix[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(matrix[i - 2][j - 2] + 1);
+            }
+            matrix[i][j] = distance;
+        }
+    }
+
+    matrix[a.len()][b.len()]
+}
+
+#[cfg(unix)]
+fn is_executable_file(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable_file(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn context(command: &str, stderr: &str, exit_code: i32) -> CorrectionContext {
+        CorrectionContext::new(command, stderr, exit_code)
+            .with_path(PathCommands::from_names(["git", "cat", "cargo", "grep", "ls"]))
+    }
+
+    fn best(corrector: &CommandCorrector, ctx: &CorrectionContext) -> Option<CommandCorrection> {
+        corrector.suggest_corrections(ctx).into_iter().next()
+    }
+
+    #[test]
+    fn test_common_typos() {
+        let corrector = CommandCorrector::new();
+
+        let correction = corrector.suggest_correction("gti status", "", 127);
+        assert!(correction.is_some());
+        let correction = correction.unwrap();
+        assert_eq!(correction.corrected_command, "git status");
+        assert_eq!(correction.reason, CorrectionReason::Misspelling);
+
+        // Only the command name is replaced, not an earlier occurrence
+        let correction = corrector.suggest_correction("LANG=gti gti status", "", 127).unwrap();
+        assert_eq!(correction.corrected_command, "LANG=gti git status");
+    }
+
+    #[test]
+    fn test_git_push_upstream() {
+        let corrector = CommandCorrector::new();
+
+        let correction = corrector.suggest_correction(
+            "git push",
+            "fatal: The current branch has no upstream branch",
+            1
+        );
+        assert!(correction.is_some());
+        let correction = correction.unwrap();
+        assert_eq!(correction.corrected_command, "git push --set-upstream origin HEAD");
+        assert_eq!(correction.reason, CorrectionReason::MissingFlags);
+
+        // The command git prints wins over the generic fallback
+        let stderr = "fatal: The current branch feature has no upstream branch.\n\
+                      To push the current branch and set the remote as upstream, use\n\n    \
+                      git push --set-upstream origin feature\n";
+        let correction = corrector.suggest_correction("git push", stderr, 128).unwrap();
+        assert_eq!(correction.corrected_command, "git push --set-upstream origin feature");
+    }
+
+    #[test]
+    fn test_chmod_permission() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("script.sh"), "#!/bin/sh\n").unwrap();
+        let corrector = CommandCorrector::new();
+
+        let ctx = context("./script.sh", "sh: ./script.sh: Permission denied", 126).with_cwd(dir.path());
+        let correction = best(&corrector, &ctx).unwrap();
+        assert_eq!(correction.corrected_command, "chmod +x ./script.sh && ./script.sh");
+        assert_eq!(correction.reason, CorrectionReason::PermissionIssue);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("git", "gti"), 1);
+        assert_eq!(edit_distance("cat", "car"), 1);
+        assert_eq!(edit_distance("hello", "hello"), 0);
+        assert_eq!(edit_distance("", "test"), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_similar_command_uses_path() {
+        let corrector = CommandCorrector::new();
+
+        let ctx = context("crgo build --release", "bash: crgo: command not found", 127);
+        let correction = best(&corrector, &ctx).unwrap();
+        assert_eq!(correction.corrected_command, "cargo build --release");
+        assert_eq!(correction.reason, CorrectionReason::CommandNotFound);
+
+        // Nothing close enough on PATH
+        let ctx = context("xyz123", "bash: xyz123: command not found", 127);
+        assert!(best(&corrector, &ctx).is_none());
+
+        // Not suggested when the tool is missing from PATH entirely
+        let ctx = CorrectionContext::new("crgo build", "bash: crgo: command not found", 127);
+        assert!(best(&corrector, &ctx).is_none());
+    }
+
+    #[test]
+    fn test_git_similar_command_from_stderr() {
+        let corrector = CommandCorrector::new();
+        let stderr = "git: 'stauts' is not a git command. See 'git --help'.\n\n\
+                      The most similar commands are\n\tstatus\n\tstage\n";
+
+        let corrections = corrector.suggest_corrections(&context("git stauts -s", stderr, 1));
+        let suggested: Vec<_> = corrections.iter().map(|c| c.corrected_command.as_str()).collect();
+        assert_eq!(suggested, ["git status -s", "git stage -s"]);
+    }
+
+    #[test]
+    fn test_cd_correction_uses_cwd() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("projects")).unwrap();
+        let corrector = CommandCorrector::new();
+
+        let ctx = context("cd projcets", "cd: projcets: No such file or directory", 1).with_cwd(dir.path());
+        let corrections = corrector.suggest_corrections(&ctx);
+        assert_eq!(corrections[0].corrected_command, "cd projects");
+        assert_eq!(corrections[1].corrected_command, "mkdir -p projcets && cd projcets");
+    }
+
+    #[test]
+    fn test_path_typo() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        let corrector = CommandCorrector::new();
+
+        let ctx = context("cat src/mian.rs", "cat: src/mian.rs: No such file or directory", 1)
+            .with_cwd(dir.path());
+        let correction = best(&corrector, &ctx).unwrap();
+        assert_eq!(correction.corrected_command, "cat src/main.rs");
+    }
+
+    #[test]
+    fn test_learning_from_feedback() {
+        let corrector = CommandCorrector::new();
+        let ctx = context("apt install htop", "E: Could not open lock file - open (13: Permission denied)", 100);
+
+        // sudo is the only rule that fires; dismissing it twice hides it
+        let correction = best(&corrector, &ctx).unwrap();
+        assert_eq!(correction.corrected_command, "sudo apt install htop");
+        corrector.record_feedback(&correction, false).unwrap();
+        assert!(best(&corrector, &ctx).is_some());
+        corrector.record_feedback(&correction, false).unwrap();
+        assert!(best(&corrector, &ctx).is_none());
+
+        // An accepted rename is learned as a typo for any arguments
+        let accepted = context("mycommand --help", "", 127).correction(
+            "my_command --help",
+            0.5,
+            CorrectionReason::Misspelling,
+            "manual",
+        );
+        corrector.record_feedback(&accepted, true).unwrap();
+        let correction = best(&corrector, &context("mycommand run", "", 127)).unwrap();
+        assert_eq!(correction.corrected_command, "my_command run");
+
+        // ... and the exact correction comes back first for the same command
+        let correction = best(&corrector, &context("mycommand --help", "", 127)).unwrap();
+        assert_eq!(correction.corrected_command, "my_command --help");
+    }
+
+    #[test]
+    fn test_feedback_is_persisted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("corrections.json");
+
+        let corrector = CommandCorrector::new().with_persistence(&path);
+        let correction = corrector.suggest_correction("gti status", "", 127).unwrap();
+        corrector.record_feedback(&correction, true).unwrap();
+
+        let memory = CorrectionMemory::load(&path).unwrap();
+        assert_eq!(memory.feedback("gti status", "git status").accepted, 1);
+        assert_eq!(memory.rule_feedback("common_typos").accepted, 1);
+        assert_eq!(memory.learned_typo("gti"), Some("git"));
+    }
+
+    #[tokio::test]
+    async fn test_learned_feedback_is_saved_in_the_background() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("corrections.json");
+
+        let corrector = CommandCorrector::new().with_persistence(&path);
+        let correction = corrector.suggest_correction("gti status", "", 127).unwrap();
+        corrector.learn(&correction, true);
+        assert_eq!(corrector.memory().feedback("gti status", "git status").accepted, 1);
+        assert!(!path.exists());
+
+        corrector.clone().save_feedback().await.unwrap();
+        let memory = CorrectionMemory::load(&path).unwrap();
+        assert_eq!(memory.feedback("gti status", "git status").accepted, 1);
+    }
+
+    #[test]
+    fn test_clones_share_learning() {
+        let corrector = CommandCorrector::new();
+        let clone = corrector.clone();
+        let correction = context("foo", "", 1).correction("bar", 0.5, CorrectionReason::Misspelling, "manual");
+        clone.record_feedback(&correction, true).unwrap();
+        assert_eq!(corrector.memory().feedback("foo", "bar").accepted, 1);
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        struct AlwaysEcho;
+        impl CorrectionRule for AlwaysEcho {
+            fn name(&self) -> &'static str {
+                "always_echo"
+            }
+            fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+                vec![ctx.correction("echo ok", 0.99, CorrectionReason::ArgumentIssue, self.name())]
+            }
+        }
+
+        let corrector = CommandCorrector::new().with_rule(AlwaysEcho);
+        assert!(corrector.rule_names().contains(&"always_echo"));
+        let correction = best(&corrector, &context("false", "", 1)).unwrap();
+        assert_eq!(correction.rule, "always_echo");
+    }
+
+    #[test]
+    fn test_disabled_corrector() {
+        let mut corrector = CommandCorrector::new();
+        corrector.set_enabled(false);
+
+        let correction = corrector.suggest_correction("gti status", "", 127);
+        assert!(correction.is_none());
+    }
+}
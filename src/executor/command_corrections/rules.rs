@@ -0,0 +1,587 @@
+use std::path::Path;
+
+use super::{
+    edit_distance, replace_span, CommandCorrection, CorrectionContext, CorrectionReason,
+    CorrectionRule,
+};
+
+/// The rules every [`CommandCorrector`](super::CommandCorrector) starts with
+pub fn default_rules() -> Vec<Box<dyn CorrectionRule>> {
+    vec![
+        Box::new(CommonTypos),
+        Box::new(GitSimilarCommand),
+        Box::new(GitPushUpstream),
+        Box::new(GitCheckoutNewBranch),
+        Box::new(CargoSimilarCommand),
+        Box::new(NpmToNpx),
+        Box::new(ChmodExecutable),
+        Box::new(Sudo),
+        Box::new(CdCorrection),
+        Box::new(PathTypo),
+        Box::new(SimilarCommand),
+    ]
+}
+
+/// Shell builtins that never show up on `PATH` but are valid command names
+const SHELL_BUILTINS: &[&str] = &[
+    "alias", "bg", "cd", "echo", "eval", "exec", "exit", "export", "fg", "history", "jobs",
+    "kill", "pwd", "read", "set", "source", "test", "type", "ulimit", "umask", "unalias",
+    "unset", "wait",
+];
+
+const COMMON_TYPOS: &[(&str, &str)] = &[
+    ("gti", "git"),
+    ("gut", "git"),
+    ("got", "git"),
+    ("igt", "git"),
+    ("ger", "git"),
+    ("car", "cat"),
+    ("cta", "cat"),
+    ("tac", "cat"),
+    ("sl", "ls"),
+    ("l", "ls"),
+    ("ll", "ls -la"),
+    ("cd..", "cd .."),
+    ("cd.", "cd ."),
+    ("cdd", "cd"),
+    ("dc", "cd"),
+    ("pdw", "pwd"),
+    ("claer", "clear"),
+    ("clera", "clear"),
+    ("clearn", "clear"),
+    ("ecoh", "echo"),
+    ("ehco", "echo"),
+    ("mkdri", "mkdir"),
+    ("mdir", "mkdir"),
+    ("rimdir", "rmdir"),
+    ("rmder", "rmdir"),
+    ("gerp", "grep"),
+    ("grpe", "grep"),
+    ("fnid", "find"),
+    ("haed", "head"),
+    ("tial", "tail"),
+    ("lses", "less"),
+    ("histroy", "history"),
+    ("exprot", "export"),
+    ("sourec", "source"),
+    ("chmode", "chmod"),
+    ("chwon", "chown"),
+    ("pytohn", "python"),
+    ("pyhton", "python"),
+    ("pythno", "python"),
+    ("ndoe", "node"),
+    ("noed", "node"),
+    ("nmp", "npm"),
+    ("mnp", "npm"),
+    ("yran", "yarn"),
+    ("cagro", "cargo"),
+    ("crago", "cargo"),
+    ("gacro", "cargo"),
+    ("doker", "docker"),
+    ("dcoker", "docker"),
+    ("docekr", "docker"),
+    ("dokcer", "docker"),
+    ("kubetcl", "kubectl"),
+    ("kubeclt", "kubectl"),
+    ("culr", "curl"),
+    ("wgte", "wget"),
+    ("rscyn", "rsync"),
+];
+
+/// Well-known misspellings of common command names
+#[derive(Debug, Clone, Copy)]
+pub struct CommonTypos;
+
+impl CorrectionRule for CommonTypos {
+    fn name(&self) -> &'static str {
+        "common_typos"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        let Some(name) = ctx.command_name() else {
+            return Vec::new();
+        };
+        // An installed command with a typo-looking name is not a typo
+        if ctx.path.contains(&name.value) {
+            return Vec::new();
+        }
+        COMMON_TYPOS
+            .iter()
+            .find(|(typo, _)| *typo == name.value)
+            .map(|(_, fixed)| {
+                ctx.correction(
+                    replace_span(&ctx.command, name.span.clone(), fixed),
+                    0.9,
+                    CorrectionReason::Misspelling,
+                    self.name(),
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Unknown command: suggest the closest executable on `PATH`
+#[derive(Debug, Clone, Copy)]
+pub struct SimilarCommand;
+
+impl CorrectionRule for SimilarCommand {
+    fn name(&self) -> &'static str {
+        "similar_command"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        if ctx.exit_code != 127 && !ctx.stderr_contains("command not found") {
+            return Vec::new();
+        }
+        let Some(name) = ctx.command_name() else {
+            return Vec::new();
+        };
+        // Paths such as ./build.sh are not looked up on PATH
+        if name.value.contains('/') {
+            return Vec::new();
+        }
+
+        let candidates = ctx
+            .path
+            .iter()
+            .chain(SHELL_BUILTINS.iter().copied());
+        closest_matches(&name.value, candidates, 3)
+            .into_iter()
+            .map(|(candidate, distance)| {
+                let confidence = similarity(&name.value, candidate, distance) * 0.85;
+                ctx.correction(
+                    replace_span(&ctx.command, name.span.clone(), candidate),
+                    confidence,
+                    CorrectionReason::CommandNotFound,
+                    self.name(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// `git stauts` -> the command git itself suggests
+#[derive(Debug, Clone, Copy)]
+pub struct GitSimilarCommand;
+
+impl CorrectionRule for GitSimilarCommand {
+    fn name(&self) -> &'static str {
+        "git_similar_command"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        if ctx.program().as_deref() != Some("git") || !ctx.stderr_contains("is not a git command") {
+            return Vec::new();
+        }
+        subcommand_suggestions(ctx, &stderr_hints(&ctx.stderr, "The most similar command"), 0.9, self.name())
+    }
+}
+
+/// `cargo biuld` -> the command cargo itself suggests
+#[derive(Debug, Clone, Copy)]
+pub struct CargoSimilarCommand;
+
+impl CorrectionRule for CargoSimilarCommand {
+    fn name(&self) -> &'static str {
+        "cargo_similar_command"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        if ctx.program().as_deref() != Some("cargo") || !ctx.stderr_contains("no such command") {
+            return Vec::new();
+        }
+        // error: no such command: `biuld`
+        //
+        //         Did you mean `build`?
+        let hints: Vec<String> = ctx
+            .stderr
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("Did you mean `"))
+            .filter_map(|rest| rest.split('`').next())
+            .map(str::to_string)
+            .collect();
+        subcommand_suggestions(ctx, &hints, 0.9, self.name())
+    }
+}
+
+/// `git push` on a branch without upstream -> set the upstream
+#[derive(Debug, Clone, Copy)]
+pub struct GitPushUpstream;
+
+impl CorrectionRule for GitPushUpstream {
+    fn name(&self) -> &'static str {
+        "git_push_upstream"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        let argv = ctx.argv();
+        if argv.first().map(String::as_str) != Some("git")
+            || argv.get(1).map(String::as_str) != Some("push")
+            || !ctx.stderr_contains("no upstream branch")
+        {
+            return Vec::new();
+        }
+        // Extra arguments other than flags mean the user already named a target
+        if argv[2..].iter().any(|arg| !arg.starts_with('-')) {
+            return Vec::new();
+        }
+
+        // Prefer the exact command git printed, e.g. `git push --set-upstream origin feature`
+        let push = ctx
+            .stderr
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("git push --set-upstream") || line.starts_with("git push -u"))
+            .unwrap_or("git push --set-upstream origin HEAD");
+        let flags: Vec<&str> = argv[2..].iter().map(String::as_str).collect();
+        let corrected = if flags.is_empty() {
+            push.to_string()
+        } else {
+            format!("{} {}", push, flags.join(" "))
+        };
+
+        vec![ctx.correction(corrected, 0.95, CorrectionReason::MissingFlags, self.name())]
+    }
+}
+
+/// `git checkout missing-branch` -> create the branch
+#[derive(Debug, Clone, Copy)]
+pub struct GitCheckoutNewBranch;
+
+impl CorrectionRule for GitCheckoutNewBranch {
+    fn name(&self) -> &'static str {
+        "git_checkout_create_branch"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        let argv = ctx.argv();
+        let create_flag = match argv.get(1).map(String::as_str) {
+            Some("checkout") => "-b",
+            Some("switch") => "-c",
+            _ => return Vec::new(),
+        };
+        if argv.first().map(String::as_str) != Some("git")
+            || argv.len() != 3
+            || argv[2].starts_with('-')
+            || !(ctx.stderr_contains("did not match any file(s) known to git")
+                || ctx.stderr_contains("invalid reference"))
+        {
+            return Vec::new();
+        }
+
+        vec![ctx.correction(
+            format!("git {} {} {}", argv[1], create_flag, argv[2]),
+            0.8,
+            CorrectionReason::MissingFlags,
+            self.name(),
+        )]
+    }
+}
+
+/// `npm <binary>` -> `npx <binary>`
+#[derive(Debug, Clone, Copy)]
+pub struct NpmToNpx;
+
+impl CorrectionRule for NpmToNpx {
+    fn name(&self) -> &'static str {
+        "npm_to_npx"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        const NPM_COMMANDS: &[&str] = &["install", "start", "test", "run", "init"];
+
+        let Some(name) = ctx.command_name() else {
+            return Vec::new();
+        };
+        let argv = ctx.argv();
+        if name.value != "npm"
+            || argv.len() < 2
+            || NPM_COMMANDS.contains(&argv[1].as_str())
+            || !(ctx.stderr_contains("command not found") || ctx.stderr_contains("Unknown command"))
+        {
+            return Vec::new();
+        }
+
+        vec![ctx.correction(
+            replace_span(&ctx.command, name.span, "npx"),
+            0.8,
+            CorrectionReason::CommandNotFound,
+            self.name(),
+        )]
+    }
+}
+
+/// `./script.sh` without the executable bit -> make it executable first
+#[derive(Debug, Clone, Copy)]
+pub struct ChmodExecutable;
+
+impl CorrectionRule for ChmodExecutable {
+    fn name(&self) -> &'static str {
+        "chmod_exec"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        let Some(name) = ctx.command_name() else {
+            return Vec::new();
+        };
+        if !name.value.contains('/') || !ctx.stderr_contains("permission denied") {
+            return Vec::new();
+        }
+        let script = ctx.cwd.join(&name.value);
+        if !script.is_file() || is_executable(&script) {
+            return Vec::new();
+        }
+
+        let command = ctx.command.trim();
+        vec![ctx.correction(
+            format!("chmod +x {} && {}", name.raw, command),
+            0.9,
+            CorrectionReason::PermissionIssue,
+            self.name(),
+        )]
+    }
+}
+
+/// Permission errors -> rerun with sudo
+#[derive(Debug, Clone, Copy)]
+pub struct Sudo;
+
+impl CorrectionRule for Sudo {
+    fn name(&self) -> &'static str {
+        "sudo"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        const PATTERNS: &[&str] = &[
+            "permission denied",
+            "operation not permitted",
+            "eacces",
+            "must be root",
+            "must be run as root",
+            "are you root",
+            "requires superuser privilege",
+            "you need to be root",
+        ];
+
+        let Some(name) = ctx.command_name() else {
+            return Vec::new();
+        };
+        // Scripts get the chmod rule; sudo does not help a shell builtin
+        if name.value == "sudo" || name.value.contains('/') || SHELL_BUILTINS.contains(&name.value.as_str()) {
+            return Vec::new();
+        }
+        if !PATTERNS.iter().any(|pattern| ctx.stderr_contains(pattern)) {
+            return Vec::new();
+        }
+
+        let confidence = if name.value == "docker" { 0.9 } else { 0.8 };
+        vec![ctx.correction(
+            format!("sudo {}", ctx.command.trim()),
+            confidence,
+            CorrectionReason::PermissionIssue,
+            self.name(),
+        )]
+    }
+}
+
+/// `cd missing` -> the closest existing directory, or create it
+#[derive(Debug, Clone, Copy)]
+pub struct CdCorrection;
+
+impl CorrectionRule for CdCorrection {
+    fn name(&self) -> &'static str {
+        "cd_correction"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        let Some(command) = ctx.first_command() else {
+            return Vec::new();
+        };
+        let is_cd = command.name.as_ref().is_some_and(|name| name.value == "cd");
+        if !is_cd || command.args.len() != 1 || !ctx.stderr_contains("no such file or directory") {
+            return Vec::new();
+        }
+        let target = &command.args[0];
+
+        let mut corrections = Vec::new();
+        if let Some(dir) = closest_entry(&ctx.cwd, &target.value, true) {
+            corrections.push(ctx.correction(
+                replace_span(&ctx.command, target.span.clone(), &dir),
+                0.85,
+                CorrectionReason::PathIssue,
+                self.name(),
+            ));
+        }
+        corrections.push(ctx.correction(
+            format!("mkdir -p {} && cd {}", target.raw, target.raw),
+            0.6,
+            CorrectionReason::PathIssue,
+            "mkdir_cd",
+        ));
+        corrections
+    }
+}
+
+/// Misspelled path argument -> the closest existing sibling
+#[derive(Debug, Clone, Copy)]
+pub struct PathTypo;
+
+impl CorrectionRule for PathTypo {
+    fn name(&self) -> &'static str {
+        "path_typo"
+    }
+
+    fn suggest(&self, ctx: &CorrectionContext) -> Vec<CommandCorrection> {
+        if !ctx.stderr_contains("no such file or directory") {
+            return Vec::new();
+        }
+        let Some(command) = ctx.first_command() else {
+            return Vec::new();
+        };
+        if command.name.as_ref().is_some_and(|name| name.value == "cd") {
+            return Vec::new();
+        }
+
+        // Fix the first missing argument the error message names, or failing
+        // that the first missing argument at all
+        let missing: Vec<_> = command
+            .args
+            .iter()
+            .filter(|arg| !arg.value.starts_with('-') && !arg.has_expansion)
+            .filter(|arg| !ctx.cwd.join(&arg.value).exists())
+            .collect();
+        let Some(arg) = missing
+            .iter()
+            .find(|arg| ctx.stderr.contains(&arg.value))
+            .or_else(|| missing.first())
+        else {
+            return Vec::new();
+        };
+
+        closest_entry(&ctx.cwd, &arg.value, false)
+            .map(|path| {
+                ctx.correction(
+                    replace_span(&ctx.command, arg.span.clone(), &path),
+                    0.75,
+                    CorrectionReason::PathIssue,
+                    self.name(),
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Replace the subcommand (the first argument) with each of `hints`
+fn subcommand_suggestions(
+    ctx: &CorrectionContext,
+    hints: &[String],
+    confidence: f32,
+    rule: &str,
+) -> Vec<CommandCorrection> {
+    let Some(subcommand) = ctx.first_command().and_then(|command| command.args.into_iter().next()) else {
+        return Vec::new();
+    };
+    hints
+        .iter()
+        .enumerate()
+        .map(|(i, hint)| {
+            ctx.correction(
+                replace_span(&ctx.command, subcommand.span.clone(), hint),
+                // Tools list their best guess first
+                confidence - 0.05 * i as f32,
+                CorrectionReason::Misspelling,
+                rule,
+            )
+        })
+        .collect()
+}
+
+/// Indented lines following a line that starts with `header`, as printed
+/// by git:
+///
+/// ```text
+/// The most similar commands are
+///         status
+///         stage
+/// ```
+fn stderr_hints(stderr: &str, header: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with(header))
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace) && !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+        .collect()
+}
+
+/// Up to `limit` candidates within typo distance of `word`, best first
+fn closest_matches<'a>(
+    word: &str,
+    candidates: impl Iterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<(&'a str, usize)> {
+    let max_distance = if word.chars().count() <= 4 { 1 } else { 2 };
+    let first = word.chars().next();
+
+    let mut matches: Vec<(&str, usize)> = candidates
+        .filter(|candidate| *candidate != word)
+        .filter_map(|candidate| {
+            let distance = edit_distance(word, candidate);
+            (distance <= max_distance).then_some((candidate, distance))
+        })
+        .collect();
+    matches.sort_by_key(|(candidate, distance)| {
+        (
+            *distance,
+            candidate.chars().next() != first,
+            candidate.len().abs_diff(word.len()),
+            *candidate,
+        )
+    });
+    matches.dedup_by_key(|(candidate, _)| *candidate);
+    matches.truncate(limit);
+    matches
+}
+
+/// 1.0 for identical words, falling towards 0.0 as edits are needed
+fn similarity(a: &str, b: &str, distance: usize) -> f32 {
+    let len = a.chars().count().max(b.chars().count()).max(1);
+    1.0 - distance as f32 / len as f32
+}
+
+/// Closest existing entry to `path` (relative to `cwd`) among its siblings,
+/// returned with the same parent prefix the user typed.
+fn closest_entry(cwd: &Path, path: &str, directories_only: bool) -> Option<String> {
+    let trimmed = path.trim_end_matches('/');
+    let (prefix, file_name) = match trimmed.rfind('/') {
+        Some(i) => (&trimmed[..=i], &trimmed[i + 1..]),
+        None => ("", trimmed),
+    };
+    if file_name.is_empty() {
+        return None;
+    }
+
+    let entries: Vec<String> = std::fs::read_dir(cwd.join(prefix))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| !directories_only || entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+
+    closest_matches(file_name, entries.iter().map(String::as_str), 1)
+        .first()
+        .map(|(entry, _)| format!("{}{}", prefix, entry))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
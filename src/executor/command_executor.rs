@@ -2,7 +2,11 @@ use std::process::Stdio;
 use tokio::process::Command;
 use tokio::io::{AsyncReadExt, BufReader};
 use serde::{Serialize, Deserialize};
-use super::command_corrections::{CommandCorrector, CommandCorrection};
+use super::command_corrections::{CommandCorrector, CommandCorrection, CorrectionContext};
+use crate::syntax_tree::ShellDialect;
+
+/// How long the shell gets to say whether it has a command
+const COMMAND_LOOKUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
 
 /// Result of command execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,42 +69,96 @@ impl CommandExecutor {
         }
     }
 
+    /// The dialect of the shell commands run in
+    pub fn dialect(&self) -> ShellDialect {
+        ShellDialect::from_shell_path(&self.shell)
+    }
+
     /// Set the working directory
     pub fn set_working_dir(&mut self, dir: std::path::PathBuf) {
         self.working_dir = dir;
     }
 
+    /// Directory commands run in
+    pub fn working_dir(&self) -> &std::path::Path {
+        &self.working_dir
+    }
+
     /// Add environment variable
     pub fn set_env_var(&mut self, key: String, value: String) {
         self.env_vars.insert(key, value);
     }
 
+    /// Use a custom command corrector, e.g. one that persists what it learns
+    pub fn with_corrector(mut self, corrector: CommandCorrector) -> Self {
+        self.corrector = corrector;
+        self
+    }
+
+    /// The corrector used to suggest fixes for failed commands
+    pub fn corrector(&self) -> &CommandCorrector {
+        &self.corrector
+    }
+
+    /// Whether the shell commands run in knows `name`, as a program on its
+    /// `PATH`, a function, an alias or a builtin. A shell that does not
+    /// answer in time is taken to know it. `name` goes into the script as
+    /// is, so it must be a plain word.
+    pub async fn has_command(self, name: &'static str) -> bool {
+        let mut cmd = Command::new(&self.shell);
+        cmd.arg("-c")
+            .arg(format!("command -v {name}"))
+            .current_dir(&self.working_dir)
+            .envs(&self.env_vars)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        match tokio::time::timeout(COMMAND_LOOKUP_TIMEOUT, cmd.status()).await {
+            Ok(status) => status.is_ok_and(|status| status.success()),
+            Err(_) => true,
+        }
+    }
+
     /// Execute a command asynchronously
     pub async fn execute_command(&self, command_text: &str) -> ExecutionResult {
         let start_time = std::time::Instant::now();
         
-        if let Some(result) = self.handle_builtin_command(command_text).await {
-            return result;
-        }
-
-        match self.execute_external_command(command_text).await {
-            Ok(mut result) => {
-                let correction = self.corrector.suggest_correction(command_text, &result.stderr, result.exit_code);
-                result.correction = correction;
-                result.execution_time = start_time.elapsed();
-                result
-            }
-            Err(e) => {
-                ExecutionResult {
-                    stdout: String::new(),
-                    stderr: format!("Error executing command: {}", e),
-                    exit_code: -1,
-                    execution_time: start_time.elapsed(),
-                    correction: None,
-                    pid: None,
+        let mut result = match self.handle_builtin_command(command_text).await {
+            Some(result) => result,
+            None => match self.execute_external_command(command_text).await {
+                Ok(mut result) => {
+                    result.execution_time = start_time.elapsed();
+                    result
                 }
-            }
+                Err(e) => {
+                    return ExecutionResult {
+                        stdout: String::new(),
+                        stderr: format!("Error executing command: {}", e),
+                        exit_code: -1,
+                        execution_time: start_time.elapsed(),
+                        correction: None,
+                        pid: None,
+                    };
+                }
+            },
+        };
+
+        result.correction = self.suggest_correction(command_text, &result);
+        result
+    }
+
+    /// Best correction for a failed command, judged against the directory
+    /// and `PATH` it ran with
+    fn suggest_correction(&self, command_text: &str, result: &ExecutionResult) -> Option<CommandCorrection> {
+        if result.exit_code == 0 {
+            return None;
         }
+        let path = self.env_vars.get("PATH").map(String::as_str).unwrap_or_default();
+        let ctx = CorrectionContext::new(command_text, result.stderr.as_str(), result.exit_code)
+            .with_cwd(self.working_dir.clone())
+            .with_path(self.corrector.path_commands(path));
+        self.corrector.suggest_corrections(&ctx).into_iter().next()
     }
 
     /// Handle built-in terminal commands
@@ -616,6 +674,39 @@ mod tests {
         assert!(result.stderr.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_has_command_looks_at_the_shell_path() {
+        let mut executor = CommandExecutor::new();
+        if which::which(&executor.shell).is_err() {
+            return;
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        executor.set_env_var("PATH".to_string(), dir.path().display().to_string());
+        assert!(!executor.clone().has_command("edit").await);
+
+        let program = dir.path().join("edit");
+        std::fs::write(&program, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        assert!(executor.has_command("edit").await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_builtin_gets_correction() {
+        let executor = CommandExecutor::new();
+        let result = executor.execute_command("cd /nonexistent-correction-test").await;
+
+        assert_eq!(result.exit_code, 1);
+        let correction = result.correction.unwrap();
+        assert_eq!(
+            correction.corrected_command,
+            "mkdir -p /nonexistent-correction-test && cd /nonexistent-correction-test"
+        );
+    }
+
     #[tokio::test]
     async fn test_external_command() {
         let executor = CommandExecutor::new();
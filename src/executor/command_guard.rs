@@ -0,0 +1,810 @@
+//! Pre-execution safety checks for commands typed into the terminal.
+//!
+//! [`CommandGuard`] parses a command line with the shell parser and looks for
+//! destructive patterns (recursive deletes of system paths, writes to raw
+//! disks, force pushes to protected branches, `kubectl delete` against
+//! production) as well as unbalanced quotes. Each rule has a configurable
+//! [`GuardPolicy`]; the strictest policy among the findings decides whether
+//! the command runs, needs confirmation or is blocked.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::syntax_tree::{parse_shell, CommandView, ShellDialect, ShellSyntaxTree};
+use crate::utils::process::output_with_deadline;
+
+/// What to do when a rule matches. Ordered from most to least permissive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GuardPolicy {
+    Allow,
+    Warn,
+    Confirm,
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardRule {
+    /// `rm -r` on `/`, the home directory or a top-level system directory.
+    RecursiveDelete,
+    /// `dd of=/dev/sda`, `mkfs`, redirects onto block devices.
+    RawDeviceWrite,
+    /// `git push --force` to a protected branch.
+    ForcePushProtected,
+    /// `kubectl delete` in a production context or namespace.
+    KubectlDeleteProduction,
+    /// A quote that is never closed.
+    UnbalancedQuotes,
+}
+
+impl GuardRule {
+    pub const ALL: [GuardRule; 5] = [
+        GuardRule::RecursiveDelete,
+        GuardRule::RawDeviceWrite,
+        GuardRule::ForcePushProtected,
+        GuardRule::KubectlDeleteProduction,
+        GuardRule::UnbalancedQuotes,
+    ];
+
+    pub fn default_policy(&self) -> GuardPolicy {
+        match self {
+            GuardRule::RecursiveDelete => GuardPolicy::Block,
+            GuardRule::RawDeviceWrite => GuardPolicy::Confirm,
+            GuardRule::ForcePushProtected => GuardPolicy::Confirm,
+            GuardRule::KubectlDeleteProduction => GuardPolicy::Confirm,
+            GuardRule::UnbalancedQuotes => GuardPolicy::Warn,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuardConfig {
+    pub enabled: bool,
+    /// Overrides of the default policy per rule.
+    pub policies: HashMap<GuardRule, GuardPolicy>,
+    /// Branch patterns that must not be force pushed; `*` matches any text.
+    pub protected_branches: Vec<String>,
+    /// Kubernetes context or namespace patterns that count as production.
+    pub production_patterns: Vec<String>,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            policies: HashMap::new(),
+            protected_branches: vec![
+                "main".to_string(),
+                "master".to_string(),
+                "release/*".to_string(),
+                "production".to_string(),
+            ],
+            production_patterns: vec!["*prod*".to_string()],
+        }
+    }
+}
+
+impl GuardConfig {
+    pub fn policy(&self, rule: GuardRule) -> GuardPolicy {
+        self.policies
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_policy())
+    }
+
+    pub fn set_policy(&mut self, rule: GuardRule, policy: GuardPolicy) {
+        self.policies.insert(rule, policy);
+    }
+}
+
+/// A rule that matched, with the part of the command line it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardFinding {
+    pub rule: GuardRule,
+    pub policy: GuardPolicy,
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+/// All findings for a command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GuardVerdict {
+    pub findings: Vec<GuardFinding>,
+}
+
+/// What the terminal should do with a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardDecision {
+    /// Run it, showing any warnings.
+    Run { warnings: Vec<String> },
+    /// Ask the user to confirm before running it.
+    Confirm { reasons: Vec<String> },
+    /// Refuse to run it.
+    Block { reasons: Vec<String> },
+}
+
+impl GuardVerdict {
+    /// The strictest policy among the findings.
+    pub fn policy(&self) -> GuardPolicy {
+        self.findings
+            .iter()
+            .map(|finding| finding.policy)
+            .max()
+            .unwrap_or(GuardPolicy::Allow)
+    }
+
+    fn messages(&self, min_policy: GuardPolicy) -> Vec<String> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.policy >= min_policy)
+            .map(|finding| finding.message.clone())
+            .collect()
+    }
+
+    /// Decide what to do; `confirmed` is whether the user already confirmed
+    /// this exact command.
+    pub fn decide(&self, confirmed: bool) -> GuardDecision {
+        match self.policy() {
+            GuardPolicy::Block => GuardDecision::Block {
+                reasons: self.messages(GuardPolicy::Block),
+            },
+            GuardPolicy::Confirm if !confirmed => GuardDecision::Confirm {
+                reasons: self.messages(GuardPolicy::Confirm),
+            },
+            _ => GuardDecision::Run {
+                warnings: self.messages(GuardPolicy::Warn),
+            },
+        }
+    }
+}
+
+/// How long a `git` or `kubectl` lookup may take.
+pub const ENVIRONMENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// State outside the command line that some rules depend on. It is only
+/// read once a rule has matched a command that needs it, such as a force
+/// push without a refspec or a `kubectl delete` without `--context`.
+pub trait GuardEnvironment: Send + Sync {
+    /// The branch checked out in the working directory.
+    fn current_git_branch(&self) -> Option<String>;
+    /// The active kubectl context.
+    fn current_kube_context(&self) -> Option<String>;
+}
+
+/// Reads the environment by running `git` and `kubectl`, in the process's
+/// working directory unless given another. Each lookup is killed after
+/// [`ENVIRONMENT_TIMEOUT`] and treated as unknown, so a hung `kubectl`
+/// cannot hold up a check.
+#[derive(Debug, Clone, Default)]
+pub struct SystemGuardEnvironment {
+    working_dir: Option<PathBuf>,
+}
+
+impl SystemGuardEnvironment {
+    /// Run `git` and `kubectl` in `working_dir`, the directory the checked
+    /// commands will run in
+    pub fn new(working_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            working_dir: Some(working_dir.into()),
+        }
+    }
+
+    fn output(&self, program: &str, args: &[&str]) -> Option<String> {
+        let mut command = Command::new(program);
+        command.args(args);
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+        let output = output_with_deadline(command, ENVIRONMENT_TIMEOUT)
+            .map_err(|e| tracing::debug!("{} for the command guard failed: {}", program, e))
+            .ok()?;
+        let text = output.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+impl GuardEnvironment for SystemGuardEnvironment {
+    fn current_git_branch(&self) -> Option<String> {
+        self.output("git", &["rev-parse", "--abbrev-ref", "HEAD"])
+    }
+
+    fn current_kube_context(&self) -> Option<String> {
+        self.output("kubectl", &["config", "current-context"])
+    }
+}
+
+/// Wrappers whose first operand is the command that actually runs.
+const COMMAND_WRAPPERS: &[&str] = &["sudo", "doas", "command", "builtin", "exec", "nohup", "time", "env", "nice"];
+
+/// kubectl global flags whose value may be the next word.
+const KUBECTL_VALUE_FLAGS: &[&str] = &[
+    "--kubeconfig", "--cluster", "--user", "-s", "--server", "--as", "--as-group", "--token",
+    "--request-timeout", "--cache-dir", "--certificate-authority", "--client-certificate",
+    "--client-key", "--tls-server-name", "-v", "--v",
+];
+
+/// Paths a recursive delete must never target.
+const CRITICAL_PATHS: &[&str] = &[
+    "/", "/*", "~", "~/*", "$HOME", "${HOME}", "$HOME/*", "/bin", "/boot", "/dev", "/etc",
+    "/home", "/lib", "/lib64", "/opt", "/proc", "/root", "/sbin", "/sys", "/usr", "/var",
+    "/System", "/Users", "/Library", "/Applications",
+];
+
+/// Device name prefixes of whole disks and partitions.
+const DISK_DEVICES: &[&str] = &["sd", "hd", "vd", "xvd", "nvme", "mmcblk", "disk", "rdisk"];
+
+pub struct CommandGuard {
+    config: GuardConfig,
+    dialect: ShellDialect,
+    environment: Box<dyn GuardEnvironment>,
+}
+
+impl std::fmt::Debug for CommandGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandGuard")
+            .field("config", &self.config)
+            .field("dialect", &self.dialect)
+            .finish()
+    }
+}
+
+impl Default for CommandGuard {
+    fn default() -> Self {
+        Self::new(GuardConfig::default())
+    }
+}
+
+impl CommandGuard {
+    pub fn new(config: GuardConfig) -> Self {
+        Self {
+            config,
+            dialect: ShellDialect::default(),
+            environment: Box::new(SystemGuardEnvironment::default()),
+        }
+    }
+
+    pub fn with_environment(mut self, environment: Box<dyn GuardEnvironment>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: ShellDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn config(&self) -> &GuardConfig {
+        &self.config
+    }
+
+    /// Check a command line before it runs.
+    pub fn check(&self, command_line: &str) -> GuardVerdict {
+        let mut verdict = GuardVerdict::default();
+        if !self.config.enabled {
+            return verdict;
+        }
+
+        let tree = parse_shell(command_line, self.dialect);
+        let mut findings = Vec::new();
+        self.check_quotes(&tree, &mut findings);
+        for command in tree.commands() {
+            let Some(argv) = effective_argv(&command) else {
+                continue;
+            };
+            self.check_recursive_delete(&command, &argv, &mut findings);
+            self.check_raw_device_write(&command, &argv, &mut findings);
+            self.check_force_push(&command, &argv, &mut findings);
+            self.check_kubectl_delete(&command, &argv, &mut findings);
+        }
+
+        verdict.findings = findings
+            .into_iter()
+            .filter_map(|(rule, message, span)| {
+                let policy = self.config.policy(rule);
+                (policy != GuardPolicy::Allow).then_some(GuardFinding {
+                    rule,
+                    policy,
+                    message,
+                    span,
+                })
+            })
+            .collect();
+        verdict
+    }
+
+    /// [`check`](Self::check) on the blocking thread pool, since reading
+    /// the environment runs `git` or `kubectl`.
+    pub async fn check_async(self, command_line: String) -> GuardVerdict {
+        tokio::task::spawn_blocking(move || self.check(&command_line))
+            .await
+            .unwrap_or_default()
+    }
+
+    fn check_quotes(&self, tree: &ShellSyntaxTree, findings: &mut Vec<Finding>) {
+        for error in tree.errors() {
+            let text = &tree.source()[error.span.start..];
+            if text.starts_with(['\'', '"']) || text.starts_with("$'") {
+                findings.push((
+                    GuardRule::UnbalancedQuotes,
+                    format!("Unbalanced quote at position {}", error.span.start),
+                    error.span.clone(),
+                ));
+            }
+        }
+    }
+
+    fn check_recursive_delete(&self, command: &CommandView, argv: &[Arg], findings: &mut Vec<Finding>) {
+        if argv[0].value != "rm" {
+            return;
+        }
+        let operands: Vec<&Arg> = operands(&argv[1..]);
+        let recursive = argv[1..].iter().take_while(|a| a.value != "--").any(|arg| {
+            arg.value == "--recursive"
+                || (arg.value.starts_with('-')
+                    && !arg.value.starts_with("--")
+                    && arg.value.contains(['r', 'R']))
+        });
+        if !recursive {
+            return;
+        }
+
+        for target in operands {
+            let path = normalize_path(&target.raw);
+            if CRITICAL_PATHS.contains(&path.as_str()) {
+                findings.push((
+                    GuardRule::RecursiveDelete,
+                    format!("Recursive delete of '{}'", target.raw),
+                    command.span.clone(),
+                ));
+            }
+        }
+    }
+
+    fn check_raw_device_write(&self, command: &CommandView, argv: &[Arg], findings: &mut Vec<Finding>) {
+        let name = argv[0].value.as_str();
+        if name == "dd" {
+            for arg in &argv[1..] {
+                if let Some(device) = arg.value.strip_prefix("of=") {
+                    if is_disk_device(device) {
+                        findings.push((
+                            GuardRule::RawDeviceWrite,
+                            format!("dd writes directly to disk device {}", device),
+                            command.span.clone(),
+                        ));
+                    }
+                }
+            }
+        } else if name.starts_with("mkfs") || name == "wipefs" {
+            findings.push((
+                GuardRule::RawDeviceWrite,
+                format!("'{}' erases a file system", name),
+                command.span.clone(),
+            ));
+        }
+
+        for redirect in &command.redirects {
+            let writes = redirect.operator.contains('>');
+            if let Some(target) = redirect.target.as_ref().filter(|_| writes) {
+                if is_disk_device(&target.value) {
+                    findings.push((
+                        GuardRule::RawDeviceWrite,
+                        format!("Output redirected to disk device {}", target.value),
+                        redirect.span.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_force_push(&self, command: &CommandView, argv: &[Arg], findings: &mut Vec<Finding>) {
+        if argv[0].value != "git" {
+            return;
+        }
+        // Skip global options such as `-C <path>` before the subcommand
+        let mut rest = &argv[1..];
+        while let Some(arg) = rest.first() {
+            if !arg.value.starts_with('-') {
+                break;
+            }
+            let takes_value = matches!(arg.value.as_str(), "-C" | "-c" | "--git-dir" | "--work-tree");
+            rest = &rest[if takes_value { 2 } else { 1 }.min(rest.len())..];
+        }
+        if rest.first().map(|a| a.value.as_str()) != Some("push") {
+            return;
+        }
+        let args = &rest[1..];
+
+        let force_flag = args.iter().any(|arg| {
+            arg.value == "--force"
+                || arg.value.starts_with("--force-with-lease")
+                || (arg.value.starts_with('-') && !arg.value.starts_with("--") && arg.value.contains('f'))
+        });
+        let positionals = operands(args);
+        let refspecs = positionals.get(1..).unwrap_or_default();
+        let forced_refspec = refspecs.iter().any(|spec| spec.value.starts_with('+'));
+        if !force_flag && !forced_refspec {
+            return;
+        }
+
+        let mut branches: Vec<String> = refspecs
+            .iter()
+            .filter(|spec| force_flag || spec.value.starts_with('+'))
+            .map(|spec| {
+                let spec = spec.value.trim_start_matches('+');
+                let destination = spec.rsplit(':').next().unwrap_or(spec);
+                destination.trim_start_matches("refs/heads/").to_string()
+            })
+            .collect();
+        if branches.is_empty() || branches.iter().any(|b| b == "HEAD") {
+            branches.retain(|b| b != "HEAD");
+            branches.extend(self.environment.current_git_branch());
+        }
+
+        for branch in branches {
+            if self
+                .config
+                .protected_branches
+                .iter()
+                .any(|pattern| glob_match(pattern, &branch))
+            {
+                findings.push((
+                    GuardRule::ForcePushProtected,
+                    format!("Force push to protected branch '{}'", branch),
+                    command.span.clone(),
+                ));
+            }
+        }
+    }
+
+    fn check_kubectl_delete(&self, command: &CommandView, argv: &[Arg], findings: &mut Vec<Finding>) {
+        if argv[0].value != "kubectl" {
+            return;
+        }
+
+        let mut context = None;
+        let mut namespace = None;
+        let mut subcommand = None;
+        let mut args = argv[1..].iter();
+        while let Some(arg) = args.next() {
+            let value = arg.value.as_str();
+            let (flag, inline) = match value.split_once('=') {
+                Some((flag, inline)) if flag.starts_with('-') => (flag, Some(inline.to_string())),
+                _ => (value, None),
+            };
+            match flag {
+                "--context" => context = inline.or_else(|| args.next().map(|a| a.value.clone())),
+                "-n" | "--namespace" => {
+                    namespace = inline.or_else(|| args.next().map(|a| a.value.clone()))
+                }
+                // Skip the separate value of other flags, so it is not
+                // taken for the subcommand
+                _ if inline.is_none() && KUBECTL_VALUE_FLAGS.contains(&flag) => {
+                    args.next();
+                }
+                _ if !value.starts_with('-') && subcommand.is_none() => subcommand = Some(value),
+                _ => {}
+            }
+        }
+        if subcommand != Some("delete") {
+            return;
+        }
+
+        let context = context.or_else(|| self.environment.current_kube_context());
+        let is_production = |name: &str| {
+            self.config
+                .production_patterns
+                .iter()
+                .any(|pattern| glob_match(&pattern.to_lowercase(), &name.to_lowercase()))
+        };
+
+        let target = match (&context, &namespace) {
+            (Some(context), _) if is_production(context) => Some(format!("context '{}'", context)),
+            (_, Some(namespace)) if is_production(namespace) => {
+                Some(format!("namespace '{}'", namespace))
+            }
+            _ => None,
+        };
+        if let Some(target) = target {
+            findings.push((
+                GuardRule::KubectlDeleteProduction,
+                format!("kubectl delete in production {}", target),
+                command.span.clone(),
+            ));
+        }
+    }
+}
+
+type Finding = (GuardRule, String, Range<usize>);
+
+/// A word of a command: unquoted value and the text as typed.
+struct Arg {
+    value: String,
+    raw: String,
+}
+
+/// The words of the command that actually runs, looking through `sudo`,
+/// `env` and similar wrappers.
+fn effective_argv(command: &CommandView) -> Option<Vec<Arg>> {
+    let mut words: Vec<Arg> = command
+        .words()
+        .into_iter()
+        .map(|word| Arg {
+            value: word.value.clone(),
+            raw: word.raw.clone(),
+        })
+        .collect();
+
+    while words
+        .first()
+        .is_some_and(|word| COMMAND_WRAPPERS.contains(&word.value.as_str()))
+    {
+        words.remove(0);
+        // Options and assignments of the wrapper itself
+        while words
+            .first()
+            .is_some_and(|word| word.value.starts_with('-') || word.value.contains('='))
+        {
+            words.remove(0);
+        }
+    }
+
+    (!words.is_empty()).then_some(words)
+}
+
+/// Arguments that are not options, honouring `--`.
+fn operands(args: &[Arg]) -> Vec<&Arg> {
+    let mut operands = Vec::new();
+    let mut options_ended = false;
+    for arg in args {
+        if !options_ended && arg.value == "--" {
+            options_ended = true;
+        } else if options_ended || !arg.value.starts_with('-') || arg.value == "-" {
+            operands.push(arg);
+        }
+    }
+    operands
+}
+
+/// Collapse repeated and trailing slashes so `//`, `/./` and `/etc/` match.
+fn normalize_path(path: &str) -> String {
+    let unquoted = path.trim_matches(|c| c == '"' || c == '\'');
+    let mut parts = Vec::new();
+    for part in unquoted.split('/') {
+        match part {
+            "" | "." => {}
+            _ => parts.push(part),
+        }
+    }
+    let joined = parts.join("/");
+    if unquoted.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+fn is_disk_device(path: &str) -> bool {
+    path.strip_prefix("/dev/")
+        .is_some_and(|device| DISK_DEVICES.iter().any(|prefix| device.starts_with(prefix)))
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeEnvironment {
+        branch: Option<&'static str>,
+        context: Option<&'static str>,
+    }
+
+    impl GuardEnvironment for FakeEnvironment {
+        fn current_git_branch(&self) -> Option<String> {
+            self.branch.map(str::to_string)
+        }
+
+        fn current_kube_context(&self) -> Option<String> {
+            self.context.map(str::to_string)
+        }
+    }
+
+    fn guard() -> CommandGuard {
+        CommandGuard::default().with_environment(Box::new(FakeEnvironment {
+            branch: Some("main"),
+            context: Some("staging"),
+        }))
+    }
+
+    fn rules(guard: &CommandGuard, command: &str) -> Vec<GuardRule> {
+        guard.check(command).findings.iter().map(|f| f.rule).collect()
+    }
+
+    #[test]
+    fn test_recursive_delete() {
+        let guard = guard();
+        assert_eq!(rules(&guard, "rm -rf /"), vec![GuardRule::RecursiveDelete]);
+        assert_eq!(rules(&guard, "sudo rm -r --no-preserve-root //"), vec![GuardRule::RecursiveDelete]);
+        assert_eq!(rules(&guard, "cd /tmp && rm -fR ~/"), vec![GuardRule::RecursiveDelete]);
+        assert_eq!(rules(&guard, "rm -rf \"$HOME\""), vec![GuardRule::RecursiveDelete]);
+        assert!(rules(&guard, "rm -rf ./target /tmp/build").is_empty());
+        assert!(rules(&guard, "rm /").is_empty());
+        assert!(rules(&guard, "echo 'rm -rf /'").is_empty());
+    }
+
+    #[test]
+    fn test_raw_device_write() {
+        let guard = guard();
+        assert_eq!(
+            rules(&guard, "sudo dd if=disk.img of=/dev/sdb bs=4M"),
+            vec![GuardRule::RawDeviceWrite]
+        );
+        assert_eq!(rules(&guard, "mkfs.ext4 /dev/nvme0n1p1"), vec![GuardRule::RawDeviceWrite]);
+        assert_eq!(rules(&guard, "cat image > /dev/disk2"), vec![GuardRule::RawDeviceWrite]);
+        assert!(rules(&guard, "dd if=/dev/sda of=backup.img").is_empty());
+        assert!(rules(&guard, "echo hi > /dev/null").is_empty());
+    }
+
+    #[test]
+    fn test_force_push_to_protected_branch() {
+        let guard = guard();
+        assert_eq!(rules(&guard, "git push --force origin main"), vec![GuardRule::ForcePushProtected]);
+        assert_eq!(rules(&guard, "git push origin +release/1.2"), vec![GuardRule::ForcePushProtected]);
+        assert_eq!(rules(&guard, "git -C repo push -f origin HEAD:master"), vec![GuardRule::ForcePushProtected]);
+        // No refspec: the current branch (main) is pushed
+        assert_eq!(rules(&guard, "git push -f"), vec![GuardRule::ForcePushProtected]);
+        assert!(rules(&guard, "git push --force origin feature/x").is_empty());
+        assert!(rules(&guard, "git push origin main").is_empty());
+    }
+
+    #[test]
+    fn test_kubectl_delete_in_production() {
+        let guard = guard();
+        assert_eq!(
+            rules(&guard, "kubectl --context prod-eu delete pod web-1"),
+            vec![GuardRule::KubectlDeleteProduction]
+        );
+        assert_eq!(
+            rules(&guard, "kubectl delete deploy api -n Production"),
+            vec![GuardRule::KubectlDeleteProduction]
+        );
+        // Current context is staging
+        assert!(rules(&guard, "kubectl delete pod web-1").is_empty());
+        assert!(rules(&guard, "kubectl --context=prod get pods").is_empty());
+        // Flag values before the subcommand are not the subcommand
+        assert_eq!(
+            rules(&guard, "kubectl -n prod delete ns x"),
+            vec![GuardRule::KubectlDeleteProduction]
+        );
+        assert_eq!(
+            rules(&guard, "kubectl --kubeconfig ~/.kube/prod --context prod delete pod x"),
+            vec![GuardRule::KubectlDeleteProduction]
+        );
+        assert!(rules(&guard, "kubectl --kubeconfig delete get pods").is_empty());
+
+        let prod = CommandGuard::default().with_environment(Box::new(FakeEnvironment {
+            branch: None,
+            context: Some("gke_prod_cluster"),
+        }));
+        assert_eq!(rules(&prod, "kubectl delete pod web-1"), vec![GuardRule::KubectlDeleteProduction]);
+    }
+
+    #[test]
+    fn test_fish_syntax() {
+        let fish = guard().with_dialect(ShellDialect::Fish);
+        assert_eq!(rules(&fish, "echo (rm -rf ~/)"), vec![GuardRule::RecursiveDelete]);
+        assert_eq!(rules(&fish, "cd /tmp; and rm -rf /"), vec![GuardRule::RecursiveDelete]);
+        assert!(rules(&fish, "echo \"(rm -rf /)\"").is_empty());
+        // In bash `and` is just a command name
+        assert!(rules(&guard(), "cd /tmp; and rm -rf /").is_empty());
+    }
+
+    #[test]
+    fn test_unbalanced_quotes() {
+        let guard = guard();
+        let verdict = guard.check("echo \"hello");
+        assert_eq!(verdict.findings[0].rule, GuardRule::UnbalancedQuotes);
+        assert_eq!(verdict.findings[0].span.start, 5);
+        assert_eq!(verdict.decide(false), GuardDecision::Run {
+            warnings: vec!["Unbalanced quote at position 5".to_string()],
+        });
+        assert!(rules(&guard, "echo \"it's fine\"").is_empty());
+    }
+
+    #[test]
+    fn test_policies_and_decisions() {
+        let mut config = GuardConfig::default();
+        let guard = CommandGuard::new(config.clone()).with_environment(Box::new(FakeEnvironment {
+            branch: None,
+            context: None,
+        }));
+
+        let verdict = guard.check("rm -rf /");
+        assert!(matches!(verdict.decide(true), GuardDecision::Block { .. }));
+
+        let verdict = guard.check("git push --force origin main");
+        assert!(matches!(verdict.decide(false), GuardDecision::Confirm { .. }));
+        assert!(matches!(verdict.decide(true), GuardDecision::Run { .. }));
+
+        config.set_policy(GuardRule::RecursiveDelete, GuardPolicy::Allow);
+        config.set_policy(GuardRule::ForcePushProtected, GuardPolicy::Block);
+        let guard = CommandGuard::new(config);
+        assert!(guard.check("rm -rf /").findings.is_empty());
+        assert_eq!(guard.check("git push -f origin master").policy(), GuardPolicy::Block);
+    }
+
+    #[test]
+    fn test_disabled_guard() {
+        let guard = CommandGuard::new(GuardConfig {
+            enabled: false,
+            ..GuardConfig::default()
+        });
+        assert_eq!(guard.check("rm -rf /").policy(), GuardPolicy::Allow);
+    }
+
+    #[test]
+    fn test_config_deserializes_partial_json() {
+        let config: GuardConfig =
+            serde_json::from_str(r#"{"policies": {"unbalanced_quotes": "block"}}"#).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.policy(GuardRule::UnbalancedQuotes), GuardPolicy::Block);
+        assert_eq!(config.policy(GuardRule::RawDeviceWrite), GuardPolicy::Confirm);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("release/*", "release/1.2"));
+        assert!(glob_match("*prod*", "gke_prod_eu"));
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("release/*", "feature/release"));
+        assert!(!glob_match("a*b*c", "acb"));
+    }
+
+    #[test]
+    fn test_system_environment_reads_the_branch_of_its_directory() {
+        if which::which("git").is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["checkout", "-q", "-b", "release/1.0"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "initial"]);
+
+        let environment = SystemGuardEnvironment::new(dir.path());
+        assert_eq!(environment.current_git_branch().as_deref(), Some("release/1.0"));
+    }
+}
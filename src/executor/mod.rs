@@ -2,8 +2,10 @@ pub mod command_executor;
 pub mod command_corrections;
 pub mod shell_integration;
 pub mod advanced_commands;
+pub mod command_guard;
 
 pub use command_executor::*;
 pub use command_corrections::*;
+pub use command_guard::{CommandGuard, GuardConfig, GuardDecision, GuardPolicy, GuardRule, GuardVerdict};
 pub use shell_integration::{ShellConfig, ShellIntegration, ShellInfo, EnhancedTextInput, SyntaxTree, SyntaxToken, SyntaxTokenType, StreamEvent};
 pub use shell_integration::ExecutionResult as ShellExecutionResult;
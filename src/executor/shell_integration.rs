@@ -15,6 +15,8 @@ use tokio::process::{Child, Command};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::syntax_tree::{parse_shell, HighlightKind, ShellDialect};
+
 #[derive(Debug, Clone)]
 pub struct ShellConfig {
     pub shell_path: PathBuf,
@@ -281,7 +283,7 @@ impl EnhancedTextInput {
     }
 }
 
-/// Simplified syntax tree for shell commands
+/// Highlighting tokens for the input buffer, backed by the shell parser
 #[derive(Debug, Clone)]
 pub struct SyntaxTree {
     pub tokens: Vec<SyntaxToken>,
@@ -289,26 +291,38 @@ pub struct SyntaxTree {
 
 impl SyntaxTree {
     pub fn parse(content: &str) -> Self {
-        let tokens = content
-            .split_whitespace()
-            .enumerate()
-            .map(|(i, token)| {
-                let token_type = if i == 0 {
-                    SyntaxTokenType::Command
-                } else if token.starts_with('-') {
-                    SyntaxTokenType::Flag
-                } else {
-                    SyntaxTokenType::Argument
-                };
-                
-                SyntaxToken {
-                    text: token.to_string(),
-                    token_type,
-                    start: 0, // Simplified - would need proper position tracking
-                    end: token.len(),
+        let tree = parse_shell(content, ShellDialect::default());
+        let mut tokens: Vec<SyntaxToken> = Vec::new();
+
+        for highlight in tree.highlights() {
+            let token_type = match highlight.kind {
+                HighlightKind::Command => SyntaxTokenType::Command,
+                HighlightKind::Flag => SyntaxTokenType::Flag,
+                HighlightKind::String | HighlightKind::Heredoc => SyntaxTokenType::String,
+                HighlightKind::Variable | HighlightKind::Substitution => SyntaxTokenType::Variable,
+                HighlightKind::Keyword
+                | HighlightKind::Operator
+                | HighlightKind::Redirect => SyntaxTokenType::Operator,
+                _ => SyntaxTokenType::Argument,
+            };
+
+            // Merge adjacent parts of the same kind, e.g. the quotes and
+            // contents of a string
+            if let Some(last) = tokens.last_mut() {
+                if last.end == highlight.span.start && last.token_type == token_type {
+                    last.end = highlight.span.end;
+                    last.text = content[last.start..last.end].to_string();
+                    continue;
                 }
-            })
-            .collect();
+            }
+
+            tokens.push(SyntaxToken {
+                text: content[highlight.span.clone()].to_string(),
+                token_type,
+                start: highlight.span.start,
+                end: highlight.span.end,
+            });
+        }
 
         Self { tokens }
     }
@@ -322,7 +336,7 @@ pub struct SyntaxToken {
     pub end: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SyntaxTokenType {
     Command,
     Flag,
@@ -0,0 +1,149 @@
+//! Resolves key presses to action names through a [`Keymap`].
+//!
+//! The dispatcher remembers the keys of an unfinished chord. A key that
+//! neither finishes nor continues it cancels the chord and is swallowed, so
+//! `ctrl-k x` never types an `x` after a mistyped chord. Modifier keys
+//! pressed on their own leave the chord alone, and a letter typed with shift
+//! finishes a chord bound to the plain letter unless the shifted one is bound.
+
+use iced::keyboard::{Key, Modifiers};
+
+use crate::input::keymap::{KeyContext, KeySequence, Keymap, Keystroke, Lookup};
+
+/// The outcome of one key press
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dispatch {
+    /// Run the named action
+    Action(String),
+    /// Part of a chord; the keys so far
+    Pending(KeySequence),
+    /// The keys started a chord that no binding finishes
+    Cancelled(KeySequence),
+    /// No binding uses this key
+    Unhandled,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyDispatcher {
+    keymap: Keymap,
+    pending: Vec<Keystroke>,
+}
+
+impl KeyDispatcher {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Swap in a reloaded keymap, dropping any unfinished chord
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.pending.clear();
+    }
+
+    /// Keys of the chord in progress
+    pub fn pending(&self) -> Option<KeySequence> {
+        (!self.pending.is_empty()).then(|| KeySequence(self.pending.clone()))
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Resolve a key press in `contexts`, most specific first (see
+    /// [`KeyContext::chain`])
+    pub fn dispatch(&mut self, key: &Key, modifiers: Modifiers, contexts: &[KeyContext]) -> Dispatch {
+        let stroke = Keystroke::new(key.clone(), modifiers);
+        if stroke.is_modifier_only() {
+            return Dispatch::Unhandled;
+        }
+        let mut strokes = std::mem::take(&mut self.pending);
+        strokes.push(stroke);
+
+        let mut lookup = self.keymap.lookup(&strokes, contexts);
+        if matches!(lookup, Lookup::Unbound) && strokes.len() > 1 {
+            if let Some(unshifted) = strokes.last().and_then(Keystroke::without_shift) {
+                let mut unshifted_strokes = strokes[..strokes.len() - 1].to_vec();
+                unshifted_strokes.push(unshifted);
+                if let found @ Lookup::Matched(_) = self.keymap.lookup(&unshifted_strokes, contexts) {
+                    lookup = found;
+                }
+            }
+        }
+
+        match lookup {
+            Lookup::Matched(binding) => Dispatch::Action(binding.action.clone()),
+            Lookup::Prefix => {
+                self.pending = strokes.clone();
+                Dispatch::Pending(KeySequence(strokes))
+            }
+            Lookup::Unbound if strokes.len() > 1 => Dispatch::Cancelled(KeySequence(strokes)),
+            Lookup::Unbound => Dispatch::Unhandled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::{BindingLayer, BindingSource};
+    use iced::keyboard::key::Named;
+    use std::path::PathBuf;
+
+    fn dispatcher() -> KeyDispatcher {
+        let overrides = BindingLayer::parse(
+            "\"workspace:show_keybinding_settings\": ctrl-k ctrl-s\n\"workspace:toggle_sync_mode\": ctrl-k z\n",
+            BindingSource::User(PathBuf::from("keybindings.yaml")),
+        )
+        .unwrap();
+        KeyDispatcher::new(Keymap::builtin().with_layer(overrides))
+    }
+
+    fn press(dispatcher: &mut KeyDispatcher, c: &str, modifiers: Modifiers) -> Dispatch {
+        dispatcher.dispatch(&Key::Character(c.into()), modifiers, KeyContext::Terminal.chain())
+    }
+
+    #[test]
+    fn test_chord_dispatch() {
+        let mut dispatcher = dispatcher();
+        assert!(matches!(press(&mut dispatcher, "k", Modifiers::CTRL), Dispatch::Pending(_)));
+        // Holding down ctrl on its own does not break the chord
+        let ctrl = dispatcher.dispatch(&Key::Named(Named::Control), Modifiers::CTRL, KeyContext::Terminal.chain());
+        assert_eq!(ctrl, Dispatch::Unhandled);
+        assert_eq!(dispatcher.pending().unwrap().to_string(), "ctrl-k");
+        assert_eq!(
+            press(&mut dispatcher, "s", Modifiers::CTRL),
+            Dispatch::Action("workspace:show_keybinding_settings".to_string())
+        );
+        assert!(dispatcher.pending().is_none());
+
+        // Uppercase with shift finishes the chord bound to the plain letter
+        press(&mut dispatcher, "k", Modifiers::CTRL);
+        assert_eq!(
+            press(&mut dispatcher, "Z", Modifiers::SHIFT),
+            Dispatch::Action("workspace:toggle_sync_mode".to_string())
+        );
+        assert!(dispatcher.pending().is_none());
+    }
+
+    #[test]
+    fn test_broken_chord_is_cancelled() {
+        let mut dispatcher = dispatcher();
+        press(&mut dispatcher, "k", Modifiers::CTRL);
+        match press(&mut dispatcher, "x", Modifiers::empty()) {
+            Dispatch::Cancelled(keys) => assert_eq!(keys.to_string(), "ctrl-k x"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(press(&mut dispatcher, "x", Modifiers::empty()), Dispatch::Unhandled);
+        assert_eq!(
+            press(&mut dispatcher, "r", Modifiers::CTRL),
+            Dispatch::Action("workspace:show_command_search".to_string())
+        );
+    }
+}
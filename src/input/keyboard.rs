@@ -4,6 +4,7 @@
 //! application functionality.
 
 use crate::model::synchronization::SynchronizationScope;
+use crate::ui::keybinding_editor::KeybindingEditorMessage;
 use crate::Message;
 use iced::keyboard::{Event as KeyboardEvent, Key, Modifiers};
 use iced::Event;
@@ -25,202 +26,117 @@ impl Default for KeyboardShortcuts {
 }
 
 impl KeyboardShortcuts {
-    /// Handle keyboard events and return corresponding messages
-    pub fn handle_event(&self, event: &Event, active_pane_id: Option<Uuid>, pane_manager: &crate::model::pane::PaneManager) -> Option<Message> {
-        if let Event::Keyboard(KeyboardEvent::KeyPressed { key, modifiers, .. }) = event {
-            self.handle_key_press(key, modifiers, active_pane_id, pane_manager)
+    /// Map Right and Alt+Right to autosuggestion acceptance.
+    ///
+    /// The focused command input captures arrow keys, so these never reach
+    /// the key dispatcher; [`GhostText`](crate::ui::ghost_text::GhostText)
+    /// checks them before the input does, and only accepts with the cursor
+    /// at the end of the input.
+    pub fn autosuggestion_key(key: &Key, modifiers: Modifiers) -> Option<Message> {
+        if *key != Key::Named(iced::keyboard::key::Named::ArrowRight) {
+            return None;
+        }
+        if modifiers.is_empty() {
+            Some(Message::AcceptAutosuggestion)
+        } else if modifiers == Modifiers::ALT {
+            Some(Message::AcceptAutosuggestionWord)
         } else {
             None
         }
     }
 
-    /// Handle key press events with modifiers
-    fn handle_key_press(
+    /// Whether a key press the command input captured still reaches the key
+    /// dispatcher: Ctrl/Cmd shortcuts the input has no use for. Ctrl/Cmd
+    /// with C, X, V or A stays with the input.
+    pub fn passes_captured(key: &Key, modifiers: Modifiers) -> bool {
+        match key {
+            Key::Character(c) => modifiers.command() && !["c", "x", "v", "a"].iter().any(|used| c.eq_ignore_ascii_case(used)),
+            _ => false,
+        }
+    }
+
+    /// Events for the key dispatcher. A focused text input captures every
+    /// key press, so presses it captured are still passed on when
+    /// [`Self::passes_captured`] says so.
+    pub fn key_event(event: Event, status: iced::event::Status) -> Option<Message> {
+        let passes_through = match &event {
+            Event::Keyboard(KeyboardEvent::KeyPressed { key, modifiers, .. }) => Self::passes_captured(key, *modifiers),
+            _ => false,
+        };
+        (status == iced::event::Status::Ignored || passes_through).then_some(Message::EventOccurred(event))
+    }
+
+    /// The message for an action resolved by the
+    /// [`KeyDispatcher`](crate::input::KeyDispatcher). Resizing needs the
+    /// active pane and its sibling; actions the terminal does not implement
+    /// yet give `None`.
+    pub fn action_message(
         &self,
-        key: &Key,
-        modifiers: &Modifiers,
+        action: &str,
         active_pane_id: Option<Uuid>,
         pane_manager: &crate::model::pane::PaneManager,
     ) -> Option<Message> {
-        let primary_modifier = if self.use_cmd_on_macos {
-            modifiers.command()
-        } else {
-            modifiers.control()
+        use crate::app::terminal::Direction;
+        use crate::model::pane::SplitDirection;
+
+        let resize = |direction: SplitDirection, delta: i16| {
+            active_pane_id.and_then(|id| {
+                pane_manager
+                    .find_sibling_pane_id(id, direction)
+                    .map(|sibling_id| Message::PaneResize(id, sibling_id, delta))
+            })
         };
 
-        match key {
-            // Pane Navigation shortcuts (Task 1.2 requirements)
-            Key::Character(c) if c.as_str() == "d" && primary_modifier && modifiers.shift() => {
-                // Ctrl+Shift+D - Split horizontally
-                Some(Message::PaneSplitHorizontal)
-            }
-
-            Key::Character(c) if c.as_str() == "D" && primary_modifier && modifiers.shift() => {
-                // Ctrl+Shift+Shift+D (effectively Ctrl+Shift+D with caps) - Split vertically
-                Some(Message::PaneSplitVertical)
-            }
-
-            Key::Character(c) if c.as_str() == "w" && primary_modifier => {
-                // Ctrl+W - Close current pane
-                Some(Message::PaneClose)
-            }
-
-            Key::Named(iced::keyboard::key::Named::Tab)
-                if primary_modifier && !modifiers.shift() =>
-            {
-                // Ctrl+Tab - Focus next pane
-                Some(Message::PaneFocusNext)
-            }
-
-            Key::Named(iced::keyboard::key::Named::Tab)
-                if primary_modifier && modifiers.shift() =>
-            {
-                // Ctrl+Shift+Tab - Focus previous pane
-                Some(Message::PaneFocusPrevious)
-            }
-
-            Key::Named(iced::keyboard::key::Named::ArrowUp) if modifiers.alt() => {
-                // Alt+Up - Focus pane above
-                Some(Message::PaneFocusDirection(
-                    crate::app::terminal::Direction::Up,
-                ))
-            }
-
-            Key::Named(iced::keyboard::key::Named::ArrowDown) if modifiers.alt() => {
-                // Alt+Down - Focus pane below
-                Some(Message::PaneFocusDirection(
-                    crate::app::terminal::Direction::Down,
-                ))
-            }
-
-            Key::Named(iced::keyboard::key::Named::ArrowLeft) if modifiers.alt() => {
-                // Alt+Left - Focus pane to the left
-                Some(Message::PaneFocusDirection(
-                    crate::app::terminal::Direction::Left,
-                ))
-            }
-
-            Key::Named(iced::keyboard::key::Named::ArrowRight) if modifiers.alt() => {
-                // Alt+Right - Focus pane to the right
-                Some(Message::PaneFocusDirection(
-                    crate::app::terminal::Direction::Right,
-                ))
-            }
-
-            // Pane Resizing shortcuts
-            Key::Named(iced::keyboard::key::Named::ArrowUp)
-                if modifiers.alt() && modifiers.shift() =>
-            {
-                active_pane_id.and_then(|id| {
-                    pane_manager.find_sibling_pane_id(id, crate::model::pane::SplitDirection::Vertical)
-                        .map(|sibling_id| Message::PaneResize(id, sibling_id, -10))
-                })
-            }
-            Key::Named(iced::keyboard::key::Named::ArrowDown)
-                if modifiers.alt() && modifiers.shift() =>
-            {
-                active_pane_id.and_then(|id| {
-                    pane_manager.find_sibling_pane_id(id, crate::model::pane::SplitDirection::Vertical)
-                        .map(|sibling_id| Message::PaneResize(id, sibling_id, 10))
-                })
-            }
-            Key::Named(iced::keyboard::key::Named::ArrowLeft)
-                if modifiers.alt() && modifiers.shift() =>
-            {
-                active_pane_id.and_then(|id| {
-                    pane_manager.find_sibling_pane_id(id, crate::model::pane::SplitDirection::Horizontal)
-                        .map(|sibling_id| Message::PaneResize(id, sibling_id, -10))
-                })
-            }
-            Key::Named(iced::keyboard::key::Named::ArrowRight)
-                if modifiers.alt() && modifiers.shift() =>
-            {
-                active_pane_id.and_then(|id| {
-                    pane_manager.find_sibling_pane_id(id, crate::model::pane::SplitDirection::Horizontal)
-                        .map(|sibling_id| Message::PaneResize(id, sibling_id, 10))
-                })
-            }
-
-            // Command Palette shortcuts
-            Key::Character(c) if c.as_str() == "p" && primary_modifier && modifiers.shift() => {
-                // Ctrl+Shift+P - Toggle command palette
-                Some(Message::CommandPaletteToggle)
-            }
-
-            // Existing shortcuts continue below
-            // Synchronization shortcuts
-            Key::Character(c) if c.as_str() == "i" && primary_modifier && modifiers.alt() => {
-                // Ctrl+Alt+I (Cmd+Opt+I on macOS) - Toggle synchronization
-                Some(Message::ToggleSynchronizationMode)
-            }
-
-            Key::Character(c) if c.as_str() == "t" && primary_modifier && modifiers.alt() => {
-                // Ctrl+Alt+T (Cmd+Opt+T on macOS) - Enable current tab sync
-                Some(Message::StartSynchronization(
-                    SynchronizationScope::CurrentTab,
-                ))
-            }
-
-            Key::Character(c) if c.as_str() == "a" && primary_modifier && modifiers.alt() => {
-                // Ctrl+Alt+A (Cmd+Opt+A on macOS) - Enable all tabs sync
-                Some(Message::StartSynchronization(SynchronizationScope::AllTabs))
-            }
-
-            Key::Character(c) if c.as_str() == "s" && primary_modifier && modifiers.alt() => {
-                // Ctrl+Alt+S (Cmd+Opt+S on macOS) - Stop synchronization
-                Some(Message::StopSynchronization)
-            }
-
-            Key::Character(c) if c.as_str() == "p" && primary_modifier && modifiers.alt() => {
-                // Ctrl+Alt+P (Cmd+Opt+P on macOS) - Pause/Resume synchronization
-                Some(Message::ToggleSynchronizationPause)
-            }
-
-            // Existing shortcuts
-            Key::Character(c) if c.as_str() == "r" && modifiers.control() => {
-                // Ctrl+R - Toggle command search
-                Some(Message::ToggleCommandSearch)
-            }
-
-            Key::Named(iced::keyboard::key::Named::Enter) => {
-                // Enter - Execute command (only if not in command search mode)
-                Some(Message::ExecuteCommand)
-            }
-
-            Key::Named(iced::keyboard::key::Named::Escape) => {
-                // Escape - Close command search or cancel synchronization
-                Some(Message::HandleEscape)
-            }
-
-            // Function keys for quick sync scope switching
-            Key::Named(iced::keyboard::key::Named::F1) => {
-                // F1 - Toggle to current tab sync
-                Some(Message::StartSynchronization(
-                    SynchronizationScope::CurrentTab,
-                ))
-            }
-
-            Key::Named(iced::keyboard::key::Named::F2) => {
-                // F2 - Toggle to all tabs sync
-                Some(Message::StartSynchronization(SynchronizationScope::AllTabs))
-            }
-
-            Key::Named(iced::keyboard::key::Named::F3) => {
-                // F3 - Stop synchronization
-                Some(Message::StopSynchronization)
-            }
-
-            // Navigation keys for command search
-            Key::Named(iced::keyboard::key::Named::ArrowUp) if !modifiers.is_empty() => {
-                Some(Message::CommandSearchNavigateUp)
+        let message = match action {
+            // Panes
+            "pane_group:add_right" | "pane_group:add_horizontal" => Message::PaneSplitHorizontal,
+            "pane_group:add_down" | "pane_group:add_vertical" => Message::PaneSplitVertical,
+            "pane_group:close" => Message::PaneClose,
+            "pane_group:navigate_next" => Message::PaneFocusNext,
+            "pane_group:navigate_prev" => Message::PaneFocusPrevious,
+            "pane_group:navigate_up" => Message::PaneFocusDirection(Direction::Up),
+            "pane_group:navigate_down" => Message::PaneFocusDirection(Direction::Down),
+            "pane_group:navigate_left" => Message::PaneFocusDirection(Direction::Left),
+            "pane_group:navigate_right" => Message::PaneFocusDirection(Direction::Right),
+            "pane_group:resize_up" => return resize(SplitDirection::Vertical, -10),
+            "pane_group:resize_down" => return resize(SplitDirection::Vertical, 10),
+            "pane_group:resize_left" => return resize(SplitDirection::Horizontal, -10),
+            "pane_group:resize_right" => return resize(SplitDirection::Horizontal, 10),
+
+            // Synchronization
+            "workspace:toggle_sync_mode" => Message::ToggleSynchronizationMode,
+            "workspace:sync_current_tab" => Message::StartSynchronization(SynchronizationScope::CurrentTab),
+            "workspace:sync_all_tabs" => Message::StartSynchronization(SynchronizationScope::AllTabs),
+            "workspace:stop_sync" => Message::StopSynchronization,
+            "workspace:toggle_sync_pause" => Message::ToggleSynchronizationPause,
+
+            // Workspace
+            "workspace:toggle_command_palette" => Message::CommandPaletteToggle,
+            "workspace:show_command_search" | "input:search_command_history" => Message::ToggleCommandSearch,
+            "workspace:show_settings_modal" => Message::InputChanged("toggle_settings".to_string()),
+            "workspace:show_keybinding_settings" => Message::KeybindingEditor(KeybindingEditorMessage::Open),
+            "editor_view:clear_buffer" => Message::InputChanged(String::new()),
+            "terminal:execute_command" => Message::ExecuteCommand,
+            "terminal:escape" | "search:close" => Message::HandleEscape,
+            "terminal:run_last_correction" => Message::RunLastCorrection,
+
+            // Command palette
+            "palette:select_previous" => Message::CommandPaletteNavigateUp,
+            "palette:select_next" => Message::CommandPaletteNavigateDown,
+            "palette:execute_selected" => Message::CommandPaletteExecuteSelected,
+            "palette:close" => Message::CommandPaletteHide,
+
+            // Command search
+            "search:select_previous" => Message::CommandSearchNavigateUp,
+            "search:select_next" => Message::CommandSearchNavigateDown,
+            "search:execute_selected" => Message::CommandSearchExecuteSelected,
+
+            _ => {
+                tracing::debug!("Unhandled action: {}", action);
+                return None;
             }
-
-            Key::Named(iced::keyboard::key::Named::ArrowDown) if !modifiers.is_empty() => {
-                Some(Message::CommandSearchNavigateDown)
-            }
-
-            _ => None,
-        }
+        };
+        Some(message)
     }
 
     /// Get help text for keyboard shortcuts
@@ -252,6 +168,9 @@ impl KeyboardShortcuts {
             \n\
             Other Shortcuts:\n\
             • Ctrl+R - Toggle command search\n\
+            • Right - Accept autosuggestion\n\
+            • {alt}+Right - Accept next word of autosuggestion\n\
+            • {modifier}+Shift+F - Run the suggested fix for the last failed command\n\
             • Enter - Execute command\n\
             • Escape - Close panels/Cancel"
         )
@@ -338,6 +257,59 @@ mod tests {
         assert!(!shortcuts.is_sync_shortcut(&Key::Character("r".into()), &Modifiers::CTRL));
     }
 
+    #[test]
+    fn test_autosuggestion_key_mapping() {
+        let right = Key::Named(iced::keyboard::key::Named::ArrowRight);
+
+        assert!(matches!(
+            KeyboardShortcuts::autosuggestion_key(&right, Modifiers::empty()),
+            Some(Message::AcceptAutosuggestion)
+        ));
+        assert!(matches!(
+            KeyboardShortcuts::autosuggestion_key(&right, Modifiers::ALT),
+            Some(Message::AcceptAutosuggestionWord)
+        ));
+        assert!(KeyboardShortcuts::autosuggestion_key(&right, Modifiers::SHIFT).is_none());
+        assert!(KeyboardShortcuts::autosuggestion_key(&Key::Character("l".into()), Modifiers::empty()).is_none());
+    }
+
+    #[test]
+    fn test_key_event_passes_shortcuts_through_a_focused_input() {
+        use iced::event::Status;
+        let key = |c: &str, modifiers| {
+            Event::Keyboard(KeyboardEvent::KeyPressed {
+                key: Key::Character(c.into()),
+                location: iced::keyboard::Location::Standard,
+                modifiers,
+                text: None,
+            })
+        };
+        let fix = Modifiers::COMMAND | Modifiers::SHIFT;
+
+        for status in [Status::Captured, Status::Ignored] {
+            assert!(KeyboardShortcuts::key_event(key("F", fix), status).is_some());
+        }
+        assert!(KeyboardShortcuts::key_event(key("f", Modifiers::empty()), Status::Ignored).is_some());
+        assert!(KeyboardShortcuts::key_event(key("f", Modifiers::empty()), Status::Captured).is_none());
+        assert!(KeyboardShortcuts::key_event(key("v", Modifiers::COMMAND), Status::Captured).is_none());
+    }
+
+    #[test]
+    fn test_correction_is_a_terminal_action() {
+        use crate::input::{Dispatch, KeyContext, KeyDispatcher, Keymap};
+        let mut dispatcher = KeyDispatcher::new(Keymap::builtin());
+        assert_eq!(
+            dispatcher.dispatch(&Key::Character("F".into()), Modifiers::COMMAND | Modifiers::SHIFT, KeyContext::Terminal.chain()),
+            Dispatch::Action("terminal:run_last_correction".to_string())
+        );
+        let message = KeyboardShortcuts::default().action_message(
+            "terminal:run_last_correction",
+            None,
+            &crate::model::pane::PaneManager::default(),
+        );
+        assert!(matches!(message, Some(Message::RunLastCorrection)));
+    }
+
     #[test]
     fn test_help_text_generation() {
         let shortcuts = KeyboardShortcuts::default();
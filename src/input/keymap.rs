@@ -0,0 +1,780 @@
+//! Key bindings by context, with chords and layered sources.
+//!
+//! A binding maps a [`KeySequence`] of one or more keystrokes (`ctrl-k
+//! ctrl-s` is a two-key chord) to an action name in a [`KeyContext`].
+//! Bindings come in layers: the built-in defaults, then a keyset from
+//! `keysets/*.yaml`, then the user's overrides. A layer that mentions an
+//! action replaces every binding that action had in the layers below it, so
+//! an override file only needs the actions it changes. Contexts fall back
+//! along [`KeyContext::chain`], which lets the palette or vim normal mode
+//! shadow terminal-wide shortcuts without conflicting with them.
+//!
+//! The YAML format is described in `keysets/FORMAT.md`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::editor::keybindings::DEFAULT_EDITOR_BINDINGS;
+
+/// Terminal-wide defaults, used until a keyset or override rebinds them.
+/// `cmd` is Command on macOS and Control elsewhere.
+pub const DEFAULT_TERMINAL_BINDINGS: &[(&str, &str)] = &[
+    ("pane_group:add_right", "cmd-shift-D"),
+    ("pane_group:close", "cmd-w"),
+    ("pane_group:navigate_next", "cmd-tab"),
+    ("pane_group:navigate_prev", "cmd-shift-tab"),
+    ("pane_group:navigate_up", "alt-up"),
+    ("pane_group:navigate_down", "alt-down"),
+    ("pane_group:navigate_left", "alt-left"),
+    ("pane_group:navigate_right", "alt-right"),
+    ("pane_group:resize_up", "alt-shift-up"),
+    ("pane_group:resize_down", "alt-shift-down"),
+    ("pane_group:resize_left", "alt-shift-left"),
+    ("pane_group:resize_right", "alt-shift-right"),
+    ("workspace:toggle_command_palette", "cmd-shift-P"),
+    ("workspace:toggle_sync_mode", "cmd-alt-i"),
+    ("workspace:sync_current_tab", "cmd-alt-t"),
+    ("workspace:sync_current_tab", "f1"),
+    ("workspace:sync_all_tabs", "cmd-alt-a"),
+    ("workspace:sync_all_tabs", "f2"),
+    ("workspace:stop_sync", "cmd-alt-s"),
+    ("workspace:stop_sync", "f3"),
+    ("workspace:toggle_sync_pause", "cmd-alt-p"),
+    ("workspace:show_command_search", "ctrl-r"),
+    ("workspace:show_keybinding_settings", "ctrl-cmd-k"),
+    ("terminal:execute_command", "enter"),
+    ("terminal:escape", "escape"),
+    ("terminal:run_last_correction", "cmd-shift-F"),
+    ("palette:select_previous", "up"),
+    ("palette:select_next", "down"),
+    ("palette:execute_selected", "enter"),
+    ("palette:close", "escape"),
+    ("search:select_previous", "up"),
+    ("search:select_next", "down"),
+    ("search:execute_selected", "enter"),
+    ("search:close", "escape"),
+];
+
+const NAMED_KEYS: &[(&str, Named)] = &[
+    ("up", Named::ArrowUp),
+    ("down", Named::ArrowDown),
+    ("left", Named::ArrowLeft),
+    ("right", Named::ArrowRight),
+    ("home", Named::Home),
+    ("end", Named::End),
+    ("pageup", Named::PageUp),
+    ("pagedown", Named::PageDown),
+    ("backspace", Named::Backspace),
+    ("enter", Named::Enter),
+    ("insert", Named::Insert),
+    ("delete", Named::Delete),
+    ("escape", Named::Escape),
+    ("tab", Named::Tab),
+    ("space", Named::Space),
+    ("f1", Named::F1),
+    ("f2", Named::F2),
+    ("f3", Named::F3),
+    ("f4", Named::F4),
+    ("f5", Named::F5),
+    ("f6", Named::F6),
+    ("f7", Named::F7),
+    ("f8", Named::F8),
+    ("f9", Named::F9),
+    ("f10", Named::F10),
+    ("f11", Named::F11),
+    ("f12", Named::F12),
+    ("f13", Named::F13),
+    ("f14", Named::F14),
+    ("f15", Named::F15),
+    ("f16", Named::F16),
+    ("f17", Named::F17),
+    ("f18", Named::F18),
+    ("f19", Named::F19),
+    ("f20", Named::F20),
+];
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum KeymapError {
+    #[error("empty key binding")]
+    EmptyBinding,
+    #[error("unknown modifier '{modifier}' in '{binding}'")]
+    UnknownModifier { binding: String, modifier: String },
+    #[error("unknown context '{0}'")]
+    UnknownContext(String),
+    #[error("invalid binding for '{action}': {message}")]
+    InvalidEntry { action: String, message: String },
+    #[error("cannot read {path}: {message}")]
+    Read { path: PathBuf, message: String },
+    #[error("cannot parse {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+}
+
+/// One key with its modifiers. Character keys are stored lowercase; the
+/// shift modifier says whether it was held.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Keystroke {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl Keystroke {
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        let key = match key {
+            Key::Character(c) => Key::Character(c.to_lowercase().into()),
+            key => key,
+        };
+        Self { key, modifiers }
+    }
+
+    /// Parse `cmd-shift-A`, `alt-f4` or `ctrl--`. `meta` is Alt and `cmd`
+    /// the platform's command key; HTML entities such as `&gt;` are decoded.
+    pub fn parse(binding: &str) -> Result<Self, KeymapError> {
+        let binding = binding.trim().replace("&gt;", ">").replace("&lt;", "<").replace("&amp;", "&");
+        let (modifier_part, key_part) = match binding.strip_suffix("--") {
+            Some(modifiers) => (modifiers, "-"),
+            None if binding == "-" => ("", "-"),
+            None => binding.rsplit_once('-').unwrap_or(("", binding.as_str())),
+        };
+        if key_part.is_empty() {
+            return Err(KeymapError::EmptyBinding);
+        }
+
+        let mut modifiers = Modifiers::empty();
+        for modifier in modifier_part.split('-').filter(|part| !part.is_empty()) {
+            modifiers |= match modifier {
+                "ctrl" => Modifiers::CTRL,
+                "cmd" => Modifiers::COMMAND,
+                "alt" | "meta" => Modifiers::ALT,
+                "shift" => Modifiers::SHIFT,
+                "super" | "win" => Modifiers::LOGO,
+                _ => {
+                    return Err(KeymapError::UnknownModifier {
+                        binding: binding.clone(),
+                        modifier: modifier.to_string(),
+                    })
+                }
+            };
+        }
+
+        let key = match key_part {
+            "numpadenter" => Key::Named(Named::Enter),
+            name => NAMED_KEYS
+                .iter()
+                .find(|(key_name, _)| *key_name == name)
+                .map(|(_, named)| Key::Named(*named))
+                .unwrap_or_else(|| Key::Character(name.into())),
+        };
+        Ok(Self::new(key, modifiers))
+    }
+
+    /// The same character key without shift, if shift was held
+    pub fn without_shift(&self) -> Option<Self> {
+        match &self.key {
+            Key::Character(_) if self.modifiers.shift() => Some(Self {
+                key: self.key.clone(),
+                modifiers: self.modifiers - Modifiers::SHIFT,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Shift, Control, Alt or the logo key pressed on its own
+    pub fn is_modifier_only(&self) -> bool {
+        matches!(
+            self.key,
+            Key::Named(Named::Shift | Named::Control | Named::Alt | Named::Super | Named::Meta)
+        )
+    }
+}
+
+impl fmt::Display for Keystroke {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let logo = if cfg!(target_os = "macos") { "cmd-" } else { "super-" };
+        for (modifier, name) in [
+            (Modifiers::CTRL, "ctrl-"),
+            (Modifiers::LOGO, logo),
+            (Modifiers::ALT, "alt-"),
+            (Modifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match &self.key {
+            // Letters are written uppercase with shift, as in keyset files
+            Key::Character(c) if self.modifiers.shift() => f.write_str(&c.to_uppercase()),
+            Key::Character(c) => f.write_str(c),
+            Key::Named(named) => match NAMED_KEYS.iter().find(|(_, key)| key == named) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", named),
+            },
+            Key::Unidentified => f.write_str("unidentified"),
+        }
+    }
+}
+
+/// Keystrokes pressed one after another
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<Keystroke>);
+
+impl KeySequence {
+    /// Parse space-separated keystrokes such as `ctrl-k ctrl-s`
+    pub fn parse(sequence: &str) -> Result<Self, KeymapError> {
+        let strokes = sequence
+            .split_whitespace()
+            .map(Keystroke::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if strokes.is_empty() {
+            return Err(KeymapError::EmptyBinding);
+        }
+        Ok(Self(strokes))
+    }
+
+    pub fn strokes(&self) -> &[Keystroke] {
+        &self.0
+    }
+
+    pub fn is_chord(&self) -> bool {
+        self.0.len() > 1
+    }
+
+    /// Whether `self` starts with all of `other` and is longer
+    pub fn extends(&self, other: &[Keystroke]) -> bool {
+        self.0.len() > other.len() && self.0.starts_with(other)
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, stroke) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", stroke)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where in the UI a binding applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyContext {
+    Terminal,
+    Editor,
+    Palette,
+    Search,
+    VimNormal,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 5] = [
+        KeyContext::Terminal,
+        KeyContext::Editor,
+        KeyContext::Palette,
+        KeyContext::Search,
+        KeyContext::VimNormal,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyContext::Terminal => "terminal",
+            KeyContext::Editor => "editor",
+            KeyContext::Palette => "palette",
+            KeyContext::Search => "search",
+            KeyContext::VimNormal => "vim_normal",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, KeymapError> {
+        Self::ALL
+            .into_iter()
+            .find(|context| context.name() == name)
+            .ok_or_else(|| KeymapError::UnknownContext(name.to_string()))
+    }
+
+    /// Default context for an action, from its `namespace:` prefix
+    pub fn for_action(action: &str) -> Self {
+        match action.split_once(':').map_or("", |(namespace, _)| namespace) {
+            "editor" | "editor_view" | "input" => KeyContext::Editor,
+            "palette" => KeyContext::Palette,
+            "search" => KeyContext::Search,
+            "vim" => KeyContext::VimNormal,
+            _ => KeyContext::Terminal,
+        }
+    }
+
+    /// This context followed by the ones whose bindings still apply in it
+    pub fn chain(self) -> &'static [KeyContext] {
+        match self {
+            KeyContext::Terminal => &[KeyContext::Terminal],
+            KeyContext::Editor => &[KeyContext::Editor, KeyContext::Terminal],
+            KeyContext::Palette => &[KeyContext::Palette, KeyContext::Terminal],
+            KeyContext::Search => &[KeyContext::Search, KeyContext::Terminal],
+            KeyContext::VimNormal => &[KeyContext::VimNormal, KeyContext::Editor, KeyContext::Terminal],
+        }
+    }
+}
+
+impl fmt::Display for KeyContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where a binding was defined, lowest layer first
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BindingSource {
+    Builtin,
+    Keyset(PathBuf),
+    User(PathBuf),
+}
+
+impl fmt::Display for BindingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingSource::Builtin => f.write_str("built-in"),
+            BindingSource::Keyset(path) => write!(f, "keyset {}", path.display()),
+            BindingSource::User(path) => write!(f, "user overrides {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBinding {
+    pub sequence: KeySequence,
+    pub action: String,
+    pub context: KeyContext,
+    pub source: BindingSource,
+}
+
+/// The bindings from one source. `unbound` lists actions the source
+/// removes without rebinding (`action: null`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingLayer {
+    pub source: BindingSource,
+    pub bindings: Vec<KeyBinding>,
+    pub unbound: Vec<String>,
+    /// Entries that could not be parsed; the rest of the file still loads
+    pub errors: Vec<KeymapError>,
+}
+
+impl BindingLayer {
+    pub fn new(source: BindingSource) -> Self {
+        Self {
+            source,
+            bindings: Vec::new(),
+            unbound: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The built-in terminal, palette, search and editor bindings
+    pub fn builtin() -> Self {
+        let mut layer = Self::new(BindingSource::Builtin);
+        let defaults = DEFAULT_TERMINAL_BINDINGS
+            .iter()
+            .copied()
+            .chain(DEFAULT_EDITOR_BINDINGS.iter().map(|(action, keys, _)| (*action, *keys)));
+        for (action, keys) in defaults {
+            layer.bind(action, keys, None);
+        }
+        layer
+    }
+
+    /// Parse a keyset or override file: a map from action names to a
+    /// binding, a list of bindings, `null`, or `{keys: ..., context: ...}`
+    pub fn parse(source: &str, origin: BindingSource) -> Result<Self, KeymapError> {
+        let path = match &origin {
+            BindingSource::Keyset(path) | BindingSource::User(path) => path.clone(),
+            BindingSource::Builtin => PathBuf::new(),
+        };
+        let entries: Option<serde_yaml::Mapping> = serde_yaml::from_str(source).map_err(|e| KeymapError::Parse {
+            path: path.clone(),
+            message: e.to_string(),
+        })?;
+
+        let mut layer = Self::new(origin);
+        for (action, value) in entries.unwrap_or_default() {
+            let Some(action) = action.as_str() else {
+                layer.errors.push(KeymapError::Parse {
+                    path: path.clone(),
+                    message: format!("action names must be strings, found {:?}", action),
+                });
+                continue;
+            };
+            if value.is_null() {
+                layer.unbound.push(action.to_string());
+                continue;
+            }
+            match serde_yaml::from_value::<EntrySpec>(value) {
+                Ok(EntrySpec::Keys(keys)) => {
+                    for keys in keys.into_vec() {
+                        layer.bind(action, &keys, None);
+                    }
+                }
+                Ok(EntrySpec::Scoped { keys, context }) => {
+                    let context = match context.as_deref().map(KeyContext::parse).transpose() {
+                        Ok(context) => context,
+                        Err(e) => {
+                            layer.errors.push(e);
+                            continue;
+                        }
+                    };
+                    for keys in keys.into_vec() {
+                        layer.bind(action, &keys, context);
+                    }
+                }
+                Err(e) => layer.errors.push(KeymapError::InvalidEntry {
+                    action: action.to_string(),
+                    message: e.to_string(),
+                }),
+            }
+        }
+        Ok(layer)
+    }
+
+    pub fn load(path: &Path, origin: BindingSource) -> Result<Self, KeymapError> {
+        let source = std::fs::read_to_string(path).map_err(|e| KeymapError::Read {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        Self::parse(&source, origin)
+    }
+
+    /// Bind `keys` to `action`; keys that do not parse go to `errors`
+    pub fn bind(&mut self, action: &str, keys: &str, context: Option<KeyContext>) {
+        match KeySequence::parse(keys) {
+            Ok(sequence) => self.bindings.push(KeyBinding {
+                sequence,
+                action: action.to_string(),
+                context: context.unwrap_or_else(|| KeyContext::for_action(action)),
+                source: self.source.clone(),
+            }),
+            Err(e) => self.errors.push(KeymapError::InvalidEntry {
+                action: action.to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    /// Actions this layer sets or removes
+    fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings
+            .iter()
+            .map(|binding| binding.action.as_str())
+            .chain(self.unbound.iter().map(String::as_str))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EntrySpec {
+    Keys(KeysSpec),
+    Scoped { keys: KeysSpec, context: Option<String> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum KeysSpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeysSpec {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            KeysSpec::One(keys) => vec![keys],
+            KeysSpec::Many(keys) => keys,
+        }
+    }
+}
+
+/// What a key sequence resolves to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup<'a> {
+    Matched(&'a KeyBinding),
+    /// The start of at least one chord: wait for the next key
+    Prefix,
+    Unbound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Several actions share the same keys
+    SameKeys,
+    /// A binding fires before a chord that starts with it can finish
+    Prefix,
+}
+
+/// Bindings in one context that get in each other's way. The first binding
+/// is the one that wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub context: KeyContext,
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |binding: &KeyBinding| format!("{} ({})", binding.action, binding.source);
+        let winner = &self.bindings[0];
+        match self.kind {
+            ConflictKind::SameKeys => {
+                let others: Vec<String> = self.bindings[1..].iter().map(describe).collect();
+                write!(
+                    f,
+                    "{}: {} runs {} and hides {}",
+                    self.context,
+                    winner.sequence,
+                    describe(winner),
+                    others.join(", ")
+                )
+            }
+            ConflictKind::Prefix => {
+                let chords: Vec<String> = self.bindings[1..]
+                    .iter()
+                    .map(|binding| format!("{} for {}", binding.sequence, describe(binding)))
+                    .collect();
+                write!(
+                    f,
+                    "{}: {} runs {} and makes unreachable the chord {}",
+                    self.context,
+                    winner.sequence,
+                    describe(winner),
+                    chords.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// The effective bindings of a stack of layers
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    layers: Vec<BindingLayer>,
+    bindings: Vec<KeyBinding>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Built-in defaults only
+    pub fn builtin() -> Self {
+        Self::new().with_layer(BindingLayer::builtin())
+    }
+
+    /// Built-in defaults, then `keyset`, then the user's overrides when the
+    /// file exists. Files that fail to load are reported in [`Self::errors`].
+    pub fn load(keyset: Option<&Path>, overrides: Option<&Path>) -> Self {
+        let mut keymap = Self::builtin();
+        let files = keyset
+            .map(|path| (path, BindingSource::Keyset(path.to_path_buf())))
+            .into_iter()
+            .chain(
+                overrides
+                    .filter(|path| path.exists())
+                    .map(|path| (path, BindingSource::User(path.to_path_buf()))),
+            );
+        for (path, origin) in files {
+            let layer = BindingLayer::load(path, origin.clone()).unwrap_or_else(|e| BindingLayer {
+                errors: vec![e],
+                ..BindingLayer::new(origin)
+            });
+            keymap.push_layer(layer);
+        }
+        keymap
+    }
+
+    /// `~/.warp/keysets/user.yaml`, written by the keybinding editor
+    pub fn user_overrides_path() -> Option<PathBuf> {
+        crate::keyset::KeysetManager::user_keysets_dir().map(|dir| dir.join("user.yaml"))
+    }
+
+    pub fn with_layer(mut self, layer: BindingLayer) -> Self {
+        self.push_layer(layer);
+        self
+    }
+
+    pub fn push_layer(&mut self, layer: BindingLayer) {
+        let replaced: Vec<&str> = layer.actions().collect();
+        self.bindings.retain(|binding| !replaced.contains(&binding.action.as_str()));
+        self.bindings.extend(layer.bindings.iter().cloned());
+        self.layers.push(layer);
+    }
+
+    pub fn layers(&self) -> &[BindingLayer] {
+        &self.layers
+    }
+
+    /// Effective bindings, lowest layer first
+    pub fn bindings(&self) -> &[KeyBinding] {
+        &self.bindings
+    }
+
+    pub fn bindings_for_action(&self, action: &str) -> Vec<&KeyBinding> {
+        self.bindings.iter().filter(|binding| binding.action == action).collect()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &KeymapError> {
+        self.layers.iter().flat_map(|layer| layer.errors.iter())
+    }
+
+    /// Resolve `strokes` in the first context of `contexts` that knows
+    /// them. An exact match wins over a chord prefix in the same context;
+    /// among exact matches the highest layer wins.
+    pub fn lookup(&self, strokes: &[Keystroke], contexts: &[KeyContext]) -> Lookup<'_> {
+        for context in contexts {
+            let in_context = || self.bindings.iter().filter(move |binding| binding.context == *context);
+            if let Some(binding) = in_context().rfind(|binding| binding.sequence.0 == strokes) {
+                return Lookup::Matched(binding);
+            }
+            if in_context().any(|binding| binding.sequence.extends(strokes)) {
+                return Lookup::Prefix;
+            }
+        }
+        Lookup::Unbound
+    }
+
+    /// Bindings that shadow each other within a context
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for context in KeyContext::ALL {
+            let bindings: Vec<&KeyBinding> = self.bindings.iter().filter(|binding| binding.context == context).collect();
+            let mut seen: Vec<&KeySequence> = Vec::new();
+            for binding in &bindings {
+                if seen.contains(&&binding.sequence) {
+                    continue;
+                }
+                seen.push(&binding.sequence);
+
+                // The winner is the last binding, as in `lookup`
+                let mut same: Vec<KeyBinding> = Vec::new();
+                for other in bindings.iter().rev().filter(|other| other.sequence == binding.sequence) {
+                    if !same.iter().any(|kept| kept.action == other.action) {
+                        same.push((*other).clone());
+                    }
+                }
+                if same.len() > 1 {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::SameKeys,
+                        context,
+                        bindings: same.clone(),
+                    });
+                }
+
+                let chords: Vec<KeyBinding> = bindings
+                    .iter()
+                    .filter(|other| other.sequence.extends(binding.sequence.strokes()))
+                    .map(|other| (*other).clone())
+                    .collect();
+                if !chords.is_empty() {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::Prefix,
+                        context,
+                        bindings: std::iter::once(same[0].clone()).chain(chords).collect(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(keys: &str) -> Keystroke {
+        Keystroke::parse(keys).unwrap()
+    }
+
+    fn user_layer(source: &str) -> BindingLayer {
+        BindingLayer::parse(source, BindingSource::User(PathBuf::from("keybindings.yaml"))).unwrap()
+    }
+
+    #[test]
+    fn test_parse_keystrokes() {
+        assert_eq!(stroke("ctrl-shift-E"), Keystroke::new(Key::Character("e".into()), Modifiers::CTRL | Modifiers::SHIFT));
+        assert_eq!(stroke("cmd--"), Keystroke::new(Key::Character("-".into()), Modifiers::COMMAND));
+        assert_eq!(stroke("shift-meta-&gt;"), Keystroke::new(Key::Character(">".into()), Modifiers::SHIFT | Modifiers::ALT));
+        assert_eq!(stroke("alt-up"), Keystroke::new(Key::Named(Named::ArrowUp), Modifiers::ALT));
+        assert_eq!(stroke("ctrl-shift-E").to_string(), "ctrl-shift-E");
+        assert!(matches!(Keystroke::parse("hyper-x"), Err(KeymapError::UnknownModifier { .. })));
+
+        let chord = KeySequence::parse("ctrl-k  ctrl-s").unwrap();
+        assert!(chord.is_chord());
+        assert_eq!(chord.to_string(), "ctrl-k ctrl-s");
+        assert!(chord.extends(&[stroke("ctrl-k")]));
+    }
+
+    #[test]
+    fn test_layers_override_by_action() {
+        let keyset = BindingLayer::parse(
+            "\"workspace:show_command_search\": ctrl-shift-R\n\"editor_view:select_all\": [cmd-a, ctrl-shift-A]\n",
+            BindingSource::Keyset(PathBuf::from("keysets/test.yaml")),
+        )
+        .unwrap();
+        let keymap = Keymap::builtin()
+            .with_layer(keyset)
+            .with_layer(user_layer("\"terminal:escape\": null\n\"editor_view:select_all\": {keys: ctrl-k ctrl-a, context: vim_normal}\n"));
+
+        let search = keymap.bindings_for_action("workspace:show_command_search");
+        assert_eq!(search.len(), 1);
+        assert_eq!(search[0].sequence.to_string(), "ctrl-shift-R");
+        assert!(keymap.bindings_for_action("terminal:escape").is_empty());
+
+        let select_all = keymap.bindings_for_action("editor_view:select_all");
+        assert_eq!(select_all.len(), 1);
+        assert_eq!(select_all[0].context, KeyContext::VimNormal);
+        assert!(matches!(select_all[0].source, BindingSource::User(_)));
+    }
+
+    #[test]
+    fn test_lookup_follows_context_chain() {
+        let keymap = Keymap::builtin().with_layer(user_layer("\"vim:save\": ctrl-k ctrl-s\n"));
+        let enter = [stroke("enter")];
+        match keymap.lookup(&enter, KeyContext::Palette.chain()) {
+            Lookup::Matched(binding) => assert_eq!(binding.action, "palette:execute_selected"),
+            other => panic!("{:?}", other),
+        }
+        match keymap.lookup(&enter, KeyContext::Terminal.chain()) {
+            Lookup::Matched(binding) => assert_eq!(binding.action, "terminal:execute_command"),
+            other => panic!("{:?}", other),
+        }
+
+        let ctrl_k = stroke("ctrl-k");
+        assert_eq!(keymap.lookup(std::slice::from_ref(&ctrl_k), KeyContext::VimNormal.chain()), Lookup::Prefix);
+        assert!(matches!(
+            keymap.lookup(&[ctrl_k.clone(), stroke("ctrl-s")], KeyContext::VimNormal.chain()),
+            Lookup::Matched(binding) if binding.action == "vim:save"
+        ));
+        assert_eq!(keymap.lookup(&[ctrl_k, stroke("ctrl-x")], KeyContext::VimNormal.chain()), Lookup::Unbound);
+    }
+
+    #[test]
+    fn test_conflicts_are_reported() {
+        let keymap = Keymap::new().with_layer(user_layer(
+            "\"terminal:first\": ctrl-k\n\"terminal:second\": ctrl-k\n\"terminal:chord\": ctrl-k ctrl-s\n\"editor:other\": ctrl-k\n",
+        ));
+        let conflicts = keymap.conflicts();
+        assert_eq!(conflicts.len(), 2, "{:?}", conflicts);
+        assert_eq!(conflicts[0].kind, ConflictKind::SameKeys);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "terminal: ctrl-k runs terminal:second (user overrides keybindings.yaml) and hides terminal:first (user overrides keybindings.yaml)"
+        );
+        assert_eq!(conflicts[1].kind, ConflictKind::Prefix);
+        assert!(conflicts[1].to_string().contains("makes unreachable the chord ctrl-k ctrl-s for terminal:chord"));
+
+        let errors = user_layer("\"terminal:bad\": hyper-x\n\"terminal:worse\": {keys: x, context: nowhere}\n").errors;
+        assert_eq!(errors.len(), 2);
+    }
+}
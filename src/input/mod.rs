@@ -1,8 +1,12 @@
 //! Input handling modules for the terminal application.
 //!
-//! This module contains keyboard shortcuts, input validation, and other
-//! input-related functionality.
+//! This module contains keyboard shortcuts, the keymap and key dispatcher,
+//! input validation, and other input-related functionality.
 
+pub mod dispatcher;
 pub mod keyboard;
+pub mod keymap;
 
+pub use dispatcher::{Dispatch, KeyDispatcher};
 pub use keyboard::{KeyboardShortcuts, shortcut_hints};
+pub use keymap::{KeyContext, Keymap};
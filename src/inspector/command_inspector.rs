@@ -3,6 +3,7 @@ use crate::editor::vim_mode::VimModeManager;
 use crate::executor::advanced_commands::AdvancedCommands;
 use crate::editor::EditorAction;
 
+#[derive(Debug, Clone)]
 pub struct CommandInspector {
     command_executor: AdvancedCommands,
     highlighter: SyntaxHighlighter,
@@ -28,6 +29,15 @@ impl CommandInspector {
         self.enabled = false;
     }
 
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Commands and paths completing `word`
+    pub fn completions(&self, word: &str, current_dir: &std::path::Path) -> Vec<String> {
+        self.command_executor.get_completions(word, current_dir)
+    }
+
     pub fn inspect_command(&self, command: &str) -> Vec<EditorAction> {
         // Highlight the syntax
         let highlighted_spans = self.highlighter.highlight(command);
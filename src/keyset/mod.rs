@@ -1,32 +1,31 @@
 //! Keyset management for Warp terminal
 //!
-//! This module handles loading and parsing YAML keybinding files according to the
-//! format specified in keysets/FORMAT.md.
+//! This module discovers the YAML keybinding files in `keysets/` and
+//! `~/.warp/keysets/` and loads the active one. Files are parsed by
+//! [`crate::input::keymap`] according to the format specified in
+//! keysets/FORMAT.md; the key dispatcher layers the active keyset over the
+//! built-in bindings, and the [`UserKeyset`] over both.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use iced::keyboard::{Key, Modifiers};
-use anyhow::{Result, Context};
-use crate::Message;
+use anyhow::{Context, Result};
+use serde_yaml::{Mapping, Value};
+use crate::input::keymap::{BindingLayer, BindingSource, KeySequence};
+
+/// The keyset that also gives the command input readline editing
+pub const EMACS_KEYSET: &str = "emacs";
 
 /// Represents a complete keyset loaded from a YAML file
 #[derive(Debug, Clone)]
 pub struct Keyset {
-    /// Map from action name to parsed keybinding
-    bindings: HashMap<String, ParsedKeybinding>,
+    /// Bindings in file order
+    layer: BindingLayer,
     /// Name of this keyset
     pub name: String,
     /// File path this keyset was loaded from
     pub path: PathBuf,
 }
 
-/// A parsed keybinding with key and modifiers
-#[derive(Debug, Clone, PartialEq)]
-struct ParsedKeybinding {
-    key: Key,
-    modifiers: Modifiers,
-}
-
 /// Manager for loading and managing keysets
 #[derive(Debug, Clone)]
 pub struct KeysetManager {
@@ -36,6 +35,12 @@ pub struct KeysetManager {
     available_keysets: HashMap<String, PathBuf>,
 }
 
+impl Default for KeysetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl KeysetManager {
     /// Create a new keyset manager
     pub fn new() -> Self {
@@ -45,7 +50,13 @@ impl KeysetManager {
         }
     }
 
-    /// Discover keysets in the keysets directory
+    /// `~/.warp/keysets`, where the user's own keysets live
+    pub fn user_keysets_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".warp").join("keysets"))
+    }
+
+    /// Discover keysets in the keysets directory. A keyset with the same
+    /// name as one discovered earlier replaces it.
     pub fn discover_keysets<P: AsRef<Path>>(&mut self, keysets_dir: P) -> Result<()> {
         let dir = keysets_dir.as_ref();
         if !dir.exists() {
@@ -86,239 +97,125 @@ impl KeysetManager {
     pub fn list_keysets(&self) -> Vec<&String> {
         self.available_keysets.keys().collect()
     }
-
-    /// Handle a keyboard event and return the corresponding message
-    pub fn handle_keyboard_event(&self, key: &Key, modifiers: &Modifiers) -> Option<Message> {
-        if let Some(keyset) = &self.active_keyset {
-            keyset.get_action_for_key(key, modifiers)
-        } else {
-            None
-        }
-    }
 }
 
 impl Keyset {
-    /// Load a keyset from a YAML file
+    /// Load a keyset from a YAML file. Entries that do not parse are
+    /// logged and skipped.
     fn load_from_file<P: AsRef<Path>>(path: P, name: &str) -> Result<Self> {
         let path = path.as_ref();
-        let contents = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read keyset file: {}", path.display()))?;
-        
-        // Parse YAML as a simple map of action -> key binding string
-        let raw_bindings: HashMap<String, String> = serde_yaml::from_str(&contents)
-            .with_context(|| format!("Failed to parse YAML in keyset file: {}", path.display()))?;
-        
-        let mut bindings = HashMap::new();
-        
-        // Parse each keybinding string into Key and Modifiers
-        for (action, binding_str) in raw_bindings {
-            match parse_keybinding(&binding_str) {
-                Ok(parsed) => {
-                    bindings.insert(action, parsed);
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to parse keybinding '{}' for action '{}': {}", binding_str, action, e);
-                }
-            }
+        let layer = BindingLayer::load(path, BindingSource::Keyset(path.to_path_buf()))?;
+        for error in &layer.errors {
+            tracing::warn!("Skipping keybinding in {}: {}", path.display(), error);
         }
-        
+
         Ok(Keyset {
-            bindings,
+            layer,
             name: name.to_string(),
             path: path.to_path_buf(),
         })
     }
 
-    /// Get the message for a key combination, if any
-    fn get_action_for_key(&self, key: &Key, modifiers: &Modifiers) -> Option<Message> {
-        // Find matching keybinding
-        for (action, binding) in &self.bindings {
-            if binding.key == *key && binding.modifiers == *modifiers {
-                return action_name_to_message(action);
-            }
-        }
-        None
+    /// The keyset's bindings, ready to layer over the built-in ones
+    pub fn layer(&self) -> &BindingLayer {
+        &self.layer
     }
 
     /// Get the keybinding string for an action
     pub fn get_binding_for_action(&self, action: &str) -> Option<String> {
-        self.bindings.get(action).map(|binding| {
-            format_keybinding(&binding.key, &binding.modifiers)
-        })
+        self.layer
+            .bindings
+            .iter()
+            .find(|binding| binding.action == action)
+            .map(|binding| binding.sequence.to_string())
     }
 }
 
-/// Parse a keybinding string like "cmd-shift-A" into Key and Modifiers
-fn parse_keybinding(binding_str: &str) -> Result<ParsedKeybinding> {
-    let parts: Vec<&str> = binding_str.split('-').collect();
-    
-    if parts.is_empty() {
-        anyhow::bail!("Empty keybinding string");
+/// The user's overrides file, edited by the keybinding editor. Entries
+/// keep their order, and entries the editor does not touch, such as
+/// bindings with a `context` or for actions it does not list, are written
+/// back as they were read.
+#[derive(Debug, Clone)]
+pub struct UserKeyset {
+    path: PathBuf,
+    entries: Mapping,
+}
+
+impl UserKeyset {
+    /// Read the file at `path`; a missing file is an empty keyset
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(source) => serde_yaml::from_str::<Option<Mapping>>(&source)
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+                .unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Mapping::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self { path, entries })
     }
-    
-    let key_part = parts.last().unwrap();
-    let modifier_parts = &parts[..parts.len() - 1];
-    
-    // Parse modifiers
-    let mut modifiers = Modifiers::empty();
-    for modifier in modifier_parts {
-        match *modifier {
-            "ctrl" => modifiers |= Modifiers::CTRL,
-            "cmd" => modifiers |= Modifiers::COMMAND,
-            "alt" => modifiers |= Modifiers::ALT,
-            "meta" => modifiers |= Modifiers::ALT, // Use ALT as META for compatibility
-            "shift" => modifiers |= Modifiers::SHIFT,
-            _ => anyhow::bail!("Unknown modifier: {}", modifier),
-        }
+
+    pub fn path(&self) -> &Path {
+        &self.path
     }
-    
-    // Parse key
-    let key = parse_key(key_part)?;
-    
-    Ok(ParsedKeybinding { key, modifiers })
-}
 
-/// Parse a key string into an iced Key
-fn parse_key(key_str: &str) -> Result<Key> {
-    let key = match key_str {
-        // Named keys
-        "up" => Key::Named(iced::keyboard::key::Named::ArrowUp),
-        "down" => Key::Named(iced::keyboard::key::Named::ArrowDown),
-        "left" => Key::Named(iced::keyboard::key::Named::ArrowLeft),
-        "right" => Key::Named(iced::keyboard::key::Named::ArrowRight),
-        "home" => Key::Named(iced::keyboard::key::Named::Home),
-        "end" => Key::Named(iced::keyboard::key::Named::End),
-        "pageup" => Key::Named(iced::keyboard::key::Named::PageUp),
-        "pagedown" => Key::Named(iced::keyboard::key::Named::PageDown),
-        "backspace" => Key::Named(iced::keyboard::key::Named::Backspace),
-        "enter" => Key::Named(iced::keyboard::key::Named::Enter),
-        "insert" => Key::Named(iced::keyboard::key::Named::Insert),
-        "delete" => Key::Named(iced::keyboard::key::Named::Delete),
-        "escape" => Key::Named(iced::keyboard::key::Named::Escape),
-        "tab" => Key::Named(iced::keyboard::key::Named::Tab),
-        "numpadenter" => Key::Named(iced::keyboard::key::Named::Enter), // Treat as regular enter for now
-        "f1" => Key::Named(iced::keyboard::key::Named::F1),
-        "f2" => Key::Named(iced::keyboard::key::Named::F2),
-        "f3" => Key::Named(iced::keyboard::key::Named::F3),
-        "f4" => Key::Named(iced::keyboard::key::Named::F4),
-        "f5" => Key::Named(iced::keyboard::key::Named::F5),
-        "f6" => Key::Named(iced::keyboard::key::Named::F6),
-        "f7" => Key::Named(iced::keyboard::key::Named::F7),
-        "f8" => Key::Named(iced::keyboard::key::Named::F8),
-        "f9" => Key::Named(iced::keyboard::key::Named::F9),
-        "f10" => Key::Named(iced::keyboard::key::Named::F10),
-        "f11" => Key::Named(iced::keyboard::key::Named::F11),
-        "f12" => Key::Named(iced::keyboard::key::Named::F12),
-        "f13" => Key::Named(iced::keyboard::key::Named::F13),
-        "f14" => Key::Named(iced::keyboard::key::Named::F14),
-        "f15" => Key::Named(iced::keyboard::key::Named::F15),
-        "f16" => Key::Named(iced::keyboard::key::Named::F16),
-        "f17" => Key::Named(iced::keyboard::key::Named::F17),
-        "f18" => Key::Named(iced::keyboard::key::Named::F18),
-        "f19" => Key::Named(iced::keyboard::key::Named::F19),
-        "f20" => Key::Named(iced::keyboard::key::Named::F20),
-        // Character keys - single character
-        s if s.chars().count() == 1 => {
-            Key::Character(s.into())
-        }
-        // Special characters that might appear in shift combinations
-        _ => Key::Character(key_str.into()),
-    };
-    
-    Ok(key)
-}
+    /// Whether the file sets or removes the bindings of `action`
+    pub fn is_customized(&self, action: &str) -> bool {
+        self.entries.contains_key(action)
+    }
 
-/// Convert action name to corresponding Message
-fn action_name_to_message(action: &str) -> Option<Message> {
-    match action {
-        // Input and command execution
-        "editor_view:clear_buffer" => Some(Message::InputChanged(String::new())),
-        
-        // Command search
-        "input:search_command_history" | "workspace:show_command_search" => Some(Message::ToggleCommandSearch),
-        
-        // Copy operations - these need block IDs, so we'll handle them differently
-        // For now, return None for actions that need additional context
-        "terminal:copy" | "terminal:copy_command" | "terminal:copy_output" => None,
-        
-        // Settings
-        "workspace:show_settings_modal" => Some(Message::InputChanged("toggle_settings".to_string())),
-        
-        // Theme operations
-        "workspace:show_theme_chooser" => None, // Would need theme chooser UI
-        
-        // Synchronization
-        "workspace:toggle_sync_mode" => Some(Message::ToggleSynchronizationMode),
-        
-        _ => {
-            tracing::debug!("Unhandled action: {}", action);
-            None
+    /// Replace the bindings of `action`; no sequences unbinds it. An entry
+    /// with a `context` keeps it.
+    pub fn set_bindings(&mut self, action: &str, sequences: &[KeySequence]) {
+        let mut keys: Vec<Value> = sequences.iter().map(|sequence| Value::String(sequence.to_string())).collect();
+        let keys = match keys.len() {
+            0 => Value::Null,
+            1 => keys.remove(0),
+            _ => Value::Sequence(keys),
+        };
+        match self.entries.get_mut(action) {
+            Some(Value::Mapping(scoped)) if !keys.is_null() => {
+                scoped.insert(Value::String("keys".to_string()), keys);
+            }
+            _ => {
+                self.entries.insert(Value::String(action.to_string()), keys);
+            }
         }
     }
-}
 
-/// Format a Key and Modifiers back into a keybinding string
-fn format_keybinding(key: &Key, modifiers: &Modifiers) -> String {
-    let mut parts = Vec::new();
-    
-    if modifiers.control() {
-        parts.push("ctrl");
+    /// Drop the entry for `action`, restoring the keyset's bindings.
+    /// Returns whether there was one.
+    pub fn reset(&mut self, action: &str) -> bool {
+        self.entries.shift_remove(action).is_some()
+    }
+
+    pub fn reset_all(&mut self) {
+        self.entries.clear();
     }
-    if modifiers.command() {
-        parts.push("cmd");
+
+    /// The entries as a layer for the [`Keymap`](crate::input::keymap::Keymap)
+    pub fn layer(&self) -> BindingLayer {
+        let origin = BindingSource::User(self.path.clone());
+        BindingLayer::parse(&self.to_yaml(), origin.clone()).unwrap_or_else(|e| BindingLayer {
+            errors: vec![e],
+            ..BindingLayer::new(origin)
+        })
     }
-    if modifiers.alt() {
-        parts.push("alt");
+
+    pub fn to_yaml(&self) -> String {
+        if self.entries.is_empty() {
+            return String::new();
+        }
+        serde_yaml::to_string(&self.entries).unwrap_or_default()
     }
-    if modifiers.shift() {
-        parts.push("shift");
+
+    /// Write the file, creating its directory
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&self.path, self.to_yaml()).with_context(|| format!("Failed to write {}", self.path.display()))
     }
-    
-    // Add the key
-    let key_str = match key {
-        Key::Named(named) => match named {
-            iced::keyboard::key::Named::ArrowUp => "up",
-            iced::keyboard::key::Named::ArrowDown => "down",
-            iced::keyboard::key::Named::ArrowLeft => "left",
-            iced::keyboard::key::Named::ArrowRight => "right",
-            iced::keyboard::key::Named::Home => "home",
-            iced::keyboard::key::Named::End => "end",
-            iced::keyboard::key::Named::PageUp => "pageup",
-            iced::keyboard::key::Named::PageDown => "pagedown",
-            iced::keyboard::key::Named::Backspace => "backspace",
-            iced::keyboard::key::Named::Enter => "enter",
-            iced::keyboard::key::Named::Insert => "insert",
-            iced::keyboard::key::Named::Delete => "delete",
-            iced::keyboard::key::Named::Escape => "escape",
-            iced::keyboard::key::Named::Tab => "tab",
-            iced::keyboard::key::Named::F1 => "f1",
-            iced::keyboard::key::Named::F2 => "f2",
-            iced::keyboard::key::Named::F3 => "f3",
-            iced::keyboard::key::Named::F4 => "f4",
-            iced::keyboard::key::Named::F5 => "f5",
-            iced::keyboard::key::Named::F6 => "f6",
-            iced::keyboard::key::Named::F7 => "f7",
-            iced::keyboard::key::Named::F8 => "f8",
-            iced::keyboard::key::Named::F9 => "f9",
-            iced::keyboard::key::Named::F10 => "f10",
-            iced::keyboard::key::Named::F11 => "f11",
-            iced::keyboard::key::Named::F12 => "f12",
-            iced::keyboard::key::Named::F13 => "f13",
-            iced::keyboard::key::Named::F14 => "f14",
-            iced::keyboard::key::Named::F15 => "f15",
-            iced::keyboard::key::Named::F16 => "f16",
-            iced::keyboard::key::Named::F17 => "f17",
-            iced::keyboard::key::Named::F18 => "f18",
-            iced::keyboard::key::Named::F19 => "f19",
-            iced::keyboard::key::Named::F20 => "f20",
-            _ => "unknown",
-        },
-        Key::Character(c) => c.as_str(),
-        _ => "unknown",
-    };
-    
-    parts.push(key_str);
-    parts.join("-")
 }
 
 #[cfg(test)]
@@ -329,10 +226,55 @@ mod tests {
     fn test_load_keyset() {
         let keyset = Keyset::load_from_file("./keysets/default-warp-keybindings.yaml", "test");
         if let Ok(keyset) = keyset {
-            assert!(keyset.bindings.len() > 0);
+            assert!(!keyset.layer().bindings.is_empty());
         } else {
             // Test file might not exist in test environment, that's ok
             assert!(true);
         }
     }
+
+    #[test]
+    fn test_user_keyset_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keysets").join("user.yaml");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            "\"vim:save\": {keys: ctrl-k ctrl-s, context: vim_normal}\n\"workspace:stop_sync\": f3\n\"pane_group:close\": cmd-w\n",
+        )
+        .unwrap();
+
+        let mut keyset = UserKeyset::load(&path).unwrap();
+        keyset.set_bindings("workspace:show_keybinding_settings", &[KeySequence::parse("ctrl-k ctrl-b").unwrap()]);
+        keyset.set_bindings("vim:save", &[KeySequence::parse("ctrl-s").unwrap(), KeySequence::parse("ctrl-x ctrl-s").unwrap()]);
+        keyset.set_bindings("pane_group:close", &[]);
+        assert!(keyset.reset("workspace:stop_sync"));
+        assert!(!keyset.reset("workspace:stop_sync"));
+        keyset.save().unwrap();
+
+        let reloaded = UserKeyset::load(&path).unwrap();
+        let layer = reloaded.layer();
+        assert!(layer.errors.is_empty(), "{:?}", layer.errors);
+        let bindings: Vec<String> = layer
+            .bindings
+            .iter()
+            .map(|binding| format!("{} {} {}", binding.context, binding.action, binding.sequence))
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                "vim_normal vim:save ctrl-s",
+                "vim_normal vim:save ctrl-x ctrl-s",
+                "terminal workspace:show_keybinding_settings ctrl-k ctrl-b",
+            ]
+        );
+        assert_eq!(layer.unbound, vec!["pane_group:close"]);
+        assert!(!reloaded.is_customized("workspace:stop_sync"));
+
+        // The saved file is picked up as a keyset named after it
+        let mut manager = KeysetManager::new();
+        manager.discover_keysets(path.parent().unwrap()).unwrap();
+        manager.load_keyset("user").unwrap();
+        assert_eq!(manager.active_keyset().unwrap().path, path);
+    }
 }
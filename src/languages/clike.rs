@@ -0,0 +1,538 @@
+//! One line lexer for the languages built from words, numbers, quoted
+//! strings and comments: Rust, Python, JavaScript, TypeScript, JSON and
+//! TOML. Each is a [`CLike`] naming its keywords, comment and string
+//! syntax and the few rules of its own, such as Rust raw strings and
+//! lifetimes or TOML table headers.
+
+use std::ops::Range;
+
+use crate::syntax_tree::highlight::{Grammar, LineState, Scope, Token};
+
+/// A kind of string literal
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub open: &'static str,
+    pub close: &'static str,
+    /// A backslash escapes the next character
+    pub escapes: bool,
+    /// The string may span lines
+    pub multiline: bool,
+}
+
+impl Quote {
+    const fn new(delimiter: &'static str, escapes: bool, multiline: bool) -> Self {
+        Self {
+            open: delimiter,
+            close: delimiter,
+            escapes,
+            multiline,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CLike {
+    pub keywords: &'static [&'static str],
+    pub constants: &'static [&'static str],
+    /// Builtin type names; capitalized words are types too when
+    /// `capitalized_types` is set
+    pub types: &'static [&'static str],
+    /// Keywords after which a name is a function or a type
+    pub function_keywords: &'static [&'static str],
+    pub type_keywords: &'static [&'static str],
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    pub nested_comments: bool,
+    /// Longest opening first
+    pub quotes: &'static [Quote],
+    /// Letters that may come right before a quote, as in `b"..."` or
+    /// `f'...'`; with `r` the string has no escapes
+    pub string_prefixes: &'static str,
+    /// Rust `r#"..."#` strings
+    pub raw_strings: bool,
+    /// Rust `'c'` characters, told apart from `'a` lifetimes
+    pub char_literals: bool,
+    /// `#` for Rust attributes, `@` for decorators
+    pub attribute: Option<char>,
+    /// A word or string before this is a key, as `:` in JSON
+    pub key_separator: Option<char>,
+    /// Every bare word that is not a constant is a key, as in TOML
+    pub bare_keys: bool,
+    /// TOML `[table]` headers
+    pub table_headers: bool,
+    pub capitalized_types: bool,
+    /// Rust `name!` macros
+    pub macros: bool,
+    /// Characters besides letters, digits and `_` that words may hold
+    pub word_chars: &'static str,
+    /// Characters besides letters, digits, `_` and `.` that numbers may
+    /// hold, such as the `-` and `:` of TOML dates
+    pub number_chars: &'static str,
+}
+
+const BASE: CLike = CLike {
+    keywords: &[],
+    constants: &[],
+    types: &[],
+    function_keywords: &[],
+    type_keywords: &[],
+    line_comment: None,
+    block_comment: None,
+    nested_comments: false,
+    quotes: &[],
+    string_prefixes: "",
+    raw_strings: false,
+    char_literals: false,
+    attribute: None,
+    key_separator: None,
+    bare_keys: false,
+    table_headers: false,
+    capitalized_types: false,
+    macros: false,
+    word_chars: "",
+    number_chars: "",
+};
+
+const JS_KEYWORDS: &[&str] = &[
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do",
+    "else", "export", "extends", "finally", "for", "from", "function", "get", "if", "import", "in", "instanceof",
+    "let", "new", "of", "return", "set", "static", "super", "switch", "this", "throw", "try", "typeof", "var",
+    "void", "while", "with", "yield",
+];
+
+const JS_QUOTES: &[Quote] = &[
+    Quote::new("\"", true, false),
+    Quote::new("'", true, false),
+    Quote::new("`", true, true),
+];
+
+impl CLike {
+    pub const RUST: CLike = CLike {
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn",
+            "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+            "Self", "static", "struct", "super", "trait", "type", "union", "unsafe", "use", "where", "while", "yield",
+        ],
+        constants: &["true", "false"],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128",
+            "isize", "f32", "f64",
+        ],
+        function_keywords: &["fn"],
+        type_keywords: &["struct", "enum", "trait", "type", "union"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        nested_comments: true,
+        quotes: &[Quote::new("\"", true, true)],
+        string_prefixes: "bcr",
+        raw_strings: true,
+        char_literals: true,
+        attribute: Some('#'),
+        capitalized_types: true,
+        macros: true,
+        ..BASE
+    };
+
+    pub const PYTHON: CLike = CLike {
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def", "del", "elif",
+            "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "match",
+            "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+        ],
+        constants: &["True", "False", "None"],
+        types: &["int", "float", "str", "bool", "bytes", "list", "dict", "set", "tuple", "object"],
+        function_keywords: &["def"],
+        type_keywords: &["class"],
+        line_comment: Some("#"),
+        quotes: &[
+            Quote::new("\"\"\"", true, true),
+            Quote::new("'''", true, true),
+            Quote::new("\"", true, false),
+            Quote::new("'", true, false),
+        ],
+        string_prefixes: "rbfuRBFU",
+        attribute: Some('@'),
+        capitalized_types: true,
+        ..BASE
+    };
+
+    pub const JAVASCRIPT: CLike = CLike {
+        keywords: JS_KEYWORDS,
+        constants: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+        function_keywords: &["function"],
+        type_keywords: &["class"],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: JS_QUOTES,
+        capitalized_types: true,
+        word_chars: "$",
+        ..BASE
+    };
+
+    pub const TYPESCRIPT: CLike = CLike {
+        keywords: &[
+            "abstract", "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+            "declare", "default", "delete", "do", "else", "enum", "export", "extends", "finally", "for", "from",
+            "function", "get", "if", "implements", "import", "in", "infer", "instanceof", "interface", "is", "keyof",
+            "let", "namespace", "new", "of", "private", "protected", "public", "readonly", "return", "satisfies",
+            "set", "static", "super", "switch", "this", "throw", "try", "type", "typeof", "var", "void", "while",
+            "with", "yield",
+        ],
+        types: &["any", "bigint", "boolean", "never", "number", "object", "string", "symbol", "unknown"],
+        type_keywords: &["class", "interface", "type", "enum"],
+        attribute: Some('@'),
+        ..Self::JAVASCRIPT
+    };
+
+    pub const JSON: CLike = CLike {
+        constants: &["true", "false", "null"],
+        // For JSON with comments, such as editor settings
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: &[Quote::new("\"", true, false)],
+        key_separator: Some(':'),
+        ..BASE
+    };
+
+    pub const TOML: CLike = CLike {
+        constants: &["true", "false", "inf", "nan"],
+        line_comment: Some("#"),
+        quotes: &[
+            Quote::new("\"\"\"", true, true),
+            Quote::new("'''", false, true),
+            Quote::new("\"", true, false),
+            Quote::new("'", false, false),
+        ],
+        key_separator: Some('='),
+        bare_keys: true,
+        table_headers: true,
+        word_chars: "-",
+        number_chars: "-:+",
+        ..BASE
+    };
+
+    fn is_word_char(&self, c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || self.word_chars.contains(c)
+    }
+
+    /// Continue a comment at `pos` that is `depth` deep. Returns where it
+    /// closes, or `None` when it runs past the line.
+    fn block_comment_end(&self, line: &str, mut pos: usize, depth: &mut usize) -> Option<usize> {
+        let (open, close) = self.block_comment?;
+        while pos < line.len() {
+            let rest = &line[pos..];
+            if rest.starts_with(close) {
+                pos += close.len();
+                *depth -= 1;
+                if *depth == 0 {
+                    return Some(pos);
+                }
+            } else if self.nested_comments && rest.starts_with(open) {
+                pos += open.len();
+                *depth += 1;
+            } else {
+                pos += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        None
+    }
+
+    fn starts_comment(&self, rest: &str) -> bool {
+        self.line_comment.is_some_and(|comment| rest.starts_with(comment))
+            || self.block_comment.is_some_and(|(open, _)| rest.starts_with(open))
+    }
+
+    /// The quote opening at `pos`, if any
+    fn quote_at(&self, line: &str, pos: usize) -> Option<Quote> {
+        self.quotes.iter().copied().find(|quote| line[pos..].starts_with(quote.open))
+    }
+
+    /// End of a string whose opening ends at `pos`: after its close, or
+    /// the end of the line when it stays open
+    fn string(&self, line: &str, pos: usize, close: &str, escapes: bool, multiline: bool, state: &mut LineState) -> usize {
+        string_end(line, pos, close, escapes).unwrap_or_else(|| {
+            if multiline {
+                *state = LineState::String {
+                    close: close.to_string(),
+                    escapes,
+                };
+            }
+            line.len()
+        })
+    }
+
+    fn push_string(&self, line: &str, range: Range<usize>, tokens: &mut Vec<Token>) {
+        let scope = if self.is_key(line, range.end) { Scope::Property } else { Scope::String };
+        tokens.push(Token::new(range, scope));
+    }
+
+    /// The token ending at `end` is followed by the key separator
+    fn is_key(&self, line: &str, end: usize) -> bool {
+        self.key_separator
+            .is_some_and(|separator| line[end..].trim_start().starts_with(separator))
+    }
+
+    /// A Rust raw string such as `r#"..."#` starting at `start` with its
+    /// `r` at `r`
+    fn raw_string(&self, line: &str, start: usize, r: usize, state: &mut LineState, tokens: &mut Vec<Token>) -> Option<usize> {
+        let hashes = line[r + 1..].bytes().take_while(|&b| b == b'#').count();
+        if line.as_bytes().get(r + 1 + hashes) != Some(&b'"') {
+            return None;
+        }
+        let close = format!("\"{}", "#".repeat(hashes));
+        let end = self.string(line, r + 2 + hashes, &close, false, true, state);
+        self.push_string(line, start..end, tokens);
+        Some(end)
+    }
+
+    /// A character literal such as `'a'` or `'\n'` at `start`, with its
+    /// quote at `quote`, or a lifetime such as `'a`
+    fn char_or_lifetime(&self, line: &str, start: usize, quote: usize, tokens: &mut Vec<Token>) -> usize {
+        let rest = &line[quote + 1..];
+        let literal_len = if let Some(escaped) = rest.strip_prefix('\\') {
+            escaped.find('\'').map(|close| close + 2)
+        } else {
+            rest.chars()
+                .next()
+                .filter(|c| rest[c.len_utf8()..].starts_with('\''))
+                .map(char::len_utf8)
+        };
+        if let Some(len) = literal_len {
+            let end = quote + 1 + len + 1;
+            tokens.push(Token::new(start..end, Scope::String));
+            return end;
+        }
+        let name_len = rest.find(|c: char| !self.is_word_char(c)).unwrap_or(rest.len());
+        let end = quote + 1 + name_len;
+        tokens.push(Token::new(start..end, Scope::Label));
+        end
+    }
+
+    fn word_scope(&self, line: &str, word: &str, end: usize, previous: Option<&str>) -> Option<Scope> {
+        let next = line[end..].trim_start();
+        if self.constants.contains(&word) {
+            Some(Scope::Constant)
+        } else if self.keywords.contains(&word) {
+            Some(Scope::Keyword)
+        } else if self.bare_keys || self.is_key(line, end) {
+            Some(Scope::Property)
+        } else if previous.is_some_and(|keyword| self.function_keywords.contains(&keyword)) {
+            Some(Scope::Function)
+        } else if previous.is_some_and(|keyword| self.type_keywords.contains(&keyword)) || self.types.contains(&word) {
+            Some(Scope::Type)
+        } else if next.starts_with('(') {
+            Some(Scope::Function)
+        } else if self.capitalized_types && word.starts_with(|c: char| c.is_uppercase()) {
+            let all_caps = word.len() > 1 && !word.chars().any(char::is_lowercase);
+            Some(if all_caps { Scope::Constant } else { Scope::Type })
+        } else {
+            None
+        }
+    }
+}
+
+/// Where a string closed by `close` ends, searching from `pos`
+fn string_end(line: &str, mut pos: usize, close: &str, escapes: bool) -> Option<usize> {
+    while pos < line.len() {
+        let rest = &line[pos..];
+        if rest.starts_with(close) {
+            return Some(pos + close.len());
+        }
+        let skip = if escapes && rest.starts_with('\\') { 1 } else { 0 };
+        pos += skip + rest[skip..].chars().next().map_or(0, char::len_utf8);
+    }
+    None
+}
+
+/// Length of the number at the start of `rest`
+fn number_len(rest: &str, extra: &str) -> usize {
+    let bytes = rest.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let c = bytes[len] as char;
+        let exponent_sign = (c == '+' || c == '-')
+            && len > 0
+            && matches!(bytes[len - 1], b'e' | b'E')
+            && !rest.starts_with("0x");
+        let decimal_point = c == '.' && bytes.get(len + 1).is_some_and(u8::is_ascii_digit);
+        if c.is_ascii_alphanumeric() || c == '_' || decimal_point || exponent_sign || extra.contains(c) {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+impl Grammar for CLike {
+    fn highlight_line(&self, line: &str, state: &mut LineState, tokens: &mut Vec<Token>) {
+        let mut pos = 0;
+        match std::mem::take(state) {
+            LineState::BlockComment { mut depth } => match self.block_comment_end(line, 0, &mut depth) {
+                Some(end) => {
+                    tokens.push(Token::new(0..end, Scope::Comment));
+                    pos = end;
+                }
+                None => {
+                    if !line.is_empty() {
+                        tokens.push(Token::new(0..line.len(), Scope::Comment));
+                    }
+                    *state = LineState::BlockComment { depth };
+                    return;
+                }
+            },
+            LineState::String { close, escapes } => {
+                pos = self.string(line, 0, &close, escapes, true, state);
+                if pos > 0 {
+                    self.push_string(line, 0..pos, tokens);
+                }
+            }
+            _ => {}
+        }
+
+        let mut previous_word: Option<&str> = None;
+        while pos < line.len() && *state == LineState::Normal {
+            let rest = &line[pos..];
+            let Some(c) = rest.chars().next() else { break };
+            let start = pos;
+            if c.is_whitespace() {
+                pos += c.len_utf8();
+                continue;
+            }
+            let word = previous_word.take();
+
+            if self.line_comment.is_some_and(|comment| rest.starts_with(comment)) {
+                tokens.push(Token::new(pos..line.len(), Scope::Comment));
+                break;
+            }
+            if let Some((open, _)) = self.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+                let mut depth = 1;
+                pos = match self.block_comment_end(line, pos + open.len(), &mut depth) {
+                    Some(end) => end,
+                    None => {
+                        *state = LineState::BlockComment { depth };
+                        line.len()
+                    }
+                };
+                tokens.push(Token::new(start..pos, Scope::Comment));
+                continue;
+            }
+            if self.table_headers && c == '[' && line[..pos].trim().is_empty() {
+                pos = line.find(']').map_or(line.len(), |close| close + 1);
+                tokens.push(Token::new(start..pos, Scope::Type));
+                continue;
+            }
+            if self.attribute == Some(c) {
+                if c == '#' && (rest.starts_with("#[") || rest.starts_with("#![")) {
+                    pos = attribute_end(line, pos);
+                    tokens.push(Token::new(start..pos, Scope::Attribute));
+                    continue;
+                }
+                if c == '@' && rest[1..].starts_with(|c: char| self.is_word_char(c)) {
+                    let name_len = rest[1..]
+                        .find(|c: char| !self.is_word_char(c) && c != '.')
+                        .unwrap_or(rest.len() - 1);
+                    pos += 1 + name_len;
+                    tokens.push(Token::new(start..pos, Scope::Attribute));
+                    continue;
+                }
+            }
+            if let Some(quote) = self.quote_at(line, pos) {
+                let from = pos + quote.open.len();
+                pos = self.string(line, from, quote.close, quote.escapes, quote.multiline, state);
+                self.push_string(line, start..pos, tokens);
+                continue;
+            }
+            if self.char_literals && c == '\'' {
+                pos = self.char_or_lifetime(line, start, pos, tokens);
+                continue;
+            }
+            if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+                pos += number_len(rest, self.number_chars);
+                tokens.push(Token::new(start..pos, Scope::Number));
+                continue;
+            }
+            if self.is_word_char(c) {
+                let len = rest.find(|c: char| !self.is_word_char(c)).unwrap_or(rest.len());
+                let word_text = &rest[..len];
+                pos += len;
+
+                // A prefixed string such as b"..", f'..' or r#".."#
+                let prefixed = len <= 2 && word_text.chars().all(|c| self.string_prefixes.contains(c));
+                if prefixed {
+                    if self.raw_strings && word_text.ends_with('r') {
+                        if let Some(end) = self.raw_string(line, start, pos - 1, state, tokens) {
+                            pos = end;
+                            continue;
+                        }
+                    }
+                    if let Some(quote) = self.quote_at(line, pos) {
+                        let escapes = quote.escapes && !word_text.contains(['r', 'R']);
+                        let from = pos + quote.open.len();
+                        pos = self.string(line, from, quote.close, escapes, quote.multiline, state);
+                        self.push_string(line, start..pos, tokens);
+                        continue;
+                    }
+                    if self.char_literals && line[pos..].starts_with('\'') {
+                        pos = self.char_or_lifetime(line, start, pos, tokens);
+                        continue;
+                    }
+                }
+
+                if self.macros && line[pos..].starts_with('!') && !line[pos..].starts_with("!=") {
+                    pos += 1;
+                    tokens.push(Token::new(start..pos, Scope::Function));
+                    continue;
+                }
+                if let Some(scope) = self.word_scope(line, word_text, pos, word) {
+                    tokens.push(Token::new(start..pos, scope));
+                }
+                previous_word = Some(word_text);
+                continue;
+            }
+
+            let scope = if "+-*/%=<>!&|^~?:".contains(c) {
+                Scope::Operator
+            } else if "()[]{},;.".contains(c) {
+                Scope::Punctuation
+            } else {
+                pos += c.len_utf8();
+                continue;
+            };
+            // Runs of operator characters make one token, such as `->` or
+            // `&&`, up to any comment right after them
+            pos += c.len_utf8();
+            while scope == Scope::Operator
+                && line[pos..].starts_with(|c: char| "+-*/%=<>!&|^~?:".contains(c))
+                && !self.starts_comment(&line[pos..])
+            {
+                pos += 1;
+            }
+            tokens.push(Token::new(start..pos, scope));
+        }
+    }
+}
+
+/// End of a `#[...]` attribute starting at `pos`, with nested brackets
+/// and strings inside it; the end of the line when it does not close there
+fn attribute_end(line: &str, pos: usize) -> usize {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line[pos..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return pos + index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    line.len()
+}
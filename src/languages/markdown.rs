@@ -0,0 +1,145 @@
+//! Markdown, line by line: headings, lists, quotes, inline code, emphasis
+//! and links. Fenced code is highlighted with the language named after the
+//! fence when the registry knows it.
+
+use crate::languages::LanguageRegistry;
+use crate::syntax_tree::highlight::{Grammar, LineState, Scope, Token};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Markdown;
+
+impl Grammar for Markdown {
+    fn highlight_line(&self, line: &str, state: &mut LineState, tokens: &mut Vec<Token>) {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if let LineState::Fence { marker, language, inner } = state {
+            let closes = trimmed.trim_end().len() >= marker.len()
+                && trimmed.trim_end().chars().all(|c| marker.starts_with(c));
+            if closes {
+                tokens.push(Token::new(indent..line.trim_end().len(), Scope::Punctuation));
+                *state = LineState::Normal;
+                return;
+            }
+            match language.as_deref().and_then(|name| LanguageRegistry::global().get(name)) {
+                Some(language) => language.grammar().highlight_line(line, inner, tokens),
+                None if !line.is_empty() => tokens.push(Token::new(0..line.len(), Scope::Code)),
+                None => {}
+            }
+            return;
+        }
+        *state = LineState::Normal;
+
+        let fence = ["```", "~~~"].into_iter().find(|fence| trimmed.starts_with(fence));
+        if let Some(fence) = fence {
+            let fence_char = fence.chars().next().unwrap_or('`');
+            let marker_len = trimmed.len() - trimmed.trim_start_matches(fence_char).len();
+            let info = trimmed[marker_len..].trim();
+            tokens.push(Token::new(indent..indent + marker_len, Scope::Punctuation));
+            if !info.is_empty() {
+                let info_start = line.len() - line[indent + marker_len..].trim_start().len();
+                tokens.push(Token::new(info_start..info_start + info.len(), Scope::Attribute));
+            }
+            *state = LineState::Fence {
+                marker: trimmed[..marker_len].to_string(),
+                language: info.split_whitespace().next().map(str::to_string),
+                inner: Box::default(),
+            };
+            return;
+        }
+
+        let hashes = trimmed.len() - trimmed.trim_start_matches('#').len();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].chars().next().is_none_or(char::is_whitespace) {
+            tokens.push(Token::new(indent..line.trim_end().len(), Scope::Heading));
+            return;
+        }
+        let rule = trimmed.trim_end();
+        if rule.len() >= 3
+            && ['-', '*', '_', '='].iter().any(|&c| rule.chars().all(|r| r == c || r == ' ') && rule.starts_with(c))
+        {
+            tokens.push(Token::new(indent..indent + rule.len(), Scope::Punctuation));
+            return;
+        }
+
+        let mut pos = indent;
+        while line[pos..].starts_with('>') {
+            tokens.push(Token::new(pos..pos + 1, Scope::Punctuation));
+            pos = line.len() - line[pos + 1..].trim_start().len();
+        }
+        if let Some(marker_len) = list_marker_len(&line[pos..]) {
+            tokens.push(Token::new(pos..pos + marker_len, Scope::Punctuation));
+            pos += marker_len;
+        }
+        inline(line, pos, tokens);
+    }
+}
+
+/// Length of a list marker such as `-`, `*`, `+`, `1.` or `2)` followed by
+/// a space
+fn list_marker_len(rest: &str) -> Option<usize> {
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let len = match rest[digits..].chars().next()? {
+        '-' | '*' | '+' if digits == 0 => 1,
+        '.' | ')' if digits > 0 => digits + 1,
+        _ => return None,
+    };
+    rest[len..].starts_with(' ').then_some(len)
+}
+
+/// Code spans, emphasis and links from `pos` on
+fn inline(line: &str, mut pos: usize, tokens: &mut Vec<Token>) {
+    while pos < line.len() {
+        let rest = &line[pos..];
+        let Some(c) = rest.chars().next() else { break };
+        let span = match c {
+            '\\' => {
+                pos += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            '`' => {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let fence = &rest[..ticks];
+                rest[ticks..].find(fence).map(|close| (close + 2 * ticks, Scope::Code))
+            }
+            '*' | '_' => {
+                let previous = line[..pos].chars().next_back();
+                let opens = c == '*' || !previous.is_some_and(char::is_alphanumeric);
+                let strong = rest[1..].starts_with(c);
+                let delimiter = &rest[..if strong { 2 } else { 1 }];
+                let inner = &rest[delimiter.len()..];
+                if opens && inner.starts_with(|c: char| !c.is_whitespace()) {
+                    inner
+                        .find(delimiter)
+                        .filter(|&close| close > 0 && !inner[..close].ends_with(char::is_whitespace))
+                        .map(|close| {
+                            let scope = if strong { Scope::Strong } else { Scope::Emphasis };
+                            (close + 2 * delimiter.len(), scope)
+                        })
+                } else {
+                    None
+                }
+            }
+            '[' | '!' if rest.starts_with('[') || rest.starts_with("![") => link_len(rest).map(|len| (len, Scope::Link)),
+            '<' => rest
+                .find('>')
+                .filter(|&close| rest[1..close].contains("://") && !rest[..close].contains(' '))
+                .map(|close| (close + 1, Scope::Link)),
+            _ => None,
+        };
+        match span {
+            Some((len, scope)) => {
+                tokens.push(Token::new(pos..pos + len, scope));
+                pos += len;
+            }
+            None => pos += c.len_utf8(),
+        }
+    }
+}
+
+/// Length of a `[text](url)` link or `![alt](src)` image at the start of
+/// `rest`
+fn link_len(rest: &str) -> Option<usize> {
+    let text_start = rest.find('[')? + 1;
+    let text_end = text_start + rest[text_start..].find("](")?;
+    let url_end = text_end + 2 + rest[text_end + 2..].find(')')?;
+    Some(url_end + 1)
+}
@@ -0,0 +1,298 @@
+//! Languages Module
+//!
+//! The languages the editor and code snippets can highlight, each a
+//! [`Grammar`] with the file extensions and names that select it. Shell
+//! commands reuse the shell parser; the rest are line lexers configured per
+//! language.
+
+mod clike;
+mod markdown;
+mod shell;
+mod yaml;
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::syntax_tree::highlight::Grammar;
+
+pub use clike::CLike;
+pub use markdown::Markdown;
+pub use shell::Shell;
+pub use yaml::Yaml;
+
+pub struct Language {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    extensions: &'static [&'static str],
+    file_names: &'static [&'static str],
+    grammar: Box<dyn Grammar>,
+}
+
+impl Language {
+    pub fn new(name: &'static str, grammar: impl Grammar + 'static) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            extensions: &[],
+            file_names: &[],
+            grammar: Box::new(grammar),
+        }
+    }
+
+    /// Other names for the language, such as the info string of a
+    /// Markdown code fence
+    pub fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub fn with_extensions(mut self, extensions: &'static [&'static str]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Whole file names, for files such as `.bashrc` or `Cargo.lock`
+    pub fn with_file_names(mut self, file_names: &'static [&'static str]) -> Self {
+        self.file_names = file_names;
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn grammar(&self) -> &dyn Grammar {
+        &*self.grammar
+    }
+}
+
+impl fmt::Debug for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Language").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LanguageRegistry {
+    languages: Vec<Arc<Language>>,
+}
+
+impl LanguageRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shell, Rust, Python, JavaScript, TypeScript, JSON, YAML, TOML and
+    /// Markdown
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            Language::new("shell", Shell)
+                .with_aliases(&["sh", "bash", "zsh", "fish", "console"])
+                .with_extensions(&["sh", "bash", "zsh", "fish", "ksh"])
+                .with_file_names(&[".bashrc", ".bash_profile", ".zshrc", ".zprofile", ".profile"]),
+        );
+        registry.register(Language::new("rust", CLike::RUST).with_aliases(&["rs"]).with_extensions(&["rs"]));
+        registry.register(
+            Language::new("python", CLike::PYTHON)
+                .with_aliases(&["py", "python3"])
+                .with_extensions(&["py", "pyi", "pyw"]),
+        );
+        registry.register(
+            Language::new("javascript", CLike::JAVASCRIPT)
+                .with_aliases(&["js", "jsx", "node"])
+                .with_extensions(&["js", "mjs", "cjs", "jsx"]),
+        );
+        registry.register(
+            Language::new("typescript", CLike::TYPESCRIPT)
+                .with_aliases(&["ts", "tsx"])
+                .with_extensions(&["ts", "mts", "cts", "tsx"]),
+        );
+        registry.register(Language::new("json", CLike::JSON).with_extensions(&["json", "jsonc"]));
+        registry.register(Language::new("yaml", Yaml).with_aliases(&["yml"]).with_extensions(&["yaml", "yml"]));
+        registry.register(
+            Language::new("toml", CLike::TOML)
+                .with_extensions(&["toml"])
+                .with_file_names(&["Cargo.lock"]),
+        );
+        registry.register(
+            Language::new("markdown", Markdown)
+                .with_aliases(&["md"])
+                .with_extensions(&["md", "markdown"]),
+        );
+        registry
+    }
+
+    /// The builtin languages, shared
+    pub fn global() -> &'static Self {
+        static REGISTRY: OnceLock<LanguageRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(Self::builtin)
+    }
+
+    /// Add a language, replacing any with the same name
+    pub fn register(&mut self, language: Language) {
+        self.languages.retain(|existing| existing.name != language.name);
+        self.languages.push(Arc::new(language));
+    }
+
+    /// The language called `name` or one of its aliases, ignoring case
+    pub fn get(&self, name: &str) -> Option<Arc<Language>> {
+        self.languages
+            .iter()
+            .find(|language| {
+                language.name.eq_ignore_ascii_case(name)
+                    || language.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    }
+
+    /// The language of a file, from its name or else its extension
+    pub fn for_path(&self, path: &Path) -> Option<Arc<Language>> {
+        let name = path.file_name()?.to_str()?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        self.languages
+            .iter()
+            .find(|language| language.file_names.contains(&name))
+            .or_else(|| {
+                let extension = extension?;
+                self.languages
+                    .iter()
+                    .find(|language| language.extensions.iter().any(|known| known.eq_ignore_ascii_case(extension)))
+            })
+            .cloned()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.languages.iter().map(|language| language.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::syntax_tree::highlight::{HighlightMap, Scope};
+
+    /// The scope of each highlighted piece of `text`, as (text, scope)
+    fn highlight<'a>(language: &str, text: &'a str) -> Vec<(&'a str, Scope)> {
+        let language = LanguageRegistry::global().get(language).unwrap();
+        HighlightMap::highlight(language, text)
+            .into_iter()
+            .map(|(range, scope)| (&text[range], scope))
+            .collect()
+    }
+
+    fn scope_of(highlights: &[(&str, Scope)], piece: &str) -> Option<Scope> {
+        highlights.iter().find(|(text, _)| *text == piece).map(|(_, scope)| *scope)
+    }
+
+    #[test]
+    fn test_lookup_by_name_alias_and_path() {
+        let registry = LanguageRegistry::global();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["shell", "rust", "python", "javascript", "typescript", "json", "yaml", "toml", "markdown"]
+        );
+        assert_eq!(registry.get("TS").unwrap().name(), "typescript");
+        assert_eq!(registry.get("bash").unwrap().name(), "shell");
+        assert!(registry.get("cobol").is_none());
+
+        let name_of = |path: &str| registry.for_path(Path::new(path)).map(|language| language.name());
+        assert_eq!(name_of("src/main.rs"), Some("rust"));
+        assert_eq!(name_of("/home/me/.zshrc"), Some("shell"));
+        assert_eq!(name_of("Cargo.lock"), Some("toml"));
+        assert_eq!(name_of("README.MD"), Some("markdown"));
+        assert_eq!(name_of("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_each_language_highlights_its_basics() {
+        let rust = highlight("rust", "#[derive(Debug)]\npub fn parse<'a>(s: &'a str) -> Vec<u8> { println!(\"{s}\"); 0x1f }");
+        for (piece, scope) in [
+            ("#[derive(Debug)]", Scope::Attribute),
+            ("pub", Scope::Keyword),
+            ("parse", Scope::Function),
+            ("'a", Scope::Label),
+            ("str", Scope::Type),
+            ("Vec", Scope::Type),
+            ("println!", Scope::Function),
+            ("\"{s}\"", Scope::String),
+            ("0x1f", Scope::Number),
+        ] {
+            assert_eq!(scope_of(&rust, piece), Some(scope), "rust {piece}");
+        }
+
+        let python = highlight("python", "@cache\ndef load(path=None):\n    return f'{path}'  # done\n\"\"\"doc\nstring\"\"\"");
+        for (piece, scope) in [
+            ("@cache", Scope::Attribute),
+            ("def", Scope::Keyword),
+            ("load", Scope::Function),
+            ("None", Scope::Constant),
+            ("f'{path}'", Scope::String),
+            ("# done", Scope::Comment),
+            ("\"\"\"doc", Scope::String),
+            ("string\"\"\"", Scope::String),
+        ] {
+            assert_eq!(scope_of(&python, piece), Some(scope), "python {piece}");
+        }
+
+        let typescript = highlight("typescript", "interface Point { x: number }\nconst p = `a\nb`; /* c */ fetch(p);");
+        for (piece, scope) in [
+            ("interface", Scope::Keyword),
+            ("Point", Scope::Type),
+            ("number", Scope::Type),
+            ("`a", Scope::String),
+            ("b`", Scope::String),
+            ("/* c */", Scope::Comment),
+            ("fetch", Scope::Function),
+        ] {
+            assert_eq!(scope_of(&typescript, piece), Some(scope), "typescript {piece}");
+        }
+
+        let json = highlight("json", r#"{"name": "warp", "tags": [1, true, null]}"#);
+        assert_eq!(scope_of(&json, "\"name\""), Some(Scope::Property));
+        assert_eq!(scope_of(&json, "\"warp\""), Some(Scope::String));
+        assert_eq!(scope_of(&json, "true"), Some(Scope::Constant));
+        assert_eq!(scope_of(&json, "1"), Some(Scope::Number));
+
+        let toml = highlight("toml", "[package]\nname = \"warp\" # app\nedition-year = 2021");
+        assert_eq!(scope_of(&toml, "[package]"), Some(Scope::Type));
+        assert_eq!(scope_of(&toml, "name"), Some(Scope::Property));
+        assert_eq!(scope_of(&toml, "edition-year"), Some(Scope::Property));
+        assert_eq!(scope_of(&toml, "# app"), Some(Scope::Comment));
+        assert_eq!(scope_of(&toml, "2021"), Some(Scope::Number));
+
+        let yaml = highlight("yaml", "steps:\n  - name: build # ci\n    run: |\n      cargo build\n    on: true");
+        assert_eq!(scope_of(&yaml, "steps"), Some(Scope::Property));
+        assert_eq!(scope_of(&yaml, "build"), Some(Scope::String));
+        assert_eq!(scope_of(&yaml, "# ci"), Some(Scope::Comment));
+        assert_eq!(scope_of(&yaml, "cargo build"), Some(Scope::String));
+        assert_eq!(scope_of(&yaml, "on"), Some(Scope::Property));
+        assert_eq!(scope_of(&yaml, "true"), Some(Scope::Constant));
+
+        let markdown = highlight("markdown", "# Title\nSome **bold** and `code` [link](https://x.y)\n```rust\nlet x = 1;\n```");
+        for (piece, scope) in [
+            ("# Title", Scope::Heading),
+            ("**bold**", Scope::Strong),
+            ("`code`", Scope::Code),
+            ("[link](https://x.y)", Scope::Link),
+            ("let", Scope::Keyword),
+        ] {
+            assert_eq!(scope_of(&markdown, piece), Some(scope), "markdown {piece}");
+        }
+
+        let shell = highlight("shell", "cat <<EOF | grep -v x\nhello $USER\nEOF\necho 'a\nb' > out.txt");
+        for (piece, scope) in [
+            ("cat", Scope::Command),
+            ("-v", Scope::Flag),
+            ("hello $USER", Scope::String),
+            ("echo", Scope::Command),
+            ("b'", Scope::String),
+            (">", Scope::Redirect),
+        ] {
+            assert_eq!(scope_of(&shell, piece), Some(scope), "shell {piece}");
+        }
+        let heredoc_end = shell.iter().rfind(|(text, _)| *text == "EOF");
+        assert_eq!(heredoc_end, Some(&("EOF", Scope::String)));
+    }
+}
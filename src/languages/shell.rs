@@ -0,0 +1,137 @@
+//! Shell scripts and the command line, highlighted by the shell parser one
+//! line at a time. What the parser cannot see from a single line, a quote
+//! or heredoc left open or a line continued with a backslash, is carried
+//! over in the [`LineState`].
+
+use crate::syntax_tree::highlight::{Grammar, LineState, Scope, Token};
+use crate::syntax_tree::{parse_shell, HighlightKind, ShellDialect};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Shell;
+
+impl Grammar for Shell {
+    fn highlight_line(&self, line: &str, state: &mut LineState, tokens: &mut Vec<Token>) {
+        let mut pos = 0;
+        match std::mem::take(state) {
+            LineState::Heredoc { delimiter, strip_tabs } => {
+                if !line.is_empty() {
+                    tokens.push(Token::new(0..line.len(), Scope::String));
+                }
+                let candidate = if strip_tabs { line.trim_start_matches('\t') } else { line };
+                if candidate != delimiter {
+                    *state = LineState::Heredoc { delimiter, strip_tabs };
+                }
+                return;
+            }
+            LineState::String { close, escapes } => match quote_end(line, 0, &close, escapes) {
+                Some(end) => {
+                    tokens.push(Token::new(0..end, Scope::String));
+                    pos = end;
+                }
+                None => {
+                    if !line.is_empty() {
+                        tokens.push(Token::new(0..line.len(), Scope::String));
+                    }
+                    *state = LineState::String { close, escapes };
+                    return;
+                }
+            },
+            LineState::Continuation => {}
+            _ => {
+                // A fresh command: its first word is the program
+                highlight_words(line, 0, "", tokens);
+                *state = line_end_state(line);
+                return;
+            }
+        }
+        // The rest continues a command, so its first word is an argument
+        highlight_words(line, pos, "_ ", tokens);
+        *state = line_end_state(&line[pos..]);
+    }
+}
+
+/// Highlight `line` from `pos` on with the shell parser, parsing it after
+/// `prefix`
+fn highlight_words(line: &str, pos: usize, prefix: &str, tokens: &mut Vec<Token>) {
+    let source = format!("{prefix}{}", &line[pos..]);
+    for highlight in parse_shell(&source, ShellDialect::default()).highlights() {
+        if highlight.span.start < prefix.len() {
+            continue;
+        }
+        let start = highlight.span.start - prefix.len() + pos;
+        let end = highlight.span.end - prefix.len() + pos;
+        tokens.push(Token::new(start..end, scope(highlight.kind)));
+    }
+}
+
+fn scope(kind: HighlightKind) -> Scope {
+    match kind {
+        HighlightKind::Command => Scope::Command,
+        HighlightKind::Keyword => Scope::Keyword,
+        HighlightKind::Argument => Scope::Argument,
+        HighlightKind::Flag => Scope::Flag,
+        HighlightKind::String | HighlightKind::Heredoc => Scope::String,
+        HighlightKind::Variable | HighlightKind::Substitution | HighlightKind::Assignment => Scope::Variable,
+        HighlightKind::Operator => Scope::Operator,
+        HighlightKind::Redirect => Scope::Redirect,
+        HighlightKind::Comment => Scope::Comment,
+        HighlightKind::Number => Scope::Number,
+        HighlightKind::Error => Scope::Error,
+    }
+}
+
+/// Where a quote closed by `close` ends, searching from `pos`
+fn quote_end(line: &str, pos: usize, close: &str, escapes: bool) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in line[pos..].char_indices() {
+        if escaped {
+            escaped = false;
+        } else if escapes && c == '\\' {
+            escaped = true;
+        } else if line[pos + index..].starts_with(close) {
+            return Some(pos + index + close.len());
+        }
+    }
+    None
+}
+
+/// What `line` leaves open: a quote, a heredoc whose body follows, or a
+/// continuation after a trailing backslash
+fn line_end_state(line: &str) -> LineState {
+    let mut heredoc = None;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            // Skips the escaped character, if there is one
+            '\\' if chars.next().is_none() => return heredoc.unwrap_or(LineState::Continuation),
+            '\'' | '"' => {
+                let escapes = c == '"';
+                match quote_end(line, index + 1, &c.to_string(), escapes) {
+                    Some(end) => while chars.peek().is_some_and(|&(next, _)| next < end) {
+                        chars.next();
+                    },
+                    None => {
+                        return LineState::String {
+                            close: c.to_string(),
+                            escapes,
+                        }
+                    }
+                }
+            }
+            '#' if index == 0 || line[..index].ends_with(char::is_whitespace) => break,
+            '<' if heredoc.is_none() && line[index..].starts_with("<<") && !line[index..].starts_with("<<<") => {
+                let rest = &line[index + 2..];
+                let strip_tabs = rest.starts_with('-');
+                let word = rest.trim_start_matches('-').trim_start();
+                let word_len = word.find(|c: char| c.is_whitespace() || ";|&<>()".contains(c)).unwrap_or(word.len());
+                let delimiter: String = word[..word_len].chars().filter(|c| !"'\"\\".contains(*c)).collect();
+                if !delimiter.is_empty() {
+                    heredoc = Some(LineState::Heredoc { delimiter, strip_tabs });
+                }
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    heredoc.unwrap_or_default()
+}
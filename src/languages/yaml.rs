@@ -0,0 +1,172 @@
+//! YAML, line by line: keys, scalars, anchors and tags, and the indented
+//! lines of `|` and `>` block scalars.
+
+use crate::syntax_tree::highlight::{Grammar, LineState, Scope, Token};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Yaml;
+
+const CONSTANTS: &[&str] = &["true", "false", "yes", "no", "on", "off", "null", "~"];
+
+impl Grammar for Yaml {
+    fn highlight_line(&self, line: &str, state: &mut LineState, tokens: &mut Vec<Token>) {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if let LineState::BlockScalar { indent: parent } = *state {
+            if line.trim().is_empty() {
+                return;
+            }
+            if indent > parent {
+                tokens.push(Token::new(indent..line.len(), Scope::String));
+                return;
+            }
+        }
+        *state = LineState::Normal;
+
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." || line.starts_with("--- ") {
+            tokens.push(Token::new(0..3, Scope::Punctuation));
+            return self.value(line, 3, indent, state, tokens);
+        }
+
+        let mut pos = indent;
+        // Sequence entries, possibly several on one line as in `- - a`
+        while line[pos..].starts_with('-') && line[pos + 1..].chars().next().is_none_or(char::is_whitespace) {
+            tokens.push(Token::new(pos..pos + 1, Scope::Punctuation));
+            pos = skip_spaces(line, pos + 1);
+        }
+        if let Some(key_end) = key_end(line, pos) {
+            tokens.push(Token::new(pos..key_end, Scope::Property));
+            tokens.push(Token::new(key_end..key_end + 1, Scope::Punctuation));
+            pos = key_end + 1;
+        }
+        self.value(line, pos, indent, state, tokens)
+    }
+}
+
+impl Yaml {
+    /// Highlight the value from `pos` to the end of the line
+    fn value(&self, line: &str, pos: usize, indent: usize, state: &mut LineState, tokens: &mut Vec<Token>) {
+        let mut pos = skip_spaces(line, pos);
+        let mut flow_depth = 0usize;
+        while pos < line.len() {
+            let rest = &line[pos..];
+            let start = pos;
+            match rest.chars().next().unwrap_or(' ') {
+                '#' => {
+                    tokens.push(Token::new(pos..line.len(), Scope::Comment));
+                    return;
+                }
+                '|' | '>' => {
+                    let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    tokens.push(Token::new(pos..pos + len, Scope::Operator));
+                    *state = LineState::BlockScalar { indent };
+                    pos += len;
+                }
+                '&' | '*' | '!' => {
+                    let len = rest.find(|c: char| c.is_whitespace() || ",[]{}".contains(c)).unwrap_or(rest.len());
+                    let scope = if rest.starts_with('!') { Scope::Type } else { Scope::Variable };
+                    tokens.push(Token::new(pos..pos + len, scope));
+                    pos += len;
+                }
+                quote @ ('"' | '\'') => {
+                    pos = quoted_end(line, pos + 1, quote);
+                    let scope = if line[pos..].starts_with(':') { Scope::Property } else { Scope::String };
+                    tokens.push(Token::new(start..pos, scope));
+                }
+                c if "[]{},:".contains(c) => {
+                    match c {
+                        '[' | '{' => flow_depth += 1,
+                        ']' | '}' => flow_depth = flow_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    tokens.push(Token::new(pos..pos + 1, Scope::Punctuation));
+                    pos += 1;
+                }
+                _ => {
+                    // A plain scalar runs to a comment, or inside a flow
+                    // collection to the next separator
+                    let in_flow = flow_depth > 0;
+                    let end = plain_end(line, pos, in_flow);
+                    let text = line[pos..end].trim_end();
+                    let scope = if in_flow && line[end..].starts_with(':') {
+                        Scope::Property
+                    } else if CONSTANTS.contains(&text.to_ascii_lowercase().as_str()) {
+                        Scope::Constant
+                    } else if is_number(text) {
+                        Scope::Number
+                    } else {
+                        Scope::String
+                    };
+                    tokens.push(Token::new(pos..pos + text.len(), scope));
+                    pos = end;
+                }
+            }
+            pos = skip_spaces(line, pos);
+        }
+    }
+}
+
+fn skip_spaces(line: &str, pos: usize) -> usize {
+    line.len() - line[pos..].trim_start().len()
+}
+
+/// End of a mapping key starting at `pos`: before a `:` followed by a
+/// space or the end of the line
+fn key_end(line: &str, pos: usize) -> Option<usize> {
+    let rest = &line[pos..];
+    let key_len = match rest.chars().next()? {
+        quote @ ('"' | '\'') => quoted_end(line, pos + 1, quote) - pos,
+        '#' | '[' | '{' | '|' | '>' | '&' | '*' | '!' => return None,
+        _ => {
+            let mut end = None;
+            for (index, c) in rest.char_indices() {
+                if c == '#' && rest[..index].ends_with(' ') {
+                    break;
+                }
+                if c == ':' && rest[index + 1..].chars().next().is_none_or(char::is_whitespace) {
+                    end = Some(index);
+                    break;
+                }
+            }
+            end?
+        }
+    };
+    let after = &rest[key_len..];
+    (after.starts_with(':') && after[1..].chars().next().is_none_or(char::is_whitespace)).then_some(pos + key_len)
+}
+
+/// End of a quoted scalar whose contents start at `pos`
+fn quoted_end(line: &str, pos: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in line[pos..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            _ if c == quote => return pos + index + 1,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+fn plain_end(line: &str, pos: usize, in_flow: bool) -> usize {
+    let rest = &line[pos..];
+    for (index, c) in rest.char_indices() {
+        let after_space = index > 0 && rest[..index].ends_with(char::is_whitespace);
+        if (c == '#' && after_space) || (in_flow && ",[]{}".contains(c)) {
+            return pos + index;
+        }
+        if in_flow && c == ':' && rest[index + 1..].chars().next().is_none_or(|c| c.is_whitespace() || c == ',') {
+            return pos + index;
+        }
+    }
+    line.len()
+}
+
+fn is_number(text: &str) -> bool {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+        && (digits.parse::<f64>().is_ok()
+            || digits.strip_prefix("0x").is_some_and(|hex| u64::from_str_radix(hex, 16).is_ok())
+            || digits.strip_prefix("0o").is_some_and(|octal| u64::from_str_radix(octal, 8).is_ok()))
+}
@@ -0,0 +1,470 @@
+//! Block structure: splits lines into headings, paragraphs, code, lists,
+//! quotes and breaks. Container blocks strip their markers and parse their
+//! content recursively.
+
+use super::inline::parse_inline;
+use super::{BlockKind, List, ListItem, MarkdownBlock};
+
+/// Columns of indentation that make a line indented code
+const CODE_INDENT: usize = 4;
+
+pub(super) fn parse_blocks(lines: &[&str]) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if is_blank(line) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let kind = if let Some(fence) = Fence::open(line) {
+            let (code, end) = fence.collect(lines, i + 1);
+            i = end;
+            BlockKind::CodeBlock { info: fence.info, code }
+        } else if let Some((level, content)) = atx_heading(line) {
+            i += 1;
+            BlockKind::Heading {
+                level,
+                content: parse_inline(&content),
+            }
+        } else if is_thematic_break(line) {
+            i += 1;
+            BlockKind::ThematicBreak
+        } else if let Some(rest) = quote_content(line) {
+            let mut inner = vec![rest];
+            i += 1;
+            while i < lines.len() {
+                match quote_content(lines[i]) {
+                    Some(rest) => inner.push(rest),
+                    // A lazy continuation line of a quoted paragraph
+                    None if !is_blank(lines[i]) && !is_blank(inner[inner.len() - 1]) && !starts_block(lines[i]) => {
+                        inner.push(lines[i])
+                    }
+                    None => break,
+                }
+                i += 1;
+            }
+            BlockKind::BlockQuote(parse_blocks(&inner))
+        } else if let Some(marker) = ListMarker::parse(line) {
+            let (list, end) = parse_list(lines, i, marker);
+            i = end;
+            BlockKind::List(list)
+        } else if indent_width(line) >= CODE_INDENT {
+            let mut code = String::new();
+            let mut end = i;
+            while i < lines.len() && (is_blank(lines[i]) || indent_width(lines[i]) >= CODE_INDENT) {
+                if !is_blank(lines[i]) {
+                    end = i + 1;
+                }
+                i += 1;
+            }
+            for line in &lines[start..end] {
+                code.push_str(strip_indent(line, CODE_INDENT));
+                code.push('\n');
+            }
+            i = end;
+            BlockKind::CodeBlock {
+                info: String::new(),
+                code,
+            }
+        } else {
+            let mut text = vec![line.trim_start()];
+            i += 1;
+            let mut level = None;
+            while i < lines.len() && !is_blank(lines[i]) {
+                if let Some(setext) = setext_level(lines[i]) {
+                    level = Some(setext);
+                    i += 1;
+                    break;
+                }
+                if starts_block(lines[i]) {
+                    break;
+                }
+                text.push(lines[i].trim_start());
+                i += 1;
+            }
+            let content = parse_inline(text.join("\n").trim_end());
+            match level {
+                Some(level) => BlockKind::Heading { level, content },
+                None => BlockKind::Paragraph(content),
+            }
+        };
+        blocks.push(MarkdownBlock { kind, lines: start..i });
+    }
+    blocks
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Width of the leading whitespace, with tabs to the next multiple of four
+fn indent_width(line: &str) -> usize {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Remove up to `columns` of leading indentation
+fn strip_indent(line: &str, columns: usize) -> &str {
+    let mut width = 0;
+    for (index, c) in line.char_indices() {
+        if width >= columns {
+            return &line[index..];
+        }
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4 - width % 4,
+            _ => return &line[index..],
+        }
+    }
+    ""
+}
+
+/// Whether `line` starts a block that interrupts a paragraph
+fn starts_block(line: &str) -> bool {
+    Fence::open(line).is_some()
+        || atx_heading(line).is_some()
+        || is_thematic_break(line)
+        || quote_content(line).is_some()
+        || ListMarker::parse(line).is_some_and(|marker| !marker.is_empty_item && marker.start.is_none_or(|n| n == 1))
+}
+
+struct Fence {
+    marker: char,
+    len: usize,
+    indent: usize,
+    info: String,
+}
+
+impl Fence {
+    fn open(line: &str) -> Option<Self> {
+        let indent = indent_width(line);
+        if indent >= CODE_INDENT {
+            return None;
+        }
+        let rest = line.trim_start();
+        let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = rest.chars().take_while(|c| *c == marker).count();
+        if len < 3 {
+            return None;
+        }
+        let info = rest[len..].trim();
+        if marker == '`' && info.contains('`') {
+            return None;
+        }
+        Some(Self {
+            marker,
+            len,
+            indent,
+            info: info.to_string(),
+        })
+    }
+
+    fn closes(&self, line: &str) -> bool {
+        let rest = line.trim_start();
+        indent_width(line) < CODE_INDENT
+            && rest.chars().take_while(|c| *c == self.marker).count() >= self.len
+            && rest.trim_start_matches(self.marker).trim().is_empty()
+    }
+
+    /// The code from `from` to the closing fence (or the end of the input)
+    /// and the index of the line after the block
+    fn collect(&self, lines: &[&str], from: usize) -> (String, usize) {
+        let mut code = String::new();
+        for (index, line) in lines.iter().enumerate().skip(from) {
+            if self.closes(line) {
+                return (code, index + 1);
+            }
+            code.push_str(strip_indent(line, self.indent));
+            code.push('\n');
+        }
+        (code, lines.len())
+    }
+}
+
+fn atx_heading(line: &str) -> Option<(u8, String)> {
+    if indent_width(line) >= CODE_INDENT {
+        return None;
+    }
+    let rest = line.trim_start();
+    let level = rest.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let after = &rest[level..];
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+    // An optional closing sequence of #s preceded by a space
+    let mut content = after.trim();
+    let without_closing = content.trim_end_matches('#');
+    if without_closing.is_empty() || without_closing.ends_with([' ', '\t']) {
+        content = without_closing.trim_end();
+    }
+    Some((level as u8, content.to_string()))
+}
+
+fn setext_level(line: &str) -> Option<u8> {
+    if indent_width(line) >= CODE_INDENT {
+        return None;
+    }
+    let rest = line.trim();
+    if !rest.is_empty() && rest.chars().all(|c| c == '=') {
+        Some(1)
+    } else if !rest.is_empty() && rest.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    if indent_width(line) >= CODE_INDENT {
+        return false;
+    }
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['*', '-', '_'].iter().any(|marker| chars.iter().all(|c| c == marker))
+}
+
+/// The content of a block quote line, without the `>` marker
+fn quote_content(line: &str) -> Option<&str> {
+    if indent_width(line) >= CODE_INDENT {
+        return None;
+    }
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ListMarker {
+    /// `-`, `+` or `*` for bullets, `.` or `)` for ordered lists
+    delimiter: char,
+    start: Option<u64>,
+    /// Columns before the item's content
+    content_indent: usize,
+    is_empty_item: bool,
+}
+
+impl ListMarker {
+    fn parse(line: &str) -> Option<Self> {
+        let indent = indent_width(line);
+        if indent >= CODE_INDENT || is_thematic_break(line) {
+            return None;
+        }
+        let rest = line.trim_start();
+        let (delimiter, start, marker_len) = match rest.chars().next()? {
+            c @ ('-' | '+' | '*') => (c, None, 1),
+            c if c.is_ascii_digit() => {
+                let digits = rest.chars().take_while(char::is_ascii_digit).count();
+                if digits > 9 {
+                    return None;
+                }
+                let delimiter = rest[digits..].chars().next().filter(|c| *c == '.' || *c == ')')?;
+                (delimiter, rest[..digits].parse().ok(), digits + 1)
+            }
+            _ => return None,
+        };
+        let after = &rest[marker_len..];
+        if after.trim().is_empty() {
+            return Some(Self {
+                delimiter,
+                start,
+                content_indent: indent + marker_len + 1,
+                is_empty_item: true,
+            });
+        }
+        let spaces = after.chars().take_while(|c| *c == ' ').count();
+        if spaces == 0 {
+            return None;
+        }
+        // Five or more spaces: the content is indented code one space in
+        let spaces = if spaces > CODE_INDENT { 1 } else { spaces };
+        Some(Self {
+            delimiter,
+            start,
+            content_indent: indent + marker_len + spaces,
+            is_empty_item: false,
+        })
+    }
+
+    fn same_list(&self, other: &ListMarker) -> bool {
+        self.delimiter == other.delimiter
+    }
+}
+
+fn parse_list(lines: &[&str], mut i: usize, first: ListMarker) -> (List, usize) {
+    let mut items = Vec::new();
+    let mut marker = first;
+
+    loop {
+        // The first line of the item, without its marker
+        let mut content: Vec<&str> = vec![strip_indent_chars(lines[i], marker.content_indent)];
+        i += 1;
+        let mut trailing_blanks = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if is_blank(line) {
+                content.push("");
+                trailing_blanks += 1;
+            } else if indent_width(line) >= marker.content_indent {
+                content.push(strip_indent(line, marker.content_indent));
+                trailing_blanks = 0;
+            } else if trailing_blanks == 0 && !starts_block(line) && ListMarker::parse(line).is_none() {
+                // Lazy continuation of the item's paragraph
+                content.push(line.trim_start());
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        content.truncate(content.len() - trailing_blanks);
+
+        let mut checked = None;
+        if let Some(first_line) = content.first_mut() {
+            for (prefix, state) in [("[ ] ", false), ("[x] ", true), ("[X] ", true)] {
+                if let Some(rest) = first_line.strip_prefix(prefix) {
+                    checked = Some(state);
+                    *first_line = rest;
+                }
+            }
+        }
+        items.push(ListItem {
+            blocks: parse_blocks(&content),
+            checked,
+        });
+
+        // Blank lines between items do not end the list
+        let next = (i..lines.len()).find(|&index| !is_blank(lines[index]));
+        match next.and_then(|index| ListMarker::parse(lines[index]).map(|next| (index, next))) {
+            Some((index, next)) if next.same_list(&first) && indent_width(lines[index]) < first.content_indent => {
+                i = index;
+                marker = next;
+            }
+            _ => break,
+        }
+    }
+
+    (
+        List {
+            start: first.start,
+            items,
+        },
+        i,
+    )
+}
+
+/// Remove the first `columns` characters of a list item's first line,
+/// which hold its marker
+fn strip_indent_chars(line: &str, columns: usize) -> &str {
+    let index = line
+        .char_indices()
+        .nth(columns)
+        .map_or(line.len(), |(index, _)| index);
+    &line[index..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse, Inline};
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<BlockKind> {
+        parse(source).blocks.into_iter().map(|block| block.kind).collect()
+    }
+
+    /// A paragraph of plain lines joined by soft breaks
+    fn paragraph(value: &str) -> BlockKind {
+        let mut inlines = Vec::new();
+        for (index, line) in value.split('\n').enumerate() {
+            if index > 0 {
+                inlines.push(Inline::SoftBreak);
+            }
+            inlines.push(Inline::Text(line.to_string()));
+        }
+        BlockKind::Paragraph(inlines)
+    }
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(
+            kinds("## Setup ##\nTitle\n=====\n#NotAHeading"),
+            vec![
+                BlockKind::Heading {
+                    level: 2,
+                    content: vec![Inline::Text("Setup".to_string())]
+                },
+                BlockKind::Heading {
+                    level: 1,
+                    content: vec![Inline::Text("Title".to_string())]
+                },
+                paragraph("#NotAHeading"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert_eq!(
+            kinds("~~~~ sh title\n```\nnested\n~~~~\n\n    indented\n\n    more\n"),
+            vec![
+                BlockKind::CodeBlock {
+                    info: "sh title".to_string(),
+                    code: "```\nnested\n".to_string()
+                },
+                BlockKind::CodeBlock {
+                    info: String::new(),
+                    code: "indented\n\nmore\n".to_string()
+                },
+            ]
+        );
+        // An unclosed fence runs to the end of the document
+        assert_eq!(
+            kinds("```\nopen"),
+            vec![BlockKind::CodeBlock {
+                info: String::new(),
+                code: "open\n".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let blocks = kinds("3. first\n   continued\n4. [x] done\n\n   second paragraph\n- other list\n");
+        assert_eq!(blocks.len(), 2);
+        let BlockKind::List(list) = &blocks[0] else {
+            panic!("expected a list, got {:?}", blocks[0]);
+        };
+        assert_eq!(list.start, Some(3));
+        assert_eq!(list.items[0].blocks[0].kind, paragraph("first\ncontinued"));
+        assert_eq!(list.items[1].checked, Some(true));
+        assert_eq!(list.items[1].blocks.len(), 2);
+
+        let BlockKind::List(nested) = &kinds("- a\n  - b\n")[0] else {
+            panic!("expected a list");
+        };
+        assert!(matches!(&nested.items[0].blocks[1].kind, BlockKind::List(inner) if inner.items.len() == 1));
+    }
+
+    #[test]
+    fn test_quotes_and_breaks() {
+        assert_eq!(
+            kinds("> quoted\nlazy\n\n***\n- - -"),
+            vec![
+                BlockKind::BlockQuote(vec![MarkdownBlock {
+                    kind: paragraph("quoted\nlazy"),
+                    lines: 0..2
+                }]),
+                BlockKind::ThematicBreak,
+                BlockKind::ThematicBreak,
+            ]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Inline content: emphasis, code spans, links, images and line breaks.
+
+use super::Inline;
+
+/// Parse the inline content of a paragraph or heading. Newlines become
+/// soft breaks unless preceded by two spaces or a backslash.
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    parse_range(&chars, 0, chars.len(), &mut out);
+    out
+}
+
+fn parse_range(chars: &[char], start: usize, end: usize, out: &mut Vec<Inline>) {
+    let mut text = String::new();
+    let mut i = start;
+    while i < end {
+        let c = chars[i];
+        match c {
+            '\\' if i + 1 < end && chars[i + 1] == '\n' => {
+                flush(&mut text, out);
+                out.push(Inline::HardBreak);
+                i += 2;
+            }
+            '\\' if i + 1 < end && chars[i + 1].is_ascii_punctuation() => {
+                text.push(chars[i + 1]);
+                i += 2;
+            }
+            '\n' => {
+                let hard = text.ends_with("  ");
+                let trimmed = text.trim_end_matches(' ').len();
+                text.truncate(trimmed);
+                flush(&mut text, out);
+                out.push(if hard { Inline::HardBreak } else { Inline::SoftBreak });
+                i += 1;
+                // Leading spaces of the next line are not content
+                while i < end && chars[i] == ' ' {
+                    i += 1;
+                }
+            }
+            '`' => match code_span(chars, i, end) {
+                Some((code, next)) => {
+                    flush(&mut text, out);
+                    out.push(Inline::Code(code));
+                    i = next;
+                }
+                None => {
+                    // An unmatched run of backticks is literal
+                    let run = run_length(chars, i, end, '`');
+                    text.extend(&chars[i..i + run]);
+                    i += run;
+                }
+            },
+            '!' if i + 1 < end && chars[i + 1] == '[' => match link(chars, i + 1, end) {
+                Some((label_end, url, next)) => {
+                    flush(&mut text, out);
+                    let mut alt = Vec::new();
+                    parse_range(chars, i + 2, label_end, &mut alt);
+                    out.push(Inline::Image {
+                        alt: super::plain_text(&alt),
+                        url,
+                    });
+                    i = next;
+                }
+                None => {
+                    text.push(c);
+                    i += 1;
+                }
+            },
+            '[' => match link(chars, i, end) {
+                Some((label_end, url, next)) => {
+                    flush(&mut text, out);
+                    let mut content = Vec::new();
+                    parse_range(chars, i + 1, label_end, &mut content);
+                    out.push(Inline::Link { content, url });
+                    i = next;
+                }
+                None => {
+                    text.push(c);
+                    i += 1;
+                }
+            },
+            '<' => match autolink(chars, i, end) {
+                Some((url, next)) => {
+                    flush(&mut text, out);
+                    out.push(Inline::Link {
+                        content: vec![Inline::Text(url.clone())],
+                        url,
+                    });
+                    i = next;
+                }
+                None => {
+                    text.push(c);
+                    i += 1;
+                }
+            },
+            '*' | '_' => match emphasis(chars, i, end) {
+                Some((strong, inner_end, next)) => {
+                    flush(&mut text, out);
+                    let delimiter = if strong { 2 } else { 1 };
+                    let mut inner = Vec::new();
+                    parse_range(chars, i + delimiter, inner_end, &mut inner);
+                    out.push(if strong {
+                        Inline::Strong(inner)
+                    } else {
+                        Inline::Emphasis(inner)
+                    });
+                    i = next;
+                }
+                None => {
+                    let run = run_length(chars, i, end, c);
+                    text.extend(&chars[i..i + run]);
+                    i += run;
+                }
+            },
+            _ => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut text, out);
+}
+
+fn flush(text: &mut String, out: &mut Vec<Inline>) {
+    if !text.is_empty() {
+        out.push(Inline::Text(std::mem::take(text)));
+    }
+}
+
+fn run_length(chars: &[char], start: usize, end: usize, c: char) -> usize {
+    chars[start..end].iter().take_while(|&&other| other == c).count()
+}
+
+/// A code span opened by the backtick run at `start`: its content and the
+/// index after the closing run
+fn code_span(chars: &[char], start: usize, end: usize) -> Option<(String, usize)> {
+    let run = run_length(chars, start, end, '`');
+    let mut i = start + run;
+    while i < end {
+        if chars[i] == '`' {
+            let closing = run_length(chars, i, end, '`');
+            if closing == run {
+                let content: String = chars[start + run..i]
+                    .iter()
+                    .map(|&c| if c == '\n' { ' ' } else { c })
+                    .collect();
+                let stripped = match content.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                    Some(inner) if !inner.trim().is_empty() => inner.to_string(),
+                    _ => content,
+                };
+                return Some((stripped, i + closing));
+            }
+            i += closing;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// A `[label](url "title")` starting at the `[` at `start`: the end of the
+/// label, the URL and the index after the link
+fn link(chars: &[char], start: usize, end: usize) -> Option<(usize, String, usize)> {
+    let mut depth = 0;
+    let mut label_end = None;
+    let mut i = start;
+    while i < end {
+        match chars[i] {
+            '\\' => i += 1,
+            '`' => {
+                if let Some((_, next)) = code_span(chars, i, end) {
+                    i = next;
+                    continue;
+                }
+            }
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    label_end = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let label_end = label_end?;
+    if chars.get(label_end + 1) != Some(&'(') {
+        return None;
+    }
+
+    let close = (label_end + 2..end).find(|&index| chars[index] == ')')?;
+    let destination: String = chars[label_end + 2..close].iter().collect();
+    let destination = destination.trim();
+    // Drop an optional title after the URL
+    let url = match destination.split_once(char::is_whitespace) {
+        Some((url, title)) if title.trim_start().starts_with(['"', '\'', '(']) => url,
+        Some(_) => return None,
+        None => destination,
+    };
+    let url = url
+        .strip_prefix('<')
+        .and_then(|url| url.strip_suffix('>'))
+        .unwrap_or(url);
+    Some((label_end, url.to_string(), close + 1))
+}
+
+/// `<scheme:...>` or `<user@host>` starting at `start`
+fn autolink(chars: &[char], start: usize, end: usize) -> Option<(String, usize)> {
+    let close = (start + 1..end).find(|&index| chars[index] == '>' || chars[index] == '<' || chars[index].is_whitespace())?;
+    if chars[close] != '>' {
+        return None;
+    }
+    let inner: String = chars[start + 1..close].iter().collect();
+    let is_uri = inner
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.len() >= 2 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)));
+    let is_email = inner.contains('@') && !inner.contains(':');
+    if !is_uri && !is_email {
+        return None;
+    }
+    let url = if is_email { format!("mailto:{}", inner) } else { inner };
+    Some((url, close + 1))
+}
+
+/// Emphasis opened by the delimiter run at `start`: whether it is strong,
+/// where the content ends and the index after the closing delimiter
+fn emphasis(chars: &[char], start: usize, end: usize) -> Option<(bool, usize, usize)> {
+    let c = chars[start];
+    let run = run_length(chars, start, end, c);
+    let before = start.checked_sub(1).map(|index| chars[index]);
+    let after = chars.get(start + run).copied().filter(|_| start + run < end);
+    // The opening run must be followed by non-whitespace, and `_` must not
+    // be inside a word
+    if after.is_none_or(char::is_whitespace) {
+        return None;
+    }
+    if c == '_' && before.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+
+    let strong = run >= 2;
+    let delimiter = if strong { 2 } else { 1 };
+    let mut i = start + run;
+    while i < end {
+        match chars[i] {
+            '\\' => i += 2,
+            '`' => match code_span(chars, i, end) {
+                Some((_, next)) => i = next,
+                None => i += run_length(chars, i, end, '`'),
+            },
+            other if other == c => {
+                let closing = run_length(chars, i, end, c);
+                let closing_after = chars.get(i + closing).copied().filter(|_| i + closing < end);
+                let can_close = !chars[i - 1].is_whitespace()
+                    && (c == '*' || !closing_after.is_some_and(char::is_alphanumeric));
+                if can_close && closing >= delimiter {
+                    // `***text***`: strong wrapping emphasis; close the outer run
+                    return Some((strong, i + closing - delimiter, i + closing));
+                }
+                i += closing;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Inline {
+        Inline::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_emphasis_and_code() {
+        assert_eq!(
+            parse_inline("a *b* __c__ `d*e*` snake_case_name 2 * 3"),
+            vec![
+                text("a "),
+                Inline::Emphasis(vec![text("b")]),
+                text(" "),
+                Inline::Strong(vec![text("c")]),
+                text(" "),
+                Inline::Code("d*e*".to_string()),
+                text(" snake_case_name 2 * 3"),
+            ]
+        );
+        assert_eq!(
+            parse_inline("**bold *and italic***"),
+            vec![Inline::Strong(vec![text("bold "), Inline::Emphasis(vec![text("and italic")])])]
+        );
+        assert_eq!(parse_inline("`` a ` b ``"), vec![Inline::Code("a ` b".to_string())]);
+    }
+
+    #[test]
+    fn test_links_and_images() {
+        assert_eq!(
+            parse_inline("[the *docs*](https://example.com \"Docs\") ![logo](img.png) <https://warp.dev> [no link]"),
+            vec![
+                Inline::Link {
+                    content: vec![text("the "), Inline::Emphasis(vec![text("docs")])],
+                    url: "https://example.com".to_string()
+                },
+                text(" "),
+                Inline::Image {
+                    alt: "logo".to_string(),
+                    url: "img.png".to_string()
+                },
+                text(" "),
+                Inline::Link {
+                    content: vec![text("https://warp.dev")],
+                    url: "https://warp.dev".to_string()
+                },
+                text(" [no link]"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_breaks_and_escapes() {
+        assert_eq!(
+            parse_inline("one  \ntwo\\\nthree\n four \\*five\\*"),
+            vec![
+                text("one"),
+                Inline::HardBreak,
+                text("two"),
+                Inline::HardBreak,
+                text("three"),
+                Inline::SoftBreak,
+                text("four *five*"),
+            ]
+        );
+    }
+}
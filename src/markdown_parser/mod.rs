@@ -0,0 +1,190 @@
+//! Markdown Parser Module
+//!
+//! A CommonMark parser for the documents shown in the terminal: notebooks,
+//! AI answers and rendered `.md` files. It covers the block structure
+//! (headings, paragraphs, fenced and indented code, lists, block quotes and
+//! thematic breaks) and the common inline syntax (emphasis, code spans,
+//! links, images, autolinks and hard breaks). Raw HTML and tables are kept
+//! as text.
+//!
+//! Parsing never fails; anything that is not recognised is a paragraph.
+
+mod blocks;
+mod inline;
+
+use std::ops::Range;
+
+pub use inline::parse_inline;
+
+/// A parsed Markdown document.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document {
+    pub blocks: Vec<MarkdownBlock>,
+}
+
+/// A block with the source lines it was parsed from. Line ranges of nested
+/// blocks are relative to the content of their container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownBlock {
+    pub kind: BlockKind,
+    pub lines: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockKind {
+    Heading { level: u8, content: Vec<Inline> },
+    Paragraph(Vec<Inline>),
+    /// `info` is the text after an opening fence, e.g. `bash` in ```` ```bash ````;
+    /// empty for indented code.
+    CodeBlock { info: String, code: String },
+    List(List),
+    BlockQuote(Vec<MarkdownBlock>),
+    ThematicBreak,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct List {
+    /// Number of the first item of an ordered list
+    pub start: Option<u64>,
+    pub items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+    pub blocks: Vec<MarkdownBlock>,
+    /// `Some` for task list items (`- [ ]` / `- [x]`)
+    pub checked: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Link { content: Vec<Inline>, url: String },
+    Image { alt: String, url: String },
+    /// A line break inside a paragraph
+    SoftBreak,
+    /// A forced line break (two trailing spaces or a trailing backslash)
+    HardBreak,
+}
+
+/// Parse a Markdown document.
+pub fn parse(source: &str) -> Document {
+    let lines: Vec<&str> = source.lines().collect();
+    Document {
+        blocks: blocks::parse_blocks(&lines),
+    }
+}
+
+impl Document {
+    /// Text of the first level-one heading, if any
+    pub fn title(&self) -> Option<String> {
+        self.blocks.iter().find_map(|block| match &block.kind {
+            BlockKind::Heading { level: 1, content } => Some(plain_text(content)),
+            _ => None,
+        })
+    }
+
+    /// Every fenced or indented code block, including those nested in lists
+    /// and quotes, in document order
+    pub fn code_blocks(&self) -> Vec<(&str, &str)> {
+        fn collect<'a>(blocks: &'a [MarkdownBlock], out: &mut Vec<(&'a str, &'a str)>) {
+            for block in blocks {
+                match &block.kind {
+                    BlockKind::CodeBlock { info, code } => out.push((info, code)),
+                    BlockKind::BlockQuote(inner) => collect(inner, out),
+                    BlockKind::List(list) => {
+                        for item in &list.items {
+                            collect(&item.blocks, out);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut out = Vec::new();
+        collect(&self.blocks, &mut out);
+        out
+    }
+}
+
+impl BlockKind {
+    /// The language of a code block: the first word of its info string
+    pub fn code_language(&self) -> Option<&str> {
+        match self {
+            BlockKind::CodeBlock { info, .. } => Some(info.split_whitespace().next().unwrap_or("")),
+            _ => None,
+        }
+    }
+}
+
+/// The text of inline content without any formatting
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) | Inline::Code(text) => out.push_str(text),
+            Inline::Emphasis(inner) | Inline::Strong(inner) | Inline::Link { content: inner, .. } => {
+                out.push_str(&plain_text(inner))
+            }
+            Inline::Image { alt, .. } => out.push_str(alt),
+            Inline::SoftBreak => out.push(' '),
+            Inline::HardBreak => out.push('\n'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Inline {
+        Inline::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_document_structure() {
+        let doc = parse(
+            "# Deploy\n\nRun the *checks* first:\n\n```bash\ncargo test\n```\n\n- one\n- two\n\n---\n> quoted\n",
+        );
+        let kinds: Vec<&BlockKind> = doc.blocks.iter().map(|block| &block.kind).collect();
+        assert_eq!(
+            kinds[0],
+            &BlockKind::Heading {
+                level: 1,
+                content: vec![text("Deploy")]
+            }
+        );
+        assert_eq!(
+            kinds[1],
+            &BlockKind::Paragraph(vec![
+                text("Run the "),
+                Inline::Emphasis(vec![text("checks")]),
+                text(" first:")
+            ])
+        );
+        assert_eq!(
+            kinds[2],
+            &BlockKind::CodeBlock {
+                info: "bash".to_string(),
+                code: "cargo test\n".to_string()
+            }
+        );
+        assert!(matches!(kinds[3], BlockKind::List(list) if list.items.len() == 2 && list.start.is_none()));
+        assert_eq!(kinds[4], &BlockKind::ThematicBreak);
+        assert!(matches!(kinds[5], BlockKind::BlockQuote(inner) if inner.len() == 1));
+        assert_eq!(doc.blocks[2].lines, 4..7);
+
+        assert_eq!(doc.title().as_deref(), Some("Deploy"));
+        assert_eq!(doc.code_blocks(), vec![("bash", "cargo test\n")]);
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let inlines = parse_inline("See **the [docs](https://example.com)** and `ls`\nnext");
+        assert_eq!(plain_text(&inlines), "See the docs and ls next");
+    }
+}
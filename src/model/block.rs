@@ -1,4 +1,4 @@
-use iced::{Element, Length, Padding, Color};
+use iced::{Element, Length, Padding};
 use iced::widget::{Text, Container, Column, Row, Scrollable, Rule};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 
 use uuid::Uuid;
 
+use crate::executor::command_corrections::CommandCorrection;
+use crate::model::theme::AppTheme;
 use crate::Message;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +16,12 @@ pub struct Block {
     pub content: BlockContent,
     pub metadata: BlockMetadata,
     pub bookmarked: bool,
+    /// Suggested fix when the command failed
+    #[serde(default)]
+    pub correction: Option<CommandCorrection>,
+    /// Command of the notebook cell this block shows, offered to run
+    #[serde(default)]
+    pub notebook_command: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +95,8 @@ impl Block {
                 shareable: true,
             },
             bookmarked: false,
+            correction: None,
+            notebook_command: None,
         }
     }
     
@@ -110,12 +120,23 @@ impl Block {
                 shareable: true,
             },
             bookmarked: false,
+            correction: None,
+            notebook_command: None,
+        }
+    }
+
+    /// A block showing Markdown, such as a notebook cell
+    pub fn new_markdown(content: String, notebook_command: Option<String>) -> Self {
+        Self {
+            notebook_command,
+            content: BlockContent::Markdown(content),
+            ..Self::new_command(String::new(), String::new())
         }
     }
 
     pub fn view(&self) -> Element<Message> {
         let content = match &self.content {
-            BlockContent::Command { input, output } => self.command_block_view(input, output),
+            BlockContent::Command { input, output } => self.command_block_view(input, output, &AppTheme::default()),
             BlockContent::Background { output, process_info, is_active, pid } => {
                 let mut col = Column::new();
                 if let Some(info) = process_info {
@@ -128,7 +149,9 @@ impl Block {
                 col = col.push(Text::new(output.clone()));
                 col.into()
             },
-            BlockContent::Markdown(content) => Text::new(content).into(),
+            BlockContent::Markdown(content) => {
+                crate::ui::markdown::view_markdown(content, iced::Font::DEFAULT, 16)
+            }
             BlockContent::FilePreview(path) => Text::new(format!("File: {}", path.display())).into(),
             BlockContent::Error(message) => Text::new(format!("Error: {}", message)).into(),
             BlockContent::Info(message) => Text::new(format!("Info: {}", message)).into(),
@@ -150,10 +173,8 @@ impl Block {
             },
             BlockContent::CodeSnippet { language, code, highlighted } => {
                 let mut col = Column::new();
-                col = col.push(Text::new("Code Snippet:"));
-                col = col.push(Text::new(format!("Language: {}", language)));
-                col = col.push(Text::new(code));
-                col = col.push(Text::new(format!("Highlighted: {}", highlighted)));
+                col = col.push(Text::new(format!("Code Snippet: {}", language)));
+                col = col.push(crate::ui::code::view_code(code, highlighted.then_some(language.as_str()), 14));
                 col.into()
             },
             BlockContent::ImagePreview { path, thumbnail: _ } => {
@@ -175,11 +196,11 @@ impl Block {
             .into()
     }
 
-    pub fn command_block_view<'a>(&self, input: &'a str, output: &'a str) -> Element<'a, Message> {
+    pub fn command_block_view<'a>(&self, input: &'a str, output: &'a str, theme: &AppTheme) -> Element<'a, Message> {
         let mut content = Column::new();
 
         // Command input with prompt
-        let prompt_text = Text::new("❯").style(Color::from_rgb(0.4, 0.8, 0.4));
+        let prompt_text = Text::new("❯").style(theme.readable_color(theme.terminal_color(2, false), theme.background_color()));
         let input_text = Text::new(input);
         let input_row = Row::new()
             .push(prompt_text)
@@ -194,10 +215,8 @@ impl Block {
 
         // Command output
         if !output.is_empty() {
-            let output_text = Text::new(output)
-                .size(13)
-                .style(Color::from_rgb(0.8, 0.8, 0.8));
-            
+            let output_text = crate::ui::terminal_output::view_output(output, theme, iced::Font::DEFAULT, 13);
+
             let output_container = Container::new(
                 Scrollable::new(output_text)
                     .height(Length::Shrink)
@@ -309,6 +328,11 @@ impl BlockManager {
         self.blocks.last().unwrap()
     }
 
+    pub fn add_markdown(&mut self, content: String, notebook_command: Option<String>) -> &Block {
+        self.blocks.push(Block::new_markdown(content, notebook_command));
+        self.blocks.last().unwrap()
+    }
+
     pub fn add_background_block(&mut self, process_info: Option<String>, pid: Option<u32>) -> &Block {
         let block = Block::new_background(process_info, pid);
         self.blocks.push(block);
@@ -344,6 +368,20 @@ impl BlockManager {
         }
     }
 
+    pub fn set_block_correction(&mut self, block_id: Uuid, correction: Option<CommandCorrection>) {
+        if let Some(block) = self.blocks.iter_mut().find(|b| b.id == block_id) {
+            block.correction = correction;
+        }
+    }
+
+    /// Remove and return a block's suggested correction
+    pub fn take_block_correction(&mut self, block_id: Uuid) -> Option<CommandCorrection> {
+        self.blocks
+            .iter_mut()
+            .find(|b| b.id == block_id)
+            .and_then(|block| block.correction.take())
+    }
+
     pub fn blocks(&self) -> &[Block] {
         &self.blocks
     }
@@ -66,6 +66,9 @@ pub struct Command {
     pub keywords: Vec<String>,
     pub enabled: bool,
     pub priority: u8, // 0-255, higher = more priority
+    /// Keymap action that runs this command, which makes it rebindable
+    #[serde(default)]
+    pub action: Option<String>,
 }
 
 impl Command {
@@ -100,6 +103,7 @@ impl Command {
             keywords,
             enabled: true,
             priority: 100, // Default medium priority
+            action: None,
         })
     }
 
@@ -135,6 +139,7 @@ pub struct CommandBuilder {
     keywords: Vec<String>,
     enabled: bool,
     priority: u8,
+    action: Option<String>,
 }
 
 impl CommandBuilder {
@@ -148,6 +153,7 @@ impl CommandBuilder {
             keywords: Vec::new(),
             enabled: true,
             priority: 100,
+            action: None,
         }
     }
 
@@ -186,6 +192,11 @@ impl CommandBuilder {
         self
     }
 
+    pub fn action(mut self, action: &str) -> Self {
+        self.action = Some(action.to_string());
+        self
+    }
+
     pub fn build(self) -> Result<Command, String> {
         Command::new(
             self.id,
@@ -198,6 +209,7 @@ impl CommandBuilder {
         .map(|mut cmd| {
             cmd.enabled = self.enabled;
             cmd.priority = self.priority;
+            cmd.action = self.action;
             cmd
         })
     }
@@ -558,6 +570,28 @@ impl CommandRegistry {
         self.commands.values().cloned().collect()
     }
 
+    /// Commands with a keymap action, by category and title
+    pub fn get_bindable_commands(&self) -> Vec<Command> {
+        let mut commands: Vec<Command> = self.commands.values().filter(|cmd| cmd.action.is_some()).cloned().collect();
+        commands.sort_by(|a, b| {
+            a.category
+                .display_name()
+                .cmp(b.category.display_name())
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        commands
+    }
+
+    /// Show `shortcut` for the command, e.g. after its key binding changed
+    pub fn set_shortcut(&mut self, command_id: &str, shortcut: &str) -> Result<(), String> {
+        if let Some(command) = self.commands.get_mut(command_id) {
+            command.shortcut = shortcut.to_string();
+            Ok(())
+        } else {
+            Err(format!("Command '{}' not found", command_id))
+        }
+    }
+
     pub fn get_categories(&self) -> Vec<CommandCategory> {
         self.categories.keys().cloned().collect()
     }
@@ -0,0 +1,132 @@
+//! Contrast - WCAG contrast ratios and readable color adjustment
+//!
+//! Ratios follow WCAG 2.x: relative luminance of the linearized sRGB
+//! channels, `(lighter + 0.05) / (darker + 0.05)`, from 1 (identical) to 21
+//! (black on white). [`ensure_contrast`] keeps a color's hue and saturation
+//! and moves only its HSL lightness until it reaches a ratio.
+
+use iced::Color;
+
+/// WCAG AA for body text, the ratio the renderer enforces
+pub const MIN_TEXT_CONTRAST: f32 = 4.5;
+
+/// WCAG AA for large text and UI components
+pub const MIN_UI_CONTRAST: f32 = 3.0;
+
+/// WCAG relative luminance, 0 for black to 1 for white
+pub fn relative_luminance(color: Color) -> f32 {
+    let linear = |channel: f32| {
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// Contrast ratio between two colors, in either order
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// `foreground` with its lightness moved just far enough to reach
+/// `min_ratio` against `background`. It gets lighter on backgrounds where
+/// white contrasts better than black, darker otherwise; when even the end
+/// of that range falls short, the end is returned. Alpha is kept.
+pub fn ensure_contrast(foreground: Color, background: Color, min_ratio: f32) -> Color {
+    if contrast_ratio(foreground, background) >= min_ratio {
+        return foreground;
+    }
+    let (hue, saturation, lightness) = to_hsl(foreground);
+    let with_lightness = |lightness: f32| Color {
+        a: foreground.a,
+        ..from_hsl(hue, saturation, lightness)
+    };
+    let lighten = contrast_ratio(Color::WHITE, background) >= contrast_ratio(Color::BLACK, background);
+    let end = if lighten { 1.0 } else { 0.0 };
+    if contrast_ratio(with_lightness(end), background) < min_ratio {
+        return with_lightness(end);
+    }
+
+    // Luminance only grows with lightness, so past the background the
+    // ratio does too: bisect for the closest lightness that passes
+    let (mut failing, mut passing) = (lightness, end);
+    for _ in 0..20 {
+        let mid = (failing + passing) / 2.0;
+        if contrast_ratio(with_lightness(mid), background) >= min_ratio {
+            passing = mid;
+        } else {
+            failing = mid;
+        }
+    }
+    with_lightness(passing)
+}
+
+/// Hue in degrees, saturation and lightness from 0 to 1
+fn to_hsl(color: Color) -> (f32, f32, f32) {
+    let max = color.r.max(color.g).max(color.b);
+    let min = color.r.min(color.g).min(color.b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == color.r {
+        60.0 * ((color.g - color.b) / delta).rem_euclid(6.0)
+    } else if max == color.g {
+        60.0 * ((color.b - color.r) / delta + 2.0)
+    } else {
+        60.0 * ((color.r - color.g) / delta + 4.0)
+    };
+    (hue, saturation.min(1.0), lightness)
+}
+
+fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Color::from_rgb((r + m).clamp(0.0, 1.0), (g + m).clamp(0.0, 1.0), (b + m).clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contrast_ratio() {
+        assert!((contrast_ratio(Color::BLACK, Color::WHITE) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(Color::WHITE, Color::WHITE) - 1.0).abs() < 0.01);
+        // #777777 on white is the classic just-failing AA gray
+        let gray = Color::from_rgb8(0x77, 0x77, 0x77);
+        assert!((contrast_ratio(gray, Color::WHITE) - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_ensure_contrast_keeps_hue() {
+        let background = Color::from_rgb8(0x1e, 0x1e, 0x1e);
+        let blue = Color::from_rgb8(0x00, 0x00, 0xaa);
+        let adjusted = ensure_contrast(blue, background, MIN_TEXT_CONTRAST);
+        let ratio = contrast_ratio(adjusted, background);
+        assert!((MIN_TEXT_CONTRAST..MIN_TEXT_CONTRAST + 0.1).contains(&ratio), "{}", ratio);
+        assert!((to_hsl(adjusted).0 - to_hsl(blue).0).abs() < 1.0);
+        assert!(adjusted.b > adjusted.r && adjusted.b > adjusted.g);
+
+        // Already readable colors are left alone, and light backgrounds darken
+        assert_eq!(ensure_contrast(Color::WHITE, background, MIN_TEXT_CONTRAST), Color::WHITE);
+        let yellow = Color::from_rgb8(0xe5, 0xc0, 0x7b);
+        let darker = ensure_contrast(yellow, Color::WHITE, MIN_TEXT_CONTRAST);
+        assert!(relative_luminance(darker) < relative_luminance(yellow));
+        assert!(contrast_ratio(darker, Color::WHITE) >= MIN_TEXT_CONTRAST);
+    }
+}
@@ -1,9 +1,17 @@
 pub mod block;
 pub mod command_registry;
+pub mod contrast;
 pub mod history;
 pub mod layout_persistence;
+pub mod notebook;
 pub mod pane;
+pub mod scrollback;
 pub mod synchronization;
 pub mod theme;
+pub mod theme_audit;
+pub mod theme_import;
+pub mod theme_loader;
+pub mod workflow_draft;
+pub mod workflow_lint;
 pub mod workflow_loader;
 pub mod workflow_params;
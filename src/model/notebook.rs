@@ -0,0 +1,439 @@
+//! Notebooks: Markdown runbooks whose shell code blocks run as commands.
+//!
+//! A notebook is a `.md` file. Fenced code blocks tagged with a shell
+//! language (`bash`, `sh`, `zsh`, `fish`, `shell` or `console`) are command
+//! cells that can be run one at a time; everything else is shown as
+//! Markdown. An optional YAML front matter block sets the title,
+//! description and tags used by search.
+//!
+//! Notebooks are read from `~/.warp/notebooks` and from `.warp/notebooks` in
+//! the current repository, so runbooks can be versioned with the project
+//! they describe.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use crate::markdown_parser::{self, BlockKind};
+
+/// Code block languages that make a command cell
+pub const SHELL_LANGUAGES: &[&str] = &["bash", "sh", "zsh", "fish", "shell", "console"];
+
+/// File extension of notebooks
+pub const NOTEBOOK_EXTENSION: &str = "md";
+
+/// Prompt marking the commands of a `console` block
+const CONSOLE_PROMPT: &str = "$ ";
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum NotebookError {
+    #[error("Could not read notebook {}: {message}", path.display())]
+    Read { path: PathBuf, message: String },
+    #[error("Could not write notebook {}: {message}", path.display())]
+    Write { path: PathBuf, message: String },
+    #[error("No notebook directory is configured")]
+    NoDirectory,
+}
+
+/// Front matter of a notebook
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotebookMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl NotebookMeta {
+    fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.tags.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotebookCell {
+    /// Markdown source shown as is
+    Markdown(String),
+    /// A shell code block
+    Command { language: String, code: String },
+}
+
+impl NotebookCell {
+    /// The command a cell runs. For `console` blocks only the lines after a
+    /// `$ ` prompt are commands; the rest is sample output.
+    pub fn command(&self) -> Option<String> {
+        let NotebookCell::Command { language, code } = self else {
+            return None;
+        };
+        let command = if language == "console" && code.lines().any(|line| line.starts_with(CONSOLE_PROMPT)) {
+            code.lines()
+                .filter_map(|line| line.strip_prefix(CONSOLE_PROMPT))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            code.trim_end().to_string()
+        };
+        Some(command)
+    }
+
+    /// The cell as Markdown source
+    pub fn to_markdown(&self) -> String {
+        match self {
+            NotebookCell::Markdown(text) => text.clone(),
+            NotebookCell::Command { language, code } => {
+                // Use a longer fence than any backtick run in the code
+                let longest = code
+                    .lines()
+                    .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+                    .max()
+                    .unwrap_or(0);
+                let fence = "`".repeat(longest.max(2) + 1);
+                format!("{fence}{language}\n{}\n{fence}", code.trim_end_matches('\n'))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notebook {
+    pub meta: NotebookMeta,
+    pub cells: Vec<NotebookCell>,
+    /// File the notebook was loaded from or saved to
+    pub path: Option<PathBuf>,
+}
+
+impl Notebook {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            meta: NotebookMeta {
+                title: Some(title.into()),
+                ..NotebookMeta::default()
+            },
+            cells: Vec::new(),
+            path: None,
+        }
+    }
+
+    pub fn with_cells(mut self, cells: Vec<NotebookCell>) -> Self {
+        self.cells = cells;
+        self
+    }
+
+    /// Split Markdown source into cells
+    pub fn parse(source: &str) -> Self {
+        let (meta, body) = split_front_matter(source);
+        let lines: Vec<&str> = body.lines().collect();
+        let document = markdown_parser::parse(body);
+
+        let mut cells = Vec::new();
+        let mut markdown_start = 0;
+        let push_markdown = |cells: &mut Vec<NotebookCell>, lines: &[&str]| {
+            let text = lines.join("\n");
+            let text = text.trim_matches('\n');
+            if !text.trim().is_empty() {
+                cells.push(NotebookCell::Markdown(text.to_string()));
+            }
+        };
+        for block in &document.blocks {
+            let BlockKind::CodeBlock { info, code } = &block.kind else {
+                continue;
+            };
+            let language = block.kind.code_language().unwrap_or_default();
+            if info.is_empty() || !SHELL_LANGUAGES.contains(&language) {
+                continue;
+            }
+            push_markdown(&mut cells, &lines[markdown_start..block.lines.start]);
+            cells.push(NotebookCell::Command {
+                language: language.to_string(),
+                code: code.clone(),
+            });
+            markdown_start = block.lines.end;
+        }
+        push_markdown(&mut cells, &lines[markdown_start.min(lines.len())..]);
+
+        Self {
+            meta,
+            cells,
+            path: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, NotebookError> {
+        let source = std::fs::read_to_string(path).map_err(|e| NotebookError::Read {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Self::parse(&source)
+        })
+    }
+
+    /// The notebook as Markdown, front matter first
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        if !self.meta.is_empty() {
+            let yaml = serde_yaml::to_string(&self.meta).unwrap_or_default();
+            out.push_str("---\n");
+            out.push_str(&yaml);
+            out.push_str("---\n\n");
+        }
+        let cells: Vec<String> = self.cells.iter().map(NotebookCell::to_markdown).collect();
+        out.push_str(&cells.join("\n\n"));
+        out.push('\n');
+        out
+    }
+
+    /// Title from the front matter, the first `#` heading or the file name
+    pub fn title(&self) -> String {
+        if let Some(title) = &self.meta.title {
+            return title.clone();
+        }
+        let heading = self.cells.iter().find_map(|cell| match cell {
+            NotebookCell::Markdown(text) => markdown_parser::parse(text).title(),
+            NotebookCell::Command { .. } => None,
+        });
+        heading
+            .or_else(|| {
+                self.path
+                    .as_ref()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "Untitled notebook".to_string())
+    }
+
+    /// Indices and commands of the command cells
+    pub fn commands(&self) -> Vec<(usize, String)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cell)| cell.command().map(|command| (index, command)))
+            .collect()
+    }
+}
+
+impl From<&Notebook> for crate::command::search::Notebook {
+    fn from(notebook: &Notebook) -> Self {
+        Self {
+            name: notebook.title(),
+            commands: notebook.commands().into_iter().map(|(_, command)| command).collect(),
+            description: notebook.meta.description.clone(),
+            tags: notebook.meta.tags.clone(),
+        }
+    }
+}
+
+/// Front matter between `---` lines at the very start, and the rest
+fn split_front_matter(source: &str) -> (NotebookMeta, &str) {
+    let Some(rest) = source.strip_prefix("---\n").or_else(|| source.strip_prefix("---\r\n")) else {
+        return (NotebookMeta::default(), source);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            return match serde_yaml::from_str::<Option<NotebookMeta>>(yaml) {
+                Ok(meta) => (meta.unwrap_or_default(), body),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid notebook front matter: {}", e);
+                    (NotebookMeta::default(), source)
+                }
+            };
+        }
+        offset += line.len();
+    }
+    (NotebookMeta::default(), source)
+}
+
+/// The directories notebooks are read from
+#[derive(Debug, Clone, Default)]
+pub struct NotebookStore {
+    dirs: Vec<PathBuf>,
+}
+
+impl NotebookStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The user's notebooks and those of the repository containing `cwd`
+    pub fn with_default_dirs(cwd: &Path) -> Self {
+        let mut store = Self::new();
+        if let Some(user) = Self::user_notebooks_dir() {
+            store = store.with_dir(user);
+        }
+        if let Some(repo) = Self::find_repo_notebooks(cwd) {
+            store = store.with_dir(repo);
+        }
+        store
+    }
+
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// `~/.warp/notebooks`, where new notebooks are saved
+    pub fn user_notebooks_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".warp").join("notebooks"))
+    }
+
+    /// `.warp/notebooks` in `cwd` or the nearest parent, stopping at the
+    /// repository root
+    pub fn find_repo_notebooks(cwd: &Path) -> Option<PathBuf> {
+        for dir in cwd.ancestors() {
+            let candidate = dir.join(".warp").join("notebooks");
+            if candidate.is_dir() {
+                return Some(candidate);
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Every notebook in the store's directories, sorted by path. Files
+    /// that cannot be read are logged and skipped.
+    pub fn list(&self) -> Vec<Notebook> {
+        let mut notebooks = Vec::new();
+        for dir in &self.dirs {
+            let files = WalkDir::new(dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    entry.file_type().is_file()
+                        && entry.path().extension().and_then(|ext| ext.to_str()) == Some(NOTEBOOK_EXTENSION)
+                });
+            for entry in files {
+                match Notebook::load(entry.path()) {
+                    Ok(notebook) => notebooks.push(notebook),
+                    Err(e) => tracing::warn!("{}", e),
+                }
+            }
+        }
+        notebooks
+    }
+
+    /// Write a notebook back to its file, or to a new file in `dir` named
+    /// after its title. Returns the path written.
+    pub fn save(notebook: &mut Notebook, dir: Option<&Path>) -> Result<PathBuf, NotebookError> {
+        let path = match (&notebook.path, dir) {
+            (Some(path), _) => path.clone(),
+            (None, Some(dir)) => {
+                let stem = file_stem(&notebook.title());
+                (1..)
+                    .map(|n| match n {
+                        1 => dir.join(format!("{}.{}", stem, NOTEBOOK_EXTENSION)),
+                        n => dir.join(format!("{}_{}.{}", stem, n, NOTEBOOK_EXTENSION)),
+                    })
+                    .find(|path| !path.exists())
+                    .expect("some numbered file name is free")
+            }
+            (None, None) => return Err(NotebookError::NoDirectory),
+        };
+        let write_error = |e: std::io::Error| NotebookError::Write {
+            path: path.clone(),
+            message: e.to_string(),
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(write_error)?;
+        }
+        std::fs::write(&path, notebook.to_markdown()).map_err(write_error)?;
+        notebook.path = Some(path.clone());
+        Ok(path)
+    }
+}
+
+/// Snake-case file name for a notebook title
+fn file_stem(title: &str) -> String {
+    let stem = title
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if stem.is_empty() {
+        "notebook".to_string()
+    } else {
+        stem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RUNBOOK: &str = "---\ndescription: Restart the API\ntags: [ops]\n---\n# Restart\n\nCheck status first.\n\n```bash\nsystemctl status api\n```\n\n```json\n{\"not\": \"a command\"}\n```\n\n```console\n$ systemctl restart api\nok\n```\n";
+
+    #[test]
+    fn test_parse_cells() {
+        let notebook = Notebook::parse(RUNBOOK);
+        assert_eq!(notebook.meta.description.as_deref(), Some("Restart the API"));
+        assert_eq!(notebook.meta.tags, vec!["ops".to_string()]);
+        assert_eq!(notebook.title(), "Restart");
+        assert_eq!(
+            notebook.cells,
+            vec![
+                NotebookCell::Markdown("# Restart\n\nCheck status first.".to_string()),
+                NotebookCell::Command {
+                    language: "bash".to_string(),
+                    code: "systemctl status api\n".to_string()
+                },
+                NotebookCell::Markdown("```json\n{\"not\": \"a command\"}\n```".to_string()),
+                NotebookCell::Command {
+                    language: "console".to_string(),
+                    code: "$ systemctl restart api\nok\n".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            notebook.commands(),
+            vec![
+                (1, "systemctl status api".to_string()),
+                (3, "systemctl restart api".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_round_trip() {
+        let notebook = Notebook::parse(RUNBOOK);
+        let reparsed = Notebook::parse(&notebook.to_markdown());
+        assert_eq!(reparsed, notebook);
+
+        let plain = Notebook::parse("Just text\n");
+        assert_eq!(plain.to_markdown(), "Just text\n");
+    }
+
+    #[test]
+    fn test_store_lists_and_saves() {
+        let dir = TempDir::new().unwrap();
+        let mut notebook = Notebook::new("Deploy steps").with_cells(vec![NotebookCell::Command {
+            language: "sh".to_string(),
+            code: "make deploy\n".to_string(),
+        }]);
+        let path = NotebookStore::save(&mut notebook, Some(dir.path())).unwrap();
+        assert_eq!(path, dir.path().join("deploy_steps.md"));
+        std::fs::write(dir.path().join("notes.txt"), "not a notebook").unwrap();
+
+        let notebooks = NotebookStore::new().with_dir(dir.path()).list();
+        assert_eq!(notebooks.len(), 1);
+        assert_eq!(notebooks[0].title(), "Deploy steps");
+        assert_eq!(notebooks[0].path.as_deref(), Some(path.as_path()));
+        assert_eq!(notebooks[0].commands(), vec![(0, "make deploy".to_string())]);
+    }
+}
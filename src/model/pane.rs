@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use iced::{Element, Border, Length};
 use iced::widget::{container, row, column, text, mouse_area};
@@ -18,6 +19,15 @@ pub enum PaneSize {
     Auto,
 }
 
+/// What a pane shows
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum PaneKind {
+    #[default]
+    Terminal,
+    /// A file open in an editor; the buffer itself lives with the app
+    Editor { path: PathBuf },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pane {
     pub id: Uuid,
@@ -32,6 +42,8 @@ pub struct Pane {
     pub is_focused: bool,
     pub shell_type: String,
     pub env_vars: HashMap<String, String>,
+    #[serde(default)]
+    pub kind: PaneKind,
 }
 
 impl Pane {
@@ -49,6 +61,26 @@ impl Pane {
             is_focused: false,
             shell_type: std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string()),
             env_vars: HashMap::new(),
+            kind: PaneKind::Terminal,
+        }
+    }
+
+    /// A pane editing the file at `path`
+    pub fn editor(path: PathBuf) -> Self {
+        let title = path
+            .file_name()
+            .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        Self {
+            kind: PaneKind::Editor { path },
+            ..Self::new(title)
+        }
+    }
+
+    /// The file this pane edits, if it is an editor
+    pub fn editor_path(&self) -> Option<&Path> {
+        match &self.kind {
+            PaneKind::Editor { path } => Some(path),
+            PaneKind::Terminal => None,
         }
     }
 
@@ -62,11 +94,14 @@ impl Pane {
             }
         ];
 
-        let content = column![
-            header,
-            text(format!("Working Dir: {}", self.working_directory.display())).size(10),
-            text(format!("Shell: {}", self.shell_type)).size(10),
-        ];
+        let content = match &self.kind {
+            PaneKind::Terminal => column![
+                header,
+                text(format!("Working Dir: {}", self.working_directory.display())).size(10),
+                text(format!("Shell: {}", self.shell_type)).size(10),
+            ],
+            PaneKind::Editor { path } => column![header, text(format!("Editing: {}", path.display())).size(10)],
+        };
 
         container(content)
             .padding(8)
@@ -199,6 +234,29 @@ impl SplitLayout {
         None
     }
 
+    pub fn find_pane(&self, pane_id: Uuid) -> Option<&Pane> {
+        self.children.iter().find_map(|node| match node {
+            SplitNode::Pane(pane) => (pane.id == pane_id).then_some(pane),
+            SplitNode::Layout(layout) => layout.find_pane(pane_id),
+        })
+    }
+
+    /// Every pane, in the order they are laid out
+    pub fn panes(&self) -> Vec<&Pane> {
+        let mut panes = Vec::new();
+        Self::collect_panes(&self.children, &mut panes);
+        panes
+    }
+
+    fn collect_panes<'a>(nodes: &'a [SplitNode], panes: &mut Vec<&'a Pane>) {
+        for node in nodes {
+            match node {
+                SplitNode::Pane(pane) => panes.push(pane),
+                SplitNode::Layout(layout) => Self::collect_panes(&layout.children, panes),
+            }
+        }
+    }
+
     pub fn set_active_pane(&mut self, pane_id: Uuid) {
         self.active_pane = Some(pane_id);
         Self::update_focus_recursive_static(pane_id, &mut self.children);
@@ -421,6 +479,33 @@ impl PaneManager {
         }
     }
 
+    /// Focus the editor pane for `path`, or split the active pane to open
+    /// one. Returns the pane's id.
+    pub fn open_editor(&mut self, path: PathBuf) -> Result<Uuid, String> {
+        if let Some(pane) = self.editor_pane(&path) {
+            let pane_id = pane.id;
+            self.root_layout.set_active_pane(pane_id);
+            return Ok(pane_id);
+        }
+        let active_id = match self.root_layout.active_pane {
+            Some(id) => id,
+            None => self.root_layout.find_first_pane_id().ok_or("No active pane")?,
+        };
+        let new_pane = Pane::editor(path);
+        let new_pane_id = new_pane.id;
+        self.root_layout.split_pane(active_id, SplitDirection::Horizontal, new_pane)?;
+        self.root_layout.set_active_pane(new_pane_id);
+        Ok(new_pane_id)
+    }
+
+    pub fn editor_pane(&self, path: &Path) -> Option<&Pane> {
+        self.root_layout.panes().into_iter().find(|pane| pane.editor_path() == Some(path))
+    }
+
+    pub fn active_pane(&self) -> Option<&Pane> {
+        self.root_layout.find_pane(self.root_layout.active_pane?)
+    }
+
     pub fn close_current_pane(&mut self) -> Result<(), String> {
         if let Some(active_id) = self.root_layout.active_pane {
             self.root_layout.close_pane(active_id)
@@ -535,3 +620,32 @@ impl Default for PaneManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_editor_splits_once_per_file() {
+        let mut manager = PaneManager::new();
+        let terminal_id = manager.root_layout.active_pane.unwrap();
+
+        let editor_id = manager.open_editor(PathBuf::from("/tmp/main.rs")).unwrap();
+        assert_eq!(manager.active_pane().unwrap().title, "main.rs");
+        assert_eq!(manager.active_pane().unwrap().editor_path(), Some(Path::new("/tmp/main.rs")));
+        assert_eq!(manager.collect_all_pane_ids(), vec![terminal_id, editor_id]);
+
+        manager.focus_next_pane();
+        assert_eq!(manager.open_editor(PathBuf::from("/tmp/main.rs")).unwrap(), editor_id);
+        assert_eq!(manager.root_layout.active_pane, Some(editor_id));
+        assert_eq!(manager.collect_all_pane_ids().len(), 2);
+    }
+
+    #[test]
+    fn test_layouts_saved_before_pane_kinds_still_load() {
+        let mut value = serde_json::to_value(Pane::new("Terminal 1".to_string())).unwrap();
+        value.as_object_mut().unwrap().remove("kind");
+        let pane: Pane = serde_json::from_value(value).unwrap();
+        assert_eq!(pane.kind, PaneKind::Terminal);
+    }
+}
@@ -0,0 +1,227 @@
+//! Terminal output kept line by line in a [`SumTree`].
+//!
+//! Each line carries its size in bytes, chars and display columns, so the
+//! store knows its widest line for horizontal scrolling, can hand out the
+//! visible rows without walking the ones above them and maps byte offsets
+//! in the joined text, such as search hits, back to rows. Past its limit
+//! the oldest lines are dropped.
+
+use std::ops::Range;
+
+use crate::string_offset;
+use crate::sum_tree::{self, Bias, Dimension, SumTree};
+
+/// Lines a [`Scrollback`] keeps unless told otherwise
+pub const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+/// Sizes of a run of lines. Bytes and chars count the newline each line
+/// ends with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineSummary {
+    pub lines: usize,
+    pub bytes: usize,
+    pub chars: usize,
+    /// Display columns of the widest line
+    pub max_width: usize,
+}
+
+impl sum_tree::Summary for LineSummary {
+    fn add_summary(&mut self, other: &Self) {
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+        self.chars += other.chars;
+        self.max_width = self.max_width.max(other.max_width);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Rows(usize);
+
+impl Dimension<LineSummary> for Rows {
+    fn add_summary(&mut self, summary: &LineSummary) {
+        self.0 += summary.lines;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Bytes(usize);
+
+impl Dimension<LineSummary> for Bytes {
+    fn add_summary(&mut self, summary: &LineSummary) {
+        self.0 += summary.bytes;
+    }
+}
+
+/// One line of output, without its newline, tagged with a `kind` such as
+/// whether it came from stdout or stderr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line<K> {
+    text: String,
+    pub kind: K,
+    summary: LineSummary,
+}
+
+impl<K> Line<K> {
+    pub fn new(text: String, kind: K) -> Self {
+        let summary = LineSummary {
+            lines: 1,
+            bytes: text.len() + 1,
+            chars: text.chars().count() + 1,
+            max_width: string_offset::display_width(&text),
+        };
+        Self { text, kind, summary }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Display columns the line takes
+    pub fn width(&self) -> usize {
+        self.summary.max_width
+    }
+}
+
+impl<K: Clone> sum_tree::Item for Line<K> {
+    type Summary = LineSummary;
+
+    fn summary(&self) -> LineSummary {
+        self.summary
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scrollback<K: Clone = ()> {
+    lines: SumTree<Line<K>>,
+    limit: usize,
+}
+
+impl<K: Clone> Default for Scrollback<K> {
+    fn default() -> Self {
+        Self {
+            lines: SumTree::new(),
+            limit: DEFAULT_SCROLLBACK_LINES,
+        }
+    }
+}
+
+impl<K: Clone> Scrollback<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `limit` lines
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self.trim();
+        self
+    }
+
+    /// Add `text` as one line per `\n`-separated piece. A trailing newline
+    /// ends the last line rather than starting an empty one.
+    pub fn push(&mut self, text: &str, kind: K) {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        self.lines.extend(text.split('\n').map(|line| Line::new(line.to_string(), kind.clone())));
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        if let Some(excess) = self.lines.len().checked_sub(self.limit) {
+            self.lines = self.lines.split_at(excess).1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines = SumTree::new();
+    }
+
+    pub fn summary(&self) -> LineSummary {
+        *self.lines.summary()
+    }
+
+    /// Number of lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Display columns of the widest line
+    pub fn max_width(&self) -> usize {
+        self.summary().max_width
+    }
+
+    pub fn line(&self, row: usize) -> Option<&Line<K>> {
+        self.lines.get(row)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Line<K>> {
+        self.lines.iter()
+    }
+
+    /// The lines at `rows`, such as the ones in view
+    pub fn rows(&self, rows: Range<usize>) -> impl Iterator<Item = &Line<K>> {
+        self.lines.iter_from(rows.start).take(rows.len())
+    }
+
+    /// Offset in [`Self::text`] where `row` starts; rows past the end map
+    /// to its length
+    pub fn offset_of_row(&self, row: usize) -> usize {
+        self.lines
+            .find(&Rows(row), Bias::Right)
+            .map_or(self.summary().bytes, |found| found.before.bytes)
+    }
+
+    /// The row holding the byte at `offset` in [`Self::text`], its
+    /// newline included
+    pub fn row_at_offset(&self, offset: usize) -> usize {
+        self.lines
+            .find(&Bytes(offset), Bias::Right)
+            .map_or(self.len(), |found| found.index)
+    }
+
+    /// Every line, each ending with a newline
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.summary().bytes);
+        for line in self.iter() {
+            text.push_str(&line.text);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_trim_and_seek() {
+        let mut scrollback = Scrollback::new().with_limit(50);
+        for n in 0..80 {
+            scrollback.push(&format!("line {n}\n{}\n", "日".repeat(n % 7)), n % 2 == 0);
+        }
+        assert_eq!(scrollback.len(), 50, "the oldest lines are dropped");
+        assert_eq!(scrollback.line(0).unwrap().text(), "line 55");
+        assert!(!scrollback.line(0).unwrap().kind);
+        assert_eq!(scrollback.max_width(), 12);
+
+        let text = scrollback.text();
+        assert_eq!(scrollback.summary().bytes, text.len());
+        assert_eq!(scrollback.summary().chars, text.chars().count());
+        for (row, line) in text.lines().enumerate() {
+            let start = scrollback.offset_of_row(row);
+            assert_eq!(&text[start..start + line.len()], line);
+            assert_eq!(scrollback.row_at_offset(start), row);
+            assert_eq!(scrollback.row_at_offset(start + line.len()), row, "the newline");
+        }
+        assert_eq!(scrollback.offset_of_row(50), text.len());
+
+        let visible: Vec<_> = scrollback.rows(10..13).map(Line::text).collect();
+        assert_eq!(visible, text.lines().skip(10).take(3).collect::<Vec<_>>());
+        scrollback.clear();
+        assert!(scrollback.is_empty());
+    }
+}
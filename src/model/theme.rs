@@ -1,31 +1,173 @@
+//! Application theme: UI colors, background fill and the ANSI palette used
+//! for command output. Themes are read from YAML files by
+//! [`crate::model::theme_loader`]; [`AppTheme::default`] is the built-in
+//! dark theme used until one is loaded.
+//!
+//! With a minimum contrast set, [`AppTheme::readable_color`] adjusts
+//! output colors that would be hard to read on their background.
+
+use crate::model::contrast::ensure_contrast;
+use iced::gradient::Linear;
+use iced::theme::Palette;
 use iced::Theme as IcedTheme;
-use iced::Color;
+use iced::{Background, Color, Gradient, Radians};
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+/// A solid color or a two-stop gradient. Backgrounds run top to bottom,
+/// accents left to right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThemeFill {
+    Solid(Color),
+    Gradient { from: Color, to: Color },
+}
+
+impl ThemeFill {
+    /// The color to use where a gradient cannot be drawn: the first stop
+    pub fn color(&self) -> Color {
+        match self {
+            ThemeFill::Solid(color) => *color,
+            ThemeFill::Gradient { from, .. } => *from,
+        }
+    }
+
+    /// The same fill with every color's alpha scaled by `alpha`
+    pub fn with_alpha(&self, alpha: f32) -> Self {
+        let fade = |color: Color| Color {
+            a: color.a * alpha,
+            ..color
+        };
+        match self {
+            ThemeFill::Solid(color) => ThemeFill::Solid(fade(*color)),
+            ThemeFill::Gradient { from, to } => ThemeFill::Gradient {
+                from: fade(*from),
+                to: fade(*to),
+            },
+        }
+    }
+
+    /// Fill running from `from` to `to` at `angle`
+    pub fn to_background(&self, angle: Radians) -> Background {
+        match self {
+            ThemeFill::Solid(color) => Background::Color(*color),
+            ThemeFill::Gradient { from, to } => {
+                Background::Gradient(Gradient::Linear(Linear::new(angle).add_stop(0.0, *from).add_stop(1.0, *to)))
+            }
+        }
+    }
+}
+
+/// Whether UI details (overlays, hover states) are drawn darker or lighter
+/// than the background. Dark themes use `Darker`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeDetails {
+    #[default]
+    Darker,
+    Lighter,
+}
+
+/// An image drawn behind the terminal content
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundImage {
+    pub path: PathBuf,
+    /// 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+}
+
+/// The 16 ANSI colors. Indexes above 15 map onto the xterm 256-color cube
+/// and grayscale ramp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnsiPalette {
+    /// Black, red, green, yellow, blue, magenta, cyan, white
+    pub normal: [Color; 8],
+    pub bright: [Color; 8],
+}
+
+impl Default for AnsiPalette {
+    fn default() -> Self {
+        Self {
+            normal: [
+                Color::from_rgb(0.2, 0.2, 0.2),
+                Color::from_rgb(0.8, 0.3, 0.3),
+                Color::from_rgb(0.3, 0.8, 0.3),
+                Color::from_rgb(0.8, 0.8, 0.3),
+                Color::from_rgb(0.3, 0.3, 0.8),
+                Color::from_rgb(0.8, 0.3, 0.8),
+                Color::from_rgb(0.3, 0.8, 0.8),
+                Color::from_rgb(0.8, 0.8, 0.8),
+            ],
+            bright: [
+                Color::from_rgb(0.6, 0.6, 0.6),
+                Color::from_rgb(1.0, 0.4, 0.4),
+                Color::from_rgb(0.4, 1.0, 0.4),
+                Color::from_rgb(1.0, 1.0, 0.4),
+                Color::from_rgb(0.4, 0.4, 1.0),
+                Color::from_rgb(1.0, 0.4, 1.0),
+                Color::from_rgb(0.4, 1.0, 1.0),
+                Color::WHITE,
+            ],
+        }
+    }
+}
+
+impl AnsiPalette {
+    /// Color for a 256-color index
+    pub fn color(&self, index: u8) -> Color {
+        match index {
+            0..=7 => self.normal[index as usize],
+            8..=15 => self.bright[index as usize - 8],
+            16..=231 => {
+                let cube = index - 16;
+                let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+                Color::from_rgb8(level(cube / 36), level((cube / 6) % 6), level(cube % 6))
+            }
+            232..=255 => {
+                let gray = 8 + (index - 232) * 10;
+                Color::from_rgb8(gray, gray, gray)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct AppTheme {
-    #[allow(dead_code)]
     name: String,
-    background_color: Color,
+    background: ThemeFill,
     text_color: Color,
-    accent_color: Color,
+    accent: ThemeFill,
     cursor_color: Color,
+    details: ThemeDetails,
+    background_image: Option<BackgroundImage>,
+    ansi: AnsiPalette,
+    minimum_contrast: Option<f32>,
 }
 
 impl Default for AppTheme {
     fn default() -> Self {
         Self {
             name: "Dark".to_string(),
-            background_color: Color::from_rgb(0.07, 0.07, 0.07),
+            background: ThemeFill::Solid(Color::from_rgb(0.07, 0.07, 0.07)),
             text_color: Color::WHITE,
-            accent_color: Color::from_rgb(0.2, 0.6, 1.0),
+            accent: ThemeFill::Solid(Color::from_rgb(0.2, 0.6, 1.0)),
             cursor_color: Color::from_rgb(0.3, 0.7, 1.0),
+            details: ThemeDetails::Darker,
+            background_image: None,
+            ansi: AnsiPalette::default(),
+            minimum_contrast: None,
         }
     }
 }
 
 impl From<AppTheme> for IcedTheme {
-    fn from(_theme: AppTheme) -> Self {
-        IcedTheme::Dark
+    fn from(theme: AppTheme) -> Self {
+        let palette = Palette {
+            background: theme.background_color(),
+            text: theme.text_color,
+            primary: theme.accent_color(),
+            success: theme.ansi.normal[2],
+            danger: theme.ansi.normal[1],
+        };
+        IcedTheme::custom(theme.name, palette)
     }
 }
 
@@ -34,8 +176,66 @@ impl AppTheme {
         Self::default()
     }
 
+    pub fn new(
+        name: impl Into<String>,
+        background: ThemeFill,
+        text_color: Color,
+        accent: ThemeFill,
+        details: ThemeDetails,
+        ansi: AnsiPalette,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            background,
+            text_color,
+            accent,
+            cursor_color: accent.color(),
+            details,
+            background_image: None,
+            ansi,
+            minimum_contrast: None,
+        }
+    }
+
+    pub fn with_background_image(mut self, image: BackgroundImage) -> Self {
+        self.background_image = Some(image);
+        self
+    }
+
+    /// Contrast ratio that output colors are adjusted to reach, if any
+    pub fn with_minimum_contrast(mut self, ratio: Option<f32>) -> Self {
+        self.minimum_contrast = ratio;
+        self
+    }
+
+    pub fn minimum_contrast(&self) -> Option<f32> {
+        self.minimum_contrast
+    }
+
+    /// `foreground` as drawn on `background`: unchanged, or with its
+    /// lightness nudged to the minimum contrast when one is set
+    pub fn readable_color(&self, foreground: Color, background: Color) -> Color {
+        match self.minimum_contrast {
+            Some(ratio) => ensure_contrast(foreground, background, ratio),
+            None => foreground,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     pub fn background_color(&self) -> iced::Color {
-        self.background_color
+        self.background.color()
+    }
+
+    pub fn background(&self) -> ThemeFill {
+        self.background
+    }
+
+    /// The window background, drawn top to bottom
+    pub fn background_fill(&self) -> Background {
+        self.background.to_background(Radians(PI))
     }
 
     pub fn text_color(&self) -> iced::Color {
@@ -43,33 +243,90 @@ impl AppTheme {
     }
 
     pub fn accent_color(&self) -> iced::Color {
-        self.accent_color
+        self.accent.color()
+    }
+
+    pub fn accent(&self) -> ThemeFill {
+        self.accent
+    }
+
+    /// The accent as a fill drawn left to right
+    pub fn accent_fill(&self) -> Background {
+        self.accent.to_background(Radians(PI / 2.0))
     }
 
     pub fn cursor_color(&self) -> iced::Color {
         self.cursor_color
     }
 
+    pub fn details(&self) -> ThemeDetails {
+        self.details
+    }
+
+    pub fn is_dark(&self) -> bool {
+        self.details == ThemeDetails::Darker
+    }
+
+    pub fn background_image(&self) -> Option<&BackgroundImage> {
+        self.background_image.as_ref()
+    }
+
+    pub fn ansi_palette(&self) -> &AnsiPalette {
+        &self.ansi
+    }
+
     pub fn terminal_color(&self, index: u8, bright: bool) -> iced::Color {
-        // Simple terminal color mapping
         match (index, bright) {
-            (0, false) => Color::from_rgb(0.2, 0.2, 0.2), // Black
-            (0, true) => Color::from_rgb(0.6, 0.6, 0.6),  // Bright Black
-            (1, false) => Color::from_rgb(0.8, 0.3, 0.3), // Red
-            (1, true) => Color::from_rgb(1.0, 0.4, 0.4),  // Bright Red
-            (2, false) => Color::from_rgb(0.3, 0.8, 0.3), // Green
-            (2, true) => Color::from_rgb(0.4, 1.0, 0.4),  // Bright Green
-            (3, false) => Color::from_rgb(0.8, 0.8, 0.3), // Yellow
-            (3, true) => Color::from_rgb(1.0, 1.0, 0.4),  // Bright Yellow
-            (4, false) => Color::from_rgb(0.3, 0.3, 0.8), // Blue
-            (4, true) => Color::from_rgb(0.4, 0.4, 1.0),  // Bright Blue
-            (5, false) => Color::from_rgb(0.8, 0.3, 0.8), // Magenta
-            (5, true) => Color::from_rgb(1.0, 0.4, 1.0),  // Bright Magenta
-            (6, false) => Color::from_rgb(0.3, 0.8, 0.8), // Cyan
-            (6, true) => Color::from_rgb(0.4, 1.0, 1.0),  // Bright Cyan
-            (7, false) => Color::from_rgb(0.8, 0.8, 0.8), // White
-            (7, true) => Color::WHITE,                     // Bright White
+            (0..=7, false) => self.ansi.normal[index as usize],
+            (0..=7, true) => self.ansi.bright[index as usize],
             _ => self.text_color,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_indexes() {
+        let palette = AnsiPalette::default();
+        assert_eq!(palette.color(1), palette.normal[1]);
+        assert_eq!(palette.color(9), palette.bright[1]);
+        assert_eq!(palette.color(16), Color::from_rgb8(0, 0, 0));
+        assert_eq!(palette.color(196), Color::from_rgb8(255, 0, 0));
+        assert_eq!(palette.color(232), Color::from_rgb8(8, 8, 8));
+        assert_eq!(palette.color(255), Color::from_rgb8(238, 238, 238));
+    }
+
+    #[test]
+    fn test_iced_theme_uses_theme_colors() {
+        let accent = Color::from_rgb8(0xbd, 0x93, 0xf9);
+        let theme = AppTheme::new(
+            "Dracula",
+            ThemeFill::Solid(Color::from_rgb8(0x28, 0x2a, 0x36)),
+            Color::from_rgb8(0xf8, 0xf8, 0xf2),
+            ThemeFill::Gradient { from: accent, to: Color::WHITE },
+            ThemeDetails::Darker,
+            AnsiPalette::default(),
+        );
+        let iced_theme: IcedTheme = theme.into();
+        let palette = iced_theme.palette();
+        assert_eq!(palette.primary, accent);
+        assert_eq!(palette.background, Color::from_rgb8(0x28, 0x2a, 0x36));
+        assert_eq!(iced_theme.to_string(), "Dracula");
+    }
+
+    #[test]
+    fn test_readable_color_only_with_minimum_contrast() {
+        let theme = AppTheme::default();
+        let blue = theme.terminal_color(4, false);
+        let background = theme.background_color();
+        assert_eq!(theme.readable_color(blue, background), blue);
+
+        let theme = theme.with_minimum_contrast(Some(crate::model::contrast::MIN_TEXT_CONTRAST));
+        let readable = theme.readable_color(blue, background);
+        assert_ne!(readable, blue);
+        assert!(crate::model::contrast::contrast_ratio(readable, background) >= crate::model::contrast::MIN_TEXT_CONTRAST);
+    }
+}
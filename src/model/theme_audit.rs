@@ -0,0 +1,264 @@
+//! Theme Audit - Report color pairs in a theme that are hard to read
+//!
+//! Every color a theme draws on its background is checked against the WCAG
+//! ratios in [`crate::model::contrast`]: the foreground must reach the text
+//! ratio (an error otherwise), the accent the UI ratio and the ANSI colors
+//! the text ratio (warnings). Gradient backgrounds are checked against both
+//! stops. The ANSI color meant to blend into the background, black on dark
+//! themes and white on light ones, is not checked.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use iced::Color;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::model::contrast::{contrast_ratio, MIN_TEXT_CONTRAST, MIN_UI_CONTRAST};
+use crate::model::theme::{AppTheme, ThemeFill};
+use crate::model::theme_loader::{load_theme_file, to_hex_color};
+
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// How serious an audit issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum AuditSeverity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for AuditSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditSeverity::Warning => f.write_str("warning"),
+            AuditSeverity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// One low-contrast pair, or a theme file that could not be loaded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditIssue {
+    /// File the theme came from, if it was read from disk
+    pub path: Option<PathBuf>,
+    pub severity: AuditSeverity,
+    /// `foreground`, `accent`, `blue`, `bright blue`..., or `invalid-theme`
+    pub pair: String,
+    /// Contrast ratio against the background, absent for load errors
+    pub ratio: Option<f32>,
+    pub message: String,
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.pair, self.message)
+    }
+}
+
+/// Issues found across a set of theme files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    pub files_checked: usize,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl AuditReport {
+    pub fn error_count(&self) -> usize {
+        self.count(AuditSeverity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(AuditSeverity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn count(&self, severity: AuditSeverity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity).count()
+    }
+}
+
+/// Checks themes for low-contrast color pairs
+#[derive(Debug, Clone)]
+pub struct ThemeAuditor {
+    /// Ratio required of the foreground and ANSI colors
+    pub min_text_contrast: f32,
+    /// Ratio required of the accent
+    pub min_ui_contrast: f32,
+    /// Also report warnings for the accent and ANSI colors
+    pub include_warnings: bool,
+}
+
+impl Default for ThemeAuditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThemeAuditor {
+    pub fn new() -> Self {
+        Self {
+            min_text_contrast: MIN_TEXT_CONTRAST,
+            min_ui_contrast: MIN_UI_CONTRAST,
+            include_warnings: true,
+        }
+    }
+
+    pub fn with_min_text_contrast(mut self, ratio: f32) -> Self {
+        self.min_text_contrast = ratio;
+        self
+    }
+
+    pub fn with_warnings(mut self, include_warnings: bool) -> Self {
+        self.include_warnings = include_warnings;
+        self
+    }
+
+    /// Audit every `.yaml`/`.yml` theme in `paths`, descending into
+    /// directories
+    pub fn audit_paths(&self, paths: &[PathBuf]) -> AuditReport {
+        let mut report = AuditReport::default();
+        for path in paths {
+            let files = WalkDir::new(path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file() && is_theme_file(entry.path()));
+            for entry in files {
+                report.files_checked += 1;
+                let root = entry.path().parent().unwrap_or(Path::new("."));
+                let issues = match load_theme_file(entry.path(), root) {
+                    Ok(theme) => self.audit_theme(&theme),
+                    Err(e) => vec![AuditIssue {
+                        path: None,
+                        severity: AuditSeverity::Error,
+                        pair: "invalid-theme".to_string(),
+                        ratio: None,
+                        message: e.to_string(),
+                    }],
+                };
+                report.issues.extend(issues.into_iter().map(|issue| AuditIssue {
+                    path: Some(entry.path().to_path_buf()),
+                    ..issue
+                }));
+            }
+        }
+        report
+    }
+
+    /// Low-contrast pairs in one theme
+    pub fn audit_theme(&self, theme: &AppTheme) -> Vec<AuditIssue> {
+        let backgrounds = match theme.background() {
+            ThemeFill::Solid(color) => vec![color],
+            ThemeFill::Gradient { from, to } => vec![from, to],
+        };
+        let mut issues = Vec::new();
+        let mut check = |pair: String, color: Color, minimum: f32, severity: AuditSeverity| {
+            let (ratio, background) = backgrounds
+                .iter()
+                .map(|background| (contrast_ratio(color, *background), *background))
+                .fold((f32::INFINITY, Color::BLACK), |worst, next| if next.0 < worst.0 { next } else { worst });
+            if ratio < minimum {
+                issues.push(AuditIssue {
+                    path: None,
+                    severity,
+                    message: format!(
+                        "{} {} on background {} has contrast {:.2}:1, below {:.1}:1",
+                        pair,
+                        to_hex_color(color),
+                        to_hex_color(background),
+                        ratio,
+                        minimum
+                    ),
+                    pair,
+                    ratio: Some(ratio),
+                });
+            }
+        };
+
+        check("foreground".to_string(), theme.text_color(), self.min_text_contrast, AuditSeverity::Error);
+        if self.include_warnings {
+            check("accent".to_string(), theme.accent_color(), self.min_ui_contrast, AuditSeverity::Warning);
+            // The slot that usually matches the background
+            let blend = if theme.is_dark() { 0 } else { 7 };
+            for (index, name) in ANSI_NAMES.iter().enumerate() {
+                if index != blend {
+                    check(name.to_string(), theme.ansi_palette().normal[index], self.min_text_contrast, AuditSeverity::Warning);
+                }
+                check(format!("bright {}", name), theme.ansi_palette().bright[index], self.min_text_contrast, AuditSeverity::Warning);
+            }
+        }
+        issues
+    }
+}
+
+fn is_theme_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("yaml") | Some("yml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::theme::{AnsiPalette, ThemeDetails};
+    use crate::model::theme_loader::parse_hex_color;
+
+    fn pairs(issues: &[AuditIssue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.pair.as_str()).collect()
+    }
+
+    #[test]
+    fn test_audit_theme_reports_low_contrast_pairs() {
+        let color = |value: &str| parse_hex_color(value).unwrap();
+        let mut ansi = AnsiPalette {
+            normal: [color("#000000"); 8],
+            bright: [color("#ffffff"); 8],
+        };
+        ansi.normal[1..].fill(color("#ffffff"));
+        ansi.normal[4] = color("#0000aa");
+        let theme = AppTheme::new(
+            "dim",
+            ThemeFill::Gradient {
+                from: color("#000000"),
+                to: color("#444444"),
+            },
+            color("#888888"),
+            ThemeFill::Solid(color("#ffffff")),
+            ThemeDetails::Darker,
+            ansi,
+        );
+
+        let issues = ThemeAuditor::new().audit_theme(&theme);
+        // Normal black blends into a dark background and is not reported
+        assert_eq!(pairs(&issues), vec!["foreground", "blue"]);
+        assert_eq!(issues[0].severity, AuditSeverity::Error);
+        // Checked against the lighter gradient stop
+        assert!(issues[0].message.contains("#444444"), "{}", issues[0].message);
+
+        assert_eq!(pairs(&ThemeAuditor::new().with_warnings(false).audit_theme(&theme)), vec!["foreground"]);
+        assert!(ThemeAuditor::new().with_min_text_contrast(1.3).audit_theme(&theme).is_empty());
+    }
+
+    #[test]
+    fn test_audit_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.yaml"), "accent: '#ff'\n").unwrap();
+        std::fs::write(
+            dir.path().join("readable.yml"),
+            "accent: '#ffffff'\nbackground: '#000000'\nforeground: '#ffffff'\ndetails: darker\nterminal_colors:\n  normal: {black: '#000000', red: '#ff5555', green: '#55ff55', yellow: '#ffff55', blue: '#8888ff', magenta: '#ff55ff', cyan: '#55ffff', white: '#ffffff'}\n  bright: {black: '#888888', red: '#ff5555', green: '#55ff55', yellow: '#ffff55', blue: '#8888ff', magenta: '#ff55ff', cyan: '#55ffff', white: '#ffffff'}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a theme").unwrap();
+
+        let report = ThemeAuditor::new().audit_paths(&[dir.path().to_path_buf()]);
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(report.warning_count(), 0);
+        assert_eq!(report.issues[0].pair, "invalid-theme");
+        assert!(report.issues[0].to_string().starts_with(&dir.path().join("broken.yaml").display().to_string()));
+    }
+}
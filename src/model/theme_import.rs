@@ -0,0 +1,710 @@
+//! Theme Import - Convert color schemes from other terminals
+//!
+//! Reads iTerm2 `.itermcolors` property lists, Alacritty TOML and YAML
+//! configs, Windows Terminal JSON schemes (a single scheme, a list, or a
+//! `settings.json` with a `schemes` array) and base16 YAML schemes into
+//! [`AppTheme`]s, and writes themes back out in any of those formats or as
+//! Warp YAML.
+//!
+//! Other terminals have no accent color; the cursor color is used when
+//! there is one, otherwise the ANSI blue. `details` follows the brightness
+//! of the background. base16 colors map onto the ANSI palette the way
+//! base16-shell does, so a base16 export of an imported scheme gives back
+//! the original.
+
+use crate::model::theme::{AnsiPalette, AppTheme, ThemeDetails, ThemeFill};
+use crate::model::theme_loader::{parse_theme, theme_to_yaml, to_hex_color, ThemeError};
+use iced::Color;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThemeFormat {
+    Warp,
+    ITerm2,
+    Alacritty,
+    WindowsTerminal,
+    Base16,
+}
+
+impl fmt::Display for ThemeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ThemeFormat::Warp => "Warp",
+            ThemeFormat::ITerm2 => "iTerm2",
+            ThemeFormat::Alacritty => "Alacritty",
+            ThemeFormat::WindowsTerminal => "Windows Terminal",
+            ThemeFormat::Base16 => "base16",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ThemeImportError {
+    #[error("cannot read {path}: {message}")]
+    Read { path: PathBuf, message: String },
+    #[error("cannot tell the theme format of {0}")]
+    UnknownFormat(PathBuf),
+    #[error("invalid {format} theme: {message}")]
+    Parse { format: ThemeFormat, message: String },
+    #[error("{format} theme has no {color} color")]
+    MissingColor { format: ThemeFormat, color: String },
+    #[error(transparent)]
+    Theme(#[from] ThemeError),
+}
+
+impl ThemeFormat {
+    pub const ALL: [ThemeFormat; 5] = [
+        ThemeFormat::Warp,
+        ThemeFormat::ITerm2,
+        ThemeFormat::Alacritty,
+        ThemeFormat::WindowsTerminal,
+        ThemeFormat::Base16,
+    ];
+
+    /// The usual file extension
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThemeFormat::Warp | ThemeFormat::Base16 => "yaml",
+            ThemeFormat::ITerm2 => "itermcolors",
+            ThemeFormat::Alacritty => "toml",
+            ThemeFormat::WindowsTerminal => "json",
+        }
+    }
+
+    /// Every extension an importable file may have
+    pub fn importable_extensions() -> &'static [&'static str] {
+        &["yaml", "yml", "itermcolors", "toml", "json"]
+    }
+
+    /// Tell the format from the extension, looking at the keys of YAML
+    /// files: base16 schemes name `base00`, Alacritty configs a `colors`
+    /// table.
+    pub fn detect(path: &Path, source: &str) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "itermcolors" => Some(ThemeFormat::ITerm2),
+            "toml" => Some(ThemeFormat::Alacritty),
+            "json" => Some(ThemeFormat::WindowsTerminal),
+            "yaml" | "yml" => {
+                let value: serde_yaml::Value = serde_yaml::from_str(source).ok()?;
+                let has = |key: &str| value.get(key).is_some();
+                if has("base00") || value.get("palette").is_some_and(|palette| palette.get("base00").is_some()) {
+                    Some(ThemeFormat::Base16)
+                } else if has("colors") {
+                    Some(ThemeFormat::Alacritty)
+                } else {
+                    Some(ThemeFormat::Warp)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// File stem for a scheme name: `One Half Dark` becomes `one_half_dark`
+pub fn theme_stem(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() {
+            stem.extend(c.to_lowercase());
+        } else if !stem.is_empty() && !stem.ends_with('_') {
+            stem.push('_');
+        }
+    }
+    stem.trim_end_matches('_').to_string()
+}
+
+/// Import every theme in a file. Windows Terminal settings may hold several.
+pub fn import_theme_file(path: &Path) -> Result<Vec<AppTheme>, ThemeImportError> {
+    let source = fs::read_to_string(path).map_err(|e| ThemeImportError::Read {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+    let format = ThemeFormat::detect(path, &source).ok_or_else(|| ThemeImportError::UnknownFormat(path.to_path_buf()))?;
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("imported");
+    let root = path.parent().unwrap_or(Path::new("."));
+    import_theme(&source, format, &theme_stem(stem), root)
+}
+
+/// Import themes from `source`. `name` names themes whose format carries
+/// no name of its own; `root` resolves Warp background images.
+pub fn import_theme(source: &str, format: ThemeFormat, name: &str, root: &Path) -> Result<Vec<AppTheme>, ThemeImportError> {
+    match format {
+        ThemeFormat::Warp => Ok(vec![parse_theme(source, name, root)?]),
+        ThemeFormat::ITerm2 => Ok(vec![import_iterm(source, name)?]),
+        ThemeFormat::Alacritty => Ok(vec![import_alacritty(source, name)?]),
+        ThemeFormat::WindowsTerminal => import_windows_terminal(source),
+        ThemeFormat::Base16 => Ok(vec![import_base16(source)?]),
+    }
+}
+
+/// Write `theme` in `format`
+pub fn export_theme(theme: &AppTheme, format: ThemeFormat) -> String {
+    match format {
+        ThemeFormat::Warp => theme_to_yaml(theme, Path::new("")),
+        ThemeFormat::ITerm2 => export_iterm(theme),
+        ThemeFormat::Alacritty => export_alacritty(theme),
+        ThemeFormat::WindowsTerminal => export_windows_terminal(theme),
+        ThemeFormat::Base16 => export_base16(theme),
+    }
+}
+
+/// Colors read from another terminal's scheme
+struct SchemeColors {
+    background: Color,
+    foreground: Color,
+    cursor: Option<Color>,
+    normal: [Color; 8],
+    bright: [Color; 8],
+}
+
+impl SchemeColors {
+    fn into_theme(self, name: &str) -> AppTheme {
+        let accent = self.cursor.unwrap_or(self.normal[4]);
+        AppTheme::new(
+            name,
+            ThemeFill::Solid(self.background),
+            self.foreground,
+            ThemeFill::Solid(accent),
+            details_for(self.background),
+            AnsiPalette {
+                normal: self.normal,
+                bright: self.bright,
+            },
+        )
+    }
+}
+
+fn details_for(background: Color) -> ThemeDetails {
+    let luminance = 0.2126 * background.r + 0.7152 * background.g + 0.0722 * background.b;
+    if luminance < 0.5 {
+        ThemeDetails::Darker
+    } else {
+        ThemeDetails::Lighter
+    }
+}
+
+/// `#rrggbb`, `rrggbb` or `0xrrggbb`; an alpha channel is dropped
+fn parse_color(value: &str, format: ThemeFormat) -> Result<Color, ThemeImportError> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .unwrap_or(value);
+    let hex = if hex.len() == 8 { &hex[..6] } else { hex };
+    crate::model::theme_loader::parse_hex_color(&format!("#{}", hex)).map_err(|_| ThemeImportError::Parse {
+        format,
+        message: format!("invalid color `{}`", value),
+    })
+}
+
+// iTerm2
+
+fn import_iterm(source: &str, name: &str) -> Result<AppTheme, ThemeImportError> {
+    let format = ThemeFormat::ITerm2;
+    let parse_error = |message: String| ThemeImportError::Parse { format, message };
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(source, options).map_err(|e| parse_error(e.to_string()))?;
+    let dict = doc
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("dict"))
+        .ok_or_else(|| parse_error("no top-level <dict>".to_string()))?;
+
+    let mut colors = Vec::new();
+    let mut entries = dict.children().filter(|node| node.is_element());
+    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+        if key.has_tag_name("key") && value.has_tag_name("dict") {
+            colors.push((key.text().unwrap_or_default().to_string(), iterm_color(value)));
+        }
+    }
+    let color = |key: &str| -> Result<Color, ThemeImportError> {
+        colors
+            .iter()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, color)| *color)
+            .ok_or_else(|| ThemeImportError::MissingColor {
+                format,
+                color: key.to_string(),
+            })
+    };
+    let ansi = |offset: usize| -> Result<[Color; 8], ThemeImportError> {
+        let mut out = [Color::BLACK; 8];
+        for (index, slot) in out.iter_mut().enumerate() {
+            *slot = color(&format!("Ansi {} Color", index + offset))?;
+        }
+        Ok(out)
+    };
+
+    Ok(SchemeColors {
+        background: color("Background Color")?,
+        foreground: color("Foreground Color")?,
+        cursor: color("Cursor Color").ok(),
+        normal: ansi(0)?,
+        bright: ansi(8)?,
+    }
+    .into_theme(name))
+}
+
+/// A color `<dict>` of `Red/Green/Blue Component` reals from 0 to 1
+fn iterm_color(dict: roxmltree::Node<'_, '_>) -> Option<Color> {
+    let mut components = [None; 3];
+    let mut entries = dict.children().filter(|node| node.is_element());
+    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+        let slot = match key.text()? {
+            "Red Component" => 0,
+            "Green Component" => 1,
+            "Blue Component" => 2,
+            _ => continue,
+        };
+        components[slot] = value.text()?.trim().parse::<f32>().ok();
+    }
+    Some(Color::from_rgb(components[0]?, components[1]?, components[2]?))
+}
+
+fn export_iterm(theme: &AppTheme) -> String {
+    let palette = theme.ansi_palette();
+    let mut entries: Vec<(String, Color)> = palette
+        .normal
+        .iter()
+        .chain(palette.bright.iter())
+        .enumerate()
+        .map(|(index, color)| (format!("Ansi {} Color", index), *color))
+        .collect();
+    entries.push(("Background Color".to_string(), theme.background_color()));
+    entries.push(("Cursor Color".to_string(), theme.cursor_color()));
+    entries.push(("Foreground Color".to_string(), theme.text_color()));
+
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n"
+    ));
+    for (key, color) in entries {
+        out.push_str(&format!("\t<key>{}</key>\n\t<dict>\n", key));
+        for (component, value) in [("Blue", color.b), ("Green", color.g), ("Red", color.r)] {
+            out.push_str(&format!("\t\t<key>{} Component</key>\n\t\t<real>{}</real>\n", component, value));
+        }
+        out.push_str("\t\t<key>Color Space</key>\n\t\t<string>sRGB</string>\n\t</dict>\n");
+    }
+    out.push_str("</dict>\n</plist>\n");
+    out
+}
+
+// Alacritty
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlacrittyConfig {
+    colors: AlacrittyColors,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlacrittyColors {
+    primary: AlacrittyPrimary,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<AlacrittyCursor>,
+    normal: AnsiNames,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bright: Option<AnsiNames>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlacrittyPrimary {
+    background: String,
+    foreground: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlacrittyCursor {
+    /// A color, or `CellForeground` / `CellBackground`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnsiNames {
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    magenta: String,
+    cyan: String,
+    white: String,
+}
+
+impl AnsiNames {
+    fn colors(&self, format: ThemeFormat) -> Result<[Color; 8], ThemeImportError> {
+        let values = [&self.black, &self.red, &self.green, &self.yellow, &self.blue, &self.magenta, &self.cyan, &self.white];
+        let mut out = [Color::BLACK; 8];
+        for (slot, value) in out.iter_mut().zip(values) {
+            *slot = parse_color(value, format)?;
+        }
+        Ok(out)
+    }
+
+    fn from_colors(colors: &[Color; 8]) -> Self {
+        Self {
+            black: to_hex_color(colors[0]),
+            red: to_hex_color(colors[1]),
+            green: to_hex_color(colors[2]),
+            yellow: to_hex_color(colors[3]),
+            blue: to_hex_color(colors[4]),
+            magenta: to_hex_color(colors[5]),
+            cyan: to_hex_color(colors[6]),
+            white: to_hex_color(colors[7]),
+        }
+    }
+}
+
+/// Alacritty's TOML config, or the YAML used before version 0.13
+fn import_alacritty(source: &str, name: &str) -> Result<AppTheme, ThemeImportError> {
+    let format = ThemeFormat::Alacritty;
+    let config: AlacrittyConfig = match toml::from_str(source) {
+        Ok(config) => config,
+        Err(toml_error) => serde_yaml::from_str(source).map_err(|_| ThemeImportError::Parse {
+            format,
+            message: toml_error.message().to_string(),
+        })?,
+    };
+    let colors = config.colors;
+    let normal = colors.normal.colors(format)?;
+    let bright = match &colors.bright {
+        Some(bright) => bright.colors(format)?,
+        None => normal,
+    };
+    let cursor = colors
+        .cursor
+        .and_then(|cursor| cursor.cursor)
+        .and_then(|cursor| parse_color(&cursor, format).ok());
+
+    Ok(SchemeColors {
+        background: parse_color(&colors.primary.background, format)?,
+        foreground: parse_color(&colors.primary.foreground, format)?,
+        cursor,
+        normal,
+        bright,
+    }
+    .into_theme(name))
+}
+
+fn export_alacritty(theme: &AppTheme) -> String {
+    let palette = theme.ansi_palette();
+    let config = AlacrittyConfig {
+        colors: AlacrittyColors {
+            primary: AlacrittyPrimary {
+                background: to_hex_color(theme.background_color()),
+                foreground: to_hex_color(theme.text_color()),
+            },
+            cursor: Some(AlacrittyCursor {
+                cursor: Some(to_hex_color(theme.cursor_color())),
+            }),
+            normal: AnsiNames::from_colors(&palette.normal),
+            bright: Some(AnsiNames::from_colors(&palette.bright)),
+        },
+    };
+    toml::to_string(&config).expect("Alacritty colors always serialize")
+}
+
+// Windows Terminal
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowsTerminalScheme {
+    name: String,
+    background: String,
+    foreground: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cursor_color: Option<String>,
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    purple: String,
+    cyan: String,
+    white: String,
+    bright_black: String,
+    bright_red: String,
+    bright_green: String,
+    bright_yellow: String,
+    bright_blue: String,
+    bright_purple: String,
+    bright_cyan: String,
+    bright_white: String,
+}
+
+/// A scheme object, a list of them, or a `settings.json` whose `schemes`
+/// holds them
+fn import_windows_terminal(source: &str) -> Result<Vec<AppTheme>, ThemeImportError> {
+    let format = ThemeFormat::WindowsTerminal;
+    let parse_error = |message: String| ThemeImportError::Parse { format, message };
+    let value: serde_json::Value = serde_json::from_str(source).map_err(|e| parse_error(e.to_string()))?;
+    let schemes = match value {
+        serde_json::Value::Object(mut object) if object.contains_key("schemes") => object.remove("schemes").unwrap_or_default(),
+        serde_json::Value::Array(_) => value,
+        single => serde_json::Value::Array(vec![single]),
+    };
+    let schemes: Vec<WindowsTerminalScheme> = serde_json::from_value(schemes).map_err(|e| parse_error(e.to_string()))?;
+    if schemes.is_empty() {
+        return Err(parse_error("no color schemes".to_string()));
+    }
+
+    schemes
+        .into_iter()
+        .map(|scheme| {
+            let color = |value: &str| parse_color(value, format);
+            let colors = SchemeColors {
+                background: color(&scheme.background)?,
+                foreground: color(&scheme.foreground)?,
+                cursor: scheme.cursor_color.as_deref().map(color).transpose()?,
+                normal: [
+                    color(&scheme.black)?,
+                    color(&scheme.red)?,
+                    color(&scheme.green)?,
+                    color(&scheme.yellow)?,
+                    color(&scheme.blue)?,
+                    color(&scheme.purple)?,
+                    color(&scheme.cyan)?,
+                    color(&scheme.white)?,
+                ],
+                bright: [
+                    color(&scheme.bright_black)?,
+                    color(&scheme.bright_red)?,
+                    color(&scheme.bright_green)?,
+                    color(&scheme.bright_yellow)?,
+                    color(&scheme.bright_blue)?,
+                    color(&scheme.bright_purple)?,
+                    color(&scheme.bright_cyan)?,
+                    color(&scheme.bright_white)?,
+                ],
+            };
+            Ok(colors.into_theme(&theme_stem(&scheme.name)))
+        })
+        .collect()
+}
+
+fn export_windows_terminal(theme: &AppTheme) -> String {
+    let palette = theme.ansi_palette();
+    let (normal, bright) = (palette.normal.map(to_hex_color), palette.bright.map(to_hex_color));
+    let [black, red, green, yellow, blue, purple, cyan, white] = normal;
+    let [bright_black, bright_red, bright_green, bright_yellow, bright_blue, bright_purple, bright_cyan, bright_white] = bright;
+    let scheme = WindowsTerminalScheme {
+        name: crate::model::theme_loader::display_name(theme.name()),
+        background: to_hex_color(theme.background_color()),
+        foreground: to_hex_color(theme.text_color()),
+        cursor_color: Some(to_hex_color(theme.cursor_color())),
+        black,
+        red,
+        green,
+        yellow,
+        blue,
+        purple,
+        cyan,
+        white,
+        bright_black,
+        bright_red,
+        bright_green,
+        bright_yellow,
+        bright_blue,
+        bright_purple,
+        bright_cyan,
+        bright_white,
+    };
+    serde_json::to_string_pretty(&scheme).expect("Windows Terminal schemes always serialize")
+}
+
+// base16
+
+/// Which base16 color fills each ANSI slot, normal then bright
+const BASE16_NORMAL: [&str; 8] = ["base00", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base05"];
+const BASE16_BRIGHT: [&str; 8] = ["base03", "base09", "base01", "base02", "base04", "base06", "base0F", "base07"];
+
+/// A classic base16 scheme (`scheme`, `base00`...) or a tinted-theming one
+/// (`name`, `palette: {base00...}`)
+fn import_base16(source: &str) -> Result<AppTheme, ThemeImportError> {
+    let format = ThemeFormat::Base16;
+    let value: serde_yaml::Value = serde_yaml::from_str(source).map_err(|e| ThemeImportError::Parse {
+        format,
+        message: e.to_string(),
+    })?;
+    let palette = value.get("palette").unwrap_or(&value);
+    let color = |key: &str| -> Result<Color, ThemeImportError> {
+        let found = palette
+            .get(key)
+            .or_else(|| palette.get(key.to_lowercase()))
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| ThemeImportError::MissingColor {
+                format,
+                color: key.to_string(),
+            })?;
+        parse_color(found, format)
+    };
+    let ansi = |keys: [&str; 8]| -> Result<[Color; 8], ThemeImportError> {
+        let mut out = [Color::BLACK; 8];
+        for (slot, key) in out.iter_mut().zip(keys) {
+            *slot = color(key)?;
+        }
+        Ok(out)
+    };
+    let scheme_name = value
+        .get("scheme")
+        .or_else(|| value.get("name"))
+        .and_then(|name| name.as_str())
+        .unwrap_or("imported");
+
+    let background = color("base00")?;
+    let accent = color("base0D")?;
+    Ok(AppTheme::new(
+        format!("base16_{}", theme_stem(scheme_name)),
+        ThemeFill::Solid(background),
+        color("base05")?,
+        ThemeFill::Solid(accent),
+        details_for(background),
+        AnsiPalette {
+            normal: ansi(BASE16_NORMAL)?,
+            bright: ansi(BASE16_BRIGHT)?,
+        },
+    ))
+}
+
+fn export_base16(theme: &AppTheme) -> String {
+    let palette = theme.ansi_palette();
+    let mut slots: Vec<(&str, Color)> = BASE16_NORMAL
+        .iter()
+        .zip(palette.normal)
+        .chain(BASE16_BRIGHT.iter().zip(palette.bright))
+        .map(|(key, color)| (*key, color))
+        .collect();
+    slots.sort_by_key(|(key, _)| *key);
+    slots.dedup_by_key(|(key, _)| *key);
+
+    let name = theme.name().strip_prefix("base16_").unwrap_or(theme.name());
+    let mut out = format!("scheme: \"{}\"\nauthor: \"Exported from Warp\"\n", crate::model::theme_loader::display_name(name));
+    for (key, color) in slots {
+        out.push_str(&format!("{}: \"{}\"\n", key, &to_hex_color(color)[1..]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+    fn sample_theme() -> AppTheme {
+        let color = |value: &str| crate::model::theme_loader::parse_hex_color(value).unwrap();
+        AppTheme::new(
+            "one_half_dark",
+            ThemeFill::Solid(color("#282c34")),
+            color("#dcdfe4"),
+            ThemeFill::Solid(color("#a3b3cc")),
+            ThemeDetails::Darker,
+            AnsiPalette {
+                normal: ["#282c34", "#e06c75", "#98c379", "#e5c07b", "#61afef", "#c678dd", "#56b6c2", "#dcdfe4"].map(color),
+                bright: ["#5d677a", "#e06c76", "#98c37a", "#e5c07c", "#61aff0", "#c678de", "#56b6c3", "#dcdfe5"].map(color),
+            },
+        )
+    }
+
+    fn assert_same_colors(imported: &AppTheme, original: &AppTheme) {
+        assert_eq!(to_hex_color(imported.background_color()), to_hex_color(original.background_color()));
+        assert_eq!(to_hex_color(imported.text_color()), to_hex_color(original.text_color()));
+        for bright in [false, true] {
+            for index in 0..8 {
+                assert_eq!(
+                    to_hex_color(imported.terminal_color(index, bright)),
+                    to_hex_color(original.terminal_color(index, bright)),
+                    "{} {}",
+                    ANSI_NAMES[index as usize],
+                    bright
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_every_format() {
+        let theme = sample_theme();
+        for format in ThemeFormat::ALL {
+            let exported = export_theme(&theme, format);
+            let imported = import_theme(&exported, format, "one_half_dark", Path::new("")).unwrap();
+            assert_eq!(imported.len(), 1, "{}", format);
+            assert_same_colors(&imported[0], &theme);
+            assert_eq!(imported[0].details(), ThemeDetails::Darker, "{}", format);
+            if format != ThemeFormat::Base16 {
+                assert_eq!(to_hex_color(imported[0].accent_color()), "#a3b3cc", "{}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_import_iterm() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Background Color</key>
+  <dict>
+    <key>Color Space</key><string>Calibrated</string>
+    <key>Red Component</key><real>1</real>
+    <key>Green Component</key><real>1</real>
+    <key>Blue Component</key><real>1</real>
+  </dict>
+</dict>
+</plist>"#;
+        assert_eq!(
+            import_theme(plist, ThemeFormat::ITerm2, "light", Path::new("")).unwrap_err(),
+            ThemeImportError::MissingColor {
+                format: ThemeFormat::ITerm2,
+                color: "Foreground Color".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_import_alacritty_yaml_and_windows_terminal_settings() {
+        let yaml = "colors:\n  primary:\n    background: '0x1d1f21'\n    foreground: '0xc5c8c6'\n  cursor:\n    cursor: CellForeground\n  normal:\n    black: '0x1d1f21'\n    red: '0xcc6666'\n    green: '0xb5bd68'\n    yellow: '0xf0c674'\n    blue: '0x81a2be'\n    magenta: '0xb294bb'\n    cyan: '0x8abeb7'\n    white: '0xc5c8c6'\n";
+        let theme = &import_theme(yaml, ThemeFormat::Alacritty, "tomorrow_night", Path::new("")).unwrap()[0];
+        assert_eq!(theme.name(), "tomorrow_night");
+        // No usable cursor color: the accent is the ANSI blue
+        assert_eq!(to_hex_color(theme.accent_color()), "#81a2be");
+        assert_eq!(theme.terminal_color(1, true), theme.terminal_color(1, false));
+
+        let scheme = export_windows_terminal(&sample_theme());
+        let settings = format!("{{\"profiles\": {{}}, \"schemes\": [{}, {}]}}", scheme, scheme.replace("One Half Dark", "Copy"));
+        let themes = import_theme(&settings, ThemeFormat::WindowsTerminal, "settings", Path::new("")).unwrap();
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name()).collect();
+        assert_eq!(names, vec!["one_half_dark", "copy"]);
+    }
+
+    #[test]
+    fn test_import_base16_matches_bundled_conversion() {
+        let scheme = "scheme: \"3024\"\nauthor: \"Jan T. Sott\"\nbase00: \"090300\"\nbase01: \"3a3432\"\nbase02: \"4a4543\"\nbase03: \"5c5855\"\nbase04: \"807d7c\"\nbase05: \"a5a2a2\"\nbase06: \"d6d5d4\"\nbase07: \"f7f7f7\"\nbase08: \"db2d20\"\nbase09: \"e8bbd0\"\nbase0A: \"fded02\"\nbase0B: \"01a252\"\nbase0C: \"b5e4f4\"\nbase0D: \"01a0e4\"\nbase0E: \"a16a94\"\nbase0F: \"cdab53\"\n";
+        assert_eq!(ThemeFormat::detect(Path::new("3024.yaml"), scheme), Some(ThemeFormat::Base16));
+        let theme = &import_theme(scheme, ThemeFormat::Base16, "unused", Path::new("")).unwrap()[0];
+        let bundled = crate::model::theme_loader::load_theme_file(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("themes/base16/base16_3024.yaml"),
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(theme.name(), bundled.name());
+        assert_same_colors(theme, &bundled);
+        assert_eq!(theme.accent_color(), bundled.accent_color());
+        assert_eq!(export_base16(theme), scheme.replace("Jan T. Sott", "Exported from Warp"));
+    }
+
+    #[test]
+    fn test_theme_stem() {
+        assert_eq!(theme_stem("One Half Dark"), "one_half_dark");
+        assert_eq!(theme_stem("  Solarized (Dark) "), "solarized_dark");
+    }
+}
@@ -0,0 +1,464 @@
+//! Theme Loader - Read Warp YAML themes into [`AppTheme`]s
+//!
+//! A theme file names an `accent`, `background` and `foreground` color,
+//! whether UI `details` are `darker` or `lighter` than the background, and
+//! the 16 `terminal_colors`. The accent may be a `left`/`right` gradient and
+//! the background a `top`/`bottom` gradient. An optional `background_image`
+//! gives a path relative to the themes directory and an opacity from 0 to
+//! 100. Extra keys (`name`, `author`, `ui_colors`, ...) are ignored.
+//!
+//! Themes come from the bundled `themes` directory and the user's
+//! `~/.warp/themes`. A theme is identified by its file stem, e.g. `dracula`;
+//! a later file with the same stem replaces an earlier one.
+
+use crate::model::theme::{AnsiPalette, AppTheme, BackgroundImage, ThemeDetails, ThemeFill};
+use iced::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+/// Directory of the themes shipped with the terminal
+pub const BUNDLED_THEMES_DIR: &str = "themes";
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ThemeError {
+    #[error("cannot read {path}: {message}")]
+    Read { path: PathBuf, message: String },
+    #[error("invalid theme {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("invalid color `{0}`, expected #rgb or #rrggbb")]
+    InvalidColor(String),
+    #[error("unknown theme details `{0}`, expected `darker` or `lighter`")]
+    InvalidDetails(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ThemeFile {
+    accent: FillSpec,
+    background: FillSpec,
+    foreground: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<DetailsSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background_image: Option<BackgroundImageSpec>,
+    terminal_colors: TerminalColorsSpec,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum FillSpec {
+    Solid(String),
+    Horizontal { left: String, right: String },
+    Vertical { top: String, bottom: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum DetailsSpec {
+    Named(String),
+    /// Per-element opacities; treated like `darker`
+    #[allow(dead_code)]
+    Custom { custom: BTreeMap<String, serde_yaml::Value> },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackgroundImageSpec {
+    path: PathBuf,
+    #[serde(default = "default_image_opacity")]
+    opacity: u8,
+}
+
+fn default_image_opacity() -> u8 {
+    100
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TerminalColorsSpec {
+    normal: AnsiColorsSpec,
+    bright: AnsiColorsSpec,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnsiColorsSpec {
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    magenta: String,
+    cyan: String,
+    white: String,
+}
+
+impl AnsiColorsSpec {
+    fn from_colors(colors: &[Color; 8]) -> Self {
+        Self {
+            black: to_hex_color(colors[0]),
+            red: to_hex_color(colors[1]),
+            green: to_hex_color(colors[2]),
+            yellow: to_hex_color(colors[3]),
+            blue: to_hex_color(colors[4]),
+            magenta: to_hex_color(colors[5]),
+            cyan: to_hex_color(colors[6]),
+            white: to_hex_color(colors[7]),
+        }
+    }
+
+    fn to_colors(&self) -> Result<[Color; 8], ThemeError> {
+        Ok([
+            parse_hex_color(&self.black)?,
+            parse_hex_color(&self.red)?,
+            parse_hex_color(&self.green)?,
+            parse_hex_color(&self.yellow)?,
+            parse_hex_color(&self.blue)?,
+            parse_hex_color(&self.magenta)?,
+            parse_hex_color(&self.cyan)?,
+            parse_hex_color(&self.white)?,
+        ])
+    }
+}
+
+impl FillSpec {
+    fn from_fill(fill: ThemeFill, horizontal: bool) -> Self {
+        match fill {
+            ThemeFill::Solid(color) => FillSpec::Solid(to_hex_color(color)),
+            ThemeFill::Gradient { from, to } if horizontal => FillSpec::Horizontal {
+                left: to_hex_color(from),
+                right: to_hex_color(to),
+            },
+            ThemeFill::Gradient { from, to } => FillSpec::Vertical {
+                top: to_hex_color(from),
+                bottom: to_hex_color(to),
+            },
+        }
+    }
+
+    fn to_fill(&self) -> Result<ThemeFill, ThemeError> {
+        Ok(match self {
+            FillSpec::Solid(color) => ThemeFill::Solid(parse_hex_color(color)?),
+            FillSpec::Horizontal { left: from, right: to } | FillSpec::Vertical { top: from, bottom: to } => {
+                ThemeFill::Gradient {
+                    from: parse_hex_color(from)?,
+                    to: parse_hex_color(to)?,
+                }
+            }
+        })
+    }
+}
+
+/// Parse `#rgb` or `#rrggbb`
+pub fn parse_hex_color(value: &str) -> Result<Color, ThemeError> {
+    let invalid = || ThemeError::InvalidColor(value.to_string());
+    let hex = value.trim().strip_prefix('#').ok_or_else(invalid)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+    match hex.len() {
+        3 => {
+            let short = |index: usize| channel(&hex[index..index + 1].repeat(2));
+            Ok(Color::from_rgb8(short(0)?, short(1)?, short(2)?))
+        }
+        6 => Ok(Color::from_rgb8(channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Format a color as `#rrggbb`, dropping alpha
+pub fn to_hex_color(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Display name for a theme file stem: `tokyo_night` becomes `Tokyo Night`
+pub fn display_name(stem: &str) -> String {
+    stem.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Parse the YAML of a theme. `root` is the themes directory that
+/// background image paths are relative to.
+pub fn parse_theme(source: &str, name: &str, root: &Path) -> Result<AppTheme, ThemeError> {
+    let parse_error = |message: String| ThemeError::Parse {
+        path: PathBuf::from(name),
+        message,
+    };
+    let file: ThemeFile = serde_yaml::from_str(source).map_err(|e| parse_error(e.to_string()))?;
+
+    let details = match &file.details {
+        None | Some(DetailsSpec::Custom { .. }) => ThemeDetails::Darker,
+        Some(DetailsSpec::Named(details)) => match details.trim() {
+            "darker" => ThemeDetails::Darker,
+            "lighter" => ThemeDetails::Lighter,
+            other => return Err(ThemeError::InvalidDetails(other.to_string())),
+        },
+    };
+    let ansi = AnsiPalette {
+        normal: file.terminal_colors.normal.to_colors()?,
+        bright: file.terminal_colors.bright.to_colors()?,
+    };
+    let theme = AppTheme::new(
+        name,
+        file.background.to_fill()?,
+        parse_hex_color(&file.foreground)?,
+        file.accent.to_fill()?,
+        details,
+        ansi,
+    );
+
+    Ok(match file.background_image {
+        Some(image) => theme.with_background_image(BackgroundImage {
+            path: root.join(image.path),
+            opacity: f32::from(image.opacity.min(100)) / 100.0,
+        }),
+        None => theme,
+    })
+}
+
+/// Write a theme in the Warp YAML format. A background image under `root`
+/// is written relative to it.
+pub fn theme_to_yaml(theme: &AppTheme, root: &Path) -> String {
+    let palette = theme.ansi_palette();
+    let file = ThemeFile {
+        accent: FillSpec::from_fill(theme.accent(), true),
+        background: FillSpec::from_fill(theme.background(), false),
+        foreground: to_hex_color(theme.text_color()),
+        details: Some(DetailsSpec::Named(
+            match theme.details() {
+                ThemeDetails::Darker => "darker",
+                ThemeDetails::Lighter => "lighter",
+            }
+            .to_string(),
+        )),
+        background_image: theme.background_image().map(|image| BackgroundImageSpec {
+            path: image.path.strip_prefix(root).unwrap_or(&image.path).to_path_buf(),
+            opacity: (image.opacity * 100.0).round() as u8,
+        }),
+        terminal_colors: TerminalColorsSpec {
+            normal: AnsiColorsSpec::from_colors(&palette.normal),
+            bright: AnsiColorsSpec::from_colors(&palette.bright),
+        },
+    };
+    serde_yaml::to_string(&file).expect("theme files always serialize")
+}
+
+/// Load the theme at `path`, named after its file stem
+pub fn load_theme_file(path: &Path, root: &Path) -> Result<AppTheme, ThemeError> {
+    let source = fs::read_to_string(path).map_err(|e| ThemeError::Read {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    parse_theme(&source, stem, root).map_err(|e| match e {
+        ThemeError::Parse { message, .. } => ThemeError::Parse {
+            path: path.to_path_buf(),
+            message,
+        },
+        other => ThemeError::Parse {
+            path: path.to_path_buf(),
+            message: other.to_string(),
+        },
+    })
+}
+
+/// The themes found in a set of directories, keyed by file stem
+#[derive(Debug, Clone, Default)]
+pub struct ThemeLoader {
+    dirs: Vec<PathBuf>,
+    themes: BTreeMap<String, AppTheme>,
+    errors: Vec<ThemeError>,
+}
+
+impl ThemeLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bundled themes followed by the user's own
+    pub fn with_default_dirs() -> Self {
+        let loader = Self::new().with_dir(BUNDLED_THEMES_DIR);
+        match Self::user_themes_dir() {
+            Some(dir) => loader.with_dir(dir),
+            None => loader,
+        }
+    }
+
+    /// The user's own theme directory, `~/.warp/themes`
+    pub fn user_themes_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".warp").join("themes"))
+    }
+
+    /// Add a directory; themes in later directories replace earlier ones
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push(dir.into());
+        self
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// (Re)load every theme, returning how many were loaded. Files that
+    /// fail to parse are skipped and reported by [`ThemeLoader::errors`].
+    pub fn load(&mut self) -> usize {
+        self.themes.clear();
+        self.errors.clear();
+        for dir in &self.dirs {
+            if !dir.is_dir() {
+                debug!("Theme directory {} does not exist", dir.display());
+                continue;
+            }
+            let files = WalkDir::new(dir)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file() && is_theme_file(entry.path()));
+            for entry in files {
+                match load_theme_file(entry.path(), dir) {
+                    Ok(theme) => {
+                        self.themes.insert(theme.name().to_string(), theme);
+                    }
+                    Err(e) => {
+                        warn!("Skipping theme: {}", e);
+                        self.errors.push(e);
+                    }
+                }
+            }
+        }
+        self.themes.len()
+    }
+
+    pub fn themes(&self) -> &BTreeMap<String, AppTheme> {
+        &self.themes
+    }
+
+    pub fn errors(&self) -> &[ThemeError] {
+        &self.errors
+    }
+
+    /// Look a theme up by file stem or display name, ignoring case
+    pub fn get(&self, name: &str) -> Option<&AppTheme> {
+        self.themes.get(name).or_else(|| {
+            let wanted = name.trim().to_lowercase().replace([' ', '-'], "_");
+            self.themes
+                .iter()
+                .find(|(stem, _)| stem.to_lowercase().replace('-', "_") == wanted)
+                .map(|(_, theme)| theme)
+        })
+    }
+}
+
+fn is_theme_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KOI: &str = r##"
+background: "#211719"
+accent:
+  left: "#f9aea8"
+  right: "#dd6258"
+foreground: "#FFF"
+background_image:
+  # credit
+  path: warp_bundled/koi_bg.jpg
+  opacity: 30
+details: lighter # Whether the theme is lighter or darker.
+terminal_colors:
+  normal: { black: "#616161", red: "#ff8272", green: "#b4fa72", yellow: "#fefdc2", blue: "#a5d5fe", magenta: "#ff8ffd", cyan: "#d0d1fe", white: "#f1f1f1" }
+  bright: { black: "#8e8e8e", red: "#ffc4bd", green: "#d6fcb9", yellow: "#fefdd5", blue: "#c1e3fe", magenta: "#ffb1fe", cyan: "#e5e6fe", white: "#feffff" }
+"##;
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), Ok(Color::from_rgb8(255, 128, 0)));
+        assert_eq!(parse_hex_color("#EEE"), Ok(Color::from_rgb8(0xee, 0xee, 0xee)));
+        assert!(parse_hex_color("ff8000").is_err());
+        assert!(parse_hex_color("#ff80").is_err());
+        assert!(parse_hex_color("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        let theme = parse_theme(KOI, "koi", Path::new("/themes")).unwrap();
+        assert_eq!(theme.name(), "koi");
+        assert_eq!(theme.background(), ThemeFill::Solid(Color::from_rgb8(0x21, 0x17, 0x19)));
+        assert_eq!(
+            theme.accent(),
+            ThemeFill::Gradient {
+                from: Color::from_rgb8(0xf9, 0xae, 0xa8),
+                to: Color::from_rgb8(0xdd, 0x62, 0x58)
+            }
+        );
+        assert_eq!(theme.text_color(), Color::WHITE);
+        assert_eq!(theme.details(), ThemeDetails::Lighter);
+        assert_eq!(theme.terminal_color(1, true), Color::from_rgb8(0xff, 0xc4, 0xbd));
+        let image = theme.background_image().unwrap();
+        assert_eq!(image.path, PathBuf::from("/themes/warp_bundled/koi_bg.jpg"));
+        assert!((image.opacity - 0.3).abs() < f32::EPSILON);
+
+        let bad = KOI.replace("lighter #", "dimmer #");
+        assert_eq!(
+            parse_theme(&bad, "koi", Path::new("/themes")).unwrap_err(),
+            ThemeError::InvalidDetails("dimmer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let theme = parse_theme(KOI, "koi", Path::new("/themes")).unwrap();
+        let yaml = theme_to_yaml(&theme, Path::new("/themes"));
+        assert!(yaml.contains("path: warp_bundled/koi_bg.jpg"), "{}", yaml);
+        assert!(yaml.contains("left: '#f9aea8'"), "{}", yaml);
+        let reparsed = parse_theme(&yaml, "koi", Path::new("/themes")).unwrap();
+        assert_eq!(reparsed.accent(), theme.accent());
+        assert_eq!(reparsed.ansi_palette(), theme.ansi_palette());
+        assert_eq!(reparsed.details(), theme.details());
+        assert_eq!(reparsed.background_image(), theme.background_image());
+    }
+
+    #[test]
+    fn test_loader_layers_and_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let (bundled, user) = (dir.path().join("bundled"), dir.path().join("user"));
+        fs::create_dir_all(bundled.join("standard")).unwrap();
+        fs::create_dir_all(&user).unwrap();
+        fs::write(bundled.join("standard/tokyo_night.yaml"), KOI).unwrap();
+        fs::write(bundled.join("standard/broken.yaml"), "accent: 3").unwrap();
+        fs::write(user.join("tokyo_night.yml"), KOI.replace("#211719", "#000000")).unwrap();
+
+        let mut loader = ThemeLoader::new().with_dir(&bundled).with_dir(&user);
+        assert_eq!(loader.load(), 1);
+        assert_eq!(loader.errors().len(), 1);
+        let theme = loader.get("Tokyo Night").unwrap();
+        assert_eq!(theme.background_color(), Color::BLACK);
+        assert_eq!(display_name(theme.name()), "Tokyo Night");
+    }
+
+    #[test]
+    fn test_bundled_themes_parse() {
+        let mut loader = ThemeLoader::new().with_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(BUNDLED_THEMES_DIR));
+        let count = loader.load();
+        assert!(loader.errors().is_empty(), "{:?}", loader.errors());
+        assert!(count > 300, "only {} themes loaded", count);
+        assert!(loader.get("dracula").is_some());
+        assert!(loader.themes().values().any(|theme| theme.background_image().is_some()));
+    }
+}
@@ -0,0 +1,570 @@
+//! Authoring workflows from commands that have already been run.
+//!
+//! A [`WorkflowDraft`] starts from the command of a block or history entry,
+//! proposes the paths, branch names, numbers and URLs in it as
+//! `{{placeholders}}`, and is saved as a spec file once the user has named
+//! it.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use thiserror::Error;
+use warp_workflows_types::{Argument, ArgumentType, Workflow};
+
+use super::block::{Block, BlockContent};
+use super::history::HistoryEntry;
+use super::workflow_lint::{LintSeverity, WorkflowLinter};
+use crate::syntax_tree::{parse_shell, ShellDialect, WordView};
+
+/// Git subcommands whose positional arguments are usually branch names
+const BRANCH_SUBCOMMANDS: &[&str] = &["branch", "checkout", "merge", "pull", "push", "rebase", "switch"];
+
+/// Git options whose value is not a branch name
+const GIT_VALUE_OPTIONS: &[&str] = &["-m", "--message", "-s", "--strategy", "-X", "--strategy-option"];
+
+/// Longest default name taken from a command
+const MAX_DEFAULT_NAME_WORDS: usize = 3;
+
+/// Errors raised while editing or saving a draft
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum WorkflowDraftError {
+    #[error("The workflow needs a name")]
+    EmptyName,
+    #[error("'{0}' is not a valid argument name; use letters, digits and underscores")]
+    InvalidParameterName(String),
+    #[error("Another argument is already called '{0}'")]
+    DuplicateParameterName(String),
+    #[error("No parameter at index {0}")]
+    UnknownParameter(usize),
+    #[error("The workflow is not valid: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+    #[error("Could not find the user workflow directory")]
+    NoWorkflowDirectory,
+    #[error("Could not write the workflow: {0}")]
+    Io(String),
+}
+
+/// What kind of value a detected parameter holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterKind {
+    Path,
+    Branch,
+    Number,
+    Url,
+}
+
+impl ParameterKind {
+    /// Base name of the placeholder
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            ParameterKind::Path => "path",
+            ParameterKind::Branch => "branch",
+            ParameterKind::Number => "number",
+            ParameterKind::Url => "url",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            ParameterKind::Path => "A file or directory path",
+            ParameterKind::Branch => "The name of a git branch",
+            ParameterKind::Number => "A whole number",
+            ParameterKind::Url => "A URL",
+        }
+    }
+
+    fn argument_type(&self) -> ArgumentType {
+        match self {
+            ParameterKind::Path => ArgumentType::Path,
+            ParameterKind::Number => ArgumentType::Int,
+            ParameterKind::Branch | ParameterKind::Url => ArgumentType::Text,
+        }
+    }
+}
+
+/// A value in the command proposed as a placeholder
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedParameter {
+    /// Placeholder name, editable by the user
+    pub name: String,
+    pub kind: ParameterKind,
+    /// The value in the original command, used as the default
+    pub value: String,
+    /// Whether the value is replaced by the placeholder
+    pub enabled: bool,
+    /// Byte ranges of every occurrence of the value in the command
+    spans: Vec<Range<usize>>,
+}
+
+/// A workflow being authored from an existing command
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkflowDraft {
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub parameters: Vec<DetectedParameter>,
+    /// The command exactly as it was run
+    source_command: String,
+}
+
+impl WorkflowDraft {
+    /// Draft a workflow from a command, detecting likely parameters
+    pub fn from_command(command: &str) -> Self {
+        let command = command.trim();
+        let parameters = detect_parameters(command);
+        let words = first_command_words(command);
+        let name_words: Vec<&str> = words
+            .iter()
+            .take_while(|word| {
+                !word.value.starts_with('-')
+                    && !parameters
+                        .iter()
+                        .any(|param| param.spans.iter().any(|span| word.span.contains(&span.start)))
+            })
+            .take(MAX_DEFAULT_NAME_WORDS)
+            .map(|word| word.value.as_str())
+            .collect();
+        let tags = words
+            .first()
+            .filter(|word| word.value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map(|word| vec![word.value.clone()])
+            .unwrap_or_default();
+
+        Self {
+            name: if name_words.is_empty() {
+                command.to_string()
+            } else {
+                name_words.join(" ")
+            },
+            description: String::new(),
+            tags,
+            parameters,
+            source_command: command.to_string(),
+        }
+    }
+
+    /// Draft a workflow from the command of a block. Blocks without a
+    /// command, such as background output, give `None`.
+    pub fn from_block(block: &Block) -> Option<Self> {
+        match &block.content {
+            BlockContent::Command { input, .. } if !input.trim().is_empty() => Some(Self::from_command(input)),
+            _ => None,
+        }
+    }
+
+    /// Draft a workflow from a history entry, keeping its tags
+    pub fn from_history_entry(entry: &HistoryEntry) -> Self {
+        let mut draft = Self::from_command(&entry.command);
+        for tag in &entry.tags {
+            if !draft.tags.contains(tag) {
+                draft.tags.push(tag.clone());
+            }
+        }
+        draft
+    }
+
+    pub fn source_command(&self) -> &str {
+        &self.source_command
+    }
+
+    /// Set the tags from comma separated text
+    pub fn set_tags(&mut self, tags: &str) {
+        self.tags = tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+
+    pub fn set_parameter_enabled(&mut self, index: usize, enabled: bool) -> Result<(), WorkflowDraftError> {
+        self.parameters
+            .get_mut(index)
+            .ok_or(WorkflowDraftError::UnknownParameter(index))?
+            .enabled = enabled;
+        Ok(())
+    }
+
+    pub fn rename_parameter(&mut self, index: usize, name: &str) -> Result<(), WorkflowDraftError> {
+        let name = name.trim();
+        if index >= self.parameters.len() {
+            return Err(WorkflowDraftError::UnknownParameter(index));
+        }
+        if !is_identifier(name) {
+            return Err(WorkflowDraftError::InvalidParameterName(name.to_string()));
+        }
+        if self
+            .parameters
+            .iter()
+            .enumerate()
+            .any(|(i, param)| i != index && param.name == name)
+        {
+            return Err(WorkflowDraftError::DuplicateParameterName(name.to_string()));
+        }
+        self.parameters[index].name = name.to_string();
+        Ok(())
+    }
+
+    /// The command with enabled parameters replaced by placeholders
+    pub fn command(&self) -> String {
+        let mut replacements: Vec<(&Range<usize>, &str)> = self
+            .parameters
+            .iter()
+            .filter(|param| param.enabled)
+            .flat_map(|param| param.spans.iter().map(move |span| (span, param.name.as_str())))
+            .collect();
+        replacements.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+
+        let mut command = self.source_command.clone();
+        for (span, name) in replacements {
+            command.replace_range(span.clone(), &format!("{{{{{}}}}}", name));
+        }
+        command
+    }
+
+    pub fn to_workflow(&self) -> Workflow {
+        let arguments = self
+            .parameters
+            .iter()
+            .filter(|param| param.enabled)
+            .map(|param| {
+                Argument::new(&param.name)
+                    .with_description(param.kind.description())
+                    .with_default_value(&param.value)
+                    .with_type(param.kind.argument_type())
+            })
+            .collect();
+        let mut workflow = Workflow::new(self.name.trim(), self.command()).with_arguments(arguments);
+        if !self.description.trim().is_empty() {
+            workflow = workflow.with_description(self.description.trim().to_string());
+        }
+        workflow.tags = self.tags.clone();
+        workflow
+    }
+
+    /// Check the draft would be a valid spec
+    pub fn validate(&self) -> Result<Workflow, WorkflowDraftError> {
+        if self.name.trim().is_empty() {
+            return Err(WorkflowDraftError::EmptyName);
+        }
+        let workflow = self.to_workflow();
+        let errors: Vec<String> = WorkflowLinter::new()
+            .lint_workflow(&workflow)
+            .into_iter()
+            .filter(|issue| issue.severity == LintSeverity::Error)
+            .map(|issue| issue.message)
+            .collect();
+        if errors.is_empty() {
+            Ok(workflow)
+        } else {
+            Err(WorkflowDraftError::Invalid(errors))
+        }
+    }
+
+    /// Write the draft as a YAML spec into `dir`, never overwriting an
+    /// existing file. Returns the path written.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, WorkflowDraftError> {
+        let workflow = self.validate()?;
+        let yaml = serde_yaml::to_string(&workflow).map_err(|e| WorkflowDraftError::Io(e.to_string()))?;
+        std::fs::create_dir_all(dir).map_err(|e| WorkflowDraftError::Io(e.to_string()))?;
+
+        let stem = file_stem(&workflow.name);
+        let path = (1..)
+            .map(|n| match n {
+                1 => dir.join(format!("{}.yaml", stem)),
+                n => dir.join(format!("{}_{}.yaml", stem, n)),
+            })
+            .find(|path| !path.exists())
+            .expect("some numbered file name is free");
+        std::fs::write(&path, yaml).map_err(|e| WorkflowDraftError::Io(e.to_string()))?;
+        Ok(path)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Snake-case file name for a workflow name
+fn file_stem(name: &str) -> String {
+    let stem = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    if stem.is_empty() {
+        "workflow".to_string()
+    } else {
+        stem
+    }
+}
+
+fn first_command_words(command: &str) -> Vec<WordView> {
+    parse_shell(command, ShellDialect::default())
+        .commands()
+        .first()
+        .map(|view| view.words().into_iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Find the values in `command` worth turning into placeholders
+fn detect_parameters(command: &str) -> Vec<DetectedParameter> {
+    let mut parameters: Vec<DetectedParameter> = Vec::new();
+    let tree = parse_shell(command, ShellDialect::default());
+
+    for view in tree.commands() {
+        let words = view.words();
+        let branches = branch_word_indices(&words);
+        // The command name itself is never a parameter
+        for (index, word) in words.iter().enumerate().skip(1) {
+            if word.has_expansion {
+                continue;
+            }
+            let Some((kind, span)) = classify(word, branches.contains(&index)) else {
+                continue;
+            };
+            let value = command[span.clone()].to_string();
+            match parameters
+                .iter_mut()
+                .find(|param| param.kind == kind && param.value == value)
+            {
+                Some(existing) => existing.spans.push(span),
+                None => {
+                    let name = unique_name(&parameters, kind.placeholder());
+                    parameters.push(DetectedParameter {
+                        name,
+                        kind,
+                        value,
+                        enabled: true,
+                        spans: vec![span],
+                    });
+                }
+            }
+        }
+    }
+    parameters
+}
+
+fn unique_name(parameters: &[DetectedParameter], base: &str) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => base.to_string(),
+            n => format!("{}_{}", base, n),
+        })
+        .find(|name| parameters.iter().all(|param| &param.name != name))
+        .expect("some numbered name is free")
+}
+
+/// Indices of the words of a git command that name branches
+fn branch_word_indices(words: &[&WordView]) -> Vec<usize> {
+    if words.first().map(|word| word.value.as_str()) != Some("git") {
+        return Vec::new();
+    }
+    let Some(sub_index) = words.iter().skip(1).position(|word| !word.value.starts_with('-')) else {
+        return Vec::new();
+    };
+    let sub_index = sub_index + 1;
+    let subcommand = words[sub_index].value.as_str();
+    if !BRANCH_SUBCOMMANDS.contains(&subcommand) {
+        return Vec::new();
+    }
+
+    let mut positional = Vec::new();
+    let mut skip_value = false;
+    for (index, word) in words.iter().enumerate().skip(sub_index + 1) {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+        if word.value == "--" {
+            break;
+        }
+        if word.value.starts_with('-') {
+            skip_value = GIT_VALUE_OPTIONS.contains(&word.value.as_str());
+            continue;
+        }
+        positional.push(index);
+    }
+    // `git push origin main`: the first positional is the remote
+    if matches!(subcommand, "push" | "pull") {
+        if positional.len() < 2 {
+            return Vec::new();
+        }
+        positional.remove(0);
+    }
+    positional
+}
+
+/// The kind of value in `word` and its byte range in the command, when the
+/// word holds something worth a placeholder
+fn classify(word: &WordView, is_branch: bool) -> Option<(ParameterKind, Range<usize>)> {
+    // Only replace text whose position in the command is known: plain
+    // words, words wrapped in a single pair of quotes, and `--flag=value`
+    let (value, start) = if word.raw == word.value {
+        match word.value.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (value, word.span.start + flag.len() + 1),
+            _ => (word.value.as_str(), word.span.start),
+        }
+    } else if word.raw.len() == word.value.len() + 2
+        && (word.raw.starts_with('"') || word.raw.starts_with('\''))
+        && word.raw[1..word.raw.len() - 1] == word.value
+    {
+        (word.value.as_str(), word.span.start + 1)
+    } else {
+        return None;
+    };
+    if value.is_empty() || value.starts_with('-') {
+        return None;
+    }
+
+    let whole = |kind| Some((kind, start..start + value.len()));
+    if is_url(value) {
+        return whole(ParameterKind::Url);
+    }
+    if is_branch {
+        return whole(ParameterKind::Branch);
+    }
+    if is_number(value) {
+        return whole(ParameterKind::Number);
+    }
+    if let Some(captures) = revision_offset_regex().captures(value) {
+        let digits = captures.get(1).expect("group always matches");
+        return Some((ParameterKind::Number, start + digits.start()..start + digits.end()));
+    }
+    if is_path(value) {
+        return whole(ParameterKind::Path);
+    }
+    None
+}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_url(value: &str) -> bool {
+    Regex::new(r"^(?:[A-Za-z][A-Za-z0-9+.-]*://|git@[\w.-]+:)\S+$")
+        .unwrap()
+        .is_match(value)
+}
+
+/// `HEAD~3` or `main^2`: the count is the parameter
+fn revision_offset_regex() -> Regex {
+    Regex::new(r"^[\w./-]+[~^](\d+)$").unwrap()
+}
+
+fn is_path(value: &str) -> bool {
+    if value.starts_with('/') || value.starts_with("./") || value.starts_with("../") || value.starts_with("~/") {
+        return true;
+    }
+    if value.contains('/') && !value.contains(':') {
+        return true;
+    }
+    // A file name with a short extension, e.g. `notes.txt` or `.env`
+    Regex::new(r"^[\w.-]*\.[A-Za-z][A-Za-z0-9]{0,4}$")
+        .unwrap()
+        .is_match(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn detected(draft: &WorkflowDraft) -> Vec<(&str, ParameterKind, &str)> {
+        draft
+            .parameters
+            .iter()
+            .map(|param| (param.name.as_str(), param.kind, param.value.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_paths_numbers_and_urls() {
+        let draft = WorkflowDraft::from_command(
+            "curl -o ./out/page.html --retry=3 https://example.com/page && cp ./out/page.html \"/tmp/backup copy\"",
+        );
+        assert_eq!(
+            detected(&draft),
+            vec![
+                ("path", ParameterKind::Path, "./out/page.html"),
+                ("number", ParameterKind::Number, "3"),
+                ("url", ParameterKind::Url, "https://example.com/page"),
+                ("path_2", ParameterKind::Path, "/tmp/backup copy"),
+            ]
+        );
+        assert_eq!(
+            draft.command(),
+            "curl -o {{path}} --retry={{number}} {{url}} && cp {{path}} \"{{path_2}}\""
+        );
+        assert_eq!(draft.name, "curl");
+        assert_eq!(draft.tags, vec!["curl".to_string()]);
+    }
+
+    #[test]
+    fn test_detects_git_branches() {
+        let draft = WorkflowDraft::from_command("git push origin feature/login");
+        assert_eq!(detected(&draft), vec![("branch", ParameterKind::Branch, "feature/login")]);
+        assert_eq!(draft.command(), "git push origin {{branch}}");
+        assert_eq!(draft.name, "git push origin");
+
+        let draft = WorkflowDraft::from_command("git merge -m 'Merge it' release HEAD~2");
+        assert_eq!(
+            detected(&draft),
+            vec![
+                ("branch", ParameterKind::Branch, "release"),
+                ("branch_2", ParameterKind::Branch, "HEAD~2"),
+            ]
+        );
+        assert_eq!(
+            WorkflowDraft::from_command("git reset --soft HEAD~3").command(),
+            "git reset --soft HEAD~{{number}}"
+        );
+    }
+
+    #[test]
+    fn test_editing_parameters() {
+        let mut draft = WorkflowDraft::from_command("tail -n 50 /var/log/syslog");
+        draft.rename_parameter(0, "lines").unwrap();
+        assert_eq!(
+            draft.rename_parameter(1, "lines"),
+            Err(WorkflowDraftError::DuplicateParameterName("lines".to_string()))
+        );
+        assert_eq!(
+            draft.rename_parameter(1, "log file"),
+            Err(WorkflowDraftError::InvalidParameterName("log file".to_string()))
+        );
+        draft.set_parameter_enabled(1, false).unwrap();
+        assert_eq!(draft.command(), "tail -n {{lines}} /var/log/syslog");
+
+        let workflow = draft.to_workflow();
+        assert_eq!(workflow.arguments.len(), 1);
+        assert_eq!(workflow.arguments[0].default_value.as_deref(), Some("50"));
+        assert_eq!(workflow.arguments[0].arg_type, ArgumentType::Int);
+    }
+
+    #[test]
+    fn test_save_writes_loadable_spec() {
+        let dir = TempDir::new().unwrap();
+        let mut draft = WorkflowDraft::from_command("ls -la ~/projects");
+        draft.name = "List projects".to_string();
+        draft.description = "Show everything in the projects folder".to_string();
+        draft.set_tags("files, listing");
+
+        let first = draft.save(dir.path()).unwrap();
+        assert_eq!(first, dir.path().join("list_projects.yaml"));
+        let report = WorkflowLinter::new().lint_paths(std::slice::from_ref(&first));
+        assert!(!report.has_errors(), "{:?}", report.issues);
+        assert_eq!(draft.save(dir.path()).unwrap(), dir.path().join("list_projects_2.yaml"));
+
+        let workflow: Workflow = serde_yaml::from_str(&std::fs::read_to_string(&first).unwrap()).unwrap();
+        assert_eq!(workflow.command, "ls -la {{path}}");
+        assert_eq!(workflow.tags, vec!["files".to_string(), "listing".to_string()]);
+
+        draft.name = "  ".to_string();
+        assert_eq!(draft.save(dir.path()), Err(WorkflowDraftError::EmptyName));
+    }
+}
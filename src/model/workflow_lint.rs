@@ -0,0 +1,598 @@
+//! Workflow Lint - Check workflow specs for mistakes before they ship
+//!
+//! The linter parses each YAML file and checks that command placeholders
+//! match the declared arguments, that `shells` only names supported shells,
+//! that workflows and arguments are described, that no two workflows share a
+//! name, and that every command parses in each shell it declares.
+//! Known errors in specs that are kept as contributed can be listed in a
+//! [`LintAllowlist`]; they are still reported, as warnings.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Serialize;
+use walkdir::WalkDir;
+use warp_workflows_types::{Shell, Workflow};
+
+use crate::command::workflow_runner::WorkflowRun;
+use crate::model::workflow_params::WorkflowParameterHandler;
+use crate::syntax_tree::{parse_shell, ShellDialect};
+
+/// Values accepted in a workflow's `shells` list
+const VALID_SHELLS: &[&str] = &["Bash", "bash", "Zsh", "zsh", "Fish", "fish"];
+
+/// How serious a lint issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum LintSeverity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for LintSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintSeverity::Warning => f.write_str("warning"),
+            LintSeverity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// The check that produced an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum LintRule {
+    /// The file is not valid YAML or not a workflow
+    InvalidYaml,
+    /// A `{{placeholder}}` has no matching argument
+    UndeclaredPlaceholder,
+    /// An argument is never used by the command
+    UnusedArgument,
+    /// `shells` names a shell that is not supported
+    InvalidShell,
+    /// The workflow or one of its arguments has no description
+    MissingDescription,
+    /// Another workflow has the same name
+    DuplicateName,
+    /// The command does not parse in one of its shells
+    ShellSyntax,
+    /// Argument types, patterns, defaults or dependencies are inconsistent
+    InvalidArgument,
+    /// Step names or conditions are invalid
+    InvalidSteps,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 9] = [
+        LintRule::InvalidYaml,
+        LintRule::UndeclaredPlaceholder,
+        LintRule::UnusedArgument,
+        LintRule::InvalidShell,
+        LintRule::MissingDescription,
+        LintRule::DuplicateName,
+        LintRule::ShellSyntax,
+        LintRule::InvalidArgument,
+        LintRule::InvalidSteps,
+    ];
+
+    /// The rule called `name`, as written in reports
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::InvalidYaml => "invalid-yaml",
+            LintRule::UndeclaredPlaceholder => "undeclared-placeholder",
+            LintRule::UnusedArgument => "unused-argument",
+            LintRule::InvalidShell => "invalid-shell",
+            LintRule::MissingDescription => "missing-description",
+            LintRule::DuplicateName => "duplicate-name",
+            LintRule::ShellSyntax => "shell-syntax",
+            LintRule::InvalidArgument => "invalid-argument",
+            LintRule::InvalidSteps => "invalid-steps",
+        }
+    }
+
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            LintRule::UnusedArgument | LintRule::MissingDescription => LintSeverity::Warning,
+            _ => LintSeverity::Error,
+        }
+    }
+}
+
+/// One problem found in a workflow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    /// File the workflow came from, if it was read from disk
+    pub path: Option<PathBuf>,
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn new(rule: LintRule, message: impl Into<String>) -> Self {
+        Self {
+            path: None,
+            rule,
+            severity: rule.severity(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.rule.name(), self.message)
+    }
+}
+
+/// Issues found across a set of workflow files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    pub files_checked: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn error_count(&self) -> usize {
+        self.count(LintSeverity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(LintSeverity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn count(&self, severity: LintSeverity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity).count()
+    }
+}
+
+/// Errors known to be in specs that are kept as they were contributed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintAllowlist {
+    entries: Vec<(LintRule, PathBuf)>,
+}
+
+impl LintAllowlist {
+    /// Parse lines of `<rule> <path>`, where the path is the end of the
+    /// spec's path such as `brew/tap.yaml`. Blank lines and `#` comments
+    /// are ignored.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (rule, path) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("line {}: expected '<rule> <path>'", number + 1))?;
+            let rule = LintRule::from_name(rule)
+                .ok_or_else(|| format!("line {}: unknown rule '{}'", number + 1, rule))?;
+            entries.push((rule, PathBuf::from(path.trim())));
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Whether `rule` is allowed to fail for the spec at `path`
+    pub fn allows(&self, rule: LintRule, path: &Path) -> bool {
+        self.entries
+            .iter()
+            .any(|(allowed, suffix)| *allowed == rule && path.ends_with(suffix))
+    }
+}
+
+/// Checks workflow specs
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowLinter {
+    /// Also report warnings such as missing descriptions
+    pub include_warnings: bool,
+    /// Errors reported as warnings
+    pub allowlist: LintAllowlist,
+}
+
+impl WorkflowLinter {
+    pub fn new() -> Self {
+        Self {
+            include_warnings: true,
+            allowlist: LintAllowlist::default(),
+        }
+    }
+
+    pub fn with_warnings(mut self, include_warnings: bool) -> Self {
+        self.include_warnings = include_warnings;
+        self
+    }
+
+    pub fn with_allowlist(mut self, allowlist: LintAllowlist) -> Self {
+        self.allowlist = allowlist;
+        self
+    }
+
+    /// Lint every `.yaml`/`.yml` file in `paths`, descending into directories
+    pub fn lint_paths(&self, paths: &[PathBuf]) -> LintReport {
+        let mut report = LintReport::default();
+        let mut names: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+        for path in paths {
+            let files = WalkDir::new(path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file() && is_workflow_file(entry.path()));
+            for entry in files {
+                report.files_checked += 1;
+                let (workflow, issues) = match std::fs::read_to_string(entry.path()) {
+                    Ok(content) => self.lint_source(&content),
+                    Err(e) => (None, vec![LintIssue::new(LintRule::InvalidYaml, e.to_string())]),
+                };
+                if let Some(workflow) = workflow {
+                    names
+                        .entry(workflow.name.trim().to_string())
+                        .or_default()
+                        .push(entry.path().to_path_buf());
+                }
+                report.issues.extend(issues.into_iter().map(|issue| LintIssue {
+                    path: Some(entry.path().to_path_buf()),
+                    ..issue
+                }));
+            }
+        }
+
+        for (name, files) in names.into_iter().filter(|(_, files)| files.len() > 1) {
+            for (i, file) in files.iter().enumerate() {
+                let others: Vec<String> = files
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| other.display().to_string())
+                    .collect();
+                report.issues.push(LintIssue {
+                    path: Some(file.clone()),
+                    ..LintIssue::new(
+                        LintRule::DuplicateName,
+                        format!("workflow name '{}' is also used by {}", name, others.join(", ")),
+                    )
+                });
+            }
+        }
+
+        for issue in &mut report.issues {
+            let allowed = issue
+                .path
+                .as_deref()
+                .is_some_and(|path| self.allowlist.allows(issue.rule, path));
+            if allowed && issue.severity == LintSeverity::Error {
+                issue.severity = LintSeverity::Warning;
+                issue.message.push_str(" (allowlisted)");
+            }
+        }
+
+        if !self.include_warnings {
+            report.issues.retain(|issue| issue.severity == LintSeverity::Error);
+        }
+        report
+    }
+
+    /// Lint the YAML text of one workflow. Returns the workflow when it
+    /// could be parsed.
+    pub fn lint_source(&self, content: &str) -> (Option<Workflow>, Vec<LintIssue>) {
+        let mut value: serde_yaml::Value = match serde_yaml::from_str(content) {
+            Ok(value) => value,
+            Err(e) => return (None, vec![LintIssue::new(LintRule::InvalidYaml, e.to_string())]),
+        };
+
+        // Report unknown shells by name rather than as a generic parse error
+        let mut issues = Vec::new();
+        if let Some(shells) = value.get_mut("shells").and_then(|shells| shells.as_sequence_mut()) {
+            shells.retain(|shell| match shell.as_str() {
+                Some(name) if VALID_SHELLS.contains(&name) => true,
+                other => {
+                    issues.push(LintIssue::new(
+                        LintRule::InvalidShell,
+                        format!(
+                            "unknown shell {}; expected one of Bash, Zsh or Fish",
+                            other.map_or_else(|| format!("{:?}", shell), |name| format!("'{}'", name))
+                        ),
+                    ));
+                    false
+                }
+            });
+        }
+
+        // Parse the text rather than the value so unquoted numbers such as
+        // `default_value: 8080` still read as strings
+        let parsed = if issues.is_empty() {
+            serde_yaml::from_str::<Workflow>(content)
+        } else {
+            serde_yaml::to_string(&value).and_then(|text| serde_yaml::from_str::<Workflow>(&text))
+        };
+        match parsed {
+            Ok(workflow) => {
+                issues.extend(self.lint_workflow(&workflow));
+                (Some(workflow), issues)
+            }
+            Err(e) => {
+                issues.push(LintIssue::new(LintRule::InvalidYaml, e.to_string()));
+                (None, issues)
+            }
+        }
+    }
+
+    /// Lint a parsed workflow
+    pub fn lint_workflow(&self, workflow: &Workflow) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+
+        if workflow.description.as_deref().is_none_or(|d| d.trim().is_empty()) {
+            issues.push(LintIssue::new(LintRule::MissingDescription, "workflow has no description"));
+        }
+        for arg in &workflow.arguments {
+            if arg.description.as_deref().is_none_or(|d| d.trim().is_empty()) {
+                issues.push(LintIssue::new(
+                    LintRule::MissingDescription,
+                    format!("argument '{}' has no description", arg.name),
+                ));
+            }
+        }
+
+        Self::check_placeholders(workflow, &mut issues);
+
+        if let Err(e) = WorkflowParameterHandler::validate_workflow(workflow) {
+            issues.push(LintIssue::new(LintRule::InvalidArgument, e.to_string()));
+        }
+        if workflow.is_multi_step() {
+            if let Err(e) = WorkflowRun::new(workflow) {
+                issues.push(LintIssue::new(LintRule::InvalidSteps, e.to_string()));
+            }
+        } else if workflow.command.trim().is_empty() {
+            issues.push(LintIssue::new(LintRule::InvalidSteps, "workflow has neither a command nor steps"));
+        }
+
+        for step in workflow.resolved_steps() {
+            for dialect in dialects(&workflow.shells) {
+                let tree = parse_shell(&neutralize_placeholders(&step.command), dialect);
+                if let Some(error) = tree.errors().first() {
+                    let location = if workflow.is_multi_step() {
+                        format!("step '{}'", step.name)
+                    } else {
+                        "command".to_string()
+                    };
+                    issues.push(LintIssue::new(
+                        LintRule::ShellSyntax,
+                        format!("{} does not parse as {:?}: {}", location, dialect, error.message),
+                    ));
+                }
+            }
+        }
+
+        if !self.include_warnings {
+            issues.retain(|issue| issue.severity == LintSeverity::Error);
+        }
+        issues
+    }
+
+    /// Placeholders must be declared arguments, or outputs of earlier steps,
+    /// and every argument should be used
+    fn check_placeholders(workflow: &Workflow, issues: &mut Vec<LintIssue>) {
+        let arguments: BTreeSet<&str> = workflow.arguments.iter().map(|arg| arg.name.as_str()).collect();
+        let mut available = arguments.clone();
+        let mut used = BTreeSet::new();
+
+        for step in workflow.resolved_steps() {
+            for name in placeholders(&step.command) {
+                if !available.contains(name.as_str()) {
+                    issues.push(LintIssue::new(
+                        LintRule::UndeclaredPlaceholder,
+                        format!("'{{{{{}}}}}' is not a declared argument", name),
+                    ));
+                }
+                used.insert(name);
+            }
+            if let Some(output) = workflow.steps.iter().find(|s| s.name == step.name).and_then(|s| s.output.as_deref()) {
+                available.insert(output);
+            }
+        }
+
+        // Arguments referenced only from other arguments still count as used
+        for arg in &workflow.arguments {
+            used.extend(WorkflowParameterHandler::dependencies(arg));
+        }
+        for name in arguments.iter().filter(|name| !used.contains(**name)) {
+            issues.push(LintIssue::new(
+                LintRule::UnusedArgument,
+                format!("argument '{}' is not used by the command", name),
+            ));
+        }
+    }
+}
+
+/// Placeholder names in `command`. Braces around a placeholder, as in
+/// `${{{array}}[@]}`, belong to the command, not the name.
+fn placeholders(command: &str) -> Vec<String> {
+    let re = Regex::new(r"\{\{([^{}]+)\}\}").unwrap();
+    re.captures_iter(command).map(|cap| cap[1].to_string()).collect()
+}
+
+/// Replace placeholders with their names so the shell parser sees plain words
+fn neutralize_placeholders(command: &str) -> String {
+    let re = Regex::new(r"\{\{([^{}]+)\}\}").unwrap();
+    re.replace_all(command, |caps: &regex::Captures| {
+        caps[1]
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+            .collect::<String>()
+    })
+    .into_owned()
+}
+
+/// Dialects to check a command in; bash when no shells are declared
+fn dialects(shells: &[Shell]) -> Vec<ShellDialect> {
+    if shells.is_empty() {
+        return vec![ShellDialect::Bash];
+    }
+    let mut dialects: Vec<ShellDialect> = Vec::new();
+    for shell in shells {
+        let dialect = match shell {
+            Shell::Bash => ShellDialect::Bash,
+            Shell::Zsh => ShellDialect::Zsh,
+            Shell::Fish => ShellDialect::Fish,
+        };
+        if !dialects.contains(&dialect) {
+            dialects.push(dialect);
+        }
+    }
+    dialects
+}
+
+fn is_workflow_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("yaml") | Some("yml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(issues: &[LintIssue]) -> Vec<LintRule> {
+        issues.iter().map(|issue| issue.rule).collect()
+    }
+
+    #[test]
+    fn test_clean_workflow() {
+        let yaml = r#"
+name: Clone a repository
+command: git clone {{url}}
+description: Clones a repository
+arguments:
+  - name: url
+    description: The repository URL
+shells: [Bash, zsh]
+"#;
+        let (workflow, issues) = WorkflowLinter::new().lint_source(yaml);
+        assert!(workflow.is_some());
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_placeholder_and_description_checks() {
+        let yaml = r#"
+name: Copy
+command: cp {{source}} {{destination}} && echo ${{{array}}[@]}
+arguments:
+  - name: source
+    description: File to copy
+  - name: dest
+  - name: array
+    description: Array to print
+"#;
+        let (_, issues) = WorkflowLinter::new().lint_source(yaml);
+        assert_eq!(
+            rules(&issues),
+            vec![
+                LintRule::MissingDescription,
+                LintRule::MissingDescription,
+                LintRule::UndeclaredPlaceholder,
+                LintRule::UnusedArgument,
+            ]
+        );
+        assert!(issues[2].message.contains("{{destination}}"));
+        assert!(issues[3].message.contains("'dest'"));
+
+        let errors_only = WorkflowLinter::new().with_warnings(false).lint_source(yaml).1;
+        assert_eq!(rules(&errors_only), vec![LintRule::UndeclaredPlaceholder]);
+    }
+
+    #[test]
+    fn test_invalid_shell_and_syntax() {
+        let yaml = r#"
+name: Broken
+description: Unbalanced quotes
+command: echo "hello
+shells: [bash, pwsh]
+"#;
+        let (workflow, issues) = WorkflowLinter::new().lint_source(yaml);
+        assert_eq!(workflow.unwrap().shells, vec![Shell::Bash]);
+        assert_eq!(rules(&issues), vec![LintRule::InvalidShell, LintRule::ShellSyntax]);
+        assert!(issues[0].message.contains("'pwsh'"));
+
+        let (workflow, issues) = WorkflowLinter::new().lint_source("name: [unclosed");
+        assert!(workflow.is_none());
+        assert_eq!(rules(&issues), vec![LintRule::InvalidYaml]);
+    }
+
+    #[test]
+    fn test_step_outputs_count_as_declared() {
+        let workflow = Workflow::new("Tag head", "")
+            .with_description("Tags the current commit".to_string())
+            .with_steps(vec![
+                warp_workflows_types::WorkflowStep::new("sha", "git rev-parse HEAD").with_output("sha"),
+                warp_workflows_types::WorkflowStep::new("tag", "git tag release {{sha}}"),
+                warp_workflows_types::WorkflowStep::new("bad", "echo (").with_condition("sometimes"),
+            ]);
+        let issues = WorkflowLinter::new().lint_workflow(&workflow);
+        assert_eq!(rules(&issues), vec![LintRule::InvalidSteps, LintRule::ShellSyntax]);
+    }
+
+    #[test]
+    fn test_duplicate_names_across_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for file in ["a.yaml", "b.yml"] {
+            std::fs::write(
+                dir.path().join(file),
+                "name: Same\ndescription: Twice\ncommand: echo hi\n",
+            )
+            .unwrap();
+        }
+        let report = WorkflowLinter::new().lint_paths(&[dir.path().to_path_buf()]);
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.error_count(), 2);
+        assert_eq!(report.issues[0].path.as_deref(), Some(dir.path().join("a.yaml").as_path()));
+        assert!(report.issues[0].to_string().contains("error[duplicate-name]"));
+    }
+
+    #[test]
+    fn test_allowlisted_errors_are_warnings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("brew")).unwrap();
+        std::fs::write(dir.path().join("brew/tap.yaml"), "name: Tap\ncommand: brew tap {{repo}}\n").unwrap();
+        let allowlist = LintAllowlist::parse("# contributed\nundeclared-placeholder brew/tap.yaml\n").unwrap();
+
+        let report = WorkflowLinter::new().lint_paths(&[dir.path().to_path_buf()]);
+        assert_eq!(report.error_count(), 1);
+        let report = WorkflowLinter::new()
+            .with_allowlist(allowlist)
+            .lint_paths(&[dir.path().to_path_buf()]);
+        assert_eq!(report.error_count(), 0);
+        let issue = report.issues.iter().find(|issue| issue.rule == LintRule::UndeclaredPlaceholder).unwrap();
+        assert!(issue.to_string().contains("warning[undeclared-placeholder]"));
+
+        assert!(LintAllowlist::parse("no-such-rule a.yaml").unwrap_err().contains("unknown rule"));
+        assert!(LintAllowlist::parse("duplicate-name").is_err());
+    }
+
+    #[test]
+    fn test_bundled_specs_have_no_errors() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let report = WorkflowLinter::new()
+            .with_warnings(false)
+            .with_allowlist(LintAllowlist::load(&root.join("workflows/lint-allowlist.txt")).unwrap())
+            .lint_paths(&[root.join("workflows/specs")]);
+        assert!(report.files_checked > 0);
+        let errors: Vec<String> = report.issues.iter().map(ToString::to_string).collect();
+        assert!(errors.is_empty(), "{}", errors.join("\n"));
+    }
+}
@@ -1,15 +1,154 @@
 //! Workflow Loader - Load and register workflows from YAML files into Command Palette
 //!
-//! This module provides functionality to dynamically load workflows from the 
-//! workflows/specs directory and register them as commands in the Command Palette.
+//! Workflows are read at runtime from layered directories: the bundled
+//! `workflows/specs`, a team directory, the user's `~/.warp/workflows` and a
+//! repository's `.warp/workflows`. A workflow in a later layer replaces one
+//! with the same ID in an earlier layer, and every such clash is reported as
+//! a [`WorkflowConflict`]. The team, user and repository directories are
+//! polled for changes so edited workflows show up without a restart; the
+//! bundled specs only change with the application and are not watched.
 
 use crate::model::command_registry::{Command, CommandCategory, CommandRegistry};
+use crate::watcher::{PollingWatcher, WatchEvent, WatcherConfig};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
+use walkdir::WalkDir;
 use warp_workflows_types::Workflow;
 
+/// Environment variable naming the team workflow directory
+pub const TEAM_WORKFLOWS_ENV: &str = "WARP_TEAM_WORKFLOWS";
+
+/// Where a layer of workflows comes from, in increasing precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum WorkflowSourceKind {
+    /// Shipped with the terminal
+    Bundled,
+    /// Shared by a team, e.g. a synced or mounted directory
+    Team,
+    /// The user's own `~/.warp/workflows`
+    User,
+    /// Versioned with the current project in `.warp/workflows`
+    Repo,
+}
+
+impl fmt::Display for WorkflowSourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            WorkflowSourceKind::Bundled => "bundled",
+            WorkflowSourceKind::Team => "team",
+            WorkflowSourceKind::User => "user",
+            WorkflowSourceKind::Repo => "repo",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A directory workflows are loaded from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowSource {
+    pub kind: WorkflowSourceKind,
+    pub path: PathBuf,
+}
+
+/// A workflow together with the file it was loaded from
+#[derive(Debug, Clone)]
+pub struct LoadedWorkflow {
+    pub id: String,
+    pub workflow: Workflow,
+    pub path: PathBuf,
+    pub source: WorkflowSourceKind,
+}
+
+/// Several files defining the same workflow ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowConflict {
+    pub id: String,
+    /// The file whose workflow is used
+    pub winner: PathBuf,
+    pub winner_source: WorkflowSourceKind,
+    /// Files whose workflow is ignored
+    pub shadowed: Vec<(WorkflowSourceKind, PathBuf)>,
+}
+
+impl fmt::Display for WorkflowConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} from {} ({}) shadows",
+            self.id,
+            self.winner.display(),
+            self.winner_source
+        )?;
+        for (i, (kind, path)) in self.shadowed.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{}{} ({})", separator, path.display(), kind)?;
+        }
+        Ok(())
+    }
+}
+
+/// A poll of the watched workflow directories, run off the UI thread with
+/// [`WorkflowPoll::run_async`] and handed back to
+/// [`WorkflowLoader::finish_poll`]
+#[derive(Debug, Clone)]
+pub struct WorkflowPoll {
+    watcher: PollingWatcher,
+    sources: Vec<WorkflowSource>,
+    /// Layers with workflow files created, changed or removed
+    pub changed: Vec<WorkflowSourceKind>,
+}
+
+impl WorkflowPoll {
+    /// Compare the directories with the previous poll
+    pub fn run(mut self) -> Self {
+        let changes = self.watcher.poll();
+        if !changes.is_empty() {
+            debug!("Workflow directory changes: {:?}", changes);
+        }
+        let mut changed: Vec<WorkflowSourceKind> = changes
+            .iter()
+            .flat_map(|event| match event {
+                WatchEvent::Created { path } | WatchEvent::Modified { path } | WatchEvent::Deleted { path } => {
+                    vec![path]
+                }
+                WatchEvent::Renamed { from, to } => vec![from, to],
+            })
+            .filter(|path| is_workflow_path(path))
+            .filter_map(|path| {
+                self.sources
+                    .iter()
+                    .filter(|source| path.starts_with(&source.path))
+                    .max_by_key(|source| source.path.components().count())
+                    .map(|source| source.kind)
+            })
+            .collect();
+        changed.sort();
+        changed.dedup();
+        self.changed = changed;
+        self
+    }
+
+    /// [`run`](Self::run) on the blocking thread pool, so that a slow or
+    /// remote home directory never stalls the UI. `None` if the task failed.
+    pub async fn run_async(self) -> Option<Self> {
+        tokio::task::spawn_blocking(move || self.run()).await.ok()
+    }
+}
+
+/// Every layer read and parsed by [`WorkflowLoader::scan`]
+#[derive(Debug, Clone)]
+pub struct WorkflowScan {
+    sources: Vec<WorkflowSource>,
+    workflows: BTreeMap<String, LoadedWorkflow>,
+    commands: Vec<Command>,
+    watcher: PollingWatcher,
+    pub stats: WorkflowStats,
+}
+
 /// Workflow loader for integrating YAML workflows into Command Palette
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowLoader {
@@ -17,6 +156,18 @@ pub struct WorkflowLoader {
     pub specs_directory: String,
     /// Number of workflows loaded
     pub loaded_count: usize,
+    /// Directories layered over the specs directory, lowest precedence first
+    #[serde(default)]
+    pub sources: Vec<WorkflowSource>,
+    /// Workflows by ID after precedence is applied
+    #[serde(skip)]
+    workflows: BTreeMap<String, LoadedWorkflow>,
+    /// Clashes found by the last load
+    #[serde(skip)]
+    conflicts: Vec<WorkflowConflict>,
+    /// Notices edits to the source directories
+    #[serde(skip)]
+    watcher: Option<PollingWatcher>,
 }
 
 /// Workflow loading statistics
@@ -26,6 +177,8 @@ pub struct WorkflowStats {
     pub workflows_loaded: usize,
     pub workflows_failed: usize,
     pub categories_found: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<WorkflowConflict>,
 }
 
 impl WorkflowLoader {
@@ -34,94 +187,280 @@ impl WorkflowLoader {
         Self {
             specs_directory: specs_directory.into(),
             loaded_count: 0,
+            sources: Vec::new(),
+            workflows: BTreeMap::new(),
+            conflicts: Vec::new(),
+            watcher: None,
+        }
+    }
+
+    /// The bundled specs layered with the team, user and repository
+    /// directories that apply to `cwd`
+    pub fn with_default_sources(specs_directory: impl Into<String>, cwd: &Path) -> Self {
+        let mut loader = Self::new(specs_directory);
+        if let Some(team) = std::env::var_os(TEAM_WORKFLOWS_ENV).filter(|dir| !dir.is_empty()) {
+            loader = loader.with_source(WorkflowSourceKind::Team, PathBuf::from(team));
+        }
+        if let Some(user) = Self::user_workflows_dir() {
+            loader = loader.with_source(WorkflowSourceKind::User, user);
+        }
+        if let Some(repo) = Self::find_repo_workflows(cwd) {
+            loader = loader.with_source(WorkflowSourceKind::Repo, repo);
+        }
+        loader
+    }
+
+    /// The user's own workflow directory, `~/.warp/workflows`
+    pub fn user_workflows_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".warp").join("workflows"))
+    }
+
+    /// Add a directory of workflows. Layers are applied in precedence order
+    /// whatever order they are added in.
+    pub fn with_source(mut self, kind: WorkflowSourceKind, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(WorkflowSource {
+            kind,
+            path: path.into(),
+        });
+        self
+    }
+
+    /// `.warp/workflows` in `cwd` or the nearest parent, stopping at the
+    /// repository root
+    pub fn find_repo_workflows(cwd: &Path) -> Option<PathBuf> {
+        for dir in cwd.ancestors() {
+            let candidate = dir.join(".warp").join("workflows");
+            if candidate.is_dir() {
+                return Some(candidate);
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Point the repository layer at the `.warp/workflows` that applies to
+    /// `cwd`. Returns true when the layer changed and workflows need reloading.
+    pub fn update_repo_source(&mut self, cwd: &Path) -> bool {
+        let repo = Self::find_repo_workflows(cwd);
+        let current = self
+            .sources
+            .iter()
+            .find(|source| source.kind == WorkflowSourceKind::Repo)
+            .map(|source| &source.path);
+        if current == repo.as_ref() {
+            return false;
+        }
+
+        self.sources.retain(|source| source.kind != WorkflowSourceKind::Repo);
+        if let Some(path) = repo {
+            self.sources.push(WorkflowSource {
+                kind: WorkflowSourceKind::Repo,
+                path,
+            });
         }
+        true
+    }
+
+    /// Every layer, lowest precedence first
+    pub fn all_sources(&self) -> Vec<WorkflowSource> {
+        let mut sources = vec![WorkflowSource {
+            kind: WorkflowSourceKind::Bundled,
+            path: PathBuf::from(&self.specs_directory),
+        }];
+        sources.extend(self.sources.iter().cloned());
+        // Stable, so layers of one kind keep the order they were added in
+        sources.sort_by_key(|source| source.kind);
+        sources
+    }
+
+    /// Workflows by ID after precedence is applied
+    pub fn workflows(&self) -> &BTreeMap<String, LoadedWorkflow> {
+        &self.workflows
+    }
+
+    /// Clashes found by the last load
+    pub fn conflicts(&self) -> &[WorkflowConflict] {
+        &self.conflicts
     }
 
     /// Load all workflows from the specs directory into the command registry
     pub fn load_workflows(&mut self, registry: &mut CommandRegistry) -> Result<WorkflowStats, String> {
-        let specs_path = Path::new(&self.specs_directory);
-        
-        if !specs_path.exists() {
+        let scan = self.scan()?;
+        Ok(self.apply_scan(scan, registry))
+    }
+
+    /// Read and parse every layer without touching the loader, so that it
+    /// can run off the UI thread; [`Self::apply_scan`] installs the result
+    pub fn scan(&self) -> Result<WorkflowScan, String> {
+        let sources = self.all_sources();
+        if !sources.iter().any(|source| source.path.is_dir()) {
             return Err(format!("Workflow specs directory '{}' does not exist", self.specs_directory));
         }
 
-        info!("Loading workflows from: {}", self.specs_directory);
-
         let mut stats = WorkflowStats {
             total_files_scanned: 0,
             workflows_loaded: 0,
             workflows_failed: 0,
             categories_found: Vec::new(),
+            conflicts: Vec::new(),
         };
+        let mut workflows: BTreeMap<String, LoadedWorkflow> = BTreeMap::new();
+        let mut conflicts: BTreeMap<String, WorkflowConflict> = BTreeMap::new();
+
+        for source in &sources {
+            if !source.path.is_dir() {
+                debug!("Skipping missing {} workflow directory: {}", source.kind, source.path.display());
+                continue;
+            }
+            info!("Loading {} workflows from: {}", source.kind, source.path.display());
+            self.scan_source(source, &mut workflows, &mut conflicts, &mut stats);
+        }
+
+        let mut commands = Vec::with_capacity(workflows.len());
+        for loaded in workflows.values() {
+            let category_name = self.extract_category_from_path(&loaded.path);
+            if !stats.categories_found.contains(&category_name) {
+                stats.categories_found.push(category_name);
+            }
+            commands.push(self.workflow_to_command(&loaded.id, loaded.workflow.clone())?);
+            debug!("Loaded workflow: {} ({})", loaded.id, loaded.path.display());
+        }
 
-        self.scan_directory_recursive(specs_path, registry, &mut stats)?;
+        for conflict in conflicts.values() {
+            warn!("Workflow conflict: {}", conflict);
+        }
+        stats.workflows_loaded = workflows.len();
+        stats.conflicts = conflicts.into_values().collect();
 
         info!(
-            "Workflow loading complete: {} loaded, {} failed, {} files scanned",
-            stats.workflows_loaded, stats.workflows_failed, stats.total_files_scanned
+            "Workflow loading complete: {} loaded, {} failed, {} conflicts, {} files scanned",
+            stats.workflows_loaded,
+            stats.workflows_failed,
+            stats.conflicts.len(),
+            stats.total_files_scanned
         );
 
-        self.loaded_count = stats.workflows_loaded;
-        Ok(stats)
+        Ok(WorkflowScan {
+            sources,
+            workflows,
+            commands,
+            watcher: PollingWatcher::new(&self.watcher_config()),
+            stats,
+        })
+    }
+
+    /// [`scan`](Self::scan) a snapshot of the loader on the blocking thread
+    /// pool
+    pub fn scan_async(&self) -> impl std::future::Future<Output = Result<WorkflowScan, String>> {
+        let loader = self.clone();
+        async move {
+            tokio::task::spawn_blocking(move || loader.scan())
+                .await
+                .map_err(|e| format!("Workflow scan failed: {}", e))?
+        }
+    }
+
+    /// Replace the loaded workflows and their palette commands with `scan`
+    pub fn apply_scan(&mut self, scan: WorkflowScan, registry: &mut CommandRegistry) -> WorkflowStats {
+        for command in registry.get_all_commands() {
+            if command.id.starts_with("workflow.") {
+                registry.unregister(&command.id);
+            }
+        }
+        for command in scan.commands {
+            registry.register_or_replace(command);
+        }
+
+        self.loaded_count = scan.stats.workflows_loaded;
+        self.workflows = scan.workflows;
+        self.conflicts = scan.stats.conflicts.clone();
+        self.watcher = Some(scan.watcher);
+        scan.stats
     }
 
-    /// Recursively scan directory for workflow files
-    fn scan_directory_recursive(
+    /// Whether `scan` read the layers the loader has now. A scan started
+    /// before the repository layer moved is out of date.
+    pub fn is_current(&self, scan: &WorkflowScan) -> bool {
+        scan.sources == self.all_sources()
+    }
+
+    /// Scan one layer, letting its workflows replace those of earlier layers
+    fn scan_source(
         &self,
-        dir: &Path,
-        registry: &mut CommandRegistry,
+        source: &WorkflowSource,
+        workflows: &mut BTreeMap<String, LoadedWorkflow>,
+        conflicts: &mut BTreeMap<String, WorkflowConflict>,
         stats: &mut WorkflowStats,
-    ) -> Result<(), String> {
-        let entries = fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory '{}': {}", dir.display(), e))?;
+    ) {
+        let entries = WalkDir::new(&source.path)
+            .follow_links(true)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Failed to read workflow directory entry: {}", e);
+                    None
+                }
+            });
 
         for entry in entries {
-            let entry = entry
-                .map_err(|e| format!("Failed to read directory entry: {}", e))?;
             let path = entry.path();
-
-            if path.is_dir() {
-                // Recursively scan subdirectories
-                self.scan_directory_recursive(&path, registry, stats)?;
-            } else if self.is_workflow_file(&path) {
-                stats.total_files_scanned += 1;
-                
-                match self.load_workflow_file(&path) {
-                    Ok(workflow) => {
-                        let workflow_id = self.generate_workflow_id(&path, &workflow);
-                        let category_name = self.extract_category_from_path(&path);
-                        
-                        if !stats.categories_found.contains(&category_name) {
-                            stats.categories_found.push(category_name);
-                        }
-
-                        let command = self.workflow_to_command(&workflow_id, workflow)?;
-                        
-                        match registry.register_or_replace(command) {
-                            _ => {
-                                stats.workflows_loaded += 1;
-                                debug!("Loaded workflow: {} ({})", workflow_id, path.display());
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        stats.workflows_failed += 1;
-                        warn!("Failed to load workflow '{}': {}", path.display(), e);
+            if !entry.file_type().is_file() || !self.is_workflow_file(path) {
+                continue;
+            }
+            stats.total_files_scanned += 1;
+
+            let workflow = match self.load_workflow_file(path) {
+                Ok(workflow) => workflow,
+                Err(e) => {
+                    stats.workflows_failed += 1;
+                    warn!("Failed to load workflow '{}': {}", path.display(), e);
+                    continue;
+                }
+            };
+            let id = Self::workflow_id(&source.path, path);
+            let loaded = LoadedWorkflow {
+                id: id.clone(),
+                workflow,
+                path: path.to_path_buf(),
+                source: source.kind,
+            };
+
+            match workflows.get(&id) {
+                None => {
+                    workflows.insert(id, loaded);
+                }
+                Some(existing) => {
+                    // Within one layer the first file wins; a later layer always wins
+                    let replaces = existing.source != source.kind;
+                    let (winner, loser) = if replaces {
+                        (&loaded, existing)
+                    } else {
+                        (existing, &loaded)
+                    };
+                    let conflict = conflicts.entry(id.clone()).or_insert_with(|| WorkflowConflict {
+                        id: id.clone(),
+                        winner: winner.path.clone(),
+                        winner_source: winner.source,
+                        shadowed: Vec::new(),
+                    });
+                    conflict.winner = winner.path.clone();
+                    conflict.winner_source = winner.source;
+                    conflict.shadowed.push((loser.source, loser.path.clone()));
+                    if replaces {
+                        workflows.insert(id, loaded);
                     }
                 }
             }
         }
-
-        Ok(())
     }
 
     /// Check if file is a workflow file
     fn is_workflow_file(&self, path: &Path) -> bool {
-        if let Some(extension) = path.extension() {
-            matches!(extension.to_str(), Some("yaml") | Some("yml"))
-        } else {
-            false
-        }
+        is_workflow_path(path)
     }
 
     /// Load a single workflow file
@@ -133,9 +472,10 @@ impl WorkflowLoader {
             .map_err(|e| format!("Failed to parse YAML in '{}': {}", path.display(), e))
     }
 
-    /// Generate a unique workflow ID based on path and content
-    fn generate_workflow_id(&self, path: &Path, _workflow: &Workflow) -> String {
-        let category = self.extract_category_from_path(path);
+    /// Generate a unique workflow ID, `workflow.<category>.<file name>`,
+    /// for a file below the source directory `root`
+    fn workflow_id(root: &Path, path: &Path) -> String {
+        let category = Self::category_below(root, path);
         let filename = path
             .file_stem()
             .and_then(|s| s.to_str())
@@ -152,10 +492,16 @@ impl WorkflowLoader {
 
     /// Extract category from file path
     fn extract_category_from_path(&self, path: &Path) -> String {
-        let specs_path = Path::new(&self.specs_directory);
-        
-        // Get relative path from specs directory
-        if let Ok(relative_path) = path.strip_prefix(specs_path) {
+        match self.source_root(path) {
+            Some(root) => Self::category_below(&root, path),
+            None => "general".to_string(),
+        }
+    }
+
+    /// The first directory below `root` on the way to `path`
+    fn category_below(root: &Path, path: &Path) -> String {
+        // Get relative path from the source directory
+        if let Ok(relative_path) = path.strip_prefix(root) {
             if let Some(first_component) = relative_path.components().next() {
                 if let Some(category_str) = first_component.as_os_str().to_str() {
                     return category_str.to_lowercase();
@@ -166,6 +512,15 @@ impl WorkflowLoader {
         "general".to_string()
     }
 
+    /// The most specific source directory containing `path`
+    fn source_root(&self, path: &Path) -> Option<PathBuf> {
+        self.all_sources()
+            .into_iter()
+            .map(|source| source.path)
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
     /// Convert a Workflow to a Command
     fn workflow_to_command(&self, workflow_id: &str, workflow: Workflow) -> Result<Command, String> {
         // Extract keywords from tags and command
@@ -202,6 +557,10 @@ impl WorkflowLoader {
 
     /// Get workflow details by ID for execution
     pub fn get_workflow_by_id(&self, workflow_id: &str) -> Result<Workflow, String> {
+        if let Some(loaded) = self.workflows.get(workflow_id) {
+            return Ok(loaded.workflow.clone());
+        }
+
         // Extract category and filename from workflow ID
         let parts: Vec<&str> = workflow_id.split('.').collect();
         if parts.len() != 3 || parts[0] != "workflow" {
@@ -211,36 +570,81 @@ impl WorkflowLoader {
         let category = parts[1];
         let filename = parts[2];
         
-        // Construct potential file paths
-        let possible_paths = vec![
-            format!("{}/{}/{}.yaml", self.specs_directory, category, filename),
-            format!("{}/{}/{}.yml", self.specs_directory, category, filename),
-        ];
-
-        for path_str in possible_paths {
-            let path = Path::new(&path_str);
-            if path.exists() {
-                return self.load_workflow_file(path);
+        // Fall back to the file in a layer, highest precedence first
+        for source in self.all_sources().iter().rev() {
+            for extension in ["yaml", "yml"] {
+                let path = source.path.join(category).join(format!("{}.{}", filename, extension));
+                if path.exists() {
+                    return self.load_workflow_file(&path);
+                }
             }
         }
 
         Err(format!("Workflow file not found for ID: {}", workflow_id))
     }
 
-    /// Reload workflows (useful for development)
+    /// Reload workflows on the calling thread. The UI uses
+    /// [`Self::scan_async`] and [`Self::apply_scan`].
     pub fn reload_workflows(&mut self, registry: &mut CommandRegistry) -> Result<WorkflowStats, String> {
         info!("Reloading workflows...");
-        
-        // Remove existing workflow commands
-        let all_commands = registry.get_all_commands();
-        for command in all_commands {
-            if command.id.starts_with("workflow.") {
-                registry.unregister(&command.id);
-            }
+        self.load_workflows(registry)
+    }
+
+    /// Reload the workflows if any workflow file was created, changed or
+    /// removed since the last load. Returns `None` when nothing changed.
+    /// Polls on the calling thread; the UI uses [`Self::begin_poll`].
+    pub fn reload_if_changed(&mut self, registry: &mut CommandRegistry) -> Option<Result<WorkflowStats, String>> {
+        let poll = self.begin_poll()?.run();
+        if !self.finish_poll(poll) {
+            return None;
         }
+        Some(self.reload_workflows(registry))
+    }
 
-        // Reload workflows
-        self.load_workflows(registry)
+    /// Take the watcher out for a poll on another thread. `None` before the
+    /// first load and while an earlier poll has not finished.
+    pub fn begin_poll(&mut self) -> Option<WorkflowPoll> {
+        let watcher = self.watcher.take()?;
+        Some(WorkflowPoll {
+            watcher,
+            sources: self.watched_sources(),
+            changed: Vec::new(),
+        })
+    }
+
+    /// Put the watcher back after a poll. Returns true when a layer changed
+    /// and the workflows need reloading. A watcher replaced by a reload
+    /// while the poll ran is kept.
+    pub fn finish_poll(&mut self, poll: WorkflowPoll) -> bool {
+        if self.watcher.is_none() {
+            self.watcher = Some(poll.watcher);
+        }
+        if !poll.changed.is_empty() {
+            info!("Workflow layers changed: {:?}", poll.changed);
+        }
+        !poll.changed.is_empty()
+    }
+
+    /// Start watching again after a poll was lost
+    pub fn restart_watcher(&mut self) {
+        if self.watcher.is_none() {
+            self.watcher = Some(PollingWatcher::new(&self.watcher_config()));
+        }
+    }
+
+    /// The layers that are watched: everything but the bundled specs
+    fn watched_sources(&self) -> Vec<WorkflowSource> {
+        self.all_sources()
+            .into_iter()
+            .filter(|source| source.kind != WorkflowSourceKind::Bundled)
+            .collect()
+    }
+
+    fn watcher_config(&self) -> WatcherConfig {
+        WatcherConfig {
+            watch_paths: self.watched_sources().into_iter().map(|source| source.path).collect(),
+            ..WatcherConfig::default()
+        }
     }
 
     /// Get loading statistics
@@ -249,6 +653,15 @@ impl WorkflowLoader {
     }
 }
 
+/// YAML files are workflows
+fn is_workflow_path(path: &Path) -> bool {
+    if let Some(extension) = path.extension() {
+        matches!(extension.to_str(), Some("yaml") | Some("yml"))
+    } else {
+        false
+    }
+}
+
 impl Default for WorkflowLoader {
     fn default() -> Self {
         Self::new("workflows/specs")
@@ -291,22 +704,174 @@ mod tests {
 
     #[test]
     fn test_generate_workflow_id() {
-        let loader = WorkflowLoader::new("workflows/specs");
-        
-        let workflow = Workflow {
-            name: "Test Workflow".to_string(),
-            command: "echo test".to_string(),
-            tags: vec![],
-            description: None,
-            arguments: vec![],
-            source_url: None,
-            author: None,
-            author_url: None,
-            shells: vec![],
-        };
-        
         let path = Path::new("workflows/specs/git/clone-with-ssh.yaml");
-        let id = loader.generate_workflow_id(path, &workflow);
+        let id = WorkflowLoader::workflow_id(Path::new("workflows/specs"), path);
         assert_eq!(id, "workflow.git.clone_with_ssh");
     }
+
+    fn write_workflow(dir: &Path, relative: &str, name: &str) {
+        let path = dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("name: {}\ncommand: echo {}\n", name, name)).unwrap();
+    }
+
+    #[test]
+    fn test_layered_sources_and_conflicts() {
+        let dir = TempDir::new().unwrap();
+        let (bundled, team, user, repo) = (
+            dir.path().join("bundled"),
+            dir.path().join("team"),
+            dir.path().join("user"),
+            dir.path().join("repo"),
+        );
+        write_workflow(&bundled, "git/sync.yaml", "Bundled sync");
+        write_workflow(&bundled, "git/log.yaml", "Bundled log");
+        write_workflow(&user, "git/sync.yaml", "User sync");
+        write_workflow(&repo, "git/sync.yml", "Repo sync");
+        write_workflow(&team, "deploy/ship.yaml", "Team ship");
+        write_workflow(&team, "deploy/ship-it.yaml", "Ship it");
+        write_workflow(&team, "deploy/ship_it.yaml", "Ship it too");
+
+        // Added out of order on purpose: precedence comes from the kind
+        let mut loader = WorkflowLoader::new(bundled.to_string_lossy())
+            .with_source(WorkflowSourceKind::Repo, &repo)
+            .with_source(WorkflowSourceKind::User, &user)
+            .with_source(WorkflowSourceKind::Team, &team)
+            .with_source(WorkflowSourceKind::User, dir.path().join("missing"));
+        let mut registry = CommandRegistry::new();
+        let stats = loader.load_workflows(&mut registry).unwrap();
+
+        assert_eq!(stats.total_files_scanned, 7);
+        assert_eq!(stats.workflows_loaded, 4);
+        assert_eq!(loader.get_workflow_by_id("workflow.git.sync").unwrap().name, "Repo sync");
+        assert_eq!(loader.workflows()["workflow.git.sync"].source, WorkflowSourceKind::Repo);
+        assert!(registry.get("workflow.deploy.ship").is_some());
+
+        let conflicts = loader.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].id, "workflow.deploy.ship_it");
+        assert_eq!(conflicts[0].winner, team.join("deploy/ship-it.yaml"));
+        assert_eq!(conflicts[1].id, "workflow.git.sync");
+        assert_eq!(conflicts[1].winner_source, WorkflowSourceKind::Repo);
+        assert_eq!(
+            conflicts[1].shadowed,
+            vec![
+                (WorkflowSourceKind::Bundled, bundled.join("git/sync.yaml")),
+                (WorkflowSourceKind::User, user.join("git/sync.yaml")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_directories() {
+        let dir = TempDir::new().unwrap();
+        let mut registry = CommandRegistry::new();
+
+        let mut loader = WorkflowLoader::new(dir.path().join("none").to_string_lossy());
+        assert!(loader.load_workflows(&mut registry).is_err());
+
+        write_workflow(&dir.path().join("user"), "misc/hello.yaml", "Hello");
+        let mut loader = loader.with_source(WorkflowSourceKind::User, dir.path().join("user"));
+        assert_eq!(loader.load_workflows(&mut registry).unwrap().workflows_loaded, 1);
+    }
+
+    #[test]
+    fn test_find_repo_workflows() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("project");
+        let nested = repo.join("src").join("bin");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(repo.join(".git")).unwrap();
+        assert_eq!(WorkflowLoader::find_repo_workflows(&nested), None);
+
+        // Directories above the repository root are not searched
+        fs::create_dir_all(dir.path().join(".warp/workflows")).unwrap();
+        assert_eq!(WorkflowLoader::find_repo_workflows(&nested), None);
+
+        fs::create_dir_all(repo.join(".warp/workflows")).unwrap();
+        assert_eq!(
+            WorkflowLoader::find_repo_workflows(&nested),
+            Some(repo.join(".warp/workflows"))
+        );
+
+        let mut loader = WorkflowLoader::default();
+        assert!(loader.update_repo_source(&nested));
+        assert!(!loader.update_repo_source(&repo));
+        assert!(loader.update_repo_source(dir.path()));
+        assert_eq!(loader.all_sources().last().unwrap().kind, WorkflowSourceKind::Repo);
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let bundled = TempDir::new().unwrap();
+        write_workflow(bundled.path(), "docker/ps.yaml", "Ps");
+        let dir = TempDir::new().unwrap();
+        write_workflow(dir.path(), "git/status.yaml", "Status");
+        let mut loader = WorkflowLoader::new(bundled.path().to_string_lossy())
+            .with_source(WorkflowSourceKind::User, dir.path());
+        let mut registry = CommandRegistry::new();
+        loader.load_workflows(&mut registry).unwrap();
+        assert!(loader.reload_if_changed(&mut registry).is_none());
+
+        fs::write(dir.path().join("git/notes.txt"), "not a workflow").unwrap();
+        assert!(loader.reload_if_changed(&mut registry).is_none());
+
+        write_workflow(dir.path(), "git/fetch.yaml", "Fetch");
+        fs::remove_file(dir.path().join("git/status.yaml")).unwrap();
+        let stats = loader.reload_if_changed(&mut registry).unwrap().unwrap();
+        assert_eq!(stats.workflows_loaded, 2);
+        assert!(registry.get("workflow.git.fetch").is_some());
+        assert!(registry.get("workflow.git.status").is_none());
+
+        // The bundled specs are not watched
+        write_workflow(bundled.path(), "docker/images.yaml", "Images");
+        assert!(loader.reload_if_changed(&mut registry).is_none());
+    }
+
+    #[test]
+    fn test_poll_reports_changed_layers() {
+        let bundled = TempDir::new().unwrap();
+        let team = TempDir::new().unwrap();
+        let user = TempDir::new().unwrap();
+        write_workflow(user.path(), "git/status.yaml", "Status");
+        let mut loader = WorkflowLoader::new(bundled.path().to_string_lossy())
+            .with_source(WorkflowSourceKind::Team, team.path())
+            .with_source(WorkflowSourceKind::User, user.path());
+        loader.load_workflows(&mut CommandRegistry::new()).unwrap();
+
+        write_workflow(user.path(), "git/fetch.yaml", "Fetch");
+        let poll = loader.begin_poll().unwrap();
+        // Only one poll at a time
+        assert!(loader.begin_poll().is_none());
+        let poll = poll.run();
+        assert_eq!(poll.changed, vec![WorkflowSourceKind::User]);
+        assert!(loader.finish_poll(poll));
+        assert!(loader.begin_poll().is_some());
+    }
+
+    #[test]
+    fn test_scan_is_applied_separately() {
+        let bundled = TempDir::new().unwrap();
+        write_workflow(bundled.path(), "docker/ps.yaml", "Ps");
+        let repo = TempDir::new().unwrap();
+        fs::create_dir_all(repo.path().join(".git")).unwrap();
+        write_workflow(&repo.path().join(".warp/workflows"), "git/status.yaml", "Status");
+        let mut loader = WorkflowLoader::new(bundled.path().to_string_lossy());
+        let mut registry = CommandRegistry::new();
+
+        let scan = loader.scan().unwrap();
+        assert!(registry.get("workflow.docker.ps").is_none());
+        assert!(loader.is_current(&scan));
+        assert_eq!(loader.apply_scan(scan, &mut registry).workflows_loaded, 1);
+        assert!(registry.get("workflow.docker.ps").is_some());
+
+        // A scan of the old layers is out of date once the repository layer moves
+        let scan = loader.scan().unwrap();
+        assert!(loader.update_repo_source(repo.path()));
+        assert!(!loader.is_current(&scan));
+        let scan = loader.scan().unwrap();
+        assert!(loader.is_current(&scan));
+        loader.apply_scan(scan, &mut registry);
+        assert!(registry.get("workflow.git.status").is_some());
+    }
 }
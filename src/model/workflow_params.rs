@@ -1,6 +1,44 @@
 use std::collections::HashMap;
-use warp_workflows_types::{Workflow, Argument};
+use std::path::PathBuf;
+use std::time::Duration;
+use warp_workflows_types::{Workflow, Argument, ArgumentType};
 use regex::Regex;
+use thiserror::Error;
+
+use crate::utils::process::run_with_deadline;
+use crate::syntax_tree::ShellDialect;
+
+/// How long a suggestion command may run before it is killed
+const SUGGESTION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Errors raised while filling in workflow parameters
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ParameterError {
+    #[error("No workflow is being filled in")]
+    NoWorkflow,
+    #[error("Unknown argument '{0}'")]
+    UnknownArgument(String),
+    #[error("'{value}' is not a whole number (argument '{name}')")]
+    InvalidInt { name: String, value: String },
+    #[error("'{value}' is not true or false (argument '{name}')")]
+    InvalidBoolean { name: String, value: String },
+    #[error("Argument '{name}' needs a path")]
+    EmptyPath { name: String },
+    #[error("'{value}' is not one of {} (argument '{name}')", options.join(", "))]
+    NotAnOption { name: String, value: String, options: Vec<String> },
+    #[error("'{value}' does not match '{pattern}' (argument '{name}')")]
+    PatternMismatch { name: String, value: String, pattern: String },
+    #[error("Invalid validation pattern for argument '{name}': {message}")]
+    InvalidPattern { name: String, message: String },
+    #[error("Argument '{name}' depends on unknown argument '{dependency}'")]
+    UnknownDependency { name: String, dependency: String },
+    #[error("Arguments depend on each other: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+    #[error("Fill in '{dependency}' before '{name}'")]
+    MissingDependency { name: String, dependency: String },
+    #[error("Suggestions for argument '{name}' failed: {message}")]
+    SuggestionsFailed { name: String, message: String },
+}
 
 /// Handles parameterization of workflow commands
 #[derive(Debug, Clone)]
@@ -9,8 +47,12 @@ pub struct WorkflowParameterHandler {
     pub workflow: Option<Workflow>,
     /// Parameter values provided by the user
     pub parameter_values: HashMap<String, String>,
-    /// Current parameter being filled (for UI state)
+    /// Current parameter being filled (for UI state), an index into the fill order
     pub current_param_index: usize,
+    /// Argument indices in the order they are filled: dependencies first
+    order: Vec<usize>,
+    /// Directory suggestion commands run in
+    working_dir: Option<PathBuf>,
 }
 
 impl Default for WorkflowParameterHandler {
@@ -25,20 +67,39 @@ impl WorkflowParameterHandler {
             workflow: None,
             parameter_values: HashMap::new(),
             current_param_index: 0,
+            order: Vec::new(),
+            working_dir: None,
         }
     }
 
+    /// Run suggestion commands in `dir` instead of the process directory
+    pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    pub fn set_working_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.working_dir = Some(dir.into());
+    }
+
     /// Set the current workflow and reset parameter state
     pub fn set_workflow(&mut self, workflow: Workflow) {
+        self.order = Self::fill_order(&workflow).unwrap_or_else(|e| {
+            tracing::warn!(workflow = %workflow.name, "{}; filling arguments in declaration order", e);
+            (0..workflow.arguments.len()).collect()
+        });
         self.workflow = Some(workflow);
         self.parameter_values.clear();
         self.current_param_index = 0;
-        
-        // Initialize with default values if available
+
+        // Initialize with default values if available. Defaults that refer to
+        // other arguments are resolved once those are filled in.
         if let Some(ref wf) = self.workflow {
             for arg in &wf.arguments {
                 if let Some(default_value) = &arg.default_value {
-                    self.parameter_values.insert(arg.name.clone(), default_value.clone());
+                    if !Self::command_has_parameters(default_value) {
+                        self.parameter_values.insert(arg.name.clone(), default_value.clone());
+                    }
                 }
             }
         }
@@ -51,16 +112,174 @@ impl WorkflowParameterHandler {
 
     /// Get the current parameter being filled
     pub fn get_current_parameter(&self) -> Option<&Argument> {
-        self.workflow.as_ref()
-            .and_then(|wf| wf.arguments.get(self.current_param_index))
+        let workflow = self.workflow.as_ref()?;
+        let index = *self.order.get(self.current_param_index)?;
+        workflow.arguments.get(index)
+    }
+
+    /// Validate the value for the current parameter, store it and advance to the next
+    pub fn set_current_parameter_value(&mut self, value: String) -> Result<(), ParameterError> {
+        let name = self
+            .get_current_parameter()
+            .map(|param| param.name.clone())
+            .ok_or(ParameterError::NoWorkflow)?;
+        self.set_parameter_value(&name, value)?;
+        self.current_param_index += 1;
+        Ok(())
+    }
+
+    /// Validate and store the value of a named parameter. Parameters that
+    /// depend on it are cleared when the value changes.
+    pub fn set_parameter_value(&mut self, name: &str, value: String) -> Result<(), ParameterError> {
+        let arg = self.argument(name)?;
+        self.check_dependencies(arg)?;
+        let value = Self::validate_value(arg, &value)?;
+
+        if self.parameter_values.get(name) != Some(&value) {
+            for dependent in self.dependents_of(name) {
+                self.parameter_values.remove(&dependent);
+            }
+        }
+        self.parameter_values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Check `value` against the argument's type, options and pattern,
+    /// returning it normalized (booleans become `true`/`false`)
+    pub fn validate_value(arg: &Argument, value: &str) -> Result<String, ParameterError> {
+        let name = arg.name.clone();
+        let value = match arg.arg_type {
+            ArgumentType::Text => value.to_string(),
+            ArgumentType::Int => match value.trim().parse::<i64>() {
+                Ok(number) => number.to_string(),
+                Err(_) => {
+                    return Err(ParameterError::InvalidInt { name, value: value.to_string() });
+                }
+            },
+            ArgumentType::Boolean => match value.trim().to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => "true".to_string(),
+                "false" | "no" | "n" | "0" => "false".to_string(),
+                _ => {
+                    return Err(ParameterError::InvalidBoolean { name, value: value.to_string() });
+                }
+            },
+            ArgumentType::Path => {
+                if value.trim().is_empty() {
+                    return Err(ParameterError::EmptyPath { name });
+                }
+                value.to_string()
+            }
+            ArgumentType::Enum => {
+                // Enums backed only by a suggestion command accept any value
+                if !arg.options.is_empty() && !arg.options.iter().any(|option| option == value) {
+                    return Err(ParameterError::NotAnOption {
+                        name,
+                        value: value.to_string(),
+                        options: arg.options.clone(),
+                    });
+                }
+                value.to_string()
+            }
+        };
+
+        if let Some(pattern) = &arg.validation {
+            if !Self::validation_regex(arg, pattern)?.is_match(&value) {
+                return Err(ParameterError::PatternMismatch {
+                    name: arg.name.clone(),
+                    value,
+                    pattern: pattern.clone(),
+                });
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Names of the arguments `arg` depends on: its `depends_on` list and any
+    /// argument referenced from its suggestion command or default value
+    pub fn dependencies(arg: &Argument) -> Vec<String> {
+        let referenced = arg
+            .suggestions
+            .iter()
+            .map(|source| source.command.as_str())
+            .chain(arg.default_value.as_deref())
+            .flat_map(Self::extract_parameter_names);
+
+        let mut dependencies: Vec<String> = Vec::new();
+        for name in arg.depends_on.iter().cloned().chain(referenced) {
+            if name != arg.name && !dependencies.contains(&name) {
+                dependencies.push(name);
+            }
+        }
+        dependencies
+    }
+
+    /// Check a workflow's argument definitions: dependencies must exist and
+    /// must not form a cycle, patterns must compile, and static defaults
+    /// must be valid values
+    pub fn validate_workflow(workflow: &Workflow) -> Result<(), ParameterError> {
+        for arg in &workflow.arguments {
+            for dependency in Self::dependencies(arg) {
+                if !workflow.arguments.iter().any(|other| other.name == dependency) {
+                    return Err(ParameterError::UnknownDependency {
+                        name: arg.name.clone(),
+                        dependency,
+                    });
+                }
+            }
+            if let Some(pattern) = &arg.validation {
+                Self::validation_regex(arg, pattern)?;
+            }
+            if let Some(default_value) = &arg.default_value {
+                if !Self::command_has_parameters(default_value) {
+                    Self::validate_value(arg, default_value)?;
+                }
+            }
+        }
+        Self::fill_order(workflow).map(|_| ())
     }
 
-    /// Set the value for the current parameter and advance to the next
-    pub fn set_current_parameter_value(&mut self, value: String) {
-        if let Some(param) = self.get_current_parameter() {
-            self.parameter_values.insert(param.name.clone(), value);
-            self.current_param_index += 1;
+    /// The default for a parameter, with references to other parameters
+    /// filled in. `None` until every referenced parameter has a value.
+    pub fn default_for(&self, name: &str) -> Option<String> {
+        let arg = self.argument(name).ok()?;
+        let default_value = arg.default_value.as_ref()?;
+        let rendered = self.render_template(default_value);
+        (!Self::command_has_parameters(&rendered)).then_some(rendered)
+    }
+
+    /// Values to offer for a parameter: the options of an enum, true/false
+    /// for booleans, entries of the working directory for paths, followed
+    /// by the output lines of the argument's suggestion command. Values
+    /// filled into the suggestion command are shell-quoted, so they reach
+    /// it as single words and never run as code.
+    pub fn suggestions_for(&self, name: &str) -> Result<Vec<String>, ParameterError> {
+        let arg = self.argument(name)?;
+        self.check_dependencies(arg)?;
+
+        let mut suggestions = match arg.arg_type {
+            ArgumentType::Enum => arg.options.clone(),
+            ArgumentType::Boolean => vec!["true".to_string(), "false".to_string()],
+            ArgumentType::Path if arg.suggestions.is_none() => self.directory_entries(),
+            _ => Vec::new(),
+        };
+
+        if let Some(source) = &arg.suggestions {
+            let command = self.render_template_with(&source.command, shell_quote);
+            let output = run_with_deadline(&command, self.working_dir.as_deref(), SUGGESTION_TIMEOUT).map_err(|message| {
+                ParameterError::SuggestionsFailed {
+                    name: arg.name.clone(),
+                    message,
+                }
+            })?;
+            for line in output.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                if !suggestions.iter().any(|existing| existing == line) {
+                    suggestions.push(line.to_string());
+                }
+            }
         }
+
+        Ok(suggestions)
     }
 
     /// Go back to the previous parameter
@@ -99,19 +318,9 @@ impl WorkflowParameterHandler {
 
     /// Replace parameters in the command string with their values
     pub fn render_command(&self) -> Option<String> {
-        if let Some(ref workflow) = self.workflow {
-            let mut command = workflow.command.clone();
-            
-            // Replace each parameter with its value
-            for (param_name, param_value) in &self.parameter_values {
-                let pattern = format!("{{{{{}}}}}", param_name);
-                command = command.replace(&pattern, param_value);
-            }
-            
-            Some(command)
-        } else {
-            None
-        }
+        self.workflow
+            .as_ref()
+            .map(|workflow| self.render_template(&workflow.command))
     }
 
     /// Extract parameter names from a command string
@@ -131,8 +340,10 @@ impl WorkflowParameterHandler {
     /// Get all remaining parameters that need to be filled
     pub fn get_remaining_parameters(&self) -> Vec<&Argument> {
         if let Some(ref workflow) = self.workflow {
-            workflow.arguments.iter()
+            self.order
+                .iter()
                 .skip(self.current_param_index)
+                .filter_map(|&index| workflow.arguments.get(index))
                 .collect()
         } else {
             vec![]
@@ -144,19 +355,14 @@ impl WorkflowParameterHandler {
         self.workflow = None;
         self.parameter_values.clear();
         self.current_param_index = 0;
+        self.order.clear();
     }
 
     /// Get a preview of the command with current parameter values
     pub fn preview_command(&self) -> Option<String> {
         if let Some(ref workflow) = self.workflow {
-            let mut command = workflow.command.clone();
-            
-            // Replace filled parameters
-            for (param_name, param_value) in &self.parameter_values {
-                let pattern = format!("{{{{{}}}}}", param_name);
-                command = command.replace(&pattern, param_value);
-            }
-            
+            let mut command = self.render_template(&workflow.command);
+
             // Show placeholder for unfilled parameters
             let re = Regex::new(r"\{\{([^}]+)\}\}").unwrap();
             for cap in re.captures_iter(&command.clone()) {
@@ -164,12 +370,139 @@ impl WorkflowParameterHandler {
                 let placeholder = format!("<{}>", param_name);
                 command = command.replace(&cap[0], &placeholder);
             }
-            
+
             Some(command)
         } else {
             None
         }
     }
+
+    /// Replace every filled `{{name}}` in `template`
+    fn render_template(&self, template: &str) -> String {
+        self.render_template_with(template, str::to_string)
+    }
+
+    /// Replace every filled `{{name}}` in `template` with its value passed
+    /// through `format_value`
+    fn render_template_with(&self, template: &str, format_value: impl Fn(&str) -> String) -> String {
+        let mut rendered = template.to_string();
+        for (param_name, param_value) in &self.parameter_values {
+            let pattern = format!("{{{{{}}}}}", param_name);
+            rendered = rendered.replace(&pattern, &format_value(param_value));
+        }
+        rendered
+    }
+
+    fn argument(&self, name: &str) -> Result<&Argument, ParameterError> {
+        self.workflow
+            .as_ref()
+            .ok_or(ParameterError::NoWorkflow)?
+            .arguments
+            .iter()
+            .find(|arg| arg.name == name)
+            .ok_or_else(|| ParameterError::UnknownArgument(name.to_string()))
+    }
+
+    fn check_dependencies(&self, arg: &Argument) -> Result<(), ParameterError> {
+        match Self::dependencies(arg)
+            .into_iter()
+            .find(|dependency| !self.parameter_values.contains_key(dependency))
+        {
+            Some(dependency) => Err(ParameterError::MissingDependency {
+                name: arg.name.clone(),
+                dependency,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Every argument that directly or indirectly depends on `name`
+    fn dependents_of(&self, name: &str) -> Vec<String> {
+        let Some(workflow) = self.workflow.as_ref() else {
+            return Vec::new();
+        };
+        let mut dependents: Vec<String> = Vec::new();
+        let mut pending = vec![name.to_string()];
+        while let Some(current) = pending.pop() {
+            for arg in &workflow.arguments {
+                if !dependents.contains(&arg.name) && Self::dependencies(arg).contains(&current) {
+                    dependents.push(arg.name.clone());
+                    pending.push(arg.name.clone());
+                }
+            }
+        }
+        dependents
+    }
+
+    /// Argument indices with every argument after its dependencies, keeping
+    /// declaration order otherwise
+    fn fill_order(workflow: &Workflow) -> Result<Vec<usize>, ParameterError> {
+        let arguments = &workflow.arguments;
+        let dependencies: Vec<Vec<usize>> = arguments
+            .iter()
+            .map(|arg| {
+                Self::dependencies(arg)
+                    .iter()
+                    .filter_map(|name| arguments.iter().position(|other| &other.name == name))
+                    .collect()
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(arguments.len());
+        let mut placed = vec![false; arguments.len()];
+        while order.len() < arguments.len() {
+            let next = (0..arguments.len())
+                .find(|&i| !placed[i] && dependencies[i].iter().all(|&dep| placed[dep]));
+            match next {
+                Some(i) => {
+                    placed[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    let cycle = (0..arguments.len())
+                        .filter(|&i| !placed[i])
+                        .map(|i| arguments[i].name.clone())
+                        .collect();
+                    return Err(ParameterError::DependencyCycle(cycle));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// The validation pattern, anchored so it must match the whole value
+    fn validation_regex(arg: &Argument, pattern: &str) -> Result<Regex, ParameterError> {
+        Regex::new(&format!("^(?:{})$", pattern)).map_err(|e| ParameterError::InvalidPattern {
+            name: arg.name.clone(),
+            message: e.to_string(),
+        })
+    }
+
+    fn directory_entries(&self) -> Vec<String> {
+        let dir = self
+            .working_dir
+            .clone()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let mut entries: Vec<String> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|entry| {
+                        let name = entry.file_name().into_string().ok()?;
+                        Some(if entry.path().is_dir() { format!("{}/", name) } else { name })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        entries.sort();
+        entries
+    }
+}
+
+/// `value` as a single `sh` word
+fn shell_quote(value: &str) -> String {
+    ShellDialect::Posix.quote(value)
 }
 
 #[cfg(test)]
@@ -185,6 +518,14 @@ mod tests {
             ])
     }
 
+    fn create_remote_branch_workflow() -> Workflow {
+        Workflow::new("Checkout remote branch", "git checkout -t {{remote}}/{{branch}}")
+            .with_arguments(vec![
+                Argument::new("branch").with_suggestions("printf '%s\\n' {{remote}}/main {{remote}}/dev"),
+                Argument::new("remote").with_options(["origin", "upstream"]),
+            ])
+    }
+
     #[test]
     fn test_parameter_extraction() {
         let command = "git commit -m '{{message}}' && git push {{remote}} {{branch}}";
@@ -196,19 +537,19 @@ mod tests {
     fn test_workflow_parameterization() {
         let mut handler = WorkflowParameterHandler::new();
         let workflow = create_test_workflow();
-        
+
         handler.set_workflow(workflow);
-        
+
         assert!(handler.has_parameters());
         assert_eq!(handler.get_parameter_progress(), (0, 2));
-        
-        handler.set_current_parameter_value("Hello World".to_string());
+
+        handler.set_current_parameter_value("Hello World".to_string()).unwrap();
         assert_eq!(handler.get_parameter_progress(), (1, 2));
-        
-        handler.set_current_parameter_value("output.txt".to_string());
+
+        handler.set_current_parameter_value("output.txt".to_string()).unwrap();
         assert_eq!(handler.get_parameter_progress(), (2, 2));
         assert!(handler.all_parameters_filled());
-        
+
         let rendered = handler.render_command().unwrap();
         assert_eq!(rendered, "echo Hello World > output.txt");
     }
@@ -217,16 +558,233 @@ mod tests {
     fn test_command_preview() {
         let mut handler = WorkflowParameterHandler::new();
         let workflow = create_test_workflow();
-        
+
         handler.set_workflow(workflow);
-        
+
         // No parameters filled yet
         let preview = handler.preview_command().unwrap();
         assert_eq!(preview, "echo <message> > <file>");
-        
+
         // Fill one parameter
-        handler.set_current_parameter_value("Hello".to_string());
+        handler.set_current_parameter_value("Hello".to_string()).unwrap();
         let preview = handler.preview_command().unwrap();
         assert_eq!(preview, "echo Hello > <file>");
     }
+
+    #[test]
+    fn test_typed_validation() {
+        let count = Argument::new("count").with_type(ArgumentType::Int);
+        assert_eq!(WorkflowParameterHandler::validate_value(&count, " 42 ").unwrap(), "42");
+        assert!(matches!(
+            WorkflowParameterHandler::validate_value(&count, "many"),
+            Err(ParameterError::InvalidInt { .. })
+        ));
+
+        let force = Argument::new("force").with_type(ArgumentType::Boolean);
+        assert_eq!(WorkflowParameterHandler::validate_value(&force, "Yes").unwrap(), "true");
+        assert!(WorkflowParameterHandler::validate_value(&force, "maybe").is_err());
+
+        let dir = Argument::new("dir").with_type(ArgumentType::Path);
+        assert!(matches!(
+            WorkflowParameterHandler::validate_value(&dir, "  "),
+            Err(ParameterError::EmptyPath { .. })
+        ));
+
+        let level = Argument::new("level").with_options(["debug", "info"]);
+        assert!(WorkflowParameterHandler::validate_value(&level, "info").is_ok());
+        assert!(matches!(
+            WorkflowParameterHandler::validate_value(&level, "trace"),
+            Err(ParameterError::NotAnOption { .. })
+        ));
+
+        // Patterns must match the whole value
+        let version = Argument::new("version").with_validation(r"\d+\.\d+\.\d+");
+        assert!(WorkflowParameterHandler::validate_value(&version, "1.2.3").is_ok());
+        assert!(matches!(
+            WorkflowParameterHandler::validate_value(&version, "v1.2.3"),
+            Err(ParameterError::PatternMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dependencies_are_filled_first() {
+        let mut handler = WorkflowParameterHandler::new();
+        handler.set_workflow(create_remote_branch_workflow());
+
+        assert_eq!(handler.get_current_parameter().unwrap().name, "remote");
+        assert!(matches!(
+            handler.suggestions_for("branch"),
+            Err(ParameterError::MissingDependency { .. })
+        ));
+
+        handler.set_current_parameter_value("origin".to_string()).unwrap();
+        assert_eq!(handler.get_current_parameter().unwrap().name, "branch");
+        assert_eq!(
+            handler.suggestions_for("branch").unwrap(),
+            vec!["origin/main", "origin/dev"]
+        );
+        handler.set_current_parameter_value("main".to_string()).unwrap();
+        assert_eq!(handler.render_command().unwrap(), "git checkout -t origin/main");
+
+        // Changing the remote invalidates the branch picked for it
+        handler.set_parameter_value("remote", "upstream".to_string()).unwrap();
+        assert!(!handler.parameter_values.contains_key("branch"));
+    }
+
+    #[test]
+    fn test_defaults_referencing_other_arguments() {
+        let workflow = Workflow::new("Tag", "git tag {{tag}}").with_arguments(vec![
+            Argument::new("version").with_validation(r"\d+\.\d+"),
+            Argument::new("tag").with_default_value("v{{version}}"),
+        ]);
+        let mut handler = WorkflowParameterHandler::new();
+        handler.set_workflow(workflow);
+
+        assert_eq!(handler.default_for("tag"), None);
+        handler.set_parameter_value("version", "1.4".to_string()).unwrap();
+        assert_eq!(handler.default_for("tag").as_deref(), Some("v1.4"));
+    }
+
+    #[test]
+    fn test_static_suggestions() {
+        let workflow = Workflow::new("Log", "run --verbose={{verbose}} --level {{level}}").with_arguments(vec![
+            Argument::new("verbose").with_type(ArgumentType::Boolean),
+            Argument::new("level").with_options(["info", "debug"]).with_suggestions("echo trace; echo info"),
+        ]);
+        let mut handler = WorkflowParameterHandler::new();
+        handler.set_workflow(workflow);
+
+        assert_eq!(handler.suggestions_for("verbose").unwrap(), vec!["true", "false"]);
+        assert_eq!(handler.suggestions_for("level").unwrap(), vec!["info", "debug", "trace"]);
+    }
+
+    #[test]
+    fn test_values_are_quoted_in_suggestion_commands() {
+        let workflow = Workflow::new("Grep", "grep {{pattern}} {{file}}").with_arguments(vec![
+            Argument::new("pattern"),
+            Argument::new("file").with_suggestions("printf '%s\\n' {{pattern}}"),
+        ]);
+        let mut handler = WorkflowParameterHandler::new();
+        handler.set_workflow(workflow);
+
+        handler.set_parameter_value("pattern", "$(echo injected) it's".to_string()).unwrap();
+        assert_eq!(handler.suggestions_for("file").unwrap(), vec!["$(echo injected) it's"]);
+        assert_eq!(shell_quote("origin/main"), "origin/main");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_failing_suggestion_command() {
+        let workflow = Workflow::new("Fail", "echo {{x}}")
+            .with_arguments(vec![Argument::new("x").with_suggestions("exit 3")]);
+        let mut handler = WorkflowParameterHandler::new();
+        handler.set_workflow(workflow);
+
+        assert!(matches!(
+            handler.suggestions_for("x"),
+            Err(ParameterError::SuggestionsFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_workflow() {
+        assert!(WorkflowParameterHandler::validate_workflow(&create_remote_branch_workflow()).is_ok());
+
+        let unknown = Workflow::new("Unknown", "echo {{a}}")
+            .with_arguments(vec![Argument::new("a").with_depends_on(["b"])]);
+        assert!(matches!(
+            WorkflowParameterHandler::validate_workflow(&unknown),
+            Err(ParameterError::UnknownDependency { .. })
+        ));
+
+        let cycle = Workflow::new("Cycle", "echo {{a}} {{b}}").with_arguments(vec![
+            Argument::new("a").with_depends_on(["b"]),
+            Argument::new("b").with_depends_on(["a"]),
+        ]);
+        assert_eq!(
+            WorkflowParameterHandler::validate_workflow(&cycle),
+            Err(ParameterError::DependencyCycle(vec!["a".to_string(), "b".to_string()]))
+        );
+
+        let bad_default = Workflow::new("Count", "head -n {{n}}")
+            .with_arguments(vec![Argument::new("n").with_type(ArgumentType::Int).with_default_value("ten")]);
+        assert!(WorkflowParameterHandler::validate_workflow(&bad_default).is_err());
+    }
+
+    #[test]
+    fn test_extended_yaml_format() {
+        let yaml = r#"
+name: Checkout remote branch
+command: git checkout -t {{remote}}/{{branch}}
+arguments:
+  - name: remote
+    type: enum
+    options: [origin, upstream]
+  - name: branch
+    depends_on: [remote]
+    validation: '[\w./-]+'
+    suggestions:
+      command: git branch -r --list '{{remote}}/*'
+  - name: depth
+    type: int
+    default_value: "1"
+"#;
+        let workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(workflow.arguments[0].arg_type, ArgumentType::Enum);
+        assert_eq!(workflow.arguments[0].options, vec!["origin", "upstream"]);
+        assert_eq!(workflow.arguments[1].depends_on, vec!["remote"]);
+        assert!(workflow.arguments[1].suggestions.is_some());
+        assert_eq!(workflow.arguments[2].arg_type, ArgumentType::Int);
+        assert!(WorkflowParameterHandler::validate_workflow(&workflow).is_ok());
+    }
+
+    #[test]
+    fn test_old_arg_type_key() {
+        let yaml = r#"
+name: Open
+command: open {{file}} {{count}}
+arguments:
+  - name: file
+    arg_type: Path
+  - name: count
+    arg_type: int
+"#;
+        let workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(workflow.arguments[0].arg_type, ArgumentType::Path);
+        assert_eq!(workflow.arguments[1].arg_type, ArgumentType::Int);
+
+        let spec = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("workflows/specs/Personal/Example workflow.yaml");
+        let content = std::fs::read_to_string(&spec).unwrap();
+        assert!(content.contains("arg_type: Text"));
+        let workflow: Workflow = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(workflow.arguments[0].arg_type, ArgumentType::Text);
+    }
+
+    #[test]
+    fn test_existing_specs_stay_compatible() {
+        let specs = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("workflows/specs");
+        let mut parsed = 0;
+        for entry in walkdir::WalkDir::new(specs).into_iter().filter_map(Result::ok) {
+            let is_yaml = entry
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "yaml" || extension == "yml");
+            if !is_yaml {
+                continue;
+            }
+            let content = std::fs::read_to_string(entry.path()).unwrap();
+            let workflow: Workflow = serde_yaml::from_str(&content)
+                .unwrap_or_else(|e| panic!("{}: {}", entry.path().display(), e));
+            assert!(
+                workflow.arguments.iter().all(|arg| arg.arg_type == ArgumentType::Text),
+                "{}",
+                entry.path().display()
+            );
+            WorkflowParameterHandler::validate_workflow(&workflow)
+                .unwrap_or_else(|e| panic!("{}: {}", entry.path().display(), e));
+            parsed += 1;
+        }
+        assert!(parsed > 0);
+    }
 }
@@ -0,0 +1,175 @@
+//! Conversions between the units text offsets are counted in.
+//!
+//! Editor buffers store byte offsets. The cursor steps over grapheme
+//! clusters, so `e` + U+0301, a flag or a ZWJ emoji sequence is a single
+//! step; screen columns count display width, where CJK and emoji take two
+//! columns and tabs run to the next tab stop; language servers and the
+//! platform IME count UTF-16 code units. The functions here work on one
+//! `&str`, and [`Rope`](crate::editor::rope::Rope) applies them per line.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns between tab stops.
+pub const TAB_WIDTH: usize = 4;
+
+/// The offset at or before `offset` that starts a character.
+pub fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// The offset at or after `offset` that starts a character.
+pub fn ceil_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset += 1;
+    }
+    offset
+}
+
+/// Start of the grapheme cluster before `offset`, or of the one containing
+/// it; 0 at the start.
+pub fn prev_grapheme_boundary(text: &str, offset: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start < offset)
+        .last()
+        .unwrap_or(0)
+}
+
+/// End of the grapheme cluster starting at or containing `offset`, or the
+/// text length.
+pub fn next_grapheme_boundary(text: &str, offset: usize) -> usize {
+    let offset = floor_char_boundary(text, offset);
+    text.grapheme_indices(true)
+        .map(|(start, grapheme)| start + grapheme.len())
+        .find(|end| *end > offset)
+        .unwrap_or(text.len())
+}
+
+pub fn is_grapheme_boundary(text: &str, offset: usize) -> bool {
+    offset == text.len() || text.grapheme_indices(true).any(|(start, _)| start == offset)
+}
+
+/// Columns a grapheme cluster takes, tabs aside. Emoji sequences joined
+/// with ZWJ render as one glyph, so no cluster is wider than two.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    UnicodeWidthStr::width(grapheme).min(2)
+}
+
+/// Columns `text` takes on one line, starting at column 0.
+pub fn display_width(text: &str) -> usize {
+    advance(0, text)
+}
+
+/// The column after drawing `text` from `column`.
+fn advance(column: usize, text: &str) -> usize {
+    text.graphemes(true).fold(column, |column, grapheme| column + width_at(column, grapheme))
+}
+
+fn width_at(column: usize, grapheme: &str) -> usize {
+    if grapheme == "\t" {
+        TAB_WIDTH - column % TAB_WIDTH
+    } else {
+        grapheme_width(grapheme)
+    }
+}
+
+/// Display column of `offset` within `line`.
+pub fn byte_to_column(line: &str, offset: usize) -> usize {
+    display_width(&line[..floor_char_boundary(line, offset)])
+}
+
+/// Offset of the grapheme drawn at `column` in `line`. A column inside a
+/// wide character or a tab maps to its start; one past the end maps to
+/// the end of the line.
+pub fn column_to_byte(line: &str, column: usize) -> usize {
+    let mut current = 0;
+    for (start, grapheme) in line.grapheme_indices(true) {
+        let next = current + width_at(current, grapheme);
+        if next > column {
+            return start;
+        }
+        current = next;
+    }
+    line.len()
+}
+
+pub fn byte_to_char(text: &str, offset: usize) -> usize {
+    text[..floor_char_boundary(text, offset)].chars().count()
+}
+
+pub fn char_to_byte(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map_or(text.len(), |(offset, _)| offset)
+}
+
+pub fn byte_to_utf16(text: &str, offset: usize) -> usize {
+    text[..floor_char_boundary(text, offset)].chars().map(char::len_utf16).sum()
+}
+
+/// Byte offset of the UTF-16 offset `utf16`. An offset between the halves
+/// of a surrogate pair maps to the start of the character.
+pub fn utf16_to_byte(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (offset, c) in text.char_indices() {
+        units += c.len_utf16();
+        if units > utf16 {
+            return offset;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // e + combining acute, a regional-indicator flag, a ZWJ family
+        let text = "e\u{301}🇯🇵👨\u{200d}👩\u{200d}👧x";
+        let flag = "e\u{301}".len();
+        let family = flag + "🇯🇵".len();
+        let x = text.len() - 1;
+        assert_eq!(next_grapheme_boundary(text, 0), flag);
+        assert_eq!(next_grapheme_boundary(text, flag), family);
+        assert_eq!(next_grapheme_boundary(text, family), x);
+        assert_eq!(prev_grapheme_boundary(text, x), family);
+        assert_eq!(prev_grapheme_boundary(text, family + 1), family);
+        assert_eq!(prev_grapheme_boundary(text, flag), 0);
+        assert!(!is_grapheme_boundary(text, 1));
+        assert!(is_grapheme_boundary(text, family));
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 2);
+        assert_eq!(display_width("a\tb"), 5);
+
+        let line = "a日b";
+        assert_eq!(byte_to_column(line, 1), 1);
+        assert_eq!(byte_to_column(line, 4), 3);
+        assert_eq!(column_to_byte(line, 2), 1, "inside the wide character");
+        assert_eq!(column_to_byte(line, 3), 4);
+        assert_eq!(column_to_byte(line, 9), line.len());
+    }
+
+    #[test]
+    fn test_utf16_and_chars() {
+        let text = "a😀é";
+        assert_eq!(byte_to_utf16(text, 5), 3);
+        assert_eq!(byte_to_utf16(text, text.len()), 4);
+        assert_eq!(utf16_to_byte(text, 3), 5);
+        assert_eq!(utf16_to_byte(text, 2), 1, "between surrogates");
+        assert_eq!(byte_to_char(text, 5), 2);
+        assert_eq!(char_to_byte(text, 2), 5);
+        assert_eq!(floor_char_boundary(text, 3), 1);
+        assert_eq!(ceil_char_boundary(text, 3), 5);
+    }
+}
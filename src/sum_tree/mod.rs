@@ -0,0 +1,703 @@
+//! A B+-tree of items that keeps a summary per node.
+//!
+//! Every node caches the [`Summary`] of the items under it, a monoid such as
+//! the byte, char and line counts of some text. Any [`Dimension`] that
+//! accumulates from the summary can then be searched in O(log n): a byte
+//! offset, a row, a UTF-16 offset. Nodes are shared through [`Arc`], so a
+//! clone is O(1) and an edit copies only the nodes on its path.
+//!
+//! The editor [`Rope`](crate::editor::rope::Rope) keeps its chunks here, and
+//! [`Scrollback`](crate::model::scrollback::Scrollback) its output lines.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+use std::slice;
+use std::sync::Arc;
+
+/// Small nodes in tests, so a few dozen items already make a deep tree
+const TREE_BASE: usize = if cfg!(test) { 2 } else { 8 };
+/// Fewest items or children of a node other than the root
+const MIN_CHILDREN: usize = TREE_BASE;
+const MAX_CHILDREN: usize = TREE_BASE * 2;
+
+/// A monoid: `Default` is the empty summary and `add_summary` appends one
+/// summary to another
+pub trait Summary: Clone + Default {
+    fn add_summary(&mut self, other: &Self);
+}
+
+pub trait Item: Clone {
+    type Summary: Summary;
+
+    fn summary(&self) -> Self::Summary;
+}
+
+/// A position the tree can be searched by, accumulated from summaries.
+/// Adding a summary never moves it backwards.
+pub trait Dimension<S: Summary>: Clone + Default + Ord {
+    fn add_summary(&mut self, summary: &S);
+}
+
+/// Which item a position on the boundary between two items belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    /// The item ending there
+    Left,
+    /// The item starting there
+    Right,
+}
+
+/// An item found by [`SumTree::find`]
+#[derive(Debug, Clone)]
+pub struct Found<'a, T: Item, D> {
+    pub item: &'a T,
+    pub index: usize,
+    /// Summary of every item before this one
+    pub before: T::Summary,
+    /// Where the item starts along the searched dimension
+    pub start: D,
+}
+
+#[derive(Clone)]
+enum Node<T: Item> {
+    Leaf {
+        items: Vec<T>,
+        summary: T::Summary,
+    },
+    Internal {
+        children: Vec<Arc<Node<T>>>,
+        summary: T::Summary,
+        count: usize,
+        height: usize,
+    },
+}
+
+impl<T: Item> Node<T> {
+    fn leaf(items: Vec<T>) -> Self {
+        let mut summary = T::Summary::default();
+        for item in &items {
+            summary.add_summary(&item.summary());
+        }
+        Node::Leaf { items, summary }
+    }
+
+    fn internal(children: Vec<Arc<Self>>) -> Self {
+        let mut summary = T::Summary::default();
+        for child in &children {
+            summary.add_summary(child.summary());
+        }
+        Node::Internal {
+            count: children.iter().map(|child| child.count()).sum(),
+            height: children[0].height() + 1,
+            children,
+            summary,
+        }
+    }
+
+    fn summary(&self) -> &T::Summary {
+        match self {
+            Node::Leaf { summary, .. } | Node::Internal { summary, .. } => summary,
+        }
+    }
+
+    /// Items under this node
+    fn count(&self) -> usize {
+        match self {
+            Node::Leaf { items, .. } => items.len(),
+            Node::Internal { count, .. } => *count,
+        }
+    }
+
+    fn height(&self) -> usize {
+        match self {
+            Node::Leaf { .. } => 0,
+            Node::Internal { height, .. } => *height,
+        }
+    }
+
+    fn is_underfull(&self) -> bool {
+        match self {
+            Node::Leaf { items, .. } => items.len() < MIN_CHILDREN,
+            Node::Internal { children, .. } => children.len() < MIN_CHILDREN,
+        }
+    }
+}
+
+/// A sequence of items, balanced as a B+-tree.
+///
+/// Every node other than the root holds between [`MIN_CHILDREN`] and
+/// [`MAX_CHILDREN`] items or children, and all leaves are at the same
+/// depth.
+pub struct SumTree<T: Item> {
+    root: Arc<Node<T>>,
+}
+
+impl<T: Item> SumTree<T> {
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(Node::leaf(Vec::new())),
+        }
+    }
+
+    /// Build a tree from `items` bottom up, filling nodes evenly
+    pub fn from_items(items: Vec<T>) -> Self {
+        if items.is_empty() {
+            return Self::new();
+        }
+        let mut nodes: Vec<Arc<Node<T>>> = groups(items).into_iter().map(|items| Arc::new(Node::leaf(items))).collect();
+        while nodes.len() > 1 {
+            nodes = groups(nodes).into_iter().map(|children| Arc::new(Node::internal(children))).collect();
+        }
+        Self::from_nodes(nodes)
+    }
+
+    /// A tree whose root holds `nodes`, or the only node itself
+    fn from_nodes(mut nodes: Vec<Arc<Node<T>>>) -> Self {
+        match nodes.len() {
+            0 => Self::new(),
+            1 => {
+                let mut root = nodes.pop().unwrap();
+                while let Node::Internal { children, .. } = &*root {
+                    if children.len() > 1 {
+                        break;
+                    }
+                    root = children[0].clone();
+                }
+                Self { root }
+            }
+            _ => Self {
+                root: Arc::new(Node::internal(nodes)),
+            },
+        }
+    }
+
+    pub fn summary(&self) -> &T::Summary {
+        self.root.summary()
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.root.count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, mut index: usize) -> Option<&T> {
+        let mut node = &*self.root;
+        loop {
+            match node {
+                Node::Leaf { items, .. } => return items.get(index),
+                Node::Internal { children, .. } => {
+                    let mut next = None;
+                    for child in children {
+                        if index < child.count() {
+                            next = Some(child);
+                            break;
+                        }
+                        index -= child.count();
+                    }
+                    node = next?;
+                }
+            }
+        }
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.iter_from(0)
+    }
+
+    /// Items from `index` on
+    pub fn iter_from(&self, mut index: usize) -> Iter<'_, T> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            items: [].iter(),
+        };
+        let mut node = &*self.root;
+        loop {
+            match node {
+                Node::Leaf { items, .. } => {
+                    iter.items = items[index.min(items.len())..].iter();
+                    return iter;
+                }
+                Node::Internal { children, .. } => {
+                    let mut next = children.len();
+                    for (i, child) in children.iter().enumerate() {
+                        if index < child.count() {
+                            next = i;
+                            break;
+                        }
+                        index -= child.count();
+                    }
+                    let Some(child) = children.get(next) else {
+                        return iter;
+                    };
+                    iter.stack.push(children[next + 1..].iter());
+                    node = child;
+                }
+            }
+        }
+    }
+
+    /// The item holding `target` along `D`, or `None` past the end. With
+    /// [`Bias::Left`] that is the first item ending at or after `target`,
+    /// with [`Bias::Right`] the first ending after it.
+    pub fn find<D: Dimension<T::Summary>>(&self, target: &D, bias: Bias) -> Option<Found<'_, T, D>> {
+        let reaches = |end: &D| match end.cmp(target) {
+            Ordering::Greater => true,
+            Ordering::Equal => bias == Bias::Left,
+            Ordering::Less => false,
+        };
+        let mut before = T::Summary::default();
+        let mut start = D::default();
+        let mut index = 0;
+        let mut node = &*self.root;
+        loop {
+            match node {
+                Node::Internal { children, .. } => {
+                    let mut next = None;
+                    for child in children {
+                        let mut end = start.clone();
+                        end.add_summary(child.summary());
+                        if reaches(&end) {
+                            next = Some(child);
+                            break;
+                        }
+                        before.add_summary(child.summary());
+                        start = end;
+                        index += child.count();
+                    }
+                    node = next?;
+                }
+                Node::Leaf { items, .. } => {
+                    for item in items {
+                        let summary = item.summary();
+                        let mut end = start.clone();
+                        end.add_summary(&summary);
+                        if reaches(&end) {
+                            return Some(Found {
+                                item,
+                                index,
+                                before,
+                                start,
+                            });
+                        }
+                        before.add_summary(&summary);
+                        start = end;
+                        index += 1;
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// The items overlapping `start..end` along `D`: from the one starting
+    /// at or holding `start` to the one holding `end`
+    pub fn slice<D: Dimension<T::Summary>>(&self, start: &D, end: &D) -> Self {
+        let from = self.find(start, Bias::Right).map_or(self.len(), |found| found.index);
+        let to = self.find(end, Bias::Left).map_or(self.len(), |found| found.index + 1);
+        if to <= from {
+            return Self::new();
+        }
+        let (_, rest) = self.split_at(from);
+        rest.split_at(to - from).0
+    }
+
+    /// The items before `index` and the rest
+    pub fn split_at(&self, index: usize) -> (Self, Self) {
+        split_node(&self.root, index)
+    }
+
+    /// Replace the items at `range` of indices with `items`
+    pub fn splice(&mut self, range: Range<usize>, items: impl IntoIterator<Item = T>) {
+        let (mut left, _) = self.split_at(range.start);
+        let (_, right) = self.split_at(range.end.max(range.start));
+        left.append(items.into_iter().collect());
+        left.append(right);
+        *self = left;
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.append(Self::from_items(vec![item]));
+    }
+
+    /// Move the items of `other` to the end of this tree
+    pub fn append(&mut self, other: Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other;
+            return;
+        }
+        *self = Self::from_nodes(concat(self.root.clone(), other.root));
+    }
+}
+
+impl<T: Item> Default for SumTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Item> Clone for SumTree<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<T: Item + fmt::Debug> fmt::Debug for SumTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Item> FromIterator<T> for SumTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_items(iter.into_iter().collect())
+    }
+}
+
+impl<T: Item> Extend<T> for SumTree<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.append(iter.into_iter().collect());
+    }
+}
+
+impl<'a, T: Item> IntoIterator for &'a SumTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Items in order, walking the leaves left to right
+pub struct Iter<'a, T: Item> {
+    /// The children still to visit on each level above the current leaf
+    stack: Vec<slice::Iter<'a, Arc<Node<T>>>>,
+    items: slice::Iter<'a, T>,
+}
+
+impl<'a, T: Item> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+            let level = self.stack.last_mut()?;
+            match level.next().map(|node| &**node) {
+                Some(Node::Leaf { items, .. }) => self.items = items.iter(),
+                Some(Node::Internal { children, .. }) => self.stack.push(children.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+fn split_node<T: Item>(node: &Arc<Node<T>>, index: usize) -> (SumTree<T>, SumTree<T>) {
+    if index == 0 {
+        return (SumTree::new(), SumTree { root: node.clone() });
+    }
+    if index >= node.count() {
+        return (SumTree { root: node.clone() }, SumTree::new());
+    }
+    match &**node {
+        Node::Leaf { items, .. } => (
+            SumTree::from_items(items[..index].to_vec()),
+            SumTree::from_items(items[index..].to_vec()),
+        ),
+        Node::Internal { children, .. } => {
+            let mut index = index;
+            let mut split = 0;
+            while index >= children[split].count() {
+                index -= children[split].count();
+                split += 1;
+            }
+            let (inner_left, inner_right) = split_node(&children[split], index);
+            let mut left = SumTree::from_nodes(children[..split].to_vec());
+            left.append(inner_left);
+            let mut right = inner_right;
+            right.append(SumTree::from_nodes(children[split + 1..].to_vec()));
+            (left, right)
+        }
+    }
+}
+
+/// Join two nodes into one or two nodes as tall as the taller of them.
+/// A lone result may be underfull; everything below it is not, as long
+/// as the same holds for `left` and `right`.
+fn concat<T: Item>(left: Arc<Node<T>>, right: Arc<Node<T>>) -> Vec<Arc<Node<T>>> {
+    match left.height().cmp(&right.height()) {
+        Ordering::Equal => {
+            if !left.is_underfull() && !right.is_underfull() {
+                return vec![left, right];
+            }
+            match (&*left, &*right) {
+                (Node::Leaf { items: a, .. }, Node::Leaf { items: b, .. }) => {
+                    let items = a.iter().chain(b).cloned().collect();
+                    groups(items).into_iter().map(|items| Arc::new(Node::leaf(items))).collect()
+                }
+                (Node::Internal { children: a, .. }, Node::Internal { children: b, .. }) => {
+                    let children = a.iter().chain(b).cloned().collect();
+                    groups(children).into_iter().map(|children| Arc::new(Node::internal(children))).collect()
+                }
+                _ => unreachable!("nodes of the same height are both leaves or both internal"),
+            }
+        }
+        Ordering::Greater => {
+            let Node::Internal { children, .. } = &*left else {
+                unreachable!("a taller node is internal")
+            };
+            let mut children = children.clone();
+            let last = children.pop().unwrap();
+            let mut joined = concat(last, right);
+            // An underfull result is merged with its sibling
+            if joined.len() == 1 && joined[0].is_underfull() {
+                if let Some(sibling) = children.pop() {
+                    joined = concat(sibling, joined.pop().unwrap());
+                }
+            }
+            children.extend(joined);
+            groups(children).into_iter().map(|children| Arc::new(Node::internal(children))).collect()
+        }
+        Ordering::Less => {
+            let Node::Internal { children, .. } = &*right else {
+                unreachable!("a taller node is internal")
+            };
+            let mut children = children.clone();
+            let first = children.remove(0);
+            let mut joined = concat(left, first);
+            if joined.len() == 1 && joined[0].is_underfull() && !children.is_empty() {
+                let sibling = children.remove(0);
+                joined = concat(joined.pop().unwrap(), sibling);
+            }
+            joined.extend(children);
+            groups(joined).into_iter().map(|children| Arc::new(Node::internal(children))).collect()
+        }
+    }
+}
+
+/// Cut `items` into as few groups of at most [`MAX_CHILDREN`] as it takes,
+/// as evenly as it allows
+fn groups<X>(mut items: Vec<X>) -> Vec<Vec<X>> {
+    let count = items.len().div_ceil(MAX_CHILDREN).max(1);
+    let mut groups = Vec::with_capacity(count);
+    for remaining in (1..=count).rev() {
+        let rest = items.split_off(items.len().div_ceil(remaining));
+        groups.push(items);
+        items = rest;
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small deterministic generator, so failures reproduce
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound.max(1) as u64) as usize
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Number(usize);
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    struct NumberSummary {
+        count: usize,
+        sum: usize,
+        max: usize,
+    }
+
+    impl Summary for NumberSummary {
+        fn add_summary(&mut self, other: &Self) {
+            self.count += other.count;
+            self.sum += other.sum;
+            self.max = self.max.max(other.max);
+        }
+    }
+
+    impl Item for Number {
+        type Summary = NumberSummary;
+
+        fn summary(&self) -> NumberSummary {
+            NumberSummary {
+                count: 1,
+                sum: self.0,
+                max: self.0,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct Sum(usize);
+
+    impl Dimension<NumberSummary> for Sum {
+        fn add_summary(&mut self, summary: &NumberSummary) {
+            self.0 += summary.sum;
+        }
+    }
+
+    fn naive_summary(items: &[Number]) -> NumberSummary {
+        let mut summary = NumberSummary::default();
+        for item in items {
+            summary.add_summary(&item.summary());
+        }
+        summary
+    }
+
+    /// Index of the item holding `target` by a linear scan
+    fn naive_find(items: &[Number], target: usize, bias: Bias) -> Option<usize> {
+        let mut end = 0;
+        items.iter().position(|item| {
+            end += item.0;
+            end > target || (bias == Bias::Left && end == target)
+        })
+    }
+
+    /// Leaves all at one depth and every node but the root within bounds;
+    /// returns the height
+    fn check(node: &Node<Number>, is_root: bool) -> usize {
+        let (len, height) = match node {
+            Node::Leaf { items, summary } => {
+                assert_eq!(*summary, naive_summary(items));
+                (items.len(), 0)
+            }
+            Node::Internal { children, count, height, .. } => {
+                assert!(children.len() > 1 || !is_root, "a root with one child is collapsed");
+                assert_eq!(*count, children.iter().map(|child| child.count()).sum::<usize>());
+                for child in children {
+                    assert_eq!(check(child, false) + 1, *height);
+                }
+                (children.len(), *height)
+            }
+        };
+        assert!(len <= MAX_CHILDREN);
+        assert!(is_root || len >= MIN_CHILDREN, "underfull node of {len}");
+        height
+    }
+
+    fn numbers(rng: &mut XorShift, count: usize) -> Vec<Number> {
+        // Zeros give items that take no room along `Sum`
+        (0..count).map(|_| Number(rng.below(5))).collect()
+    }
+
+    #[test]
+    fn test_edits_match_vec() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut tree = SumTree::new();
+        let mut expected: Vec<Number> = Vec::new();
+        for _ in 0..500 {
+            match rng.below(4) {
+                0 => {
+                    let item = Number(rng.below(5));
+                    tree.push(item);
+                    expected.push(item);
+                }
+                1 => {
+                    let start = rng.below(expected.len() + 1);
+                    let end = (start + rng.below(8)).min(expected.len());
+                    let count = rng.below(12);
+                    let items = numbers(&mut rng, count);
+                    tree.splice(start..end, items.clone());
+                    expected.splice(start..end, items);
+                }
+                2 => {
+                    let index = rng.below(expected.len() + 1);
+                    let (left, right) = tree.split_at(index);
+                    assert!(left.iter().eq(&expected[..index]));
+                    assert!(right.iter().eq(&expected[index..]));
+                    check(&left.root, true);
+                    check(&right.root, true);
+                    tree = left;
+                    tree.append(right);
+                }
+                _ => {
+                    let count = rng.below(40);
+                    let items = numbers(&mut rng, count);
+                    tree.extend(items.clone());
+                    expected.extend(items);
+                }
+            }
+            check(&tree.root, true);
+            assert_eq!(tree.len(), expected.len());
+            assert_eq!(*tree.summary(), naive_summary(&expected));
+            assert!(tree.iter().eq(&expected));
+        }
+        assert!(tree.root.height() > 2);
+    }
+
+    #[test]
+    fn test_seek_matches_vec() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let expected = numbers(&mut rng, 300);
+        let tree = SumTree::from_items(expected.clone());
+        check(&tree.root, true);
+        let total = tree.summary().sum;
+
+        for target in 0..=total + 1 {
+            for bias in [Bias::Left, Bias::Right] {
+                let found = tree.find(&Sum(target), bias);
+                let index = naive_find(&expected, target, bias);
+                assert_eq!(found.as_ref().map(|found| found.index), index, "{target} {bias:?}");
+                if let (Some(found), Some(index)) = (found, index) {
+                    assert_eq!(found.item, &expected[index]);
+                    assert_eq!(found.before, naive_summary(&expected[..index]));
+                    assert_eq!(found.start, Sum(found.before.sum));
+                }
+            }
+        }
+        for index in 0..=expected.len() {
+            assert_eq!(tree.get(index), expected.get(index));
+            assert!(tree.iter_from(index).eq(&expected[index..]));
+        }
+
+        for _ in 0..200 {
+            let start = rng.below(total + 1);
+            let end = start + rng.below(total + 1 - start);
+            let from = naive_find(&expected, start, Bias::Right).unwrap_or(expected.len());
+            let to = naive_find(&expected, end, Bias::Left).map_or(expected.len(), |index| index + 1);
+            let slice = tree.slice(&Sum(start), &Sum(end));
+            check(&slice.root, true);
+            assert!(slice.iter().eq(expected.get(from..to).unwrap_or_default()));
+        }
+    }
+
+    #[test]
+    fn test_clones_share_nodes() {
+        let tree: SumTree<Number> = (0..100).map(Number).collect();
+        let mut edited = tree.clone();
+        edited.splice(10..20, [Number(7)]);
+        assert_eq!(tree.len(), 100);
+        assert_eq!(edited.len(), 91);
+        assert_eq!(edited.get(10), Some(&Number(7)));
+        assert_eq!(tree.summary().max, 99);
+        assert!(tree.iter().map(|number| number.0).eq(0..100));
+    }
+}
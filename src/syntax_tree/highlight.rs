@@ -0,0 +1,470 @@
+//! Incremental highlighting for the languages in the
+//! [`LanguageRegistry`](crate::languages::LanguageRegistry).
+//!
+//! A [`Grammar`] highlights one line at a time, starting from the
+//! [`LineState`] the line before it ended in: inside a block comment, a
+//! multi-line string, a heredoc. [`HighlightMap`] keeps every line's tokens
+//! and end state in a [`SumTree`]. An edit marks the lines it touched dirty,
+//! and [`HighlightMap::reparse`] relexes them, carrying on into the lines
+//! after only until a line ends in the same state as before. Typing inside a
+//! line relexes that line; opening a block comment relexes up to where the
+//! comment now closes.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::editor::rope::Rope;
+use crate::editor::syntax_highlighter::TokenType;
+use crate::languages::Language;
+use crate::sum_tree::{Bias, Dimension, Item, SumTree, Summary};
+
+/// What a span of text is, independent of any color theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Keyword,
+    Function,
+    Type,
+    /// `true`, `null`, `None` and the like
+    Constant,
+    Number,
+    String,
+    Comment,
+    Variable,
+    /// A Rust lifetime or loop label
+    Label,
+    /// A key in JSON, YAML or TOML
+    Property,
+    /// An attribute or decorator
+    Attribute,
+    Operator,
+    Punctuation,
+    /// The program a shell command runs
+    Command,
+    Flag,
+    Argument,
+    Redirect,
+    Heading,
+    Emphasis,
+    Strong,
+    Link,
+    /// Inline or fenced code in Markdown
+    Code,
+    Error,
+}
+
+impl Scope {
+    /// The token type whose [`SyntaxTheme`](crate::editor::syntax_highlighter::SyntaxTheme)
+    /// style the scope is drawn with
+    pub fn token_type(self) -> TokenType {
+        match self {
+            Scope::Keyword => TokenType::Keyword,
+            Scope::Function => TokenType::Function,
+            Scope::Type => TokenType::Type,
+            Scope::Constant | Scope::Code => TokenType::Literal,
+            Scope::Number => TokenType::Number,
+            Scope::String => TokenType::String,
+            Scope::Comment => TokenType::Comment,
+            Scope::Variable | Scope::Label => TokenType::Variable,
+            Scope::Property => TokenType::Property,
+            Scope::Attribute => TokenType::Attribute,
+            Scope::Operator => TokenType::Operator,
+            Scope::Punctuation => TokenType::Punctuation,
+            Scope::Command => TokenType::Command,
+            Scope::Flag => TokenType::Flag,
+            Scope::Argument => TokenType::Argument,
+            Scope::Redirect => TokenType::Redirect,
+            Scope::Heading => TokenType::Heading,
+            Scope::Emphasis => TokenType::Emphasis,
+            Scope::Strong => TokenType::Strong,
+            Scope::Link => TokenType::Link,
+            Scope::Error => TokenType::Error,
+        }
+    }
+}
+
+/// A highlighted span of one line, relative to the line's start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub range: Range<usize>,
+    pub scope: Scope,
+}
+
+impl Token {
+    pub fn new(range: Range<usize>, scope: Scope) -> Self {
+        Self { range, scope }
+    }
+}
+
+/// What a line leaves open for the next one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum LineState {
+    #[default]
+    Normal,
+    /// Inside `depth` nested block comments
+    BlockComment { depth: usize },
+    /// Inside a string that ends at `close`
+    String { close: String, escapes: bool },
+    /// After a shell line ending in a backslash
+    Continuation,
+    /// Inside a heredoc, until a line holding just `delimiter`
+    Heredoc { delimiter: String, strip_tabs: bool },
+    /// Inside a YAML block scalar, whose lines are indented past `indent`
+    BlockScalar { indent: usize },
+    /// Inside a Markdown code block closed by `marker`. `inner` is the
+    /// state of the fenced code's own language.
+    Fence {
+        marker: String,
+        language: Option<String>,
+        inner: Box<LineState>,
+    },
+}
+
+pub trait Grammar: Send + Sync {
+    /// Append the tokens of `line`, which has no newline, in order, and
+    /// leave `state` as the line ends
+    fn highlight_line(&self, line: &str, state: &mut LineState, tokens: &mut Vec<Token>);
+}
+
+/// Text a [`HighlightMap`] reads one line at a time
+pub trait LineSource {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The line starting at `offset`, without its newline
+    fn line_at(&self, offset: usize) -> Cow<'_, str>;
+}
+
+impl LineSource for str {
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn line_at(&self, offset: usize) -> Cow<'_, str> {
+        let rest = &self[offset..];
+        Cow::Borrowed(&rest[..rest.find('\n').unwrap_or(rest.len())])
+    }
+}
+
+impl LineSource for String {
+    fn len(&self) -> usize {
+        String::len(self)
+    }
+
+    fn line_at(&self, offset: usize) -> Cow<'_, str> {
+        self.as_str().line_at(offset)
+    }
+}
+
+impl LineSource for Rope {
+    fn len(&self) -> usize {
+        Rope::len(self)
+    }
+
+    fn line_at(&self, offset: usize) -> Cow<'_, str> {
+        Cow::Owned(self.line(self.offset_to_point(offset).row))
+    }
+}
+
+/// One line, or after an edit a dirty run of lines waiting to be relexed
+#[derive(Debug, Clone)]
+struct Line {
+    /// Bytes, including the newline unless this is the last line
+    len: usize,
+    tokens: Arc<[Token]>,
+    end_state: LineState,
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LineSummary {
+    lines: usize,
+    bytes: usize,
+    dirty: usize,
+}
+
+impl Summary for LineSummary {
+    fn add_summary(&mut self, other: &Self) {
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+        self.dirty += other.dirty;
+    }
+}
+
+impl Item for Line {
+    type Summary = LineSummary;
+
+    fn summary(&self) -> LineSummary {
+        LineSummary {
+            lines: 1,
+            bytes: self.len,
+            dirty: self.dirty as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Rows(usize);
+
+impl Dimension<LineSummary> for Rows {
+    fn add_summary(&mut self, summary: &LineSummary) {
+        self.0 += summary.lines;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Bytes(usize);
+
+impl Dimension<LineSummary> for Bytes {
+    fn add_summary(&mut self, summary: &LineSummary) {
+        self.0 += summary.bytes;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Dirty(usize);
+
+impl Dimension<LineSummary> for Dirty {
+    fn add_summary(&mut self, summary: &LineSummary) {
+        self.0 += summary.dirty;
+    }
+}
+
+/// The highlights of a text, kept up to date through its edits
+#[derive(Debug, Clone)]
+pub struct HighlightMap {
+    language: Arc<Language>,
+    lines: SumTree<Line>,
+}
+
+impl HighlightMap {
+    pub fn new(language: Arc<Language>, text: &(impl LineSource + ?Sized)) -> Self {
+        let mut map = Self {
+            language,
+            lines: SumTree::from_items(vec![Line {
+                len: text.len(),
+                tokens: Arc::new([]),
+                end_state: LineState::Normal,
+                dirty: true,
+            }]),
+        };
+        map.reparse(text);
+        map
+    }
+
+    /// Highlights of all of `text`, with offsets into it
+    pub fn highlight(language: Arc<Language>, text: &str) -> Vec<(Range<usize>, Scope)> {
+        let map = Self::new(language, text);
+        map.highlights(0..map.line_count()).collect()
+    }
+
+    pub fn language(&self) -> &Arc<Language> {
+        &self.language
+    }
+
+    /// Length in bytes of the text the map describes
+    pub fn len(&self) -> usize {
+        self.lines.summary().bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lines, once reparsed
+    pub fn line_count(&self) -> usize {
+        self.lines.summary().lines
+    }
+
+    /// Note that the bytes at `range` were replaced by `new_len` bytes.
+    /// Offsets are into the text as it was after any earlier edits. The
+    /// lines the edit touched become dirty until [`Self::reparse`].
+    pub fn edit(&mut self, range: Range<usize>, new_len: usize) {
+        let total = self.len();
+        let range = range.start.min(total)..range.end.clamp(range.start.min(total), total);
+        let locate = |offset: usize| match self.lines.find(&Bytes(offset), Bias::Right) {
+            Some(found) => (found.index, found.before.bytes),
+            None => {
+                let last = self.lines.len() - 1;
+                (last, total - self.lines.get(last).map_or(0, |line| line.len))
+            }
+        };
+        let (first, start) = locate(range.start);
+        let (last, last_start) = locate(range.end);
+        let Some(last_line) = self.lines.get(last) else { return };
+        let old_len = last_start + last_line.len - start;
+        let dirty = Line {
+            len: old_len - range.len() + new_len,
+            tokens: Arc::new([]),
+            end_state: last_line.end_state.clone(),
+            dirty: true,
+        };
+        self.lines.splice(first..last + 1, [dirty]);
+    }
+
+    /// Relex the dirty lines of `text`, which must be the text after every
+    /// edit so far. Returns how many lines were relexed.
+    pub fn reparse(&mut self, text: &(impl LineSource + ?Sized)) -> usize {
+        debug_assert_eq!(self.len(), text.len(), "highlight map out of step with its text");
+        let mut relexed = 0;
+        while let Some(found) = self.lines.find(&Dirty(1), Bias::Left) {
+            let index = found.index;
+            let mut pos = found.before.bytes;
+            let mut state = match index.checked_sub(1) {
+                Some(previous) => self.lines.get(previous).map(|line| line.end_state.clone()).unwrap_or_default(),
+                None => LineState::Normal,
+            };
+            let mut old_index = index;
+            let mut old_end = pos;
+            let mut lines = Vec::new();
+            loop {
+                let line = text.line_at(pos);
+                let line_end = pos + line.len();
+                let next = (line_end + 1).min(text.len());
+                let mut tokens = Vec::new();
+                self.language.grammar().highlight_line(&line, &mut state, &mut tokens);
+                lines.push(Line {
+                    len: next - pos,
+                    tokens: tokens.into(),
+                    end_state: state.clone(),
+                    dirty: false,
+                });
+                relexed += 1;
+                if line_end == text.len() {
+                    old_index = self.lines.len();
+                    break;
+                }
+                pos = next;
+
+                while old_end < pos {
+                    let Some(line) = self.lines.get(old_index) else { break };
+                    old_end += line.len;
+                    old_index += 1;
+                }
+                // The rest is as it was once a line ends where an old one
+                // did, in the state that one did, and no dirty line follows
+                let converged = old_end == pos
+                    && self.lines.get(old_index - 1).is_some_and(|line| line.end_state == state)
+                    && self.lines.get(old_index).is_some_and(|line| !line.dirty);
+                if converged {
+                    break;
+                }
+            }
+            self.lines.splice(index..old_index, lines);
+        }
+        relexed
+    }
+
+    /// Tokens of zero-based line `row`, relative to the line's start
+    pub fn line_tokens(&self, row: usize) -> &[Token] {
+        self.lines.get(row).map_or(&[], |line| &line.tokens)
+    }
+
+    /// Highlights of the lines in `rows`, with offsets into the whole text
+    pub fn highlights(&self, rows: Range<usize>) -> impl Iterator<Item = (Range<usize>, Scope)> + '_ {
+        let mut line_start = self
+            .lines
+            .find(&Rows(rows.start), Bias::Right)
+            .map_or(self.len(), |found| found.before.bytes);
+        self.lines.iter_from(rows.start).take(rows.len()).flat_map(move |line| {
+            let start = line_start;
+            line_start += line.len;
+            line.tokens
+                .iter()
+                .map(move |token| (start + token.range.start..start + token.range.end, token.scope))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::LanguageRegistry;
+
+    fn rust() -> Arc<Language> {
+        LanguageRegistry::global().get("rust").unwrap()
+    }
+
+    /// Apply an edit to both the text and the map
+    fn edit(map: &mut HighlightMap, text: &mut String, range: Range<usize>, new_text: &str) {
+        text.replace_range(range.clone(), new_text);
+        map.edit(range, new_text.len());
+    }
+
+    fn assert_matches_full_parse(map: &HighlightMap, text: &str) {
+        let fresh = HighlightMap::new(map.language().clone(), text);
+        assert_eq!(map.line_count(), fresh.line_count(), "{text:?}");
+        for row in 0..fresh.line_count() {
+            assert_eq!(map.line_tokens(row), fresh.line_tokens(row), "line {row} of {text:?}");
+        }
+    }
+
+    #[test]
+    fn test_edit_relexes_only_the_changed_line() {
+        let mut text: String = (0..200).map(|i| format!("let x{i} = \"value\"; // note\n")).collect();
+        let mut map = HighlightMap::new(rust(), &text);
+        assert_eq!(map.line_count(), 201);
+
+        let offset = text.find("x100").unwrap();
+        edit(&mut map, &mut text, offset..offset, "y");
+        assert_eq!(map.reparse(&text), 1);
+        assert_matches_full_parse(&map, &text);
+
+        let tokens: Vec<Scope> = map.line_tokens(100).iter().map(|token| token.scope).collect();
+        assert_eq!(tokens, [Scope::Keyword, Scope::Operator, Scope::String, Scope::Punctuation, Scope::Comment]);
+    }
+
+    #[test]
+    fn test_block_comment_relexes_until_it_closes() {
+        let mut text: String = (0..50)
+            .map(|i| if i == 20 { "*/ fn f20() {}\n".to_string() } else { format!("fn f{i}() {{}}\n") })
+            .collect();
+        let mut map = HighlightMap::new(rust(), &text);
+
+        // Opening a comment relexes up to the line where it now closes
+        edit(&mut map, &mut text, 0..0, "/* ");
+        assert_eq!(map.reparse(&text), 21);
+        assert!(map.highlights(0..20).all(|(_, scope)| scope == Scope::Comment));
+        assert_eq!(map.line_tokens(20)[1].scope, Scope::Keyword);
+        assert_eq!(map.line_tokens(21)[0].scope, Scope::Keyword);
+        assert_matches_full_parse(&map, &text);
+
+        edit(&mut map, &mut text, 0..3, "");
+        assert_eq!(map.reparse(&text), 21);
+        assert_matches_full_parse(&map, &text);
+    }
+
+    #[test]
+    fn test_random_edits_match_a_full_parse() {
+        let mut rng = 0x2545f4914f6cdd1du64;
+        let mut next = |bound: usize| {
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            (rng % bound as u64) as usize
+        };
+        let pieces = ["/*", "*/", "\"", "\n", "r#\"", "\"#", "//", "fn ", "x", " ", "'a", "{", "}", "1.5"];
+        let mut text = String::from("fn main() {\n    let s = \"a\";\n}\n");
+        let mut map = HighlightMap::new(rust(), &text);
+        for _ in 0..300 {
+            // Edit at char boundaries only; the pieces are all ASCII
+            let start = next(text.len() + 1);
+            let end = (start + next(4)).min(text.len());
+            let mut inserted = String::new();
+            for _ in 0..next(3) {
+                inserted.push_str(pieces[next(pieces.len())]);
+            }
+            edit(&mut map, &mut text, start..end, &inserted);
+            // Sometimes let edits pile up before reparsing
+            if next(3) > 0 {
+                map.reparse(&text);
+                assert_matches_full_parse(&map, &text);
+            }
+        }
+        map.reparse(&text);
+        assert_matches_full_parse(&map, &text);
+    }
+}
@@ -0,0 +1,17 @@
+//! Syntax Tree Module
+//!
+//! Lossless syntax trees for text typed into the terminal. The shell parser
+//! backs highlighting, completion, corrections and validation of the input
+//! buffer. [`highlight`] keeps highlights of edited text up to date for any
+//! language in the [`languages`](crate::languages) registry.
+
+pub mod highlight;
+pub mod shell;
+
+pub use highlight::{Grammar, HighlightMap, LineState, Scope, Token};
+
+pub use shell::{
+    parse as parse_shell, CommandView, Highlight, HighlightKind, NodeKind, ParseError,
+    RedirectView, ShellDialect, ShellSyntaxTree, SyntaxElement, SyntaxNode, SyntaxToken, TokenKind,
+    WordView,
+};
@@ -0,0 +1,1755 @@
+//! Lossless command-line parser for POSIX sh, bash, zsh and fish.
+//!
+//! [`parse`] turns the input buffer into a [`ShellSyntaxTree`]. Every byte of the
+//! input belongs to exactly one leaf token, so the tree can be turned back into
+//! the original text and every element carries its byte span. The parser never
+//! fails: malformed input (unterminated quotes, unbalanced parentheses, a
+//! dangling `|`) produces [`ParseError`]s and `Error` tokens, and parsing
+//! continues after them. This matters because the buffer is usually
+//! incomplete while the user is typing.
+//!
+//! Higher level views over the tree ([`CommandView`], [`WordView`],
+//! [`Highlight`]) are what highlighting, completion, correction and validation
+//! consume.
+
+use std::ops::Range;
+
+use crate::shell::SupportedShell;
+
+/// Shell grammar variant used while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ShellDialect {
+    Posix,
+    #[default]
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl ShellDialect {
+    /// `<<<` here-strings, `&>` and `|&`.
+    fn has_bash_extensions(self) -> bool {
+        matches!(self, ShellDialect::Bash | ShellDialect::Zsh)
+    }
+
+    /// Fish uses `(cmd)` for command substitution and has no heredocs.
+    fn is_fish(self) -> bool {
+        self == ShellDialect::Fish
+    }
+
+    /// Guess the dialect from a shell path or name such as `/usr/bin/zsh`.
+    pub fn from_shell_path(path: &str) -> Self {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match name {
+            "zsh" => ShellDialect::Zsh,
+            "fish" => ShellDialect::Fish,
+            "bash" => ShellDialect::Bash,
+            _ => ShellDialect::Posix,
+        }
+    }
+
+    /// `value` as a single word: bare when it only has characters the shell
+    /// leaves alone, single-quoted otherwise.
+    pub fn quote(self, value: &str) -> String {
+        let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c);
+        if !value.is_empty() && value.chars().all(is_plain) {
+            value.to_string()
+        } else if self.is_fish() {
+            format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+        } else {
+            format!("'{}'", value.replace('\'', r"'\''"))
+        }
+    }
+}
+
+impl From<&SupportedShell> for ShellDialect {
+    fn from(shell: &SupportedShell) -> Self {
+        match shell {
+            SupportedShell::Bash | SupportedShell::GitBash | SupportedShell::Wsl2 => {
+                ShellDialect::Bash
+            }
+            SupportedShell::Zsh => ShellDialect::Zsh,
+            SupportedShell::Fish => ShellDialect::Fish,
+            _ => ShellDialect::Posix,
+        }
+    }
+}
+
+/// Kinds of interior nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// A list of commands separated by `;`, `&`, `&&`, `||` or newlines.
+    Program,
+    /// Two or more commands joined by `|` or `|&`.
+    Pipeline,
+    /// A simple command: assignments, words and redirects.
+    Command,
+    /// `( ... )`
+    Subshell,
+    /// `{ ...; }` (or `begin ...; end` in fish, which parses as commands)
+    Group,
+    /// A word made of literal, quoted and expansion parts.
+    Word,
+    /// `NAME=value` before the command name.
+    Assignment,
+    /// A redirect operator and its target.
+    Redirect,
+    /// `"..."` with its literal and expansion parts.
+    DoubleQuoted,
+    /// `$( ... )`, `` `...` `` or fish `( ... )`.
+    CommandSubstitution,
+}
+
+/// Kinds of leaf tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Whitespace,
+    /// A backslash-newline line continuation.
+    LineContinuation,
+    Newline,
+    Comment,
+    /// Unquoted word text, including backslash escapes.
+    Literal,
+    /// `'...'` or `$'...'`
+    SingleQuoted,
+    /// The `"` delimiters of a double-quoted string.
+    Quote,
+    /// `$name`, `${...}`, `$1`, `$?` ...
+    Variable,
+    /// `$(( ... ))`
+    Arithmetic,
+    /// `$(`, `(`, `)` and `` ` `` delimiters of a substitution.
+    SubstitutionDelimiter,
+    /// `;`, `&`, `&&`, `||`, `;;`
+    Operator,
+    /// `|` or `|&`
+    Pipe,
+    /// `(` / `)` of a subshell and `{` / `}` of a group.
+    GroupDelimiter,
+    /// `!` negating a pipeline.
+    Bang,
+    /// `>`, `2>>`, `&>`, `<<`, `<<<` ...
+    RedirectOperator,
+    /// `=` in an assignment.
+    Equals,
+    /// Heredoc body text, up to (not including) the terminator line.
+    HeredocBody,
+    /// The heredoc terminator line.
+    HeredocEnd,
+    /// Text that could not be parsed.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.span.clone(),
+            SyntaxElement::Token(token) => token.span.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Range<usize>,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn new(kind: NodeKind, start: usize) -> Self {
+        Self {
+            kind,
+            span: start..start,
+            children: Vec::new(),
+        }
+    }
+
+    fn push_token(&mut self, kind: TokenKind, span: Range<usize>) {
+        if span.is_empty() {
+            return;
+        }
+        self.span.end = self.span.end.max(span.end);
+        self.children
+            .push(SyntaxElement::Token(SyntaxToken { kind, span }));
+    }
+
+    fn push_node(&mut self, node: SyntaxNode) {
+        self.span.end = self.span.end.max(node.span.end);
+        self.children.push(SyntaxElement::Node(node));
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// All leaf tokens below this node, in source order.
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, out: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(out),
+                SyntaxElement::Token(token) => out.push(token),
+            }
+        }
+    }
+
+    /// All descendant nodes (including `self`) of the given kind, in source order.
+    pub fn descendants(&self, kind: NodeKind) -> Vec<&SyntaxNode> {
+        let mut nodes = Vec::new();
+        self.collect_descendants(kind, &mut nodes);
+        nodes
+    }
+
+    fn collect_descendants<'a>(&'a self, kind: NodeKind, out: &mut Vec<&'a SyntaxNode>) {
+        if self.kind == kind {
+            out.push(self);
+        }
+        for node in self.child_nodes() {
+            node.collect_descendants(kind, out);
+        }
+    }
+}
+
+/// A syntax error with the byte span it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+/// Words that start or continue compound commands rather than naming a program.
+const KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+    "in", "function", "select", "time", "coproc",
+];
+
+/// Fish keywords, in addition to the shared ones that fish also uses.
+const FISH_KEYWORDS: &[&str] = &[
+    "if", "else", "for", "in", "while", "function", "begin", "end", "switch", "case", "and", "or",
+    "not", "time",
+];
+
+fn is_keyword(dialect: ShellDialect, word: &str) -> bool {
+    if dialect.is_fish() {
+        FISH_KEYWORDS.contains(&word)
+    } else {
+        KEYWORDS.contains(&word) || word == "!"
+    }
+}
+
+/// The result of parsing a command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellSyntaxTree {
+    source: String,
+    dialect: ShellDialect,
+    root: SyntaxNode,
+    errors: Vec<ParseError>,
+}
+
+/// Parse `source` as a command line in the given dialect.
+pub fn parse(source: &str, dialect: ShellDialect) -> ShellSyntaxTree {
+    let mut parser = Parser {
+        src: source,
+        pos: 0,
+        dialect,
+        errors: Vec::new(),
+        pending_heredocs: Vec::new(),
+    };
+    let root = parser.parse_program(0, Closer::Eof);
+
+    ShellSyntaxTree {
+        source: source.to_string(),
+        dialect,
+        root,
+        errors: parser.errors,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Closer {
+    Eof,
+    /// `)` of a subshell or `$(`
+    Paren,
+    /// `}` of a group
+    Brace,
+    /// closing backtick
+    Backtick,
+}
+
+struct PendingHeredoc {
+    delimiter: String,
+    strip_tabs: bool,
+    span: Range<usize>,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    dialect: ShellDialect,
+    errors: Vec<ParseError>,
+    pending_heredocs: Vec<PendingHeredoc>,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.rest().chars().nth(offset)
+    }
+
+    fn at_eof(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&mut self, message: impl Into<String>, span: Range<usize>) {
+        self.errors.push(ParseError {
+            message: message.into(),
+            span,
+        });
+    }
+
+    fn is_closer(&self, closer: Closer) -> bool {
+        match closer {
+            Closer::Eof => false,
+            Closer::Paren => self.peek() == Some(')'),
+            Closer::Backtick => self.peek() == Some('`'),
+            Closer::Brace => self.peek() == Some('}') && self.is_word_end_at(self.pos + 1),
+        }
+    }
+
+    fn is_word_end_at(&self, at: usize) -> bool {
+        match self.src[at..].chars().next() {
+            None => true,
+            Some(c) => c.is_whitespace() || matches!(c, ';' | '&' | '|' | ')'),
+        }
+    }
+
+    /// Consume horizontal whitespace and line continuations into `node`.
+    fn eat_blank(&mut self, node: &mut SyntaxNode) -> bool {
+        let start_pos = self.pos;
+        loop {
+            let start = self.pos;
+            match self.peek() {
+                Some(' ' | '\t' | '\r') => {
+                    while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+                        self.bump();
+                    }
+                    node.push_token(TokenKind::Whitespace, start..self.pos);
+                }
+                Some('\\') if self.peek_at(1) == Some('\n') => {
+                    self.pos += 2;
+                    node.push_token(TokenKind::LineContinuation, start..self.pos);
+                }
+                _ => break,
+            }
+        }
+        self.pos > start_pos
+    }
+
+    /// Consume a newline, then any heredoc bodies that were waiting for it.
+    fn eat_newline(&mut self, node: &mut SyntaxNode) {
+        let start = self.pos;
+        self.bump();
+        node.push_token(TokenKind::Newline, start..self.pos);
+
+        for heredoc in std::mem::take(&mut self.pending_heredocs) {
+            let body_start = self.pos;
+            let mut terminated = false;
+            while !self.at_eof() {
+                let line_start = self.pos;
+                let line_end = self
+                    .rest()
+                    .find('\n')
+                    .map(|i| self.pos + i)
+                    .unwrap_or(self.src.len());
+                let line = &self.src[line_start..line_end];
+                let candidate = if heredoc.strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    line
+                };
+                if candidate == heredoc.delimiter {
+                    node.push_token(TokenKind::HeredocBody, body_start..line_start);
+                    node.push_token(TokenKind::HeredocEnd, line_start..line_end);
+                    self.pos = line_end;
+                    if self.peek() == Some('\n') {
+                        let nl = self.pos;
+                        self.bump();
+                        node.push_token(TokenKind::Newline, nl..self.pos);
+                    }
+                    terminated = true;
+                    break;
+                }
+                self.pos = (line_end + 1).min(self.src.len());
+            }
+            if !terminated {
+                node.push_token(TokenKind::HeredocBody, body_start..self.pos);
+                self.error(
+                    format!("Unterminated heredoc, expected '{}'", heredoc.delimiter),
+                    heredoc.span,
+                );
+            }
+        }
+    }
+
+    fn eat_comment(&mut self, node: &mut SyntaxNode) {
+        let start = self.pos;
+        let end = self
+            .rest()
+            .find('\n')
+            .map(|i| self.pos + i)
+            .unwrap_or(self.src.len());
+        self.pos = end;
+        node.push_token(TokenKind::Comment, start..end);
+    }
+
+    /// Parse a command list until `closer` (not consumed) or end of input.
+    fn parse_program(&mut self, start: usize, closer: Closer) -> SyntaxNode {
+        let mut program = SyntaxNode::new(NodeKind::Program, start);
+        // Whether the last significant element was an operator that needs a
+        // command after it (`|`, `&&`, `||`).
+        let mut dangling: Option<Range<usize>> = None;
+
+        loop {
+            self.eat_blank(&mut program);
+            if self.at_eof() || self.is_closer(closer) {
+                break;
+            }
+
+            let start = self.pos;
+            let rest = self.rest();
+            match self.peek() {
+                Some('\n') => self.eat_newline(&mut program),
+                Some('#') => self.eat_comment(&mut program),
+                Some(';') if rest.starts_with(";;") => {
+                    self.pos += 2;
+                    program.push_token(TokenKind::Operator, start..self.pos);
+                }
+                Some(';') => {
+                    self.pos += 1;
+                    program.push_token(TokenKind::Operator, start..self.pos);
+                    if let Some(span) = dangling.take() {
+                        self.error("Expected a command after operator", span);
+                    }
+                }
+                Some('&') if rest.starts_with("&&") => {
+                    self.pos += 2;
+                    program.push_token(TokenKind::Operator, start..self.pos);
+                    dangling = Some(start..self.pos);
+                }
+                Some('|') if rest.starts_with("||") => {
+                    self.pos += 2;
+                    program.push_token(TokenKind::Operator, start..self.pos);
+                    dangling = Some(start..self.pos);
+                }
+                Some('&') => {
+                    self.pos += 1;
+                    program.push_token(TokenKind::Operator, start..self.pos);
+                }
+                Some(')') | Some('`') if closer == Closer::Eof || closer == Closer::Brace => {
+                    self.bump();
+                    program.push_token(TokenKind::Error, start..self.pos);
+                    let c = &self.src[start..self.pos];
+                    self.error(format!("Unexpected '{}'", c), start..self.pos);
+                }
+                Some(')') if closer == Closer::Backtick => {
+                    self.bump();
+                    program.push_token(TokenKind::Error, start..self.pos);
+                    self.error("Unexpected ')'", start..self.pos);
+                }
+                Some('|') => {
+                    // A pipe with no command before it
+                    self.bump();
+                    if self.peek() == Some('&') {
+                        self.bump();
+                    }
+                    program.push_token(TokenKind::Error, start..self.pos);
+                    self.error("Expected a command before '|'", start..self.pos);
+                }
+                _ => {
+                    let pipeline = self.parse_pipeline(closer);
+                    program.push_node(pipeline);
+                    dangling = None;
+                    // A pipeline ending in a dangling `|` has already been reported
+                }
+            }
+        }
+
+        if let Some(span) = dangling {
+            self.error("Expected a command after operator", span);
+        }
+        program.span.start = start;
+        program.span.end = program.span.end.max(self.pos);
+        program
+    }
+
+    /// Parse `[!] command { | command }`. Returns the bare command when there
+    /// is no pipe.
+    fn parse_pipeline(&mut self, closer: Closer) -> SyntaxNode {
+        let start = self.pos;
+        let mut pipeline = SyntaxNode::new(NodeKind::Pipeline, start);
+
+        if self.peek() == Some('!') && self.is_word_end_at(self.pos + 1) {
+            self.bump();
+            pipeline.push_token(TokenKind::Bang, start..self.pos);
+            self.eat_blank(&mut pipeline);
+        }
+
+        let mut commands = 0;
+        loop {
+            let command = self.parse_command(closer);
+            pipeline.push_node(command);
+            commands += 1;
+
+            let checkpoint = self.pos;
+            let mut blank = SyntaxNode::new(NodeKind::Pipeline, self.pos);
+            self.eat_blank(&mut blank);
+            let rest = self.rest();
+            let pipe_len = if rest.starts_with("|&") && self.dialect.has_bash_extensions() {
+                2
+            } else if rest.starts_with('|') && !rest.starts_with("||") {
+                1
+            } else {
+                0
+            };
+            if pipe_len == 0 {
+                self.pos = checkpoint;
+                break;
+            }
+
+            pipeline.children.extend(blank.children);
+            let pipe_start = self.pos;
+            self.pos += pipe_len;
+            pipeline.push_token(TokenKind::Pipe, pipe_start..self.pos);
+
+            // Commands may continue on the next line after a pipe
+            loop {
+                self.eat_blank(&mut pipeline);
+                match self.peek() {
+                    Some('\n') => self.eat_newline(&mut pipeline),
+                    Some('#') => self.eat_comment(&mut pipeline),
+                    _ => break,
+                }
+            }
+
+            if self.at_eof() || self.is_closer(closer) || self.starts_operator() {
+                self.error("Expected a command after '|'", pipe_start..self.pos);
+                break;
+            }
+        }
+
+        if commands == 1 && pipeline.children.len() == 1 {
+            match pipeline.children.pop() {
+                Some(SyntaxElement::Node(node)) => return node,
+                _ => unreachable!("pipeline holds exactly one command"),
+            }
+        }
+        pipeline.span.start = start;
+        pipeline
+    }
+
+    fn starts_operator(&self) -> bool {
+        matches!(self.peek(), Some(';' | '&' | '|' | ')'))
+    }
+
+    /// Parse a subshell, group or simple command.
+    fn parse_command(&mut self, closer: Closer) -> SyntaxNode {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') if !self.dialect.is_fish() => {
+                let mut subshell = SyntaxNode::new(NodeKind::Subshell, start);
+                self.bump();
+                subshell.push_token(TokenKind::GroupDelimiter, start..self.pos);
+                let inner = self.parse_program(self.pos, Closer::Paren);
+                subshell.push_node(inner);
+                if self.peek() == Some(')') {
+                    let close = self.pos;
+                    self.bump();
+                    subshell.push_token(TokenKind::GroupDelimiter, close..self.pos);
+                } else {
+                    self.error("Unclosed '(' subshell", start..start + 1);
+                }
+                self.parse_trailing_redirects(&mut subshell, closer);
+                subshell
+            }
+            Some('{') if !self.dialect.is_fish() && self.is_word_end_at(self.pos + 1) => {
+                let mut group = SyntaxNode::new(NodeKind::Group, start);
+                self.bump();
+                group.push_token(TokenKind::GroupDelimiter, start..self.pos);
+                let inner = self.parse_program(self.pos, Closer::Brace);
+                group.push_node(inner);
+                if self.peek() == Some('}') {
+                    let close = self.pos;
+                    self.bump();
+                    group.push_token(TokenKind::GroupDelimiter, close..self.pos);
+                } else {
+                    self.error("Unclosed '{' group", start..start + 1);
+                }
+                self.parse_trailing_redirects(&mut group, closer);
+                group
+            }
+            _ => self.parse_simple_command(closer),
+        }
+    }
+
+    fn parse_trailing_redirects(&mut self, node: &mut SyntaxNode, closer: Closer) {
+        loop {
+            let checkpoint = self.pos;
+            let mut blank = SyntaxNode::new(node.kind, self.pos);
+            self.eat_blank(&mut blank);
+            if self.redirect_operator_len().is_some() && !self.is_closer(closer) {
+                node.children.extend(blank.children);
+                let redirect = self.parse_redirect();
+                node.push_node(redirect);
+            } else {
+                self.pos = checkpoint;
+                break;
+            }
+        }
+    }
+
+    fn parse_simple_command(&mut self, closer: Closer) -> SyntaxNode {
+        let mut command = SyntaxNode::new(NodeKind::Command, self.pos);
+        let mut seen_name = false;
+        let mut after_keyword = false;
+
+        loop {
+            if self.at_eof() || self.is_closer(closer) {
+                break;
+            }
+            match self.peek() {
+                Some('\n' | ';' | '|' | ')') => break,
+                Some('&') if !self.rest().starts_with("&>") => break,
+                Some('&') if !self.dialect.has_bash_extensions() => break,
+                Some('#') if self.pos == command.span.start || self.prev_is_blank() => break,
+                Some('`') if closer == Closer::Backtick => break,
+                Some('(') if !self.dialect.is_fish() && after_keyword => {
+                    // A subshell after `do`, `then` and the like
+                    let subshell = self.parse_command(closer);
+                    command.push_node(subshell);
+                    after_keyword = false;
+                    continue;
+                }
+                Some('(') if !self.dialect.is_fish() => {
+                    // `(` in the middle of a simple command is a syntax error
+                    let start = self.pos;
+                    self.bump();
+                    command.push_token(TokenKind::Error, start..self.pos);
+                    self.error("Unexpected '('", start..self.pos);
+                    continue;
+                }
+                _ => {}
+            }
+
+            if self.redirect_operator_len().is_some() {
+                let redirect = self.parse_redirect();
+                command.push_node(redirect);
+            } else if !seen_name && self.at_assignment() {
+                let assignment = self.parse_assignment(closer);
+                command.push_node(assignment);
+            } else {
+                let word = self.parse_word(closer);
+                if word.children.is_empty() {
+                    // Nothing consumable here; avoid looping forever
+                    let start = self.pos;
+                    self.bump();
+                    command.push_token(TokenKind::Error, start..self.pos);
+                    self.error("Unexpected character", start..self.pos);
+                    continue;
+                }
+                // Assignments may follow keywords such as `if` or `then`
+                let text = &self.src[word.span.clone()];
+                after_keyword = !seen_name && is_keyword(self.dialect, text);
+                if !after_keyword {
+                    seen_name = true;
+                }
+                command.push_node(word);
+            }
+
+            // Interior whitespace belongs to the command; trailing whitespace
+            // before an operator belongs to the parent.
+            let checkpoint = self.pos;
+            let mut blank = SyntaxNode::new(NodeKind::Command, self.pos);
+            if !self.eat_blank(&mut blank) {
+                continue;
+            }
+            let ends_command = self.at_eof()
+                || self.is_closer(closer)
+                || matches!(self.peek(), Some('\n' | ';' | '|' | ')' | '#'))
+                || (self.peek() == Some('&') && !self.rest().starts_with("&>"))
+                || (self.peek() == Some('`') && closer == Closer::Backtick);
+            if !ends_command {
+                command.children.extend(blank.children);
+                command.span.end = self.pos;
+            } else {
+                self.pos = checkpoint;
+                break;
+            }
+        }
+
+        command
+    }
+
+    fn prev_is_blank(&self) -> bool {
+        self.src[..self.pos]
+            .chars()
+            .next_back()
+            .map(|c| c.is_whitespace())
+            .unwrap_or(true)
+    }
+
+    fn at_assignment(&self) -> bool {
+        let rest = self.rest();
+        let name_len = rest
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .count();
+        name_len > 0
+            && !rest.starts_with(|c: char| c.is_ascii_digit())
+            && (rest[name_len..].starts_with('=') || rest[name_len..].starts_with("+="))
+            && !self.dialect.is_fish()
+    }
+
+    fn parse_assignment(&mut self, closer: Closer) -> SyntaxNode {
+        let start = self.pos;
+        let mut assignment = SyntaxNode::new(NodeKind::Assignment, start);
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_') {
+            self.bump();
+        }
+        assignment.push_token(TokenKind::Literal, start..self.pos);
+        let eq = self.pos;
+        if self.peek() == Some('+') {
+            self.bump();
+        }
+        self.bump();
+        assignment.push_token(TokenKind::Equals, eq..self.pos);
+
+        if self.peek() == Some('(') && self.dialect.has_bash_extensions() {
+            self.parse_array_value(&mut assignment);
+            return assignment;
+        }
+        let value = self.parse_word(closer);
+        if !value.children.is_empty() {
+            assignment.push_node(value);
+        }
+        assignment
+    }
+
+    /// `(a b c)` after `NAME=` or `NAME+=` in bash and zsh.
+    fn parse_array_value(&mut self, assignment: &mut SyntaxNode) {
+        let open = self.pos;
+        self.bump();
+        assignment.push_token(TokenKind::GroupDelimiter, open..self.pos);
+        loop {
+            self.eat_blank(assignment);
+            let start = self.pos;
+            match self.peek() {
+                Some(')') => {
+                    self.bump();
+                    assignment.push_token(TokenKind::GroupDelimiter, start..self.pos);
+                    return;
+                }
+                Some('\n') => {
+                    self.bump();
+                    assignment.push_token(TokenKind::Newline, start..self.pos);
+                }
+                None => {
+                    self.error("Unclosed '(' array", open..open + 1);
+                    return;
+                }
+                _ => {
+                    let word = self.parse_word(Closer::Paren);
+                    if word.children.is_empty() {
+                        self.bump();
+                        assignment.push_token(TokenKind::Error, start..self.pos);
+                        self.error("Unexpected character in array", start..self.pos);
+                    } else {
+                        assignment.push_node(word);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Length of the redirect operator at the cursor, including a leading fd.
+    fn redirect_operator_len(&self) -> Option<usize> {
+        let rest = self.rest();
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        let after = &rest[digits..];
+
+        let ops: &[&str] = if self.dialect.has_bash_extensions() {
+            &[
+                "<<<", "<<-", "&>>", "<<", ">>", ">&", "<&", ">|", "<>", "&>", "<", ">",
+            ]
+        } else if self.dialect.is_fish() {
+            &[">>", ">?", "<?", ">&", "<&", "&>", "<", ">"]
+        } else {
+            &["<<-", "<<", ">>", ">&", "<&", ">|", "<>", "<", ">"]
+        };
+
+        // `&>` never takes an fd prefix
+        ops.iter()
+            .find(|op| after.starts_with(**op) && !(digits > 0 && op.starts_with('&')))
+            .map(|op| digits + op.len())
+    }
+
+    fn parse_redirect(&mut self) -> SyntaxNode {
+        let start = self.pos;
+        let mut redirect = SyntaxNode::new(NodeKind::Redirect, start);
+        let len = self.redirect_operator_len().unwrap_or(1);
+        self.pos += len;
+        redirect.push_token(TokenKind::RedirectOperator, start..self.pos);
+        let op = self.src[start..self.pos].trim_start_matches(|c: char| c.is_ascii_digit());
+
+        self.eat_blank(&mut redirect);
+        let target = if self.at_eof() || matches!(self.peek(), Some('\n' | ';' | '|' | '&' | ')')) {
+            None
+        } else {
+            Some(self.parse_word(Closer::Eof))
+        };
+
+        match target {
+            Some(word) if !word.children.is_empty() => {
+                if op == "<<" || op == "<<-" {
+                    let delimiter = word_value(self.src, &word);
+                    self.pending_heredocs.push(PendingHeredoc {
+                        delimiter,
+                        strip_tabs: op == "<<-",
+                        span: start..word.span.end,
+                    });
+                }
+                redirect.push_node(word);
+            }
+            _ => self.error(format!("Expected a target after '{}'", op), start..self.pos),
+        }
+        redirect
+    }
+
+    fn is_word_terminator(&self, c: char, closer: Closer) -> bool {
+        if c.is_whitespace() {
+            return true;
+        }
+        match c {
+            ';' | '|' | '&' | '<' | '>' => true,
+            ')' => true,
+            '(' => !self.dialect.is_fish(),
+            '`' => closer == Closer::Backtick,
+            _ => false,
+        }
+    }
+
+    /// Parse one word. Returns an empty node if no word starts here.
+    fn parse_word(&mut self, closer: Closer) -> SyntaxNode {
+        let mut word = SyntaxNode::new(NodeKind::Word, self.pos);
+
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            match c {
+                '\\' => {
+                    self.bump();
+                    if self.bump().is_none() {
+                        self.error("Trailing backslash", start..self.pos);
+                    }
+                    self.push_literal(&mut word, start);
+                }
+                '\'' => {
+                    self.bump();
+                    self.eat_single_quoted(&mut word, start);
+                }
+                '$' if self.peek_at(1) == Some('\'') && self.dialect.has_bash_extensions() => {
+                    self.pos += 2;
+                    self.eat_single_quoted(&mut word, start);
+                }
+                '"' => {
+                    let quoted = self.parse_double_quoted();
+                    word.push_node(quoted);
+                }
+                '$' => self.parse_dollar(&mut word),
+                '`' if closer != Closer::Backtick => {
+                    let substitution = self.parse_backtick();
+                    word.push_node(substitution);
+                }
+                '(' if self.dialect.is_fish() => {
+                    let substitution = self.parse_paren_substitution(start, 1);
+                    word.push_node(substitution);
+                }
+                c if self.is_word_terminator(c, closer) => break,
+                '}' if closer == Closer::Brace
+                    && word.children.is_empty()
+                    && self.is_word_end_at(self.pos + 1) =>
+                {
+                    break;
+                }
+                _ => {
+                    self.bump();
+                    self.push_literal(&mut word, start);
+                }
+            }
+        }
+
+        word
+    }
+
+    /// Append a literal span, merging with a directly preceding literal.
+    fn push_literal(&self, node: &mut SyntaxNode, start: usize) {
+        if let Some(SyntaxElement::Token(last)) = node.children.last_mut() {
+            if last.kind == TokenKind::Literal && last.span.end == start {
+                last.span.end = self.pos;
+                node.span.end = self.pos;
+                return;
+            }
+        }
+        node.push_token(TokenKind::Literal, start..self.pos);
+    }
+
+    fn eat_single_quoted(&mut self, word: &mut SyntaxNode, start: usize) {
+        let ansi = self.src[start..].starts_with('$');
+        loop {
+            match self.bump() {
+                Some('\'') => break,
+                Some('\\') if ansi => {
+                    self.bump();
+                }
+                Some(_) => {}
+                None => {
+                    self.error("Unterminated single quote", start..self.pos);
+                    break;
+                }
+            }
+        }
+        word.push_token(TokenKind::SingleQuoted, start..self.pos);
+    }
+
+    fn parse_double_quoted(&mut self) -> SyntaxNode {
+        let start = self.pos;
+        let mut quoted = SyntaxNode::new(NodeKind::DoubleQuoted, start);
+        self.bump();
+        quoted.push_token(TokenKind::Quote, start..self.pos);
+
+        loop {
+            let part_start = self.pos;
+            match self.peek() {
+                None => {
+                    self.error("Unterminated double quote", start..self.pos);
+                    break;
+                }
+                Some('"') => {
+                    self.bump();
+                    quoted.push_token(TokenKind::Quote, part_start..self.pos);
+                    break;
+                }
+                Some('\\') => {
+                    self.bump();
+                    self.bump();
+                    self.push_literal(&mut quoted, part_start);
+                }
+                Some('$') => self.parse_dollar(&mut quoted),
+                Some('`') => {
+                    let substitution = self.parse_backtick();
+                    quoted.push_node(substitution);
+                }
+                Some('(') if self.dialect.is_fish() => {
+                    // Fish does not expand `(...)` inside double quotes
+                    self.bump();
+                    self.push_literal(&mut quoted, part_start);
+                }
+                Some(_) => {
+                    self.bump();
+                    self.push_literal(&mut quoted, part_start);
+                }
+            }
+        }
+        quoted
+    }
+
+    /// Parse an expansion starting with `$` into `parent`.
+    fn parse_dollar(&mut self, parent: &mut SyntaxNode) {
+        let start = self.pos;
+        let rest = self.rest();
+
+        if rest.starts_with("$((") && !self.dialect.is_fish() {
+            self.pos += 3;
+            let mut depth = 2usize;
+            while depth > 0 {
+                match self.bump() {
+                    Some('(') => depth += 1,
+                    Some(')') => depth -= 1,
+                    Some(_) => {}
+                    None => {
+                        self.error("Unterminated arithmetic expansion", start..self.pos);
+                        break;
+                    }
+                }
+            }
+            parent.push_token(TokenKind::Arithmetic, start..self.pos);
+        } else if rest.starts_with("$(") {
+            let substitution = self.parse_paren_substitution(start, 2);
+            parent.push_node(substitution);
+        } else if rest.starts_with("${") && !self.dialect.is_fish() {
+            self.pos += 2;
+            let mut depth = 1usize;
+            while depth > 0 {
+                match self.bump() {
+                    Some('{') => depth += 1,
+                    Some('}') => depth -= 1,
+                    Some(_) => {}
+                    None => {
+                        self.error("Unterminated '${' expansion", start..self.pos);
+                        break;
+                    }
+                }
+            }
+            parent.push_token(TokenKind::Variable, start..self.pos);
+        } else {
+            self.bump();
+            match self.peek() {
+                Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                    while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_') {
+                        self.bump();
+                    }
+                    parent.push_token(TokenKind::Variable, start..self.pos);
+                }
+                Some(c) if c.is_ascii_digit() || "?#@*$!-".contains(c) => {
+                    self.bump();
+                    parent.push_token(TokenKind::Variable, start..self.pos);
+                }
+                _ => {
+                    // A lone `$` is literal
+                    self.push_literal(parent, start);
+                }
+            }
+        }
+    }
+
+    /// Parse `$( ... )` (open_len 2) or fish `( ... )` (open_len 1).
+    fn parse_paren_substitution(&mut self, start: usize, open_len: usize) -> SyntaxNode {
+        let mut substitution = SyntaxNode::new(NodeKind::CommandSubstitution, start);
+        self.pos += open_len;
+        substitution.push_token(TokenKind::SubstitutionDelimiter, start..self.pos);
+        let inner = self.parse_program(self.pos, Closer::Paren);
+        substitution.push_node(inner);
+        if self.peek() == Some(')') {
+            let close = self.pos;
+            self.bump();
+            substitution.push_token(TokenKind::SubstitutionDelimiter, close..self.pos);
+        } else {
+            self.error("Unclosed command substitution", start..start + open_len);
+        }
+        substitution
+    }
+
+    fn parse_backtick(&mut self) -> SyntaxNode {
+        let start = self.pos;
+        let mut substitution = SyntaxNode::new(NodeKind::CommandSubstitution, start);
+        self.bump();
+        substitution.push_token(TokenKind::SubstitutionDelimiter, start..self.pos);
+        let inner = self.parse_program(self.pos, Closer::Backtick);
+        substitution.push_node(inner);
+        if self.peek() == Some('`') {
+            let close = self.pos;
+            self.bump();
+            substitution.push_token(TokenKind::SubstitutionDelimiter, close..self.pos);
+        } else {
+            self.error("Unclosed backtick substitution", start..start + 1);
+        }
+        substitution
+    }
+}
+
+/// The value of a word with quotes removed and escapes resolved. Expansions
+/// are kept verbatim since their value is only known to the shell.
+fn word_value(src: &str, word: &SyntaxNode) -> String {
+    let mut value = String::new();
+    for child in &word.children {
+        match child {
+            SyntaxElement::Token(token) => {
+                let text = &src[token.span.clone()];
+                match token.kind {
+                    TokenKind::Literal => push_unescaped(&mut value, text, false),
+                    TokenKind::SingleQuoted => {
+                        let inner = text.strip_prefix('$').unwrap_or(text);
+                        let inner = inner.strip_prefix('\'').unwrap_or(inner);
+                        value.push_str(inner.strip_suffix('\'').unwrap_or(inner));
+                    }
+                    _ => value.push_str(text),
+                }
+            }
+            SyntaxElement::Node(node) if node.kind == NodeKind::DoubleQuoted => {
+                for part in &node.children {
+                    match part {
+                        SyntaxElement::Token(t) if t.kind == TokenKind::Quote => {}
+                        SyntaxElement::Token(t) if t.kind == TokenKind::Literal => {
+                            push_unescaped(&mut value, &src[t.span.clone()], true)
+                        }
+                        other => value.push_str(&src[other.span()]),
+                    }
+                }
+            }
+            SyntaxElement::Node(node) => value.push_str(&src[node.span.clone()]),
+        }
+    }
+    value
+}
+
+fn push_unescaped(out: &mut String, text: &str, in_double_quotes: bool) {
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('\n') => {}
+                Some(next) if !in_double_quotes || "$`\"\\".contains(next) => out.push(next),
+                Some(next) => {
+                    out.push('\\');
+                    out.push(next);
+                }
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// A word of a command with its span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordView {
+    pub span: Range<usize>,
+    /// The word exactly as typed.
+    pub raw: String,
+    /// The word with quotes removed and escapes resolved.
+    pub value: String,
+    /// Whether the word contains parameter, arithmetic or command expansions.
+    pub has_expansion: bool,
+}
+
+/// A redirect of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectView {
+    pub span: Range<usize>,
+    /// The operator including any fd prefix, e.g. `2>`.
+    pub operator: String,
+    pub target: Option<WordView>,
+}
+
+/// A simple command with its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandView {
+    pub span: Range<usize>,
+    pub assignments: Vec<WordView>,
+    /// Leading keywords such as `if`, `then`, `do` or fish `and`.
+    pub keywords: Vec<WordView>,
+    pub name: Option<WordView>,
+    pub args: Vec<WordView>,
+    pub redirects: Vec<RedirectView>,
+}
+
+impl CommandView {
+    /// The command name followed by its arguments, unquoted.
+    pub fn argv(&self) -> Vec<String> {
+        self.name
+            .iter()
+            .chain(self.args.iter())
+            .map(|word| word.value.clone())
+            .collect()
+    }
+
+    /// All words (name and arguments) with their spans.
+    pub fn words(&self) -> Vec<&WordView> {
+        self.name.iter().chain(self.args.iter()).collect()
+    }
+}
+
+/// Semantic classes used for highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    Command,
+    Keyword,
+    Argument,
+    Flag,
+    String,
+    Variable,
+    Substitution,
+    Operator,
+    Redirect,
+    Comment,
+    Number,
+    Assignment,
+    Heredoc,
+    Error,
+}
+
+/// A highlighted span of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub span: Range<usize>,
+    pub kind: HighlightKind,
+}
+
+impl ShellSyntaxTree {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn dialect(&self) -> ShellDialect {
+        self.dialect
+    }
+
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// The source text of a span.
+    pub fn text(&self, span: Range<usize>) -> &str {
+        &self.source[span]
+    }
+
+    /// Reassemble the source from the leaf tokens. Always equals the input.
+    pub fn to_source(&self) -> String {
+        self.root
+            .tokens()
+            .iter()
+            .map(|token| &self.source[token.span.clone()])
+            .collect()
+    }
+
+    /// Every simple command, including those inside subshells, groups and
+    /// command substitutions, in source order.
+    pub fn commands(&self) -> Vec<CommandView> {
+        self.root
+            .descendants(NodeKind::Command)
+            .into_iter()
+            .map(|node| self.command_view(node))
+            .collect()
+    }
+
+    /// The innermost simple command containing `offset`, or the command that
+    /// ends right at `offset` (the cursor after the last word).
+    pub fn command_at(&self, offset: usize) -> Option<CommandView> {
+        self.root
+            .descendants(NodeKind::Command)
+            .into_iter()
+            .filter(|node| node.span.start <= offset && offset <= node.span.end)
+            .min_by_key(|node| node.span.len())
+            .map(|node| self.command_view(node))
+    }
+
+    /// The command being typed when the cursor is at `cursor`. Unlike
+    /// [`command_at`](Self::command_at) this also finds the command when the
+    /// cursor sits after trailing blanks, where a new argument is starting.
+    pub fn command_at_cursor(&self, cursor: usize) -> Option<CommandView> {
+        let cursor = cursor.min(self.source.len());
+        let typed_end = self.source[..cursor].trim_end_matches([' ', '\t']).len();
+        self.command_at(typed_end)
+    }
+
+    /// The word containing or ending at `offset`.
+    pub fn word_at(&self, offset: usize) -> Option<WordView> {
+        let command = self.command_at(offset)?;
+        command
+            .assignments
+            .into_iter()
+            .chain(command.keywords)
+            .chain(command.name)
+            .chain(command.args)
+            .chain(command.redirects.into_iter().filter_map(|r| r.target))
+            .find(|word| word.span.start <= offset && offset <= word.span.end)
+    }
+
+    fn word_view(&self, node: &SyntaxNode) -> WordView {
+        let has_expansion = node.tokens().iter().any(|t| {
+            matches!(
+                t.kind,
+                TokenKind::Variable | TokenKind::Arithmetic | TokenKind::SubstitutionDelimiter
+            )
+        });
+        WordView {
+            span: node.span.clone(),
+            raw: self.source[node.span.clone()].to_string(),
+            value: word_value(&self.source, node),
+            has_expansion,
+        }
+    }
+
+    fn command_view(&self, node: &SyntaxNode) -> CommandView {
+        let mut view = CommandView {
+            span: node.span.clone(),
+            assignments: Vec::new(),
+            keywords: Vec::new(),
+            name: None,
+            args: Vec::new(),
+            redirects: Vec::new(),
+        };
+
+        for child in node.child_nodes() {
+            match child.kind {
+                NodeKind::Assignment => view.assignments.push(self.word_view(child)),
+                NodeKind::Redirect => view.redirects.push(RedirectView {
+                    span: child.span.clone(),
+                    operator: child
+                        .children
+                        .first()
+                        .map(|op| self.source[op.span()].to_string())
+                        .unwrap_or_default(),
+                    target: child
+                        .child_nodes()
+                        .find(|n| n.kind == NodeKind::Word)
+                        .map(|n| self.word_view(n)),
+                }),
+                NodeKind::Word => {
+                    let word = self.word_view(child);
+                    if view.name.is_some() {
+                        view.args.push(word);
+                    } else if word.raw == word.value && is_keyword(self.dialect, &word.value) {
+                        view.keywords.push(word);
+                    } else {
+                        view.name = Some(word);
+                    }
+                }
+                _ => {}
+            }
+        }
+        view
+    }
+
+    /// Semantic highlight spans covering every non-whitespace token.
+    pub fn highlights(&self) -> Vec<Highlight> {
+        let mut highlights = Vec::new();
+        self.highlight_node(&self.root, &mut highlights);
+        highlights.sort_by_key(|h| h.span.start);
+        highlights
+    }
+
+    fn highlight_node(&self, node: &SyntaxNode, out: &mut Vec<Highlight>) {
+        if node.kind == NodeKind::Command {
+            let view = self.command_view(node);
+            let word_kind = |span: &Range<usize>| {
+                if view.name.as_ref().map(|n| &n.span) == Some(span) {
+                    Some(HighlightKind::Command)
+                } else if view.keywords.iter().any(|k| &k.span == span) {
+                    Some(HighlightKind::Keyword)
+                } else {
+                    None
+                }
+            };
+
+            for child in &node.children {
+                match child {
+                    SyntaxElement::Node(word) if word.kind == NodeKind::Word => {
+                        let kind = word_kind(&word.span).unwrap_or_else(|| {
+                            let text = &self.source[word.span.clone()];
+                            if text.starts_with('-') && text.len() > 1 {
+                                HighlightKind::Flag
+                            } else if text.chars().all(|c| c.is_ascii_digit()) {
+                                HighlightKind::Number
+                            } else {
+                                HighlightKind::Argument
+                            }
+                        });
+                        self.highlight_word(word, kind, out);
+                    }
+                    SyntaxElement::Node(assignment) if assignment.kind == NodeKind::Assignment => {
+                        for part in &assignment.children {
+                            match part {
+                                SyntaxElement::Node(value) => {
+                                    self.highlight_word(value, HighlightKind::Argument, out)
+                                }
+                                SyntaxElement::Token(token) => out.push(Highlight {
+                                    span: token.span.clone(),
+                                    kind: HighlightKind::Assignment,
+                                }),
+                            }
+                        }
+                    }
+                    SyntaxElement::Node(other) => self.highlight_node(other, out),
+                    SyntaxElement::Token(token) => self.highlight_token(token, out),
+                }
+            }
+            return;
+        }
+
+        if node.kind == NodeKind::Redirect {
+            for child in &node.children {
+                match child {
+                    SyntaxElement::Node(word) => {
+                        self.highlight_word(word, HighlightKind::Argument, out)
+                    }
+                    SyntaxElement::Token(token) => self.highlight_token(token, out),
+                }
+            }
+            return;
+        }
+
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) => self.highlight_node(child, out),
+                SyntaxElement::Token(token) => self.highlight_token(token, out),
+            }
+        }
+    }
+
+    fn highlight_word(&self, word: &SyntaxNode, kind: HighlightKind, out: &mut Vec<Highlight>) {
+        for child in &word.children {
+            match child {
+                SyntaxElement::Token(token) if token.kind == TokenKind::Literal => {
+                    out.push(Highlight {
+                        span: token.span.clone(),
+                        kind,
+                    })
+                }
+                SyntaxElement::Token(token) => self.highlight_token(token, out),
+                SyntaxElement::Node(node) if node.kind == NodeKind::DoubleQuoted => {
+                    for part in &node.children {
+                        match part {
+                            SyntaxElement::Token(t)
+                                if matches!(t.kind, TokenKind::Literal | TokenKind::Quote) =>
+                            {
+                                out.push(Highlight {
+                                    span: t.span.clone(),
+                                    kind: HighlightKind::String,
+                                })
+                            }
+                            SyntaxElement::Token(t) => self.highlight_token(t, out),
+                            SyntaxElement::Node(n) => self.highlight_node(n, out),
+                        }
+                    }
+                }
+                SyntaxElement::Node(node) => self.highlight_node(node, out),
+            }
+        }
+    }
+
+    fn highlight_token(&self, token: &SyntaxToken, out: &mut Vec<Highlight>) {
+        let kind = match token.kind {
+            TokenKind::Whitespace | TokenKind::LineContinuation | TokenKind::Newline => return,
+            TokenKind::Comment => HighlightKind::Comment,
+            TokenKind::Literal => HighlightKind::Argument,
+            TokenKind::SingleQuoted | TokenKind::Quote => HighlightKind::String,
+            TokenKind::Variable | TokenKind::Arithmetic => HighlightKind::Variable,
+            TokenKind::SubstitutionDelimiter => HighlightKind::Substitution,
+            TokenKind::Operator
+            | TokenKind::Pipe
+            | TokenKind::GroupDelimiter
+            | TokenKind::Bang
+            | TokenKind::Equals => HighlightKind::Operator,
+            TokenKind::RedirectOperator => HighlightKind::Redirect,
+            TokenKind::HeredocBody | TokenKind::HeredocEnd => HighlightKind::Heredoc,
+            TokenKind::Error => HighlightKind::Error,
+        };
+        out.push(Highlight {
+            span: token.span.clone(),
+            kind,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bash(source: &str) -> ShellSyntaxTree {
+        parse(source, ShellDialect::Bash)
+    }
+
+    fn argvs(tree: &ShellSyntaxTree) -> Vec<Vec<String>> {
+        tree.commands().iter().map(|c| c.argv()).collect()
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let inputs = [
+            "",
+            "ls -la",
+            "  echo 'it''s' \"a $b\" \\\n  c  ",
+            "cat <<EOF | grep x\nline $one\nEOF\necho done",
+            "(cd /tmp && make) > log 2>&1 &",
+            "echo $(git rev-parse --abbrev-ref HEAD)-`date`",
+            "echo 'unterminated",
+            "ls | | wc )",
+            "FOO=1 BAR=\"x y\" env | sort # trailing comment",
+            "echo ünïcödé 日本語 | wc -c",
+        ];
+        for input in inputs {
+            let tree = bash(input);
+            assert_eq!(tree.to_source(), input, "round trip failed for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_quoted_arguments() {
+        let tree = bash(r#"git commit -m "fix the build" 'single quoted' esc\ aped"#);
+        assert_eq!(
+            argvs(&tree),
+            vec![vec![
+                "git",
+                "commit",
+                "-m",
+                "fix the build",
+                "single quoted",
+                "esc aped"
+            ]]
+        );
+        assert!(!tree.has_errors());
+    }
+
+    #[test]
+    fn test_pipes_and_lists() {
+        let tree = bash("cat file | grep foo && echo ok || echo fail; ls &");
+        assert_eq!(
+            argvs(&tree),
+            vec![
+                vec!["cat", "file"],
+                vec!["grep", "foo"],
+                vec!["echo", "ok"],
+                vec!["echo", "fail"],
+                vec!["ls"],
+            ]
+        );
+        assert_eq!(tree.root().descendants(NodeKind::Pipeline).len(), 1);
+    }
+
+    #[test]
+    fn test_redirects() {
+        let tree = bash("make 2>&1 >> build.log < input.txt");
+        let command = &tree.commands()[0];
+        assert_eq!(command.argv(), vec!["make"]);
+        let ops: Vec<_> = command
+            .redirects
+            .iter()
+            .map(|r| r.operator.as_str())
+            .collect();
+        assert_eq!(ops, vec!["2>&", ">>", "<"]);
+        assert_eq!(
+            command.redirects[1].target.as_ref().unwrap().value,
+            "build.log"
+        );
+    }
+
+    #[test]
+    fn test_subshells_and_substitutions() {
+        let tree = bash("(cd src && cargo build) ; echo \"$(whoami) at $(pwd)\"");
+        assert_eq!(
+            argvs(&tree),
+            vec![
+                vec!["cd", "src"],
+                vec!["cargo", "build"],
+                vec!["echo", "$(whoami) at $(pwd)"],
+                vec!["whoami"],
+                vec!["pwd"],
+            ]
+        );
+        assert_eq!(tree.root().descendants(NodeKind::Subshell).len(), 1);
+        assert!(!tree.has_errors());
+    }
+
+    #[test]
+    fn test_heredoc() {
+        let tree = bash("cat <<-'END' > out\n\tbody $not_expanded\n\tEND\necho after");
+        assert!(!tree.has_errors(), "{:?}", tree.errors());
+        assert_eq!(argvs(&tree), vec![vec!["cat"], vec!["echo", "after"]]);
+        let body = tree
+            .root()
+            .tokens()
+            .into_iter()
+            .find(|t| t.kind == TokenKind::HeredocBody)
+            .unwrap();
+        assert_eq!(tree.text(body.span.clone()), "\tbody $not_expanded\n");
+    }
+
+    #[test]
+    fn test_unterminated_heredoc_is_reported() {
+        let tree = bash("cat <<EOF\nstill typing");
+        assert!(tree.errors()[0].message.contains("Unterminated heredoc"));
+    }
+
+    #[test]
+    fn test_variables() {
+        let tree = bash("echo $HOME ${PATH:-/bin} $1 $? \"$USER\" $");
+        let variables: Vec<_> = tree
+            .root()
+            .tokens()
+            .into_iter()
+            .filter(|t| t.kind == TokenKind::Variable)
+            .map(|t| tree.text(t.span.clone()))
+            .collect();
+        assert_eq!(
+            variables,
+            vec!["$HOME", "${PATH:-/bin}", "$1", "$?", "$USER"]
+        );
+        assert!(tree.commands()[0].args[0].has_expansion);
+    }
+
+    #[test]
+    fn test_assignments_and_keywords() {
+        let tree = bash("if FOO=bar grep -q x f; then echo y; fi");
+        let commands = tree.commands();
+        assert_eq!(commands[0].keywords[0].value, "if");
+        assert_eq!(commands[0].assignments[0].raw, "FOO=bar");
+        assert_eq!(commands[0].name.as_ref().unwrap().value, "grep");
+        assert_eq!(commands[1].keywords[0].value, "then");
+        assert_eq!(commands[1].name.as_ref().unwrap().value, "echo");
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let tree = bash("echo \"unterminated | grep x");
+        assert!(tree.errors()[0]
+            .message
+            .contains("Unterminated double quote"));
+        // The quote swallows the rest of the line
+        assert_eq!(tree.commands().len(), 1);
+
+        let tree = bash("ls | ");
+        assert!(tree.errors()[0]
+            .message
+            .contains("Expected a command after '|'"));
+
+        let tree = bash("echo hi ) ; ls");
+        assert!(tree.errors()[0].message.contains("Unexpected ')'"));
+        assert_eq!(argvs(&tree), vec![vec!["echo", "hi"], vec!["ls"]]);
+
+        let tree = bash("(echo open");
+        assert!(tree.errors()[0].message.contains("Unclosed '('"));
+
+        let tree = bash("arr=(one two");
+        assert!(tree.errors()[0].message.contains("Unclosed '(' array"));
+    }
+
+    #[test]
+    fn test_arrays_and_keyword_subshells() {
+        let inputs = [
+            "arr=()",
+            "arr+=(one \"two three\" $(ls)) ; echo ${arr[@]}",
+            "for d in */; do (cd $d && make); done",
+            "if true; then (echo yes) > out; fi",
+        ];
+        for input in inputs {
+            let tree = bash(input);
+            assert!(!tree.has_errors(), "{:?}: {:?}", input, tree.errors());
+            assert_eq!(tree.to_source(), input);
+        }
+
+        let tree = bash("FOO=(a b) env");
+        assert_eq!(tree.commands()[0].name.as_ref().unwrap().value, "env");
+        // Posix sh has no arrays
+        assert!(parse("arr=(a b)", ShellDialect::Posix).has_errors());
+    }
+
+    #[test]
+    fn test_fish_dialect() {
+        let tree = parse(
+            "set -l branch (git branch --show-current); and echo $branch",
+            ShellDialect::Fish,
+        );
+        assert!(!tree.has_errors(), "{:?}", tree.errors());
+        assert_eq!(
+            argvs(&tree),
+            vec![
+                vec!["set", "-l", "branch", "(git branch --show-current)"],
+                vec!["git", "branch", "--show-current"],
+                vec!["echo", "$branch"],
+            ]
+        );
+        assert_eq!(tree.commands()[2].keywords[0].value, "and");
+    }
+
+    #[test]
+    fn test_bash_extensions() {
+        let tree = bash("grep x <<< \"$text\" |& tee log &> /dev/null");
+        let ops: Vec<_> = tree
+            .commands()
+            .iter()
+            .flat_map(|c| c.redirects.iter().map(|r| r.operator.clone()))
+            .collect();
+        assert_eq!(ops, vec!["<<<", "&>"]);
+        assert_eq!(tree.root().descendants(NodeKind::Pipeline).len(), 1);
+    }
+
+    #[test]
+    fn test_command_and_word_at_cursor() {
+        let source = "cd src && git checkout fea";
+        let tree = bash(source);
+        let command = tree.command_at(source.len()).unwrap();
+        assert_eq!(command.name.unwrap().value, "git");
+        let word = tree.word_at(source.len()).unwrap();
+        assert_eq!(word.value, "fea");
+        assert_eq!(word.span, 23..26);
+        assert!(tree.word_at(9).is_none());
+
+        let tree = bash("git push ");
+        assert_eq!(tree.command_at_cursor(9).unwrap().name.unwrap().value, "git");
+        assert!(bash("git push | ").command_at_cursor(11).is_none());
+    }
+
+    #[test]
+    fn test_highlights() {
+        let source = "FOO=1 git push -f origin \"$b\" > log # c";
+        let tree = bash(source);
+        let kinds: Vec<_> = tree
+            .highlights()
+            .into_iter()
+            .map(|h| (tree.text(h.span).to_string(), h.kind))
+            .collect();
+        assert!(kinds.contains(&("git".to_string(), HighlightKind::Command)));
+        assert!(kinds.contains(&("-f".to_string(), HighlightKind::Flag)));
+        assert!(kinds.contains(&("origin".to_string(), HighlightKind::Argument)));
+        assert!(kinds.contains(&("$b".to_string(), HighlightKind::Variable)));
+        assert!(kinds.contains(&(">".to_string(), HighlightKind::Redirect)));
+        assert!(kinds.contains(&("# c".to_string(), HighlightKind::Comment)));
+        assert!(kinds.contains(&("FOO".to_string(), HighlightKind::Assignment)));
+    }
+
+    #[test]
+    fn test_dialect_from_shell_path() {
+        assert_eq!(
+            ShellDialect::from_shell_path("/usr/bin/zsh"),
+            ShellDialect::Zsh
+        );
+        assert_eq!(ShellDialect::from_shell_path("fish"), ShellDialect::Fish);
+        assert_eq!(
+            ShellDialect::from_shell_path("/bin/sh"),
+            ShellDialect::Posix
+        );
+    }
+}
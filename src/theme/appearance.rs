@@ -0,0 +1,379 @@
+//! OS Appearance - Follow the desktop's light/dark preference
+//!
+//! On Linux the preference is the `color-scheme` key of the
+//! `org.freedesktop.appearance` namespace, read through the XDG desktop
+//! portal's `org.freedesktop.portal.Settings` interface on the session bus.
+//! The portal emits `SettingChanged` whenever the user flips it. Desktops
+//! without the portal fall back to GTK's settings: `GTK_THEME`, then
+//! `gtk-application-prefer-dark-theme` or a dark `gtk-theme-name` in
+//! `settings.ini`, which are polled for changes.
+
+use std::path::Path;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Portal namespace holding the preference
+pub const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+/// Key of the preference within [`APPEARANCE_NAMESPACE`]
+pub const COLOR_SCHEME_KEY: &str = "color-scheme";
+pub const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+pub const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+pub const SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+
+/// How often GTK settings are re-read when there is no portal
+pub const GTK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorScheme {
+    NoPreference,
+    Dark,
+    Light,
+}
+
+impl ColorScheme {
+    /// The portal's encoding: 0 no preference, 1 dark, 2 light. Unknown
+    /// values are treated as no preference, as the spec asks.
+    pub fn from_portal(value: u32) -> Self {
+        match value {
+            1 => ColorScheme::Dark,
+            2 => ColorScheme::Light,
+            _ => ColorScheme::NoPreference,
+        }
+    }
+
+    /// Whether to use the dark theme. Without a preference the terminal
+    /// keeps its default, dark.
+    pub fn prefers_dark(self) -> bool {
+        self != ColorScheme::Light
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum AppearanceError {
+    #[error("D-Bus error: {0}")]
+    Bus(String),
+    #[error("unexpected color-scheme value: {0}")]
+    InvalidValue(String),
+}
+
+/// The scheme named by a `GTK_THEME` value such as `Adwaita:dark`
+pub fn gtk_theme_env_scheme(value: &str) -> ColorScheme {
+    if is_dark_theme_name(value) {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    }
+}
+
+/// The scheme set in a GTK `settings.ini`, or `None` if it sets neither
+/// the dark preference nor a theme
+pub fn parse_gtk_settings(ini: &str) -> Option<ColorScheme> {
+    let mut in_settings = false;
+    let mut prefer_dark = None;
+    let mut theme_name = None;
+    for line in ini.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_settings = line == "[Settings]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_settings) else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim() {
+            "gtk-application-prefer-dark-theme" => prefer_dark = Some(matches!(value, "1" | "true")),
+            "gtk-theme-name" => theme_name = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    match (prefer_dark, theme_name) {
+        (Some(true), _) => Some(ColorScheme::Dark),
+        (_, Some(name)) => Some(gtk_theme_env_scheme(&name)),
+        (Some(false), None) => Some(ColorScheme::Light),
+        (None, None) => None,
+    }
+}
+
+fn is_dark_theme_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with("-dark") || name.ends_with(":dark") || name.ends_with("_dark") || name.contains("-dark-")
+}
+
+/// The GTK preference: `GTK_THEME`, then GTK 4 and GTK 3 `settings.ini`
+/// under `config_dir`
+pub fn gtk_color_scheme(config_dir: &Path) -> Option<ColorScheme> {
+    if let Ok(theme) = std::env::var("GTK_THEME") {
+        if !theme.is_empty() {
+            return Some(gtk_theme_env_scheme(&theme));
+        }
+    }
+    ["gtk-4.0", "gtk-3.0"].iter().find_map(|version| {
+        let ini = std::fs::read_to_string(config_dir.join(version).join("settings.ini")).ok()?;
+        parse_gtk_settings(&ini)
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub use portal::{subscription, PortalSettings};
+
+/// Following the OS is only supported on Linux
+#[cfg(not(target_os = "linux"))]
+pub fn subscription() -> iced::Subscription<ColorScheme> {
+    iced::Subscription::none()
+}
+
+#[cfg(target_os = "linux")]
+mod portal {
+    use super::*;
+    use iced::futures::channel::mpsc;
+    use iced::futures::stream::BoxStream;
+    use iced::futures::{SinkExt, StreamExt};
+    use tracing::{debug, info};
+    use zbus::zvariant::{OwnedValue, Value};
+    use zbus::{Connection, MatchRule, MessageStream};
+
+    impl From<zbus::Error> for AppearanceError {
+        fn from(error: zbus::Error) -> Self {
+            AppearanceError::Bus(error.to_string())
+        }
+    }
+
+    fn scheme_from_value(value: &Value<'_>) -> Result<ColorScheme, AppearanceError> {
+        match value {
+            Value::U32(value) => Ok(ColorScheme::from_portal(*value)),
+            // `Read` wraps the value in a second variant
+            Value::Value(inner) => scheme_from_value(inner),
+            other => Err(AppearanceError::InvalidValue(format!("{:?}", other))),
+        }
+    }
+
+    /// The portal's Settings interface on a D-Bus connection
+    pub struct PortalSettings {
+        connection: Connection,
+    }
+
+    impl PortalSettings {
+        pub fn new(connection: Connection) -> Self {
+            Self { connection }
+        }
+
+        /// Connect to the portal on the session bus
+        pub async fn session() -> Result<Self, AppearanceError> {
+            Ok(Self::new(Connection::session().await?))
+        }
+
+        /// The current preference. Portals older than version 2 lack
+        /// `ReadOne`; `Read` is used for them.
+        pub async fn color_scheme(&self) -> Result<ColorScheme, AppearanceError> {
+            let reply = match self.call("ReadOne").await {
+                Ok(reply) => reply,
+                Err(zbus::Error::MethodError(name, ..)) if name.as_str() == "org.freedesktop.DBus.Error.UnknownMethod" => {
+                    self.call("Read").await?
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let value: OwnedValue = reply.body().deserialize()?;
+            scheme_from_value(&value)
+        }
+
+        async fn call(&self, method: &str) -> zbus::Result<zbus::Message> {
+            self.connection
+                .call_method(
+                    Some(PORTAL_DESTINATION),
+                    PORTAL_PATH,
+                    Some(SETTINGS_INTERFACE),
+                    method,
+                    &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY),
+                )
+                .await
+        }
+
+        /// Each new preference as the portal announces it
+        pub async fn changes(&self) -> Result<BoxStream<'static, ColorScheme>, AppearanceError> {
+            let rule = MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface(SETTINGS_INTERFACE)?
+                .member("SettingChanged")?
+                .path(PORTAL_PATH)?
+                .build();
+            let stream = MessageStream::for_match_rule(rule, &self.connection, None).await?;
+            Ok(stream
+                .filter_map(|message| async move {
+                    let message = message.ok()?;
+                    let (namespace, key, value): (String, String, OwnedValue) = message.body().deserialize().ok()?;
+                    if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+                        return None;
+                    }
+                    scheme_from_value(&value).ok()
+                })
+                .boxed())
+        }
+    }
+
+    /// The OS preference, reported at start and on every change
+    pub fn subscription() -> iced::Subscription<ColorScheme> {
+        iced::subscription::channel(std::any::TypeId::of::<PortalSettings>(), 4, |mut output| async move {
+            let mut last = None;
+            if let Err(e) = watch_portal(&mut output, &mut last).await {
+                info!("XDG desktop portal unavailable ({}), using GTK settings", e);
+            }
+            loop {
+                if let Some(scheme) = dirs::config_dir().and_then(|dir| gtk_color_scheme(&dir)) {
+                    report(&mut output, &mut last, scheme).await;
+                }
+                tokio::time::sleep(GTK_POLL_INTERVAL).await;
+            }
+        })
+    }
+
+    async fn watch_portal(
+        output: &mut mpsc::Sender<ColorScheme>,
+        last: &mut Option<ColorScheme>,
+    ) -> Result<(), AppearanceError> {
+        let portal = PortalSettings::session().await?;
+        // Subscribe first so a change between the read and the
+        // subscription is not lost
+        let mut changes = portal.changes().await?;
+        report(output, last, portal.color_scheme().await?).await;
+        while let Some(scheme) = changes.next().await {
+            report(output, last, scheme).await;
+        }
+        Err(AppearanceError::Bus("portal signal stream closed".to_string()))
+    }
+
+    async fn report(output: &mut mpsc::Sender<ColorScheme>, last: &mut Option<ColorScheme>, scheme: ColorScheme) {
+        if *last != Some(scheme) {
+            debug!("OS color scheme is {:?}", scheme);
+            *last = Some(scheme);
+            let _ = output.send(scheme).await;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::os::unix::net::UnixStream;
+        use zbus::{connection, fdo, Guid};
+
+        /// Stand-in for the portal's Settings interface
+        struct StubSettings {
+            scheme: u32,
+            /// Whether `ReadOne` exists (portal version 2 and later)
+            read_one: bool,
+        }
+
+        #[zbus::interface(name = "org.freedesktop.portal.Settings")]
+        impl StubSettings {
+            fn read_one(&self, namespace: &str, key: &str) -> fdo::Result<OwnedValue> {
+                if !self.read_one {
+                    return Err(fdo::Error::UnknownMethod("ReadOne".to_string()));
+                }
+                self.lookup(namespace, key).map(OwnedValue::from)
+            }
+
+            fn read(&self, namespace: &str, key: &str) -> fdo::Result<OwnedValue> {
+                let value = Value::new(Value::U32(self.lookup(namespace, key)?));
+                OwnedValue::try_from(value).map_err(|e| fdo::Error::Failed(e.to_string()))
+            }
+        }
+
+        impl StubSettings {
+            fn lookup(&self, namespace: &str, key: &str) -> fdo::Result<u32> {
+                if namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY {
+                    Ok(self.scheme)
+                } else {
+                    Err(fdo::Error::Failed(format!("no setting {}.{}", namespace, key)))
+                }
+            }
+        }
+
+        /// A portal connection to a stub served over a socket pair
+        async fn stub_portal(settings: StubSettings) -> (Connection, PortalSettings) {
+            let (server_socket, client_socket) = UnixStream::pair().unwrap();
+            let server = connection::Builder::unix_stream(server_socket)
+                .server(Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(PORTAL_PATH, settings)
+                .unwrap()
+                .build();
+            let client = connection::Builder::unix_stream(client_socket).p2p().build();
+            let (server, client) = iced::futures::try_join!(server, client).unwrap();
+            (server, PortalSettings::new(client))
+        }
+
+        #[test]
+        fn test_reads_and_follows_the_portal() {
+            zbus::block_on(async {
+                let (server, portal) = stub_portal(StubSettings {
+                    scheme: 2,
+                    read_one: true,
+                })
+                .await;
+                assert_eq!(portal.color_scheme().await, Ok(ColorScheme::Light));
+
+                let mut changes = portal.changes().await.unwrap();
+                let emit = |namespace: &'static str, key: &'static str, value: u32| {
+                    let server = server.clone();
+                    async move {
+                        server
+                            .emit_signal(
+                                None::<()>,
+                                PORTAL_PATH,
+                                SETTINGS_INTERFACE,
+                                "SettingChanged",
+                                &(namespace, key, Value::U32(value)),
+                            )
+                            .await
+                            .unwrap()
+                    }
+                };
+                emit("org.gnome.desktop.interface", "text-scaling", 7).await;
+                emit(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY, 1).await;
+                assert_eq!(changes.next().await, Some(ColorScheme::Dark));
+                emit(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY, 0).await;
+                assert_eq!(changes.next().await, Some(ColorScheme::NoPreference));
+            });
+        }
+
+        #[test]
+        fn test_falls_back_to_read_on_old_portals() {
+            zbus::block_on(async {
+                let (_server, portal) = stub_portal(StubSettings {
+                    scheme: 1,
+                    read_one: false,
+                })
+                .await;
+                assert_eq!(portal.color_scheme().await, Ok(ColorScheme::Dark));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_scheme_values() {
+        assert_eq!(ColorScheme::from_portal(1), ColorScheme::Dark);
+        assert_eq!(ColorScheme::from_portal(2), ColorScheme::Light);
+        assert_eq!(ColorScheme::from_portal(7), ColorScheme::NoPreference);
+        assert!(ColorScheme::NoPreference.prefers_dark());
+        assert!(!ColorScheme::Light.prefers_dark());
+    }
+
+    #[test]
+    fn test_gtk_settings() {
+        let ini = "[Settings]\ngtk-theme-name=Adwaita\ngtk-application-prefer-dark-theme=1\n";
+        assert_eq!(parse_gtk_settings(ini), Some(ColorScheme::Dark));
+        let ini = "[Settings]\ngtk-theme-name = \"Arc-Dark\"\n";
+        assert_eq!(parse_gtk_settings(ini), Some(ColorScheme::Dark));
+        let ini = "[Settings]\ngtk-theme-name=Adwaita\ngtk-application-prefer-dark-theme=false\n";
+        assert_eq!(parse_gtk_settings(ini), Some(ColorScheme::Light));
+        assert_eq!(parse_gtk_settings("[Other]\ngtk-theme-name=Arc-Dark\n"), None);
+        assert_eq!(gtk_theme_env_scheme("Adwaita:dark"), ColorScheme::Dark);
+        assert_eq!(gtk_theme_env_scheme("Yaru"), ColorScheme::Light);
+    }
+}
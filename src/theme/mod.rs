@@ -1 +1,2 @@
+pub mod appearance;
 pub mod iced_integration;
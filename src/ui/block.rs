@@ -1,26 +1,28 @@
 use crate::Block;
 use crate::Message;
+use crate::model::theme::AppTheme;
+use crate::ui::code::view_code;
 use crate::ui::icons;
+use crate::ui::terminal_output::view_output;
 use iced::widget::{button, column, container, row, text, Space};
 use iced::{Alignment, Element, Length, Font, Color};
 use iced::theme;
 
-pub fn view_block(block: &Block, font: Font, size: u16) -> Element<Message> {
+pub fn view_block<'a>(block: &'a Block, app_theme: &AppTheme, font: Font, size: u16) -> Element<'a, Message> {
     let block_content = match &block.content {
-        crate::model::block::BlockContent::Command { input, output } => block.command_block_view(input, output),
+        crate::model::block::BlockContent::Command { input, output } => block.command_block_view(input, output, app_theme),
         crate::model::block::BlockContent::Background { output, process_info, is_active, pid } => {
             let mut col = column![
                 text(format!("Background Process: {}", process_info.as_deref().unwrap_or("N/A"))).font(font).size(size - 2),
                 text(format!("PID: {}", pid.map_or("N/A".to_string(), |p| p.to_string()))).font(font).size(size - 2),
                 text(format!("Active: {}", is_active)).font(font).size(size - 2),
-                text(output).font(font).size(size - 2),
             ].spacing(4);
             if !output.is_empty() {
-                col = col.push(text(output).font(font).size(size - 2));
+                col = col.push(view_output(output, app_theme, font, size - 2));
             }
             col.into()
         },
-        crate::model::block::BlockContent::Markdown(content) => text(content).into(),
+        crate::model::block::BlockContent::Markdown(content) => crate::ui::markdown::view_markdown(content, font, size),
         crate::model::block::BlockContent::FilePreview(path) => text(format!("File: {}", path.display())).into(),
         crate::model::block::BlockContent::Error(message) => text(format!("Error: {}", message)).into(),
         crate::model::block::BlockContent::Info(message) => text(format!("Info: {}", message)).into(),
@@ -28,7 +30,7 @@ pub fn view_block(block: &Block, font: Font, size: u16) -> Element<Message> {
             column![
                 text("Interactive Command:").font(font).size(size - 4),
                 text(input).font(font).size(size - 2),
-                text(output).font(font).size(size - 2),
+                view_output(output, app_theme, font, size - 2),
                 text(format!("Streaming: {}, RealTime Updates: {}", streaming, real_time_updates)).font(font).size(size - 2)
             ].spacing(4).into()
         },
@@ -42,10 +44,8 @@ pub fn view_block(block: &Block, font: Font, size: u16) -> Element<Message> {
         },
         crate::model::block::BlockContent::CodeSnippet { language, code, highlighted } => {
             column![
-                text("Code Snippet:").font(font).size(size - 4),
-                text(format!("Language: {}", language)).font(font).size(size - 2),
-                text(code).font(font).size(size - 2),
-                text(format!("Highlighted: {}", highlighted)).font(font).size(size - 2)
+                text(format!("Code Snippet: {}", language)).font(font).size(size - 4),
+                view_code(code, highlighted.then_some(language.as_str()), size - 2),
             ].spacing(4).into()
         },
         crate::model::block::BlockContent::ImagePreview { path, thumbnail: _ } => {
@@ -107,6 +107,15 @@ pub fn view_block(block: &Block, font: Font, size: u16) -> Element<Message> {
     .on_press(Message::BookmarkBlock(block.id))
     .style(if block.bookmarked { theme::Button::Primary } else { theme::Button::Secondary });
 
+    let workflow_btn = button(
+        row![
+            text(icons::WORKFLOW).size(size - 2),
+            text(" Workflow").font(font).size(size - 2)
+        ].spacing(4)
+    )
+    .on_press(Message::SaveBlockAsWorkflow(block.id))
+    .style(theme::Button::Secondary);
+
     let controls = column![
         status_icon,
         row![
@@ -117,11 +126,56 @@ pub fn view_block(block: &Block, font: Font, size: u16) -> Element<Message> {
             share_btn,
             bookmark_btn,
         ].spacing(4),
+        workflow_btn,
     ]
     .spacing(6)
     .align_items(Alignment::Start)
     .width(Length::Fixed(140.0));
     
+    // Run button for a notebook command cell
+    let block_content: Element<Message> = match &block.notebook_command {
+        Some(_) => column![
+            block_content,
+            button(
+                row![
+                    text(icons::RUN).size(size - 2),
+                    text(" Run").font(font).size(size - 2)
+                ].spacing(4)
+            )
+            .on_press(Message::RunNotebookCell(block.id))
+            .style(theme::Button::Primary),
+        ]
+        .spacing(6)
+        .into(),
+        None => block_content,
+    };
+
+    // Suggested fix for a failed command
+    let block_content: Element<Message> = match &block.correction {
+        Some(correction) => column![
+            block_content,
+            row![
+                text("Did you mean").font(font).size(size - 2).style(Color::from_rgb(0.7, 0.7, 0.8)),
+                button(
+                    row![
+                        text(icons::RUN).size(size - 2),
+                        text(&correction.corrected_command).font(font).size(size - 2)
+                    ].spacing(4)
+                )
+                .on_press(Message::RunCorrection(block.id))
+                .style(theme::Button::Primary),
+                button(text("✕").size(size - 2))
+                    .on_press(Message::DismissCorrection(block.id))
+                    .style(theme::Button::Text),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        ]
+        .spacing(6)
+        .into(),
+        None => block_content,
+    };
+
     let content_with_controls = row![
         block_content,
         Space::with_width(Length::Fill),
@@ -0,0 +1,90 @@
+//! Renders source code highlighted by its language, for code snippet
+//! blocks and fenced code in Markdown.
+
+use std::sync::OnceLock;
+
+use iced::widget::{container, text, Column, Row};
+use iced::{font, theme, Background, Border, Color, Element, Font, Length};
+
+use crate::editor::syntax_highlighter::{SyntaxHighlighter, SyntaxTheme, TokenType};
+use crate::languages::LanguageRegistry;
+use crate::syntax_tree::highlight::HighlightMap;
+use crate::Message;
+
+/// The dark syntax theme, which suits the code background
+fn syntax_theme() -> &'static SyntaxTheme {
+    static THEME: OnceLock<SyntaxTheme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        SyntaxHighlighter::new()
+            .theme("dark")
+            .cloned()
+            .expect("the dark syntax theme is built in")
+    })
+}
+
+/// `code` in a code box, highlighted as the registry language called
+/// `language`; as plain text with `None` or a language the registry does
+/// not know
+pub fn view_code<'a>(code: &str, language: Option<&str>, size: u16) -> Element<'a, Message> {
+    let code = code.trim_end_matches('\n');
+    let highlights = language
+        .and_then(|name| LanguageRegistry::global().get(name))
+        .map(|language| HighlightMap::new(language, code));
+    let theme = syntax_theme();
+
+    let lines = code.split('\n').enumerate().map(|(row, line)| {
+        let tokens = highlights.as_ref().map_or(&[][..], |highlights| highlights.line_tokens(row));
+        let mut pieces = Row::new();
+        let mut pos = 0;
+        for token in tokens {
+            let start = token.range.start.max(pos);
+            if start >= token.range.end {
+                continue;
+            }
+            if start > pos {
+                pieces = pieces.push(piece(&line[pos..start], theme, TokenType::Default, size));
+            }
+            pieces = pieces.push(piece(&line[start..token.range.end], theme, token.scope.token_type(), size));
+            pos = token.range.end;
+        }
+        if pos < line.len() || line.is_empty() {
+            pieces = pieces.push(piece(&line[pos..], theme, TokenType::Default, size));
+        }
+        pieces.into()
+    });
+
+    container(Column::with_children(lines))
+        .padding(8)
+        .width(Length::Fill)
+        .style(theme::Container::Custom(Box::new(CodeBlockStyle)))
+        .into()
+}
+
+fn piece<'a>(content: &str, theme: &SyntaxTheme, token_type: TokenType, size: u16) -> Element<'a, Message> {
+    let style = theme.style(&token_type);
+    let piece_font = Font {
+        weight: if style.bold { font::Weight::Bold } else { font::Weight::Normal },
+        style: if style.italic { font::Style::Italic } else { font::Style::Normal },
+        ..Font::MONOSPACE
+    };
+    text(content.to_string()).font(piece_font).size(size).style(style.color).into()
+}
+
+struct CodeBlockStyle;
+
+impl container::StyleSheet for CodeBlockStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(Background::Color(Color::from_rgb(0.1, 0.1, 0.12))),
+            border: Border {
+                color: Color::from_rgb(0.22, 0.22, 0.26),
+                width: 1.0,
+                radius: 4.0.into(),
+            },
+            text_color: Some(Color::from_rgb(0.85, 0.88, 0.85)),
+            ..Default::default()
+        }
+    }
+}
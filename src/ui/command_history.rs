@@ -905,8 +905,12 @@ impl CommandHistoryUI {
                 text("⭐").size(font_size - 4).style(Color::from_rgb(1.0, 0.8, 0.0))
             } else {
                 text("").size(font_size - 4)
-            }
-        ];
+            },
+            button(text("🧩 Save as workflow").size(font_size - 4))
+                .on_press(Message::SaveHistoryAsWorkflow(entry.id))
+                .style(theme::Button::Text),
+        ]
+        .align_items(Alignment::Center);
 
         let content = column![header_row, directory_row]
             .spacing(4)
@@ -3,6 +3,7 @@
 //! This module provides a comprehensive command palette with fuzzy search, categories,
 //! keyboard shortcuts, and rich command descriptions.
 
+use crate::input::keymap::Keymap;
 use crate::model::command_registry::{Command, CommandCategory, CommandRegistry};
 use crate::model::workflow_loader::WorkflowLoader;
 use crate::ui::quick_actions::{QuickActionsEngine, QuickAction};
@@ -135,7 +136,10 @@ impl CommandPalette {
             show_favorites_only: false,
             favorites: Vec::new(),
             search_config: SearchConfig::default(),
-            workflow_loader: WorkflowLoader::default(),
+            workflow_loader: match std::env::current_dir() {
+                Ok(cwd) => WorkflowLoader::with_default_sources("workflows/specs", &cwd),
+                Err(_) => WorkflowLoader::default(),
+            },
             quick_actions_engine: QuickActionsEngine::new(),
             cached_quick_actions: Vec::new(),
         };
@@ -434,6 +438,7 @@ impl CommandPalette {
             keywords: Vec::new(), // QuickAction doesn't have keywords field
             enabled: true,
             priority: 0, // QuickAction doesn't have priority field, using confidence instead
+            action: None,
         }
     }
 
@@ -576,6 +581,21 @@ impl CommandPalette {
         }
     }
 
+    /// Show each bindable command's current key bindings as its shortcut
+    pub fn sync_shortcuts(&mut self, keymap: &Keymap) {
+        for command in self.command_registry.get_bindable_commands() {
+            let Some(action) = &command.action else { continue };
+            let shortcut = keymap
+                .bindings_for_action(action)
+                .iter()
+                .map(|binding| binding.sequence.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = self.command_registry.set_shortcut(&command.id, &shortcut);
+        }
+        self.update_results();
+    }
+
     /// Refresh cached quick actions (to be called from async context)
     pub async fn refresh_quick_actions(&mut self) {
         match self.quick_actions_engine.get_quick_actions(10).await {
@@ -1005,6 +1025,7 @@ impl CommandPalette {
             .category(CommandCategory::Pane)
             .shortcut("Ctrl+Shift+D")
             .keywords(vec!["split".to_string(), "horizontal".to_string(), "pane".to_string(), "divide".to_string()])
+            .action("pane_group:add_right")
             .build().unwrap()).unwrap();
 
         self.command_registry.register(Command::builder("pane.split.vertical", "Split Pane Vertically")
@@ -1012,6 +1033,7 @@ impl CommandPalette {
             .category(CommandCategory::Pane)
             .shortcut("Ctrl+Shift+Shift+D")
             .keywords(vec!["split".to_string(), "vertical".to_string(), "pane".to_string(), "divide".to_string()])
+            .action("pane_group:add_down")
             .build().unwrap()).unwrap();
 
         self.command_registry.register(Command::builder("pane.close", "Close Pane")
@@ -1019,6 +1041,7 @@ impl CommandPalette {
             .category(CommandCategory::Pane)
             .shortcut("Ctrl+W")
             .keywords(vec!["close".to_string(), "pane".to_string(), "exit".to_string(), "quit".to_string()])
+            .action("pane_group:close")
             .build().unwrap()).unwrap();
 
         self.command_registry.register(Command::builder("pane.focus.next", "Focus Next Pane")
@@ -1026,8 +1049,53 @@ impl CommandPalette {
             .category(CommandCategory::Pane)
             .shortcut("Ctrl+Tab")
             .keywords(vec!["focus".to_string(), "next".to_string(), "pane".to_string(), "switch".to_string()])
+            .action("pane_group:navigate_next")
+            .build().unwrap()).unwrap();
+
+        self.command_registry.register(Command::builder("pane.focus.previous", "Focus Previous Pane")
+            .description("Move focus to the previous pane")
+            .category(CommandCategory::Pane)
+            .keywords(vec!["focus".to_string(), "previous".to_string(), "pane".to_string(), "switch".to_string()])
+            .action("pane_group:navigate_prev")
             .build().unwrap()).unwrap();
 
+        for (direction, title, position) in [
+            ("up", "Up", "above"),
+            ("down", "Down", "below"),
+            ("left", "Left", "to the left"),
+            ("right", "Right", "to the right"),
+        ] {
+            self.command_registry.register(Command::builder(&format!("pane.focus.{}", direction), &format!("Focus Pane {}", title))
+                .description(&format!("Move focus to the pane {}", position))
+                .category(CommandCategory::Pane)
+                .keywords(vec!["focus".to_string(), direction.to_string(), "pane".to_string(), "navigate".to_string()])
+                .action(&format!("pane_group:navigate_{}", direction))
+                .build().unwrap()).unwrap();
+
+            self.command_registry.register(Command::builder(&format!("pane.resize.{}", direction), &format!("Move Pane Divider {}", title))
+                .description(&format!("Move the divider of the current pane {}", direction))
+                .category(CommandCategory::Pane)
+                .keywords(vec!["resize".to_string(), direction.to_string(), "pane".to_string(), "divider".to_string()])
+                .action(&format!("pane_group:resize_{}", direction))
+                .build().unwrap()).unwrap();
+        }
+
+        // Synchronization Commands
+        for (id, title, description, action) in [
+            ("sync.toggle", "Toggle Input Sync", "Turn synchronized input across panes on or off", "workspace:toggle_sync_mode"),
+            ("sync.current_tab", "Sync Panes in Current Tab", "Send input to every pane in the current tab", "workspace:sync_current_tab"),
+            ("sync.all_tabs", "Sync Panes in All Tabs", "Send input to every pane in every tab", "workspace:sync_all_tabs"),
+            ("sync.stop", "Stop Input Sync", "Stop sending input to several panes", "workspace:stop_sync"),
+            ("sync.pause", "Pause or Resume Input Sync", "Pause synchronized input without leaving sync mode", "workspace:toggle_sync_pause"),
+        ] {
+            self.command_registry.register(Command::builder(id, title)
+                .description(description)
+                .category(CommandCategory::Pane)
+                .keywords(vec!["sync".to_string(), "synchronize".to_string(), "broadcast".to_string(), "input".to_string()])
+                .action(action)
+                .build().unwrap()).unwrap();
+        }
+
         // Settings Commands
         self.command_registry.register(Command {
             id: "settings.open".to_string(),
@@ -1041,6 +1109,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: Some("workspace:show_settings_modal".to_string()),
         }).unwrap();
 
         self.command_registry.register(Command {
@@ -1055,6 +1124,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
         self.command_registry.register(Command {
@@ -1069,8 +1139,16 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
+        self.command_registry.register(Command::builder("keybindings.open", "Open Keybindings Editor")
+            .description("View and change key bindings")
+            .category(CommandCategory::Settings)
+            .keywords(vec!["keybindings".to_string(), "shortcuts".to_string(), "keys".to_string(), "rebind".to_string()])
+            .action("workspace:show_keybinding_settings")
+            .build().unwrap()).unwrap();
+
         // Theme Commands
         self.command_registry.register(Command {
             id: "theme.toggle".to_string(),
@@ -1084,6 +1162,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
         self.command_registry.register(Command {
@@ -1098,6 +1177,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
         // History Commands
@@ -1113,6 +1193,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: Some("workspace:show_command_search".to_string()),
         }).unwrap();
 
         self.command_registry.register(Command {
@@ -1127,6 +1208,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
         // Search Commands
@@ -1142,6 +1224,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: None,
         }).unwrap();
 
         // Custom Commands
@@ -1157,6 +1240,7 @@ impl CommandPalette {
                 .collect(),
             enabled: true,
             priority: 0,
+            action: Some("workspace:toggle_command_palette".to_string()),
         }).unwrap();
     }
 }
@@ -3,6 +3,8 @@ use iced::widget::{column, row, text, text_input, button, container, scrollable,
 use crate::Message;
 use crate::utils::fuzzy_matcher::FuzzyMatcher;
 use crate::model::history::HistoryManager;
+use crate::model::notebook::NotebookStore;
+use std::path::PathBuf;
 use warp_workflows_types::Workflow;
 
 #[derive(Debug, Clone)]
@@ -41,7 +43,8 @@ pub struct SearchResult {
 pub enum SearchResultType {
     CommandHistory,
     Workflow,
-    Notebook,
+    /// Opens the notebook at this path
+    Notebook(PathBuf),
     AiGenerate,
 }
 
@@ -58,6 +61,7 @@ pub struct NotebookItem {
     pub name: String,
     pub commands: Vec<String>,
     pub description: String,
+    pub path: PathBuf,
 }
 
 impl Default for CommandSearchPanel {
@@ -77,7 +81,7 @@ impl CommandSearchPanel {
             fuzzy_matcher: FuzzyMatcher::new(),
             command_history: Vec::new(),
             workflows: Self::load_workflows_from_directories(),
-            notebooks: Self::load_default_notebooks(),
+            notebooks: Self::load_notebooks(),
             show_landing_page: true,
         }
     }
@@ -85,6 +89,7 @@ impl CommandSearchPanel {
     pub fn toggle_visibility(&mut self) {
         self.is_visible = !self.is_visible;
         if self.is_visible {
+            self.notebooks = Self::load_notebooks();
             self.show_landing_page = self.query.is_empty();
             if !self.query.is_empty() {
                 self.update_search_results();
@@ -364,7 +369,7 @@ impl CommandSearchPanel {
                 results.push(SearchResult {
                     text: notebook.commands.join(" && "),
                     description: format!("{} - {}", notebook.name, notebook.description),
-                    result_type: SearchResultType::Notebook,
+                    result_type: SearchResultType::Notebook(notebook.path.clone()),
                     score,
                     icon: "📄".to_string(),
                 });
@@ -467,29 +472,22 @@ impl CommandSearchPanel {
         ]
     }
 
-    fn load_default_notebooks() -> Vec<NotebookItem> {
-        vec![
-            NotebookItem {
-                name: "Git Workflow".to_string(),
-                commands: vec![
-                    "git status".to_string(),
-                    "git add .".to_string(),
-                    "git commit -m \"Update\"".to_string(),
-                    "git push origin main".to_string(),
-                ],
-                description: "Standard git workflow".to_string(),
-            },
-            NotebookItem {
-                name: "System Info".to_string(),
-                commands: vec![
-                    "uname -a".to_string(),
-                    "lscpu".to_string(),
-                    "free -h".to_string(),
-                    "df -h".to_string(),
-                ],
-                description: "Get system information".to_string(),
-            },
-        ]
+    /// Notebooks from the user's and the current repository's notebook
+    /// directories
+    fn load_notebooks() -> Vec<NotebookItem> {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        NotebookStore::with_default_dirs(&cwd)
+            .list()
+            .into_iter()
+            .filter_map(|notebook| {
+                Some(NotebookItem {
+                    name: notebook.title(),
+                    commands: notebook.commands().into_iter().map(|(_, command)| command).collect(),
+                    description: notebook.meta.description.clone().unwrap_or_default(),
+                    path: notebook.path?,
+                })
+            })
+            .collect()
     }
 
     pub fn view(&self, font: Font, font_size: u16) -> Element<Message> {
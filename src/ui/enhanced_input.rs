@@ -6,11 +6,14 @@
 //! - Multi-line input with proper indentation
 //! - Command history navigation
 //! - AI-powered suggestions and corrections
+//! - Fish-style inline autosuggestions (ghost text)
 //! - GPU-accelerated rendering for smooth performance
 
+use crate::editor::autosuggestion::Autosuggestion;
 use crate::executor::shell_integration::{SyntaxTree, SyntaxTokenType};
+use crate::ui::ghost_text::GhostText;
 use crate::Message;
-use iced::widget::{button, column, container, row, text, text_input, Space};
+use iced::widget::{button, column, container, row, text, text_input, Space, TextInput};
 use iced::{theme, Alignment, Background, Color, Element, Font, Length, border, Padding};
 use std::time::Instant;
 
@@ -27,6 +30,7 @@ pub struct EnhancedInputState {
     pub last_update: Instant,
     pub is_multiline: bool,
     pub ai_suggestion: Option<String>,
+    pub autosuggestion: Option<Autosuggestion>,
 }
 
 impl Default for EnhancedInputState {
@@ -42,6 +46,7 @@ impl Default for EnhancedInputState {
             last_update: Instant::now(),
             is_multiline: false,
             ai_suggestion: None,
+            autosuggestion: None,
         }
     }
 }
@@ -71,10 +76,46 @@ impl EnhancedInputState {
     pub fn set_content(&mut self, content: String) {
         self.content = content;
         self.cursor_position = self.cursor_position.min(self.content.len());
+        // Keep the ghost text while the user types along with it
+        self.autosuggestion = self
+            .autosuggestion
+            .as_ref()
+            .and_then(|suggestion| suggestion.rebase(&self.content));
         self.update_syntax_tree();
         self.last_update = Instant::now();
     }
 
+    /// Install an autosuggestion computed in the background. Results for an
+    /// input that has since changed are discarded.
+    pub fn set_autosuggestion(&mut self, suggestion: Option<Autosuggestion>) {
+        self.autosuggestion = suggestion.filter(|s| s.input == self.content);
+    }
+
+    /// The ghost text to render after the cursor, if any.
+    pub fn autosuggestion_suffix(&self) -> Option<&str> {
+        self.autosuggestion.as_ref().map(|s| s.suffix.as_str())
+    }
+
+    /// Accept the whole autosuggestion (Right arrow).
+    pub fn accept_autosuggestion(&mut self) -> Option<String> {
+        let suggestion = self.autosuggestion.take()?;
+        self.content = suggestion.full_text();
+        self.cursor_position = self.content.len();
+        self.update_syntax_tree();
+        self.last_update = Instant::now();
+        Some(self.content.clone())
+    }
+
+    /// Accept the next word of the autosuggestion (Alt-Right). The rest of
+    /// the suggestion stays visible.
+    pub fn accept_autosuggestion_word(&mut self) -> Option<String> {
+        let word = self.autosuggestion.as_ref()?.next_word().to_string();
+        let content = format!("{}{}", self.content, word);
+        self.set_content(content);
+        self.cursor_position = self.content.len();
+        Some(self.content.clone())
+    }
+
     pub fn update_suggestions(&mut self, suggestions: Vec<Suggestion>) {
         self.suggestions = suggestions;
         self.selected_suggestion = 0;
@@ -147,6 +188,10 @@ pub fn enhanced_input_widget<'a>(
 
     // Main input field with syntax highlighting
     let input_field = create_syntax_highlighted_input(&state.content, placeholder, font, size);
+    let input_field: Element<'a, Message> = match state.autosuggestion_suffix() {
+        Some(suffix) => GhostText::new(input_field, &state.content, suffix, font, size, [12, 16]).into(),
+        None => input_field.into(),
+    };
     
     // Input container with enhanced styling
     let input_container = container(input_field)
@@ -202,7 +247,7 @@ fn create_syntax_highlighted_input<'a>(
     placeholder: &'a str,
     font: Font,
     size: u16,
-) -> Element<'a, Message> {
+) -> TextInput<'a, Message> {
     // For now, we'll use a regular text input
     // In a full implementation, this would be a custom widget with syntax highlighting
     text_input(placeholder, content)
@@ -211,7 +256,6 @@ fn create_syntax_highlighted_input<'a>(
         .size(size)
         .font(font)
         .width(Length::Fill)
-        .into()
 }
 
 /// Create AI suggestion overlay
@@ -539,3 +583,58 @@ impl iced::widget::container::StyleSheet for SelectedSuggestionStyle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::autosuggestion::AutosuggestionSource;
+
+    fn state_with_suggestion(input: &str, suffix: &str) -> EnhancedInputState {
+        let mut state = EnhancedInputState::new();
+        state.set_content(input.to_string());
+        state.set_autosuggestion(Some(Autosuggestion {
+            input: input.to_string(),
+            suffix: suffix.to_string(),
+            source: AutosuggestionSource::History,
+        }));
+        state
+    }
+
+    #[test]
+    fn test_stale_autosuggestion_is_discarded() {
+        let mut state = EnhancedInputState::new();
+        state.set_content("git s".to_string());
+        state.set_autosuggestion(Some(Autosuggestion {
+            input: "git".to_string(),
+            suffix: " status".to_string(),
+            source: AutosuggestionSource::History,
+        }));
+        assert!(state.autosuggestion.is_none());
+    }
+
+    #[test]
+    fn test_accept_autosuggestion() {
+        let mut state = state_with_suggestion("git c", "heckout main");
+        assert_eq!(state.accept_autosuggestion().as_deref(), Some("git checkout main"));
+        assert_eq!(state.cursor_position, state.content.len());
+        assert!(state.autosuggestion.is_none());
+    }
+
+    #[test]
+    fn test_accept_autosuggestion_word_by_word() {
+        let mut state = state_with_suggestion("git", " checkout main");
+        assert_eq!(state.accept_autosuggestion_word().as_deref(), Some("git checkout"));
+        assert_eq!(state.autosuggestion_suffix(), Some(" main"));
+        assert_eq!(state.accept_autosuggestion_word().as_deref(), Some("git checkout main"));
+        assert!(state.autosuggestion.is_none());
+    }
+
+    #[test]
+    fn test_typing_along_keeps_suggestion() {
+        let mut state = state_with_suggestion("ca", "rgo build");
+        state.set_content("car".to_string());
+        assert_eq!(state.autosuggestion_suffix(), Some("go build"));
+        state.set_content("cat".to_string());
+        assert!(state.autosuggestion.is_none());
+    }
+}
@@ -1,19 +1,35 @@
 use std::path::PathBuf;
 use rfd::FileDialog;
+use crate::model::theme_import::ThemeFormat;
 
 pub struct FilePicker;
 
 impl FilePicker {
-    /// Open a file picker for selecting theme files (.yaml, .yml, .json)
-    pub async fn pick_theme_file() -> Option<PathBuf> {
+    /// Theme and color scheme filters: Warp and base16 YAML, iTerm2,
+    /// Alacritty and Windows Terminal
+    fn theme_dialog() -> FileDialog {
         FileDialog::new()
-            .add_filter("Theme Files", &["yaml", "yml", "json"])
+            .add_filter("Theme Files", ThemeFormat::importable_extensions())
             .add_filter("YAML Files", &["yaml", "yml"])
-            .add_filter("JSON Files", &["json"])
+            .add_filter("iTerm2 Color Schemes", &["itermcolors"])
+            .add_filter("Alacritty Configs", &["toml", "yaml", "yml"])
+            .add_filter("Windows Terminal Schemes", &["json"])
+    }
+
+    /// Open a file picker for selecting a theme file
+    pub async fn pick_theme_file() -> Option<PathBuf> {
+        Self::theme_dialog()
             .set_title("Select Theme File")
             .pick_file()
     }
 
+    /// Open a file picker for selecting one or more theme files
+    pub async fn pick_theme_files() -> Option<Vec<PathBuf>> {
+        Self::theme_dialog()
+            .set_title("Select Theme Files")
+            .pick_files()
+    }
+
     /// Open a file picker for selecting background image files
     pub async fn pick_background_image() -> Option<PathBuf> {
         FileDialog::new()
@@ -25,15 +41,14 @@ impl FilePicker {
             .pick_file()
     }
 
-    /// Open a file picker for saving theme files
-    pub async fn save_theme_file(default_name: Option<&str>) -> Option<PathBuf> {
+    /// Open a file picker for saving a theme in `format`
+    pub async fn save_theme_file(default_name: Option<&str>, format: ThemeFormat) -> Option<PathBuf> {
         let mut dialog = FileDialog::new()
-            .add_filter("YAML Files", &["yaml"])
-            .add_filter("JSON Files", &["json"])
+            .add_filter(format!("{} Files", format), &[format.extension()])
             .set_title("Save Theme As");
 
         if let Some(name) = default_name {
-            dialog = dialog.set_file_name(name);
+            dialog = dialog.set_file_name(format!("{}.{}", name, format.extension()));
         }
 
         dialog.save_file()
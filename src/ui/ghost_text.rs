@@ -0,0 +1,239 @@
+//! Fish-style autosuggestion drawn inline in a text input.
+//!
+//! [`GhostText`] wraps the command input and draws the suggested suffix,
+//! dimmed, right after the typed text. It also owns acceptance: Right and
+//! Alt+Right accept the suggestion only while the wrapped input is focused
+//! with its cursor at the end, and move the cursor as usual otherwise.
+
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::widget::{tree, Operation, Tree};
+use iced::advanced::{mouse, overlay, renderer, text, Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::widget::text_input::{self, cursor, Value};
+use iced::widget::{row, Text};
+use iced::{keyboard, Color, Element, Font, Length, Padding, Point, Rectangle, Size, Theme, Vector};
+
+use crate::input::keyboard::KeyboardShortcuts;
+use crate::Message;
+
+type Renderer = iced::Renderer;
+type Paragraph = <Renderer as text::Renderer>::Paragraph;
+
+const GHOST_COLOR: Color = Color::from_rgba(0.6, 0.6, 0.7, 0.55);
+
+pub struct GhostText<'a> {
+    input: Element<'a, Message>,
+    ghost: Element<'a, Message>,
+    typed: &'a str,
+}
+
+impl<'a> GhostText<'a> {
+    /// `input` showing `typed`, with `suffix` drawn after it. `padding` is
+    /// the input's own padding, which places the suffix on its text line.
+    /// `input` is a text input, or a widget wrapping one.
+    pub fn new(
+        input: impl Into<Element<'a, Message>>,
+        typed: &'a str,
+        suffix: &'a str,
+        font: Font,
+        size: u16,
+        padding: impl Into<Padding>,
+    ) -> Self {
+        let padding = padding.into();
+        // The typed prefix is laid out but transparent, so the suffix starts
+        // where the typed text ends
+        let ghost = row![
+            Text::new(typed).font(font).size(size).style(Color::TRANSPARENT),
+            Text::new(suffix).font(font).size(size).style(GHOST_COLOR),
+        ]
+        .padding(Padding {
+            top: 0.0,
+            bottom: 0.0,
+            ..padding
+        });
+        Self {
+            input: input.into(),
+            ghost: ghost.into(),
+            typed,
+        }
+    }
+
+    /// Whether the wrapped input has focus with its cursor after the last
+    /// character
+    fn cursor_at_end(&self, tree: &Tree) -> bool {
+        let Some(state) = input_state(&tree.children[0]) else {
+            return false;
+        };
+        let value = Value::new(self.typed);
+        state.is_focused()
+            && matches!(state.cursor().state(&value), cursor::State::Index(index) if index == value.len())
+    }
+}
+
+/// The state of the text input in `tree`, which may be wrapped
+pub(crate) fn input_state(tree: &Tree) -> Option<&text_input::State<Paragraph>> {
+    if tree.tag == tree::Tag::of::<text_input::State<Paragraph>>() {
+        return Some(tree.state.downcast_ref());
+    }
+    tree.children.iter().find_map(input_state)
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for GhostText<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::stateless()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.input), Tree::new(&self.ghost)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.input, &self.ghost]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.input.as_widget().size()
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        let input = self.input.as_widget().layout(&mut tree.children[0], renderer, limits);
+        let bounds = input.size();
+        let ghost = self
+            .ghost
+            .as_widget()
+            .layout(&mut tree.children[1], renderer, &layout::Limits::new(Size::ZERO, bounds));
+        // Centred vertically, as the input centres its text
+        let top = ((bounds.height - ghost.size().height) / 2.0).max(0.0);
+        layout::Node::with_children(bounds, vec![input, ghost.move_to(Point::new(0.0, top))])
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        let input_layout = layout.children().next().expect("the input is laid out first");
+        self.input
+            .as_widget()
+            .operate(&mut tree.children[0], input_layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = &event {
+            if let Some(message) = KeyboardShortcuts::autosuggestion_key(key, *modifiers) {
+                if self.cursor_at_end(tree) {
+                    shell.publish(message);
+                    return event::Status::Captured;
+                }
+            }
+        }
+        let input_layout = layout.children().next().expect("the input is laid out first");
+        self.input.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            input_layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let input_layout = layout.children().next().expect("the input is laid out first");
+        self.input
+            .as_widget()
+            .mouse_interaction(&tree.children[0], input_layout, cursor, viewport, renderer)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let mut children = layout.children();
+        let (Some(input_layout), Some(ghost_layout)) = (children.next(), children.next()) else {
+            return;
+        };
+        self.input
+            .as_widget()
+            .draw(&tree.children[0], renderer, theme, style, input_layout, cursor, viewport);
+        // Clipped to the input, so a long suggestion never spills past it
+        renderer::Renderer::with_layer(renderer, layout.bounds(), |renderer| {
+            self.ghost
+                .as_widget()
+                .draw(&tree.children[1], renderer, theme, style, ghost_layout, cursor, viewport);
+        });
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        let input_layout = layout.children().next()?;
+        self.input
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], input_layout, renderer, translation)
+    }
+}
+
+impl<'a> From<GhostText<'a>> for Element<'a, Message> {
+    fn from(ghost_text: GhostText<'a>) -> Self {
+        Element::new(ghost_text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::widget::text_input;
+
+    #[test]
+    fn test_accepts_only_with_the_cursor_at_the_end_of_the_focused_input() {
+        let ghost = GhostText::new(text_input("", "git st"), "git st", "atus", Font::MONOSPACE, 14, 0);
+        let mut tree = Tree {
+            tag: ghost.tag(),
+            state: tree::State::None,
+            children: ghost.children(),
+        };
+        fn input_state(tree: &mut Tree) -> &mut text_input::State<Paragraph> {
+            tree.children[0].state.downcast_mut()
+        }
+
+        assert!(!ghost.cursor_at_end(&tree), "unfocused");
+        input_state(&mut tree).focus();
+        assert!(ghost.cursor_at_end(&tree));
+        input_state(&mut tree).move_cursor_to(3);
+        assert!(!ghost.cursor_at_end(&tree), "cursor inside the text");
+        input_state(&mut tree).select_all();
+        assert!(!ghost.cursor_at_end(&tree), "text selected");
+    }
+}
@@ -23,6 +23,8 @@ pub const SUCCESS: char = '✅';     // Success checkmark
 pub const ERROR: char = '❌';       // Error cross
 pub const WARNING: char = '⚠';     // Warning triangle
 pub const INFO: char = 'ℹ';        // Information
+pub const RUN: char = '▶';         // Run
+pub const WORKFLOW: char = '🧩';    // Puzzle piece for workflows
 
 // Helper function to get appropriate icon based on context
 pub fn get_status_icon(exit_code: Option<i32>) -> char {
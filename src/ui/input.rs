@@ -1,16 +1,47 @@
 //! Enhanced input component for the terminal with modern design.
 
+use crate::editor::text_input::TextInputHandler;
+use crate::ui::ghost_text::GhostText;
+use crate::ui::line_editor_input::LineEditorInput;
 use crate::Message;
 use iced::widget::{button, container, row, text, column, Space, tooltip, text_input};
 use iced::{theme, Alignment, Background, Color, Element, Length, border, Font};
 
-pub fn enhanced_input_section(
-    current_input: &str,
+/// Identifier of the main command input, used to move its cursor after an
+/// autosuggestion is accepted.
+pub fn command_input_id() -> text_input::Id {
+    text_input::Id::new("command-input")
+}
+
+/// The command input. `line_editor` edits it with vim or readline when
+/// either is on.
+pub fn enhanced_input_section<'a>(
+    current_input: &'a str,
+    autosuggestion: Option<&'a str>,
+    notice: Option<&'a str>,
+    line_editor: Option<&'a TextInputHandler>,
     font: Font,
     size: u16,
     is_executing: bool,
-) -> Element<'_, Message> {
-    modern_text_input_section(current_input, font, size, is_executing, None, &[])
+) -> Element<'a, Message> {
+    let input_section = create_modern_input_section(current_input, font, size, is_executing, autosuggestion, line_editor);
+
+    // Command guard warnings and confirmation prompts above the input
+    match notice {
+        Some(notice) => column![
+            container(
+                text(notice)
+                    .font(font)
+                    .size(size - 2)
+                    .style(Color::from_rgb(0.95, 0.7, 0.3))
+            )
+            .padding([6, 24]),
+            input_section,
+        ]
+        .spacing(0)
+        .into(),
+        None => input_section,
+    }
 }
 
 // New modern text editor input section
@@ -23,7 +54,7 @@ pub fn modern_text_input_section<'a>(
     recent_commands: &'a [String],
 ) -> Element<'a, Message> {
     // Create the main input section with modern editor
-    let main_input_section = create_modern_input_section(current_input, font, size, is_executing, suggestion);
+    let main_input_section = create_modern_input_section(current_input, font, size, is_executing, suggestion, None);
     
     // Add suggestions panel if there are recent commands or suggestions
     if !recent_commands.is_empty() || suggestion.is_some() {
@@ -44,15 +75,29 @@ fn create_modern_input_section<'a>(
     font: Font,
     size: u16,
     is_executing: bool,
-    _suggestion: Option<&'a str>,
+    autosuggestion: Option<&'a str>,
+    line_editor: Option<&'a TextInputHandler>,
 ) -> Element<'a, Message> {
     // Create a simple text input for now (can be enhanced later)
     let input_field = text_input("Code, ask, build, or run commands", current_input)
+        .id(command_input_id())
         .on_input(Message::InputChanged)
         .padding([14, 18])
         .size(size)
         .font(font)
         .width(Length::Fill);
+
+    // Vim or readline get keys before the input does
+    let input_field: Element<'a, Message> = match line_editor {
+        Some(handler) => LineEditorInput::new(input_field, handler, current_input).into(),
+        None => input_field.into(),
+    };
+
+    // Inline autosuggestion rendered as dimmed ghost text after the typed input
+    let input_field = match autosuggestion {
+        Some(suffix) => GhostText::new(input_field, current_input, suffix, font, size, [14, 18]).into(),
+        None => input_field,
+    };
     
     // Enhanced terminal prompt with user info
     let prompt_section = row![
@@ -98,7 +143,10 @@ fn create_modern_input_section<'a>(
             "Current working directory",
             tooltip::Position::Top
         ),
-    ].spacing(6).align_items(Alignment::Center);
+    ]
+    .push_maybe(line_editor.and_then(|handler| vim_status(handler, font, size)))
+    .spacing(6)
+    .align_items(Alignment::Center);
     
     // Action buttons (right side) - enhanced for modern editing
     let action_buttons = create_modern_action_buttons(is_executing, font, size);
@@ -140,6 +188,23 @@ fn create_modern_input_section<'a>(
         .into()
 }
 
+/// Vim's mode, or the `:` command line being typed and then its message
+fn vim_status<'a>(handler: &TextInputHandler, font: Font, size: u16) -> Option<Element<'a, Message>> {
+    let vim = &handler.vim_manager;
+    if !vim.is_enabled() {
+        return None;
+    }
+    let status = vim
+        .command_line()
+        .or_else(|| vim.message().map(str::to_string))
+        .unwrap_or_else(|| format!("-- {} --", vim.get_mode_display()));
+    Some(
+        container(text(status).font(font).size(size - 6).style(Color::from_rgb(0.9, 0.8, 0.4)))
+            .padding([2, 4])
+            .into(),
+    )
+}
+
 // Advanced input box with auto-suggestions and enhanced UI
 pub fn advanced_input_with_suggestions<'a>(
     current_input: &'a str,
@@ -161,7 +226,7 @@ pub fn advanced_input_with_suggestions<'a>(
             suggestions_panel
         ].spacing(0).into()
     } else {
-        main_input_section.into()
+        main_input_section
     }
 }
 
@@ -0,0 +1,477 @@
+//! Keybindings tab of the settings.
+//!
+//! Lists every command in the [`CommandRegistry`](crate::model::command_registry::CommandRegistry)
+//! that has a keymap action, with its current bindings. A binding is
+//! changed by recording a key combination or chord; conflicts it would
+//! cause are shown while recording, before anything is saved. Every change
+//! is written straight to the [`UserKeyset`], which the caller then layers
+//! over the active keyset again.
+
+use std::path::PathBuf;
+
+use iced::widget::{button, column, container, row, text, text_input, Space};
+use iced::{theme, Alignment, Color, Element, Font, Length};
+
+use crate::input::keymap::{KeyBinding, KeySequence, Keymap, Keystroke};
+use crate::keyset::UserKeyset;
+use crate::model::command_registry::{Command, CommandCategory};
+use crate::Message;
+
+/// Longest chord that can be recorded
+const MAX_RECORDED_KEYS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum KeybindingEditorMessage {
+    /// Show the settings on this tab
+    Open,
+    FilterChanged(String),
+    /// Start recording a binding for an action
+    Record(String),
+    /// Replace the action's bindings with the recorded keys
+    Replace,
+    /// Add the recorded keys to the action's bindings
+    Add,
+    CancelRecording,
+    /// Remove every binding of an action
+    Unbind(String),
+    /// Drop the user's change to an action
+    Reset(String),
+    ResetAll,
+}
+
+/// One bindable command with its effective bindings
+#[derive(Debug, Clone)]
+pub struct ActionRow {
+    pub title: String,
+    pub category: CommandCategory,
+    pub action: String,
+    pub bindings: Vec<KeyBinding>,
+    /// The user keyset sets or removes this action's bindings
+    pub customized: bool,
+    /// Conflicts this action is part of, as readable reports
+    pub conflicts: Vec<String>,
+}
+
+/// Keys recorded so far for one action
+#[derive(Debug, Clone)]
+struct Recording {
+    action: String,
+    strokes: Vec<Keystroke>,
+    /// Conflicts the recorded keys would cause if they replaced the
+    /// action's bindings
+    conflicts: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct KeybindingEditor {
+    pub filter: String,
+    rows: Vec<ActionRow>,
+    /// Built-in bindings and the active keyset
+    base: Keymap,
+    user: Result<UserKeyset, String>,
+    recording: Option<Recording>,
+    pub error: Option<String>,
+}
+
+impl KeybindingEditor {
+    /// Edit the user keyset at `path`. An unreadable file is reported and
+    /// left alone rather than overwritten.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let user = match path {
+            Some(path) => UserKeyset::load(path).map_err(|e| format!("{:#}", e)),
+            None => Err("No home directory to save key bindings in".to_string()),
+        };
+        Self {
+            filter: String::new(),
+            rows: Vec::new(),
+            base: Keymap::builtin(),
+            error: user.as_ref().err().cloned(),
+            user,
+            recording: None,
+        }
+    }
+
+    /// Rebuild the list from the bindable `commands` and the keymap the
+    /// user keyset is layered over
+    pub fn refresh(&mut self, commands: Vec<Command>, base: Keymap) {
+        self.base = base;
+        let keymap = self.keymap();
+        let conflicts = keymap.conflicts();
+        self.rows = commands
+            .into_iter()
+            .filter_map(|command| {
+                let action = command.action?;
+                Some(ActionRow {
+                    bindings: keymap.bindings_for_action(&action).into_iter().cloned().collect(),
+                    customized: self.user.as_ref().is_ok_and(|user| user.is_customized(&action)),
+                    conflicts: conflicts
+                        .iter()
+                        .filter(|conflict| conflict.bindings.iter().any(|binding| binding.action == action))
+                        .map(ToString::to_string)
+                        .collect(),
+                    title: command.title,
+                    category: command.category,
+                    action,
+                })
+            })
+            .collect();
+    }
+
+    /// The base keymap with the user keyset on top
+    pub fn keymap(&self) -> Keymap {
+        match &self.user {
+            Ok(user) => self.base.clone().with_layer(user.layer()),
+            Err(_) => self.base.clone(),
+        }
+    }
+
+    pub fn rows(&self) -> &[ActionRow] {
+        &self.rows
+    }
+
+    /// Rows whose title, action or bindings contain the filter
+    pub fn visible_rows(&self) -> impl Iterator<Item = &ActionRow> {
+        let filter = self.filter.trim().to_lowercase();
+        self.rows.iter().filter(move |row| {
+            filter.is_empty()
+                || row.title.to_lowercase().contains(&filter)
+                || row.action.contains(&filter)
+                || row.bindings.iter().any(|binding| binding.sequence.to_string().to_lowercase().contains(&filter))
+        })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Add a key press to the recording. Modifier keys on their own are
+    /// ignored, and a recording is capped at a four-key chord.
+    pub fn record(&mut self, stroke: Keystroke) {
+        let Some(recording) = &mut self.recording else { return };
+        if stroke.is_modifier_only() || recording.strokes.len() >= MAX_RECORDED_KEYS {
+            return;
+        }
+        recording.strokes.push(stroke);
+        let action = recording.action.clone();
+        let sequence = KeySequence(recording.strokes.clone());
+        let conflicts = match self.with_bindings(&action, vec![sequence]) {
+            Some(user) => self
+                .base
+                .clone()
+                .with_layer(user.layer())
+                .conflicts()
+                .iter()
+                .filter(|conflict| conflict.bindings.iter().any(|binding| binding.action == action))
+                .map(ToString::to_string)
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(recording) = &mut self.recording {
+            recording.conflicts = conflicts;
+        }
+    }
+
+    /// Apply a message. Returns true when the user keyset was saved and
+    /// the keymap needs reloading; failures are kept in [`Self::error`].
+    pub fn update(&mut self, message: KeybindingEditorMessage) -> bool {
+        self.error = None;
+        let change = match message {
+            KeybindingEditorMessage::Open => None,
+            KeybindingEditorMessage::FilterChanged(filter) => {
+                self.filter = filter;
+                None
+            }
+            KeybindingEditorMessage::Record(action) => {
+                self.recording = Some(Recording {
+                    action,
+                    strokes: Vec::new(),
+                    conflicts: Vec::new(),
+                });
+                None
+            }
+            KeybindingEditorMessage::CancelRecording => {
+                self.recording = None;
+                None
+            }
+            KeybindingEditorMessage::Replace | KeybindingEditorMessage::Add => {
+                match self.recording.take().filter(|recording| !recording.strokes.is_empty()) {
+                    Some(recording) => {
+                        let mut sequences = Vec::new();
+                        if matches!(message, KeybindingEditorMessage::Add) {
+                            sequences = self.current_sequences(&recording.action);
+                        }
+                        let sequence = KeySequence(recording.strokes);
+                        if !sequences.contains(&sequence) {
+                            sequences.push(sequence);
+                        }
+                        self.with_bindings(&recording.action, sequences)
+                    }
+                    None => None,
+                }
+            }
+            KeybindingEditorMessage::Unbind(action) => self.with_bindings(&action, Vec::new()),
+            KeybindingEditorMessage::Reset(action) => self.edit(|user| {
+                user.reset(&action);
+            }),
+            KeybindingEditorMessage::ResetAll => self.edit(UserKeyset::reset_all),
+        };
+
+        let Some(user) = change else { return false };
+        if let Err(e) = user.save() {
+            self.error = Some(format!("{:#}", e));
+            return false;
+        }
+        self.user = Ok(user);
+        true
+    }
+
+    /// A copy of the user keyset with `action` bound to `sequences`
+    fn with_bindings(&mut self, action: &str, sequences: Vec<KeySequence>) -> Option<UserKeyset> {
+        self.edit(|user| user.set_bindings(action, &sequences))
+    }
+
+    /// A copy of the user keyset with `edit` applied, or `None` with the
+    /// reason in [`Self::error`] when the file could not be read
+    fn edit(&mut self, edit: impl FnOnce(&mut UserKeyset)) -> Option<UserKeyset> {
+        match &self.user {
+            Ok(user) => {
+                let mut user = user.clone();
+                edit(&mut user);
+                Some(user)
+            }
+            Err(e) => {
+                self.error = Some(format!("Key bindings cannot be changed until this is fixed: {}", e));
+                None
+            }
+        }
+    }
+
+    fn current_sequences(&self, action: &str) -> Vec<KeySequence> {
+        self.rows
+            .iter()
+            .find(|row| row.action == action)
+            .map(|row| row.bindings.iter().map(|binding| binding.sequence.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn view(&self) -> Element<'static, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.7);
+        let red = Color::from_rgb(0.9, 0.2, 0.2);
+        let send = |message: KeybindingEditorMessage| Message::KeybindingEditor(message);
+
+        let saved_to = match &self.user {
+            Ok(user) => format!("Changes are saved to {}", user.path().display()),
+            Err(_) => "Changes cannot be saved".to_string(),
+        };
+        let error: Element<'static, Message> = match &self.error {
+            Some(error) => text(error.clone()).size(13).style(red).into(),
+            None => Space::with_height(0).into(),
+        };
+
+        let recording: Element<'static, Message> = match &self.recording {
+            Some(recording) => {
+                let title = self
+                    .rows
+                    .iter()
+                    .find(|row| row.action == recording.action)
+                    .map_or(recording.action.clone(), |row| row.title.clone());
+                let keys = if recording.strokes.is_empty() {
+                    "Press a key combination or chord…".to_string()
+                } else {
+                    KeySequence(recording.strokes.clone()).to_string()
+                };
+                let recorded = !recording.strokes.is_empty();
+                container(
+                    column![
+                        text(format!("Recording: {}", title)).size(16),
+                        text(keys).size(18).font(Font::MONOSPACE),
+                        column(
+                            recording
+                                .conflicts
+                                .iter()
+                                .map(|conflict| text(conflict.clone()).size(12).style(red).into())
+                                .collect::<Vec<Element<'static, Message>>>()
+                        )
+                        .spacing(4),
+                        row![
+                            button(text("Replace").size(13))
+                                .on_press_maybe(recorded.then(|| send(KeybindingEditorMessage::Replace)))
+                                .style(theme::Button::Primary),
+                            button(text("Add").size(13))
+                                .on_press_maybe(recorded.then(|| send(KeybindingEditorMessage::Add)))
+                                .style(theme::Button::Secondary),
+                            button(text("Cancel").size(13))
+                                .on_press(send(KeybindingEditorMessage::CancelRecording))
+                                .style(theme::Button::Secondary),
+                        ]
+                        .spacing(8),
+                    ]
+                    .spacing(8),
+                )
+                .padding([16, 20])
+                .width(Length::Fill)
+                .style(theme::Container::Box)
+                .into()
+            }
+            None => Space::with_height(0).into(),
+        };
+
+        let mut list = column![].spacing(6);
+        let mut category = None;
+        for action_row in self.visible_rows() {
+            if category != Some(&action_row.category) {
+                category = Some(&action_row.category);
+                list = list.push(Space::with_height(8)).push(text(action_row.category.display_name()).size(16));
+            }
+            list = list.push(self.row_view(action_row, muted, red));
+        }
+
+        column![
+            text("Keybindings").size(24),
+            text(saved_to).size(13).style(muted),
+            error,
+            row![
+                text_input("Search actions or keys", &self.filter)
+                    .on_input(move |filter| send(KeybindingEditorMessage::FilterChanged(filter)))
+                    .size(14),
+                button(text("Reset all").size(13))
+                    .on_press(send(KeybindingEditorMessage::ResetAll))
+                    .style(theme::Button::Destructive),
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+            recording,
+            list,
+        ]
+        .spacing(12)
+        .width(Length::Fill)
+        .into()
+    }
+
+    fn row_view(&self, action_row: &ActionRow, muted: Color, red: Color) -> Element<'static, Message> {
+        let bindings = if action_row.bindings.is_empty() {
+            "Unbound".to_string()
+        } else {
+            action_row
+                .bindings
+                .iter()
+                .map(|binding| binding.sequence.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut buttons = row![button(text("Record").size(12))
+            .on_press(Message::KeybindingEditor(KeybindingEditorMessage::Record(action_row.action.clone())))
+            .style(theme::Button::Secondary)]
+        .spacing(6);
+        if !action_row.bindings.is_empty() {
+            buttons = buttons.push(
+                button(text("Remove").size(12))
+                    .on_press(Message::KeybindingEditor(KeybindingEditorMessage::Unbind(action_row.action.clone())))
+                    .style(theme::Button::Secondary),
+            );
+        }
+        if action_row.customized {
+            buttons = buttons.push(
+                button(text("Reset").size(12))
+                    .on_press(Message::KeybindingEditor(KeybindingEditorMessage::Reset(action_row.action.clone())))
+                    .style(theme::Button::Secondary),
+            );
+        }
+
+        let title = if action_row.customized {
+            format!("{} (changed)", action_row.title)
+        } else {
+            action_row.title.clone()
+        };
+        column![
+            row![
+                column![
+                    text(title).size(14),
+                    text(action_row.action.clone()).size(11).style(muted),
+                ]
+                .width(Length::Fill),
+                text(bindings).size(13).font(Font::MONOSPACE),
+                buttons,
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+            column(
+                action_row
+                    .conflicts
+                    .iter()
+                    .map(|conflict| text(conflict.clone()).size(12).style(red).into())
+                    .collect::<Vec<Element<'static, Message>>>()
+            )
+            .spacing(2),
+        ]
+        .spacing(4)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::keyboard::{Key, Modifiers};
+
+    fn commands() -> Vec<Command> {
+        ["pane_group:close", "workspace:show_command_search"]
+            .into_iter()
+            .map(|action| {
+                Command::builder(&action.replace(':', "."), action)
+                    .category(CommandCategory::Pane)
+                    .action(action)
+                    .build()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn press(editor: &mut KeybindingEditor, c: &str, modifiers: Modifiers) {
+        editor.record(Keystroke::new(Key::Character(c.into()), modifiers));
+    }
+
+    #[test]
+    fn test_record_shows_conflicts_and_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.yaml");
+        let mut editor = KeybindingEditor::new(Some(path.clone()));
+        editor.refresh(commands(), Keymap::builtin());
+        assert!(editor.rows().iter().all(|row| row.conflicts.is_empty() && !row.customized));
+
+        // ctrl-r is already command search
+        editor.update(KeybindingEditorMessage::Record("pane_group:close".to_string()));
+        press(&mut editor, "r", Modifiers::CTRL);
+        let conflicts = &editor.recording.as_ref().unwrap().conflicts;
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].contains("workspace:show_command_search"), "{}", conflicts[0]);
+        assert!(!path.exists());
+
+        assert!(editor.update(KeybindingEditorMessage::Add));
+        editor.refresh(commands(), Keymap::builtin());
+        let close = &editor.rows()[0];
+        assert!(close.customized);
+        assert_eq!(close.bindings.len(), 2);
+        assert_eq!(close.conflicts.len(), 1);
+        assert_eq!(editor.keymap().bindings_for_action("pane_group:close").len(), 2);
+        assert!(std::fs::read_to_string(&path).unwrap().contains("ctrl-r"));
+
+        assert!(editor.update(KeybindingEditorMessage::Reset("pane_group:close".to_string())));
+        editor.refresh(commands(), Keymap::builtin());
+        assert!(editor.rows().iter().all(|row| row.conflicts.is_empty() && !row.customized));
+    }
+
+    #[test]
+    fn test_unreadable_user_keyset_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.yaml");
+        std::fs::write(&path, "[not, a, map").unwrap();
+        let mut editor = KeybindingEditor::new(Some(path.clone()));
+        editor.refresh(commands(), Keymap::builtin());
+        assert!(editor.error.is_some());
+
+        assert!(!editor.update(KeybindingEditorMessage::Unbind("pane_group:close".to_string())));
+        assert!(editor.error.as_ref().unwrap().contains("cannot be changed"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[not, a, map");
+    }
+}
@@ -0,0 +1,303 @@
+//! Vim and readline editing in the command input.
+//!
+//! [`LineEditorInput`] wraps the command input and, while it has focus,
+//! offers each key press to the terminal's [`TextInputHandler`] first. A key
+//! vim or readline would act on is captured and published as
+//! [`Message::LineEditorKey`] with the input's cursor, and the terminal
+//! edits the line in the handler and shows the result. Other keys reach
+//! the input as usual.
+
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::widget::{tree, Operation, Tree};
+use iced::advanced::{mouse, overlay, renderer, Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::keyboard::{self, Key, Modifiers};
+use iced::widget::text_input::{cursor, Value};
+use iced::{Element, Length, Rectangle, Size, Theme, Vector};
+
+use crate::editor::text_input::TextInputHandler;
+use crate::ui::ghost_text::input_state;
+use crate::Message;
+
+type Renderer = iced::Renderer;
+
+pub struct LineEditorInput<'a> {
+    input: Element<'a, Message>,
+    handler: &'a TextInputHandler,
+    typed: &'a str,
+}
+
+impl<'a> LineEditorInput<'a> {
+    /// `input` is the text input showing `typed`. A
+    /// [`GhostText`](super::ghost_text::GhostText) goes around this, so an
+    /// autosuggestion is accepted before vim or readline see the key.
+    pub fn new(input: impl Into<Element<'a, Message>>, handler: &'a TextInputHandler, typed: &'a str) -> Self {
+        Self {
+            input: input.into(),
+            handler,
+            typed,
+        }
+    }
+
+    /// The message for a key press the handler takes, if the input has
+    /// focus. The cursor is sent as a byte offset into the typed text.
+    fn claim(&self, tree: &Tree, key: &Key, modifiers: Modifiers) -> Option<Message> {
+        let state = input_state(&tree.children[0])?;
+        if !state.is_focused() {
+            return None;
+        }
+        let value = Value::new(self.typed);
+        let index = match state.cursor().state(&value) {
+            cursor::State::Index(index) => index,
+            cursor::State::Selection { end, .. } => end,
+        };
+        let cursor = value.until(index).to_string().len();
+        self.handler
+            .claims_line_key(self.typed, cursor, key, &modifiers)
+            .then(|| Message::LineEditorKey(key.clone(), modifiers, cursor))
+    }
+}
+
+/// Where the input's cursor goes for byte offset `cursor` in `text`
+pub fn cursor_index(text: &str, cursor: usize) -> usize {
+    Value::new(text.get(..cursor).unwrap_or(text)).len()
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for LineEditorInput<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::stateless()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.input)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.input]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.input.as_widget().size()
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.input.as_widget().layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.input
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = &event {
+            if let Some(message) = self.claim(tree, key, *modifiers) {
+                shell.publish(message);
+                return event::Status::Captured;
+            }
+        }
+        self.input.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.input
+            .as_widget()
+            .mouse_interaction(&tree.children[0], layout, cursor, viewport, renderer)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.input
+            .as_widget()
+            .draw(&tree.children[0], renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.input
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
+
+impl<'a> From<LineEditorInput<'a>> for Element<'a, Message> {
+    fn from(input: LineEditorInput<'a>) -> Self {
+        Element::new(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::readline::ReadlineOutcome;
+    use crate::input::Keymap;
+    use iced::advanced::text;
+    use iced::keyboard::key::Named;
+    use iced::widget::text_input;
+    use std::path::Path;
+
+    type Paragraph = <Renderer as text::Renderer>::Paragraph;
+
+    struct Typed {
+        line: String,
+        /// Keys left to the input
+        unclaimed: Vec<Key>,
+        /// Whether the last key asked for the external editor
+        external_edit: bool,
+    }
+
+    /// Types `keys` into a focused command input, handling the keys it
+    /// claims as the terminal does
+    fn type_keys(handler: &mut TextInputHandler, keys: &[(Key, Modifiers)]) -> Typed {
+        fn state(tree: &mut Tree) -> &mut text_input::State<Paragraph> {
+            tree.children[0].state.downcast_mut()
+        }
+        let mut line = String::new();
+        let mut unclaimed = Vec::new();
+        let mut external_edit = false;
+        let mut tree: Option<Tree> = None;
+        for (key, modifiers) in keys {
+            let claimed = {
+                let input = text_input("", &line).on_input(Message::InputChanged);
+                let editor = LineEditorInput::new(input, handler, &line);
+                let tree = tree.get_or_insert_with(|| {
+                    let mut tree = Tree {
+                        tag: editor.tag(),
+                        state: tree::State::None,
+                        children: editor.children(),
+                    };
+                    state(&mut tree).focus();
+                    tree
+                });
+                editor.diff(tree);
+                editor.claim(tree, key, *modifiers)
+            };
+            match claimed {
+                Some(Message::LineEditorKey(key, modifiers, cursor)) => {
+                    let outcome = handler.handle_line_key(&line, cursor, &key, &modifiers);
+                    external_edit = outcome == ReadlineOutcome::EditInExternalEditor;
+                    line = handler.get_content();
+                    let index = cursor_index(&line, handler.get_cursor_position());
+                    state(tree.as_mut().unwrap()).move_cursor_to(index);
+                }
+                _ => unclaimed.push(key.clone()),
+            }
+        }
+        Typed {
+            line,
+            unclaimed,
+            external_edit,
+        }
+    }
+
+    fn chars(text: &str) -> Vec<(Key, Modifiers)> {
+        text.chars()
+            .map(|c| (Key::Character(c.to_string().into()), Modifiers::empty()))
+            .collect()
+    }
+
+    fn chord(modifiers: Modifiers, c: &str) -> (Key, Modifiers) {
+        (Key::Character(c.into()), modifiers)
+    }
+
+    #[test]
+    fn test_vim_edits_the_command_input() {
+        let mut handler = TextInputHandler::new();
+        handler.set_vim_mode(true);
+        handler.start_line();
+
+        let mut keys = chars("git commit -m wip");
+        keys.push((Key::Named(Named::Escape), Modifiers::empty()));
+        keys.extend(chars("0wcwstatus"));
+        keys.push((Key::Named(Named::Escape), Modifiers::empty()));
+        keys.extend(chars("wD"));
+        keys.push((Key::Named(Named::Enter), Modifiers::empty()));
+        let typed = type_keys(&mut handler, &keys);
+
+        assert_eq!(typed.line, "git status ");
+        assert_eq!(typed.unclaimed, vec![Key::Named(Named::Enter)], "Enter still runs the command");
+    }
+
+    #[test]
+    fn test_readline_edits_the_command_input() {
+        // As the terminal sets it up with the emacs keyset active
+        let mut handler = TextInputHandler::new();
+        handler.set_emacs_mode(true);
+        handler
+            .readline
+            .set_keymap(&Keymap::load(Some(Path::new("keysets/emacs.yaml")), None));
+
+        let mut keys = chars("echo world hello");
+        keys.push(chord(Modifiers::ALT, "t"));
+        keys.extend([chord(Modifiers::CTRL, "a"), chord(Modifiers::ALT, "u")]);
+        keys.extend([chord(Modifiers::CTRL, "e"), chord(Modifiers::CTRL, "w")]);
+        keys.extend([chord(Modifiers::ALT, "2"), chord(Modifiers::CTRL, "b")]);
+        keys.push(chord(Modifiers::CTRL, "t"));
+        keys.extend([chord(Modifiers::CTRL, "e"), chord(Modifiers::CTRL, "y")]);
+        keys.push((Key::Named(Named::ArrowUp), Modifiers::empty()));
+        keys.push((Key::Character("_".into()), Modifiers::CTRL | Modifiers::SHIFT));
+        keys.extend([chord(Modifiers::CTRL, "x"), chord(Modifiers::CTRL, "e")]);
+        let typed = type_keys(&mut handler, &keys);
+
+        // The yank is undone, and the line is left for the external editor
+        assert_eq!(typed.line, "ECHO helol ");
+        assert_eq!(typed.unclaimed, vec![Key::Named(Named::ArrowUp)], "Up still walks history");
+        assert!(typed.external_edit);
+        handler.finish_external_edit(Ok("echo edited".to_string()));
+        assert_eq!(handler.get_content(), "echo edited");
+    }
+
+    #[test]
+    fn test_keys_reach_the_input_without_vim_or_readline() {
+        let mut handler = TextInputHandler::new();
+        let typed = type_keys(&mut handler, &chars("ls"));
+        assert_eq!(typed.line, "");
+        assert_eq!(typed.unclaimed.len(), 2);
+    }
+}
@@ -0,0 +1,117 @@
+//! Renders Markdown documents as iced widgets.
+
+use iced::widget::{column, container, horizontal_rule, row, text, Column};
+use iced::{theme, Color, Element, Font, Length};
+
+use crate::markdown_parser::{self, plain_text, BlockKind, Inline, MarkdownBlock};
+use crate::ui::code::view_code;
+use crate::Message;
+
+const HEADING_COLOR: Color = Color::from_rgb(0.95, 0.95, 0.98);
+const QUOTE_COLOR: Color = Color::from_rgb(0.65, 0.65, 0.72);
+const LINK_COLOR: Color = Color::from_rgb(0.45, 0.65, 0.95);
+
+/// Parse and render Markdown source
+pub fn view_markdown<'a>(source: &str, font: Font, size: u16) -> Element<'a, Message> {
+    view_blocks(&markdown_parser::parse(source).blocks, font, size)
+}
+
+fn view_blocks<'a>(blocks: &[MarkdownBlock], font: Font, size: u16) -> Element<'a, Message> {
+    blocks
+        .iter()
+        .fold(Column::new().spacing(8), |col, block| col.push(view_block(block, font, size)))
+        .into()
+}
+
+fn view_block<'a>(block: &MarkdownBlock, font: Font, size: u16) -> Element<'a, Message> {
+    match &block.kind {
+        BlockKind::Heading { level, content } => {
+            // h1 is 10 points larger than body text, h6 the same size
+            let heading_size = size + 2 * (6 - (*level).min(6) as u16);
+            text(inline_text(content))
+                .font(Font {
+                    weight: iced::font::Weight::Bold,
+                    ..font
+                })
+                .size(heading_size)
+                .style(HEADING_COLOR)
+                .into()
+        }
+        BlockKind::Paragraph(content) => paragraph(content, font, size),
+        BlockKind::CodeBlock { info, code } => {
+            // The info string names the language, as in ```rust
+            let language = info.split_whitespace().next();
+            view_code(code, language, size.saturating_sub(1))
+        }
+        BlockKind::List(list) => list
+            .items
+            .iter()
+            .enumerate()
+            .fold(column![].spacing(4), |col, (index, item)| {
+                let marker = match (item.checked, list.start) {
+                    (Some(true), _) => "☑".to_string(),
+                    (Some(false), _) => "☐".to_string(),
+                    (None, Some(start)) => format!("{}.", start + index as u64),
+                    (None, None) => "•".to_string(),
+                };
+                col.push(
+                    row![
+                        text(marker).font(font).size(size).width(Length::Fixed(24.0)),
+                        view_blocks(&item.blocks, font, size),
+                    ]
+                    .spacing(4),
+                )
+            })
+            .into(),
+        BlockKind::BlockQuote(inner) => container(view_blocks(inner, font, size))
+            .padding([0, 0, 0, 12])
+            .style(theme::Container::Custom(Box::new(QuoteStyle)))
+            .into(),
+        BlockKind::ThematicBreak => horizontal_rule(1).into(),
+    }
+}
+
+/// A paragraph as text. Links and images are followed by their target so
+/// it stays visible without rich text support.
+fn paragraph<'a>(content: &[Inline], font: Font, size: u16) -> Element<'a, Message> {
+    let paragraph = text(inline_text(content)).font(font).size(size);
+    if matches!(content, [Inline::Link { .. }]) {
+        paragraph.style(LINK_COLOR).into()
+    } else {
+        paragraph.into()
+    }
+}
+
+fn inline_text(content: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in content {
+        match inline {
+            Inline::Link { content, url } => {
+                let label = plain_text(content);
+                if label == *url {
+                    out.push_str(url);
+                } else {
+                    out.push_str(&format!("{} ({})", label, url));
+                }
+            }
+            Inline::Image { alt, url } => out.push_str(&format!("[{}] ({})", alt, url)),
+            Inline::Emphasis(inner) | Inline::Strong(inner) => out.push_str(&inline_text(inner)),
+            Inline::Code(code) => out.push_str(&format!("`{}`", code)),
+            other => out.push_str(&plain_text(std::slice::from_ref(other))),
+        }
+    }
+    out
+}
+
+struct QuoteStyle;
+
+impl container::StyleSheet for QuoteStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            text_color: Some(QUOTE_COLOR),
+            ..Default::default()
+        }
+    }
+}
@@ -1,16 +1,25 @@
 pub mod block;
+pub mod code;
 pub mod command_history;
 pub mod command_palette;
 pub mod command_search;
 pub mod enhanced_input;
 pub mod file_picker;
+pub mod ghost_text;
 pub mod icons;
 pub mod input;
+pub mod keybinding_editor;
+pub mod line_editor_input;
+pub mod markdown;
 pub mod modern_components;
 pub mod pane;
 pub mod quick_actions;
+pub mod save_workflow;
 pub mod settings;
 pub mod settings_handler;
 pub mod synchronization;
+pub mod terminal_output;
+pub mod theme_background;
 pub mod theme_selector;
 pub mod welcome;
+pub mod workflow_arguments;
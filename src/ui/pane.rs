@@ -2,6 +2,7 @@ use iced::{
     widget::{column, container, row, text, text_input, scrollable},
     Alignment, Background, Border, Color, Element, Length, Padding, Theme,
 };
+use crate::model::scrollback::Scrollback;
 use crate::ui::modern_components::WarpUI;
 
 
@@ -19,17 +20,11 @@ pub enum PaneMessage {
 #[derive(Debug, Clone)]
 pub struct PaneState {
     pub input_value: String,
-    pub output_content: Vec<OutputLine>,
+    pub output_content: Scrollback<OutputType>,
     pub is_maximized: bool,
     pub version: String,
 }
 
-#[derive(Debug, Clone)]
-pub struct OutputLine {
-    pub content: String,
-    pub line_type: OutputType,
-}
-
 #[derive(Debug, Clone)]
 pub enum OutputType {
     Command,
@@ -40,18 +35,12 @@ pub enum OutputType {
 
 impl Default for PaneState {
     fn default() -> Self {
+        let mut output_content = Scrollback::new();
+        output_content.push("Welcome to Warp Terminal", OutputType::Info);
+        output_content.push("Type commands below or ask AI for help", OutputType::Info);
         Self {
             input_value: String::new(),
-            output_content: vec![
-                OutputLine {
-                    content: "Welcome to Warp Terminal".to_string(),
-                    line_type: OutputType::Info,
-                },
-                OutputLine {
-                    content: "Type commands below or ask AI for help".to_string(),
-                    line_type: OutputType::Info,
-                },
-            ],
+            output_content,
             is_maximized: false,
             version: "v0.2024.01.09.08.02.stable_01".to_string(),
         }
@@ -64,7 +53,7 @@ impl PaneState {
     }
 
     pub fn add_output_line(&mut self, content: String, line_type: OutputType) {
-        self.output_content.push(OutputLine { content, line_type });
+        self.output_content.push(&content, line_type);
     }
 
     pub fn clear_output(&mut self) {
@@ -205,20 +194,20 @@ fn create_input_area<'a>(input_value: &str) -> Element<'a, PaneMessage> {
         .into()
 }
 
-fn create_content_area<'a>(output_lines: &[OutputLine]) -> Element<'a, PaneMessage> {
+fn create_content_area<'a>(output_lines: &Scrollback<OutputType>) -> Element<'a, PaneMessage> {
     let mut content = column![]
         .spacing(4)
         .width(Length::Fill);
 
-    for line in output_lines {
-        let line_color = match line.line_type {
+    for line in output_lines.iter() {
+        let line_color = match line.kind {
             OutputType::Command => Color::from_rgba(0.4, 0.8, 0.4, 1.0), // Green
             OutputType::Output => Color::from_rgba(0.9, 0.9, 0.95, 1.0), // Light gray
             OutputType::Error => Color::from_rgba(0.9, 0.4, 0.4, 1.0),   // Red
             OutputType::Info => Color::from_rgba(0.4, 0.7, 0.9, 1.0),    // Blue
         };
 
-        let line_text = text(&line.content)
+        let line_text = text(line.text())
             .size(14)
             .style(line_color);
 
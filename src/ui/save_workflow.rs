@@ -0,0 +1,205 @@
+//! "Save as workflow" dialog.
+//!
+//! Lets the user name a [`WorkflowDraft`] taken from a block or history
+//! entry, choose which detected values become arguments, and write it to
+//! their workflow directory.
+
+use std::path::{Path, PathBuf};
+
+use iced::widget::{button, checkbox, column, container, row, scrollable, text, text_input, Space};
+use iced::{theme, Alignment, Background, Border, Color, Element, Font, Length};
+
+use crate::model::workflow_draft::{WorkflowDraft, WorkflowDraftError};
+use crate::Message;
+
+#[derive(Debug, Clone)]
+pub enum SaveWorkflowMessage {
+    NameChanged(String),
+    DescriptionChanged(String),
+    TagsChanged(String),
+    ParameterToggled(usize, bool),
+    ParameterRenamed(usize, String),
+    Save,
+    Cancel,
+}
+
+/// State of the dialog while a draft is being edited
+#[derive(Debug, Clone)]
+pub struct SaveWorkflowDialog {
+    pub draft: WorkflowDraft,
+    /// Tags as typed, comma separated
+    pub tags_input: String,
+    /// Argument names as typed; applied to the draft when valid
+    pub parameter_names: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl SaveWorkflowDialog {
+    pub fn new(draft: WorkflowDraft) -> Self {
+        Self {
+            tags_input: draft.tags.join(", "),
+            parameter_names: draft.parameters.iter().map(|param| param.name.clone()).collect(),
+            draft,
+            error: None,
+        }
+    }
+
+    /// Apply an edit. `Save` and `Cancel` are handled by the caller.
+    pub fn update(&mut self, message: SaveWorkflowMessage) {
+        self.error = None;
+        match message {
+            SaveWorkflowMessage::NameChanged(name) => self.draft.name = name,
+            SaveWorkflowMessage::DescriptionChanged(description) => self.draft.description = description,
+            SaveWorkflowMessage::TagsChanged(tags) => {
+                self.draft.set_tags(&tags);
+                self.tags_input = tags;
+            }
+            SaveWorkflowMessage::ParameterToggled(index, enabled) => {
+                if let Err(e) = self.draft.set_parameter_enabled(index, enabled) {
+                    self.error = Some(e.to_string());
+                }
+            }
+            SaveWorkflowMessage::ParameterRenamed(index, name) => {
+                if let Err(e) = self.draft.rename_parameter(index, &name) {
+                    self.error = Some(e.to_string());
+                }
+                if let Some(input) = self.parameter_names.get_mut(index) {
+                    *input = name;
+                }
+            }
+            SaveWorkflowMessage::Save | SaveWorkflowMessage::Cancel => {}
+        }
+    }
+
+    /// Write the draft into `dir`. Refuses while an argument name as typed
+    /// has not been accepted.
+    pub fn save(&mut self, dir: Option<&Path>) -> Result<PathBuf, WorkflowDraftError> {
+        let result = match (self.pending_rename(), dir) {
+            (Some(e), _) => Err(e),
+            (None, None) => Err(WorkflowDraftError::NoWorkflowDirectory),
+            (None, Some(dir)) => self.draft.save(dir),
+        };
+        self.error = result.as_ref().err().map(ToString::to_string);
+        result
+    }
+
+    fn pending_rename(&self) -> Option<WorkflowDraftError> {
+        self.parameter_names
+            .iter()
+            .zip(&self.draft.parameters)
+            .find(|(input, param)| input.trim() != param.name)
+            .map(|(input, _)| {
+                if self.draft.parameters.iter().any(|param| param.name == input.trim()) {
+                    WorkflowDraftError::DuplicateParameterName(input.trim().to_string())
+                } else {
+                    WorkflowDraftError::InvalidParameterName(input.trim().to_string())
+                }
+            })
+    }
+
+    pub fn view(&self, font: Font, font_size: u16) -> Element<'_, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.7);
+        let label = |value: &str| text(value.to_string()).font(font).size(font_size - 2).style(muted);
+        let edit = |f: fn(String) -> SaveWorkflowMessage| move |value| Message::SaveWorkflow(f(value));
+
+        let parameters: Element<Message> = if self.draft.parameters.is_empty() {
+            label("No paths, branches, numbers or URLs found in the command").into()
+        } else {
+            self.draft
+                .parameters
+                .iter()
+                .enumerate()
+                .fold(column![].spacing(6), |col, (index, param)| {
+                    col.push(
+                        row![
+                            checkbox("", param.enabled).on_toggle(move |enabled| {
+                                Message::SaveWorkflow(SaveWorkflowMessage::ParameterToggled(index, enabled))
+                            }),
+                            text_input("name", &self.parameter_names[index])
+                                .on_input(move |name| {
+                                    Message::SaveWorkflow(SaveWorkflowMessage::ParameterRenamed(index, name))
+                                })
+                                .font(font)
+                                .size(font_size - 2)
+                                .width(Length::Fixed(160.0)),
+                            text(format!("{} = {}", param.kind.placeholder(), param.value))
+                                .font(font)
+                                .size(font_size - 2)
+                                .style(muted),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                })
+                .into()
+        };
+
+        let error: Element<Message> = match &self.error {
+            Some(error) => text(error).font(font).size(font_size - 2).style(Color::from_rgb(0.9, 0.4, 0.4)).into(),
+            None => Space::with_height(0).into(),
+        };
+
+        let content = column![
+            text("Save as workflow").font(font).size(font_size + 4),
+            label("Command"),
+            text(self.draft.command()).font(Font::MONOSPACE).size(font_size),
+            label("Name"),
+            text_input("Workflow name", &self.draft.name)
+                .on_input(edit(SaveWorkflowMessage::NameChanged))
+                .font(font)
+                .size(font_size),
+            label("Description"),
+            text_input("What the workflow does", &self.draft.description)
+                .on_input(edit(SaveWorkflowMessage::DescriptionChanged))
+                .font(font)
+                .size(font_size),
+            label("Tags (comma separated)"),
+            text_input("git, cleanup", &self.tags_input)
+                .on_input(edit(SaveWorkflowMessage::TagsChanged))
+                .font(font)
+                .size(font_size),
+            label("Arguments"),
+            parameters,
+            error,
+            row![
+                Space::with_width(Length::Fill),
+                button(text("Cancel").font(font).size(font_size))
+                    .on_press(Message::SaveWorkflow(SaveWorkflowMessage::Cancel))
+                    .style(theme::Button::Secondary),
+                button(text("Save").font(font).size(font_size))
+                    .on_press(Message::SaveWorkflow(SaveWorkflowMessage::Save))
+                    .style(theme::Button::Primary),
+            ]
+            .spacing(8),
+        ]
+        .spacing(8)
+        .padding(20)
+        .max_width(720);
+
+        container(scrollable(content))
+            .style(theme::Container::Custom(Box::new(DialogStyle)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+}
+
+pub(crate) struct DialogStyle;
+
+impl container::StyleSheet for DialogStyle {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(Background::Color(Color::from_rgb(0.12, 0.12, 0.15))),
+            border: Border {
+                color: Color::from_rgb(0.25, 0.25, 0.3),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            text_color: Some(Color::from_rgb(0.92, 0.92, 0.95)),
+            ..Default::default()
+        }
+    }
+}
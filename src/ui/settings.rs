@@ -1,12 +1,14 @@
 use iced::widget::{
     button, column, row, text, slider, checkbox, radio, text_input, container, 
-    scrollable, Space
+    scrollable, pick_list, Space
 };
 use iced::{Alignment, Element, Length, alignment};
 use crate::Message;
 use iced::theme;
 use serde::{Deserialize, Serialize};
 use crate::model::pane::SplitLayout;
+use crate::executor::command_guard::GuardConfig;
+use crate::ui::keybinding_editor::KeybindingEditor;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FontWeight {
@@ -42,6 +44,7 @@ pub enum SettingsTab {
     Window,
     Icon,
     Input,
+    Keybindings,
     History,
     Features,
     Advanced,
@@ -55,6 +58,7 @@ impl std::fmt::Display for SettingsTab {
             SettingsTab::Window => write!(f, "Window"),
             SettingsTab::Icon => write!(f, "Icon"),
             SettingsTab::Input => write!(f, "Input"),
+            SettingsTab::Keybindings => write!(f, "Keybindings"),
             SettingsTab::History => write!(f, "History"),
             SettingsTab::Features => write!(f, "Features"),
             SettingsTab::Advanced => write!(f, "Advanced"),
@@ -92,6 +96,13 @@ pub struct SettingsState {
     
     // Input
     pub input_type: InputType,
+    /// Edit the command input with vim keys
+    #[serde(default)]
+    pub vim_mode: bool,
+    /// Name of the keyset in `keysets/`; `emacs` also gives the command
+    /// input readline editing
+    #[serde(default = "default_keyset")]
+    pub keyset: String,
     
     // History
     pub history_enabled: bool,
@@ -109,6 +120,10 @@ pub struct SettingsState {
     pub enable_autocomplete: bool,
     pub enable_ai_command_search: bool,
     pub enable_smart_suggestions: bool,
+
+    // Safety checks run before a command executes
+    #[serde(default)]
+    pub command_guard: GuardConfig,
     
     // Layout Management
     pub pane_layout: Option<SplitLayout>,
@@ -142,6 +157,8 @@ pub enum SettingsMessage {
     WindowBlurChanged(u8),
     // Input settings
     InputTypeChanged(InputType),
+    VimModeChanged(bool),
+    KeysetChanged(String),
     // History settings
     HistoryEnabledChanged(bool),
     MaxHistoryEntriesChanged(String),
@@ -161,6 +178,7 @@ pub enum SettingsMessage {
     AutocompleteChanged(bool),
     AiCommandSearchChanged(bool),
     SmartSuggestionsChanged(bool),
+    CommandGuardChanged(bool),
     // Layout settings
     AutoSaveLayoutChanged(bool),
     RestoreLayoutOnStartupChanged(bool),
@@ -173,6 +191,13 @@ pub enum SettingsMessage {
     CloseSettings,
 }
 
+/// The keyset used until the user picks another
+pub const DEFAULT_KEYSET: &str = "default-warp-keybindings";
+
+fn default_keyset() -> String {
+    DEFAULT_KEYSET.to_string()
+}
+
 impl Default for SettingsState {
     fn default() -> Self {
         Self {
@@ -203,6 +228,8 @@ impl Default for SettingsState {
             
             // Input
             input_type: InputType::Universal,
+            vim_mode: false,
+            keyset: default_keyset(),
             
             // History
             history_enabled: true,
@@ -226,11 +253,17 @@ impl Default for SettingsState {
             auto_save_layout: true,
             restore_layout_on_startup: true,
             layout_autosave_interval: 30,
+            command_guard: GuardConfig::default(),
         }
     }
 }
 
-pub fn settings_view(state: &SettingsState, errors: &Vec<String>) -> Element<'static, Message> {
+pub fn settings_view(
+    state: &SettingsState,
+    errors: &[String],
+    keybindings: &KeybindingEditor,
+    keysets: Vec<String>,
+) -> Element<'static, Message> {
     // Error banner
     let error_banner: Element<Message> = if !errors.is_empty() {
         container(
@@ -268,6 +301,7 @@ pub fn settings_view(state: &SettingsState, errors: &Vec<String>) -> Element<'st
         SettingsTab::Window,
         SettingsTab::Icon,
         SettingsTab::Input,
+        SettingsTab::Keybindings,
         SettingsTab::History,
         SettingsTab::Features,
         SettingsTab::Advanced,
@@ -300,7 +334,8 @@ pub fn settings_view(state: &SettingsState, errors: &Vec<String>) -> Element<'st
         SettingsTab::Appearance => appearance_tab(state),
         SettingsTab::Window => window_tab(state),
         SettingsTab::Icon => icon_tab(state),
-        SettingsTab::Input => input_tab(state),
+        SettingsTab::Input => input_tab(state, keysets),
+        SettingsTab::Keybindings => keybindings.view(),
         SettingsTab::History => history_tab(state),
         SettingsTab::Features => features_tab(state),
         SettingsTab::Advanced => advanced_tab(state),
@@ -568,7 +603,7 @@ fn icon_tab(_state: &SettingsState) -> Element<'static, Message> {
     .into()
 }
 
-fn input_tab(state: &SettingsState) -> Element<'static, Message> {
+fn input_tab(state: &SettingsState, keysets: Vec<String>) -> Element<'static, Message> {
     column![
         text("Input").size(24),
         Space::with_height(20),
@@ -578,6 +613,17 @@ fn input_tab(state: &SettingsState) -> Element<'static, Message> {
             radio("Universal", InputType::Universal, Some(state.input_type), |_| Message::InputChanged("input_universal".into())),
             radio("Classic", InputType::Classic, Some(state.input_type), |_| Message::InputChanged("input_classic".into())),
         ].spacing(20),
+        Space::with_height(20),
+        checkbox("Edit commands with vim keys", state.vim_mode)
+            .on_toggle(|value| Message::SettingsChanged(SettingsMessage::VimModeChanged(value)))
+            .text_size(13),
+        Space::with_height(10),
+        text("Keyset").size(14),
+        text("The emacs keyset edits commands with readline keys when vim keys are off").size(12),
+        pick_list(keysets, Some(state.keyset.clone()), |keyset| {
+            Message::SettingsChanged(SettingsMessage::KeysetChanged(keyset))
+        })
+        .text_size(13),
     ]
     .spacing(12)
     .align_items(Alignment::Start)
@@ -605,6 +651,9 @@ fn features_tab(state: &SettingsState) -> Element<'static, Message> {
                 checkbox("Smart suggestions", state.enable_smart_suggestions)
                     .on_toggle(|_| Message::InputChanged("smart_suggestions".into()))
                     .text_size(13),
+                checkbox("Check commands for dangerous patterns before running", state.command_guard.enabled)
+                    .on_toggle(|value| Message::SettingsChanged(SettingsMessage::CommandGuardChanged(value)))
+                    .text_size(13),
             ]
         )
         .padding([16, 20])
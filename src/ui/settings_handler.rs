@@ -67,6 +67,8 @@ impl SettingsHandler {
 
             // Input settings
             SettingsMessage::InputTypeChanged(input_type) => state.input_type = input_type,
+            SettingsMessage::VimModeChanged(value) => state.vim_mode = value,
+            SettingsMessage::KeysetChanged(keyset) => state.keyset = keyset,
 
             // History settings
             SettingsMessage::HistoryEnabledChanged(value) => state.history_enabled = value,
@@ -112,6 +114,7 @@ impl SettingsHandler {
             SettingsMessage::AutocompleteChanged(value) => state.enable_autocomplete = value,
             SettingsMessage::AiCommandSearchChanged(value) => state.enable_ai_command_search = value,
             SettingsMessage::SmartSuggestionsChanged(value) => state.enable_smart_suggestions = value,
+            SettingsMessage::CommandGuardChanged(value) => state.command_guard.enabled = value,
 
             // Layout settings
             SettingsMessage::AutoSaveLayoutChanged(value) => state.auto_save_layout = value,
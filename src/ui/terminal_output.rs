@@ -0,0 +1,132 @@
+//! Renders command output, coloring ANSI-styled spans with the theme's
+//! palette. Foregrounds go through [`AppTheme::readable_color`], so themes
+//! with a minimum contrast never draw unreadable text. File paths in the
+//! output can be clicked to open them in an editor pane.
+
+use std::path::PathBuf;
+
+use iced::widget::{button, container, text, Column, Row};
+use iced::{font, theme, Background, Color, Element, Font};
+
+use crate::model::theme::AppTheme;
+use crate::utils::ansi::{self, AnsiColor, AnsiSpan};
+use crate::utils::file_links::{find_file_links, FileLink};
+use crate::Message;
+
+/// Command output in the theme's foreground, with any SGR colors taken
+/// from the theme's ANSI palette
+pub fn view_output<'a>(output: &str, app_theme: &AppTheme, font: Font, size: u16) -> Element<'a, Message> {
+    if !ansi::has_escapes(output) {
+        let color = app_theme.readable_color(app_theme.text_color(), app_theme.background_color());
+        if output.lines().all(|line| find_file_links(line).is_empty()) {
+            return text(output.to_string()).font(font).size(size).style(color).into();
+        }
+        return output
+            .lines()
+            .fold(Column::new(), |col, line| {
+                // Keep empty lines one line high
+                let line = if line.is_empty() { " " } else { line };
+                col.push(view_linked_text(line, color, app_theme, |segment, color| {
+                    text(segment.to_string()).font(font).size(size).style(color).into()
+                }))
+            })
+            .into();
+    }
+
+    ansi::parse_ansi(output)
+        .into_iter()
+        .fold(Column::new(), |col, line| {
+            // Keep empty lines one line high
+            if line.is_empty() {
+                return col.push(text(" ").font(font).size(size));
+            }
+            col.push(
+                line.into_iter()
+                    .fold(Row::new(), |row, span| row.push(view_span(span, app_theme, font, size))),
+            )
+        })
+        .into()
+}
+
+fn view_span<'a>(span: AnsiSpan, app_theme: &AppTheme, font: Font, size: u16) -> Element<'a, Message> {
+    let style = span.style;
+    let resolve = |color: AnsiColor| match color {
+        // Bold text in one of the eight basic colors uses the bright variant
+        AnsiColor::Indexed(index) if style.bold && index < 8 => app_theme.ansi_palette().color(index + 8),
+        AnsiColor::Indexed(index) => app_theme.ansi_palette().color(index),
+        AnsiColor::Rgb(r, g, b) => Color::from_rgb8(r, g, b),
+    };
+    let mut foreground = style.foreground.map_or(app_theme.text_color(), resolve);
+    let mut background = style.background.map(resolve);
+    if style.inverse {
+        let swapped = background.unwrap_or(app_theme.background_color());
+        background = Some(foreground);
+        foreground = swapped;
+    }
+    foreground = app_theme.readable_color(foreground, background.unwrap_or(app_theme.background_color()));
+    if style.dim {
+        foreground.a *= 0.6;
+    }
+
+    let span_font = Font {
+        weight: if style.bold { font::Weight::Bold } else { font.weight },
+        style: if style.italic { font::Style::Italic } else { font.style },
+        ..font
+    };
+    let label = view_linked_text(&span.text, foreground, app_theme, |segment, color| {
+        text(segment.to_string()).font(span_font).size(size).style(color).into()
+    });
+    match background {
+        Some(color) => container(label)
+            .style(theme::Container::Custom(Box::new(SpanBackground(color))))
+            .into(),
+        None => label,
+    }
+}
+
+/// `line` drawn by `view_text` in `color`, with the file paths in it made
+/// into buttons that open the file
+fn view_linked_text<'a>(
+    line: &str,
+    color: Color,
+    app_theme: &AppTheme,
+    view_text: impl Fn(&str, Color) -> Element<'a, Message>,
+) -> Element<'a, Message> {
+    let links = find_file_links(line);
+    if links.is_empty() {
+        return view_text(line, color);
+    }
+
+    let link_color = app_theme.readable_color(app_theme.terminal_color(4, true), app_theme.background_color());
+    let mut row = Row::new();
+    let mut end = 0;
+    for FileLink { range, path, line: line_number } in links {
+        if range.start > end {
+            row = row.push(view_text(&line[end..range.start], color));
+        }
+        row = row.push(
+            button(view_text(&line[range.clone()], link_color))
+                .padding(0)
+                .style(theme::Button::Text)
+                .on_press(Message::OpenInEditor(PathBuf::from(path), line_number)),
+        );
+        end = range.end;
+    }
+    if end < line.len() {
+        row = row.push(view_text(&line[end..], color));
+    }
+    row.into()
+}
+
+struct SpanBackground(Color);
+
+impl container::StyleSheet for SpanBackground {
+    type Style = iced::Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(Background::Color(self.0)),
+            ..Default::default()
+        }
+    }
+}
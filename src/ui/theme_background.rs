@@ -0,0 +1,193 @@
+//! The theme's background drawn behind the whole window.
+//!
+//! [`ThemeBackground`] fills its bounds with the theme's background color or
+//! gradient and, if the theme has one, draws its background image over the
+//! fill. The image covers the window, centred and cropped, and its opacity
+//! comes from veiling it with the background fill again.
+
+use iced::advanced::image::{self as image_renderer, FilterMethod};
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::widget::{tree, Operation, Tree};
+use iced::advanced::{mouse, overlay, renderer, Clipboard, Shell, Widget};
+use iced::event::{self, Event};
+use iced::widget::image::Handle;
+use iced::{Background, Element, Length, Radians, Rectangle, Size, Theme, Vector};
+use std::f32::consts::PI;
+
+use crate::model::theme::AppTheme;
+use crate::Message;
+
+type Renderer = iced::Renderer;
+
+pub struct ThemeBackground<'a> {
+    content: Element<'a, Message>,
+    fill: Background,
+    /// The image and the fill drawn over it to fade it
+    image: Option<(Handle, Background)>,
+}
+
+impl<'a> ThemeBackground<'a> {
+    pub fn new(content: impl Into<Element<'a, Message>>, theme: &AppTheme) -> Self {
+        let image = theme.background_image().map(|image| {
+            let veil = theme.background().with_alpha(1.0 - image.opacity.clamp(0.0, 1.0));
+            (Handle::from_path(&image.path), veil.to_background(Radians(PI)))
+        });
+        Self {
+            content: content.into(),
+            fill: theme.background_fill(),
+            image,
+        }
+    }
+}
+
+/// Where an image of `image` size is drawn to cover `bounds`: scaled to the
+/// smallest size that fills them and centred, so the overflow is cropped
+/// evenly on both sides
+fn cover(image: Size, bounds: Rectangle) -> Rectangle {
+    if image.width <= 0.0 || image.height <= 0.0 {
+        return bounds;
+    }
+    let scale = (bounds.width / image.width).max(bounds.height / image.height);
+    let size = Size::new(image.width * scale, image.height * scale);
+    Rectangle {
+        x: bounds.x + (bounds.width - size.width) / 2.0,
+        y: bounds.y + (bounds.height - size.height) / 2.0,
+        width: size.width,
+        height: size.height,
+    }
+}
+
+impl<'a> Widget<Message, Theme, Renderer> for ThemeBackground<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::stateless()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.content]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(&self, tree: &mut Tree, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        self.content.as_widget().layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content
+            .as_widget()
+            .mouse_interaction(&tree.children[0], layout, cursor, viewport, renderer)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let quad = renderer::Quad {
+            bounds,
+            ..renderer::Quad::default()
+        };
+        renderer::Renderer::fill_quad(renderer, quad, self.fill);
+        if let Some((handle, veil)) = &self.image {
+            let dimensions = image_renderer::Renderer::dimensions(renderer, handle);
+            let image = Size::new(dimensions.width as f32, dimensions.height as f32);
+            renderer::Renderer::with_layer(renderer, bounds, |renderer| {
+                image_renderer::Renderer::draw(renderer, handle.clone(), FilterMethod::Linear, cover(image, bounds));
+            });
+            renderer::Renderer::fill_quad(renderer, quad, *veil);
+        }
+        self.content
+            .as_widget()
+            .draw(&tree.children[0], renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
+
+impl<'a> From<ThemeBackground<'a>> for Element<'a, Message> {
+    fn from(background: ThemeBackground<'a>) -> Self {
+        Element::new(background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Point;
+
+    #[test]
+    fn test_cover_fills_the_bounds_and_crops_evenly() {
+        let bounds = Rectangle::new(Point::new(10.0, 20.0), Size::new(800.0, 600.0));
+        // Wider than the window: full height, cropped left and right
+        let wide = cover(Size::new(1600.0, 600.0), bounds);
+        assert_eq!(wide, Rectangle::new(Point::new(-390.0, 20.0), Size::new(1600.0, 600.0)));
+        // Small and tall: scaled up to the full width
+        let tall = cover(Size::new(100.0, 200.0), bounds);
+        assert_eq!(tall, Rectangle::new(Point::new(10.0, -480.0), Size::new(800.0, 1600.0)));
+        assert_eq!(cover(Size::ZERO, bounds), bounds);
+    }
+}
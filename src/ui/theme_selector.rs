@@ -2,7 +2,12 @@ use crate::ui::modern_components::WarpUI;
 use crate::ui::file_picker;
 use iced::widget::{button, column, container, row, text, Space, slider};
 use iced::{theme, Alignment, Background, Border, Color, Element, Length, Shadow, Vector};
-use std::path::PathBuf;
+use crate::model::theme::{AppTheme, ThemeFill};
+use crate::model::theme_import::{export_theme, import_theme_file, ThemeFormat};
+use crate::model::theme_loader::{display_name, ThemeLoader};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 pub struct ThemeSelector {
     pub available_themes: Vec<ThemeInfo>,
@@ -16,6 +21,10 @@ pub struct ThemeSelector {
     pub background_opacity: f32,
     pub background_blur: f32,
     pub show_background_settings: bool,
+    /// Where imported themes are written as Warp YAML; `None` keeps them in
+    /// memory only
+    pub import_dir: Option<PathBuf>,
+    themes: BTreeMap<String, AppTheme>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +39,44 @@ pub struct ThemeInfo {
     pub last_used: Option<u64>,
 }
 
+impl ThemeInfo {
+    /// Card for a loaded theme, previewed with its own colors
+    pub fn from_theme(theme: &AppTheme) -> Self {
+        let category = if theme.name().contains("high_contrast") {
+            ThemeCategory::HighContrast
+        } else if theme.background_image().is_some() || matches!(theme.background(), ThemeFill::Gradient { .. }) {
+            ThemeCategory::Special
+        } else if theme.is_dark() {
+            ThemeCategory::Dark
+        } else {
+            ThemeCategory::Light
+        };
+        let description = match (&category, theme.is_dark()) {
+            (ThemeCategory::Special, _) if theme.background_image().is_some() => "Theme with a background image",
+            (ThemeCategory::Special, _) => "Theme with a gradient background",
+            (_, true) => "Dark theme",
+            (_, false) => "Light theme",
+        };
+
+        Self {
+            name: theme.name().to_string(),
+            display_name: display_name(theme.name()),
+            category,
+            description: description.to_string(),
+            author: None,
+            colors: ThemeColorPreview {
+                background: theme.background_color(),
+                foreground: theme.text_color(),
+                accent: theme.accent_color(),
+                primary: theme.terminal_color(4, false),
+                secondary: theme.terminal_color(2, false),
+            },
+            is_favorite: false,
+            last_used: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ThemeCategory {
     All,
@@ -65,6 +112,8 @@ impl ThemeSelector {
             background_opacity: 0.8,
             background_blur: 0.0,
             show_background_settings: false,
+            import_dir: ThemeLoader::user_themes_dir(),
+            themes: BTreeMap::new(),
         };
 
         selector.load_default_themes();
@@ -72,85 +121,15 @@ impl ThemeSelector {
     }
 
     fn load_default_themes(&mut self) {
-        // Add some default themes for demonstration
-        let default_themes = vec![
-            ("dark_default", "Dark Default", ThemeCategory::Dark, "Default dark theme"),
-            ("light_default", "Light Default", ThemeCategory::Light, "Default light theme"),
-            ("high_contrast", "High Contrast", ThemeCategory::HighContrast, "High contrast theme"),
-            ("dracula", "Dracula", ThemeCategory::Dark, "Popular dark theme"),
-            ("monokai", "Monokai", ThemeCategory::Dark, "Classic dark theme"),
-            ("github_light", "GitHub Light", ThemeCategory::Light, "GitHub's light theme"),
-            ("solarized_dark", "Solarized Dark", ThemeCategory::Dark, "Solarized dark variant"),
-            ("nord", "Nord", ThemeCategory::Dark, "Arctic blue theme"),
-        ];
-
-        for (name, display_name, category, description) in default_themes {
-            let colors = self.generate_sample_colors(&category);
-            
-            self.available_themes.push(ThemeInfo {
-                name: name.to_string(),
-                display_name: display_name.to_string(),
-                category,
-                description: description.to_string(),
-                author: Some("Warp Team".to_string()),
-                colors,
-                is_favorite: false,
-                last_used: None,
-            });
-        }
+        let mut loader = ThemeLoader::with_default_dirs();
+        loader.load();
+        self.available_themes = loader.themes().values().map(ThemeInfo::from_theme).collect();
+        self.themes = loader.themes().clone();
 
         // Sort themes by name
         self.available_themes.sort_by(|a, b| a.display_name.cmp(&b.display_name));
     }
 
-    fn generate_sample_colors(&self, category: &ThemeCategory) -> ThemeColorPreview {
-        match category {
-            ThemeCategory::Dark => ThemeColorPreview {
-                background: Color::from_rgb(0.1, 0.1, 0.12),
-                foreground: Color::from_rgb(0.9, 0.9, 0.9),
-                accent: Color::from_rgb(0.2, 0.6, 1.0),
-                primary: Color::from_rgb(0.3, 0.7, 0.9),
-                secondary: Color::from_rgb(0.4, 0.8, 0.6),
-            },
-            ThemeCategory::Light => ThemeColorPreview {
-                background: Color::from_rgb(0.98, 0.98, 0.98),
-                foreground: Color::from_rgb(0.2, 0.2, 0.2),
-                accent: Color::from_rgb(0.0, 0.4, 0.8),
-                primary: Color::from_rgb(0.1, 0.5, 0.8),
-                secondary: Color::from_rgb(0.2, 0.6, 0.4),
-            },
-            ThemeCategory::HighContrast => ThemeColorPreview {
-                background: Color::from_rgb(0.0, 0.0, 0.0),
-                foreground: Color::from_rgb(1.0, 1.0, 1.0),
-                accent: Color::from_rgb(1.0, 1.0, 0.0),
-                primary: Color::from_rgb(0.0, 1.0, 1.0),
-                secondary: Color::from_rgb(1.0, 0.0, 1.0),
-            },
-            _ => ThemeColorPreview {
-                background: Color::from_rgb(0.15, 0.15, 0.2),
-                foreground: Color::from_rgb(0.85, 0.85, 0.9),
-                accent: Color::from_rgb(0.4, 0.6, 1.0),
-                primary: Color::from_rgb(0.5, 0.7, 0.9),
-                secondary: Color::from_rgb(0.6, 0.8, 0.7),
-            },
-        }
-    }
-
-
-    fn format_display_name(&self, name: &str) -> String {
-        name.replace("_", " ")
-            .split_whitespace()
-            .map(|word| {
-                let mut chars = word.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(" ")
-    }
-
     pub fn set_search_query(&mut self, query: String) {
         self.search_query = query;
     }
@@ -210,11 +189,19 @@ impl ThemeSelector {
         self.show_background_settings = !self.show_background_settings;
     }
 
-    pub async fn load_custom_theme_async(&mut self) -> Result<(), String> {
-        match file_picker::FilePicker::pick_theme_file().await {
-            Some(theme_path) => {
-                self.load_custom_theme(theme_path)
-            }
+    pub fn with_import_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.import_dir = dir;
+        self
+    }
+
+    /// The loaded theme named `theme_name`
+    pub fn theme(&self, theme_name: &str) -> Option<&AppTheme> {
+        self.themes.get(theme_name)
+    }
+
+    pub async fn load_custom_theme_async(&mut self) -> Result<Vec<String>, String> {
+        match file_picker::FilePicker::pick_theme_files().await {
+            Some(theme_paths) => self.load_custom_themes(theme_paths),
             None => Err("No theme file selected".to_string())
         }
     }
@@ -229,47 +216,80 @@ impl ThemeSelector {
         }
     }
 
-    pub async fn save_current_theme_async(&self, theme_name: &str) -> Result<(), String> {
-        match file_picker::FilePicker::save_theme_file(Some(theme_name)).await {
-            Some(save_path) => {
-                // Here you would implement the actual theme saving logic
-                // For now, we'll just return success
-                println!("Theme would be saved to: {:?}", save_path);
-                Ok(())
-            }
+    pub async fn save_current_theme_async(&self, theme_name: &str, format: ThemeFormat) -> Result<(), String> {
+        match file_picker::FilePicker::save_theme_file(Some(theme_name), format).await {
+            Some(save_path) => self.export_theme(theme_name, format, &save_path),
             None => Err("Theme save cancelled".to_string())
         }
     }
 
-    pub fn load_custom_theme(&mut self, theme_path: PathBuf) -> Result<(), String> {
-        // This would integrate with the theme loader
-        // For now, we'll create a placeholder custom theme
-        let theme_name = theme_path.file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("custom_theme")
-            .to_string();
-        
-        let colors = self.generate_sample_colors(&ThemeCategory::Custom);
-        
+    /// Write the loaded theme `theme_name` to `path` in `format`
+    pub fn export_theme(&self, theme_name: &str, format: ThemeFormat, path: &Path) -> Result<(), String> {
+        let theme = self.theme(theme_name).ok_or_else(|| format!("Unknown theme: {}", theme_name))?;
+        fs::write(path, export_theme(theme, format)).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+    }
+
+    /// Import every theme in `theme_path`, which may be a Warp theme, an
+    /// iTerm2 `.itermcolors` file, an Alacritty config, Windows Terminal
+    /// schemes or a base16 scheme. Themes from other terminals are also
+    /// written to the import directory as Warp YAML. Returns the names of
+    /// the imported themes.
+    pub fn load_custom_theme(&mut self, theme_path: PathBuf) -> Result<Vec<String>, String> {
+        let source = fs::read_to_string(&theme_path).map_err(|e| format!("Failed to read {}: {}", theme_path.display(), e))?;
+        let format = ThemeFormat::detect(&theme_path, &source);
+        let themes = import_theme_file(&theme_path).map_err(|e| e.to_string())?;
+
+        let mut names = Vec::new();
+        for theme in themes {
+            if format != Some(ThemeFormat::Warp) {
+                if let Some(dir) = &self.import_dir {
+                    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+                    let path = dir.join(format!("{}.yaml", theme.name()));
+                    fs::write(&path, export_theme(&theme, ThemeFormat::Warp))
+                        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+                }
+            }
+            names.push(theme.name().to_string());
+            self.add_custom_theme(theme);
+        }
+
+        // Re-sort themes
+        self.available_themes.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+
+        Ok(names)
+    }
+
+    /// Import several files, keeping the themes that load and reporting
+    /// every file that does not
+    pub fn load_custom_themes(&mut self, theme_paths: Vec<PathBuf>) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
+        let mut errors = Vec::new();
+        for path in theme_paths {
+            match self.load_custom_theme(path) {
+                Ok(loaded) => names.extend(loaded),
+                Err(error) => errors.push(error),
+            }
+        }
+        if errors.is_empty() {
+            Ok(names)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    fn add_custom_theme(&mut self, theme: AppTheme) {
+        let theme_name = theme.name().to_string();
         let custom_theme = ThemeInfo {
-            name: theme_name.clone(),
-            display_name: self.format_display_name(&theme_name),
             category: ThemeCategory::Custom,
             description: "Custom theme loaded from file".to_string(),
             author: Some("User".to_string()),
-            colors,
-            is_favorite: false,
-            last_used: None,
+            ..ThemeInfo::from_theme(&theme)
         };
-        
+
         // Remove existing custom theme with same name
         self.available_themes.retain(|t| t.name != theme_name);
         self.available_themes.push(custom_theme);
-        
-        // Re-sort themes
-        self.available_themes.sort_by(|a, b| a.display_name.cmp(&b.display_name));
-        
-        Ok(())
+        self.themes.insert(theme_name, theme);
     }
 
     fn get_filtered_themes(&self) -> Vec<&ThemeInfo> {
@@ -838,3 +858,28 @@ impl Default for ThemeSelector {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_custom_theme_imports_and_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let import_dir = dir.path().join("themes");
+        let mut selector = ThemeSelector::new().with_import_dir(Some(import_dir.clone()));
+        let scheme = export_theme(selector.theme("dracula").unwrap(), ThemeFormat::WindowsTerminal).replace("Dracula", "Dracula Copy");
+        let scheme_path = dir.path().join("dracula.json");
+        fs::write(&scheme_path, scheme).unwrap();
+
+        assert_eq!(selector.load_custom_theme(scheme_path).unwrap(), vec!["dracula_copy".to_string()]);
+        assert!(selector.available_themes.iter().any(|t| t.name == "dracula_copy" && t.category == ThemeCategory::Custom));
+        assert!(import_dir.join("dracula_copy.yaml").exists());
+
+        let exported = dir.path().join("dracula_copy.itermcolors");
+        selector.export_theme("dracula_copy", ThemeFormat::ITerm2, &exported).unwrap();
+        let errors = selector.load_custom_themes(vec![exported, dir.path().join("missing.toml")]).unwrap_err();
+        assert!(errors.contains("missing.toml"));
+        assert!(selector.theme("dracula_copy").is_some());
+    }
+}
@@ -0,0 +1,292 @@
+//! Workflow arguments dialog.
+//!
+//! Asks for the arguments of a workflow picked from the palette one at a
+//! time, dependencies first. Each argument offers the values it suggests,
+//! and a value is only accepted once it passes the argument's type and
+//! validation pattern.
+
+use std::future::Future;
+use std::path::PathBuf;
+
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Space};
+use iced::{theme, Alignment, Color, Element, Font, Length};
+use warp_workflows_types::{Argument, ArgumentType, Workflow};
+
+use crate::model::workflow_params::WorkflowParameterHandler;
+use crate::ui::save_workflow::DialogStyle;
+use crate::Message;
+
+/// Suggestions shown for one argument
+const MAX_SUGGESTIONS: usize = 50;
+
+#[derive(Debug, Clone)]
+pub enum WorkflowArgumentsMessage {
+    InputChanged(String),
+    /// Use a suggested value and move on
+    SuggestionPicked(String),
+    SuggestionsLoaded { name: String, result: Result<Vec<String>, String> },
+    Submit,
+    Back,
+    Cancel,
+}
+
+/// State of the dialog while arguments are being filled in
+#[derive(Debug, Clone)]
+pub struct WorkflowArgumentsDialog {
+    pub handler: WorkflowParameterHandler,
+    /// Value of the current argument as typed
+    pub input: String,
+    pub suggestions: Vec<String>,
+    pub loading_suggestions: bool,
+    pub error: Option<String>,
+}
+
+impl WorkflowArgumentsDialog {
+    /// Ask for the arguments of `workflow`, running suggestion commands in
+    /// `working_dir`
+    pub fn new(workflow: Workflow, working_dir: impl Into<PathBuf>) -> Self {
+        let mut handler = WorkflowParameterHandler::new().with_working_dir(working_dir);
+        handler.set_workflow(workflow);
+        let mut dialog = Self {
+            handler,
+            input: String::new(),
+            suggestions: Vec::new(),
+            loading_suggestions: false,
+            error: None,
+        };
+        dialog.start_argument();
+        dialog
+    }
+
+    pub fn workflow(&self) -> Option<&Workflow> {
+        self.handler.workflow.as_ref()
+    }
+
+    /// The argument being asked for
+    pub fn current(&self) -> Option<&Argument> {
+        self.handler.get_current_parameter()
+    }
+
+    /// Every argument has a valid value
+    pub fn is_complete(&self) -> bool {
+        self.current().is_none() && self.handler.all_parameters_filled()
+    }
+
+    /// Apply a message. `Cancel` is handled by the caller.
+    pub fn update(&mut self, message: WorkflowArgumentsMessage) {
+        match message {
+            WorkflowArgumentsMessage::InputChanged(value) => {
+                self.input = value;
+                self.error = None;
+            }
+            WorkflowArgumentsMessage::SuggestionPicked(value) => {
+                self.input = value;
+                self.submit();
+            }
+            WorkflowArgumentsMessage::SuggestionsLoaded { name, result } => {
+                // Answers for an argument the user already moved past are dropped
+                if self.current().is_some_and(|arg| arg.name == name) {
+                    self.loading_suggestions = false;
+                    match result {
+                        Ok(mut suggestions) => {
+                            suggestions.truncate(MAX_SUGGESTIONS);
+                            self.suggestions = suggestions;
+                        }
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+            }
+            WorkflowArgumentsMessage::Submit => self.submit(),
+            WorkflowArgumentsMessage::Back => {
+                self.handler.previous_parameter();
+                self.start_argument();
+            }
+            WorkflowArgumentsMessage::Cancel => {}
+        }
+    }
+
+    /// Work out the suggestions for the current argument on a blocking
+    /// worker, since they may run a shell command
+    pub fn load_suggestions(&mut self) -> Option<impl Future<Output = WorkflowArgumentsMessage> + Send + 'static> {
+        let name = self.current()?.name.clone();
+        let handler = self.handler.clone();
+        self.loading_suggestions = true;
+        Some(async move {
+            let argument = name.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                handler.suggestions_for(&argument).map_err(|e| e.to_string())
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+            WorkflowArgumentsMessage::SuggestionsLoaded { name, result }
+        })
+    }
+
+    fn submit(&mut self) {
+        match self.handler.set_current_parameter_value(self.input.clone()) {
+            Ok(()) => self.start_argument(),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    /// Prefill the input for the current argument with its value so far or
+    /// its default
+    fn start_argument(&mut self) {
+        self.input = self
+            .current()
+            .and_then(|arg| {
+                self.handler
+                    .parameter_values
+                    .get(&arg.name)
+                    .cloned()
+                    .or_else(|| self.handler.default_for(&arg.name))
+            })
+            .unwrap_or_default();
+        self.suggestions.clear();
+        self.loading_suggestions = false;
+        self.error = None;
+    }
+
+    pub fn view(&self, font: Font, font_size: u16) -> Element<'_, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.7);
+        let label = |value: String| text(value).font(font).size(font_size - 2).style(muted);
+        let send = |message: WorkflowArgumentsMessage| Message::WorkflowArguments(message);
+
+        let name = self.workflow().map_or("Workflow", |workflow| workflow.name.as_str());
+        let (filled, total) = self.handler.get_parameter_progress();
+        let mut content = column![
+            text(name.to_string()).font(font).size(font_size + 4),
+            label("Command".to_string()),
+            text(self.handler.preview_command().unwrap_or_default())
+                .font(Font::MONOSPACE)
+                .size(font_size),
+        ]
+        .spacing(8)
+        .padding(20)
+        .max_width(720);
+
+        if let Some(arg) = self.current() {
+            let position = self.handler.current_param_index + 1;
+            let mut heading = format!("Argument {position} of {total}: {} ({})", arg.name, kind(arg));
+            if let Some(pattern) = &arg.validation {
+                heading.push_str(&format!(", matching {pattern}"));
+            }
+            content = content.push(label(heading));
+            if let Some(description) = &arg.description {
+                content = content.push(text(description).font(font).size(font_size - 1));
+            }
+            content = content.push(
+                text_input(&arg.name, &self.input)
+                    .on_input(move |value| send(WorkflowArgumentsMessage::InputChanged(value)))
+                    .on_submit(send(WorkflowArgumentsMessage::Submit))
+                    .font(Font::MONOSPACE)
+                    .size(font_size),
+            );
+
+            if self.loading_suggestions {
+                content = content.push(label("Loading suggestions…".to_string()));
+            } else if !self.suggestions.is_empty() {
+                let suggestions = self.suggestions.iter().fold(column![].spacing(2), |col, value| {
+                    col.push(
+                        button(text(value).font(Font::MONOSPACE).size(font_size - 1))
+                            .on_press(send(WorkflowArgumentsMessage::SuggestionPicked(value.clone())))
+                            .style(theme::Button::Text)
+                            .width(Length::Fill),
+                    )
+                });
+                content = content.push(suggestions);
+            }
+        } else {
+            content = content.push(label(format!("{filled} of {total} arguments filled in")));
+        }
+
+        if let Some(error) = &self.error {
+            content = content.push(text(error).font(font).size(font_size - 2).style(Color::from_rgb(0.9, 0.4, 0.4)));
+        }
+
+        let back = button(text("Back").font(font).size(font_size)).style(theme::Button::Secondary);
+        let back = if self.handler.current_param_index > 0 {
+            back.on_press(send(WorkflowArgumentsMessage::Back))
+        } else {
+            back
+        };
+        let last = self.handler.current_param_index + 1 >= total;
+        content = content.push(
+            row![
+                Space::with_width(Length::Fill),
+                button(text("Cancel").font(font).size(font_size))
+                    .on_press(send(WorkflowArgumentsMessage::Cancel))
+                    .style(theme::Button::Secondary),
+                back,
+                button(text(if last { "Done" } else { "Next" }).font(font).size(font_size))
+                    .on_press(send(WorkflowArgumentsMessage::Submit))
+                    .style(theme::Button::Primary),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        );
+
+        container(scrollable(content))
+            .style(theme::Container::Custom(Box::new(DialogStyle)))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .into()
+    }
+}
+
+/// What an argument accepts, in words
+fn kind(arg: &Argument) -> String {
+    match arg.arg_type {
+        ArgumentType::Text => "text".to_string(),
+        ArgumentType::Int => "whole number".to_string(),
+        ArgumentType::Boolean => "true or false".to_string(),
+        ArgumentType::Path => "path".to_string(),
+        ArgumentType::Enum if arg.options.is_empty() => "choice".to_string(),
+        ArgumentType::Enum => format!("one of {}", arg.options.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dialog() -> WorkflowArgumentsDialog {
+        let workflow = Workflow::new("Checkout", "git checkout -t {{remote}}/{{branch}}").with_arguments(vec![
+            Argument::new("branch").with_suggestions("printf '%s\\n' {{remote}}/main"),
+            Argument::new("remote").with_options(["origin", "upstream"]).with_type(ArgumentType::Enum),
+        ]);
+        WorkflowArgumentsDialog::new(workflow, std::env::temp_dir())
+    }
+
+    #[tokio::test]
+    async fn test_fills_arguments_in_dependency_order_with_suggestions() {
+        let mut dialog = dialog();
+        assert_eq!(dialog.current().unwrap().name, "remote");
+
+        dialog.update(WorkflowArgumentsMessage::InputChanged("elsewhere".to_string()));
+        dialog.update(WorkflowArgumentsMessage::Submit);
+        assert!(dialog.error.is_some());
+        assert_eq!(dialog.current().unwrap().name, "remote");
+
+        dialog.update(WorkflowArgumentsMessage::SuggestionPicked("origin".to_string()));
+        assert_eq!(dialog.current().unwrap().name, "branch");
+        let loaded = dialog.load_suggestions().unwrap().await;
+        dialog.update(loaded);
+        assert_eq!(dialog.suggestions, vec!["origin/main"]);
+
+        dialog.update(WorkflowArgumentsMessage::SuggestionPicked("main".to_string()));
+        assert!(dialog.is_complete());
+        assert_eq!(dialog.handler.render_command().unwrap(), "git checkout -t origin/main");
+    }
+
+    #[test]
+    fn test_stale_suggestions_are_dropped() {
+        let mut dialog = dialog();
+        dialog.update(WorkflowArgumentsMessage::SuggestionsLoaded {
+            name: "branch".to_string(),
+            result: Ok(vec!["origin/main".to_string()]),
+        });
+        assert!(dialog.suggestions.is_empty());
+    }
+}
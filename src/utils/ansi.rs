@@ -0,0 +1,215 @@
+//! ANSI escape handling for command output.
+//!
+//! Splits output into lines of styled spans from SGR (`ESC [ ... m`)
+//! sequences: the 16 named colors, 256-color indexes, 24-bit colors, bold,
+//! dim, italic, underline and inverse. Other escape sequences (cursor
+//! movement, OSC titles, ...) are dropped.
+
+/// A color as named by the program, resolved against the theme's palette
+/// when drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiColor {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AnsiStyle {
+    pub foreground: Option<AnsiColor>,
+    pub background: Option<AnsiColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsiSpan {
+    pub text: String,
+    pub style: AnsiStyle,
+}
+
+/// Whether `text` contains any escape sequence
+pub fn has_escapes(text: &str) -> bool {
+    text.contains('\x1b')
+}
+
+/// The text without escape sequences
+pub fn strip_ansi(text: &str) -> String {
+    parse_ansi(text)
+        .iter()
+        .map(|line| line.iter().map(|span| span.text.as_str()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse output into lines of spans. Styles carry over line breaks, as
+/// they do in a terminal.
+pub fn parse_ansi(text: &str) -> Vec<Vec<AnsiSpan>> {
+    let mut lines = vec![Vec::new()];
+    let mut style = AnsiStyle::default();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    let flush = |current: &mut String, style: AnsiStyle, lines: &mut Vec<Vec<AnsiSpan>>| {
+        if !current.is_empty() {
+            lines.last_mut().expect("at least one line").push(AnsiSpan {
+                text: std::mem::take(current),
+                style,
+            });
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut terminator = None;
+                    for next in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&next) {
+                            terminator = Some(next);
+                            break;
+                        }
+                        params.push(next);
+                    }
+                    if terminator == Some('m') {
+                        flush(&mut current, style, &mut lines);
+                        apply_sgr(&mut style, &params);
+                    }
+                }
+                Some(']') => {
+                    // OSC: ends with BEL or ST (`ESC \`)
+                    while let Some(next) = chars.next() {
+                        if next == '\x07' || (next == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' => {
+                flush(&mut current, style, &mut lines);
+                lines.push(Vec::new());
+            }
+            '\r' => {}
+            c => current.push(c),
+        }
+    }
+    flush(&mut current, style, &mut lines);
+    if text.ends_with('\n') {
+        lines.pop();
+    }
+    lines
+}
+
+fn apply_sgr(style: &mut AnsiStyle, params: &str) {
+    let codes: Vec<u16> = if params.is_empty() {
+        vec![0]
+    } else {
+        params.split([';', ':']).map(|code| code.parse().unwrap_or(0)).collect()
+    };
+
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => *style = AnsiStyle::default(),
+            1 => style.bold = true,
+            2 => style.dim = true,
+            3 => style.italic = true,
+            4 => style.underline = true,
+            7 => style.inverse = true,
+            22 => {
+                style.bold = false;
+                style.dim = false;
+            }
+            23 => style.italic = false,
+            24 => style.underline = false,
+            27 => style.inverse = false,
+            code @ 30..=37 => style.foreground = Some(AnsiColor::Indexed((code - 30) as u8)),
+            39 => style.foreground = None,
+            code @ 40..=47 => style.background = Some(AnsiColor::Indexed((code - 40) as u8)),
+            49 => style.background = None,
+            code @ 90..=97 => style.foreground = Some(AnsiColor::Indexed((code - 90 + 8) as u8)),
+            code @ 100..=107 => style.background = Some(AnsiColor::Indexed((code - 100 + 8) as u8)),
+            code @ (38 | 48) => {
+                let (color, used) = extended_color(&codes[i + 1..]);
+                if code == 38 {
+                    style.foreground = color.or(style.foreground);
+                } else {
+                    style.background = color.or(style.background);
+                }
+                i += used;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// `5;n` or `2;r;g;b` after a 38/48: the color and how many codes it used
+fn extended_color(codes: &[u16]) -> (Option<AnsiColor>, usize) {
+    let channel = |index: usize| codes.get(index).map(|&value| value.min(255) as u8);
+    match codes.first() {
+        Some(5) => (channel(1).map(AnsiColor::Indexed), 2),
+        Some(2) => match (channel(1), channel(2), channel(3)) {
+            (Some(r), Some(g), Some(b)) => (Some(AnsiColor::Rgb(r, g, b)), 4),
+            _ => (None, codes.len()),
+        },
+        _ => (None, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: AnsiStyle) -> AnsiSpan {
+        AnsiSpan {
+            text: text.to_string(),
+            style,
+        }
+    }
+
+    #[test]
+    fn test_sgr_colors() {
+        let lines = parse_ansi("ok \x1b[1;31merror\x1b[0m\n\x1b[38;5;208morange\x1b[48;2;1;2;3m bg\x1b[39;49m done\n");
+        let red = AnsiStyle {
+            foreground: Some(AnsiColor::Indexed(1)),
+            bold: true,
+            ..Default::default()
+        };
+        let orange = AnsiStyle {
+            foreground: Some(AnsiColor::Indexed(208)),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines,
+            vec![
+                vec![span("ok ", AnsiStyle::default()), span("error", red)],
+                vec![
+                    span("orange", orange),
+                    span(
+                        " bg",
+                        AnsiStyle {
+                            background: Some(AnsiColor::Rgb(1, 2, 3)),
+                            ..orange
+                        }
+                    ),
+                    span(" done", AnsiStyle::default()),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_dropped() {
+        let output = "\x1b]0;title\x07\x1b[2J\x1b[Hline one\r\n\x1b[92mline\x1b[K two\x1b]8;;http://x\x1b\\";
+        assert_eq!(strip_ansi(output), "line one\nline two");
+        assert!(has_escapes(output));
+        assert!(!has_escapes("plain"));
+        let lines = parse_ansi(output);
+        assert_eq!(lines[1][0].style.foreground, Some(AnsiColor::Indexed(10)));
+    }
+}
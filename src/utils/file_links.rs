@@ -0,0 +1,109 @@
+//! File paths in command output.
+//!
+//! Finds words that look like paths, such as `src/main.rs:12:5` in a
+//! compiler error or `Cargo.toml` in a listing, so they can be clicked to
+//! open the file. Nothing here touches the file system; whether a path
+//! exists is only known when it is opened.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A path found in a line of output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLink {
+    /// Bytes of the line the link covers, any `:line:column` included
+    pub range: Range<usize>,
+    pub path: String,
+    /// One-based line number following the path
+    pub line: Option<usize>,
+}
+
+/// `path` relative to `working_dir`, with a leading `~` expanded
+pub fn resolve_path(path: &Path, working_dir: &Path) -> PathBuf {
+    match path.strip_prefix("~").ok().zip(dirs::home_dir()) {
+        Some((rest, home)) => home.join(rest),
+        None => working_dir.join(path),
+    }
+}
+
+/// Paths in one line of output, in order
+pub fn find_file_links(line: &str) -> Vec<FileLink> {
+    let is_separator = |c: char| c.is_whitespace() || "\"'`()[]<>{},;=".contains(c);
+    let mut links = Vec::new();
+    let mut word_start = None;
+    for (index, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        if !is_separator(c) {
+            word_start.get_or_insert(index);
+            continue;
+        }
+        let Some(start) = word_start.take() else { continue };
+        let word = line[start..index].trim_end_matches(['.', ':']);
+        if let Some((path, line_number)) = parse_link(word) {
+            links.push(FileLink {
+                range: start..start + word.len(),
+                path: path.to_string(),
+                line: line_number,
+            });
+        }
+    }
+    links
+}
+
+/// Split `path:line:column` and check the path part looks like a file
+fn parse_link(word: &str) -> Option<(&str, Option<usize>)> {
+    let mut parts = word.splitn(3, ':');
+    let path = parts.next()?;
+    let line = parts.next().and_then(|line| line.parse().ok());
+    looks_like_file(path).then_some((path, line))
+}
+
+fn looks_like_file(path: &str) -> bool {
+    if path.is_empty() || path.contains("://") || path.ends_with('/') {
+        return false;
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    // A name with an extension starting with a letter, such as `main.rs`
+    // but not `1.5` or `v2.0`, or any path with a directory part
+    let has_extension = name
+        .rsplit_once('.')
+        .is_some_and(|(stem, extension)| {
+            !stem.is_empty() && extension.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        });
+    let has_directory = path.contains('/') && name.chars().any(char::is_alphanumeric);
+    has_extension || has_directory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_paths_with_locations() {
+        let line = "  --> src/app/terminal.rs:684:17";
+        let links = find_file_links(line);
+        assert_eq!(links.len(), 1);
+        assert_eq!(&line[links[0].range.clone()], "src/app/terminal.rs:684:17");
+        assert_eq!(links[0].path, "src/app/terminal.rs");
+        assert_eq!(links[0].line, Some(684));
+
+        let paths: Vec<_> = find_file_links("Cargo.toml  README.md  src/  ./run  (see ~/notes.txt).")
+            .into_iter()
+            .map(|link| link.path)
+            .collect();
+        assert_eq!(paths, ["Cargo.toml", "README.md", "./run", "~/notes.txt"]);
+
+        let working_dir = Path::new("/work");
+        assert_eq!(resolve_path(Path::new("src/lib.rs"), working_dir), Path::new("/work/src/lib.rs"));
+        assert_eq!(resolve_path(Path::new("/etc/hosts"), working_dir), Path::new("/etc/hosts"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(resolve_path(Path::new("~/notes.txt"), working_dir), home.join("notes.txt"));
+        }
+    }
+
+    #[test]
+    fn test_skips_words_that_are_not_paths() {
+        for line in ["version 1.5 and v2.0 done.", "https://example.com/a.html", "see std::fs docs", "a / b"] {
+            assert_eq!(find_file_links(line), [], "{line}");
+        }
+    }
+}
@@ -5,3 +5,6 @@ pub mod gpu_renderer;
 pub mod suggestions;
 pub mod fuzzy_matcher;
 pub mod pty;
+pub mod ansi;
+pub mod file_links;
+pub mod process;
@@ -0,0 +1,127 @@
+//! Running short-lived helper processes that must not outlive a deadline.
+//!
+//! Commands run in their own process group, so that killing them also kills
+//! whatever they started (`git ls-remote`, `kubectl get`, ...) instead of
+//! leaving those running with the output pipe open.
+
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Run a command through `sh` and return its stdout, killing it and its
+/// children once it has run for `timeout`
+pub fn run_with_deadline(command: &str, working_dir: Option<&Path>, timeout: Duration) -> Result<String, String> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    output_with_deadline(cmd, timeout)
+}
+
+/// Run `cmd` without input and return its stdout, killing it and its
+/// children once it has run for `timeout`
+pub fn output_with_deadline(mut cmd: Command, timeout: Duration) -> Result<String, String> {
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    let mut stdout = child.stdout.take().ok_or("stdout was not captured")?;
+    // Read on a separate thread so a chatty command cannot fill the pipe and stall
+    let (sender, output) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = sender.send(stdout.read_to_string(&mut output).map(|_| output));
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                kill_process_group(&mut child);
+                // Every writer is gone, so the reader sees the end of the pipe
+                let _ = output.recv();
+                return Err(format!("timed out after {:?}", timeout));
+            }
+            Err(e) => {
+                kill_process_group(&mut child);
+                let _ = output.recv();
+                return Err(e.to_string());
+            }
+        }
+    };
+
+    // A background job of the command may still hold the pipe; the rest of
+    // the group goes once the deadline passes
+    let output = match output.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output,
+        Err(_) => {
+            kill_process_group(&mut child);
+            output.recv().map_err(|_| "failed to read output".to_string())?
+        }
+    }
+    .map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(output)
+    } else {
+        Err(format!("exited with {}", status))
+    }
+}
+
+/// Kill `child` together with its process group and reap it. The child
+/// must have been spawned as a group leader with `process_group(0)`.
+pub fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+            // SAFETY: killpg only sends a signal; the group is the child's own
+            unsafe {
+                libc::killpg(pid, libc::SIGKILL);
+            }
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_and_exit_status() {
+        assert_eq!(run_with_deadline("echo hi", None, Duration::from_secs(5)).unwrap(), "hi\n");
+        assert!(run_with_deadline("exit 3", None, Duration::from_secs(5))
+            .unwrap_err()
+            .contains("exited"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_grandchildren() {
+        // The background `sleep` inherits the pipe; the call only returns
+        // promptly if it is killed along with `sh`
+        let started = Instant::now();
+        let result = run_with_deadline("sleep 30 & sleep 30", None, Duration::from_millis(200));
+        assert!(result.unwrap_err().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_background_job_holding_the_pipe_is_killed() {
+        // `sh` exits at once, but the backgrounded `sleep` keeps stdout open
+        let started = Instant::now();
+        let result = run_with_deadline("echo hi; sleep 30 &", None, Duration::from_millis(200));
+        assert_eq!(result.unwrap(), "hi\n");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -2,6 +2,8 @@ use iced::{Element, Color};
 use iced::widget::text;
 use std::collections::HashMap;
 
+use crate::syntax_tree::{parse_shell, HighlightKind, ShellDialect};
+
 #[derive(Debug, Clone)]
 pub struct SyntaxHighlighter {
     keywords: HashMap<String, Color>,
@@ -78,98 +80,61 @@ impl SyntaxHighlighter {
     }
     
     pub fn tokenize(&self, input: &str) -> Vec<Token> {
+        let tree = parse_shell(input, ShellDialect::default());
         let mut tokens = Vec::new();
-        let mut current_token = String::new();
-        let mut chars = input.chars().peekable();
-        let mut in_string = false;
-        let mut string_char = '"';
-        let mut in_comment = false;
-        
-        while let Some(ch) = chars.next() {
-            match ch {
-                '"' | '\'' if !in_comment => {
-                    if in_string && ch == string_char {
-                        current_token.push(ch);
-                        tokens.push(Token {
-                            text: current_token.clone(),
-                            color: Some(self.strings_color),
-                            is_bold: false,
-                        });
-                        current_token.clear();
-                        in_string = false;
-                    } else if !in_string {
-                        if !current_token.is_empty() {
-                            self.push_token(&mut tokens, &current_token);
-                            current_token.clear();
-                        }
-                        current_token.push(ch);
-                        in_string = true;
-                        string_char = ch;
-                    } else {
-                        current_token.push(ch);
-                    }
-                }
-                '#' if !in_string => {
-                    if !current_token.is_empty() {
-                        self.push_token(&mut tokens, &current_token);
-                        current_token.clear();
-                    }
-                    in_comment = true;
-                    current_token.push(ch);
-                }
-                ' ' | '\t' if !in_string && !in_comment => {
-                    if !current_token.is_empty() {
-                        self.push_token(&mut tokens, &current_token);
-                        current_token.clear();
-                    }
-                    tokens.push(Token {
-                        text: ch.to_string(),
-                        color: None,
-                        is_bold: false,
-                    });
-                }
-                _ => {
-                    current_token.push(ch);
-                }
-            }
-        }
-        
-        if !current_token.is_empty() {
-            if in_comment {
+        let mut pos = 0;
+
+        for highlight in tree.highlights() {
+            if highlight.span.start > pos {
+                // Whitespace between highlighted parts
                 tokens.push(Token {
-                    text: current_token,
-                    color: Some(self.comments_color),
+                    text: input[pos..highlight.span.start].to_string(),
+                    color: None,
                     is_bold: false,
                 });
-            } else {
-                self.push_token(&mut tokens, &current_token);
             }
+            let text = &input[highlight.span.clone()];
+            tokens.push(Token {
+                text: text.to_string(),
+                color: self.color_for(highlight.kind, text),
+                is_bold: highlight.kind == HighlightKind::Command,
+            });
+            pos = highlight.span.end;
         }
-        
+
+        if pos < input.len() {
+            tokens.push(Token {
+                text: input[pos..].to_string(),
+                color: None,
+                is_bold: false,
+            });
+        }
+
         tokens
     }
-    
-    fn push_token(&self, tokens: &mut Vec<Token>, text: &str) {
-        let color = if let Some(&color) = self.keywords.get(text) {
-            Some(color)
-        } else if self.operators.contains_key(text) {
-            Some(self.operators[text])
-        } else if text.chars().all(|c| c.is_ascii_digit() || c == '.') {
-            Some(self.numbers_color)
-        } else if text.starts_with('-') && text.len() > 1 {
+
+    fn color_for(&self, kind: HighlightKind, text: &str) -> Option<Color> {
+        match kind {
+            HighlightKind::Command | HighlightKind::Keyword => self.keywords.get(text).copied(),
+            HighlightKind::Operator | HighlightKind::Redirect => Some(
+                self.operators
+                    .get(text)
+                    .copied()
+                    .unwrap_or(Color::from_rgb(0.6, 0.6, 0.6)),
+            ),
+            HighlightKind::String | HighlightKind::Heredoc => Some(self.strings_color),
+            HighlightKind::Comment => Some(self.comments_color),
+            HighlightKind::Number => Some(self.numbers_color),
             // Command flags
-            Some(Color::from_rgb(0.6, 0.8, 0.8))
-        } else {
-            None
-        };
-        
-        tokens.push(Token {
-            text: text.to_string(),
-            color,
-            is_bold: false,
-        });
+            HighlightKind::Flag => Some(Color::from_rgb(0.6, 0.8, 0.8)),
+            HighlightKind::Variable | HighlightKind::Substitution => {
+                Some(Color::from_rgb(0.8, 0.6, 0.8))
+            }
+            HighlightKind::Error => Some(Color::from_rgb(0.9, 0.3, 0.3)),
+            HighlightKind::Argument | HighlightKind::Assignment => None,
+        }
     }
-    
+
     pub fn highlight_text<'a>(&self, input: &'a str) -> Element<'a, crate::Message> {
         let _tokens = self.tokenize(input);
         
@@ -210,35 +175,59 @@ pub enum SyntaxKind {
 
 impl SyntaxTree {
     pub fn parse(input: &str) -> Self {
-        let mut parser = Parser::new(input);
-        let root = parser.parse();
-        
-        Self { root }
+        Self::parse_with_dialect(input, ShellDialect::default())
     }
-}
 
-#[allow(dead_code)]
-struct Parser {
-    input: String,
-    pos: usize,
-}
+    /// Parse `input` with the shell parser. The root spans the whole input and
+    /// has one child per highlighted part, with its byte span.
+    pub fn parse_with_dialect(input: &str, dialect: ShellDialect) -> Self {
+        let tree = parse_shell(input, dialect);
+        let children = tree
+            .highlights()
+            .into_iter()
+            .map(|highlight| {
+                let text = &input[highlight.span.clone()];
+                let kind = match highlight.kind {
+                    HighlightKind::Command | HighlightKind::Keyword => SyntaxKind::Command,
+                    HighlightKind::Flag => SyntaxKind::Flag,
+                    HighlightKind::String | HighlightKind::Heredoc => SyntaxKind::String,
+                    HighlightKind::Number => SyntaxKind::Number,
+                    HighlightKind::Operator if text.starts_with('|') && text != "||" => {
+                        SyntaxKind::Pipe
+                    }
+                    HighlightKind::Operator => SyntaxKind::Operator,
+                    HighlightKind::Redirect => SyntaxKind::Redirect,
+                    HighlightKind::Comment => SyntaxKind::Comment,
+                    HighlightKind::Variable | HighlightKind::Substitution => SyntaxKind::Variable,
+                    HighlightKind::Error => SyntaxKind::Error,
+                    HighlightKind::Argument | HighlightKind::Assignment
+                        if text.contains('/') || text.starts_with('~') =>
+                    {
+                        SyntaxKind::Path
+                    }
+                    HighlightKind::Argument | HighlightKind::Assignment => SyntaxKind::Argument,
+                };
+                SyntaxNode {
+                    kind,
+                    text: text.to_string(),
+                    children: Vec::new(),
+                    span: (highlight.span.start, highlight.span.end),
+                }
+            })
+            .collect();
 
-impl Parser {
-    fn new(input: &str) -> Self {
+        let kind = if tree.has_errors() {
+            SyntaxKind::Error
+        } else {
+            SyntaxKind::Command
+        };
         Self {
-            input: input.to_string(),
-            pos: 0,
-        }
-    }
-    
-    fn parse(&mut self) -> SyntaxNode {
-        // Simple parsing implementation
-        // In a real implementation, this would be much more sophisticated
-        SyntaxNode {
-            kind: SyntaxKind::Command,
-            text: self.input.clone(),
-            children: Vec::new(),
-            span: (0, self.input.len()),
+            root: SyntaxNode {
+                kind,
+                text: input.to_string(),
+                children,
+                span: (0, input.len()),
+            },
         }
     }
 }
@@ -0,0 +1,42 @@
+//! Path filters built from the watcher's ignore patterns.
+
+use std::path::Path;
+
+/// Matches paths against simple ignore patterns: `*.ext` matches file
+/// names by suffix, `dir/*` matches anything below a directory called
+/// `dir`, and any other pattern matches a file or directory name exactly.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    patterns: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.patterns.iter().any(|pattern| Self::matches(pattern, path))
+    }
+
+    fn matches(pattern: &str, path: &Path) -> bool {
+        let names = || {
+            path.components()
+                .filter_map(|component| component.as_os_str().to_str())
+        };
+        if let Some(dir) = pattern.strip_suffix("/*") {
+            // The last component is the entry itself, not a parent directory
+            let count = names().count();
+            return names().take(count.saturating_sub(1)).any(|name| name == dir);
+        }
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        match pattern.strip_prefix('*') {
+            Some(suffix) => file_name.ends_with(suffix),
+            None => file_name == pattern,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Polling file system watcher.
+//!
+//! Takes a snapshot of every file under the watched paths and reports the
+//! differences on each poll. Cheap enough for configuration directories
+//! checked every few seconds, and works without platform notification APIs.
+//! Symbolic links to directories are not followed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use walkdir::WalkDir;
+
+use super::filters::PathFilter;
+use super::{WatchEvent, WatcherConfig};
+
+/// What a poll compares to notice a file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn of(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+
+    /// The stamp of the file at `path`, if there is one
+    pub(crate) fn read(path: &Path) -> Option<Self> {
+        std::fs::metadata(path).ok().filter(|metadata| metadata.is_file()).map(|metadata| Self::of(&metadata))
+    }
+}
+
+/// Detects created, modified and deleted files by comparing snapshots
+#[derive(Debug, Clone, Default)]
+pub struct PollingWatcher {
+    paths: Vec<PathBuf>,
+    recursive: bool,
+    filter: PathFilter,
+    snapshot: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl PollingWatcher {
+    /// Watch the configured paths. The first snapshot is taken immediately,
+    /// so only later changes are reported. Paths that do not exist yet are
+    /// watched for their creation.
+    pub fn new(config: &WatcherConfig) -> Self {
+        let mut watcher = Self {
+            paths: config.watch_paths.clone(),
+            recursive: config.recursive,
+            filter: PathFilter::new(&config.ignore_patterns),
+            snapshot: BTreeMap::new(),
+        };
+        watcher.snapshot = watcher.scan();
+        watcher
+    }
+
+    pub fn watch_paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Changes since the previous poll, ordered by path
+    pub fn poll(&mut self) -> Vec<WatchEvent> {
+        let current = self.scan();
+        let mut events = Vec::new();
+
+        for (path, stamp) in &current {
+            match self.snapshot.get(path) {
+                None => events.push(WatchEvent::Created { path: path.clone() }),
+                Some(previous) if previous != stamp => {
+                    events.push(WatchEvent::Modified { path: path.clone() })
+                }
+                Some(_) => {}
+            }
+        }
+        for path in self.snapshot.keys() {
+            if !current.contains_key(path) {
+                events.push(WatchEvent::Deleted { path: path.clone() });
+            }
+        }
+
+        self.snapshot = current;
+        events
+    }
+
+    fn scan(&self) -> BTreeMap<PathBuf, FileStamp> {
+        let mut files = BTreeMap::new();
+        for root in &self.paths {
+            // Linked directories are not entered, so a link cycle cannot
+            // make a poll run forever. Linked files are still stamped.
+            let walker = WalkDir::new(root)
+                .follow_links(false)
+                .max_depth(if self.recursive { usize::MAX } else { 1 });
+            for entry in walker.into_iter().filter_map(Result::ok) {
+                let file_type = entry.file_type();
+                if !(file_type.is_file() || file_type.is_symlink()) || self.is_ignored(root, entry.path()) {
+                    continue;
+                }
+                if let Some(stamp) = FileStamp::read(entry.path()) {
+                    files.insert(entry.path().to_path_buf(), stamp);
+                }
+            }
+        }
+        files
+    }
+
+    fn is_ignored(&self, root: &Path, path: &Path) -> bool {
+        self.filter
+            .is_ignored(path.strip_prefix(root).unwrap_or(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn watcher_for(dir: &Path) -> PollingWatcher {
+        PollingWatcher::new(&WatcherConfig {
+            watch_paths: vec![dir.to_path_buf()],
+            ..WatcherConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_poll_reports_changes() {
+        let dir = TempDir::new().unwrap();
+        let existing = dir.path().join("existing.yaml");
+        std::fs::write(&existing, "name: a").unwrap();
+        let mut watcher = watcher_for(dir.path());
+        assert!(watcher.poll().is_empty());
+
+        let created = dir.path().join("nested").join("new.yaml");
+        std::fs::create_dir_all(created.parent().unwrap()).unwrap();
+        std::fs::write(&created, "name: b").unwrap();
+        std::fs::write(&existing, "name: changed").unwrap();
+        std::fs::write(dir.path().join("scratch.tmp"), "ignored").unwrap();
+
+        let events = watcher.poll();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], WatchEvent::Modified { path } if path == &existing));
+        assert!(matches!(&events[1], WatchEvent::Created { path } if path == &created));
+
+        std::fs::remove_file(&existing).unwrap();
+        let events = watcher.poll();
+        assert!(matches!(events.as_slice(), [WatchEvent::Deleted { path }] if path == &existing));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle_is_not_followed() {
+        let dir = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+        let target = TempDir::new().unwrap();
+        let linked = dir.path().join("linked.yaml");
+        std::fs::write(target.path().join("a.yaml"), "name: a").unwrap();
+        std::os::unix::fs::symlink(target.path().join("a.yaml"), &linked).unwrap();
+        let mut watcher = watcher_for(dir.path());
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(target.path().join("a.yaml"), "name: changed").unwrap();
+        let events = watcher.poll();
+        assert!(matches!(events.as_slice(), [WatchEvent::Modified { path }] if path == &linked));
+    }
+
+    #[test]
+    fn test_missing_directory_is_watched_for_creation() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("later");
+        let mut watcher = watcher_for(&missing);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::create_dir(&missing).unwrap();
+        std::fs::write(missing.join("a.yaml"), "name: a").unwrap();
+        assert_eq!(watcher.poll().len(), 1);
+    }
+}
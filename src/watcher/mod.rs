@@ -7,6 +7,9 @@ pub mod fs_watcher;
 pub mod event_handler;
 pub mod filters;
 
+pub use filters::PathFilter;
+pub use fs_watcher::PollingWatcher;
+
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::collections::HashSet;
@@ -59,6 +59,8 @@ fn create_test_settings() -> SettingsState {
         window_opacity: 95.0,
         window_blur_radius: 2,
         input_type: InputType::Universal,
+        vim_mode: true,
+        keyset: "emacs".to_string(),
         history_enabled: true,
         max_history_entries: 50000,
         history_dedup_mode: HistoryDedupMode::Global,
@@ -76,6 +78,7 @@ fn create_test_settings() -> SettingsState {
         layout_autosave_interval: 300,
         pane_layout: None,
         restore_layout_on_startup: false,
+        command_guard: Default::default(),
     }
 }
 
@@ -122,6 +125,8 @@ async fn test_settings_save_and_load_cycle() -> Result<()> {
     assert_eq!(loaded_settings.window_columns, 120);
     assert_eq!(loaded_settings.max_history_entries, 50000);
     assert_eq!(loaded_settings.history_exclude_patterns.len(), 2);
+    assert!(loaded_settings.vim_mode);
+    assert_eq!(loaded_settings.keyset, "emacs");
 
     println!("✅ Settings save/load cycle completed successfully");
     Ok(())
@@ -0,0 +1,81 @@
+//! Snapshot of every bundled workflow's rendered command.
+//!
+//! Each spec is rendered with its argument defaults, or `<name>` for
+//! arguments without one, so changes to placeholder substitution show up as
+//! a diff. Run with `UPDATE_SNAPSHOTS=1` to accept intended changes.
+
+use std::path::Path;
+
+use walkdir::WalkDir;
+use warp_terminal::model::workflow_params::WorkflowParameterHandler;
+use warp_workflows_types::Workflow;
+
+const SPECS_DIR: &str = "workflows/specs";
+const SNAPSHOT: &str = "tests/snapshots/workflow_commands.snap";
+
+fn render(workflow: Workflow) -> String {
+    let mut handler = WorkflowParameterHandler::new();
+    handler.set_workflow(workflow.clone());
+    for arg in &workflow.arguments {
+        let value = handler
+            .default_for(&arg.name)
+            .unwrap_or_else(|| format!("<{}>", arg.name));
+        // Defaults that fail validation are rendered verbatim
+        if handler.set_parameter_value(&arg.name, value.clone()).is_err() {
+            handler.set_parameter_value(&arg.name, format!("<{}>", arg.name)).ok();
+        }
+    }
+    handler.render_command().unwrap_or_default()
+}
+
+fn render_specs(root: &Path) -> String {
+    let mut lines: Vec<String> = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && matches!(
+                    entry.path().extension().and_then(|ext| ext.to_str()),
+                    Some("yaml" | "yml")
+                )
+        })
+        .map(|entry| {
+            let relative = entry.path().strip_prefix(root).unwrap().display().to_string();
+            let content = std::fs::read_to_string(entry.path()).unwrap();
+            let workflow: Workflow = serde_yaml::from_str(&content)
+                .unwrap_or_else(|e| panic!("{}: {}", relative, e));
+            let rendered = render(workflow).trim_end().replace('\n', "\n    ");
+            format!("{}:\n    {}", relative, rendered)
+        })
+        .collect();
+    lines.sort();
+    lines.join("\n") + "\n"
+}
+
+#[test]
+fn test_rendered_workflow_commands_match_snapshot() {
+    let actual = render_specs(Path::new(SPECS_DIR));
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(SNAPSHOT, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(SNAPSHOT)
+        .expect("missing snapshot; run with UPDATE_SNAPSHOTS=1 to create it");
+    if actual != expected {
+        let first_difference = actual
+            .lines()
+            .zip(expected.lines())
+            .position(|(a, e)| a != e)
+            .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+        panic!(
+            "rendered workflow commands differ from {} at line {}:\n  expected: {:?}\n  actual:   {:?}\n\
+             run with UPDATE_SNAPSHOTS=1 if the change is intended",
+            SNAPSHOT,
+            first_difference + 1,
+            expected.lines().nth(first_difference),
+            actual.lines().nth(first_difference),
+        );
+    }
+}
@@ -14,6 +14,8 @@ pub enum Shell {
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
 pub struct Workflow {
     pub name: String,
+    /// The command to run. Multi-step workflows may leave it empty.
+    #[serde(default)]
     pub command: String,
     #[serde(default)]
     pub tags: Vec<String>,
@@ -25,6 +27,9 @@ pub struct Workflow {
     pub author_url: Option<String>,
     #[serde(default)]
     pub shells: Vec<Shell>,
+    /// Commands run one after another instead of the single `command`.
+    #[serde(default)]
+    pub steps: Vec<WorkflowStep>,
 }
 
 impl Workflow {
@@ -71,6 +76,7 @@ impl Workflow {
             author: None,
             author_url: None,
             shells: vec![],
+            steps: vec![],
         }
     }
 
@@ -84,6 +90,29 @@ impl Workflow {
         self
     }
 
+    pub fn with_steps(mut self, steps: Vec<WorkflowStep>) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    pub fn steps(&self) -> &Vec<WorkflowStep> {
+        &self.steps
+    }
+
+    pub fn is_multi_step(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    /// The steps to run. A single-command workflow is one step named after
+    /// the workflow.
+    pub fn resolved_steps(&self) -> Vec<WorkflowStep> {
+        if self.steps.is_empty() {
+            vec![WorkflowStep::new(self.name.clone(), self.command.clone())]
+        } else {
+            self.steps.clone()
+        }
+    }
+
     pub fn load_from_yaml(file_path: &str) -> Result<Self, Error> {
         let content = std::fs::read_to_string(file_path)?;
         let workflow: Workflow = serde_yaml::from_str(&content)?;
@@ -96,6 +125,115 @@ pub struct Argument {
     pub name: String,
     pub description: Option<String>,
     pub default_value: Option<String>,
+    /// The kind of value the argument accepts. Defaults to free text.
+    /// Older specs spell the key `arg_type`.
+    #[serde(default, rename = "type", alias = "arg_type")]
+    pub arg_type: ArgumentType,
+    /// The allowed values of an `enum` argument.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// A regular expression the whole value must match.
+    #[serde(default)]
+    pub validation: Option<String>,
+    /// A shell command whose output lines are offered as values.
+    #[serde(default)]
+    pub suggestions: Option<SuggestionSource>,
+    /// Arguments that must be filled in before this one.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// One command of a multi-step workflow.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+pub struct WorkflowStep {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Variable the step's trimmed stdout is stored in for later steps.
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Condition on earlier exit codes deciding whether the step runs.
+    #[serde(default, rename = "if")]
+    pub condition: Option<String>,
+    /// Keep going with the next steps when this one fails.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// How many more times a failing step is run before giving up.
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+}
+
+impl WorkflowStep {
+    pub fn new(name: impl Into<String>, command: impl Into<String>) -> Self {
+        WorkflowStep {
+            name: name.into(),
+            command: command.into(),
+            description: None,
+            output: None,
+            condition: None,
+            continue_on_error: false,
+            retries: 0,
+            working_directory: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_output(mut self, variable: impl Into<String>) -> Self {
+        self.output = Some(variable.into());
+        self
+    }
+
+    pub fn with_condition(mut self, condition: impl Into<String>) -> Self {
+        self.condition = Some(condition.into());
+        self
+    }
+
+    pub fn with_continue_on_error(mut self, continue_on_error: bool) -> Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_working_directory(mut self, dir: impl Into<String>) -> Self {
+        self.working_directory = Some(dir.into());
+        self
+    }
+}
+
+/// The kind of value a workflow argument accepts.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentType {
+    #[default]
+    #[serde(alias = "string", alias = "Text")]
+    Text,
+    #[serde(alias = "integer", alias = "Int")]
+    Int,
+    #[serde(alias = "bool", alias = "Boolean")]
+    Boolean,
+    #[serde(alias = "Path")]
+    Path,
+    #[serde(alias = "choice", alias = "Enum")]
+    Enum,
+}
+
+/// Where the suggested values for an argument come from.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+pub struct SuggestionSource {
+    /// Shell command printing one suggestion per line. It may reference
+    /// other arguments with `{{name}}`.
+    pub command: String,
 }
 
 impl Argument {
@@ -104,6 +242,11 @@ impl Argument {
             description: None,
             name: name.into(),
             default_value: None,
+            arg_type: ArgumentType::default(),
+            options: vec![],
+            validation: None,
+            suggestions: None,
+            depends_on: vec![],
         }
     }
 
@@ -112,6 +255,48 @@ impl Argument {
         self
     }
 
+    pub fn with_default_value(mut self, default_value: impl Into<String>) -> Self {
+        self.default_value = Some(default_value.into());
+        self
+    }
+
+    pub fn with_type(mut self, arg_type: ArgumentType) -> Self {
+        self.arg_type = arg_type;
+        self
+    }
+
+    /// Make this an `enum` argument with the given choices.
+    pub fn with_options<I, S>(mut self, options: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.arg_type = ArgumentType::Enum;
+        self.options = options.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_validation(mut self, pattern: impl Into<String>) -> Self {
+        self.validation = Some(pattern.into());
+        self
+    }
+
+    pub fn with_suggestions(mut self, command: impl Into<String>) -> Self {
+        self.suggestions = Some(SuggestionSource {
+            command: command.into(),
+        });
+        self
+    }
+
+    pub fn with_depends_on<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.depends_on = names.into_iter().map(Into::into).collect();
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -123,4 +308,24 @@ impl Argument {
     pub fn default_value(&self) -> &Option<String> {
         &self.default_value
     }
+
+    pub fn arg_type(&self) -> ArgumentType {
+        self.arg_type
+    }
+
+    pub fn options(&self) -> &Vec<String> {
+        &self.options
+    }
+
+    pub fn validation(&self) -> &Option<String> {
+        &self.validation
+    }
+
+    pub fn suggestions(&self) -> &Option<SuggestionSource> {
+        &self.suggestions
+    }
+
+    pub fn depends_on(&self) -> &Vec<String> {
+        &self.depends_on
+    }
 }